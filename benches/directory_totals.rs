@@ -0,0 +1,116 @@
+//! Directory totals of a generated tree of about a million files
+//!
+//! The sizes and counts of every directory come from a single lookup of each
+//! file's parent by numeric id, then one pass up the id tree (see
+//! `scanner/aggregate.rs`). The previous implementation walked each file's
+//! whole ancestor chain through the path map instead. This benchmark times
+//! both on 1.19 million files in 597,871 directories; run it with
+//! `cargo bench --bench directory_totals`.
+//!
+//! The id index is timed through `ScanResults::retain_files`, which also
+//! keys the directory list by path first. The ancestor walk is measured for
+//! its file pass only: counting every directory's subdirectories by scanning
+//! all other directories, as it did too, is quadratic and would take hours on
+//! this tree.
+
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion};
+use diranalyzer::prelude::*;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Levels of directories below the root, directories per directory, and files per directory
+const LEVELS: usize = 6;
+const FANOUT: usize = 9;
+const FILES_PER_DIRECTORY: usize = 2;
+
+fn directory(path: PathBuf, depth: usize) -> DirectoryEntry {
+    DirectoryEntry {
+        path,
+        total_size: 0,
+        file_count: 0,
+        recursive_file_count: 0,
+        subdirectory_count: 0,
+        recursive_subdirectory_count: 0,
+        depth,
+        owners: Vec::new(),
+        root: 0,
+        mode: None,
+    }
+}
+
+/// A scan of `/bench` as the walk leaves it: every file and directory, no totals yet
+fn generated_scan() -> ScanResults {
+    let mut results = ScanResults::default();
+    let mut frontier = vec![PathBuf::from("/bench")];
+    results.directories.push(directory(frontier[0].clone(), 0));
+    for depth in 0..=LEVELS {
+        let mut next = Vec::new();
+        for dir in &frontier {
+            for f in 0..FILES_PER_DIRECTORY {
+                let path = dir.join(format!("file{}.dat", f));
+                results.files.push(FileEntry { path, size: (depth * 1000 + f) as u64, depth: depth + 1, ..Default::default() });
+            }
+            if depth == LEVELS {
+                continue;
+            }
+            for c in 0..FANOUT {
+                let child = dir.join(format!("d{}", c));
+                results.directories.push(directory(child.clone(), depth + 1));
+                next.push(child);
+            }
+        }
+        frontier = next;
+    }
+    results
+}
+
+/// The file pass of the previous implementation, walking every file's ancestor chain
+fn ancestor_walk(files: &[FileEntry], directories: &mut HashMap<PathBuf, DirectoryEntry>) {
+    for file in files {
+        let mut current_path = file.path.parent();
+        while let Some(dir_path) = current_path {
+            if let Some(dir_entry) = directories.get_mut(dir_path) {
+                dir_entry.total_size += file.size;
+                if dir_path == file.path.parent().unwrap_or_else(|| Path::new("")) {
+                    dir_entry.file_count += 1;
+                }
+            }
+            current_path = dir_path.parent();
+        }
+    }
+}
+
+fn directory_totals(c: &mut Criterion) {
+    let scan = generated_scan();
+    assert!(scan.files.len() > 1_000_000);
+    let by_path: HashMap<PathBuf, DirectoryEntry> = scan.directories.iter().map(|dir| (dir.path.clone(), dir.clone())).collect();
+
+    let mut group = c.benchmark_group("directory_totals");
+    group.sample_size(10).measurement_time(Duration::from_secs(30));
+    // Each routine hands back what it worked on, so freeing it stays out of the timings
+    group.bench_function("id index", |b| {
+        b.iter_batched(
+            || scan.clone(),
+            |mut scan| {
+                scan.retain_files(|_| true, 5);
+                scan
+            },
+            BatchSize::PerIteration,
+        )
+    });
+    group.bench_function("ancestor walk (file pass only)", |b| {
+        b.iter_batched(
+            || by_path.clone(),
+            |mut directories| {
+                ancestor_walk(&scan.files, &mut directories);
+                directories
+            },
+            BatchSize::PerIteration,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(benches, directory_totals);
+criterion_main!(benches);
@@ -0,0 +1,70 @@
+//! Add a chargeback section to the report: what each team's storage costs
+//!
+//! Teams are given as `team=directory` pairs below the scanned root. The
+//! directories are pinned, so the results list them whatever their rank.
+//!
+//! ```text
+//! cargo run --example chargeback -- /data analytics=/data/analytics web=/data/www
+//! ```
+
+use anyhow::{Context, Result};
+use clap::Parser;
+use diranalyzer::prelude::*;
+use std::path::PathBuf;
+use std::time::Instant;
+
+/// Monthly price of a gigabyte
+const COST_PER_GB: f64 = 0.023;
+
+/// Storage cost per team, from the size of the directories each team owns
+struct Chargeback {
+    teams: Vec<(String, PathBuf)>,
+    cost_per_gb: f64,
+}
+
+impl Chargeback {
+    fn cost(&self, size: u64) -> String {
+        format!("${:.2} per month", size as f64 / 1e9 * self.cost_per_gb)
+    }
+}
+
+impl SectionProvider for Chargeback {
+    fn section(&self, results: &AnalysisResults) -> Option<ExtensionSection> {
+        let mut section = ExtensionSection::new("Chargeback");
+        let mut assigned = 0;
+        for (team, path) in &self.teams {
+            let Some(directory) = results.largest_directories.iter().find(|directory| &directory.path == path) else {
+                section = section.row(team.as_str(), format!("{} was not scanned", path.display()));
+                continue;
+            };
+            assigned += directory.size;
+            section = section.sized_row(team.as_str(), self.cost(directory.size), directory.size);
+        }
+        let unassigned = results.scan_info.total_size.saturating_sub(assigned);
+        Some(section.sized_row("Unassigned", self.cost(unassigned), unassigned))
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let mut argv = std::env::args().skip(1);
+    let root = argv.next().context("usage: chargeback ROOT TEAM=DIR...")?;
+    let teams = argv
+        .map(|pair| {
+            let (team, dir) = pair.split_once('=').with_context(|| format!("expected TEAM=DIR, got {}", pair))?;
+            Ok((team.to_string(), PathBuf::from(dir)))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut cli = vec!["diranalyzer".to_string(), "--full-report".to_string(), "--no-history".to_string(), root];
+    for (_, dir) in &teams {
+        cli.extend(["--pin".to_string(), dir.display().to_string()]);
+    }
+    let args = Args::parse_from(cli);
+
+    let start = Instant::now();
+    let mut analyzer = DirectoryAnalyzer::new(args.clone())?
+        .with_section_provider(Chargeback { teams, cost_per_gb: COST_PER_GB });
+    let results = analyzer.analyze().await?;
+    generate_report(&results, &args, analyzer.config(), start.elapsed())
+}
@@ -0,0 +1,250 @@
+//! Findings that operators have reviewed and accepted
+//!
+//! Some findings come back on every run and are fine as they are: a 900 GB
+//! directory that is supposed to be there, or a duplicate group kept on
+//! purpose. `diranalyzer accept` records such a finding, by the canonical path
+//! of a file or directory or by the stable id of a duplicate group, with the
+//! reason it was accepted, in `accepted.json` in the config directory.
+//!
+//! Accepted findings stay in the totals and the exports, flagged `accepted`.
+//! The report moves them out of the listings into a count of their own unless
+//! `--show-accepted` asks for them. Each change rewrites the whole store under
+//! a lock, as the notes store does.
+
+use crate::config;
+use crate::writes::{FileLock, WriteGuard};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// An acceptance as kept in the store
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Acceptance {
+    pub reason: String,
+    pub accepted: DateTime<Utc>,
+}
+
+/// What an acceptance covers
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AcceptedTarget {
+    /// A file or directory, listed wherever a section lists its path
+    Path(PathBuf),
+    /// A duplicate group by its `group_id`
+    Group(String),
+}
+
+/// An acceptance of a finding of the scanned tree, as carried by the results
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AcceptedFinding {
+    /// For paths, spelled as the scan spells them, under the scan root as given
+    pub target: AcceptedTarget,
+    pub reason: String,
+    pub accepted: DateTime<Utc>,
+}
+
+/// The acceptances that apply to a set of results, and whether the report shows what they cover
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AcceptedFindings {
+    /// Sorted by target
+    pub findings: Vec<AcceptedFinding>,
+    /// Leave accepted findings in the listings, as `--show-accepted` asks
+    #[serde(skip)]
+    pub shown: bool,
+}
+
+/// The contents of the store file
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct AcceptanceFile {
+    /// Keyed by canonical path
+    #[serde(default)]
+    paths: BTreeMap<PathBuf, Acceptance>,
+    #[serde(default)]
+    groups: BTreeMap<String, Acceptance>,
+}
+
+/// The acceptance file of the current user
+pub struct AcceptanceStore {
+    path: PathBuf,
+    writes: WriteGuard,
+}
+
+impl AcceptedTarget {
+    /// A duplicate group id such as `dg-0b8166c4eab71b38` names a group unless a path of that name exists
+    pub fn parse(target: &str) -> Self {
+        let is_group_id = target
+            .strip_prefix("dg-")
+            .is_some_and(|digits| digits.len() == 16 && digits.chars().all(|c| c.is_ascii_hexdigit()));
+        if is_group_id && fs::symlink_metadata(target).is_err() {
+            AcceptedTarget::Group(target.to_string())
+        } else {
+            AcceptedTarget::Path(PathBuf::from(target))
+        }
+    }
+}
+
+impl std::fmt::Display for AcceptedTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AcceptedTarget::Path(path) => write!(f, "{}", path.display()),
+            AcceptedTarget::Group(id) => write!(f, "duplicate group {}", id),
+        }
+    }
+}
+
+impl AcceptedFindings {
+    /// Whether the report lists a finding flagged `accepted` in its section
+    pub fn lists(&self, accepted: bool) -> bool {
+        self.shown || !accepted
+    }
+
+    /// The acceptance of the file or directory at `path`
+    pub fn for_path(&self, path: &Path) -> Option<&AcceptedFinding> {
+        self.findings.iter().find(|finding| matches!(&finding.target, AcceptedTarget::Path(accepted) if accepted == path))
+    }
+
+    /// The acceptance of the duplicate group `group_id`
+    pub fn for_group(&self, group_id: &str) -> Option<&AcceptedFinding> {
+        self.findings.iter().find(|finding| matches!(&finding.target, AcceptedTarget::Group(accepted) if accepted == group_id))
+    }
+}
+
+impl AcceptanceStore {
+    /// Open the store in the config directory
+    pub fn for_current_user() -> Option<Self> {
+        Some(Self::at(config::config_dir()?.join("accepted.json")))
+    }
+
+    /// Open a store backed by an explicit file
+    pub fn at(path: PathBuf) -> Self {
+        Self { path, writes: WriteGuard::unrestricted() }
+    }
+
+    /// Location of the backing file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Every acceptance, paths first. A missing file yields none.
+    pub fn list(&self) -> Result<Vec<(AcceptedTarget, Acceptance)>> {
+        let file = self.load()?;
+        let paths = file.paths.into_iter().map(|(path, acceptance)| (AcceptedTarget::Path(path), acceptance));
+        let groups = file.groups.into_iter().map(|(id, acceptance)| (AcceptedTarget::Group(id), acceptance));
+        Ok(paths.chain(groups).collect())
+    }
+
+    /// Accept `target` for `reason`, returning the acceptance it replaces
+    ///
+    /// A path must exist; it is stored canonical so it applies however a scan spells it.
+    pub fn accept(&self, target: &AcceptedTarget, reason: &str) -> Result<Option<Acceptance>> {
+        let acceptance = Acceptance { reason: reason.to_string(), accepted: Utc::now() };
+        match target {
+            AcceptedTarget::Path(path) => {
+                let canonical = fs::canonicalize(path).with_context(|| format!("Cannot accept {}", path.display()))?;
+                self.update(|file| file.paths.insert(canonical, acceptance))
+            }
+            AcceptedTarget::Group(id) => self.update(|file| file.groups.insert(id.clone(), acceptance)),
+        }
+    }
+
+    /// Withdraw the acceptance of `target`, whose path need not exist any more
+    pub fn revoke(&self, target: &AcceptedTarget) -> Result<Option<Acceptance>> {
+        match target {
+            AcceptedTarget::Path(path) => {
+                let key = fs::canonicalize(path).or_else(|_| std::path::absolute(path))?;
+                self.update(|file| file.paths.remove(&key))
+            }
+            AcceptedTarget::Group(id) => self.update(|file| file.groups.remove(id)),
+        }
+    }
+
+    /// The acceptances of `root`, the paths beneath it, and every duplicate group, paths spelled as a scan of `root` spells them
+    pub fn findings_under(&self, root: &Path) -> Result<Vec<AcceptedFinding>> {
+        let canonical_root = fs::canonicalize(root).ok();
+        let mut findings: Vec<AcceptedFinding> = self
+            .list()?
+            .into_iter()
+            .filter_map(|(target, acceptance)| {
+                let target = match target {
+                    AcceptedTarget::Path(path) => {
+                        let relative = path.strip_prefix(canonical_root.as_ref()?).ok()?;
+                        AcceptedTarget::Path(if relative.as_os_str().is_empty() { root.to_path_buf() } else { root.join(relative) })
+                    }
+                    group => group,
+                };
+                Some(AcceptedFinding { target, reason: acceptance.reason, accepted: acceptance.accepted })
+            })
+            .collect();
+        findings.sort_by(|a, b| a.target.cmp(&b.target));
+        Ok(findings)
+    }
+
+    fn load(&self) -> Result<AcceptanceFile> {
+        let data = match fs::read(&self.path) {
+            Ok(data) => data,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(AcceptanceFile::default()),
+            Err(error) => return Err(error).with_context(|| format!("Failed to read accepted findings: {}", self.path.display())),
+        };
+        serde_json::from_slice(&data).with_context(|| format!("Failed to parse accepted findings: {}", self.path.display()))
+    }
+
+    /// Change the acceptances under the store's lock and write them back
+    fn update<T>(&self, change: impl FnOnce(&mut AcceptanceFile) -> T) -> Result<T> {
+        if let Some(parent) = self.path.parent() {
+            self.writes.create_dir_all(parent)
+                .with_context(|| format!("Failed to create config directory: {}", parent.display()))?;
+        }
+
+        let _lock = FileLock::acquire(&self.path.with_extension("lock"), &self.writes)?;
+        let mut file = self.load()?;
+        let outcome = change(&mut file);
+
+        let temp_path = self.path.with_extension("tmp");
+        let mut temp = self.writes.create(&temp_path)?;
+        serde_json::to_writer_pretty(&mut temp, &file)?;
+        temp.sync_all()?;
+        self.writes.rename(&temp_path, &self.path)
+            .with_context(|| format!("Failed to update accepted findings: {}", self.path.display()))?;
+        Ok(outcome)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accept_list_revoke() {
+        let dir = tempfile::tempdir().unwrap();
+        let tree = dir.path().join("tree");
+        fs::create_dir_all(tree.join("data")).unwrap();
+        let store = AcceptanceStore::at(dir.path().join("config/accepted.json"));
+        let group = AcceptedTarget::parse("dg-0b8166c4eab71b38");
+        assert_eq!(group, AcceptedTarget::Group("dg-0b8166c4eab71b38".to_string()));
+        assert_eq!(AcceptedTarget::parse("dg-123"), AcceptedTarget::Path(PathBuf::from("dg-123")));
+
+        assert!(store.list().unwrap().is_empty());
+        assert_eq!(store.accept(&AcceptedTarget::Path(tree.join("data")), "supposed to be large").unwrap(), None);
+        assert!(store.accept(&group, "kept on purpose").unwrap().is_none());
+        assert!(store.accept(&AcceptedTarget::Path(tree.join("missing")), "x").is_err());
+
+        let spelled = tree.join("data/..");
+        let findings = store.findings_under(&spelled).unwrap();
+        assert_eq!(findings.len(), 2);
+        assert_eq!(findings[0].target, AcceptedTarget::Path(spelled.join("data")));
+        assert_eq!((&findings[1].target, findings[1].reason.as_str()), (&group, "kept on purpose"));
+        // Paths outside the root do not apply; groups always do
+        let elsewhere = dir.path().join("elsewhere");
+        fs::create_dir_all(&elsewhere).unwrap();
+        assert_eq!(store.findings_under(&elsewhere).unwrap().len(), 1);
+
+        fs::remove_dir(tree.join("data")).unwrap();
+        assert!(store.revoke(&AcceptedTarget::Path(tree.join("data"))).unwrap().is_some());
+        assert!(store.revoke(&group).unwrap().is_some());
+        assert!(store.revoke(&group).unwrap().is_none());
+        assert!(store.list().unwrap().is_empty());
+    }
+}
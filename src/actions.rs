@@ -0,0 +1,477 @@
+//! Destructive actions on duplicate groups, and undoing them
+//!
+//! `--dedupe hardlink` replaces redundant copies with hard links to the first
+//! copy of each group; `--dedupe delete` removes them. Every operation is
+//! recorded in an undo [`journal`] first. `diranalyzer undo <journal>` turns
+//! hard links back into independent files with their original metadata and
+//! reports what cannot be restored (deleted content).
+
+pub mod journal;
+
+use crate::analyzer::DuplicateGroup;
+use crate::duplicates::{FileHasher, Sha256Hasher};
+use crate::writes::WriteGuard;
+use anyhow::{bail, Context, Result};
+use clap::ValueEnum;
+use journal::{EntryMetadata, FileTime, JournalEntry, JournalWriter};
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+/// What to do with redundant copies
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum DedupeAction {
+    /// Replace copies with hard links to the kept file
+    Hardlink,
+    /// Delete copies (cannot be undone)
+    Delete,
+}
+
+/// Options for [`dedupe`]
+#[derive(Debug, Clone, Copy)]
+pub struct DedupeOptions {
+    pub action: DedupeAction,
+    /// Put back parent-directory modification times the operations changed
+    pub preserve_dir_mtime: bool,
+}
+
+/// Outcome of a dedupe run
+#[derive(Debug, Default)]
+pub struct DedupeReport {
+    pub replaced: u64,
+    pub reclaimed_bytes: u64,
+    /// Copies left alone, with the reason
+    pub skipped: Vec<(PathBuf, String)>,
+}
+
+/// Outcome of an undo run
+#[derive(Debug, Default)]
+pub struct UndoReport {
+    pub restored: u64,
+    /// Operations that could not be reverted, with the reason
+    pub unrestorable: Vec<(PathBuf, String)>,
+}
+
+/// Apply `options.action` to every copy but the first of each group, journaling each operation
+///
+/// Every change goes through `writes`; copies it refuses are skipped.
+pub fn dedupe(
+    groups: &[DuplicateGroup],
+    options: DedupeOptions,
+    journal: &mut JournalWriter,
+    writes: &WriteGuard,
+) -> Result<DedupeReport> {
+    let mut report = DedupeReport::default();
+    for group in groups {
+        let mut files = group.files.clone();
+        files.sort();
+        let Some((keep, copies)) = files.split_first() else {
+            continue;
+        };
+        for copy in copies {
+            match dedupe_copy(keep, copy, options, journal, writes) {
+                Ok(size) => {
+                    report.replaced += 1;
+                    report.reclaimed_bytes += size;
+                }
+                Err(reason) => report.skipped.push((copy.clone(), format!("{:#}", reason))),
+            }
+        }
+    }
+    Ok(report)
+}
+
+fn dedupe_copy(
+    keep: &Path,
+    copy: &Path,
+    options: DedupeOptions,
+    journal: &mut JournalWriter,
+    writes: &WriteGuard,
+) -> Result<u64> {
+    writes.check(copy)?;
+    if same_file(keep, copy)? {
+        bail!("already a hard link to {}", keep.display());
+    }
+    if options.action == DedupeAction::Hardlink && !same_device(keep, copy)? {
+        bail!("on a different filesystem than {}", keep.display());
+    }
+    if !same_contents(keep, copy)? {
+        bail!("contents changed since the scan");
+    }
+
+    let parent = copy.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+    let parent_mtime = capture(parent).ok().map(|metadata| metadata.mtime);
+    let metadata = capture(copy)?;
+    let sha256 = Sha256Hasher.hash_file(copy)?;
+
+    // Absolute paths let the journal be replayed from any working directory
+    let path = std::path::absolute(copy)?;
+    let entry = match options.action {
+        DedupeAction::Hardlink => JournalEntry::Hardlink {
+            path,
+            target: std::path::absolute(keep)?,
+            metadata: metadata.clone(),
+            sha256,
+            parent_mtime,
+        },
+        DedupeAction::Delete => JournalEntry::Delete {
+            path,
+            metadata: metadata.clone(),
+            sha256,
+            parent_mtime,
+        },
+    };
+    journal.record(&entry)?;
+
+    match options.action {
+        DedupeAction::Hardlink => {
+            // Link under a temporary name, then rename over the copy atomically
+            let staging = staging_path(copy);
+            writes.hard_link(keep, &staging)?;
+            if let Err(error) = writes.rename(&staging, copy) {
+                let _ = writes.remove_file(&staging);
+                return Err(error).with_context(|| format!("Failed to replace {}", copy.display()));
+            }
+        }
+        DedupeAction::Delete => {
+            writes.remove_file(copy).with_context(|| format!("Failed to delete {}", copy.display()))?;
+        }
+    }
+
+    if options.preserve_dir_mtime {
+        if let Some(mtime) = parent_mtime {
+            set_mtime(parent, mtime, writes)?;
+        }
+    }
+    Ok(metadata.size)
+}
+
+/// Revert the operations of a journal, newest first, writing through `writes`
+pub fn undo(journal_path: &Path, writes: &WriteGuard) -> Result<UndoReport> {
+    let mut report = UndoReport::default();
+    for entry in journal::read_journal(journal_path)?.into_iter().rev() {
+        match entry {
+            JournalEntry::Header { .. } => {}
+            JournalEntry::Hardlink { path, target, metadata, sha256, parent_mtime } => {
+                match restore_copy(&path, &target, &metadata, &sha256, parent_mtime, writes) {
+                    Ok(()) => report.restored += 1,
+                    Err(reason) => report.unrestorable.push((path, format!("{:#}", reason))),
+                }
+            }
+            JournalEntry::Delete { path, .. } => {
+                report.unrestorable.push((path, "content was deleted permanently".to_string()));
+            }
+        }
+    }
+    Ok(report)
+}
+
+fn restore_copy(
+    path: &Path,
+    target: &Path,
+    metadata: &EntryMetadata,
+    sha256: &str,
+    parent_mtime: Option<FileTime>,
+    writes: &WriteGuard,
+) -> Result<()> {
+    if !path.exists() {
+        bail!("no longer exists");
+    }
+    if !same_file(path, target)? {
+        bail!("is no longer a hard link to {} (not applied, or already restored)", target.display());
+    }
+
+    let staging = staging_path(path);
+    writes.copy(target, &staging)?;
+    let restored = (|| {
+        if Sha256Hasher.hash_file(&staging)? != sha256 {
+            bail!("{} was modified after deduplication", target.display());
+        }
+        apply(&staging, metadata, writes)?;
+        writes.rename(&staging, path).with_context(|| format!("Failed to replace {}", path.display()))
+    })();
+    if restored.is_err() {
+        let _ = writes.remove_file(&staging);
+    }
+    restored?;
+
+    if let (Some(parent), Some(mtime)) = (path.parent().filter(|p| !p.as_os_str().is_empty()), parent_mtime) {
+        set_mtime(parent, mtime, writes)?;
+    }
+    Ok(())
+}
+
+/// Temporary sibling used to swap a file atomically
+fn staging_path(path: &Path) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!(".{}.diranalyzer-{}", name, std::process::id()))
+}
+
+/// Byte-for-byte comparison
+fn same_contents(a: &Path, b: &Path) -> Result<bool> {
+    let (mut a, mut b) = (fs::File::open(a)?, fs::File::open(b)?);
+    if a.metadata()?.len() != b.metadata()?.len() {
+        return Ok(false);
+    }
+    let (mut buf_a, mut buf_b) = (vec![0u8; 64 * 1024], vec![0u8; 64 * 1024]);
+    loop {
+        let read = a.read(&mut buf_a)?;
+        if read == 0 {
+            return Ok(true);
+        }
+        b.read_exact(&mut buf_b[..read])?;
+        if buf_a[..read] != buf_b[..read] {
+            return Ok(false);
+        }
+    }
+}
+
+/// Capture the metadata an undo needs to restore
+pub fn capture(path: &Path) -> Result<EntryMetadata> {
+    let metadata = fs::symlink_metadata(path).with_context(|| format!("Failed to read metadata of {}", path.display()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        Ok(EntryMetadata {
+            size: metadata.len(),
+            mtime: FileTime { secs: metadata.mtime(), nanos: metadata.mtime_nsec() as u32 },
+            mode: Some(metadata.mode()),
+            uid: Some(metadata.uid()),
+            gid: Some(metadata.gid()),
+        })
+    }
+    #[cfg(not(unix))]
+    {
+        let since_epoch = metadata
+            .modified()?
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap_or_default();
+        Ok(EntryMetadata {
+            size: metadata.len(),
+            mtime: FileTime { secs: since_epoch.as_secs() as i64, nanos: since_epoch.subsec_nanos() },
+            mode: None,
+            uid: None,
+            gid: None,
+        })
+    }
+}
+
+/// Restore ownership, permissions, and modification time
+fn apply(path: &Path, metadata: &EntryMetadata, writes: &WriteGuard) -> Result<()> {
+    writes.check(path)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let current = capture(path)?;
+        if (metadata.uid, metadata.gid) != (current.uid, current.gid) {
+            std::os::unix::fs::chown(path, metadata.uid, metadata.gid)
+                .with_context(|| format!("Failed to restore the owner of {}", path.display()))?;
+        }
+        if let Some(mode) = metadata.mode {
+            fs::set_permissions(path, fs::Permissions::from_mode(mode & 0o7777))?;
+        }
+    }
+    set_mtime(path, metadata.mtime, writes)
+}
+
+fn set_mtime(path: &Path, mtime: FileTime, writes: &WriteGuard) -> Result<()> {
+    writes.check(path)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        let c_path = std::ffi::CString::new(path.as_os_str().as_bytes())?;
+        let times = [
+            libc::timespec { tv_sec: 0, tv_nsec: libc::UTIME_OMIT },
+            libc::timespec { tv_sec: mtime.secs as libc::time_t, tv_nsec: mtime.nanos as _ },
+        ];
+        // SAFETY: the path is NUL-terminated and `times` holds the two required entries
+        let result = unsafe { libc::utimensat(libc::AT_FDCWD, c_path.as_ptr(), times.as_ptr(), libc::AT_SYMLINK_NOFOLLOW) };
+        if result != 0 {
+            return Err(std::io::Error::last_os_error())
+                .with_context(|| format!("Failed to set the modification time of {}", path.display()));
+        }
+        Ok(())
+    }
+    #[cfg(not(unix))]
+    {
+        let time = std::time::UNIX_EPOCH + std::time::Duration::new(mtime.secs.max(0) as u64, mtime.nanos);
+        fs::File::options().write(true).open(path)?.set_modified(time)?;
+        Ok(())
+    }
+}
+
+fn same_file(a: &Path, b: &Path) -> Result<bool> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        let (a, b) = (fs::metadata(a)?, fs::metadata(b)?);
+        Ok((a.dev(), a.ino()) == (b.dev(), b.ino()))
+    }
+    #[cfg(not(unix))]
+    {
+        Ok(fs::canonicalize(a)? == fs::canonicalize(b)?)
+    }
+}
+
+fn same_device(a: &Path, b: &Path) -> Result<bool> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        Ok(fs::metadata(a)?.dev() == fs::metadata(b)?.dev())
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = (a, b);
+        Ok(true)
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use crate::duplicates::DuplicateFinder;
+    use crate::scanner::FileEntry;
+    use std::collections::BTreeMap;
+    use std::os::unix::fs::{MetadataExt, PermissionsExt};
+
+    /// Content and restorable metadata of every entry below `root`
+    fn snapshot(root: &Path) -> BTreeMap<PathBuf, (Vec<u8>, EntryMetadata)> {
+        walkdir::WalkDir::new(root)
+            .into_iter()
+            .map(|entry| entry.unwrap().into_path())
+            .map(|path| {
+                let contents = if path.is_file() { fs::read(&path).unwrap() } else { Vec::new() };
+                let mut metadata = capture(&path).unwrap();
+                if path.is_dir() {
+                    metadata.size = 0; // directory sizes vary with internal layout
+                }
+                (path, (contents, metadata))
+            })
+            .collect()
+    }
+
+    fn write(path: &Path, contents: &[u8], mode: u32, mtime: i64) {
+        fs::write(path, contents).unwrap();
+        fs::set_permissions(path, fs::Permissions::from_mode(mode)).unwrap();
+        set_mtime(path, FileTime { secs: mtime, nanos: 123_456_789 }, &WriteGuard::unrestricted()).unwrap();
+    }
+
+    async fn duplicate_groups(root: &Path) -> Vec<DuplicateGroup> {
+        let files: Vec<FileEntry> = walkdir::WalkDir::new(root)
+            .into_iter()
+            .map(|entry| entry.unwrap())
+            .filter(|entry| entry.file_type().is_file())
+            .map(|entry| FileEntry {
+                path: entry.path().to_path_buf(),
+                size: entry.metadata().unwrap().len(),
+                depth: entry.depth(),
+                ..Default::default()
+            })
+            .collect();
+        DuplicateFinder::new(1, Some(1)).find_duplicates(&files).await.unwrap()
+    }
+
+    fn fixture(root: &Path) {
+        fs::create_dir_all(root.join("a")).unwrap();
+        fs::create_dir_all(root.join("b/nested")).unwrap();
+        write(&root.join("a/photo.jpg"), &[7u8; 5_000], 0o644, 1_600_000_000);
+        write(&root.join("b/photo copy.jpg"), &[7u8; 5_000], 0o600, 1_650_000_000);
+        write(&root.join("b/nested/photo.jpg"), &[7u8; 5_000], 0o640, 1_700_000_000);
+        write(&root.join("a/unique.txt"), b"only one", 0o644, 1_600_000_000);
+        for dir in ["a", "b", "b/nested"] {
+            set_mtime(&root.join(dir), FileTime { secs: 1_500_000_000, nanos: 0 }, &WriteGuard::unrestricted()).unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_hardlink_dedupe_and_undo_restore_the_tree() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("tree");
+        fixture(&root);
+        let before = snapshot(&root);
+
+        let journal_path = dir.path().join("undo.ndjson");
+        let mut journal = JournalWriter::create(&journal_path, &WriteGuard::unrestricted()).unwrap();
+        let options = DedupeOptions { action: DedupeAction::Hardlink, preserve_dir_mtime: true };
+        let report = dedupe(&duplicate_groups(&root).await, options, &mut journal, &WriteGuard::unrestricted()).unwrap();
+        drop(journal);
+
+        assert_eq!((report.replaced, report.reclaimed_bytes), (2, 10_000));
+        assert_eq!(fs::metadata(root.join("a/photo.jpg")).unwrap().nlink(), 3);
+        // Parent directories keep their modification times
+        assert_eq!(capture(&root.join("b")).unwrap().mtime, before[&root.join("b")].1.mtime);
+
+        // Running again finds nothing left to do
+        let mut again = JournalWriter::create(&dir.path().join("again.ndjson"), &WriteGuard::unrestricted()).unwrap();
+        let report = dedupe(&duplicate_groups(&root).await, options, &mut again, &WriteGuard::unrestricted()).unwrap();
+        assert_eq!(report.replaced, 0);
+        assert_eq!(report.skipped.len(), 2);
+
+        let undone = undo(&journal_path, &WriteGuard::unrestricted()).unwrap();
+        assert_eq!(undone.restored, 2);
+        assert!(undone.unrestorable.is_empty(), "{:?}", undone.unrestorable);
+        assert_eq!(snapshot(&root), before);
+        assert_eq!(fs::metadata(root.join("a/photo.jpg")).unwrap().nlink(), 1);
+
+        // A second undo reports the operations as already reverted
+        let repeated = undo(&journal_path, &WriteGuard::unrestricted()).unwrap();
+        assert_eq!(repeated.restored, 0);
+        assert_eq!(repeated.unrestorable.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_deletes_are_reported_as_unrestorable() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("tree");
+        fixture(&root);
+
+        let journal_path = dir.path().join("undo.ndjson");
+        let mut journal = JournalWriter::create(&journal_path, &WriteGuard::unrestricted()).unwrap();
+        let options = DedupeOptions { action: DedupeAction::Delete, preserve_dir_mtime: false };
+        let report = dedupe(&duplicate_groups(&root).await, options, &mut journal, &WriteGuard::unrestricted()).unwrap();
+        drop(journal);
+
+        assert_eq!(report.replaced, 2);
+        assert!(root.join("a/photo.jpg").exists());
+        assert!(!root.join("b/nested/photo.jpg").exists());
+
+        let undone = undo(&journal_path, &WriteGuard::unrestricted()).unwrap();
+        assert_eq!(undone.restored, 0);
+        assert_eq!(undone.unrestorable.len(), 2);
+        assert!(undone.unrestorable[0].1.contains("deleted permanently"));
+    }
+
+    #[tokio::test]
+    async fn test_changed_copies_are_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("tree");
+        fixture(&root);
+        let groups = duplicate_groups(&root).await;
+        fs::write(root.join("b/photo copy.jpg"), [8u8; 5_000]).unwrap();
+
+        let mut journal = JournalWriter::create(&dir.path().join("undo.ndjson"), &WriteGuard::unrestricted()).unwrap();
+        let options = DedupeOptions { action: DedupeAction::Hardlink, preserve_dir_mtime: false };
+        let report = dedupe(&groups, options, &mut journal, &WriteGuard::unrestricted()).unwrap();
+
+        assert_eq!(report.replaced, 1);
+        assert_eq!(report.skipped, [(root.join("b/photo copy.jpg"), "contents changed since the scan".to_string())]);
+        assert_eq!(fs::read(root.join("b/photo copy.jpg")).unwrap(), [8u8; 5_000]);
+    }
+
+    #[tokio::test]
+    async fn test_read_only_guard_leaves_every_copy() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("tree");
+        fixture(&root);
+        let groups = duplicate_groups(&root).await;
+        let before = fs::metadata(root.join("b/photo copy.jpg")).unwrap().ino();
+
+        let mut journal = JournalWriter::create(&dir.path().join("undo.ndjson"), &WriteGuard::unrestricted()).unwrap();
+        let options = DedupeOptions { action: DedupeAction::Delete, preserve_dir_mtime: true };
+        let report = dedupe(&groups, options, &mut journal, &WriteGuard::read_only(&root)).unwrap();
+
+        assert_eq!(report.replaced, 0);
+        assert!(report.skipped.iter().all(|(_, reason)| reason.contains("read-only")), "{:?}", report.skipped);
+        assert_eq!(fs::metadata(root.join("b/photo copy.jpg")).unwrap().ino(), before);
+    }
+}
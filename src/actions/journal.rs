@@ -0,0 +1,159 @@
+//! Undo journal for destructive actions
+//!
+//! A journal is newline-delimited JSON: a header line carrying the format
+//! version, then one line per operation. Each operation is written and
+//! flushed *before* it is carried out, so an interrupted run still leaves a
+//! journal covering everything it may have touched.
+
+use crate::writes::WriteGuard;
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// Current journal format version
+pub const JOURNAL_VERSION: u32 = 1;
+
+/// A modification time with nanosecond precision
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileTime {
+    pub secs: i64,
+    pub nanos: u32,
+}
+
+/// Metadata of a file before an operation changed it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EntryMetadata {
+    pub size: u64,
+    pub mtime: FileTime,
+    pub mode: Option<u32>,
+    pub uid: Option<u32>,
+    pub gid: Option<u32>,
+}
+
+/// One line of a journal
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum JournalEntry {
+    Header {
+        version: u32,
+        created: DateTime<Utc>,
+        tool_version: String,
+    },
+    /// `path` was replaced by a hard link to `target`
+    Hardlink {
+        path: PathBuf,
+        target: PathBuf,
+        metadata: EntryMetadata,
+        sha256: String,
+        /// Modification time of the parent directory before the operation
+        parent_mtime: Option<FileTime>,
+    },
+    /// `path` was deleted; its content cannot be restored
+    Delete {
+        path: PathBuf,
+        metadata: EntryMetadata,
+        sha256: String,
+        parent_mtime: Option<FileTime>,
+    },
+}
+
+/// Appends operations to a journal file
+pub struct JournalWriter {
+    path: PathBuf,
+    writer: BufWriter<File>,
+}
+
+impl JournalWriter {
+    /// Create a journal through `writes`, refusing to overwrite an existing one
+    pub fn create(path: &Path, writes: &WriteGuard) -> Result<Self> {
+        let file = writes
+            .open(path, File::options().write(true).create_new(true))
+            .with_context(|| format!("Failed to create undo journal: {}", path.display()))?;
+        let mut journal = Self { path: path.to_path_buf(), writer: BufWriter::new(file) };
+        journal.record(&JournalEntry::Header {
+            version: JOURNAL_VERSION,
+            created: Utc::now(),
+            tool_version: env!("CARGO_PKG_VERSION").to_string(),
+        })?;
+        Ok(journal)
+    }
+
+    /// Write and flush one operation
+    pub fn record(&mut self, entry: &JournalEntry) -> Result<()> {
+        serde_json::to_writer(&mut self.writer, entry)?;
+        self.writer.write_all(b"\n")?;
+        self.writer
+            .flush()
+            .with_context(|| format!("Failed to write undo journal: {}", self.path.display()))
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+/// Read the operations of a journal, checking its version
+pub fn read_journal(path: &Path) -> Result<Vec<JournalEntry>> {
+    let file = File::open(path).with_context(|| format!("Failed to open undo journal: {}", path.display()))?;
+    let mut entries = Vec::new();
+    for (number, line) in BufReader::new(file).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let entry: JournalEntry = serde_json::from_str(&line)
+            .with_context(|| format!("Invalid journal entry on line {} of {}", number + 1, path.display()))?;
+        entries.push(entry);
+    }
+
+    match entries.first() {
+        Some(JournalEntry::Header { version, .. }) if *version == JOURNAL_VERSION => {
+            entries.remove(0);
+            Ok(entries)
+        }
+        Some(JournalEntry::Header { version, .. }) => bail!(
+            "Undo journal {} has format version {}, this build reads version {}",
+            path.display(),
+            version,
+            JOURNAL_VERSION
+        ),
+        _ => bail!("{} is not an undo journal: missing header", path.display()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip_and_version_check() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("undo.ndjson");
+        let entry = JournalEntry::Delete {
+            path: PathBuf::from("/data/copy.bin"),
+            metadata: EntryMetadata {
+                size: 10,
+                mtime: FileTime { secs: 1_700_000_000, nanos: 5 },
+                mode: Some(0o100644),
+                uid: Some(1000),
+                gid: Some(1000),
+            },
+            sha256: "ab".repeat(32),
+            parent_mtime: None,
+        };
+
+        let mut journal = JournalWriter::create(&path, &WriteGuard::unrestricted()).unwrap();
+        journal.record(&entry).unwrap();
+        drop(journal);
+        assert_eq!(read_journal(&path).unwrap(), [entry]);
+        assert!(JournalWriter::create(&path, &WriteGuard::unrestricted()).is_err(), "existing journals are never overwritten");
+
+        let contents = std::fs::read_to_string(&path).unwrap();
+        std::fs::write(&path, contents.replacen("\"version\":1", "\"version\":99", 1)).unwrap();
+        let error = read_journal(&path).unwrap_err().to_string();
+        assert!(error.contains("format version 99"), "{}", error);
+    }
+}
@@ -0,0 +1,266 @@
+//! How long ago the collected files were last used, in age buckets
+//!
+//! A file's last use is the later of its access and modification times. Access
+//! times say more about what is still read, but many filesystems are mounted
+//! `noatime` and never update them; there, and where the walk recorded no
+//! access times at all, only modification times are used. Which one a run
+//! used is recorded in its scan information.
+//!
+//! The bucket boundaries come from `age_buckets` in the configuration file.
+//! With `--stale-threshold`, the largest files unused for at least that long
+//! are listed as well.
+
+use crate::scanner::{self, FileEntry};
+use anyhow::{bail, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Bucket boundaries used when the configuration names none
+pub const DEFAULT_AGE_BUCKETS: [&str; 4] = ["30d", "180d", "1y", "3y"];
+
+const DAY_SECS: u64 = 86_400;
+
+/// Which timestamp a run took as a file's last use
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AgeSource {
+    /// The later of the access and modification times
+    Accessed,
+    /// The modification time alone
+    #[default]
+    Modified,
+}
+
+impl AgeSource {
+    /// Access times where the walk recorded some and no root is mounted `noatime`
+    pub fn for_run(files: &[FileEntry], roots: &[PathBuf]) -> Self {
+        if files.iter().any(|file| file.accessed.is_some()) {
+            Self::for_roots(roots)
+        } else {
+            Self::Modified
+        }
+    }
+
+    /// Access times unless a root is mounted `noatime`, before any file is seen
+    ///
+    /// Where no file has an access time, [`AgeSource::last_used`] gives the
+    /// same ages under either source; only the label differs from [`AgeSource::for_run`].
+    pub fn for_roots(roots: &[PathBuf]) -> Self {
+        if roots.iter().any(|root| scanner::atime_disabled(root)) {
+            Self::Modified
+        } else {
+            Self::Accessed
+        }
+    }
+
+    /// When `file` was last used, or `None` for an undated file
+    pub fn last_used(self, file: &FileEntry) -> Option<DateTime<Utc>> {
+        match self {
+            Self::Accessed => file.accessed.max(file.modified),
+            Self::Modified => file.modified,
+        }
+    }
+}
+
+/// Parse the configured bucket boundaries into ascending ages in seconds
+pub fn boundaries(specs: &[String]) -> Result<Vec<u64>> {
+    let mut boundaries = Vec::with_capacity(specs.len());
+    for spec in specs {
+        let age = match crate::utils::parse_duration(spec) {
+            Ok(age) => age.as_secs(),
+            Err(error) => bail!("Invalid age bucket in the configuration: {}", error),
+        };
+        if age == 0 {
+            bail!("Invalid age bucket in the configuration: '{}' is no age", spec);
+        }
+        boundaries.push(age);
+    }
+    boundaries.sort_unstable();
+    boundaries.dedup();
+    Ok(boundaries)
+}
+
+/// Files last used within one range of ages
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AgeBucket {
+    /// The youngest age in the bucket, in seconds
+    pub min_age_secs: u64,
+    /// The age the next bucket starts at; `None` for the oldest bucket
+    pub max_age_secs: Option<u64>,
+    pub count: u64,
+    pub total_size: u64,
+}
+
+/// The collected files by age of their last use, youngest bucket first
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AgeBreakdown {
+    pub buckets: Vec<AgeBucket>,
+    /// Files without the timestamp the run used
+    pub undated_files: u64,
+    pub undated_size: u64,
+}
+
+impl AgeBreakdown {
+    /// Sort `files` into the buckets `boundaries` delimit, ages counted back from `ages_from`
+    pub fn of(files: &[FileEntry], boundaries: &[u64], source: AgeSource, ages_from: DateTime<Utc>) -> Self {
+        let mut breakdown = Self::new(boundaries);
+        for file in files {
+            breakdown.add(file, source, ages_from);
+        }
+        breakdown
+    }
+
+    /// Empty buckets delimited by `boundaries`
+    pub fn new(boundaries: &[u64]) -> Self {
+        let starts = std::iter::once(0).chain(boundaries.iter().copied());
+        let ends = boundaries.iter().copied().map(Some).chain([None]);
+        AgeBreakdown {
+            buckets: starts.zip(ends).map(|(start, end)| AgeBucket { min_age_secs: start, max_age_secs: end, ..Default::default() }).collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Put one file in its bucket
+    pub fn add(&mut self, file: &FileEntry, source: AgeSource, ages_from: DateTime<Utc>) {
+        let Some(age) = age_secs(source.last_used(file), ages_from) else {
+            self.undated_files += 1;
+            self.undated_size += file.counted_size();
+            return;
+        };
+        let bucket = self.buckets.partition_point(|bucket| bucket.min_age_secs <= age) - 1;
+        self.buckets[bucket].count += 1;
+        self.buckets[bucket].total_size += file.counted_size();
+    }
+
+    /// Bytes of every file, dated or not
+    pub fn total_size(&self) -> u64 {
+        self.buckets.iter().map(|bucket| bucket.total_size).sum::<u64>() + self.undated_size
+    }
+}
+
+/// One file unused for at least the stale threshold
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StaleFile {
+    pub path: PathBuf,
+    pub size: u64,
+    pub last_used: DateTime<Utc>,
+}
+
+/// Files unused for at least `--stale-threshold`, with the largest of them
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StaleFiles {
+    pub threshold_secs: u64,
+    pub count: u64,
+    pub total_size: u64,
+    /// Largest first, at most `--top` of them
+    pub largest: Vec<StaleFile>,
+}
+
+impl StaleFiles {
+    /// The files last used at least `threshold_secs` before `ages_from`, keeping the `top` largest
+    pub fn of(files: &[FileEntry], threshold_secs: u64, source: AgeSource, ages_from: DateTime<Utc>, top: usize) -> Self {
+        let mut stale = StaleFiles { threshold_secs, ..Default::default() };
+        let mut found: Vec<StaleFile> = files.iter().filter_map(|file| stale.add(file, source, ages_from)).collect();
+        found.sort_by(StaleFile::order);
+        found.truncate(top);
+        stale.largest = found;
+        stale
+    }
+
+    /// Count `file` if it is stale, returning it for the caller to list; `largest` is left as it is
+    pub fn add(&mut self, file: &FileEntry, source: AgeSource, ages_from: DateTime<Utc>) -> Option<StaleFile> {
+        let last_used = source.last_used(file)?;
+        let age = age_secs(Some(last_used), ages_from)?;
+        if age < self.threshold_secs {
+            return None;
+        }
+        self.count += 1;
+        self.total_size += file.counted_size();
+        Some(StaleFile { path: file.path.clone(), size: file.counted_size(), last_used })
+    }
+}
+
+impl StaleFile {
+    /// Largest first, ties by path, as [`StaleFiles::largest`] is ordered
+    pub fn order(a: &StaleFile, b: &StaleFile) -> std::cmp::Ordering {
+        b.size.cmp(&a.size).then_with(|| a.path.cmp(&b.path))
+    }
+}
+
+/// Seconds from `time` to `ages_from`; a time after it counts as no age at all
+fn age_secs(time: Option<DateTime<Utc>>, ages_from: DateTime<Utc>) -> Option<u64> {
+    time.map(|time| (ages_from - time).num_seconds().max(0) as u64)
+}
+
+/// An age in the largest whole unit [`parse_duration`](crate::utils::parse_duration) reads back, such as `1y` or `180d`
+pub fn format_age(secs: u64) -> String {
+    let units = [(365 * DAY_SECS, "y"), (7 * DAY_SECS, "w"), (DAY_SECS, "d"), (3_600, "h"), (60, "m")];
+    units
+        .iter()
+        .find(|(unit, _)| secs >= *unit && secs.is_multiple_of(*unit))
+        .map_or_else(|| format!("{}s", secs), |(unit, suffix)| format!("{}{}", secs / unit, suffix))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 6, 1, 0, 0, 0).unwrap()
+    }
+
+    fn file(path: &str, size: u64, modified_days: Option<i64>, accessed_days: Option<i64>) -> FileEntry {
+        let ago = |days: i64| now() - Duration::days(days);
+        FileEntry {
+            path: PathBuf::from(path),
+            size,
+            modified: modified_days.map(ago),
+            depth: 1,
+            accessed: accessed_days.map(ago),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_files_fall_into_buckets_by_last_use() {
+        let boundaries = boundaries(&DEFAULT_AGE_BUCKETS.map(String::from)).unwrap();
+        let files = [
+            file("fresh", 10, Some(2), None),
+            file("read-lately", 20, Some(800), Some(5)),
+            file("year", 40, Some(400), Some(400)),
+            file("ancient", 80, Some(2_000), None),
+            file("future", 160, Some(-3), None),
+            file("undated", 320, None, None),
+        ];
+
+        let by_access = AgeBreakdown::of(&files, &boundaries, AgeSource::Accessed, now());
+        let sizes = |breakdown: &AgeBreakdown| breakdown.buckets.iter().map(|bucket| bucket.total_size).collect::<Vec<_>>();
+        assert_eq!(sizes(&by_access), [190, 0, 0, 40, 80]);
+        assert_eq!((by_access.undated_files, by_access.undated_size, by_access.total_size()), (1, 320, 630));
+        assert_eq!(by_access.buckets[4], AgeBucket { min_age_secs: 3 * 365 * DAY_SECS, max_age_secs: None, count: 1, total_size: 80 });
+
+        // Without access times, the file read lately is as old as its last write
+        let by_modification = AgeBreakdown::of(&files, &boundaries, AgeSource::Modified, now());
+        assert_eq!(sizes(&by_modification), [170, 0, 0, 60, 80]);
+    }
+
+    #[test]
+    fn test_stale_files_keep_the_largest() {
+        let files = [file("a", 10, Some(500), None), file("b", 30, Some(900), None), file("c", 20, Some(366), None), file("d", 99, Some(10), None)];
+
+        let stale = StaleFiles::of(&files, 365 * DAY_SECS, AgeSource::Modified, now(), 2);
+        assert_eq!((stale.count, stale.total_size), (3, 60));
+        assert_eq!(stale.largest.iter().map(|file| file.path.to_str().unwrap()).collect::<Vec<_>>(), ["b", "c"]);
+    }
+
+    #[test]
+    fn test_boundaries_and_their_labels() {
+        let specs = ["1y", "30d", "30d", "6months"].map(String::from);
+        assert_eq!(boundaries(&specs).unwrap().into_iter().map(format_age).collect::<Vec<_>>(), ["30d", "180d", "1y"]);
+        assert!(boundaries(&["0d".to_string()]).is_err());
+        assert!(boundaries(&["soon".to_string()]).is_err());
+        assert_eq!((format_age(3 * 365 * DAY_SECS), format_age(2 * 7 * DAY_SECS), format_age(90)), ("3y".into(), "2w".into(), "90s".into()));
+    }
+}
@@ -1,21 +1,112 @@
 //! Core directory analysis functionality
 
-use crate::cli::Args;
-use crate::scanner::{DirectoryScanner, ScanResults};
-use crate::duplicates::DuplicateFinder;
-use crate::utils::{FileTypeClassifier, SizeBreakdown};
-use anyhow::Result;
+mod fold;
+
+use crate::annotations::KnowledgeBase;
+use crate::cli::{Args, ExportScope, SourceKind};
+use crate::config::Config;
+use crate::duplicates;
+use crate::entropy::{self, EntropyReport, SampleOptions};
+use crate::preview;
+use crate::extensions::{ExtensionSection, SectionProvider};
+use crate::export::stream::{self, RecordSender, StreamRecord};
+use crate::hash_cache::{CacheUsage, HashCache};
+use crate::interrupt::Cancellation;
+use crate::history::{self, DuplicateSnapshot, HistoryRecord, HistoryStore, ResolvedGroup, Trend};
+use crate::interop;
+use crate::metrics::{CustomMetric, MetricInputs, MetricSet, MetricTally};
+use crate::accepted::{AcceptanceStore, AcceptedFindings};
+use crate::ages::{self, AgeBreakdown, AgeSource, StaleFiles};
+use crate::notes::{Annotation, NoteStore};
+use crate::refilter::{AppliedFilters, AppliedSizeRange, FileMatcher, SizeRange};
+use crate::security::SecurityAudit;
+use crate::space::{self, Shortfall};
+use crate::scanner::{self, Aliases, CapKind, CaseCollision, ScanEntry, SymlinkReport, Coverage, DateFilter, DepthBoundary, DirectoryEntry, DiskUsage, DirectoryRetention, DirectoryScanner, ErrorSummary, ErrorType, ExcludePattern, ExclusionStats, ExclusionTrace, FileEntry, FileFilter, FileSource, InterimSnapshot, InventorySource, OwnerShare, Ownership, PartialScan, ScanError, ScanResults, SkippedMount, SlowDirectory, SlowThresholds};
+use crate::duplicates::{DuplicateFinder, ResultsJournal};
+use crate::utils::latency::{IoLatency, IoLatencyReport};
+use crate::utils::resource::{PhaseRecorder, PhaseUsage};
+use crate::utils::time::{self, ClockSkew};
+use crate::utils::{self, format_count, format_duration, panic_message, random_seed, FileTypeClassifier, RuleHits, SizeBreakdown, TypeId};
+use crate::versions::{VersionGrouper, VersionReport};
+use crate::writes::{WriteGuard, WriteTargets};
+use fold::{FileFold, FileListings, FoldContext, Listings};
+use anyhow::{bail, Context, Result};
 use chrono::{DateTime, Utc};
+use colored::Colorize;
+use humansize::{format_size, DECIMAL};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::task::Poll;
+use std::time::{Duration, Instant};
 
 /// Main analyzer that orchestrates the analysis process
 pub struct DirectoryAnalyzer {
     args: Args,
-    scanner: DirectoryScanner,
+    /// The root as given, before a symlinked one was resolved into `args.path`
+    path_given: PathBuf,
+    config: Config,
+    /// Lists the files to analyze: the local walker unless `--source` names another
+    source: Box<dyn FileSource>,
+    /// Walkers of the further roots given after PATH, in order
+    more_sources: Vec<Box<dyn FileSource>>,
+    /// Roots left out because another root already covers them
+    nested_roots: Vec<PathBuf>,
+    /// Roots of a `--paths-from` list that are not readable directories, reported as scan errors
+    unreadable_roots: Vec<ScanError>,
     duplicate_finder: Option<DuplicateFinder>,
+    hash_cache: Option<Arc<HashCache>>,
+    section_providers: Vec<Box<dyn SectionProvider>>,
     classifier: FileTypeClassifier,
+    /// Custom metrics of the configuration, tallied during the analysis pass
+    metrics: MetricSet,
+    annotations: KnowledgeBase,
+    clock: Box<dyn Clock>,
+    writes: WriteGuard,
+    /// What the run writes inside the scanned tree, left out of the scan
+    own_outputs: WriteTargets,
+    /// Shared by the scanner and the duplicate finder with `--io-latency-stats`
+    latency: IoLatency,
+    /// Receives scan and hashing progress with `--export ndjson`
+    stream: Option<RecordSender>,
+    /// `--min-file-size` and `--max-file-size`, checked when the analyzer is built
+    size_range: Option<SizeRange>,
+    /// Where the file age buckets start, in seconds, from `age_buckets` in the configuration
+    age_boundaries: Vec<u64>,
+    /// Stops the scan and the duplicate search early, such as on Ctrl-C
+    cancellation: Option<Cancellation>,
+    /// When `--max-duration` runs out, for the duplicate search; the scan keeps its own
+    deadline: Option<Instant>,
+}
+
+/// Source of the current time for result timestamps and time-based scores
+///
+/// Tests substitute a [`FixedClock`] so that results are reproducible.
+pub trait Clock: Send + Sync {
+    fn now(&self) -> DateTime<Utc>;
+}
+
+/// The system's wall clock
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// A clock that always reads the same instant
+#[derive(Debug, Clone, Copy)]
+pub struct FixedClock(pub DateTime<Utc>);
+
+impl Clock for FixedClock {
+    fn now(&self) -> DateTime<Utc> {
+        self.0
+    }
 }
 
 /// Complete analysis results
@@ -28,17 +119,235 @@ pub struct AnalysisResults {
     pub largest_directories: Vec<DirectoryInfo>,
     pub duplicate_groups: Option<Vec<DuplicateGroup>>,
     pub statistics: Statistics,
+    pub trend: Option<Trend>,
+    #[serde(default)]
+    pub phase_errors: Vec<PhaseError>,
+    /// Errors the walk met, by cause and subtree, with the first ones listed
+    #[serde(default)]
+    pub scan_errors: ErrorSummary,
+    #[serde(default)]
+    pub directory_filter: Option<DirectoryFilter>,
+    #[serde(default)]
+    pub largest_xattr_files: Vec<XattrFile>,
+    #[serde(default)]
+    pub diagnostics: Diagnostics,
+    #[serde(default)]
+    pub residuals: Residuals,
+    #[serde(default)]
+    pub regenerable: RegenerableTotals,
+    /// High-entropy shares of recently modified bytes, with `--entropy-sample`
+    #[serde(default)]
+    pub entropy: Option<EntropyReport>,
+    /// Duplicate groups of the previous run that are gone now, when history is enabled
+    #[serde(default)]
+    pub resolved_groups: Option<Vec<ResolvedGroup>>,
+    /// Sibling names equal under case folding, with `--detect-case-collisions`
+    #[serde(default)]
+    pub case_collisions: Option<Vec<CaseCollision>>,
+    /// Files that look like versions of one another by name alone, with `--find-versions`
+    #[serde(default)]
+    pub heuristic_versions: Option<VersionReport>,
+    /// Symlinks by what their targets are, with `--find-broken-links`
+    #[serde(default)]
+    pub symlink_report: Option<SymlinkReport>,
+    /// Mount points and files on other filesystems, left out with `--one-file-system`
+    #[serde(default)]
+    pub skipped_mounts: Option<Vec<SkippedMount>>,
+    /// Directories with no files anywhere below them, sorted; none when the scan is partial
+    #[serde(default)]
+    pub empty_directories: Vec<PathBuf>,
+    /// Files of zero bytes in the size range, sorted
+    #[serde(default)]
+    pub empty_files: Vec<PathBuf>,
+    /// Operator notes on paths of the scanned tree, sorted by path
+    #[serde(default)]
+    pub annotations: Vec<Annotation>,
+    /// Acceptances of paths of the scanned tree and of duplicate groups
+    #[serde(default)]
+    pub accepted: AcceptedFindings,
+    /// The report-time filters the totals and listings reflect, when any were given
+    #[serde(default)]
+    pub report_filters: Option<AppliedFilters>,
+    /// The `--min-file-size`/`--max-file-size` range the file listings reflect, when given
+    #[serde(default)]
+    pub file_size_range: Option<AppliedSizeRange>,
+    /// Every directory and file collected, before report-time filters, with `--export-scope full`
+    #[serde(default)]
+    pub entries: Option<Vec<ScanEntry>>,
+    /// Metrics defined in the configuration file, in the order they are defined
+    #[serde(default)]
+    pub custom_metrics: Vec<CustomMetric>,
+    /// Sections contributed by registered [`SectionProvider`]s, in registration order
+    #[serde(default)]
+    pub extensions: Vec<ExtensionSection>,
+    /// The "Data" category by format, such as `parquet` or `csv.gz`, once it reaches
+    /// `data_formats_min_size` bytes
+    #[serde(default)]
+    pub data_formats: Option<BTreeMap<String, TypeStats>>,
+    /// Files and bytes per owning user, by login name or the numeric uid where there is
+    /// none; empty where files have no owners
+    #[serde(default)]
+    pub owners: HashMap<String, OwnerStats>,
+    /// Entries with risky permissions, every one of them, with `--security-audit`
+    #[serde(default)]
+    pub security_audit: Option<SecurityAudit>,
+
+    /// Files and bytes by how long ago they were last used
+    #[serde(default)]
+    pub age_breakdown: AgeBreakdown,
+    /// The largest files unused for at least `--stale-threshold`
+    #[serde(default)]
+    pub stale_files: Option<StaleFiles>,
+}
+
+/// Regenerable files and duplicate groups; they still count toward every total
+///
+/// The actionable share of a total is the total minus these figures.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RegenerableTotals {
+    pub files: u64,
+    pub size: u64,
+    pub duplicate_groups: u64,
+    pub wasted_space: u64,
+    /// Whether `--hide-regenerable` left them out of the listings
+    pub hidden: bool,
+}
+
+/// Entries and bytes left out of a listing cut to its largest entries
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Residual {
+    pub count: u64,
+    pub size: u64,
+}
+
+impl Residual {
+    /// The residual made up of entries with the given sizes
+    pub fn of(sizes: impl IntoIterator<Item = u64>) -> Self {
+        sizes.into_iter().fold(Self::default(), |residual, size| Self {
+            count: residual.count + 1,
+            size: residual.size + size,
+        })
+    }
+}
+
+impl std::ops::Add for Residual {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self { count: self.count + other.count, size: self.size + other.size }
+    }
+}
+
+/// What the listings cut to `--top` during analysis left out
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Residuals {
+    pub largest_files: Residual,
+    pub largest_directories: Residual,
+    pub largest_xattr_files: Residual,
+}
+
+/// Details for debugging a run's configuration, kept out of the report
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Diagnostics {
+    pub exclusions: ExclusionStats,
+    #[serde(default)]
+    pub slow_directories: Vec<SlowDirectory>,
+    /// Files claimed by each path classification rule
+    #[serde(default)]
+    pub classification_rules: Vec<RuleHits>,
+    /// Pinned directories that could not be listed, and why
+    #[serde(default)]
+    pub pin_warnings: Vec<String>,
+    /// Use of the hash cache by the duplicate search, with `--hash-cache`
+    #[serde(default)]
+    pub hash_cache: Option<CacheUsage>,
+    /// Directories reached a second time, as through bind mounts, and counted once
+    #[serde(default)]
+    pub aliases: Aliases,
+}
+
+/// A file carrying extended attributes
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct XattrFile {
+    pub path: PathBuf,
+    pub xattr_size: u64,
+}
+
+/// Directories left out of listings by `--min-dir-size`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DirectoryFilter {
+    pub min_size: u64,
+    pub hidden_directories: u64,
+}
+
+/// An internal failure that was contained; the affected results are incomplete
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PhaseError {
+    pub phase: String,
+    pub message: String,
+    pub path: Option<PathBuf>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ScanInfo {
+    /// The root every path of the results is under; the same as `path_resolved`
+    ///
+    /// With further roots it is the first of [`roots`](Self::roots).
     pub path: PathBuf,
+    /// The root as given, which may be a symlink to the tree
+    #[serde(default)]
+    pub path_given: PathBuf,
+    /// The directory walked: the canonical target of a symlinked root, the given path otherwise
+    #[serde(default)]
+    pub path_resolved: PathBuf,
+    /// Every root walked, PATH first; entries name theirs by index
+    #[serde(default)]
+    pub roots: Vec<PathBuf>,
     pub timestamp: DateTime<Utc>,
-    pub depth_limit: usize,
+    /// The `--depth` limit, `None` when unlimited
+    pub depth_limit: Option<usize>,
+    /// The `--min-depth`: files and directories above it are left out
+    #[serde(default)]
+    pub min_depth: usize,
     pub total_files: u64,
     pub total_directories: u64,
     pub total_size: u64,
     pub scan_duration_ms: u64,
+    pub coverage: Coverage,
+    pub ownership: Option<Ownership>,
+    /// The `--modified-before`/`--modified-after` bounds the scan applied, resolved to instants
+    #[serde(default)]
+    pub date_filter: Option<DateFilter>,
+    /// Entries the scan could not read or process
+    #[serde(default)]
+    pub error_count: u64,
+    /// Total extended attribute size, when measured with `--include-xattrs`
+    #[serde(default)]
+    pub xattr_total: Option<u64>,
+    /// Size with shared (reflinked) extents counted once, where they could be probed
+    #[serde(default)]
+    pub physical_size: Option<u64>,
+    /// Apparent and allocated sizes with `--disk-usage`, when `total_size` counts allocated bytes
+    #[serde(default)]
+    pub disk_usage: Option<DiskUsage>,
+    /// Directories whose contents the depth limit kept out of the totals
+    #[serde(default)]
+    pub depth_boundary: DepthBoundary,
+    /// Set when `--max-files`, `--max-bytes`, or `--max-duration` stopped the scan before it covered the tree
+    #[serde(default)]
+    pub partial: Option<PartialScan>,
+    /// Seed of the run's random choices; pass it to `--seed` to replay them
+    #[serde(default)]
+    pub seed: Option<u64>,
+    /// Skew detected from modification times in the future, measured before any correction
+    #[serde(default)]
+    pub clock_skew: Option<ClockSkew>,
+    /// The `--assume-clock-skew` that ages were corrected by, in seconds
+    #[serde(default)]
+    pub assumed_clock_skew_secs: Option<u64>,
+    /// Whether file ages count access times or modification times only
+    #[serde(default)]
+    pub age_source: AgeSource,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -55,22 +364,216 @@ pub struct FileInfo {
     pub size: u64,
     pub file_type: String,
     pub modified: Option<DateTime<Utc>>,
+    /// Birth time, where the filesystem records one
+    #[serde(default)]
+    pub created: Option<DateTime<Utc>>,
+    /// A build output or cache entry that regenerates on its own
+    #[serde(default)]
+    pub regenerable: bool,
+    /// First lines, image dimensions, or archive entry count, with `--previews`
+    #[serde(default)]
+    pub preview: Option<String>,
+    /// Accepted with `accept`, so the report counts it instead of listing it
+    #[serde(default)]
+    pub accepted: bool,
+    /// Where the file points, when it is a symbolic link
+    #[serde(default)]
+    pub symlink_target: Option<PathBuf>,
+    /// The file's length when `size` counts its allocated bytes (`--disk-usage`)
+    #[serde(default)]
+    pub apparent_size: Option<u64>,
+    /// Less than half of its length is allocated, measured with `--disk-usage`
+    #[serde(default)]
+    pub sparse: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DirectoryInfo {
     pub path: PathBuf,
     pub size: u64,
+    /// Files directly in the directory
     pub file_count: u64,
+    /// Files anywhere below the directory, its own included; 0 in exports of earlier versions
+    #[serde(default)]
+    pub recursive_file_count: u64,
     pub subdirectory_count: u64,
+    /// Directories anywhere below the directory; 0 in exports of earlier versions
+    #[serde(default)]
+    pub recursive_subdirectory_count: u64,
+    /// Why a well-known directory is large and how to clean it up
+    #[serde(default)]
+    pub annotation: Option<String>,
+    /// Who to ask before archiving it, where files have owners
+    #[serde(default)]
+    pub owner_mix: Option<OwnerMix>,
+    /// Listed because of `--pin`, whatever its rank or size
+    #[serde(default)]
+    pub pinned: bool,
+    /// Bytes below it per file type category
+    #[serde(default)]
+    pub composition: BTreeMap<String, u64>,
+    /// Accepted with `accept`, so the report counts it instead of listing it
+    #[serde(default)]
+    pub accepted: bool,
+    /// Index of the root it is under in [`ScanInfo::roots`]
+    #[serde(default)]
+    pub root: usize,
+}
+
+/// The paths listed in `list`, or on stdin for `-`
+fn read_listed_roots(list: &Path, nul_separated: bool) -> Result<Vec<PathBuf>> {
+    let listed = if list == Path::new("-") {
+        utils::read_path_list(std::io::stdin().lock(), nul_separated)
+    } else {
+        std::fs::File::open(list).and_then(|file| utils::read_path_list(file, nul_separated))
+    };
+    listed.with_context(|| format!("Cannot read the path list {}", list.display()))
+}
+
+/// Why `root` cannot be walked, as a scan error; `None` for a readable directory
+fn root_error(root: &Path) -> Option<ScanError> {
+    let error = utils::validate_directory(root).err()?;
+    let io_error = std::fs::metadata(root).err();
+    let error_type = match io_error.as_ref().map(std::io::Error::kind) {
+        Some(std::io::ErrorKind::NotFound) => ErrorType::FileNotFound,
+        Some(std::io::ErrorKind::PermissionDenied) => ErrorType::PermissionDenied,
+        Some(_) => ErrorType::IoError,
+        None => ErrorType::Other,
+    };
+    // The first line only: a listing of suggestions fits a report of errors poorly
+    let error = error.to_string().lines().next().unwrap_or_default().to_string();
+    Some(ScanError { path: root.to_path_buf(), error, error_type, errno: io_error.and_then(|error| error.raw_os_error()) })
+}
+
+/// A filter added once and applied to the walk of every root
+struct SharedFilter(Arc<dyn FileFilter>);
+
+impl FileFilter for SharedFilter {
+    fn include(&self, entry: &FileEntry) -> bool {
+        self.0.include(entry)
+    }
+
+    fn prune(&self, directory: &Path) -> bool {
+        self.0.prune(directory)
+    }
+}
+
+/// Directories `--pin` keeps in the listings, and the pins that cannot be honoured
+#[derive(Debug, Default)]
+struct Pins {
+    entries: Vec<DirectoryEntry>,
+    warnings: Vec<String>,
+}
+
+impl Pins {
+    fn contains(&self, path: &Path) -> bool {
+        self.entries.iter().any(|entry| entry.path == path)
+    }
+}
+
+/// What one user owns of the collected files
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct OwnerStats {
+    pub uid: u32,
+    pub count: u64,
+    pub total_size: u64,
+}
+
+impl OwnerStats {
+    /// The collected files by owner, keyed by the name `user_name` gives a uid or the uid itself
+    pub fn by_owner(files: &[FileEntry], user_name: impl FnMut(u32) -> Option<String>) -> HashMap<String, OwnerStats> {
+        let mut by_uid: HashMap<u32, OwnerStats> = HashMap::new();
+        for file in files {
+            Self::count(&mut by_uid, file);
+        }
+        Self::named(by_uid, user_name)
+    }
+
+    /// Count `file` for its owner, if the walk recorded one
+    fn count(by_uid: &mut HashMap<u32, OwnerStats>, file: &FileEntry) {
+        if let Some(uid) = file.uid {
+            let stats = by_uid.entry(uid).or_insert_with(|| OwnerStats { uid, ..Default::default() });
+            stats.count += 1;
+            stats.total_size += file.counted_size();
+        }
+    }
+
+    fn named(by_uid: HashMap<u32, OwnerStats>, mut user_name: impl FnMut(u32) -> Option<String>) -> HashMap<String, OwnerStats> {
+        by_uid.into_iter().map(|(uid, stats)| (user_name(uid).unwrap_or_else(|| uid.to_string()), stats)).collect()
+    }
+}
+
+/// The largest owners of the bytes below a directory
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OwnerMix {
+    /// At most [`OwnerMix::LISTED`], largest first
+    pub owners: Vec<DirectoryOwner>,
+    /// The largest owner holds more than [`OwnerMix::CLEAR_SHARE`] of the bytes
+    pub clear_owner: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DirectoryOwner {
+    pub user: String,
+    pub uid: u32,
+    pub size: u64,
+}
+
+impl OwnerMix {
+    /// Owners listed per directory
+    pub const LISTED: usize = 3;
+    /// Share of a directory's bytes above which one owner is its clear owner
+    pub const CLEAR_SHARE: f64 = 0.9;
+
+    /// The mix of a directory of `total` bytes, or `None` if no byte has an owner
+    ///
+    /// `shares` must be largest first; `user_name` resolves uids to login names.
+    pub fn of(shares: &[OwnerShare], total: u64, mut user_name: impl FnMut(u32) -> Option<String>) -> Option<Self> {
+        let largest = shares.first()?;
+        Some(Self {
+            clear_owner: largest.size as f64 > total as f64 * Self::CLEAR_SHARE,
+            owners: shares
+                .iter()
+                .take(Self::LISTED)
+                .map(|share| DirectoryOwner {
+                    user: user_name(share.uid).unwrap_or_else(|| share.uid.to_string()),
+                    uid: share.uid,
+                    size: share.size,
+                })
+                .collect(),
+        })
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DuplicateGroup {
+    /// Identifies the group across runs, derived from the content digest and any partition
+    #[serde(default)]
+    pub group_id: String,
     pub hash: String,
     pub file_size: u64,
     pub files: Vec<PathBuf>,
     pub wasted_space: u64,
+    pub newest_modified: Option<DateTime<Utc>>,
+    pub priority: f64,
+    /// Subtree the group was confined to by `--duplicates-within-depth`
+    #[serde(default)]
+    pub partition: Option<PathBuf>,
+    /// Every copy is regenerable, so removing them reclaims nothing lasting
+    #[serde(default)]
+    pub regenerable: bool,
+    /// First run that recorded the group, when history is enabled
+    #[serde(default)]
+    pub first_seen: Option<DateTime<Utc>>,
+    /// This run, when history is enabled
+    #[serde(default)]
+    pub last_seen: Option<DateTime<Utc>>,
+    /// Accepted with `accept` by its group id, so the report counts it instead of listing it
+    #[serde(default)]
+    pub accepted: bool,
+    /// Grouped by size and etag from an object-storage listing; the contents were never compared
+    #[serde(default)]
+    pub unverified: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -78,73 +581,585 @@ pub struct Statistics {
     pub files_per_second: f64,
     pub bytes_per_second: u64,
     pub memory_usage_mb: f64,
-    pub duplicate_files: u64,
-    pub wasted_space: u64,
+    /// Confirmed duplicate groups
+    #[serde(default)]
+    pub duplicate_groups: u64,
+    /// Copies beyond the first in each group, the files fdupes and jdupes count as duplicates
+    ///
+    /// Read from `duplicate_files` in exports of earlier versions, which
+    /// counted every member instead; that alias goes in the next release.
+    #[serde(alias = "duplicate_files")]
+    pub redundant_files: u64,
+    /// Bytes that removing the redundant copies would free
+    ///
+    /// Hard links to one inode and fully shared (reflinked) copies take no
+    /// space of their own and free nothing. Read from `wasted_space` in
+    /// exports of earlier versions; that alias goes in the next release.
+    #[serde(alias = "wasted_space")]
+    pub reclaimable_bytes: u64,
     pub compression_ratio: f64,
+    /// CPU, context switch, and IO usage per analysis phase
+    #[serde(default)]
+    pub resource_usage: Vec<PhaseUsage>,
+    /// Latencies of filesystem operations, with `--io-latency-stats`; absent otherwise
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub io_latency: Option<IoLatencyReport>,
 }
 
 impl DirectoryAnalyzer {
     /// Create a new analyzer with the given configuration
-    pub fn new(args: Args) -> Result<Self> {
-        let scanner = DirectoryScanner::new(&args)?;
+    ///
+    /// Fails before touching the scanned tree when `--read-only` is combined
+    /// with options that would write inside it.
+    pub fn new(mut args: Args) -> Result<Self> {
+        // Settled once, so that every feature and the export see the same seed
+        args.seed.get_or_insert_with(random_seed);
+        // Resolved once, so that outputs, exclusions, and history all see the walked tree's paths
+        let mut path_given = args.path.clone();
+        if (!args.more_paths.is_empty() || args.path_list().is_some()) && args.source != SourceKind::Local {
+            bail!("Several roots can only be walked on the local filesystem, not read from an inventory with --source");
+        }
+        let mut nested_roots = Vec::new();
+        let mut unreadable_roots = Vec::new();
+        if args.source == SourceKind::Local {
+            let mut given = args.roots();
+            if let Some((list, nul_separated)) = args.path_list() {
+                // A listed path that went away is one error of the run, not a reason to stop it
+                for root in read_listed_roots(list, nul_separated)? {
+                    match root_error(&root) {
+                        Some(error) => unreadable_roots.push(error),
+                        None => given.push(root),
+                    }
+                }
+                if given.is_empty() {
+                    bail!("None of the paths listed in {} is a readable directory", list.display());
+                }
+                path_given = given[0].clone();
+            }
+            let resolved: Vec<PathBuf> = given.iter().map(|root| scanner::resolve_root(root)).collect();
+            let (roots, nested) = scanner::distinct_roots(&resolved);
+            nested_roots = nested;
+            args.path = roots[0].clone();
+            args.more_paths = roots[1..].to_vec();
+        }
+        if args.depth_limit().is_some_and(|limit| args.min_depth > limit) {
+            bail!("--min-depth ({}) is deeper than --depth ({}), which leaves nothing to collect", args.min_depth, args.max_depth);
+        }
+        let size_range = SizeRange::from_args(&args)?;
+        if args.low_memory {
+            // Each of these needs the collected files after the walk, which a low-memory run does not keep
+            if args.export_scope == ExportScope::Full {
+                bail!("--export-scope full keeps every collected file, which --low-memory does not");
+            }
+            if !args.report_filters.is_empty() {
+                bail!("The report filters (--older-than, --newer-than, --only, --larger-than, --smaller-than) need the collected files after the walk, which --low-memory does not keep");
+            }
+            if args.filter_affects_dirs {
+                bail!("--filter-affects-dirs needs the collected files after the walk, which --low-memory does not keep");
+            }
+        }
+        let writes = WriteGuard::for_args(&args)?;
+        let config = Config::load(args.config.as_deref())?;
+        let own_outputs = WriteTargets::for_args(&args).within(&args.path);
+        let latency = IoLatency::new(args.io_latency_stats);
+        let deadline = args.max_duration.map(|duration| Instant::now() + duration);
+        let walker = |args: &Args| -> Result<Box<dyn FileSource>> {
+            Ok(Box::new(DirectoryScanner::new(args)?.with_slow_thresholds(SlowThresholds {
+                entries: config.slow_directory_entries,
+                duration: Duration::try_from_secs_f64(config.slow_directory_secs).unwrap_or(Duration::MAX),
+            }).with_owner_limit(config.owners_per_directory).with_own_outputs(own_outputs.clone()).with_io_latency(latency.clone())))
+        };
+        let source: Box<dyn FileSource> = match args.source {
+            SourceKind::Local => walker(&args)?,
+            SourceKind::S3Inventory => Box::new(InventorySource::open(&args)?.with_owner_limit(config.owners_per_directory)),
+        };
+        let more_sources = args.more_paths.iter().map(|root| walker(&Args { path: root.clone(), ..args.clone() })).collect::<Result<Vec<_>>>()?;
+        // From here on the root is where the source lists its paths, such as s3://bucket for an inventory
+        args.path = source.root().to_path_buf();
+        let hash_cache = (args.hash_cache || args.hash_cache_dir.is_some())
+            .then(|| args.hash_cache_dir.clone().or_else(HashCache::default_dir))
+            .flatten()
+            .map(|dir| Arc::new(HashCache::open(dir, writes.clone())));
         let duplicate_finder = if args.find_duplicates {
-            Some(DuplicateFinder::new(args.min_duplicate_size, args.threads))
+            let finder = DuplicateFinder::new(args.min_duplicate_size, args.threads)
+                .strict(args.strict)
+                .within_depth(args.duplicates_within_depth)
+                .with_deadline(deadline)
+                .with_io_latency(latency.clone());
+            let finder = match results_journal(&args, &writes) {
+                Some(journal) => finder.with_journal(journal),
+                None => finder,
+            };
+            Some(match &hash_cache {
+                Some(cache) => finder.with_cache(Arc::clone(cache)),
+                None => finder,
+            })
         } else {
             None
         };
-        let classifier = FileTypeClassifier::new();
+        let classifier = FileTypeClassifier::new()
+            .with_regenerable(&config.regenerable)
+            .with_rules(&config.classification_rules)?;
+        let metrics = MetricSet::compile(&config.custom_metrics, &classifier)?;
+        let age_boundaries = ages::boundaries(&config.age_buckets)?;
+        let annotations = KnowledgeBase::for_current_user(&config.path_annotations);
 
         Ok(Self {
             args,
-            scanner,
+            path_given,
+            config,
+            source,
+            more_sources,
+            nested_roots,
+            unreadable_roots,
             duplicate_finder,
+            hash_cache,
+            section_providers: Vec::new(),
             classifier,
+            metrics,
+            annotations,
+            clock: Box::new(SystemClock),
+            writes,
+            own_outputs,
+            latency,
+            stream: None,
+            size_range,
+            age_boundaries,
+            cancellation: None,
+            deadline,
         })
     }
 
+    /// Replace the clock that stamps results and ages duplicate groups
+    pub fn with_clock(mut self, clock: impl Clock + 'static) -> Self {
+        self.clock = Box::new(clock);
+        self
+    }
+
+    /// Add a filter to the scan, run after the built-in ones (see [`FileFilter`])
+    pub fn with_filter(mut self, filter: impl FileFilter + 'static) -> Self {
+        if self.more_sources.is_empty() {
+            self.source.add_filter(Box::new(filter));
+            return self;
+        }
+        let filter: Arc<dyn FileFilter> = Arc::new(filter);
+        for source in std::iter::once(&mut self.source).chain(&mut self.more_sources) {
+            source.add_filter(Box::new(SharedFilter(Arc::clone(&filter))));
+        }
+        self
+    }
+
+    /// List the files to analyze from `source` instead of walking PATH
+    ///
+    /// Results are rooted where the source lists its paths, and further roots
+    /// given after PATH are dropped. Filters added before stay with the
+    /// source that is replaced.
+    pub fn with_source(mut self, mut source: impl FileSource + 'static) -> Self {
+        if let Some(cancellation) = &self.cancellation {
+            source.stop_on(cancellation.clone());
+        }
+        self.args.path = source.root().to_path_buf();
+        self.path_given = self.args.path.clone();
+        self.args.more_paths.clear();
+        self.more_sources.clear();
+        self.source = Box::new(source);
+        self
+    }
+
+    /// Add a custom report section, built once the rest of the results are final
+    pub fn with_section_provider(mut self, provider: impl SectionProvider + 'static) -> Self {
+        self.section_providers.push(Box::new(provider));
+        self
+    }
+
+    /// Settings loaded from the configuration file
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// The guard every file this run writes must pass
+    pub fn writes(&self) -> &WriteGuard {
+        &self.writes
+    }
+
+    /// Follow `path` from the root through the filters this run applies, naming the rule that keeps it out
+    ///
+    /// The run's own outputs and any added filters take part, as they do in [`analyze`](Self::analyze).
+    pub fn explain_path(&self, path: &Path) -> Result<ExclusionTrace> {
+        self.source_under(path).explain_path(path)
+    }
+
+    /// Every root walked, PATH first
+    fn roots(&self) -> Vec<PathBuf> {
+        std::iter::once(self.source.root()).chain(self.more_sources.iter().map(|source| source.root())).map(Path::to_path_buf).collect()
+    }
+
+    /// The source of the root `path` lies under, PATH's when it lies under none of the others
+    fn source_under(&self, path: &Path) -> &dyn FileSource {
+        let absolute = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+        self.more_sources
+            .iter()
+            .find(|source| absolute.starts_with(std::path::absolute(source.root()).unwrap_or_else(|_| source.root().to_path_buf())))
+            .unwrap_or(&self.source)
+            .as_ref()
+    }
+
+    /// Send interim scan totals and hashing progress to `sender` as the run goes on
+    ///
+    /// Interim totals go out every `--interim-every`, or every
+    /// [`PROGRESS_EVERY`](crate::export::stream::PROGRESS_EVERY) without it,
+    /// instead of being printed.
+    pub fn with_stream(mut self, sender: RecordSender) -> Self {
+        let every = self.args.interim_every.unwrap_or(stream::PROGRESS_EVERY);
+        let scan_sender = sender.clone();
+        self.source.report_interim(Some(every), Box::new(move |snapshot: &InterimSnapshot| {
+            // A closed stream has nobody left to tell
+            let _ = scan_sender.send(StreamRecord::ScanProgress(snapshot.into()));
+        }));
+        self.duplicate_finder = self.duplicate_finder.map(|finder| finder.with_progress(sender.clone()));
+        self.stream = Some(sender);
+        self
+    }
+
+    /// Stop the scan and the duplicate search once `cancellation` is set
+    ///
+    /// What was found until then is analyzed, reported, and exported, marked as
+    /// partial (see [`CapKind::Interrupted`]); entropy sampling and previews,
+    /// which read files, are skipped.
+    pub fn with_cancellation(mut self, cancellation: Cancellation) -> Self {
+        for source in std::iter::once(&mut self.source).chain(&mut self.more_sources) {
+            source.stop_on(cancellation.clone());
+        }
+        self.duplicate_finder = self.duplicate_finder.map(|finder| finder.with_cancellation(cancellation.clone()));
+        self.cancellation = Some(cancellation);
+        self
+    }
+
+    /// Replace the duplicate finder, enabling duplicate detection
+    ///
+    /// The finder shares the hash cache, the results journal, the latency
+    /// statistics, the progress stream, the cancellation, and the `--max-duration`
+    /// deadline when the run has them.
+    pub fn with_duplicate_finder(mut self, finder: DuplicateFinder) -> Self {
+        let mut finder = finder.with_io_latency(self.latency.clone());
+        if let Some(sender) = &self.stream {
+            finder = finder.with_progress(sender.clone());
+        }
+        if let Some(journal) = results_journal(&self.args, &self.writes) {
+            finder = finder.with_journal(journal);
+        }
+        if let Some(cancellation) = &self.cancellation {
+            finder = finder.with_cancellation(cancellation.clone());
+        }
+        if self.deadline.is_some() {
+            finder = finder.with_deadline(self.deadline);
+        }
+        self.duplicate_finder = Some(match &self.hash_cache {
+            Some(cache) => finder.with_cache(Arc::clone(cache)),
+            None => finder,
+        });
+        self
+    }
+
     /// Perform comprehensive directory analysis
     pub async fn analyze(&mut self) -> Result<AnalysisResults> {
         let start_time = std::time::Instant::now();
+        let strict = self.args.strict;
+        let low_memory = self.args.low_memory;
+        let mut phase_errors = Vec::new();
+        let mut resources = PhaseRecorder::start();
+
+        // Phase 0: Probe how much of the first level we can actually read
+        let mut coverage = self.source.probe_coverage()?;
+        for source in &self.more_sources {
+            let more = source.probe_coverage()?;
+            coverage.readable_entries += more.readable_entries;
+            coverage.unreadable_entries += more.unreadable_entries;
+        }
+        let unreadable_fraction = 1.0 - coverage.readable_fraction();
+        if unreadable_fraction > self.config.coverage_warning_threshold && !self.args.quiet {
+            println!("{}", format!(
+                "⚠ {} of {} first-level entries are unreadable ({:.0}%); totals will be incomplete. \
+                 Consider running with sudo or narrowing the path.",
+                coverage.unreadable_entries,
+                coverage.readable_entries + coverage.unreadable_entries,
+                unreadable_fraction * 100.0
+            ).yellow().bold());
+        }
+        let root_matches = self.source.patterns_matching_root();
+        if !root_matches.is_empty() && !self.args.quiet {
+            println!("{}", format!(
+                "⚠ Exclude pattern(s) {} match the scan root {}; the report will likely be empty.",
+                root_matches.iter().map(|pattern| format!("`{}`", pattern)).collect::<Vec<_>>().join(", "),
+                self.args.path.display()
+            ).yellow().bold());
+        }
+        if !self.args.quiet {
+            for root in &self.nested_roots {
+                println!("{}", format!("⚠ {} lies within another root and is scanned with it, once.", root.display()).yellow().bold());
+            }
+        }
+        if low_memory && !self.args.quiet {
+            let skipped: Vec<&str> = [
+                (self.args.find_duplicates, "duplicate detection"),
+                (self.args.entropy_sample, "entropy sampling"),
+                (self.args.find_versions, "version grouping"),
+            ]
+            .into_iter()
+            .filter_map(|(requested, feature)| requested.then_some(feature))
+            .collect();
+            if !skipped.is_empty() {
+                println!("{}", format!("⚠ --low-memory keeps no list of the files to compare; skipping {}.", skipped.join(", ")).yellow().bold());
+            }
+        }
+        self.check_space()?;
+        resources.mark("probe");
         
         // Phase 1: Scan directory structure
         if !self.args.quiet {
             println!("🔍 Scanning directory structure...");
         }
+        if self.args.verbose && !self.own_outputs.is_empty() {
+            println!("Leaving out diranalyzer's own files:");
+            for target in self.own_outputs.iter() {
+                println!("  {}", target);
+            }
+        }
         
-        let scan_results = self.scanner.scan().await?;
+        // With --low-memory the walk hands each file to the fold instead of keeping it
+        let roots = self.roots();
+        let mut fold = low_memory.then(|| FileFold::new(FoldContext {
+            args: &self.args,
+            classifier: &self.classifier,
+            metrics: &self.metrics,
+            age_boundaries: &self.age_boundaries,
+            size_range: self.size_range,
+            data_formats_min_size: self.config.data_formats_min_size,
+        }, &roots, self.clock.now()));
+        let source = self.source.as_mut();
+        let mut scan_results = guard_phase("scan", strict, &mut phase_errors, async { list_files(source, None, 0, fold.as_mut()) })
+            .await?
+            .unwrap_or_default();
+        resources.mark("scan");
+
+        // Phase 1b: Walk on below the depth limit, only where it cut something off
+        let boundary = scan_results.depth_boundary.directories.clone();
+        if self.args.auto_depth && !boundary.is_empty() {
+            if !self.args.quiet {
+                println!("🔽 Descending into {} directories at the depth limit...", format_count(boundary.len() as u64));
+            }
+            if let Some(below) = guard_phase("auto-depth", strict, &mut phase_errors, async { list_files(source, Some(&boundary), 0, fold.as_mut()) }).await? {
+                scan_results.absorb(below, self.config.owners_per_directory);
+            }
+            resources.mark("auto-depth");
+        }
+
+        // Phase 1c: Walk the further roots into the same results, each to its own depth limit
+        for (index, source) in self.more_sources.iter_mut().enumerate() {
+            if !self.args.quiet {
+                println!("🔍 Scanning {}...", source.root().display());
+            }
+            let Some(mut more) = guard_phase("scan", strict, &mut phase_errors, async { list_files(source.as_mut(), None, index + 1, fold.as_mut()) }).await? else {
+                continue;
+            };
+            let boundary = more.depth_boundary.directories.clone();
+            if self.args.auto_depth && !boundary.is_empty() {
+                if let Some(below) = guard_phase("auto-depth", strict, &mut phase_errors, async { list_files(source.as_mut(), Some(&boundary), index + 1, fold.as_mut()) }).await? {
+                    more.absorb(below, self.config.owners_per_directory);
+                }
+            }
+            scan_results.add_root(more, index + 1, self.config.owners_per_directory);
+        }
+        if !self.more_sources.is_empty() {
+            resources.mark("more roots");
+        }
+        let folded = fold.map(FileFold::finish);
+        for error in &self.unreadable_roots {
+            if !self.args.quiet {
+                println!("{}", format!("⚠ Skipping listed path {}: {}", error.path.display(), error.error).yellow().bold());
+            }
+            if scan_results.errors.len() < scan_results.error_log.listing_limit() {
+                scan_results.errors.push(error.clone());
+            }
+            scan_results.error_log.push(error.clone());
+        }
+        if let Some(partial) = scan_results.partial {
+            self.report_partial(partial);
+        }
+        let clock_skew = match &folded {
+            Some(folded) => folded.clock_skew,
+            None => time::detect_skew(scan_results.files.iter().filter_map(|file| file.modified), self.clock.now()),
+        };
+        if let Some(skew) = clock_skew {
+            self.report_clock_skew(skew);
+        }
+        // Ages are measured from here, so that a filesystem clock running ahead does not make files look new
+        let ages_from = time::age_reference(self.clock.now(), self.args.assume_clock_skew.unwrap_or_default());
+        self.report_exclusions(&scan_results.exclusions);
+        self.report_undated_files(scan_results.date_filter.as_ref());
+        self.report_slow_directories(&scan_results.slow_directories);
+
+        // A full snapshot keeps what was collected, so that it can be refiltered from scratch
+        let entries = (self.args.export_scope == ExportScope::Full).then(|| scan_results.entries());
+        let report_filters = self.apply_report_filters(&mut scan_results, ages_from)?;
+        let (disk_usage, owners, security_audit, age_source, age_breakdown, stale_files, file_size_range, folded_listings) = match folded {
+            Some(folded) => (
+                folded.disk_usage,
+                folded.owners,
+                folded.security_audit.map(|audit| audit.with_directories(&scan_results.directories)),
+                folded.age_source,
+                folded.age_breakdown,
+                folded.stale_files,
+                folded.file_size_range,
+                Some(folded.listings),
+            ),
+            None => {
+                // Taken before the size range, which may leave the totals counting files it drops
+                let disk_usage = self.args.disk_usage.then(|| DiskUsage::of(&scan_results.files));
+                let owners = OwnerStats::by_owner(&scan_results.files, scanner::user_name);
+                // Before retention drops the directories the report does not list
+                let security_audit = self.args.security_audit.then(|| SecurityAudit::of(&scan_results.files, &scan_results.directories, scanner::user_name));
+                let age_source = AgeSource::for_run(&scan_results.files, &roots);
+                let (age_breakdown, stale_files) = self.file_ages(&scan_results.files, age_source, ages_from);
+                let file_size_range = self.apply_size_range(&mut scan_results);
+                (disk_usage, owners, security_audit, age_source, age_breakdown, stale_files, file_size_range, None)
+            }
+        };
+        let pins = self.resolve_pins(&scan_results);
+        self.retain_directories(&mut scan_results, &pins);
         
         // Phase 2: Analyze file types and sizes
         if !self.args.quiet {
             println!("📊 Analyzing file types and sizes...");
         }
         
-        let (size_breakdown, file_type_distribution, largest_files, largest_directories) = 
-            self.analyze_files_and_directories(&scan_results).await?;
+        let AnalysisPass { listings, largest_directories, residuals, heuristic_versions } =
+            guard_phase("analysis", strict, &mut phase_errors, self.analyze_files_and_directories(&scan_results, &pins, ages_from, folded_listings))
+                .await?
+                .unwrap_or_default();
+        let Listings { size_breakdown, file_type_distribution, data_formats, mut largest_files, mut regenerable, metric_tally, xattr_total, largest_xattr_files, empty_files, .. } = listings;
+        resources.mark("analysis");
+        self.report_rule_hits();
 
-        // Phase 3: Find duplicates if requested
-        let duplicate_groups = if let Some(ref mut finder) = self.duplicate_finder {
+        // Phase 3: Find duplicates if requested, or take them from another tool's findings
+        let mut hash_cache = None;
+        let mut out_of_time = false;
+        let mut duplicate_groups = if let Some(path) = &self.args.import_duplicates {
+            if !self.args.quiet {
+                println!("📥 Importing duplicate groups from {}...", path.display());
+            }
+            Some(interop::import_duplicates(path)?)
+        } else if let Some(finder) = self.duplicate_finder.as_mut().filter(|_| !low_memory) {
             if !self.args.quiet {
                 println!("🔎 Detecting duplicate files...");
+                if scan_results.partial.is_some() {
+                    println!("{}", format!(
+                        "⚠ Duplicates are only searched among the {} files collected; copies in the rest of the tree are not found.",
+                        format_count(scan_results.total_files)
+                    ).yellow().bold());
+                }
+            }
+            let groups = guard_phase("duplicates", strict, &mut phase_errors, finder.find_duplicates(&scan_results.files))
+                .await?;
+            out_of_time = self.deadline.is_some_and(|deadline| Instant::now() >= deadline);
+            phase_errors.extend(finder.take_failures().into_iter().map(|failure| PhaseError::from_scan_error("duplicates", failure)));
+            hash_cache = self.finish_hash_cache();
+            groups
+        } else {
+            None
+        };
+
+        if let Some(groups) = &mut duplicate_groups {
+            for group in groups.iter_mut() {
+                group.priority = duplicates::priority_score(group, &self.config.duplicate_priority, ages_from);
+                group.regenerable = group.files.iter().all(|path| self.classifier.is_regenerable(path));
+                if group.regenerable {
+                    regenerable.duplicate_groups += 1;
+                    regenerable.wasted_space += group.wasted_space;
+                }
+            }
+            duplicates::sort_groups(groups, &self.args.duplicates_sort);
+            resources.mark("duplicates");
+        }
+
+        // Interrupted after the walk, the run still covers only part of what it set out to
+        let cancelled = self.cancellation.as_ref().is_some_and(Cancellation::is_cancelled);
+        if cancelled && scan_results.partial.is_none() {
+            let partial = PartialScan { cap: CapKind::Interrupted, limit: scan_results.total_files };
+            self.report_partial(partial);
+            scan_results.partial = Some(partial);
+        }
+        // So does a duplicate search that ran out of time
+        if out_of_time && scan_results.partial.is_none() {
+            let limit = self.args.max_duration.unwrap_or_default().as_secs();
+            let partial = PartialScan { cap: CapKind::Duration, limit };
+            self.report_partial(partial);
+            scan_results.partial = Some(partial);
+        }
+        // Past the deadline or cancelled, nothing more is read
+        let stopped_early = cancelled || self.deadline.is_some_and(|deadline| Instant::now() >= deadline);
+
+        // Sampling and previews read contents, which a listing of object storage cannot reach
+        let reads_contents = self.source.reads_contents();
+        if !reads_contents && (self.args.entropy_sample || self.args.previews) && !self.args.quiet {
+            println!("{}", "⚠ File contents cannot be read from this source; entropy sampling and previews are skipped.".yellow().bold());
+        }
+
+        // Phase 4: Sample recently modified files for random-looking content
+        let entropy = if self.args.entropy_sample && reads_contents && !low_memory && !stopped_early {
+            if !self.args.quiet {
+                println!("🧪 Sampling recently modified files...");
             }
-            Some(finder.find_duplicates(&scan_results.files).await?)
+            let options = SampleOptions { window_days: self.args.entropy_window, budget: self.args.entropy_budget };
+            let report = entropy::sample_recent(&scan_results.files, &options, ages_from);
+            resources.mark("entropy");
+            Some(report)
         } else {
             None
         };
 
+        // Phase 5: Preview the listed largest files, a bounded read each
+        if self.args.previews && reads_contents && !stopped_early {
+            if !self.args.quiet {
+                println!("👁  Previewing the largest files...");
+            }
+            preview::attach(&mut largest_files);
+            resources.mark("previews");
+        }
+
         let scan_duration = start_time.elapsed();
         
         // Calculate statistics
-        let statistics = self.calculate_statistics(&scan_results, &duplicate_groups, scan_duration);
+        let mut statistics = self.calculate_statistics(&scan_results, &duplicate_groups, scan_duration);
+        statistics.resource_usage = resources.finish();
+        let custom_metrics = self.evaluate_metrics(&metric_tally, &scan_results, &statistics, &regenerable);
 
-        let results = AnalysisResults {
+        let mut results = AnalysisResults {
             scan_info: ScanInfo {
                 path: self.args.path.clone(),
-                timestamp: Utc::now(),
-                depth_limit: self.args.max_depth,
+                path_given: self.path_given.clone(),
+                path_resolved: self.args.path.clone(),
+                roots: self.roots(),
+                timestamp: self.clock.now(),
+                depth_limit: self.args.depth_limit(),
+                min_depth: self.args.min_depth,
                 total_files: scan_results.total_files,
                 total_directories: scan_results.total_directories,
                 total_size: scan_results.total_size,
                 scan_duration_ms: scan_duration.as_millis() as u64,
+                coverage,
+                ownership: scan_results.ownership.clone(),
+                date_filter: scan_results.date_filter.clone(),
+                error_count: scan_results.error_log.total(),
+                xattr_total: self.args.include_xattrs.then_some(xattr_total),
+                physical_size: scan_results.physical_size,
+                disk_usage,
+                depth_boundary: scan_results.depth_boundary.clone(),
+                partial: scan_results.partial,
+                seed: self.args.seed,
+                clock_skew,
+                assumed_clock_skew_secs: self.args.assume_clock_skew.map(|skew| skew.as_secs()),
+                age_source,
             },
             size_breakdown,
             file_type_distribution,
@@ -152,110 +1167,2623 @@ impl DirectoryAnalyzer {
             largest_directories,
             duplicate_groups,
             statistics,
+            trend: None,
+            phase_errors,
+            scan_errors: scan_results.error_log.summarize(&scan_results.errors),
+            directory_filter: self.directory_filter(&scan_results, &pins),
+            largest_xattr_files,
+            diagnostics: Diagnostics {
+                exclusions: scan_results.exclusions.clone(),
+                slow_directories: scan_results.slow_directories.clone(),
+                classification_rules: self.classifier.rule_hits(),
+                pin_warnings: pins.warnings,
+                hash_cache,
+                aliases: scan_results.aliases.clone(),
+            },
+            residuals,
+            regenerable,
+            entropy,
+            resolved_groups: None,
+            case_collisions: self.args.detect_case_collisions.then(|| scan_results.case_collisions.clone()),
+            heuristic_versions,
+            owners,
+            security_audit,
+            age_breakdown,
+            stale_files,
+            symlink_report: self.args.find_broken_links.then(|| scan_results.symlink_report.clone()),
+            skipped_mounts: self.args.one_file_system.then(|| scan_results.skipped_mounts.clone()),
+            empty_directories: scan_results.empty_directories.clone(),
+            empty_files,
+            annotations: Vec::new(),
+            accepted: AcceptedFindings::default(),
+            report_filters,
+            file_size_range,
+            entries,
+            custom_metrics,
+            extensions: Vec::new(),
+            data_formats,
         };
+        self.attach_notes(&mut results);
+        self.attach_acceptances(&mut results);
+
+        // A partial or filtered scan would show up in the trend as a sudden shrink
+        let filtered = results.report_filters.is_some() || results.file_size_range.is_some() || !self.more_sources.is_empty();
+        if !self.args.no_history && results.scan_info.partial.is_none() && !filtered {
+            results.trend = self.record_history(&mut results);
+        }
+
+        // Phase 6: Custom sections, which see everything above
+        let mut extension_errors = Vec::new();
+        for provider in &self.section_providers {
+            let section = guard_phase("extensions", strict, &mut extension_errors, async { Ok(provider.section(&results)) }).await?;
+            results.extensions.extend(section.flatten());
+        }
+        results.phase_errors.extend(extension_errors);
 
         Ok(results)
     }
 
-    async fn analyze_files_and_directories(&self, scan_results: &ScanResults) -> Result<(
-        SizeBreakdown,
-        HashMap<String, TypeStats>,
-        Vec<FileInfo>,
-        Vec<DirectoryInfo>
-    )> {
-        let mut file_type_distribution: HashMap<String, TypeStats> = HashMap::new();
-        let mut largest_files = Vec::new();
-        let mut largest_directories = Vec::new();
+    /// Results of a full snapshot recomputed over the files that pass the report-time filters
+    ///
+    /// Totals, listings, and file types come from the snapshot's entries as a
+    /// run of this analyzer would compute them; duplicate groups keep the
+    /// copies that pass and go once fewer than two are left. Findings of the
+    /// walk itself, such as errors, exclusions, and broken symlinks, stay as
+    /// the snapshot recorded them. The tree is not touched.
+    pub async fn refilter(&self, snapshot: &AnalysisResults) -> Result<AnalysisResults> {
+        let Some(entries) = &snapshot.entries else {
+            bail!("The snapshot holds no entries to refilter; export the run with --export-scope full");
+        };
+        let skew = Duration::from_secs(snapshot.scan_info.assumed_clock_skew_secs.unwrap_or_default());
+        let ages_from = time::age_reference(snapshot.scan_info.timestamp, skew);
+        let mut scan_results = ScanResults::from_entries(entries, self.config.owners_per_directory);
+        let report_filters = self.apply_report_filters(&mut scan_results, ages_from)?;
 
-        // Analyze files
-        for file_entry in &scan_results.files {
-            let file_type = self.classifier.classify(&file_entry.path);
-            let file_info = FileInfo {
-                path: file_entry.path.clone(),
-                size: file_entry.size,
-                file_type: file_type.clone(),
-                modified: file_entry.modified,
-            };
+        let (age_breakdown, stale_files) = self.file_ages(&scan_results.files, snapshot.scan_info.age_source, ages_from);
 
-            // Update file type statistics
-            let type_stats = file_type_distribution.entry(file_type).or_insert(TypeStats {
-                count: 0,
-                total_size: 0,
-                average_size: 0,
-                largest_file: None,
-            });
+        let pins = Pins::default();
+        self.retain_directories(&mut scan_results, &pins);
+        let AnalysisPass { listings, largest_directories, residuals, heuristic_versions } =
+            self.analyze_files_and_directories(&scan_results, &pins, ages_from, None).await?;
+        let Listings { size_breakdown, file_type_distribution, data_formats, largest_files, mut regenerable, metric_tally, xattr_total, largest_xattr_files, empty_files, .. } = listings;
 
-            type_stats.count += 1;
-            type_stats.total_size += file_entry.size;
-            type_stats.average_size = type_stats.total_size / type_stats.count;
+        let kept: HashSet<&Path> = scan_results.files.iter().map(|file| file.path.as_path()).collect();
+        let duplicate_groups = snapshot.duplicate_groups.as_ref().map(|groups| {
+            groups
+                .iter()
+                .filter_map(|group| {
+                    let files: Vec<PathBuf> = group.files.iter().filter(|path| kept.contains(path.as_path())).cloned().collect();
+                    (files.len() > 1).then(|| DuplicateGroup {
+                        wasted_space: group.file_size * (files.len() as u64 - 1),
+                        files,
+                        ..group.clone()
+                    })
+                })
+                .collect::<Vec<_>>()
+        });
+        for group in duplicate_groups.iter().flatten().filter(|group| group.regenerable) {
+            regenerable.duplicate_groups += 1;
+            regenerable.wasted_space += group.wasted_space;
+        }
 
-            if type_stats.largest_file.as_ref().map(|f| f.size).unwrap_or(0) < file_entry.size {
-                type_stats.largest_file = Some(file_info.clone());
-            }
+        let mut statistics = snapshot.statistics.clone();
+        let (redundant_files, reclaimable_bytes) = duplicates::duplicate_totals(duplicate_groups.as_deref().unwrap_or_default());
+        statistics.duplicate_groups = duplicate_groups.as_ref().map_or(0, |groups| groups.len() as u64);
+        statistics.redundant_files = redundant_files;
+        statistics.reclaimable_bytes = reclaimable_bytes;
+        statistics.compression_ratio = if scan_results.total_size > 0 {
+            scan_results.total_size.saturating_sub(reclaimable_bytes) as f64 / scan_results.total_size as f64
+        } else {
+            1.0
+        };
 
-            largest_files.push(file_info);
+        let custom_metrics = self.evaluate_metrics(&metric_tally, &scan_results, &statistics, &regenerable);
+        let mut scan_info = snapshot.scan_info.clone();
+        scan_info.total_files = scan_results.total_files;
+        scan_info.total_size = scan_results.total_size;
+        scan_info.xattr_total = scan_info.xattr_total.map(|_| xattr_total);
+        scan_info.disk_usage = scan_info.disk_usage.map(|_| DiskUsage::of(&scan_results.files));
+        if report_filters.is_some() {
+            scan_info.physical_size = None;
         }
 
-        // Sort and limit largest files
-        largest_files.sort_by(|a, b| b.size.cmp(&a.size));
-        largest_files.truncate(self.args.top_count);
+        let mut results = AnalysisResults {
+            scan_info,
+            size_breakdown,
+            file_type_distribution,
+            largest_files,
+            largest_directories,
+            duplicate_groups,
+            statistics,
+            trend: None,
+            directory_filter: self.directory_filter(&scan_results, &pins),
+            largest_xattr_files,
+            residuals,
+            regenerable,
+            resolved_groups: None,
+            heuristic_versions,
+            report_filters,
+            // The stored entries were collected before the size range narrowed the listings
+            file_size_range: None,
+            custom_metrics,
+            extensions: Vec::new(),
+            data_formats,
+            owners: OwnerStats::by_owner(&scan_results.files, scanner::user_name),
+            security_audit: snapshot.security_audit.as_ref().map(|_| SecurityAudit::of(&scan_results.files, &scan_results.directories, scanner::user_name)),
+            age_breakdown,
+            stale_files,
+            empty_files,
+            accepted: AcceptedFindings { shown: self.args.show_accepted, ..snapshot.accepted.clone() },
+            ..snapshot.clone()
+        };
+        results.flag_accepted();
+        Ok(results)
+    }
 
-        // Analyze directories
-        for dir_entry in &scan_results.directories {
-            largest_directories.push(DirectoryInfo {
-                path: dir_entry.path.clone(),
-                size: dir_entry.total_size,
-                file_count: dir_entry.file_count,
-                subdirectory_count: dir_entry.subdirectory_count,
-            });
+    /// The files by age of their last use, and the stale ones with `--stale-threshold`
+    fn file_ages(&self, files: &[FileEntry], source: AgeSource, ages_from: DateTime<Utc>) -> (AgeBreakdown, Option<StaleFiles>) {
+        let breakdown = AgeBreakdown::of(files, &self.age_boundaries, source, ages_from);
+        let stale = self.args.stale_threshold.map(|threshold| StaleFiles::of(files, threshold.as_secs(), source, ages_from, self.args.top_count));
+        (breakdown, stale)
+    }
+
+    /// Drop the collected files that fail the report-time filters, recomputing the totals
+    fn apply_report_filters(&self, scan_results: &mut ScanResults, ages_from: DateTime<Utc>) -> Result<Option<AppliedFilters>> {
+        let filters = &self.args.report_filters;
+        if filters.is_empty() {
+            return Ok(None);
         }
+        let matcher = FileMatcher::new(filters, ages_from)?;
+        let collected_files = scan_results.total_files;
+        scan_results.retain_files(|file| matcher.matches(file), self.config.owners_per_directory);
+        Ok(Some(AppliedFilters::new(filters, scan_results.total_files, collected_files)))
+    }
 
-        // Sort and limit largest directories
-        largest_directories.sort_by(|a, b| b.size.cmp(&a.size));
-        largest_directories.truncate(self.args.top_count);
+    /// Drop the collected files outside `--min-file-size` and `--max-file-size`
+    ///
+    /// Directory sizes and totals keep counting them unless `--filter-affects-dirs`.
+    fn apply_size_range(&self, scan_results: &mut ScanResults) -> Option<AppliedSizeRange> {
+        let range = self.size_range?;
+        let collected_files = scan_results.files.len() as u64;
+        if self.args.filter_affects_dirs {
+            scan_results.retain_files(|file| range.contains(file.counted_size()), self.config.owners_per_directory);
+        } else {
+            scan_results.files.retain(|file| range.contains(file.counted_size()));
+        }
+        Some(AppliedSizeRange {
+            range,
+            affects_directories: self.args.filter_affects_dirs,
+            matched_files: scan_results.files.len() as u64,
+            collected_files,
+        })
+    }
 
-        let size_breakdown = SizeBreakdown::from_scan_results(scan_results);
+    /// How many directories `--min-dir-size` leaves out of the listing
+    fn directory_filter(&self, scan_results: &ScanResults, pins: &Pins) -> Option<DirectoryFilter> {
+        self.args.min_dir_size.map(|min_size| DirectoryFilter {
+            min_size,
+            hidden_directories: scan_results
+                .directories
+                .iter()
+                .filter(|d| d.total_size < min_size && !pins.contains(&d.path))
+                .count() as u64
+                + scan_results.dropped_directories.under_floor,
+        })
+    }
 
-        Ok((size_breakdown, file_type_distribution, largest_files, largest_directories))
+    /// Drop the directories no listing can show, unless a feature needs every one
+    ///
+    /// The largest, the pinned, and the root stay; the listing, its residual,
+    /// and the count of hidden directories come out as they would from all.
+    fn retain_directories(&self, scan_results: &mut ScanResults, pins: &Pins) {
+        if full_retention(&self.args, &self.config).is_some() {
+            return;
+        }
+        scan_results.retain_directories(&DirectoryRetention {
+            top: self.args.top_count,
+            min_size: self.args.min_dir_size.unwrap_or(0),
+            keep: pins.entries.iter().map(|entry| entry.path.clone()).collect(),
+        });
+        if self.args.verbose && !scan_results.dropped_directories.is_empty() {
+            println!(
+                "Kept {} of {} directories in memory for the listings",
+                format_count(scan_results.directories.len() as u64),
+                format_count(scan_results.total_directories)
+            );
+        }
     }
 
-    fn calculate_statistics(
-        &self,
-        scan_results: &ScanResults,
-        duplicate_groups: &Option<Vec<DuplicateGroup>>,
-        duration: std::time::Duration,
-    ) -> Statistics {
-        let duration_secs = duration.as_secs_f64();
-        let files_per_second = scan_results.total_files as f64 / duration_secs;
-        let bytes_per_second = (scan_results.total_size as f64 / duration_secs) as u64;
+    /// Carry the operator notes on paths of the scanned tree into the results
+    fn attach_notes(&self, results: &mut AnalysisResults) {
+        let store = match &self.args.notes_file {
+            Some(path) => NoteStore::at(path.clone()),
+            None => match NoteStore::for_current_user() {
+                Some(store) => store,
+                None => return,
+            },
+        };
+        match store.annotations_under(&self.args.path) {
+            Ok(annotations) => results.annotations = annotations,
+            // An unreadable notes file costs the notes, not the run
+            Err(error) => results.phase_errors.push(PhaseError {
+                phase: "notes".to_string(),
+                message: format!("{:#}", error),
+                path: Some(store.path().to_path_buf()),
+            }),
+        }
+    }
 
-        let (duplicate_files, wasted_space) = if let Some(groups) = duplicate_groups {
-            let duplicate_files = groups.iter().map(|g| g.files.len() as u64).sum::<u64>();
-            let wasted_space = groups.iter().map(|g| g.wasted_space).sum();
-            (duplicate_files, wasted_space)
-        } else {
-            (0, 0)
+    /// Flag the listed findings accepted with `accept`
+    fn attach_acceptances(&self, results: &mut AnalysisResults) {
+        let store = match &self.args.accepted_file {
+            Some(path) => AcceptanceStore::at(path.clone()),
+            None => match AcceptanceStore::for_current_user() {
+                Some(store) => store,
+                None => return,
+            },
         };
+        match store.findings_under(&self.args.path) {
+            Ok(findings) => {
+                results.accepted = AcceptedFindings { findings, shown: self.args.show_accepted };
+                results.flag_accepted();
+            }
+            // As with notes, an unreadable store costs the acceptances, not the run
+            Err(error) => results.phase_errors.push(PhaseError {
+                phase: "acceptances".to_string(),
+                message: format!("{:#}", error),
+                path: Some(store.path().to_path_buf()),
+            }),
+        }
+    }
 
-        let compression_ratio = if scan_results.total_size > 0 {
-            (scan_results.total_size - wasted_space) as f64 / scan_results.total_size as f64
-        } else {
-            1.0
+    /// Warn that many files are dated in the future, unless the skew is already assumed
+    fn report_clock_skew(&self, skew: ClockSkew) {
+        if self.args.quiet || self.args.assume_clock_skew.is_some() {
+            return;
+        }
+        println!("{}", format!(
+            "⚠ {} of {} files ({:.0}%) are dated a median {} in the future; the filesystem's clock is probably ahead. Use --assume-clock-skew {}s to correct ages.",
+            format_count(skew.files_ahead),
+            format_count(skew.files_dated),
+            skew.share() * 100.0,
+            format_duration(skew.median_ahead()),
+            skew.median_ahead_secs
+        ).yellow().bold());
+    }
+
+    /// Warn that a cap stopped the scan and the results cover only part of the tree
+    fn report_partial(&self, partial: PartialScan) {
+        if self.args.quiet {
+            return;
+        }
+        let limit = match partial.cap {
+            CapKind::Files => format!("{} files", format_count(partial.limit)),
+            CapKind::Bytes => format_size(partial.limit, DECIMAL),
+            CapKind::Duration => {
+                println!("{}", format!(
+                    "⚠ Ran out of time at --max-duration {}; the results cover only what was found until then and are not recorded in the history.",
+                    ages::format_age(partial.limit)
+                ).yellow().bold());
+                return;
+            }
+            CapKind::Interrupted => {
+                println!("{}", format!(
+                    "⚠ Interrupted after {} files; the results cover only what was found until then and are not recorded in the history.",
+                    format_count(partial.limit)
+                ).yellow().bold());
+                return;
+            }
         };
+        println!("{}", format!(
+            "⚠ Stopped scanning at {} {}; the results cover only part of the tree and are not recorded in the history.",
+            partial.cap.flag(),
+            limit
+        ).yellow().bold());
+    }
 
-        Statistics {
-            files_per_second,
-            bytes_per_second,
-            memory_usage_mb: self.estimate_memory_usage(),
-            duplicate_files,
-            wasted_space,
-            compression_ratio,
+    /// Name the files the date filter left out for want of a modification time, with `--verbose`
+    fn report_undated_files(&self, date_filter: Option<&DateFilter>) {
+        let Some(date_filter) = date_filter.filter(|filter| filter.undated_files > 0 && self.args.verbose && !self.args.quiet) else {
+            return;
+        };
+        println!("{}", format!(
+            "⚠ {} files have no readable modification time and were left out by --modified-before/--modified-after",
+            format_count(date_filter.undated_files)
+        ).yellow().bold());
+        for path in &date_filter.undated_examples {
+            println!("  {}", path.display());
+        }
+        let unnamed = date_filter.undated_files.saturating_sub(date_filter.undated_examples.len() as u64);
+        if unnamed > 0 {
+            println!("  ... and {} more", format_count(unnamed));
         }
     }
 
-    fn estimate_memory_usage(&self) -> f64 {
-        // Rough estimation based on system info
-        // In a real implementation, you might use a proper memory profiling crate
+    /// Warn when exclusions removed nearly everything, and list per-pattern hits under `--verbose`
+    fn report_exclusions(&self, exclusions: &ExclusionStats) {
+        if self.args.quiet {
+            return;
+        }
+
+        let fraction = exclusions.excluded_fraction();
+        if fraction > self.config.exclude_warning_threshold {
+            let mut causes: Vec<String> = exclusions
+                .responsible_patterns()
+                .iter()
+                .map(|p| format!("`{}` ({} hits)", p.pattern, p.hits))
+                .collect();
+            if exclusions.hidden > 0 {
+                causes.push(format!("hidden entries ({} hits, see --show-hidden)", exclusions.hidden));
+            }
+            if let Some(ignored) = exclusions.ignored.filter(|ignored| *ignored > 0) {
+                causes.push(format!("ignore files ({} hits, see --respect-gitignore)", ignored));
+            }
+            println!("{}", format!(
+                "⚠ Exclusions removed {} of {} entries ({:.0}%): {}",
+                exclusions.excluded,
+                exclusions.encountered,
+                fraction * 100.0,
+                causes.join(", ")
+            ).yellow().bold());
+        }
+
+        if self.args.verbose {
+            println!("Exclusions: {} of {} entries ({} hidden)", exclusions.excluded, exclusions.encountered, exclusions.hidden);
+            for pattern in &exclusions.patterns {
+                println!("  {:>8} hits  {}", pattern.hits, pattern.pattern);
+            }
+            if exclusions.own_outputs > 0 {
+                println!("  {:>8} hits  diranalyzer's own files", exclusions.own_outputs);
+            }
+            if let Some(ignored) = exclusions.ignored {
+                println!("  {:>8} hits  .gitignore and .ignore rules", ignored);
+            }
+        }
+    }
+
+    /// Look up the directories given with `--pin` or in the configuration
+    ///
+    /// A pin below the depth limit is measured on its own, without changing any
+    /// total. A pin that does not exist, lies outside the scan root, or is
+    /// excluded gets a warning instead of silently going missing.
+    fn resolve_pins(&self, scan_results: &ScanResults) -> Pins {
+        let mut pins = Pins::default();
+        let roots = self.roots();
+        let absolute_roots: Vec<PathBuf> = roots.iter().map(|root| std::path::absolute(root).unwrap_or_else(|_| root.clone())).collect();
+        let mut seen = HashSet::new();
+        for pin in self.args.pins.iter().chain(&self.config.pinned_directories) {
+            let absolute = std::path::absolute(pin).unwrap_or_else(|_| pin.clone());
+            if !seen.insert(absolute.clone()) {
+                continue;
+            }
+            let Some((index, relative)) = absolute_roots.iter().enumerate().find_map(|(index, root)| Some((index, absolute.strip_prefix(root).ok()?))) else {
+                pins.warnings.push(format!("pinned {} is outside the scan root {}", pin.display(), self.args.path.display()));
+                continue;
+            };
+            if !absolute.is_dir() {
+                pins.warnings.push(format!("pinned {} is not an existing directory", pin.display()));
+                continue;
+            }
+
+            // Spelled the way the scan spells its paths, and checked at every level the walk passes
+            let mut path = roots[index].clone();
+            let source = self.source_under(&path);
+            let mut excluded = false;
+            for component in relative.components() {
+                path.push(component);
+                excluded |= source.excludes_path(&path);
+            }
+            if excluded {
+                pins.warnings.push(format!("pinned {} is excluded by the hidden-file rule or an exclude pattern", pin.display()));
+                continue;
+            }
+
+            let entry = match scan_results.directories.iter().find(|d| d.path == path) {
+                Some(entry) => entry.clone(),
+                None => DirectoryEntry { root: index, ..source.measure_directory(&path) },
+            };
+            pins.entries.push(entry);
+        }
+
+        if !self.args.quiet {
+            for warning in &pins.warnings {
+                println!("{}", format!("⚠ {}", warning).yellow().bold());
+            }
+        }
+        pins
+    }
+
+    /// List directories that were slow to read under `--verbose`
+    fn report_slow_directories(&self, slow_directories: &[SlowDirectory]) {
+        if !self.args.verbose || slow_directories.is_empty() {
+            return;
+        }
+        println!("Slow directories:");
+        for directory in slow_directories {
+            println!(
+                "  {:>8.1}s {:>12} entries  {}",
+                directory.elapsed_ms as f64 / 1000.0,
+                format_count(directory.entries),
+                directory.path.display()
+            );
+        }
+    }
+
+    /// List how many files each path classification rule claimed under `--verbose`
+    fn report_rule_hits(&self) {
+        if !self.args.verbose || self.args.quiet {
+            return;
+        }
+        println!("Classification rules:");
+        for rule in self.classifier.rule_hits() {
+            let extensions = if rule.extensions.is_empty() { String::new() } else { format!(" [.{}]", rule.extensions.join(", .")) };
+            let origin = if rule.builtin { " (built-in)" } else { "" };
+            println!("  {:>8} hits  {}{} -> {}{}", rule.hits, rule.pattern, extensions, rule.category, origin);
+        }
+    }
+
+    /// Record this run's use of the hash cache and let it compact
+    ///
+    /// A cache problem is reported but never fails the analysis; the hashes
+    /// it could not keep are simply read again next time.
+    fn finish_hash_cache(&self) -> Option<CacheUsage> {
+        let cache = self.hash_cache.as_ref()?;
+        let usage = match cache.finish(&self.args.path) {
+            Ok(usage) => usage,
+            Err(error) => {
+                if !self.args.quiet {
+                    println!("{}", format!("⚠ Hash cache not updated: {:#}", error).yellow().bold());
+                }
+                cache.usage()
+            }
+        };
+        if self.args.verbose {
+            println!("Hash cache: {} of {} lookups hit ({:.1}%), {} hashes stored in {}",
+                usage.hits, usage.lookups, usage.hit_rate() * 100.0, usage.stored, cache.dir().display());
+        }
+        Some(usage)
+    }
+
+    /// Warn, or fail with `--strict-space`, when the planned outputs may not fit
+    ///
+    /// Export and journal estimates need the file count, taken from the last
+    /// recorded run or from a bounded pre-count of the tree.
+    fn check_space(&self) -> Result<()> {
+        let store = history_store(&self.args, &self.config, &self.writes);
+        let expected_files = if self.args.export.is_some() || self.args.dedupe.is_some() {
+            let recorded = store.as_ref().and_then(|store| store.load().ok()?.last().map(|record| record.total_files));
+            recorded.unwrap_or_else(|| space::precount(&self.args.path, self.args.max_depth, self.args.show_hidden))
+        } else {
+            0
+        };
+
+        let outputs = planned_outputs(&self.args, expected_files, store.as_ref());
+        let shortfalls: Vec<String> = space::shortfalls(&outputs, space::available).iter().map(Shortfall::describe).collect();
+        if shortfalls.is_empty() {
+            return Ok(());
+        }
+        if self.args.strict_space {
+            anyhow::bail!("Not enough free space: {}", shortfalls.join("; "));
+        }
+        if !self.args.quiet {
+            println!("{}", format!("⚠ Outputs may not fit: {}", shortfalls.join("; ")).yellow().bold());
+        }
+        Ok(())
+    }
+
+    /// Append this run to the root's history and compute the trend against earlier runs.
+    /// Entropy shares are compared with the last run that sampled them, and growth
+    /// is attributed to unique and duplicated bytes when both runs searched for duplicates.
+    /// History problems are reported but never fail the analysis.
+    fn record_history(&self, results: &mut AnalysisResults) -> Option<Trend> {
+        let store = HistoryStore::for_root(&self.args.path, self.config.history_retention)?.with_writes(self.writes.clone());
+        // A cache inside a read-only tree still yields the trend, it just is not updated
+        let writable = self.writes.permits(store.path());
+        let current = HistoryRecord {
+            timestamp: results.scan_info.timestamp,
+            total_size: results.scan_info.total_size,
+            total_files: results.scan_info.total_files,
+            duplicate_waste: results.statistics.reclaimable_bytes,
+            entropy: results.entropy.as_ref().map(EntropyReport::snapshot),
+            duplicates: results
+                .duplicate_groups
+                .as_deref()
+                .map(|groups| DuplicateSnapshot::of(results.scan_info.total_size, groups)),
+        };
+
+        let outcome = store.load().and_then(|previous| {
+            if let Some(report) = &mut results.entropy {
+                if let Some(earlier) = previous.iter().rev().find_map(|record| record.entropy.as_deref()) {
+                    report.compare_with(earlier);
+                }
+            }
+            if writable {
+                store.append(&current)?;
+            }
+            if let Some(groups) = &mut results.duplicate_groups {
+                let ledger = store.load_ledger()?;
+                let (updated, resolved) = history::track_groups(ledger.as_ref(), groups, current.timestamp);
+                results.resolved_groups = ledger.is_some().then_some(resolved);
+                if writable {
+                    store.save_ledger(&updated)?;
+                }
+            }
+            let mut trend = history::compute_trend(&previous, &current);
+            if let (Some(trend), Some(last)) = (&mut trend, history::last_run(&previous, &current)) {
+                trend.growth = history::attribute_growth(last, &current, results.duplicate_groups.as_deref().unwrap_or_default());
+            }
+            Ok(trend)
+        });
+
+        match outcome {
+            Ok(trend) => trend,
+            Err(error) => {
+                if !self.args.quiet {
+                    eprintln!("⚠ Could not update scan history: {:#}", error);
+                }
+                None
+            }
+        }
+    }
+
+    /// The listings over the files and directories left after the size range
+    ///
+    /// A `--low-memory` run passes the listings its fold took as the walk went,
+    /// and only the directories are listed here.
+    async fn analyze_files_and_directories(&self, scan_results: &ScanResults, pins: &Pins, ages_from: DateTime<Utc>, folded: Option<Listings>) -> Result<AnalysisPass> {
+        let mut largest_directories = Vec::new();
+
+        let mut residuals = Residuals::default();
+        let mut versions = (self.args.find_versions && folded.is_none()).then(VersionGrouper::default);
+
+        // Analyze directories; the size floor only affects listings, not totals
+        let min_dir_size = self.args.min_dir_size.unwrap_or(0);
+        let mut user_names: HashMap<u32, Option<String>> = HashMap::new();
+        let mut user_name = |uid| user_names.entry(uid).or_insert_with(|| scanner::user_name(uid)).clone();
+        let unpinned = scan_results.directories.iter().filter(|d| d.total_size >= min_dir_size && !pins.contains(&d.path));
+        for dir_entry in unpinned.chain(&pins.entries) {
+            largest_directories.push(DirectoryInfo {
+                path: dir_entry.path.clone(),
+                size: dir_entry.total_size,
+                file_count: dir_entry.file_count,
+                recursive_file_count: dir_entry.recursive_file_count,
+                subdirectory_count: dir_entry.subdirectory_count,
+                recursive_subdirectory_count: dir_entry.recursive_subdirectory_count,
+                annotation: None,
+                owner_mix: OwnerMix::of(&dir_entry.owners, dir_entry.total_size, &mut user_name),
+                pinned: pins.contains(&dir_entry.path),
+                composition: BTreeMap::new(),
+                accepted: false,
+                root: dir_entry.root,
+            });
+        }
+
+        // Sort and limit largest directories; pinned ones past the limit stay, after the ranked ones
+        largest_directories.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.path.cmp(&b.path)));
+        let tail = largest_directories.split_off(self.args.top_count.min(largest_directories.len()));
+        let (pinned_tail, unlisted): (Vec<_>, Vec<_>) = tail.into_iter().partition(|d| d.pinned);
+        residuals.largest_directories = Residual::of(unlisted.iter().map(|d| d.size));
+        residuals.largest_directories.count += scan_results.dropped_directories.ranked_out;
+        residuals.largest_directories.size += scan_results.dropped_directories.ranked_out_size;
+        largest_directories.extend(pinned_tail);
+        for dir in &mut largest_directories {
+            // Scan paths may be relative, knowledge base paths never are
+            let absolute = std::path::absolute(&dir.path).unwrap_or_else(|_| dir.path.clone());
+            dir.annotation = self.annotations.lookup(&absolute).map(|annotation| annotation.text());
+        }
+
+        // Break the listed directories down by file type as the files are classified
+        let listed: HashMap<PathBuf, usize> =
+            largest_directories.iter().enumerate().map(|(index, dir)| (dir.path.clone(), index)).collect();
+        let mut compositions: Vec<HashMap<TypeId, u64>> = vec![HashMap::new(); largest_directories.len()];
+
+        // Analyze files by type id; names and `FileInfo`s are only made for what the results keep
+        let mut file_listings = folded.is_none().then(|| FileListings::new(&self.classifier, &self.metrics, &self.args, ages_from));
+        for file_entry in &scan_results.files {
+            let Some(file_listings) = &mut file_listings else { break };
+            let (type_id, size) = file_listings.add(file_entry);
+            for ancestor in file_entry.path.ancestors().skip(1) {
+                if let Some(&index) = listed.get(ancestor) {
+                    *compositions[index].entry(type_id).or_default() += size;
+                }
+            }
+            if let Some(versions) = &mut versions {
+                versions.add(&file_entry.path, size, file_entry.modified);
+            }
+        }
+
+        for (dir, composition) in largest_directories.iter_mut().zip(compositions) {
+            dir.composition = composition.into_iter().map(|(id, size)| (self.classifier.type_name(id).to_string(), size)).collect();
+        }
+        let listings = match file_listings {
+            Some(file_listings) => file_listings.finish(self.config.data_formats_min_size),
+            None => folded.unwrap_or_default(),
+        };
+        residuals.largest_files = listings.largest_files_residual;
+        residuals.largest_xattr_files = listings.largest_xattr_residual;
+
+        Ok(AnalysisPass { listings, largest_directories, residuals, heuristic_versions: versions.map(VersionGrouper::finish) })
+    }
+
+    /// The custom metrics over what the analysis pass tallied and the run's totals
+    fn evaluate_metrics(&self, tally: &MetricTally, scan_results: &ScanResults, statistics: &Statistics, regenerable: &RegenerableTotals) -> Vec<CustomMetric> {
+        self.metrics.evaluate(tally, &MetricInputs {
+            total_size: scan_results.total_size,
+            total_files: scan_results.total_files,
+            total_directories: scan_results.total_directories,
+            reclaimable_bytes: statistics.reclaimable_bytes,
+            regenerable_size: regenerable.size,
+        })
+    }
+
+    fn calculate_statistics(
+        &self,
+        scan_results: &ScanResults,
+        duplicate_groups: &Option<Vec<DuplicateGroup>>,
+        duration: std::time::Duration,
+    ) -> Statistics {
+        // Rates stay finite: NaN or infinity would make the JSON export invalid
+        let duration_secs = duration.as_secs_f64();
+        let (files_per_second, bytes_per_second) = if duration_secs > 0.0 {
+            (
+                scan_results.total_files as f64 / duration_secs,
+                (scan_results.total_size as f64 / duration_secs) as u64,
+            )
+        } else {
+            (0.0, 0)
+        };
+
+        let (redundant_files, reclaimable_bytes) = duplicates::duplicate_totals(duplicate_groups.as_deref().unwrap_or_default());
+
+        let compression_ratio = if scan_results.total_size > 0 {
+            // Imported duplicate groups may cover files outside this scan
+            scan_results.total_size.saturating_sub(reclaimable_bytes) as f64 / scan_results.total_size as f64
+        } else {
+            1.0
+        };
+
+        Statistics {
+            files_per_second,
+            bytes_per_second,
+            memory_usage_mb: self.estimate_memory_usage(),
+            duplicate_groups: duplicate_groups.as_ref().map_or(0, |groups| groups.len() as u64),
+            redundant_files,
+            reclaimable_bytes,
+            compression_ratio,
+            resource_usage: Vec::new(),
+            io_latency: self.latency.report(),
+        }
+    }
+
+    fn estimate_memory_usage(&self) -> f64 {
+        // Rough estimation based on system info
+        // In a real implementation, you might use a proper memory profiling crate
         50.0 // Placeholder: 50MB estimated
     }
 }
+
+/// Memory a collected file takes during the run, its path included
+const BYTES_PER_FILE: u64 = 300;
+
+/// Levels below the root that `--check` lists to try the patterns on
+const PROBE_DEPTH: usize = 3;
+
+/// Entries `--check` lists at most to try the patterns on
+const PROBE_ENTRIES: usize = 10_000;
+
+/// Hashing threads a spinning disk serves without seeking back and forth
+const ROTATIONAL_HASH_THREADS: usize = 2;
+
+/// Threads per CPU beyond which `--threads` only adds contention
+const THREADS_PER_CPU: usize = 4;
+
+/// What `--check` found wrong with the configuration of a run
+#[derive(Debug, Clone, Default)]
+pub struct CheckReport {
+    pub findings: Vec<CheckFinding>,
+    /// Files the pre-count found, at most [`space::PRECOUNT_LIMIT`]; `None` when the tree cannot be read
+    pub expected_files: Option<u64>,
+}
+
+/// One problem found by `--check`
+#[derive(Debug, Clone, PartialEq)]
+pub struct CheckFinding {
+    pub category: CheckCategory,
+    pub message: String,
+}
+
+/// The part of a run a [`CheckFinding`] concerns
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckCategory {
+    /// The scanned tree or another input
+    Path,
+    /// `--exclude` and `--only`
+    Patterns,
+    /// The export, journal, history, and hash cache destinations
+    Outputs,
+    /// The configuration file and the thread count
+    Settings,
+    /// The configured memory limit
+    Memory,
+}
+
+impl CheckReport {
+    /// 0 when nothing was found, 1 otherwise
+    pub fn exit_code(&self) -> i32 {
+        if self.findings.is_empty() { 0 } else { 1 }
+    }
+
+    /// Whether the pre-count stopped at its limit, so the tree holds more files than counted
+    pub fn precount_stopped(&self) -> bool {
+        self.expected_files.is_some_and(|files| files >= space::PRECOUNT_LIMIT)
+    }
+
+    fn find(&mut self, category: CheckCategory, message: impl Into<String>) {
+        self.findings.push(CheckFinding { category, message: message.into() });
+    }
+}
+
+impl CheckCategory {
+    pub fn label(&self) -> &'static str {
+        match self {
+            CheckCategory::Path => "path",
+            CheckCategory::Patterns => "patterns",
+            CheckCategory::Outputs => "outputs",
+            CheckCategory::Settings => "settings",
+            CheckCategory::Memory => "memory",
+        }
+    }
+}
+
+/// Check the configuration of a run of `args` against the filesystem, without scanning
+///
+/// Runs the validators a real run starts with, and does the cheap parts of
+/// its work: the patterns are tried on the first [`PROBE_DEPTH`] levels of the
+/// tree, the destinations are checked for room and permission, the thread
+/// count is weighed against the CPUs and the disk, and the pre-count is
+/// weighed against `memory_limit_mb`, as is a feature keeping every directory.
+/// Nothing is written.
+pub fn check_run(args: &Args) -> CheckReport {
+    let mut report = CheckReport::default();
+    let readable = match utils::validate_directory(&args.path) {
+        Ok(()) => true,
+        Err(error) => {
+            report.find(CheckCategory::Path, format!("{:#}", error));
+            false
+        }
+    };
+    if let Some(file) = &args.import_duplicates {
+        if let Err(error) = std::fs::File::open(file) {
+            report.find(CheckCategory::Path, format!("Cannot read --import-duplicates {}: {}", file.display(), error));
+        }
+    }
+    let config = Config::load(args.config.as_deref()).unwrap_or_else(|error| {
+        report.find(CheckCategory::Settings, format!("{:#}", error));
+        Config::default()
+    });
+    // Metrics may select the categories of configured rules
+    let classifier = FileTypeClassifier::new().with_rules(&config.classification_rules).unwrap_or_default();
+    if let Err(error) = MetricSet::compile(&config.custom_metrics, &classifier) {
+        report.find(CheckCategory::Settings, format!("{:#}", error));
+    }
+    if let Err(error) = ages::boundaries(&config.age_buckets) {
+        report.find(CheckCategory::Settings, format!("{:#}", error));
+    }
+    let writes = WriteGuard::for_args(args).unwrap_or_else(|error| {
+        report.find(CheckCategory::Outputs, format!("{:#}", error));
+        WriteGuard::unrestricted()
+    });
+
+    check_patterns(args, readable, &mut report);
+
+    report.expected_files = readable.then(|| space::precount(&args.path, args.max_depth, args.show_hidden));
+    let store = history_store(args, &config, &writes);
+    let mut outputs = planned_outputs(args, report.expected_files.unwrap_or(0), store.as_ref());
+    if args.hash_cache || args.hash_cache_dir.is_some() {
+        match args.hash_cache_dir.clone().or_else(HashCache::default_dir) {
+            Some(directory) => outputs.push(space::PlannedOutput { purpose: "the hash cache", directory, bytes: 0, inodes: 1 }),
+            None => report.find(CheckCategory::Outputs, "Could not determine the hash cache directory"),
+        }
+    }
+    for output in &outputs {
+        if let Err(error) = space::writable(&output.directory) {
+            report.find(CheckCategory::Outputs, format!("{}: {:#}", output.purpose, error));
+        }
+    }
+    for shortfall in space::shortfalls(&outputs, space::available) {
+        report.find(CheckCategory::Outputs, format!("Not enough free space: {}", shortfall.describe()));
+    }
+
+    check_threads(args, readable, &mut report);
+
+    // A low-memory run keeps no files, so their number does not bound its memory
+    if let (Some(limit_mb), Some(counted), false) = (config.memory_limit_mb, report.expected_files, args.low_memory) {
+        let files = args.max_files.map_or(counted, |cap| counted.min(cap));
+        let needed = files * BYTES_PER_FILE;
+        if needed > limit_mb * 1_000_000 {
+            let at_least = if report.precount_stopped() && files == counted { "at least " } else { "" };
+            report.find(CheckCategory::Memory, format!(
+                "{}{} files need about {} in memory, over memory_limit_mb ({} MB); cap the run with --max-files or fold them as they are found with --low-memory",
+                at_least, format_count(files), format_size(needed, DECIMAL), limit_mb
+            ));
+        }
+    }
+    if let (Some(limit_mb), Some(feature)) = (config.memory_limit_mb, full_retention(args, &config)) {
+        report.find(CheckCategory::Memory, format!(
+            "{} keeps every directory in memory after the scan, which memory_limit_mb ({} MB) does not account for",
+            feature, limit_mb
+        ));
+    }
+    report
+}
+
+/// Compile `--exclude` and `--only` and try them on the first levels of the tree
+fn check_patterns(args: &Args, readable: bool, report: &mut CheckReport) {
+    let depth = PROBE_DEPTH.min(args.max_depth);
+    let probe: Vec<walkdir::DirEntry> = if readable {
+        walkdir::WalkDir::new(&args.path)
+            .min_depth(1)
+            .max_depth(depth)
+            .into_iter()
+            .filter_map(|entry| entry.ok())
+            .take(PROBE_ENTRIES)
+            .collect()
+    } else {
+        Vec::new()
+    };
+    // Nothing to try them on says nothing about the patterns
+    let tried = !probe.is_empty();
+
+    let globs = args.exclude_patterns.iter().map(|pattern| ("--exclude", ExcludePattern::glob(pattern)));
+    let regexes = args.exclude_regexes.iter().map(|pattern| ("--exclude-regex", ExcludePattern::regex(pattern)));
+    let includes = args.include_patterns.iter().map(|pattern| ("--include", ExcludePattern::include_glob(pattern)));
+    for (option, pattern) in globs.chain(regexes).chain(includes) {
+        match pattern {
+            Err(error) => report.find(CheckCategory::Patterns, format!("{:#}", error)),
+            Ok(pattern) if tried && !probe.iter().any(|entry| pattern.is_match(&args.path, entry.path())) => {
+                report.find(
+                    CheckCategory::Patterns,
+                    format!("{} {} matches nothing in the first {} levels", option, pattern.as_str(), depth),
+                );
+            }
+            Ok(_) => {}
+        }
+    }
+    let files: Vec<&walkdir::DirEntry> = probe.iter().filter(|entry| entry.file_type().is_file()).collect();
+    for glob in &args.report_filters.only {
+        match utils::glob_to_regex(glob).and_then(|regex| Ok(regex::Regex::new(&regex)?)) {
+            Err(error) => report.find(CheckCategory::Patterns, format!("--only {} does not compile: {:#}", glob, error)),
+            Ok(regex) if !files.is_empty() && !files.iter().any(|entry| regex.is_match(&entry.path().to_string_lossy())) => {
+                report.find(CheckCategory::Patterns, format!("--only {} matches no file in the first {} levels", glob, depth));
+            }
+            Ok(_) => {}
+        }
+    }
+}
+
+/// Weigh `--threads` against the CPUs, and hashing threads against a spinning disk
+fn check_threads(args: &Args, readable: bool, report: &mut CheckReport) {
+    let cpus = std::thread::available_parallelism().map_or(1, |cpus| cpus.get());
+    let threads = args.threads.filter(|&threads| threads > 0).unwrap_or(cpus);
+    if threads > cpus * THREADS_PER_CPU {
+        report.find(CheckCategory::Settings, format!("--threads {} is over {} per CPU on {} CPUs", threads, THREADS_PER_CPU, cpus));
+    }
+    let spinning = readable && space::is_rotational(&args.path) == Some(true);
+    if args.find_duplicates && spinning && threads > ROTATIONAL_HASH_THREADS {
+        report.find(CheckCategory::Settings, format!(
+            "Hashing on {} threads makes the spinning disk under {} seek between files; use --threads {}",
+            threads, args.path.display(), ROTATIONAL_HASH_THREADS
+        ));
+    }
+}
+
+/// The history store a run appends to, unless history is off or the run may not write it
+fn history_store(args: &Args, config: &Config, writes: &WriteGuard) -> Option<HistoryStore> {
+    HistoryStore::for_root(&args.path, config.history_retention)
+        .filter(|store| !args.no_history && writes.permits(store.path()))
+}
+
+/// The feature that makes a run keep every directory after the scan, if any
+///
+/// A full snapshot exports every directory, and the configuration may ask
+/// for all of them; otherwise only those the listings can show are kept.
+fn full_retention(args: &Args, config: &Config) -> Option<&'static str> {
+    if args.export_scope == ExportScope::Full {
+        Some("--export-scope full")
+    } else if config.retain_all_directories {
+        Some("retain_all_directories")
+    } else {
+        None
+    }
+}
+
+/// The journal a duplicate search records its completed size groups in, unless the run may not write it
+///
+/// `--read-only` runs keep none: the cache may well sit on the volume they leave untouched.
+fn results_journal(args: &Args, writes: &WriteGuard) -> Option<ResultsJournal> {
+    if writes.is_read_only() {
+        return None;
+    }
+    ResultsJournal::for_root(&args.path)
+        .filter(|journal| writes.permits(journal.path()))
+        .map(|journal| journal.with_writes(writes.clone()).resuming(args.resume))
+}
+
+/// The files a run writes, given the files expected in the tree
+fn planned_outputs(args: &Args, expected_files: u64, store: Option<&HistoryStore>) -> Vec<space::PlannedOutput> {
+    let history = store.map(|store| {
+        let existing = std::fs::metadata(store.path()).map_or(0, |metadata| metadata.len());
+        (store.path(), existing)
+    });
+    space::plan(args, expected_files, history)
+}
+
+impl AnalysisResults {
+    /// Whether an internal failure left parts of these results incomplete
+    pub fn is_degraded(&self) -> bool {
+        !self.phase_errors.is_empty()
+    }
+
+    /// Listed files, directories, and duplicate groups flagged `accepted`
+    pub fn accepted_count(&self) -> usize {
+        self.largest_files.iter().filter(|file| file.accepted).count()
+            + self.largest_directories.iter().filter(|dir| dir.accepted).count()
+            + self.duplicate_groups.iter().flatten().filter(|group| group.accepted).count()
+    }
+
+    /// Set the `accepted` flag of every listed file, directory, and duplicate group from [`AnalysisResults::accepted`]
+    fn flag_accepted(&mut self) {
+        let accepted = &self.accepted;
+        for file in &mut self.largest_files {
+            file.accepted = accepted.for_path(&file.path).is_some();
+        }
+        for directory in &mut self.largest_directories {
+            directory.accepted = accepted.for_path(&directory.path).is_some();
+        }
+        for group in self.duplicate_groups.iter_mut().flatten() {
+            group.accepted = accepted.for_group(&group.group_id).is_some();
+        }
+    }
+}
+
+impl PhaseError {
+    fn from_scan_error(phase: &str, error: ScanError) -> Self {
+        Self {
+            phase: phase.to_string(),
+            message: error.error,
+            path: Some(error.path),
+        }
+    }
+}
+
+/// What the analysis pass lists of the files and directories
+#[derive(Default)]
+struct AnalysisPass {
+    listings: Listings,
+    largest_directories: Vec<DirectoryInfo>,
+    residuals: Residuals,
+    heuristic_versions: Option<VersionReport>,
+}
+
+/// List the files of `source`, below `directories` when given, handing them to `fold` when there is one
+///
+/// Handed-out files are tagged with `root`, which [`ScanResults::add_root`]
+/// can no longer do for them.
+fn list_files(source: &mut dyn FileSource, directories: Option<&[PathBuf]>, root: usize, fold: Option<&mut FileFold<'_>>) -> Result<ScanResults> {
+    let Some(fold) = fold else {
+        return match directories {
+            Some(directories) => source.list_below(directories),
+            None => source.list(),
+        };
+    };
+    let mut sink = |mut file: FileEntry| {
+        file.root = root;
+        fold.add(file);
+    };
+    match directories {
+        Some(directories) => source.stream_below(directories, &mut sink),
+        None => source.stream(&mut sink),
+    }
+}
+
+/// Await one analysis phase, containing a panic inside it.
+///
+/// A panic is recorded in `errors` and yields `Ok(None)` so the analysis can
+/// continue with whatever the other phases produce. In strict mode panics
+/// propagate as usual.
+async fn guard_phase<T>(
+    phase: &str,
+    strict: bool,
+    errors: &mut Vec<PhaseError>,
+    future: impl Future<Output = Result<T>>,
+) -> Result<Option<T>> {
+    if strict {
+        return future.await.map(Some);
+    }
+
+    let mut future = Box::pin(future);
+    let outcome = std::future::poll_fn(|cx| {
+        match panic::catch_unwind(AssertUnwindSafe(|| future.as_mut().poll(cx))) {
+            Ok(Poll::Pending) => Poll::Pending,
+            Ok(Poll::Ready(result)) => Poll::Ready(Ok(result)),
+            Err(payload) => Poll::Ready(Err(payload)),
+        }
+    })
+    .await;
+
+    match outcome {
+        Ok(result) => result.map(Some),
+        Err(payload) => {
+            errors.push(PhaseError {
+                phase: phase.to_string(),
+                message: format!("panic: {}", panic_message(payload.as_ref())),
+                path: None,
+            });
+            Ok(None)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::accepted::AcceptedTarget;
+    use crate::duplicates::PoisonedHasher;
+    use crate::metrics::MetricUnit;
+    use crate::utils::latency::IoOperation;
+    use clap::Parser;
+    use std::path::Path;
+
+    fn write_files(dir: &Path) {
+        for name in ["a.bin", "b.bin", "poison-1.bin", "poison-2.bin"] {
+            let fill = if name.starts_with("poison") { b'p' } else { b'a' };
+            std::fs::write(dir.join(name), vec![fill; if fill == b'p' { 3000 } else { 2000 }]).unwrap();
+        }
+    }
+
+    fn analyzer_for(dir: &Path, extra: &[&str]) -> DirectoryAnalyzer {
+        crate::config::isolate_cache_dir();
+        let mut argv = vec!["diranalyzer", "--quiet", "--no-history", "--min-size", "1"];
+        argv.extend_from_slice(extra);
+        argv.push(dir.to_str().unwrap());
+        DirectoryAnalyzer::new(Args::parse_from(argv))
+            .unwrap()
+            .with_duplicate_finder(DuplicateFinder::new(1, Some(2)).with_hasher(Box::new(PoisonedHasher)))
+    }
+
+    #[test]
+    fn test_owner_mix_names_owners_and_finds_a_clear_one() {
+        let share = |uid, size| OwnerShare { uid, size };
+        let names = |uid| (uid == 1000).then(|| "alice".to_string());
+
+        let mixed = OwnerMix::of(&[share(1000, 600), share(1001, 300), share(1002, 60), share(1003, 40)], 1_000, names).unwrap();
+        assert!(!mixed.clear_owner);
+        assert_eq!(
+            mixed.owners.iter().map(|owner| (owner.user.as_str(), owner.size)).collect::<Vec<_>>(),
+            [("alice", 600), ("1001", 300), ("1002", 60)]
+        );
+
+        // Unowned bytes count against the largest owner's share
+        assert!(OwnerMix::of(&[share(1000, 910)], 1_000, names).unwrap().clear_owner);
+        assert!(!OwnerMix::of(&[share(1000, 900)], 1_000, names).unwrap().clear_owner);
+        assert_eq!(OwnerMix::of(&[], 1_000, names), None);
+    }
+
+    #[test]
+    fn test_check_finds_each_category() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("tree");
+        std::fs::create_dir_all(root.join("logs")).unwrap();
+        std::fs::write(root.join("logs/app.log"), b"x").unwrap();
+        std::fs::write(dir.path().join("file"), b"x").unwrap();
+        std::fs::write(dir.path().join("tight.json"), br#"{"memory_limit_mb": 0}"#).unwrap();
+        std::fs::write(dir.path().join("roomy.json"), br#"{"memory_limit_mb": 1000000}"#).unwrap();
+        std::fs::write(dir.path().join("broken.json"), b"{").unwrap();
+        std::fs::write(dir.path().join("metric.json"), br#"{"custom_metrics": [{"name": "m", "expression": "size(type:Imagez)"}]}"#).unwrap();
+
+        let check = |extra: &[&str], path: &Path| {
+            let mut argv = vec!["diranalyzer", "--no-history"];
+            argv.extend_from_slice(extra);
+            argv.push(path.to_str().unwrap());
+            check_run(&Args::parse_from(argv))
+        };
+        let categories = |report: &CheckReport| report.findings.iter().map(|finding| finding.category).collect::<Vec<_>>();
+
+        let at = |name: &str| dir.path().join(name).to_string_lossy().into_owned();
+        let (output, blocked_output, broken, tight, missing) = (at("out/r.json"), at("file/r.json"), at("broken.json"), at("tight.json"), dir.path().join("missing"));
+        let (roomy, metric) = (at("roomy.json"), at("metric.json"));
+
+        let clean = check(&["--exclude", "logs", "--only", "*.log", "--export", "json", "--output", &output], &root);
+        assert_eq!(categories(&clean), []);
+        assert_eq!((clean.exit_code(), clean.expected_files), (0, Some(1)));
+
+        assert_eq!(categories(&check(&["--config", &roomy], &root)), []);
+
+        let cases: [(Vec<&str>, &Path, CheckCategory); 10] = [
+            (vec![], &missing, CheckCategory::Path),
+            (vec!["--exclude-regex", "("], &root, CheckCategory::Patterns),
+            (vec!["--exclude", "*.tmp"], &root, CheckCategory::Patterns),
+            (vec!["--only", "*.csv"], &root, CheckCategory::Patterns),
+            (vec!["--export", "json", "--output", &blocked_output], &root, CheckCategory::Outputs),
+            (vec!["--threads", "1000000"], &root, CheckCategory::Settings),
+            (vec!["--config", &broken], &root, CheckCategory::Settings),
+            (vec!["--config", &metric], &root, CheckCategory::Settings),
+            (vec!["--config", &tight], &root, CheckCategory::Memory),
+            (vec!["--config", &roomy, "--export", "json", "--output", &output, "--export-scope", "full"], &root, CheckCategory::Memory),
+        ];
+        for (extra, path, category) in cases {
+            let report = check(&extra, path);
+            assert_eq!(categories(&report), [category], "{:?}: {:?}", extra, report.findings);
+            assert_eq!(report.exit_code(), 1);
+        }
+    }
+
+    #[test]
+    fn test_read_only_refuses_writing_options_before_scanning() {
+        let dir = tempfile::tempdir().unwrap();
+        // Never created: the refusal must come before the walk would notice
+        let root = dir.path().join("volume");
+        let args = Args::parse_from([
+            "diranalyzer",
+            "--read-only",
+            "--dedupe",
+            "delete",
+            "--journal",
+            root.join("undo.ndjson").to_str().unwrap(),
+            "--preserve-dir-mtime",
+            "--export",
+            "csv",
+            "--output-template",
+            "report.{ext}",
+            "--keep-last",
+            "3",
+            "--resume",
+            root.to_str().unwrap(),
+        ]);
+
+        let error = DirectoryAnalyzer::new(args).err().unwrap().to_string();
+        for flag in ["--dedupe", "--journal", "--preserve-dir-mtime", "--output-template", "--keep-last", "--resume", "--export without --output"] {
+            assert!(error.contains(flag), "{} missing from: {}", flag, error);
+        }
+        assert!(!root.exists());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_own_tree_has_a_clear_owner() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("mine");
+        std::fs::create_dir_all(root.join("sub")).unwrap();
+        std::fs::write(root.join("sub/data.bin"), vec![0u8; 2_000]).unwrap();
+
+        let results = analyzer_for(&root, &[]).analyze().await.unwrap();
+        let mix = results.largest_directories[0].owner_mix.as_ref().unwrap();
+        assert!(mix.clear_owner);
+        // SAFETY: geteuid has no preconditions
+        assert_eq!(mix.owners[0].uid, unsafe { libc::geteuid() });
+        assert_eq!(mix.owners[0].size, 2_000);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_owner_totals_cover_every_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("mine");
+        std::fs::create_dir_all(root.join("sub")).unwrap();
+        std::fs::write(root.join("a.bin"), vec![0u8; 500]).unwrap();
+        std::fs::write(root.join("sub/b.bin"), vec![0u8; 1_500]).unwrap();
+
+        let results = analyzer_for(&root, &[]).analyze().await.unwrap();
+        // SAFETY: geteuid has no preconditions
+        let uid = unsafe { libc::geteuid() };
+        let user = scanner::user_name(uid).unwrap_or_else(|| uid.to_string());
+        assert_eq!(results.owners.len(), 1);
+        assert_eq!(results.owners[&user], OwnerStats { uid, count: 2, total_size: 2_000 });
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_security_audit_finds_risky_modes_only_when_asked() {
+        use std::os::unix::fs::PermissionsExt;
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("tree");
+        std::fs::create_dir_all(root.join("drop")).unwrap();
+        std::fs::create_dir_all(root.join("shared")).unwrap();
+        let chmod = |name: &str, mode| std::fs::set_permissions(root.join(name), std::fs::Permissions::from_mode(mode)).unwrap();
+        for (name, mode) in [("open.txt", 0o666), ("tool", 0o4755), ("plain.txt", 0o644)] {
+            std::fs::write(root.join(name), b"data").unwrap();
+            chmod(name, mode);
+        }
+        std::fs::write(root.join("drop/note.txt"), b"data").unwrap();
+        chmod("drop", 0o777);
+        chmod("shared", 0o1777);
+
+        let results = analyzer_for(&root, &[]).analyze().await.unwrap();
+        assert!(results.security_audit.is_none());
+
+        let audit = analyzer_for(&root, &["--security-audit"]).analyze().await.unwrap().security_audit.unwrap();
+        assert_eq!(audit.audited_entries, 7);
+        assert_eq!(audit.world_writable_files.iter().map(|finding| finding.path.clone()).collect::<Vec<_>>(), [root.join("open.txt")]);
+        assert_eq!((audit.setuid_files[0].path.clone(), audit.setuid_files[0].mode), (root.join("tool"), 0o4755));
+        assert!(audit.setgid_files.is_empty());
+        assert_eq!(audit.open_directories.iter().map(|finding| finding.path.clone()).collect::<Vec<_>>(), [root.join("drop")]);
+    }
+
+    #[tokio::test]
+    async fn test_stale_threshold_lists_files_unused_that_long() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("tree");
+        std::fs::create_dir_all(&root).unwrap();
+        let write = |name: &str, size: usize, days: u64| {
+            std::fs::write(root.join(name), vec![0u8; size]).unwrap();
+            let used = std::time::SystemTime::now() - Duration::from_secs(days * 86_400);
+            let times = std::fs::FileTimes::new().set_accessed(used).set_modified(used);
+            std::fs::File::options().write(true).open(root.join(name)).unwrap().set_times(times).unwrap();
+        };
+        write("recent.bin", 100, 1);
+        write("last-year.bin", 300, 400);
+        write("ancient.bin", 200, 2_000);
+
+        let results = analyzer_for(&root, &["--stale-threshold", "1y", "--top", "1"]).analyze().await.unwrap();
+        let sizes: Vec<u64> = results.age_breakdown.buckets.iter().map(|bucket| bucket.total_size).collect();
+        assert_eq!(sizes, [100, 0, 0, 300, 200]);
+        let stale = results.stale_files.unwrap();
+        assert_eq!((stale.threshold_secs, stale.count, stale.total_size), (365 * 86_400, 2, 500));
+        assert_eq!(stale.largest.iter().map(|file| file.path.clone()).collect::<Vec<_>>(), [root.join("last-year.bin")]);
+
+        assert!(analyzer_for(&root, &[]).analyze().await.unwrap().stale_files.is_none());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_symlinked_root_is_scanned_as_its_target() {
+        let dir = tempfile::tempdir().unwrap();
+        let base = dir.path().canonicalize().unwrap();
+        let tree = base.join("tree");
+        std::fs::create_dir_all(tree.join("a")).unwrap();
+        std::fs::create_dir_all(base.join("outside")).unwrap();
+        std::fs::write(tree.join("a/one.bin"), vec![0u8; 3_000]).unwrap();
+        std::fs::write(tree.join("two.bin"), vec![0u8; 1_000]).unwrap();
+        std::fs::write(base.join("outside/three.bin"), vec![0u8; 500]).unwrap();
+        std::os::unix::fs::symlink(base.join("outside"), tree.join("elsewhere")).unwrap();
+        let link = base.join("link");
+        std::os::unix::fs::symlink(&tree, &link).unwrap();
+
+        let summary = |results: &AnalysisResults| {
+            let info = &results.scan_info;
+            let directories: Vec<_> = results.largest_directories.iter().map(|d| (d.path.clone(), d.size)).collect();
+            let files: Vec<_> = results.largest_files.iter().map(|f| (f.path.clone(), f.size)).collect();
+            (info.path.clone(), info.total_files, info.total_directories, info.total_size, directories, files)
+        };
+        let direct = analyzer_for(&tree, &["--all"]).analyze().await.unwrap();
+        let through = analyzer_for(&link, &["--all"]).analyze().await.unwrap();
+        assert_eq!(summary(&through), summary(&direct));
+        assert_eq!(through.scan_info.path_given, link);
+        assert_eq!(through.scan_info.path_resolved, tree);
+        assert_eq!(direct.scan_info.path_given, tree);
+
+        // The flag only governs the link below the root, not the root itself
+        let followed = analyzer_for(&tree, &["--all", "--follow-links"]).analyze().await.unwrap();
+        let followed_through = analyzer_for(&link, &["--all", "--follow-links"]).analyze().await.unwrap();
+        assert_eq!(summary(&followed_through), summary(&followed));
+        assert_eq!(followed_through.scan_info.path_resolved, tree);
+        assert!(followed.largest_files.iter().any(|f| f.path == tree.join("elsewhere/three.bin")));
+        // Unfollowed, the link is an entry of its own and nothing below it is read
+        let below: Vec<_> = direct.largest_files.iter().filter(|f| f.path.starts_with(tree.join("elsewhere"))).collect();
+        assert_eq!(below.len(), 1);
+        assert_eq!((&below[0].path, below[0].symlink_target.as_ref()), (&tree.join("elsewhere"), Some(&base.join("outside"))));
+    }
+
+    #[tokio::test]
+    async fn test_auto_depth_descends_below_the_boundary() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("deep");
+        std::fs::create_dir_all(root.join("a/b/c")).unwrap();
+        std::fs::write(root.join("a/b/c/deep.bin"), vec![0u8; 5_000]).unwrap();
+        std::fs::write(root.join("a/b/loose.bin"), vec![0u8; 700]).unwrap();
+        let argv = |extra: &[&'static str]| {
+            let mut argv = vec!["diranalyzer", "--quiet", "--no-history", "--depth", "2"];
+            argv.extend_from_slice(extra);
+            argv.push(root.to_str().unwrap());
+            Args::parse_from(argv)
+        };
+
+        let limited = DirectoryAnalyzer::new(argv(&[])).unwrap().analyze().await.unwrap();
+        assert_eq!(limited.scan_info.total_size, 0);
+        assert_eq!(limited.scan_info.depth_boundary.directories, vec![root.join("a/b")]);
+        assert_eq!(limited.scan_info.depth_boundary.immediate_size, 700);
+
+        let expanded = DirectoryAnalyzer::new(argv(&["--auto-depth"])).unwrap().analyze().await.unwrap();
+        assert_eq!(expanded.scan_info.total_size, 5_700);
+        assert_eq!(expanded.scan_info.total_files, 2);
+        assert!(expanded.scan_info.depth_boundary.expanded);
+        let sizes: Vec<_> = expanded.largest_directories.iter().map(|d| (d.path.clone(), d.size)).collect();
+        assert_eq!(sizes[0], (root.clone(), 5_700));
+        assert!(sizes.contains(&(root.join("a/b/c"), 5_000)));
+    }
+
+    #[tokio::test]
+    async fn test_pinned_directories_are_listed_whatever_their_rank() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("tree");
+        for path in ["big", "logs", "a/b/c/deep", "cache"] {
+            std::fs::create_dir_all(root.join(path)).unwrap();
+        }
+        std::fs::write(root.join("big/data.bin"), vec![0u8; 50_000]).unwrap();
+        std::fs::write(root.join("logs/app.log"), vec![0u8; 300]).unwrap();
+        std::fs::write(root.join("a/b/c/deep/core.bin"), vec![0u8; 4_000]).unwrap();
+        std::fs::write(root.join("a/b/c/top.bin"), vec![0u8; 100]).unwrap();
+        std::fs::write(root.join("cache/blob"), vec![0u8; 10]).unwrap();
+        let pin = |path: &str| root.join(path).to_str().unwrap().to_string();
+        let (logs, deep, cache, missing) = (pin("logs"), pin("a/b/c/deep"), pin("cache"), pin("gone"));
+        let outside = dir.path().to_str().unwrap().to_string();
+        let argv = [
+            "diranalyzer", "--quiet", "--no-history", "--top", "1", "--depth", "3", "--min-dir-size", "1000",
+            "--exclude", "cache", "--pin", &logs, "--pin", &deep, "--pin", &cache, "--pin", &missing,
+            "--pin", &outside, "--pin", &logs, root.to_str().unwrap(),
+        ];
+
+        let results = DirectoryAnalyzer::new(Args::parse_from(argv)).unwrap().analyze().await.unwrap();
+
+        let listed: Vec<_> = results.largest_directories.iter().map(|d| (d.path.clone(), d.size, d.pinned)).collect();
+        assert_eq!(
+            listed,
+            vec![
+                (root.clone(), 50_300, false),
+                // Below the depth limit: measured on its own, without changing the totals
+                (root.join("a/b/c/deep"), 4_000, true),
+                // Under --min-dir-size, listed once although pinned twice
+                (root.join("logs"), 300, true),
+            ]
+        );
+        assert_eq!(results.scan_info.total_size, 50_300);
+        // big passes the size floor but not --top; a, a/b, and a/b/c hold nothing within the depth limit
+        assert_eq!(results.residuals.largest_directories.count, 1);
+        assert_eq!(results.directory_filter.as_ref().unwrap().hidden_directories, 3);
+
+        let warnings = &results.diagnostics.pin_warnings;
+        assert_eq!(warnings.len(), 3, "{:?}", warnings);
+        assert!(warnings[0].contains("cache") && warnings[0].contains("excluded"));
+        assert!(warnings[1].contains("gone") && warnings[1].contains("not an existing directory"));
+        assert!(warnings[2].contains("outside the scan root"));
+    }
+
+    #[tokio::test]
+    async fn test_hash_panic_degrades_results() {
+        let dir = tempfile::tempdir().unwrap();
+        write_files(dir.path());
+
+        let results = analyzer_for(dir.path(), &[]).analyze().await.unwrap();
+
+        assert!(results.is_degraded());
+        assert_eq!(results.phase_errors.len(), 2);
+        assert!(results.phase_errors.iter().all(|e| e.phase == "duplicates" && e.path.is_some()));
+        assert_eq!(results.scan_info.total_files, 4);
+        let groups = results.duplicate_groups.unwrap();
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].file_size, 2000);
+    }
+
+    #[tokio::test]
+    async fn test_own_outputs_stay_out_of_consecutive_runs() {
+        let dir = tempfile::tempdir().unwrap();
+        let tree = |name: &str| {
+            let root = dir.path().join(name);
+            std::fs::create_dir_all(root.join("data")).unwrap();
+            std::fs::write(root.join("data/blob.bin"), vec![0u8; 5_000]).unwrap();
+            root
+        };
+        let (explicit, templated) = (tree("explicit"), tree("templated"));
+        let output = explicit.join("report.json");
+        let template = format!("{}/reports/{{root}}_{{seq}}.{{ext}}", templated.display());
+
+        for (root, extra) in [(&explicit, ["--output", output.to_str().unwrap()]), (&templated, ["--output-template", template.as_str()])] {
+            for _ in 0..2 {
+                let mut argv = vec!["diranalyzer", "--quiet", "--no-history", "--export", "json"];
+                argv.extend_from_slice(&extra);
+                argv.push(root.to_str().unwrap());
+                let args = Args::parse_from(argv);
+                let results = DirectoryAnalyzer::new(args.clone()).unwrap().analyze().await.unwrap();
+
+                assert_eq!((results.scan_info.total_files, results.scan_info.total_size), (1, 5_000));
+                assert!(results.largest_files.iter().all(|file| file.path.ends_with("data/blob.bin")));
+                let naming = crate::export::naming::OutputNaming::from_args(&args);
+                crate::export::export_results(&results, args.export.as_ref().unwrap(), &args.output, &naming, args.bundle_shard_size, &WriteGuard::unrestricted())
+                    .unwrap();
+            }
+        }
+        assert!(output.exists());
+        assert_eq!(std::fs::read_dir(templated.join("reports")).unwrap().count(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_caps_stop_the_scan_and_mark_it_partial() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("tree");
+        for group in 0..10 {
+            let directory = root.join(format!("d{}", group));
+            std::fs::create_dir_all(&directory).unwrap();
+            for index in 0..10 {
+                std::fs::write(directory.join(format!("f{}.bin", index)), vec![group as u8; 1_000]).unwrap();
+            }
+        }
+
+        let analyze = |extra: &'static [&'static str]| {
+            let mut argv = vec!["diranalyzer", "--quiet", "--no-history", "--duplicates", "--min-size", "1"];
+            argv.extend_from_slice(extra);
+            argv.push(root.to_str().unwrap());
+            let mut analyzer = DirectoryAnalyzer::new(Args::parse_from(argv)).unwrap();
+            async move { analyzer.analyze().await.unwrap() }
+        };
+
+        let complete = analyze(&[]).await;
+        assert_eq!((complete.scan_info.total_files, complete.scan_info.partial), (100, None));
+
+        let by_count = analyze(&["--max-files", "25"]).await;
+        assert_eq!(by_count.scan_info.total_files, 25);
+        assert_eq!(by_count.scan_info.partial, Some(PartialScan { cap: CapKind::Files, limit: 25 }));
+        // Duplicates come from the collected files only
+        let grouped: usize = by_count.duplicate_groups.as_ref().unwrap().iter().map(|group| group.files.len()).sum();
+        assert!(grouped <= 25);
+
+        let by_size = analyze(&["--max-bytes", "42kB", "--auto-depth", "--depth", "1"]).await;
+        assert_eq!((by_size.scan_info.total_files, by_size.scan_info.total_size), (42, 42_000));
+        assert_eq!(by_size.scan_info.partial, Some(PartialScan { cap: CapKind::Bytes, limit: 42_000 }));
+
+        let json = serde_json::to_value(&by_size).unwrap();
+        assert_eq!(json["scan_info"]["partial"], serde_json::json!({ "cap": "bytes", "limit": 42_000 }));
+    }
+
+    #[tokio::test]
+    async fn test_cancellation_ends_the_run_with_partial_results() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("tree");
+        for group in 0..10 {
+            let directory = root.join(format!("d{}", group));
+            std::fs::create_dir_all(&directory).unwrap();
+            for index in 0..10 {
+                std::fs::write(directory.join(format!("f{}.bin", index)), vec![group as u8; 1_000]).unwrap();
+            }
+        }
+
+        // Cancel in the second directory, as a Ctrl-C during the walk would; its
+        // files are read together and none of them is kept
+        let cancellation = Cancellation::new();
+        let seen = std::sync::atomic::AtomicUsize::new(0);
+        let in_second_directory = {
+            let cancellation = cancellation.clone();
+            move |_: &FileEntry| {
+                if seen.fetch_add(1, std::sync::atomic::Ordering::SeqCst) == 14 {
+                    cancellation.cancel();
+                }
+                true
+            }
+        };
+        let args = Args::parse_from(["diranalyzer", "--quiet", "--no-history", "--duplicates", "--threads", "1", root.to_str().unwrap()]);
+        let mut analyzer = DirectoryAnalyzer::new(args).unwrap().with_filter(in_second_directory).with_cancellation(cancellation);
+        let results = analyzer.analyze().await.unwrap();
+
+        let collected = results.scan_info.total_files;
+        assert!((10..100).contains(&collected), "collected {} files", collected);
+        assert_eq!(results.scan_info.partial, Some(PartialScan { cap: CapKind::Interrupted, limit: collected }));
+        // Nothing is hashed once cancelled
+        assert!(results.duplicate_groups.unwrap_or_default().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_search_stops_at_the_deadline() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["a.bin", "b.bin"] {
+            std::fs::write(dir.path().join(name), vec![7u8; 4_000]).unwrap();
+        }
+        let args = Args::parse_from(["diranalyzer", "--quiet", "--no-history", "--duplicates", "--max-duration", "1h", dir.path().to_str().unwrap()]);
+
+        let complete = DirectoryAnalyzer::new(args.clone()).unwrap().analyze().await.unwrap();
+        assert_eq!(complete.scan_info.partial, None);
+        assert_eq!(complete.duplicate_groups.unwrap().len(), 1);
+
+        // The walk finishes in time, but the search finds its time already spent
+        let mut analyzer = DirectoryAnalyzer::new(args).unwrap();
+        analyzer.deadline = Some(Instant::now());
+        analyzer.duplicate_finder = analyzer.duplicate_finder.map(|finder| finder.with_deadline(Some(Instant::now())));
+        let results = analyzer.analyze().await.unwrap();
+        assert_eq!(results.scan_info.total_files, 2);
+        assert_eq!(results.scan_info.partial, Some(PartialScan { cap: CapKind::Duration, limit: 3_600 }));
+        assert!(results.duplicate_groups.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_io_latency_stats_are_opt_in() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("tree");
+        std::fs::create_dir_all(root.join("sub")).unwrap();
+        for name in ["a.bin", "sub/b.bin"] {
+            std::fs::write(root.join(name), vec![3u8; 20_000]).unwrap();
+        }
+
+        let analyze = |extra: &'static [&'static str]| {
+            let mut argv = vec!["diranalyzer", "--quiet", "--no-history", "--duplicates"];
+            argv.extend_from_slice(extra);
+            argv.push(root.to_str().unwrap());
+            let mut analyzer = DirectoryAnalyzer::new(Args::parse_from(argv)).unwrap();
+            async move { analyzer.analyze().await.unwrap() }
+        };
+
+        let plain = analyze(&[]).await;
+        assert!(plain.statistics.io_latency.is_none());
+        let json = serde_json::to_value(&plain).unwrap();
+        assert!(json["statistics"].get("io_latency").is_none());
+
+        let timed = analyze(&["--io-latency-stats"]).await;
+        let latency = timed.statistics.io_latency.as_ref().unwrap();
+        let counts: Vec<(IoOperation, u64)> =
+            latency.operations.iter().map(|entry| (entry.operation, entry.histogram.count)).collect();
+        // Root, "sub", and the two files are walked and statted; the files are opened and read to the end
+        assert_eq!(counts[..3], [(IoOperation::ReadDir, 4), (IoOperation::Stat, 4), (IoOperation::Open, 2)]);
+        assert_eq!(counts[3].0, IoOperation::Read);
+        assert!(counts[3].1 >= 2 * (20_000 / 8_192 + 1));
+        assert!(latency.slowest.iter().all(|operation| operation.path.starts_with(&root)));
+
+        let json = serde_json::to_value(&timed).unwrap();
+        assert_eq!(json["statistics"]["io_latency"]["operations"][0]["operation"], "read_dir");
+    }
+
+    #[tokio::test]
+    async fn test_stream_carries_progress_then_results() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("tree");
+        std::fs::create_dir_all(root.join("notes")).unwrap();
+        // Enough entries for the walk to look at the clock a few times
+        for index in 0..198 {
+            std::fs::write(root.join(format!("notes/{}.txt", index)), b"note").unwrap();
+        }
+        for name in ["a.bin", "b.bin"] {
+            std::fs::write(root.join(name), vec![5u8; 20_000]).unwrap();
+        }
+
+        let path = dir.path().join("run.ndjson");
+        let writes = WriteGuard::unrestricted();
+        let sink = crate::export::stream::RecordSink::create(&path, &writes).unwrap();
+        let args = Args::parse_from(["diranalyzer", "--quiet", "--no-history", "--duplicates", "--interim-every", "0", root.to_str().unwrap()]);
+        let results = DirectoryAnalyzer::new(args).unwrap().with_stream(sink.sender()).analyze().await.unwrap();
+        crate::export::finish_stream(&results, sink, &Some(path.clone()), &Default::default(), &writes).unwrap();
+
+        let lines: Vec<serde_json::Value> =
+            std::fs::read_to_string(&path).unwrap().lines().map(|line| serde_json::from_str(line).unwrap()).collect();
+        let kinds: Vec<&str> = lines.iter().map(|line| line["record"].as_str().unwrap()).collect();
+        let first_result = kinds.iter().position(|&kind| kind == "scan_info").unwrap();
+        assert!(kinds[..first_result].iter().all(|&kind| kind == "scan_progress" || kind == "hash_progress"));
+        assert!(kinds.contains(&"scan_progress"));
+
+        let scanned: Vec<u64> =
+            lines.iter().filter(|line| line["record"] == "scan_progress").map(|line| line["files"].as_u64().unwrap()).collect();
+        assert!(scanned.windows(2).all(|pair| pair[0] <= pair[1]) && scanned.iter().all(|&files| files <= 200));
+        let hashed = lines.iter().rfind(|line| line["record"] == "hash_progress").unwrap();
+        assert_eq!((hashed["hashed"].as_u64(), hashed["candidates"].as_u64()), (Some(2), Some(2)));
+
+        assert_eq!(lines[first_result]["total_files"], 200);
+        assert_eq!(kinds.iter().filter(|&&kind| kind == "duplicate_group").count(), 1);
+        assert_eq!(kinds[kinds.len() - 2..], ["statistics", "end"]);
+        assert_eq!(lines.last().unwrap()["complete"], true);
+    }
+
+    #[tokio::test]
+    async fn test_find_versions_groups_siblings_during_analysis() {
+        let dir = tempfile::tempdir().unwrap();
+        let docs = dir.path().join("docs");
+        std::fs::create_dir_all(docs.join("sub")).unwrap();
+        for (name, size) in [("plan.pdf", 400), ("plan_v2.pdf", 500), ("plan (copy).pdf", 300), ("notes.txt", 50), ("sub/plan.pdf", 400)] {
+            std::fs::write(docs.join(name), vec![b'x'; size]).unwrap();
+        }
+        let old = std::time::SystemTime::now() - Duration::from_secs(86_400);
+        std::fs::File::options().write(true).open(docs.join("plan (copy).pdf")).unwrap().set_modified(old).unwrap();
+
+        let plain = analyzer_for(dir.path(), &[]).analyze().await.unwrap();
+        assert!(plain.heuristic_versions.is_none());
+
+        let results = analyzer_for(dir.path(), &["--find-versions"]).analyze().await.unwrap();
+        let versions = results.heuristic_versions.unwrap();
+        assert_eq!(versions.groups.len(), 1);
+        let group = &versions.groups[0];
+        assert_eq!((group.directory.as_path(), group.base_name.as_str()), (docs.as_path(), "plan.pdf"));
+        assert_eq!(group.members.len(), 3);
+        assert_eq!(group.members.last().unwrap().name, "plan (copy).pdf");
+        assert_eq!(versions.superseded_files, 2);
+        assert_eq!(versions.superseded_bytes, group.members[1..].iter().map(|member| member.size).sum::<u64>());
+    }
+
+    #[tokio::test]
+    async fn test_notes_on_the_tree_become_annotations() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("tree");
+        std::fs::create_dir_all(root.join("data")).unwrap();
+        std::fs::write(root.join("data/big.bin"), vec![0u8; 1000]).unwrap();
+        let notes_file = dir.path().join("notes.json");
+        let store = NoteStore::at(notes_file.clone());
+        store.add(&root.join("data"), "owned by data team").unwrap();
+        store.add(dir.path(), "above the tree").unwrap();
+
+        let notes_arg = format!("--notes-file={}", notes_file.display());
+        let results = analyzer_for(&root, &[&notes_arg]).analyze().await.unwrap();
+        let annotations: Vec<(&Path, &str)> = results.annotations.iter().map(|a| (a.path.as_path(), a.text.as_str())).collect();
+        assert_eq!(annotations, [(root.join("data").as_path(), "owned by data team")]);
+        assert!(results.largest_directories.iter().any(|d| d.path == root.join("data")));
+
+        // A damaged notes file degrades the run instead of failing it
+        std::fs::write(&notes_file, b"{not json").unwrap();
+        let results = analyzer_for(&root, &[&notes_arg]).analyze().await.unwrap();
+        assert!(results.annotations.is_empty());
+        assert_eq!(results.phase_errors[0].phase, "notes");
+    }
+
+    #[tokio::test]
+    async fn test_accepted_findings_are_flagged_and_kept() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("tree");
+        std::fs::create_dir_all(root.join("data")).unwrap();
+        std::fs::write(root.join("data/big.bin"), vec![1u8; 4_000]).unwrap();
+        std::fs::write(root.join("copy-a.bin"), vec![2u8; 2_000]).unwrap();
+        std::fs::write(root.join("copy-b.bin"), vec![2u8; 2_000]).unwrap();
+        let accepted_file = dir.path().join("accepted.json");
+        let accepted_arg = format!("--accepted-file={}", accepted_file.display());
+        let args = [accepted_arg.as_str(), "--duplicates", "--export", "json", "--export-scope", "full"];
+
+        let before = analyzer_for(&root, &args).analyze().await.unwrap();
+        let group_id = before.duplicate_groups.as_ref().unwrap()[0].group_id.clone();
+        let store = AcceptanceStore::at(accepted_file);
+        store.accept(&AcceptedTarget::Path(root.join("data")), "supposed to be there").unwrap();
+        store.accept(&AcceptedTarget::Path(root.join("data/big.bin")), "reference data").unwrap();
+        store.accept(&AcceptedTarget::parse(&group_id), "kept on purpose").unwrap();
+
+        let results = analyzer_for(&root, &args).analyze().await.unwrap();
+        let directory = results.largest_directories.iter().find(|d| d.path == root.join("data")).unwrap();
+        assert!(directory.accepted);
+        assert!(results.largest_files.iter().all(|file| file.accepted == (file.path == root.join("data/big.bin"))));
+        assert!(results.duplicate_groups.as_ref().unwrap()[0].accepted);
+        assert_eq!((results.accepted.findings.len(), results.accepted_count(), results.accepted.shown), (3, 3, false));
+        // Accepted findings stay in the totals
+        assert_eq!((results.scan_info.total_size, results.statistics.reclaimable_bytes), (before.scan_info.total_size, 2_000));
+
+        // `report` flags the refiltered listings from the snapshot's acceptances
+        let snapshot: AnalysisResults = serde_json::from_str(&serde_json::to_string(&results).unwrap()).unwrap();
+        let refiltered = analyzer_for(&root, &["--show-accepted"]).refilter(&snapshot).await.unwrap();
+        assert_eq!((refiltered.accepted_count(), refiltered.accepted.shown), (3, true));
+    }
+
+    #[tokio::test]
+    async fn test_report_filters_recompute_a_full_snapshot() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        std::fs::create_dir_all(root.join("logs/old")).unwrap();
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        let long_ago = std::time::SystemTime::now() - Duration::from_secs(400 * 86_400);
+        for (name, size, old) in [
+            ("logs/app.log", 300, false),
+            ("logs/old/app.1.log", 700, true),
+            ("logs/old/dump.bin", 5_000, true),
+            ("src/main.rs", 2_000, false),
+            ("src/lib.rs", 50, true),
+        ] {
+            std::fs::write(root.join(name), vec![b'x'; size]).unwrap();
+            if old {
+                std::fs::File::options().write(true).open(root.join(name)).unwrap().set_modified(long_ago).unwrap();
+            }
+        }
+
+        async fn refiltered(root: &Path, snapshot: &AnalysisResults, filters: &[&str]) -> AnalysisResults {
+            analyzer_for(root, filters).refilter(snapshot).await.unwrap()
+        }
+        let size_of = |results: &AnalysisResults, path: &str| {
+            results.largest_directories.iter().find(|d| d.path == root.join(path)).map(|d| d.size)
+        };
+
+        let summary = analyzer_for(root, &[]).analyze().await.unwrap();
+        assert!(summary.entries.is_none());
+        assert!(analyzer_for(root, &["--older-than", "1y"]).refilter(&summary).await.is_err());
+
+        let snapshot = analyzer_for(root, &["--export", "json", "--export-scope", "full"]).analyze().await.unwrap();
+        // Read back as `report` reads a JSON export
+        let snapshot: AnalysisResults = serde_json::from_str(&serde_json::to_string(&snapshot).unwrap()).unwrap();
+        assert_eq!((snapshot.scan_info.total_files, snapshot.scan_info.total_size), (5, 8_050));
+
+        let unfiltered = refiltered(root, &snapshot, &[]).await;
+        assert_eq!((unfiltered.scan_info.total_files, unfiltered.scan_info.total_size), (5, 8_050));
+        assert!(unfiltered.report_filters.is_none());
+
+        let old = refiltered(root, &snapshot, &["--older-than", "1y"]).await;
+        assert_eq!((old.scan_info.total_files, old.scan_info.total_size), (3, 5_750));
+        assert_eq!((size_of(&old, "logs"), size_of(&old, "src")), (Some(5_700), Some(50)));
+        assert_eq!(old.largest_files[0].path, root.join("logs/old/dump.bin"));
+        let applied = old.report_filters.as_ref().unwrap();
+        assert_eq!((applied.filters.as_slice(), applied.matched_files, applied.collected_files), (["--older-than 1y".to_string()].as_slice(), 3, 5));
+        // The snapshot keeps every entry, so refiltered results can be refiltered again
+        assert_eq!(old.entries.as_ref().map(Vec::len), snapshot.entries.as_ref().map(Vec::len));
+
+        let logs = refiltered(root, &snapshot, &["--only", "*.log"]).await;
+        assert_eq!((logs.scan_info.total_files, logs.scan_info.total_size), (2, 1_000));
+        assert_eq!(logs.file_type_distribution.values().map(|stats| stats.count).sum::<u64>(), 2);
+        assert_eq!(size_of(&logs, "src"), Some(0));
+
+        let old_logs = refiltered(root, &snapshot, &["--only", "*.log", "--older-than", "1y"]).await;
+        assert_eq!((old_logs.scan_info.total_files, old_logs.scan_info.total_size), (1, 700));
+
+        let large = refiltered(root, &snapshot, &["--larger-than", "1KB", "--smaller-than", "3KB"]).await;
+        assert_eq!((large.scan_info.total_files, large.scan_info.total_size), (1, 2_000));
+        assert_eq!(large.size_breakdown.small_files_count, 1);
+
+        // Filtering during the run gives the same totals
+        let run = analyzer_for(root, &["--older-than", "1y"]).analyze().await.unwrap();
+        assert_eq!((run.scan_info.total_files, run.scan_info.total_size), (3, 5_750));
+        assert_eq!(run.report_filters, old.report_filters);
+    }
+
+    #[tokio::test]
+    async fn test_custom_metrics_are_tallied_in_the_analysis_pass() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("tree");
+        std::fs::create_dir_all(root.join(".cache/pip")).unwrap();
+        std::fs::create_dir_all(root.join("photos")).unwrap();
+        for (name, size) in [(".cache/pip/wheel.whl", 3_000), ("photos/a.jpg", 4_000), ("photos/b.png", 2_000), ("notes.log", 1_000)] {
+            std::fs::write(root.join(name), vec![b'x'; size]).unwrap();
+        }
+        let config = dir.path().join("metrics.json");
+        std::fs::write(&config, serde_json::json!({"custom_metrics": [
+            {"name": "cache_ratio", "expression": "size(\"**/.cache/**\") / total_size"},
+            {"name": "media_bytes", "expression": "size(type:Images) + size(type:Videos)"},
+            {"name": "media_share", "expression": "percent(media_bytes, total_size)"},
+            {"name": "average_photo", "expression": "media_bytes / count(type:Images)"},
+        ]}).to_string()).unwrap();
+        let config = config.to_str().unwrap();
+
+        let values = |results: &AnalysisResults| results.custom_metrics.iter().map(|metric| metric.value).collect::<Vec<_>>();
+        let snapshot = analyzer_for(&root, &["--config", config, "--all", "--export", "json", "--export-scope", "full"]).analyze().await.unwrap();
+        assert_eq!(values(&snapshot), [Some(0.3), Some(6_000.0), Some(60.0), Some(3_000.0)]);
+        assert_eq!(snapshot.custom_metrics[3].unit, MetricUnit::Bytes);
+
+        // Refiltered results evaluate the metrics over the files that pass
+        let snapshot: AnalysisResults = serde_json::from_str(&serde_json::to_string(&snapshot).unwrap()).unwrap();
+        let logs = analyzer_for(&root, &["--config", config, "--all", "--only", "*.log"]).refilter(&snapshot).await.unwrap();
+        assert_eq!(values(&logs), [Some(0.0), Some(0.0), Some(0.0), None]);
+
+        std::fs::write(dir.path().join("bad.json"), br#"{"custom_metrics": [{"name": "bad", "expression": "total_size + total_files"}]}"#).unwrap();
+        let args = Args::parse_from(["diranalyzer", "--config", dir.path().join("bad.json").to_str().unwrap(), root.to_str().unwrap()]);
+        let error = DirectoryAnalyzer::new(args).err().unwrap();
+        assert!(format!("{:#}", error).starts_with("Invalid custom metric \"bad\" at column 12: cannot add bytes and a count"));
+    }
+
+    #[tokio::test]
+    async fn test_data_category_is_broken_down_by_format() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("tree");
+        std::fs::create_dir_all(root.join("lake/2024")).unwrap();
+        let files = [
+            ("lake/2024/a.parquet", 6_000),
+            ("lake/2024/b.parquet", 4_000),
+            ("lake/events.parquet.snappy", 3_000),
+            ("exports/users.csv", 500),
+            ("exports/users.csv.gz", 200),
+            ("arrays.npz", 1_500),
+            ("notes.txt", 100),
+        ];
+        for (name, size) in files {
+            std::fs::create_dir_all(root.join(name).parent().unwrap()).unwrap();
+            std::fs::write(root.join(name), vec![b'x'; size]).unwrap();
+        }
+        let config = dir.path().join("data.json");
+        std::fs::write(&config, r#"{"data_formats_min_size": 10000}"#).unwrap();
+
+        let results = analyzer_for(&root, &["--config", config.to_str().unwrap()]).analyze().await.unwrap();
+        let data = &results.file_type_distribution["Data"];
+        assert_eq!((data.count, data.total_size), (6, 15_200));
+        let formats = results.data_formats.as_ref().unwrap();
+        let summary: Vec<_> = formats.iter().map(|(format, stats)| (format.as_str(), stats.count, stats.total_size)).collect();
+        assert_eq!(summary, [("csv", 1, 500), ("csv.gz", 1, 200), ("npz", 1, 1_500), ("parquet", 2, 10_000), ("parquet.snappy", 1, 3_000)]);
+        assert_eq!(formats["parquet"].largest_file.as_ref().unwrap().path, root.join("lake/2024/a.parquet"));
+
+        // Exports carry the breakdown: the JSON field and a CSV row per format
+        let json: serde_json::Value = serde_json::to_value(&results).unwrap();
+        assert_eq!(json["data_formats"]["parquet.snappy"]["total_size"], 3_000);
+        let csv_path = dir.path().join("out.csv");
+        let naming = crate::export::naming::OutputNaming::default();
+        crate::export::export_results(&results, &crate::cli::ExportFormat::Csv, &Some(csv_path.clone()), &naming, 0, &WriteGuard::unrestricted()).unwrap();
+        let csv = std::fs::read_to_string(&csv_path).unwrap();
+        assert!(csv.contains(&format!("DataFormat,{},10000,parquet,,,,,2 files,", root.join("lake/2024/a.parquet").display())), "{}", csv);
+
+        // Below the threshold the category is only a row of the distribution
+        std::fs::write(&config, r#"{"data_formats_min_size": 20000}"#).unwrap();
+        let results = analyzer_for(&root, &["--config", config.to_str().unwrap()]).analyze().await.unwrap();
+        assert_eq!(results.file_type_distribution["Data"].count, 6);
+        assert!(results.data_formats.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_ignore_files_leave_out_files_totals_and_duplicates() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("tree");
+        for directory in ["src", "target/debug", "web/dist"] {
+            std::fs::create_dir_all(root.join(directory)).unwrap();
+        }
+        std::fs::write(root.join(".gitignore"), "target/\n*.tmp\n").unwrap();
+        std::fs::write(root.join("web/.gitignore"), "dist\n").unwrap();
+        std::fs::write(root.join("src/main.rs"), vec![b'a'; 2_000]).unwrap();
+        std::fs::write(root.join("target/debug/main"), vec![b'a'; 2_000]).unwrap();
+        std::fs::write(root.join("web/dist/main.rs"), vec![b'a'; 2_000]).unwrap();
+        std::fs::write(root.join("src/scratch.tmp"), vec![b'b'; 500]).unwrap();
+        let args = ["--all", "--duplicates", "--respect-gitignore", "--no-global-gitignore"];
+
+        let results = analyzer_for(&root, &args).analyze().await.unwrap();
+        // The two .gitignore files and main.rs; the copies below ignored directories are never read
+        assert_eq!(results.scan_info.total_files, 3);
+        assert!(results.largest_directories.iter().all(|d| !d.path.starts_with(root.join("target")) && !d.path.starts_with(root.join("web/dist"))));
+        assert!(results.duplicate_groups.as_ref().unwrap().is_empty());
+        let exclusions = &results.diagnostics.exclusions;
+        assert_eq!(exclusions.ignored, Some(3));
+        let causes: Vec<_> = exclusions.causes.iter().map(|count| (count.cause.to_string(), count.entries, count.size)).collect();
+        assert_eq!(
+            causes,
+            [
+                (format!("`*.tmp` in {}", root.join(".gitignore").display()), 1, 500),
+                (format!("`target/` in {}", root.join(".gitignore").display()), 1, 0),
+                (format!("`dist` in {}", root.join("web/.gitignore").display()), 1, 0),
+            ]
+        );
+
+        // Without the flag nothing is read from the ignore files
+        let results = analyzer_for(&root, &["--all", "--duplicates"]).analyze().await.unwrap();
+        assert_eq!(results.scan_info.total_files, 6);
+        assert_eq!(results.diagnostics.exclusions.ignored, None);
+        assert_eq!(results.duplicate_groups.as_ref().unwrap()[0].files.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_file_size_range_narrows_listings_and_optionally_directories() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("tree");
+        for directory in ["a", "b"] {
+            std::fs::create_dir_all(root.join(directory)).unwrap();
+            std::fs::write(root.join(directory).join("big.bin"), vec![b'b'; 6_000]).unwrap();
+            std::fs::write(root.join(directory).join("copy.txt"), vec![b'c'; 1_500]).unwrap();
+        }
+        std::fs::write(root.join("a/mid.log"), vec![b'm'; 2_000]).unwrap();
+        let range = ["--duplicates", "--min-file-size", "2k", "--max-file-size", "6k"];
+
+        // Both bounds are inclusive; the small copies make no duplicate group, but still count in directories
+        let results = analyzer_for(&root, &range).analyze().await.unwrap();
+        assert_eq!((results.scan_info.total_files, results.scan_info.total_size), (5, 17_000));
+        assert_eq!(results.largest_directories[0].size, 17_000);
+        let mut listed: Vec<_> = results.largest_files.iter().map(|file| file.size).collect();
+        listed.sort();
+        assert_eq!(listed, [2_000, 6_000, 6_000]);
+        assert_eq!(results.file_type_distribution.values().map(|stats| stats.total_size).sum::<u64>(), 14_000);
+        let groups = results.duplicate_groups.as_ref().unwrap();
+        assert_eq!(groups.iter().map(|group| group.file_size).collect::<Vec<_>>(), [6_000]);
+        let applied = results.file_size_range.as_ref().unwrap();
+        assert_eq!((applied.affects_directories, applied.matched_files, applied.collected_files), (false, 3, 5));
+
+        let results = analyzer_for(&root, &[&range[..], &["--filter-affects-dirs"]].concat()).analyze().await.unwrap();
+        assert_eq!((results.scan_info.total_files, results.scan_info.total_size), (3, 14_000));
+        assert_eq!(results.largest_directories[0].size, 14_000);
+        assert!(results.file_size_range.unwrap().affects_directories);
+
+        let args = Args::parse_from(["diranalyzer", "--min-file-size", "1G", "--max-file-size", "50M", root.to_str().unwrap()]);
+        let error = DirectoryAnalyzer::new(args).err().unwrap();
+        assert!(error.to_string().contains("--max-file-size (50 MB) is smaller than --min-file-size (1 GB)"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn test_modification_dates_filter_files_during_the_scan() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("tree");
+        std::fs::create_dir_all(root.join("logs")).unwrap();
+        let aged = |name: &str, len: usize, date: &str| {
+            let path = root.join(name);
+            std::fs::write(&path, vec![b'x'; len]).unwrap();
+            let modified = DateTime::parse_from_rfc3339(date).unwrap();
+            std::fs::File::options().write(true).open(&path).unwrap().set_modified(modified.into()).unwrap();
+        };
+        aged("old.bin", 4_000, "2019-06-01T00:00:00Z");
+        aged("logs/2022.log", 3_000, "2022-03-15T08:00:00Z");
+        aged("logs/2023.log", 500, "2023-01-01T00:00:00Z");
+        aged("logs/skip.tmp", 2_000, "2022-08-01T00:00:00Z");
+        aged("new.bin", 1_000, "2025-02-01T00:00:00Z");
+
+        // Before is exclusive, after inclusive
+        let results = analyzer_for(&root, &["--modified-before", "2023-01-01"]).analyze().await.unwrap();
+        assert_eq!(results.scan_info.total_files, 3);
+        let results = analyzer_for(&root, &["--modified-after", "2023-01-01"]).analyze().await.unwrap();
+        assert_eq!((results.scan_info.total_files, results.scan_info.total_size), (2, 1_500));
+        let date_filter = results.scan_info.date_filter.as_ref().unwrap();
+        assert_eq!(date_filter.after, Some("2023-01-01T00:00:00Z".parse().unwrap()));
+        assert_eq!((date_filter.before, date_filter.undated_files), (None, 0));
+
+        // Combined with an exclude pattern and a size filter
+        let args = ["--modified-after", "2020-01-01T00:00:00Z", "--modified-before", "2024-01-01", "--exclude", "*.tmp", "--min-file-size", "1k"];
+        let results = analyzer_for(&root, &args).analyze().await.unwrap();
+        assert_eq!(results.largest_files.iter().map(|file| file.size).collect::<Vec<_>>(), [3_000]);
+        let exclusions = &results.diagnostics.exclusions;
+        let modified = exclusions.causes.iter().find(|count| count.cause == scanner::ExclusionCause::Modified).unwrap();
+        // The excluded temp file is counted under its pattern, which comes first
+        assert_eq!((modified.entries, modified.size), (2, 5_000));
+
+        // Relative ages are measured from now
+        let results = analyzer_for(&root, &["--modified-after", "6months"]).analyze().await.unwrap();
+        assert_eq!(results.scan_info.total_files, 0);
+        assert!(results.scan_info.date_filter.unwrap().after.unwrap() < Utc::now());
+        assert!(Args::try_parse_from(["diranalyzer", "--modified-before", "last tuesday", "."]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_include_globs_keep_matching_files_in_every_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("tree");
+        for directory in ["movies/2023", "series", "docs"] {
+            std::fs::create_dir_all(root.join(directory)).unwrap();
+        }
+        std::fs::write(root.join("movies/2023/a.mp4"), vec![b'a'; 5_000]).unwrap();
+        std::fs::write(root.join("series/a.mp4"), vec![b'a'; 5_000]).unwrap();
+        std::fs::write(root.join("series/b.mkv"), vec![b'b'; 3_000]).unwrap();
+        std::fs::write(root.join("series/sample.mkv"), vec![b's'; 400]).unwrap();
+        std::fs::write(root.join("docs/a.txt"), vec![b'a'; 5_000]).unwrap();
+        let args = ["--duplicates", "--include", "*.mp4", "--include", "*.mkv", "--exclude", "sample.*"];
+
+        let results = analyzer_for(&root, &args).analyze().await.unwrap();
+        // The excluded sample matches an include too; --exclude wins
+        assert_eq!((results.scan_info.total_files, results.scan_info.total_size), (3, 13_000));
+        // Every directory is still walked and counted, even those left without files
+        assert_eq!(results.scan_info.total_directories, 5);
+        assert_eq!(results.file_type_distribution.keys().collect::<Vec<_>>(), ["Videos"]);
+        assert_eq!(results.file_type_distribution["Videos"].total_size, 13_000);
+        assert!(results.largest_files.iter().all(|file| !file.path.ends_with("a.txt")));
+        let groups = results.duplicate_groups.as_ref().unwrap();
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].files.len(), 2);
+        let causes: Vec<_> = results.diagnostics.exclusions.causes.iter().map(|count| (count.cause.to_string(), count.entries, count.size)).collect();
+        assert_eq!(causes, [("pattern `sample.*`".to_string(), 1, 400), ("matched no --include pattern".to_string(), 1, 5_000)]);
+
+        let args = Args::parse_from(["diranalyzer", "--include", "/", root.to_str().unwrap()]);
+        let error = DirectoryAnalyzer::new(args).err().unwrap();
+        assert!(error.to_string().contains("Invalid --include glob"), "{}", error);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_disk_usage_counts_allocated_bytes() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("images");
+        std::fs::create_dir_all(root.join("vm")).unwrap();
+        std::fs::write(root.join("dense.bin"), vec![b'd'; 200_000]).unwrap();
+        // A hole with nothing written, as a fresh VM image has
+        std::fs::File::create(root.join("vm/disk.img")).unwrap().set_len(64 * 1024 * 1024).unwrap();
+
+        let apparent = analyzer_for(&root, &[]).analyze().await.unwrap();
+        assert_eq!(apparent.scan_info.total_size, 200_000 + 64 * 1024 * 1024);
+        assert!(apparent.scan_info.disk_usage.is_none());
+        assert!(apparent.largest_files[0].path.ends_with("disk.img"));
+
+        let allocated = analyzer_for(&root, &["--disk-usage"]).analyze().await.unwrap();
+        let usage = allocated.scan_info.disk_usage.clone().unwrap();
+        assert_eq!(usage.apparent_size, apparent.scan_info.total_size);
+        assert_eq!(usage.allocated_size, allocated.scan_info.total_size);
+        assert_eq!((usage.sparse_files, usage.sparse_size), (1, 64 * 1024 * 1024));
+        // The dense file now outranks the image, which holds hardly any blocks
+        assert!(allocated.largest_files[0].path.ends_with("dense.bin"));
+        let image = allocated.largest_files.iter().find(|file| file.path.ends_with("disk.img")).unwrap();
+        assert!(image.sparse && image.size < 1024 * 1024);
+        assert_eq!(image.apparent_size, Some(64 * 1024 * 1024));
+        let vm = allocated.largest_directories.iter().find(|d| d.path == root.join("vm")).unwrap();
+        assert_eq!(vm.size, image.size);
+    }
+
+    #[tokio::test]
+    async fn test_depth_bounds_are_recorded_and_checked() {
+        let dir = tempfile::tempdir().unwrap();
+        let deepest = (0..12).fold(dir.path().join("tall"), |path, level| path.join(format!("l{}", level)));
+        std::fs::create_dir_all(&deepest).unwrap();
+        std::fs::write(deepest.join("bottom.bin"), vec![b'b'; 2_000]).unwrap();
+        std::fs::write(dir.path().join("tall/top.bin"), vec![b't'; 1_000]).unwrap();
+        let root = dir.path().join("tall");
+
+        let results = analyzer_for(&root, &["--depth", "unlimited", "--min-depth", "2"]).analyze().await.unwrap();
+        assert_eq!((results.scan_info.depth_limit, results.scan_info.min_depth), (None, 2));
+        assert_eq!((results.scan_info.total_files, results.scan_info.total_size), (1, 2_000));
+        let exported = serde_json::to_value(&results.scan_info).unwrap();
+        assert_eq!(exported["depth_limit"], serde_json::Value::Null);
+
+        let args = Args::parse_from(["diranalyzer", "--depth", "3", "--min-depth", "4", root.to_str().unwrap()]);
+        let error = DirectoryAnalyzer::new(args).err().unwrap();
+        assert!(error.to_string().contains("--min-depth (4) is deeper than --depth (3)"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn test_several_roots_make_one_report_and_share_duplicates() {
+        let dir = tempfile::tempdir().unwrap();
+        let (home, srv) = (dir.path().join("home"), dir.path().join("srv"));
+        for directory in [home.join("alice"), srv.join("backup")] {
+            std::fs::create_dir_all(directory).unwrap();
+        }
+        std::fs::write(home.join("alice/photo.jpg"), vec![b'p'; 4_000]).unwrap();
+        std::fs::write(srv.join("backup/photo.jpg"), vec![b'p'; 4_000]).unwrap();
+        std::fs::write(srv.join("backup/db.sql"), vec![b'd'; 9_000]).unwrap();
+        let (home_arg, srv_arg, nested_arg) = (home.to_str().unwrap(), srv.to_str().unwrap(), home.join("alice"));
+        let args = ["diranalyzer", "--quiet", "--no-history", "--duplicates", "--min-size", "1", home_arg, srv_arg, nested_arg.to_str().unwrap()];
+
+        let results = DirectoryAnalyzer::new(Args::parse_from(args)).unwrap().analyze().await.unwrap();
+        // The root inside another is walked once, with the outer one
+        assert_eq!(results.scan_info.roots, [home.clone(), srv.clone()]);
+        assert_eq!((results.scan_info.total_files, results.scan_info.total_size), (3, 17_000));
+        assert_eq!(results.scan_info.total_directories, 4);
+        let groups = results.duplicate_groups.as_ref().unwrap();
+        assert_eq!(groups.len(), 1);
+        let mut copies = groups[0].files.clone();
+        copies.sort();
+        assert_eq!(copies, [home.join("alice/photo.jpg"), srv.join("backup/photo.jpg")]);
+        let roots: Vec<_> = results.largest_directories.iter().map(|d| (d.path.clone(), d.size, d.root)).collect();
+        assert_eq!(roots, [(srv.clone(), 13_000, 1), (srv.join("backup"), 13_000, 1), (home.clone(), 4_000, 0), (home.join("alice"), 4_000, 0)]);
+
+        let inventory = ["diranalyzer", "--source", "s3-inventory", home_arg, srv_arg];
+        let error = DirectoryAnalyzer::new(Args::parse_from(inventory)).err().unwrap();
+        assert!(error.to_string().contains("Several roots can only be walked on the local filesystem"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn test_listed_paths_are_roots_and_missing_ones_are_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let (a, b, gone) = (dir.path().join("a"), dir.path().join("b"), dir.path().join("gone"));
+        for (root, len) in [(&a, 1_000), (&b, 2_000)] {
+            std::fs::create_dir_all(root).unwrap();
+            std::fs::write(root.join("data.bin"), vec![b'x'; len]).unwrap();
+        }
+        let list = dir.path().join("roots.txt");
+        std::fs::write(&list, format!("# from find\n{}\n\n{}\n{}\n", a.display(), gone.display(), b.display())).unwrap();
+        let analyze = |option: &str, list: &Path| {
+            let args = Args::parse_from(["diranalyzer", "--quiet", "--no-history", option, list.to_str().unwrap()]);
+            DirectoryAnalyzer::new(args)
+        };
+
+        // PATH left at its default is not scanned along with the list
+        let results = analyze("--paths-from", &list).unwrap().analyze().await.unwrap();
+        assert_eq!(results.scan_info.roots, [a.clone(), b.clone()]);
+        assert_eq!((results.scan_info.total_files, results.scan_info.total_size), (2, 3_000));
+        assert_eq!(results.scan_info.error_count, 1);
+        let error = &results.scan_errors.errors[0];
+        assert_eq!((&error.path, error.error_type), (&gone, ErrorType::FileNotFound));
+
+        std::fs::write(&list, format!("{}\0{}\0", b.display(), a.display())).unwrap();
+        let results = analyze("--paths-from0", &list).unwrap().analyze().await.unwrap();
+        assert_eq!(results.scan_info.roots, [b.clone(), a.clone()]);
+
+        std::fs::write(&list, format!("{}\n", gone.display())).unwrap();
+        let error = analyze("--paths-from", &list).err().unwrap();
+        assert!(error.to_string().contains("None of the paths listed in"), "{}", error);
+        assert!(analyze("--paths-from", &dir.path().join("missing.txt")).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_hidden_directories_are_pruned_with_everything_below() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("tree");
+        for directory in [".cache/foo", "src/.git/objects", "docs"] {
+            std::fs::create_dir_all(root.join(directory)).unwrap();
+        }
+        // Only the hidden directories' names start with a dot, not the files below them
+        std::fs::write(root.join(".cache/foo/huge.bin"), vec![b'h'; 50_000]).unwrap();
+        std::fs::write(root.join("src/.git/objects/pack"), vec![b'p'; 20_000]).unwrap();
+        std::fs::write(root.join(".cache/foo/readme.txt"), vec![b'r'; 3_000]).unwrap();
+        std::fs::write(root.join("docs/readme.txt"), vec![b'r'; 3_000]).unwrap();
+        std::fs::write(root.join("src/main.rs"), vec![b'm'; 1_000]).unwrap();
+
+        let results = analyzer_for(&root, &["--duplicates"]).analyze().await.unwrap();
+        assert_eq!((results.scan_info.total_files, results.scan_info.total_size), (2, 4_000));
+        let listed: Vec<_> = results.largest_directories.iter().map(|d| d.path.strip_prefix(&root).unwrap().to_path_buf()).collect();
+        assert!(listed.iter().all(|path| path.components().all(|c| !c.as_os_str().to_string_lossy().starts_with('.'))), "{:?}", listed);
+        assert!(results.largest_files.iter().all(|f| f.path.starts_with(root.join("docs")) || f.path.starts_with(root.join("src"))));
+        assert!(results.duplicate_groups.as_ref().unwrap().is_empty());
+        // Each hidden directory is one pruned entry; nothing below it was read
+        assert_eq!(results.diagnostics.exclusions.hidden, 2);
+
+        let results = analyzer_for(&root, &["--all", "--duplicates"]).analyze().await.unwrap();
+        assert_eq!((results.scan_info.total_files, results.scan_info.total_size), (5, 77_000));
+        assert!(results.largest_directories.iter().any(|d| d.path == root.join(".cache")));
+        assert_eq!(results.duplicate_groups.as_ref().unwrap()[0].files.len(), 2);
+    }
+
+    /// Type distribution, largest files, and per-directory compositions
+    type Aggregates = (HashMap<String, TypeStats>, Vec<FileInfo>, Vec<BTreeMap<String, u64>>);
+
+    /// The aggregation as it was before categories were interned, keyed by name throughout
+    fn string_keyed(
+        classifier: &FileTypeClassifier,
+        files: &[FileEntry],
+        listed: &[PathBuf],
+        top_count: usize,
+        hide_regenerable: bool,
+    ) -> Aggregates {
+        let mut distribution: HashMap<String, TypeStats> = HashMap::new();
+        let mut largest_files = Vec::new();
+        let mut compositions = vec![BTreeMap::new(); listed.len()];
+        for file in files {
+            let file_type = classifier.classify(&file.path);
+            for (index, dir) in listed.iter().enumerate() {
+                if file.path.starts_with(dir) && file.path != *dir {
+                    *compositions[index].entry(file_type.clone()).or_default() += file.size;
+                }
+            }
+            let info = FileInfo {
+                path: file.path.clone(),
+                size: file.size,
+                file_type: file_type.clone(),
+                modified: file.modified,
+                created: file.created,
+                regenerable: classifier.is_regenerable(&file.path),
+                preview: None,
+                accepted: false,
+                symlink_target: file.symlink_target.clone(),
+                apparent_size: None,
+                sparse: false,
+            };
+            let stats = distribution.entry(file_type).or_insert(TypeStats { count: 0, total_size: 0, average_size: 0, largest_file: None });
+            stats.count += 1;
+            stats.total_size += file.size;
+            stats.average_size = stats.total_size / stats.count;
+            let larger = match &stats.largest_file {
+                Some(largest) => (file.size, &largest.path) > (largest.size, &file.path),
+                None => file.size > 0,
+            };
+            if larger {
+                stats.largest_file = Some(info.clone());
+            }
+            if !(info.regenerable && hide_regenerable) {
+                largest_files.push(info);
+            }
+        }
+        largest_files.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.path.cmp(&b.path)));
+        largest_files.truncate(top_count);
+        (distribution, largest_files, compositions)
+    }
+
+    #[tokio::test]
+    async fn test_interned_types_report_as_names_did() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("tree");
+        for (name, size) in [
+            ("src/main.rs", 900),
+            ("src/lib.RS", 900),
+            ("src/build/out.o", 4_000),
+            ("node_modules/pkg/index.js", 700),
+            ("data/2024/a.json", 300),
+            ("data/2024/b.JSON", 300),
+            ("data/readme.txt", 120),
+            ("media/clip.mp4", 8_000),
+            ("media/empty.png", 0),
+            ("media/noext", 50),
+            ("media/__pycache__/m.pyc", 60),
+        ] {
+            std::fs::create_dir_all(root.join(name).parent().unwrap()).unwrap();
+            std::fs::write(root.join(name), vec![b'x'; size]).unwrap();
+        }
+        let config = dir.path().join("config.json");
+        std::fs::write(&config, br#"{"classification_rules": [{"path": "data/**", "extensions": ["json"], "category": "Data"}]}"#).unwrap();
+
+        for extra in [&[][..], &["--hide-regenerable", "--top", "3"][..]] {
+            let mut argv = vec!["--export", "json", "--export-scope", "full", "--config", config.to_str().unwrap()];
+            argv.extend_from_slice(extra);
+            let mut analyzer = analyzer_for(&root, &argv);
+            let results = analyzer.analyze().await.unwrap();
+
+            let files = ScanResults::from_entries(results.entries.as_deref().unwrap(), 1).files;
+            let listed: Vec<PathBuf> = results.largest_directories.iter().map(|dir| dir.path.clone()).collect();
+            let (distribution, largest_files, compositions) =
+                string_keyed(&analyzer.classifier, &files, &listed, analyzer.args.top_count, analyzer.args.hide_regenerable);
+
+            // Name-keyed maps serialize sorted, so equal values mean equal reports
+            assert_eq!(serde_json::to_value(&results.file_type_distribution).unwrap(), serde_json::to_value(&distribution).unwrap());
+            assert_eq!(serde_json::to_value(&results.largest_files).unwrap(), serde_json::to_value(&largest_files).unwrap());
+            let listed_compositions: Vec<_> = results.largest_directories.iter().map(|dir| dir.composition.clone()).collect();
+            assert_eq!(listed_compositions, compositions);
+            assert_eq!(results.file_type_distribution["Data"].count, 2);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_low_memory_run_reports_what_a_full_run_does() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("tree");
+        for (name, size) in [
+            ("src/main.rs", 900),
+            ("src/build/out.o", 4_000),
+            ("src/build/deep/more/out2.o", 4_000),
+            ("data/a.json", 300),
+            ("data/b.csv", 300),
+            ("media/clip.mp4", 8_000),
+            ("media/empty.png", 0),
+            ("media/copy.mp4", 8_000),
+        ] {
+            std::fs::create_dir_all(root.join(name).parent().unwrap()).unwrap();
+            std::fs::write(root.join(name), vec![b'x'; size]).unwrap();
+        }
+        let common = ["--top", "3", "--depth", "2", "--auto-depth", "--disk-usage", "--min-file-size", "100", "--duplicates", "--find-versions"];
+
+        let full = analyzer_for(&root, &common).analyze().await.unwrap();
+        let low_memory = analyzer_for(&root, &[&common[..], &["--low-memory"]].concat()).analyze().await.unwrap();
+
+        fn json(value: &impl Serialize) -> serde_json::Value {
+            serde_json::to_value(value).unwrap()
+        }
+        assert_eq!(json(&low_memory.file_type_distribution), json(&full.file_type_distribution));
+        assert_eq!(json(&low_memory.largest_files), json(&full.largest_files));
+        assert_eq!(json(&low_memory.size_breakdown), json(&full.size_breakdown));
+        assert_eq!(json(&low_memory.owners), json(&full.owners));
+        assert_eq!(json(&low_memory.age_breakdown), json(&full.age_breakdown));
+        assert_eq!(json(&low_memory.residuals), json(&full.residuals));
+        assert_eq!(low_memory.scan_info.disk_usage, full.scan_info.disk_usage);
+        assert_eq!(low_memory.file_size_range, full.file_size_range);
+        let directories = |results: &AnalysisResults| results.largest_directories.iter().map(|d| (d.path.clone(), d.size, d.file_count)).collect::<Vec<_>>();
+        assert_eq!(directories(&low_memory), directories(&full));
+        assert_eq!((low_memory.scan_info.total_files, low_memory.scan_info.total_size), (full.scan_info.total_files, full.scan_info.total_size));
+
+        // What needs every file at once is skipped
+        assert!(full.duplicate_groups.as_ref().is_some_and(|groups| !groups.is_empty()));
+        assert!(low_memory.duplicate_groups.is_none() && low_memory.heuristic_versions.is_none());
+        assert!(low_memory.phase_errors.is_empty());
+
+        let full_export = Args::parse_from(["diranalyzer", "--low-memory", "--export", "json", "--export-scope", "full", root.to_str().unwrap()]);
+        let error = DirectoryAnalyzer::new(full_export).err().unwrap();
+        assert!(error.to_string().contains("--low-memory"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn test_min_dir_size_only_filters_listings() {
+        let dir = tempfile::tempdir().unwrap();
+        // Temp dirs are dot-named, so scan a visible subdirectory
+        let root = dir.path().join("tree");
+        std::fs::create_dir_all(root.join("big")).unwrap();
+        std::fs::write(root.join("big/data.bin"), vec![0u8; 50_000]).unwrap();
+        for name in ["s1", "s2", "s3"] {
+            std::fs::create_dir(root.join(name)).unwrap();
+            std::fs::write(root.join(name).join("note.txt"), vec![0u8; 100]).unwrap();
+        }
+
+        let analyze = |extra: &'static [&'static str]| {
+            let mut argv = vec!["diranalyzer", "--quiet", "--no-history"];
+            argv.extend_from_slice(extra);
+            argv.push(root.to_str().unwrap());
+            let mut analyzer = DirectoryAnalyzer::new(Args::parse_from(argv)).unwrap();
+            async move { analyzer.analyze().await.unwrap() }
+        };
+        let unfiltered = analyze(&[]).await;
+        let filtered = analyze(&["--min-dir-size", "10kB"]).await;
+
+        assert_eq!(filtered.scan_info.total_size, unfiltered.scan_info.total_size);
+        assert_eq!(filtered.scan_info.total_directories, unfiltered.scan_info.total_directories);
+        // The root and "big" both hold the 50 kB file; the three small directories are hidden
+        assert_eq!(unfiltered.largest_directories.len(), 5);
+        assert_eq!(filtered.largest_directories.len(), 2);
+        assert_eq!(filtered.largest_directories[0].size, 50_300);
+        let composition: Vec<_> = filtered.largest_directories[0].composition.iter().map(|(name, size)| (name.as_str(), *size)).collect();
+        assert_eq!(composition, [("Documents", 300), ("Executables", 50_000)]);
+        let filter = filtered.directory_filter.unwrap();
+        assert_eq!((filter.min_size, filter.hidden_directories), (10_000, 3));
+        assert!(unfiltered.directory_filter.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_residuals_account_for_truncated_listings() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("tree");
+        for (i, name) in ["a", "b", "c", "d"].iter().enumerate() {
+            std::fs::create_dir_all(root.join(name)).unwrap();
+            for j in 0..3 {
+                std::fs::write(root.join(name).join(format!("{}.bin", j)), vec![0u8; 1000 * (i + 1) + j]).unwrap();
+            }
+        }
+
+        let analyze = |top: &'static str| {
+            let argv = ["diranalyzer", "--quiet", "--no-history", "--top", top, root.to_str().unwrap()];
+            let mut analyzer = DirectoryAnalyzer::new(Args::parse_from(argv)).unwrap();
+            async move { analyzer.analyze().await.unwrap() }
+        };
+        let bounded = analyze("2").await;
+        let unbounded = analyze("100").await;
+
+        let files = &bounded.residuals.largest_files;
+        assert_eq!(bounded.largest_files.len(), 2);
+        assert_eq!(files.count + 2, bounded.scan_info.total_files);
+        assert_eq!(bounded.largest_files.iter().map(|f| f.size).sum::<u64>() + files.size, bounded.scan_info.total_size);
+
+        let directories = &bounded.residuals.largest_directories;
+        assert_eq!(directories.count + 2, unbounded.largest_directories.len() as u64);
+        assert_eq!(
+            bounded.largest_directories.iter().map(|d| d.size).sum::<u64>() + directories.size,
+            unbounded.largest_directories.iter().map(|d| d.size).sum::<u64>()
+        );
+        assert_eq!(unbounded.residuals.largest_files, Residual::default());
+    }
+
+    #[tokio::test]
+    async fn test_bounded_directories_list_what_all_directories_list() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("tree");
+        // Three levels of four directories; equal sizes in every subtree make ties the listing breaks by path
+        for a in 0..4 {
+            for b in 0..4 {
+                let leaf = root.join(format!("a{}", a)).join(format!("b{}", b));
+                std::fs::create_dir_all(&leaf).unwrap();
+                std::fs::write(leaf.join("data.bin"), vec![0u8; 100 * (a % 2 + 1) + 10 * b]).unwrap();
+            }
+        }
+        std::fs::write(dir.path().join("all.json"), br#"{"retain_all_directories": true}"#).unwrap();
+
+        let analyze = |config: Option<&str>| {
+            let pin = root.join("a0/b0");
+            let mut argv = vec!["diranalyzer", "--quiet", "--no-history", "--top", "4", "--min-dir-size", "150", "--pin"];
+            argv.push(pin.to_str().unwrap());
+            let config = config.map(|name| dir.path().join(name));
+            if let Some(config) = &config {
+                argv.extend(["--config", config.to_str().unwrap()]);
+            }
+            argv.push(root.to_str().unwrap());
+            let mut analyzer = DirectoryAnalyzer::new(Args::parse_from(argv)).unwrap();
+            async move { analyzer.analyze().await.unwrap() }
+        };
+        let bounded = analyze(None).await;
+        let all = analyze(Some("all.json")).await;
+
+        let listing = |results: &AnalysisResults| {
+            results
+                .largest_directories
+                .iter()
+                .map(|d| (d.path.clone(), d.size, d.file_count, d.subdirectory_count, d.pinned, d.composition.clone()))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(listing(&bounded), listing(&all));
+        assert_eq!(bounded.largest_directories.len(), 5, "the top four and the pin");
+        assert_eq!(bounded.residuals.largest_directories, all.residuals.largest_directories);
+        assert!(all.residuals.largest_directories.count > 0);
+        let hidden = |results: &AnalysisResults| results.directory_filter.as_ref().unwrap().hidden_directories;
+        assert_eq!(hidden(&bounded), hidden(&all));
+        assert!(hidden(&all) > 0);
+        assert_eq!(bounded.scan_info.total_directories, all.scan_info.total_directories);
+    }
+
+    #[tokio::test]
+    async fn test_assumed_clock_skew_corrects_ages_but_not_mtimes() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("nfs");
+        std::fs::create_dir_all(&root).unwrap();
+        // Written just now by a server whose clock runs three days ahead
+        let stamped = std::time::SystemTime::now() + Duration::from_secs(3 * 86_400);
+        for i in 0..12 {
+            let file = std::fs::File::create(root.join(format!("{}.dat", i))).unwrap();
+            file.set_len(2_000).unwrap();
+            file.set_modified(stamped).unwrap();
+        }
+
+        let analyze = |extra: &'static [&'static str]| {
+            let mut argv = vec!["diranalyzer", "--quiet", "--no-history", "--entropy-sample", "--entropy-window", "1"];
+            argv.extend_from_slice(extra);
+            argv.push(root.to_str().unwrap());
+            let mut analyzer = DirectoryAnalyzer::new(Args::parse_from(argv)).unwrap();
+            async move { analyzer.analyze().await.unwrap() }
+        };
+
+        let raw = analyze(&[]).await;
+        let skew = raw.scan_info.clock_skew.unwrap();
+        assert_eq!((skew.files_ahead, skew.files_dated), (12, 12));
+        assert!(skew.median_ahead_secs.abs_diff(3 * 86_400) < 60);
+        // Future files all look recent
+        assert_eq!(raw.entropy.unwrap().sampled_files, 12);
+
+        // Corrected by more than the skew, they are older than the one-day window
+        let corrected = analyze(&["--assume-clock-skew", "100h"]).await;
+        assert_eq!(corrected.scan_info.assumed_clock_skew_secs, Some(360_000));
+        // Detection still reports what the filesystem says
+        assert_eq!(corrected.scan_info.clock_skew.map(|skew| skew.files_ahead), Some(12));
+        assert_eq!(corrected.entropy.unwrap().sampled_files, 0);
+        assert_eq!(corrected.largest_files[0].modified, raw.largest_files[0].modified);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_duplicate_statistics_count_redundant_copies() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("tree");
+        std::fs::create_dir_all(&root).unwrap();
+        // Three names for two inodes of one content, and a plain pair of another
+        std::fs::write(root.join("a.bin"), vec![b'a'; 4000]).unwrap();
+        std::fs::hard_link(root.join("a.bin"), root.join("a-link.bin")).unwrap();
+        std::fs::write(root.join("a-copy.bin"), vec![b'a'; 4000]).unwrap();
+        std::fs::write(root.join("b.bin"), vec![b'b'; 1000]).unwrap();
+        std::fs::write(root.join("b-copy.bin"), vec![b'b'; 1000]).unwrap();
+
+        let args = Args::parse_from(["diranalyzer", "--quiet", "--no-history", "--duplicates", "--min-size", "1", root.to_str().unwrap()]);
+        let results = DirectoryAnalyzer::new(args).unwrap().analyze().await.unwrap();
+
+        let statistics = &results.statistics;
+        // The hard link is no copy: each group keeps one name of its inode
+        assert_eq!((statistics.duplicate_groups, statistics.redundant_files), (2, 2));
+        assert_eq!(statistics.reclaimable_bytes, 5000);
+        let linked = results.duplicate_groups.iter().flatten().find(|group| group.file_size == 4000).unwrap();
+        assert_eq!(linked.files, [root.join("a-copy.bin"), root.join("a-link.bin")]);
+
+        // Exports of earlier versions name the fields after what they used to count
+        let mut old = serde_json::to_value(statistics).unwrap();
+        let fields = old.as_object_mut().unwrap();
+        fields.remove("redundant_files");
+        fields.remove("reclaimable_bytes");
+        fields.insert("duplicate_files".into(), 5.into());
+        fields.insert("wasted_space".into(), 9000.into());
+        let old: Statistics = serde_json::from_value(old).unwrap();
+        assert_eq!((old.redundant_files, old.reclaimable_bytes), (5, 9000));
+    }
+
+    #[tokio::test]
+    async fn test_created_after_keeps_files_born_since() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("new.txt"), b"fresh").unwrap();
+        let analyze = |since: &'static str| {
+            let args = Args::parse_from(["diranalyzer", "--quiet", "--no-history", "--created-after", since, dir.path().to_str().unwrap()]);
+            async move { DirectoryAnalyzer::new(args).unwrap().analyze().await.unwrap() }
+        };
+
+        let past = analyze("2000-01-01").await;
+        let date_filter = past.scan_info.date_filter.clone().unwrap();
+        assert_eq!(date_filter.created_after, Some("2000-01-01T00:00:00Z".parse().unwrap()));
+        let Some(created) = past.largest_files.first().and_then(|file| file.created) else {
+            // Without birth times the file is left out, not an error
+            assert_eq!(date_filter.undated_files, 1);
+            assert!(past.largest_files.is_empty() && past.scan_info.error_count == 0);
+            return;
+        };
+        assert!(created <= Utc::now());
+        assert_eq!(date_filter.undated_files, 0);
+
+        let future = analyze("2999-01-01").await;
+        assert!(future.largest_files.is_empty());
+        assert_eq!(future.scan_info.date_filter.unwrap().undated_files, 0);
+    }
+
+    #[tokio::test]
+    async fn test_regenerable_split_of_mixed_tree() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("project");
+        for sub in ["src", "backup", "build", "build-old", "__pycache__"] {
+            std::fs::create_dir_all(root.join(sub)).unwrap();
+        }
+        // One actionable duplicate pair, one regenerable pair, and a lone cache file
+        std::fs::write(root.join("src/main.c"), vec![b'c'; 3000]).unwrap();
+        std::fs::write(root.join("backup/main.c"), vec![b'c'; 3000]).unwrap();
+        std::fs::write(root.join("build/main.o"), vec![b'o'; 5000]).unwrap();
+        std::fs::write(root.join("build-old/main.o"), vec![b'o'; 5000]).unwrap();
+        std::fs::write(root.join("__pycache__/util.cpython-312.pyc"), vec![b'p'; 700]).unwrap();
+
+        let analyze = |extra: &'static [&'static str]| {
+            let mut argv = vec!["diranalyzer", "--quiet", "--no-history", "--duplicates", "--min-size", "1"];
+            argv.extend_from_slice(extra);
+            argv.push(root.to_str().unwrap());
+            let mut analyzer = DirectoryAnalyzer::new(Args::parse_from(argv)).unwrap();
+            async move { analyzer.analyze().await.unwrap() }
+        };
+        let results = analyze(&[]).await;
+
+        let regenerable = &results.regenerable;
+        assert_eq!((regenerable.files, regenerable.size), (3, 10_700));
+        assert_eq!(results.scan_info.total_size - regenerable.size, 6_000);
+        assert_eq!((regenerable.duplicate_groups, regenerable.wasted_space), (1, 5_000));
+        assert_eq!(results.statistics.reclaimable_bytes - regenerable.wasted_space, 3_000);
+        assert!(!regenerable.hidden);
+        assert_eq!(results.largest_files.iter().filter(|f| f.regenerable).count(), 3);
+
+        let hidden = analyze(&["--hide-regenerable"]).await;
+        assert!(hidden.regenerable.hidden);
+        assert_eq!(hidden.regenerable.size, regenerable.size, "totals are unaffected");
+        assert_eq!(hidden.scan_info.total_size, results.scan_info.total_size);
+        assert_eq!(hidden.largest_files.len(), 2);
+        assert!(hidden.largest_files.iter().all(|f| !f.regenerable));
+        assert_eq!(hidden.duplicate_groups.unwrap().len(), 2, "groups stay in the results");
+    }
+
+    struct FileCount;
+
+    impl SectionProvider for FileCount {
+        fn section(&self, results: &AnalysisResults) -> Option<ExtensionSection> {
+            Some(ExtensionSection::new("Files").sized_row("scanned", results.scan_info.total_files.to_string(), results.scan_info.total_size))
+        }
+    }
+
+    struct Broken;
+
+    impl SectionProvider for Broken {
+        fn section(&self, _results: &AnalysisResults) -> Option<ExtensionSection> {
+            panic!("no team mapping")
+        }
+    }
+
+    #[tokio::test]
+    async fn test_section_providers_see_the_results() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("tree");
+        std::fs::create_dir_all(&root).unwrap();
+        write_files(&root);
+
+        let results = analyzer_for(&root, &[])
+            .with_section_provider(Broken)
+            .with_section_provider(FileCount)
+            .analyze()
+            .await
+            .unwrap();
+
+        assert_eq!(results.extensions, [ExtensionSection::new("Files").sized_row("scanned", "4", 10_000)]);
+        // A failing provider loses its section, not the run
+        let error = results.phase_errors.iter().find(|error| error.phase == "extensions").unwrap();
+        assert_eq!(error.message, "panic: no team mapping");
+    }
+
+    #[tokio::test]
+    async fn test_phase_panic_is_contained() {
+        let mut errors = Vec::new();
+        let outcome: Option<()> = guard_phase("analysis", false, &mut errors, async { panic!("boom") })
+            .await
+            .unwrap();
+
+        assert!(outcome.is_none());
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].phase, "analysis");
+        assert_eq!(errors[0].message, "panic: boom");
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "boom")]
+    async fn test_strict_phase_panics() {
+        let mut errors = Vec::new();
+        let _: Option<()> = guard_phase("analysis", true, &mut errors, async { panic!("boom") })
+            .await
+            .unwrap();
+    }
+
+    #[test]
+    fn test_concurrent_analyses_share_the_hash_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("tree");
+        for project in 0..4u8 {
+            let path = root.join(format!("project-{}", project));
+            std::fs::create_dir_all(&path).unwrap();
+            for copy in ["a.bin", "b.bin", "c.bin"] {
+                std::fs::write(path.join(copy), vec![project; 4_000]).unwrap();
+            }
+            std::fs::write(path.join("unique.bin"), vec![project + 100; 4_000]).unwrap();
+        }
+        let cache_dir = dir.path().join("hashes");
+        let analyze = |path: PathBuf| {
+            let args = Args::parse_from([
+                "diranalyzer", "--quiet", "--no-history", "--duplicates", "--min-size", "1",
+                "--hash-cache-dir", cache_dir.to_str().unwrap(), path.to_str().unwrap(),
+            ]);
+            let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build().unwrap();
+            runtime.block_on(DirectoryAnalyzer::new(args).unwrap().analyze()).unwrap()
+        };
+
+        // Whole-tree and per-project runs overlap on every file
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..8)
+                .map(|run| {
+                    let path = if run % 2 == 0 { root.clone() } else { root.join(format!("project-{}", run / 2)) };
+                    let analyze = &analyze;
+                    scope.spawn(move || (0..3).map(|_| analyze(path.clone())).collect::<Vec<_>>())
+                })
+                .collect();
+            for (run, handle) in handles.into_iter().enumerate() {
+                for results in handle.join().unwrap() {
+                    let expected = if run % 2 == 0 { 4 } else { 1 };
+                    assert_eq!(results.duplicate_groups.unwrap().len(), expected);
+                    assert!(results.diagnostics.hash_cache.is_some());
+                }
+            }
+        });
+
+        // Every hash any run stored is there for the next one
+        let results = analyze(root.clone());
+        let usage = results.diagnostics.hash_cache.unwrap();
+        assert_eq!((usage.lookups, usage.hits, usage.stored), (16, 16, 0));
+        assert_eq!(crate::hash_cache::load_runs(&cache_dir).unwrap().len(), 25);
+    }
+}
@@ -0,0 +1,380 @@
+//! What the analysis adds up from the files, one file at a time
+//!
+//! [`FileListings`] is the analysis pass over the files every run makes:
+//! the size breakdown, the file types, the regenerable totals, the custom
+//! metrics, and the largest files. The listings only ever hold their `--top`
+//! entries, so the pass needs no more memory than its results.
+//!
+//! With `--low-memory` the walk keeps no files at all. [`FileFold`] takes each
+//! one as the walk hands it out and folds it into everything the run reports
+//! of the files, including what is otherwise computed before the size range,
+//! such as the owners and the age buckets. What needs every file at once is
+//! left out: duplicate detection, entropy sampling, version grouping, and the
+//! file types of the listed directories, which are only known once the walk
+//! is done. Ages are counted from the start of the scan instead of its end.
+
+use super::{FileInfo, OwnerStats, RegenerableTotals, Residual, TypeStats, XattrFile};
+use crate::ages::{AgeBreakdown, AgeSource, StaleFile, StaleFiles};
+use crate::cli::Args;
+use crate::metrics::{MetricSet, MetricTally};
+use crate::refilter::{AppliedSizeRange, SizeRange};
+use crate::scanner::{self, DiskUsage, FileEntry};
+use crate::security::SecurityAudit;
+use crate::utils::time::{self, ClockSkew, SkewTally};
+use crate::utils::{self, FileTypeClassifier, SizeBreakdown, TypeId};
+use chrono::{DateTime, Utc};
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BTreeMap, HashMap};
+use std::path::PathBuf;
+
+/// The first `limit` items offered, in `order`, with what the others add up to
+struct Largest<T> {
+    limit: usize,
+    order: fn(&T, &T) -> Ordering,
+    size: fn(&T) -> u64,
+    /// Sorted by `order`
+    items: Vec<T>,
+    rest: Residual,
+}
+
+impl<T> Largest<T> {
+    fn new(limit: usize, order: fn(&T, &T) -> Ordering, size: fn(&T) -> u64) -> Self {
+        Self { limit, order, size, items: Vec::new(), rest: Residual::default() }
+    }
+
+    /// Whether an item would be kept, given whether it comes before the last one kept
+    ///
+    /// Lets the caller skip building an item the listing would drop at once.
+    fn admits(&self, before_last: impl FnOnce(&T) -> bool) -> bool {
+        self.items.len() < self.limit || self.items.last().is_some_and(before_last)
+    }
+
+    /// Keep `item` if it is among the first `limit`; an item equal to a kept one goes after it
+    fn offer(&mut self, item: T) {
+        let at = self.items.partition_point(|kept| (self.order)(kept, &item) != Ordering::Greater);
+        if at >= self.limit {
+            self.count_out((self.size)(&item));
+            return;
+        }
+        self.items.insert(at, item);
+        if self.items.len() > self.limit {
+            if let Some(dropped) = self.items.pop() {
+                self.count_out((self.size)(&dropped));
+            }
+        }
+    }
+
+    /// Count an item of `size` that is not kept
+    fn count_out(&mut self, size: u64) {
+        self.rest.count += 1;
+        self.rest.size += size;
+    }
+}
+
+/// A collected file with its category, kept for a listing
+#[derive(Clone)]
+struct KeptFile {
+    entry: FileEntry,
+    /// What the totals count of it, see [`FileEntry::counted_size`]
+    size: u64,
+    type_id: TypeId,
+    regenerable: bool,
+}
+
+impl KeptFile {
+    fn info(&self, classifier: &FileTypeClassifier) -> FileInfo {
+        FileInfo {
+            path: self.entry.path.clone(),
+            size: self.size,
+            file_type: classifier.type_name(self.type_id).to_string(),
+            modified: self.entry.modified,
+            created: self.entry.created,
+            regenerable: self.regenerable,
+            preview: None,
+            accepted: false,
+            symlink_target: self.entry.symlink_target.clone(),
+            apparent_size: self.entry.disk_usage.map(|_| self.entry.size),
+            sparse: self.entry.is_sparse(),
+        }
+    }
+
+    /// Largest first, ties by path, so that the listing does not depend on the walk order
+    fn order(a: &KeptFile, b: &KeptFile) -> Ordering {
+        b.size.cmp(&a.size).then_with(|| a.entry.path.cmp(&b.entry.path))
+    }
+}
+
+/// [`TypeStats`] as they add up, with a copy of the largest file
+#[derive(Default)]
+struct TypeTally {
+    count: u64,
+    total_size: u64,
+    largest: Option<KeptFile>,
+}
+
+impl TypeTally {
+    fn add(&mut self, file: &FileEntry, size: u64, type_id: TypeId, regenerable: bool) {
+        self.count += 1;
+        self.total_size += size;
+        // Ties go to the first path so the result does not depend on the walk order
+        let larger = match &self.largest {
+            Some(largest) => (size, &largest.entry.path) > (largest.size, &file.path),
+            None => size > 0,
+        };
+        if larger {
+            self.largest = Some(KeptFile { entry: file.clone(), size, type_id, regenerable });
+        }
+    }
+
+    fn stats(&self, classifier: &FileTypeClassifier) -> TypeStats {
+        TypeStats {
+            count: self.count,
+            total_size: self.total_size,
+            average_size: self.total_size / self.count,
+            largest_file: self.largest.as_ref().map(|file| file.info(classifier)),
+        }
+    }
+}
+
+/// The analysis pass over the files in the size range, as it adds up
+pub(super) struct FileListings<'a> {
+    classifier: &'a FileTypeClassifier,
+    metrics: &'a MetricSet,
+    /// The category whose files are broken down by format
+    data: Option<TypeId>,
+    size_breakdown: SizeBreakdown,
+    types: HashMap<TypeId, TypeTally>,
+    formats: HashMap<String, TypeTally>,
+    regenerable: RegenerableTotals,
+    metric_tally: MetricTally,
+    largest_files: Largest<KeptFile>,
+    xattr_total: u64,
+    largest_xattr_files: Largest<XattrFile>,
+    empty_files: Vec<PathBuf>,
+}
+
+/// What [`FileListings`] came to
+#[derive(Default)]
+pub(super) struct Listings {
+    pub size_breakdown: SizeBreakdown,
+    pub file_type_distribution: HashMap<String, TypeStats>,
+    /// The data files by format, when they make up at least `data_formats_min_size`
+    pub data_formats: Option<BTreeMap<String, TypeStats>>,
+    pub largest_files: Vec<FileInfo>,
+    pub largest_files_residual: Residual,
+    /// The regenerable files; the duplicate counts are left to the duplicate search
+    pub regenerable: RegenerableTotals,
+    pub metric_tally: MetricTally,
+    pub xattr_total: u64,
+    pub largest_xattr_files: Vec<XattrFile>,
+    pub largest_xattr_residual: Residual,
+    /// Files of zero bytes, links aside, sorted
+    pub empty_files: Vec<PathBuf>,
+}
+
+impl<'a> FileListings<'a> {
+    /// Listings of the `--top` entries of `args`, with metric ages counted back from `ages_from`
+    pub(super) fn new(classifier: &'a FileTypeClassifier, metrics: &'a MetricSet, args: &Args, ages_from: DateTime<Utc>) -> Self {
+        Self {
+            classifier,
+            metrics,
+            data: classifier.types().find(|(_, name)| *name == utils::DATA).map(|(id, _)| id),
+            size_breakdown: SizeBreakdown::default(),
+            types: HashMap::new(),
+            formats: HashMap::new(),
+            regenerable: RegenerableTotals { hidden: args.hide_regenerable, ..Default::default() },
+            metric_tally: metrics.tally(ages_from),
+            largest_files: Largest::new(args.top_count, KeptFile::order, |file| file.size),
+            xattr_total: 0,
+            largest_xattr_files: Largest::new(args.top_count, |a, b| b.xattr_size.cmp(&a.xattr_size), |file| file.xattr_size),
+            empty_files: Vec::new(),
+        }
+    }
+
+    /// Add one file, returning the category it was put in and the size counted of it
+    pub(super) fn add(&mut self, file: &FileEntry) -> (TypeId, u64) {
+        let size = file.counted_size();
+        let type_id = self.classifier.classify_id(&file.path);
+        let regenerable = self.classifier.is_regenerable(&file.path);
+
+        self.size_breakdown.add(size);
+        if regenerable {
+            self.regenerable.files += 1;
+            self.regenerable.size += size;
+        }
+        self.metrics.add(&mut self.metric_tally, file, type_id);
+        self.types.entry(type_id).or_default().add(file, size, type_id, regenerable);
+        if Some(type_id) == self.data {
+            // A path rule may put files of any extension in the category
+            let format = self.classifier.mapped_extension(&file.path).map_or_else(|| "other".to_string(), std::borrow::Cow::into_owned);
+            self.formats.entry(format).or_default().add(file, size, type_id, regenerable);
+        }
+
+        // Entries are only copied when a listing keeps them
+        if !(regenerable && self.regenerable.hidden) {
+            if self.largest_files.admits(|last| (Reverse(size), &file.path) < (Reverse(last.size), &last.entry.path)) {
+                self.largest_files.offer(KeptFile { entry: file.clone(), size, type_id, regenerable });
+            } else {
+                self.largest_files.count_out(size);
+            }
+        }
+        if file.xattr_size > 0 {
+            self.xattr_total += file.xattr_size;
+            if self.largest_xattr_files.admits(|last| file.xattr_size > last.xattr_size) {
+                self.largest_xattr_files.offer(XattrFile { path: file.path.clone(), xattr_size: file.xattr_size });
+            } else {
+                self.largest_xattr_files.count_out(file.xattr_size);
+            }
+        }
+        // A link's size is that of its target path; a sparse file's length is what makes it empty
+        if file.size == 0 && !file.is_symlink {
+            self.empty_files.push(file.path.clone());
+        }
+        (type_id, size)
+    }
+
+    pub(super) fn finish(self, data_formats_min_size: u64) -> Listings {
+        let classifier = self.classifier;
+        let mut empty_files = self.empty_files;
+        empty_files.sort();
+        let data_size = self.data.and_then(|id| self.types.get(&id)).map_or(0, |tally| tally.total_size);
+        let data_formats = (data_size > 0 && data_size >= data_formats_min_size)
+            .then(|| self.formats.iter().map(|(format, tally)| (format.clone(), tally.stats(classifier))).collect());
+        Listings {
+            size_breakdown: self.size_breakdown,
+            file_type_distribution: self.types.iter().map(|(&id, tally)| (classifier.type_name(id).to_string(), tally.stats(classifier))).collect(),
+            data_formats,
+            largest_files: self.largest_files.items.iter().map(|file| file.info(classifier)).collect(),
+            largest_files_residual: self.largest_files.rest,
+            regenerable: self.regenerable,
+            metric_tally: self.metric_tally,
+            xattr_total: self.xattr_total,
+            largest_xattr_files: self.largest_xattr_files.items,
+            largest_xattr_residual: self.largest_xattr_files.rest,
+            empty_files,
+        }
+    }
+}
+
+/// What a fold takes from the analyzer, whose file source runs the walk meanwhile
+pub(super) struct FoldContext<'a> {
+    pub args: &'a Args,
+    pub classifier: &'a FileTypeClassifier,
+    pub metrics: &'a MetricSet,
+    pub age_boundaries: &'a [u64],
+    pub size_range: Option<SizeRange>,
+    pub data_formats_min_size: u64,
+}
+
+/// Everything a `--low-memory` run reports of its files, folded in as the walk hands them out
+pub(super) struct FileFold<'a> {
+    args: &'a Args,
+    size_range: Option<SizeRange>,
+    data_formats_min_size: u64,
+    ages_from: DateTime<Utc>,
+    skew: SkewTally,
+    disk_usage: DiskUsage,
+    owners: HashMap<u32, OwnerStats>,
+    /// Resolved once per uid, as the audit meets their files
+    user_names: HashMap<u32, Option<String>>,
+    security_audit: SecurityAudit,
+    age_source: AgeSource,
+    accessed_recorded: bool,
+    age_breakdown: AgeBreakdown,
+    stale_files: Option<StaleFiles>,
+    stale_listed: Largest<StaleFile>,
+    collected_files: u64,
+    matched_files: u64,
+    listings: FileListings<'a>,
+}
+
+/// What [`FileFold`] came to
+pub(super) struct FoldedFiles {
+    pub clock_skew: Option<ClockSkew>,
+    pub disk_usage: Option<DiskUsage>,
+    pub owners: HashMap<String, OwnerStats>,
+    /// The files' findings, without the directories, which are checked once their totals are known
+    pub security_audit: Option<SecurityAudit>,
+    pub age_source: AgeSource,
+    pub age_breakdown: AgeBreakdown,
+    pub stale_files: Option<StaleFiles>,
+    pub file_size_range: Option<AppliedSizeRange>,
+    pub listings: Listings,
+}
+
+impl<'a> FileFold<'a> {
+    /// A fold for a run over `roots` starting at `now`
+    pub(super) fn new(context: FoldContext<'a>, roots: &[std::path::PathBuf], now: DateTime<Utc>) -> Self {
+        let args = context.args;
+        let ages_from = time::age_reference(now, args.assume_clock_skew.unwrap_or_default());
+        Self {
+            args,
+            size_range: context.size_range,
+            data_formats_min_size: context.data_formats_min_size,
+            ages_from,
+            skew: SkewTally::new(now),
+            disk_usage: DiskUsage::default(),
+            owners: HashMap::new(),
+            user_names: HashMap::new(),
+            security_audit: SecurityAudit::default(),
+            age_source: AgeSource::for_roots(roots),
+            accessed_recorded: false,
+            age_breakdown: AgeBreakdown::new(context.age_boundaries),
+            stale_files: args.stale_threshold.map(|threshold| StaleFiles { threshold_secs: threshold.as_secs(), ..Default::default() }),
+            stale_listed: Largest::new(args.top_count, StaleFile::order, |file| file.size),
+            collected_files: 0,
+            matched_files: 0,
+            listings: FileListings::new(context.classifier, context.metrics, args, ages_from),
+        }
+    }
+
+    /// Fold in one file, in the order a run that keeps its files takes these steps
+    pub(super) fn add(&mut self, file: FileEntry) {
+        if let Some(modified) = file.modified {
+            self.skew.add(modified);
+        }
+
+        // Taken before the size range, which may leave the totals counting files it drops
+        if self.args.disk_usage {
+            self.disk_usage.add(&file);
+        }
+        OwnerStats::count(&mut self.owners, &file);
+        if self.args.security_audit {
+            let user_names = &mut self.user_names;
+            self.security_audit.add_file(&file, |uid| user_names.entry(uid).or_insert_with(|| scanner::user_name(uid)).clone());
+        }
+        self.accessed_recorded |= file.accessed.is_some();
+        self.age_breakdown.add(&file, self.age_source, self.ages_from);
+        if let Some(stale) = self.stale_files.as_mut().and_then(|stale| stale.add(&file, self.age_source, self.ages_from)) {
+            self.stale_listed.offer(stale);
+        }
+
+        self.collected_files += 1;
+        if self.size_range.is_some_and(|range| !range.contains(file.counted_size())) {
+            return;
+        }
+        self.matched_files += 1;
+        self.listings.add(&file);
+    }
+
+    /// The results, with the clock skew measured against the start of the scan
+    pub(super) fn finish(self) -> FoldedFiles {
+        FoldedFiles {
+            clock_skew: self.skew.finish(),
+            disk_usage: self.args.disk_usage.then_some(self.disk_usage),
+            owners: OwnerStats::named(self.owners, scanner::user_name),
+            security_audit: self.args.security_audit.then_some(self.security_audit),
+            // Without access times the ages are modification ages under either source
+            age_source: if self.accessed_recorded { self.age_source } else { AgeSource::Modified },
+            age_breakdown: self.age_breakdown,
+            stale_files: self.stale_files.map(|stale| StaleFiles { largest: self.stale_listed.items, ..stale }),
+            file_size_range: self.size_range.map(|range| AppliedSizeRange {
+                range,
+                affects_directories: false,
+                matched_files: self.matched_files,
+                collected_files: self.collected_files,
+            }),
+            listings: self.listings.finish(self.data_formats_min_size),
+        }
+    }
+}
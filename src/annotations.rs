@@ -0,0 +1,187 @@
+//! Explanations for well-known large directories
+//!
+//! Package stores, container layers, and journals are expected to be big and
+//! are rarely what a user is hunting for. A small knowledge base maps such
+//! paths to a short explanation and a suggested cleanup command, which the
+//! report prints beneath matching entries of the largest-directories list.
+//!
+//! Patterns are either absolute (`/var/log/journal`) or relative to the
+//! user's home directory (`~/.cargo/registry`) and match a directory exactly.
+//! Entries from the configuration file's `path_annotations` are consulted
+//! before the built-in ones, so they can also replace them.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// One knowledge base entry
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PathAnnotation {
+    /// Absolute path, or a path starting with `~/`
+    pub path: String,
+    pub explanation: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cleanup: Option<String>,
+}
+
+impl PathAnnotation {
+    fn new(path: &str, explanation: &str, cleanup: Option<&str>) -> Self {
+        Self {
+            path: path.to_string(),
+            explanation: explanation.to_string(),
+            cleanup: cleanup.map(str::to_string),
+        }
+    }
+
+    /// The explanation followed by the cleanup suggestion, if any
+    pub fn text(&self) -> String {
+        match &self.cleanup {
+            Some(cleanup) => format!("{} Cleanup: `{}`", self.explanation, cleanup),
+            None => self.explanation.clone(),
+        }
+    }
+}
+
+/// Annotations shipped with diranalyzer
+pub fn builtin() -> Vec<PathAnnotation> {
+    vec![
+        PathAnnotation::new(
+            "/var/lib/docker/overlay2",
+            "Docker image and container layers.",
+            Some("docker system prune"),
+        ),
+        PathAnnotation::new(
+            "/var/lib/containers/storage",
+            "Podman images and containers.",
+            Some("podman system prune"),
+        ),
+        PathAnnotation::new(
+            "/var/log/journal",
+            "systemd journal logs.",
+            Some("journalctl --vacuum-size=500M"),
+        ),
+        PathAnnotation::new(
+            "/var/cache/pacman",
+            "Downloaded Arch Linux packages, kept for downgrades.",
+            Some("paccache -r"),
+        ),
+        PathAnnotation::new("/var/cache/apt", "Downloaded Debian packages.", Some("apt-get clean")),
+        PathAnnotation::new("/var/cache/dnf", "Downloaded Fedora packages and metadata.", Some("dnf clean all")),
+        PathAnnotation::new(
+            "/var/lib/flatpak",
+            "Flatpak applications and shared runtimes.",
+            Some("flatpak uninstall --unused"),
+        ),
+        PathAnnotation::new(
+            "/var/lib/snapd/snaps",
+            "Snap packages, including retained older revisions.",
+            Some("snap set system refresh.retain=2"),
+        ),
+        PathAnnotation::new("/nix/store", "The Nix package store.", Some("nix-collect-garbage -d")),
+        PathAnnotation::new(
+            "~/.cargo/registry",
+            "Downloaded crate sources and the registry index, fetched again on demand.",
+            Some("rm -rf ~/.cargo/registry/cache ~/.cargo/registry/src"),
+        ),
+        PathAnnotation::new(
+            "~/.rustup/toolchains",
+            "Installed Rust toolchains.",
+            Some("rustup toolchain uninstall <toolchain>"),
+        ),
+        PathAnnotation::new("~/.npm/_cacache", "The npm package cache.", Some("npm cache clean --force")),
+        PathAnnotation::new("~/.gradle/caches", "Gradle dependency and build caches.", None),
+        PathAnnotation::new("~/.m2/repository", "Downloaded Maven artifacts, fetched again on demand.", None),
+        PathAnnotation::new("~/.cache", "Per-user application caches, rebuilt on demand.", None),
+        PathAnnotation::new("~/.local/share/Trash", "Files moved to the desktop trash.", Some("gio trash --empty")),
+    ]
+}
+
+/// Looks up the annotation of a directory
+#[derive(Debug, Clone)]
+pub struct KnowledgeBase {
+    entries: Vec<(PathBuf, PathAnnotation)>,
+}
+
+impl KnowledgeBase {
+    /// `extra` entries take precedence over the built-in ones; without a
+    /// `home`, home-relative patterns never match
+    pub fn new(extra: &[PathAnnotation], home: Option<&Path>) -> Self {
+        let entries = extra
+            .iter()
+            .cloned()
+            .chain(builtin())
+            .filter_map(|annotation| Some((expand(&annotation.path, home)?, annotation)))
+            .collect();
+        Self { entries }
+    }
+
+    /// Knowledge base for the current user's home directory
+    pub fn for_current_user(extra: &[PathAnnotation]) -> Self {
+        let home = std::env::var_os("HOME").filter(|home| !home.is_empty()).map(PathBuf::from);
+        Self::new(extra, home.as_deref())
+    }
+
+    /// The entry for `directory`, which should be absolute
+    pub fn lookup(&self, directory: &Path) -> Option<&PathAnnotation> {
+        // Comparing components ignores trailing slashes and `.` segments
+        self.entries
+            .iter()
+            .find(|(path, _)| path.components().eq(directory.components()))
+            .map(|(_, annotation)| annotation)
+    }
+}
+
+/// The absolute path a pattern stands for
+fn expand(pattern: &str, home: Option<&Path>) -> Option<PathBuf> {
+    if pattern == "~" {
+        return home.map(Path::to_path_buf);
+    }
+    match pattern.strip_prefix("~/") {
+        Some(relative) => home.map(|home| home.join(relative)),
+        None => Some(PathBuf::from(pattern)).filter(|path| path.is_absolute()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn knowledge_base(extra: &[PathAnnotation]) -> KnowledgeBase {
+        KnowledgeBase::new(extra, Some(Path::new("/home/alex")))
+    }
+
+    #[test]
+    fn test_absolute_pattern() {
+        let kb = knowledge_base(&[]);
+        let annotation = kb.lookup(Path::new("/var/lib/docker/overlay2")).unwrap();
+        assert_eq!(annotation.text(), "Docker image and container layers. Cleanup: `docker system prune`");
+
+        assert!(kb.lookup(Path::new("/var/lib/docker/overlay2/")).is_some());
+        assert!(kb.lookup(Path::new("/var/lib/docker")).is_none(), "parents are not annotated");
+        assert!(kb.lookup(Path::new("/var/lib/docker/overlay2/abc")).is_none(), "children are not annotated");
+    }
+
+    #[test]
+    fn test_home_relative_pattern() {
+        let kb = knowledge_base(&[]);
+        assert!(kb.lookup(Path::new("/home/alex/.cargo/registry")).is_some());
+        assert!(kb.lookup(Path::new("/home/sam/.cargo/registry")).is_none());
+
+        let homeless = KnowledgeBase::new(&[], None);
+        assert!(homeless.lookup(Path::new("/home/alex/.cargo/registry")).is_none());
+        assert!(homeless.lookup(Path::new("/var/log/journal")).is_some());
+    }
+
+    #[test]
+    fn test_configured_entries_take_precedence() {
+        let extra = [
+            PathAnnotation::new("/var/log/journal", "Kept for auditing.", None),
+            PathAnnotation::new("~/datasets", "Training data.", Some("make clean-data")),
+            PathAnnotation::new("relative/path", "Never matches.", None),
+        ];
+        let kb = knowledge_base(&extra);
+
+        assert_eq!(kb.lookup(Path::new("/var/log/journal")).unwrap().text(), "Kept for auditing.");
+        assert_eq!(kb.lookup(Path::new("/home/alex/datasets")).unwrap().cleanup.as_deref(), Some("make clean-data"));
+        assert!(kb.lookup(Path::new("relative/path")).is_none());
+    }
+}
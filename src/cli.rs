@@ -1,28 +1,121 @@
 //! Command-line interface definitions and argument parsing
 
-use clap::{Parser, ValueEnum};
-use std::path::PathBuf;
+use clap::{Parser, Subcommand, ValueEnum};
+use std::path::{Path, PathBuf};
 
 #[derive(Parser, Debug, Clone)]
 #[command(
     name = "diranalyzer",
     about = "A high-performance CLI tool for comprehensive directory analysis",
-    long_about = "DirAnalyzer provides detailed insights into directory structures including size breakdowns, file type distributions, and duplicate file detection. Perfect for system administration, cleanup operations, and storage optimization."
+    long_about = "DirAnalyzer provides detailed insights into directory structures including size breakdowns, file type distributions, and duplicate file detection. Perfect for system administration, cleanup operations, and storage optimization.",
+    args_conflicts_with_subcommands = true
 )]
 pub struct Args {
+    /// Auxiliary command to run instead of an analysis
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     /// Directory path to analyze
-    #[arg(value_name = "PATH", help = "Path to the directory to analyze")]
+    #[arg(
+        value_name = "PATH",
+        default_value = ".",
+        help = "Path to the directory to analyze (default: current directory)"
+    )]
     pub path: PathBuf,
 
-    /// Maximum depth for directory traversal
+    /// Further directories analyzed together with PATH into one report
+    #[arg(
+        value_name = "MORE",
+        help = "Further directories to analyze together with PATH, in one report; roots inside another root are skipped"
+    )]
+    pub more_paths: Vec<PathBuf>,
+
+    /// File listing further directories to analyze, one per line; `-` reads stdin
+    #[arg(
+        long = "paths-from",
+        value_name = "FILE",
+        conflicts_with = "paths_from0",
+        help = "Also analyze the directories listed in FILE, one per line, skipping blank lines and # comments; - reads stdin. PATH is then only analyzed when given"
+    )]
+    pub paths_from: Option<PathBuf>,
+
+    /// Like --paths-from, with NUL-separated paths
+    #[arg(
+        long = "paths-from0",
+        value_name = "FILE",
+        help = "Like --paths-from, with the paths separated by NUL bytes, as find -print0 writes them"
+    )]
+    pub paths_from0: Option<PathBuf>,
+
+    /// Where the files to analyze are listed
+    #[arg(
+        long = "source",
+        value_name = "KIND",
+        value_enum,
+        default_value = "local",
+        help = "Where the files come from: walk PATH (local), or read PATH as an S3 inventory CSV listing (s3-inventory)"
+    )]
+    pub source: SourceKind,
+
+    /// Maximum depth for directory traversal, [`UNLIMITED_DEPTH`] for none
     #[arg(
         short = 'd',
         long = "depth",
         default_value = "10",
-        help = "Maximum depth for directory traversal"
+        value_parser = crate::utils::parse_depth,
+        help = "Maximum depth for directory traversal; 0 or \"unlimited\" for no limit"
     )]
     pub max_depth: usize,
 
+    /// Shallowest depth of the files and directories collected
+    #[arg(
+        long = "min-depth",
+        value_name = "N",
+        default_value = "0",
+        help = "Leave out files and directories less than N levels below the root; what lies deeper is still walked and counted"
+    )]
+    pub min_depth: usize,
+
+    /// Stop after collecting this many files
+    #[arg(
+        long = "max-files",
+        value_name = "N",
+        help = "Stop scanning once N files are collected and report on those, marked as partial"
+    )]
+    pub max_files: Option<u64>,
+
+    /// Stop after collecting this many bytes
+    #[arg(
+        long = "max-bytes",
+        value_name = "SIZE",
+        value_parser = crate::utils::parse_size,
+        help = "Stop scanning before the collected files pass SIZE (e.g. 50GB) and report on those, marked as partial"
+    )]
+    pub max_bytes: Option<u64>,
+
+    /// Stop after running this long
+    #[arg(
+        long = "max-duration",
+        value_name = "DURATION",
+        value_parser = crate::utils::parse_duration,
+        help = "Stop scanning and searching for duplicates once the run has taken DURATION (e.g. 30s, 10m) and report on what was found, marked as partial"
+    )]
+    pub max_duration: Option<std::time::Duration>,
+
+    /// Fail runs that a limit stopped
+    #[arg(
+        long = "strict-limits",
+        help = "Exit with an error after reporting when --max-files, --max-bytes, or --max-duration stopped the run"
+    )]
+    pub strict_limits: bool,
+
+    /// Fold files into the totals as they are found instead of keeping them
+    #[arg(
+        long = "low-memory",
+        help = "Fold each file into running totals as the walk finds it instead of keeping every file in memory; skips duplicate detection, entropy sampling, and version grouping"
+    )]
+    pub low_memory: bool,
+
     /// Enable duplicate file detection
     #[arg(
         long = "duplicates",
@@ -38,6 +131,60 @@ pub struct Args {
     )]
     pub min_duplicate_size: u64,
 
+    /// Ordering of duplicate groups in reports and exports
+    #[arg(
+        long = "duplicates-sort",
+        value_enum,
+        default_value = "waste",
+        help = "Order duplicate groups by wasted space, priority score, file size, or copy count"
+    )]
+    pub duplicates_sort: DuplicateSort,
+
+    /// Only group duplicates inside the same subtree at this depth
+    #[arg(
+        long = "duplicates-within-depth",
+        value_name = "N",
+        help = "Only group duplicates that share their ancestor directory at depth N"
+    )]
+    pub duplicates_within_depth: Option<usize>,
+
+    /// Reuse content hashes from earlier runs
+    #[arg(
+        long = "hash-cache",
+        help = "Reuse hashes of unchanged files from earlier runs; safe to share between concurrent runs"
+    )]
+    pub hash_cache: bool,
+
+    /// Hash cache location
+    #[arg(
+        long = "hash-cache-dir",
+        value_name = "DIR",
+        help = "Directory of the hash cache (implies --hash-cache; default: ~/.cache/diranalyzer/hashes)"
+    )]
+    pub hash_cache_dir: Option<PathBuf>,
+
+    /// Resume an interrupted duplicate search
+    #[arg(
+        long = "resume",
+        help = "Take over the size groups an interrupted duplicate search already hashed, as its journal records them"
+    )]
+    pub resume: bool,
+
+    /// Import duplicate groups instead of hashing
+    #[arg(
+        long = "import-duplicates",
+        value_name = "FILE",
+        help = "Report duplicate groups from rmlint JSON or jdupes output instead of hashing"
+    )]
+    pub import_duplicates: Option<PathBuf>,
+
+    /// Walk past the depth limit where it cut data off
+    #[arg(
+        long = "auto-depth",
+        help = "Walk on below directories at the depth limit that have contents, so totals are complete"
+    )]
+    pub auto_depth: bool,
+
     /// Show hidden files and directories
     #[arg(
         short = 'a',
@@ -46,6 +193,103 @@ pub struct Args {
     )]
     pub show_hidden: bool,
 
+    /// Account for extended attribute sizes
+    #[arg(
+        long = "include-xattrs",
+        help = "Measure extended attributes of every file and report the largest (extra syscalls per file)"
+    )]
+    pub include_xattrs: bool,
+
+    /// Count allocated bytes instead of apparent sizes
+    #[arg(
+        long = "disk-usage",
+        help = "Count the bytes allocated on disk, as du does, instead of apparent sizes in every total and listing"
+    )]
+    pub disk_usage: bool,
+
+    /// Audit permissions for risky mode bits
+    #[arg(
+        long = "security-audit",
+        help = "Record permissions and list world-writable files, setuid and setgid files, and world-writable directories without the sticky bit"
+    )]
+    pub security_audit: bool,
+
+    /// Only include files owned by this user
+    #[arg(
+        long = "user",
+        visible_alias = "owner",
+        value_name = "NAME",
+        help = "Only include files owned by this user (name or numeric uid)"
+    )]
+    pub user: Option<String>,
+
+    /// Only include files of this group
+    #[arg(
+        long = "group",
+        value_name = "GROUP",
+        help = "Only include files whose group is GROUP (name or numeric gid)"
+    )]
+    pub group: Option<String>,
+
+    /// Only include files owned by the invoking user
+    #[arg(
+        long = "mine",
+        conflicts_with = "user",
+        help = "Only include files owned by the invoking user"
+    )]
+    pub mine: bool,
+
+    /// Only include files last modified before this
+    #[arg(
+        long = "modified-before",
+        value_name = "DATE",
+        value_parser = crate::scanner::parse_date_spec,
+        help = "Only include files last modified before DATE: a date (2023-01-01), an RFC 3339 timestamp, or an age (2y, 90d, 6months)"
+    )]
+    pub modified_before: Option<crate::scanner::DateSpec>,
+
+    /// Only include files last modified at or after this
+    #[arg(
+        long = "modified-after",
+        value_name = "DATE",
+        value_parser = crate::scanner::parse_date_spec,
+        help = "Only include files last modified at or after DATE: a date (2023-01-01), an RFC 3339 timestamp, or an age (2y, 90d, 6months)"
+    )]
+    pub modified_after: Option<crate::scanner::DateSpec>,
+
+    /// Only include files created at or after this
+    #[arg(
+        long = "created-after",
+        value_name = "DATE",
+        value_parser = crate::scanner::parse_date_spec,
+        help = "Only include files created at or after DATE, as --modified-after does for modification times; files without a recorded birth time are left out"
+    )]
+    pub created_after: Option<crate::scanner::DateSpec>,
+
+    /// List the largest files unused for at least this long
+    #[arg(
+        long = "stale-threshold",
+        value_name = "AGE",
+        value_parser = crate::utils::parse_duration,
+        help = "List the largest files not accessed or modified for at least AGE (e.g. 1y, 180d), with their count and total size"
+    )]
+    pub stale_threshold: Option<std::time::Duration>,
+
+    /// Skip what .gitignore and .ignore files in the tree ignore
+    #[arg(
+        long = "respect-gitignore",
+        help = "Skip entries ignored by .gitignore and .ignore files in the tree, its git work tree, and the global excludes file"
+    )]
+    pub respect_gitignore: bool,
+
+    /// Leave the global excludes file out of --respect-gitignore
+    #[arg(
+        long = "no-global-gitignore",
+        requires = "respect_gitignore",
+        help = "With --respect-gitignore, ignore the global git excludes file (core.excludesFile)"
+    )]
+    pub no_global_gitignore: bool,
+
     /// Export results to file
     #[arg(
         short = 'e',
@@ -59,10 +303,172 @@ pub struct Args {
     #[arg(
         short = 'o',
         long = "output",
-        help = "Output file path for export (default: auto-generated)"
+        help = "Output file path for export, or - for an ndjson stream on stdout (default: auto-generated)"
     )]
     pub output: Option<PathBuf>,
 
+    /// Template for auto-generated export filenames
+    #[arg(
+        long = "output-template",
+        value_name = "TEMPLATE",
+        conflicts_with = "output",
+        value_parser = crate::export::naming::parse_template,
+        help = "Name auto-generated exports from a template with {root}, {date}, {time}, {seq}, {ext}, {hostname}"
+    )]
+    pub output_template: Option<String>,
+
+    /// Retention for auto-named exports
+    #[arg(
+        long = "keep-last",
+        value_name = "N",
+        conflicts_with = "output",
+        value_parser = clap::value_parser!(u64).range(1..),
+        help = "Keep only the N newest auto-named exports in the target directory"
+    )]
+    pub keep_last: Option<u64>,
+
+    /// Largest shard of a json-bundle export
+    #[arg(
+        long = "bundle-shard-size",
+        value_name = "SIZE",
+        default_value = "256MB",
+        value_parser = crate::utils::parse_size,
+        help = "Start a new NDJSON shard of a json-bundle export before one passes SIZE"
+    )]
+    pub bundle_shard_size: u64,
+
+    /// How much of the scan a JSON export keeps
+    #[arg(
+        long = "export-scope",
+        value_enum,
+        default_value = "summary",
+        requires = "export",
+        help = "What JSON and json-bundle exports hold: the listings (summary), or also every scanned entry (full) for `report` to refilter"
+    )]
+    pub export_scope: ExportScope,
+
+    /// Filters applied to the collected entries before anything is totalled
+    #[command(flatten)]
+    pub report_filters: ReportFilters,
+
+    /// Report output format
+    #[arg(
+        long = "report-format",
+        value_enum,
+        default_value = "terminal",
+        help = "Report output format: colored terminal, plain text, or Markdown"
+    )]
+    pub report_format: ReportFormat,
+
+    /// Report language
+    #[arg(
+        long = "lang",
+        value_enum,
+        default_value = "en",
+        help = "Language of the report; untranslated strings fall back to English"
+    )]
+    pub lang: crate::reporter::Lang,
+
+    /// Show only the totals, top directories and files, and duplicate waste
+    #[arg(
+        long = "brief",
+        conflicts_with = "full_report",
+        help = "Show a ten-line summary: totals, top 3 directories and files, duplicate waste"
+    )]
+    pub brief: bool,
+
+    /// Always show the full report, even for small trees
+    #[arg(
+        long = "full-report",
+        help = "Show the full report even when the tree is small enough for the brief one"
+    )]
+    pub full_report: bool,
+
+    /// Hide directories smaller than this from directory listings
+    #[arg(
+        long = "min-dir-size",
+        value_name = "SIZE",
+        value_parser = crate::utils::parse_size,
+        help = "Hide directories smaller than SIZE (e.g. 1MB) from directory listings; totals are unaffected"
+    )]
+    pub min_dir_size: Option<u64>,
+
+    /// Leave files smaller than this out of the file listings
+    #[arg(
+        long = "min-file-size",
+        value_name = "SIZE",
+        value_parser = crate::utils::parse_size,
+        help = "Consider only files of at least SIZE (e.g. 50M) for file types, largest files, and duplicates; directory sizes still count every file"
+    )]
+    pub min_file_size: Option<u64>,
+
+    /// Leave files larger than this out of the file listings
+    #[arg(
+        long = "max-file-size",
+        value_name = "SIZE",
+        value_parser = crate::utils::parse_size,
+        help = "Consider only files of at most SIZE (e.g. 1.5G) for file types, largest files, and duplicates; directory sizes still count every file"
+    )]
+    pub max_file_size: Option<u64>,
+
+    /// Apply --min-file-size and --max-file-size to directory sizes and totals too
+    #[arg(
+        long = "filter-affects-dirs",
+        help = "Make --min-file-size and --max-file-size apply to directory sizes and the scan totals as well"
+    )]
+    pub filter_affects_dirs: bool,
+
+    /// Collapse regenerable build outputs and caches in listings
+    #[arg(
+        long = "hide-regenerable",
+        help = "Leave regenerable build outputs and caches out of the largest-file and duplicate listings; totals are unaffected"
+    )]
+    pub hide_regenerable: bool,
+
+    /// Sample recently modified files for high-entropy content
+    #[arg(
+        long = "entropy-sample",
+        help = "Read samples of recently modified files and report directories whose share of random-looking (encrypted or compressed) bytes jumped since the last run"
+    )]
+    pub entropy_sample: bool,
+
+    /// Modification window for --entropy-sample
+    #[arg(
+        long = "entropy-window",
+        value_name = "DAYS",
+        default_value = "7",
+        requires = "entropy_sample",
+        help = "Only sample files modified within the last DAYS days"
+    )]
+    pub entropy_window: u64,
+
+    /// Read budget for --entropy-sample
+    #[arg(
+        long = "entropy-budget",
+        value_name = "SIZE",
+        default_value = "64MB",
+        value_parser = crate::utils::parse_size,
+        requires = "entropy_sample",
+        help = "Read at most SIZE bytes of samples; the largest recent files are sampled first"
+    )]
+    pub entropy_budget: u64,
+
+    /// How far the scanned filesystem's clock runs ahead of this machine's
+    #[arg(
+        long = "assume-clock-skew",
+        value_name = "DURATION",
+        value_parser = crate::utils::parse_duration,
+        help = "Treat file modification times as DURATION ahead (e.g. 2h) when measuring ages; displayed times stay as they are"
+    )]
+    pub assume_clock_skew: Option<std::time::Duration>,
+
+    /// Preview the contents of the listed largest files
+    #[arg(
+        long = "previews",
+        help = "Show the first lines of text files, image dimensions, and archive entry counts for the listed largest files"
+    )]
+    pub previews: bool,
+
     /// Number of top items to display in reports
     #[arg(
         short = 'n',
@@ -72,14 +478,42 @@ pub struct Args {
     )]
     pub top_count: usize,
 
-    /// Exclude patterns (glob syntax)
+    /// Exclude globs, matched against paths relative to the scan root
     #[arg(
         long = "exclude",
-        help = "Exclude files/directories matching patterns (glob syntax)",
+        value_name = "GLOB",
+        help = "Exclude files/directories matching a glob relative to the scan root, such as \"*.iso\" or \"node_modules/**\" (repeatable)",
         action = clap::ArgAction::Append
     )]
     pub exclude_patterns: Vec<String>,
 
+    /// Exclude regexes, matched against full paths
+    #[arg(
+        long = "exclude-regex",
+        value_name = "REGEX",
+        help = "Exclude files/directories whose full path matches a regular expression (repeatable)",
+        action = clap::ArgAction::Append
+    )]
+    pub exclude_regexes: Vec<String>,
+
+    /// Include globs; when given, only matching files are analyzed
+    #[arg(
+        long = "include",
+        value_name = "GLOB",
+        help = "Analyze only files matching a glob relative to the scan root, such as \"*.mp4\" (repeatable); directories are still walked, and --exclude wins over it",
+        action = clap::ArgAction::Append
+    )]
+    pub include_patterns: Vec<String>,
+
+    /// Directories listed whatever their rank
+    #[arg(
+        long = "pin",
+        value_name = "PATH",
+        action = clap::ArgAction::Append,
+        help = "Always list this directory among the largest, whatever its rank (repeatable)"
+    )]
+    pub pins: Vec<PathBuf>,
+
     /// Follow symbolic links
     #[arg(
         long = "follow-links",
@@ -87,6 +521,14 @@ pub struct Args {
     )]
     pub follow_links: bool,
 
+    /// Stay on the filesystem of the scan root
+    #[arg(
+        short = 'x',
+        long = "one-file-system",
+        help = "Skip directories and files on other filesystems than the root's, like du -x, and list the mount points skipped"
+    )]
+    pub one_file_system: bool,
+
     /// Verbose output
     #[arg(
         short = 'v',
@@ -107,9 +549,596 @@ pub struct Args {
     #[arg(
         short = 't',
         long = "threads",
-        help = "Number of threads for parallel processing (default: auto-detect)"
+        help = "Number of threads walking the tree (default: 1) and hashing duplicates (default: one per CPU)"
     )]
     pub threads: Option<usize>,
+
+    /// Configuration file path
+    #[arg(
+        long = "config",
+        value_name = "FILE",
+        help = "Path to a JSON configuration file (default: ~/.config/diranalyzer/config.json)"
+    )]
+    pub config: Option<PathBuf>,
+
+    /// Disable run history
+    #[arg(
+        long = "no-history",
+        help = "Do not record this run in the scan history or show the trend line"
+    )]
+    pub no_history: bool,
+
+    /// Operator notes shown next to the paths they concern
+    #[arg(
+        long = "notes-file",
+        value_name = "FILE",
+        help = "Notes added with `note add` (default: ~/.config/diranalyzer/notes.json)"
+    )]
+    pub notes_file: Option<PathBuf>,
+
+    /// Findings accepted with `accept`, left out of the listings
+    #[arg(
+        long = "accepted-file",
+        value_name = "FILE",
+        help = "Findings accepted with `accept` (default: ~/.config/diranalyzer/accepted.json)"
+    )]
+    pub accepted_file: Option<PathBuf>,
+
+    /// List accepted findings where they belong
+    #[arg(
+        long = "show-accepted",
+        help = "List accepted findings in their sections, marked with the reason, instead of only counting them"
+    )]
+    pub show_accepted: bool,
+
+    /// Abort when the outputs may not fit
+    #[arg(
+        long = "strict-space",
+        help = "Abort before scanning when the export, journal, or history may not fit on disk"
+    )]
+    pub strict_space: bool,
+
+    /// Estimate the run's cost from a sample instead of running it
+    #[arg(
+        long = "estimate",
+        help = "Sample the tree, estimate files, size, and time per phase with confidence bands, then exit"
+    )]
+    pub estimate: bool,
+
+    /// Validate the run's configuration against the filesystem instead of running it
+    #[arg(
+        long = "check",
+        conflicts_with = "estimate",
+        help = "Check the path, patterns, output destinations, threads, and memory limit without scanning; exit 1 on any finding"
+    )]
+    pub check: bool,
+
+    /// Explain what the filters left out: a summary by cause after the run, or one path's trace instead of it
+    #[arg(
+        long = "explain-exclusions",
+        value_name = "PATH",
+        num_args = 0..=1,
+        require_equals = true,
+        conflicts_with_all = ["check", "estimate"],
+        help = "Summarize skipped entries by cause after the report; with =PATH, trace that path through the filters without scanning and exit 1 if it is left out"
+    )]
+    pub explain_exclusions: Option<Option<PathBuf>>,
+
+    /// Entries listed for the estimate
+    #[arg(
+        long = "estimate-entries",
+        value_name = "N",
+        default_value_t = crate::estimate::DEFAULT_SAMPLE_ENTRIES,
+        help = "Directory entries --estimate may list"
+    )]
+    pub estimate_entries: usize,
+
+    /// Seed of every random choice, so a run can be replayed
+    #[arg(
+        long = "seed",
+        value_name = "SEED",
+        help = "Seed for sampling; a random one is picked, shown with --verbose, and recorded in exports"
+    )]
+    pub seed: Option<u64>,
+
+    /// Send a structured run summary to journald or syslog
+    #[arg(
+        long = "log-summary-to-syslog",
+        help = "Send a structured summary of the run to journald (or syslog as a fallback)"
+    )]
+    pub log_summary_to_syslog: bool,
+
+    /// Print interim totals during long scans
+    #[arg(
+        long = "interim-every",
+        value_name = "DURATION",
+        value_parser = crate::utils::parse_duration,
+        help = "Print files, bytes, errors, and the largest directories found so far every DURATION (e.g. 10m) while scanning; SIGUSR1 prints one at any time"
+    )]
+    pub interim_every: Option<std::time::Duration>,
+
+    /// Time individual filesystem operations
+    #[arg(
+        long = "io-latency-stats",
+        help = "Record readdir, stat, open, and read latencies and report percentiles and the slowest paths"
+    )]
+    pub io_latency_stats: bool,
+
+    /// Look for names that collide on case-insensitive filesystems
+    #[arg(
+        long = "detect-case-collisions",
+        help = "Report sibling names that are equal under case folding (Report.pdf and report.pdf), which collide when copied to macOS or Windows"
+    )]
+    pub detect_case_collisions: bool,
+
+    /// Group files that look like versions of one another by name
+    #[arg(
+        long = "find-versions",
+        help = "Group files in one directory whose names differ only by copy markers, (N), _vN, .bak/.old, or ISO dates, and total the bytes of all but the newest (heuristic)"
+    )]
+    pub find_versions: bool,
+
+    /// Resolve every symlink and report the broken ones
+    #[arg(
+        long = "find-broken-links",
+        help = "Resolve every symlink met, even without --follow-links, and list the broken ones by directory with their dangling targets"
+    )]
+    pub find_broken_links: bool,
+
+    /// Print the empty directories instead of the report
+    #[arg(
+        long = "print-empty-dirs",
+        help = "Print every empty directory to stdout, one per line and deepest first, instead of the report, for piping into xargs rmdir"
+    )]
+    pub print_empty_dirs: bool,
+
+    /// List every scan error
+    #[arg(
+        long = "errors-full",
+        help = "List every scan error in the report and exports, not only the first 1000 next to the counts by cause and subtree"
+    )]
+    pub errors_full: bool,
+
+    /// Fail runs that met scan errors
+    #[arg(
+        long = "fail-on-errors",
+        help = "Exit with an error after reporting when the walk could not read some paths"
+    )]
+    pub fail_on_errors: bool,
+
+    /// Abort on internal errors
+    #[arg(
+        long = "strict",
+        help = "Abort on the first internal error instead of reporting partial results"
+    )]
+    pub strict: bool,
+
+    /// Replace or remove redundant duplicate copies
+    #[arg(
+        long = "dedupe",
+        value_name = "ACTION",
+        value_enum,
+        help = "Replace redundant duplicate copies with hard links, or delete them; writes an undo journal"
+    )]
+    pub dedupe: Option<crate::actions::DedupeAction>,
+
+    /// Undo journal for --dedupe
+    #[arg(
+        long = "journal",
+        value_name = "FILE",
+        requires = "dedupe",
+        help = "Where to write the undo journal (default: diranalyzer-undo-<timestamp>.ndjson)"
+    )]
+    pub journal: Option<PathBuf>,
+
+    /// Keep directory modification times unchanged by --dedupe
+    #[arg(
+        long = "preserve-dir-mtime",
+        requires = "dedupe",
+        help = "Restore the modification times of directories changed by --dedupe"
+    )]
+    pub preserve_dir_mtime: bool,
+
+    /// Guarantee that nothing inside the scanned tree is written
+    #[arg(
+        long = "read-only",
+        help = "Never write inside the scanned tree; options that would are refused before scanning"
+    )]
+    pub read_only: bool,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Show the recorded size history of a scanned directory
+    History(HistoryArgs),
+    /// Revert the operations recorded in a --dedupe undo journal
+    Undo(UndoArgs),
+    /// Verify that DST holds an identical copy of SRC
+    MirrorCheck(MirrorCheckArgs),
+    /// Inspect the shared hash cache
+    Cache(CacheArgs),
+    /// Show two exported runs side by side
+    Compare(CompareArgs),
+    /// Attach notes to paths that later reports show next to them
+    Note(NoteArgs),
+    /// Accept a finding so later reports count it instead of listing it
+    Accept(AcceptArgs),
+    /// Report on a full snapshot again, with report-time filters and without rescanning
+    Report(ReportArgs),
+    /// Check an export against its integrity footer
+    VerifyExport(VerifyExportArgs),
+    /// Analyze a changing scratch tree over and over and check every run, to reproduce races
+    #[command(hide = true)]
+    SelfTest(SelfTestArgs),
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct VerifyExportArgs {
+    /// Export to check
+    #[arg(value_name = "PATH", help = "A JSON, CSV, or NDJSON export, or a json-bundle directory")]
+    pub path: PathBuf,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct SelfTestArgs {
+    /// How long to keep starting runs
+    #[arg(
+        long = "duration",
+        value_name = "DURATION",
+        default_value = "60s",
+        value_parser = crate::utils::parse_duration,
+        help = "Keep starting runs for DURATION (e.g. 10m)"
+    )]
+    pub duration: std::time::Duration,
+
+    /// Seed of the option sequence and the churn
+    #[arg(long = "seed", value_name = "N", help = "Replay the options and churn of an earlier self-test")]
+    pub seed: Option<u64>,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct ReportArgs {
+    /// Snapshot to report on
+    #[arg(value_name = "SNAPSHOT", help = "A JSON export or json-bundle directory written with --export-scope full")]
+    pub snapshot: PathBuf,
+
+    #[command(flatten)]
+    pub filters: ReportFilters,
+
+    /// Number of top items to display
+    #[arg(short = 'n', long = "top", default_value = "20", help = "Number of top items to display in size and type reports")]
+    pub top_count: usize,
+
+    /// Report output format
+    #[arg(long = "report-format", value_enum, default_value = "terminal", help = "Report output format")]
+    pub report_format: ReportFormat,
+
+    /// Show the full report even for small snapshots
+    #[arg(long = "full-report", help = "Show the full report even when the snapshot is small")]
+    pub full_report: bool,
+
+    /// Language of the report
+    #[arg(long = "lang", value_enum, default_value = "en", help = "Language of the report")]
+    pub lang: crate::reporter::Lang,
+
+    /// Export the refiltered results
+    #[arg(short = 'e', long = "export", value_enum, help = "Export the refiltered results to the specified format")]
+    pub export: Option<ExportFormat>,
+
+    /// Output file path for export
+    #[arg(short = 'o', long = "output", requires = "export", help = "Output file path for export (default: auto-generated)")]
+    pub output: Option<PathBuf>,
+
+    /// List accepted findings where they belong
+    #[arg(long = "show-accepted", help = "List accepted findings in their sections instead of only counting them")]
+    pub show_accepted: bool,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+#[command(args_conflicts_with_subcommands = true)]
+pub struct AcceptArgs {
+    /// Acceptance file
+    #[arg(long = "accepted-file", value_name = "FILE", global = true, help = "Acceptance file (default: ~/.config/diranalyzer/accepted.json)")]
+    pub file: Option<PathBuf>,
+
+    /// Path or duplicate group id to accept
+    #[arg(value_name = "PATH|GROUP_ID", requires = "reason")]
+    pub target: Option<String>,
+
+    /// Why the finding is fine as it is
+    #[arg(long = "reason", value_name = "TEXT", requires = "target")]
+    pub reason: Option<String>,
+
+    #[command(subcommand)]
+    pub command: Option<AcceptCommand>,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum AcceptCommand {
+    /// List every accepted finding
+    List,
+    /// Withdraw the acceptance of a path or duplicate group id
+    Rm(AcceptRmArgs),
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct AcceptRmArgs {
+    /// Path or duplicate group id whose acceptance to withdraw
+    #[arg(value_name = "PATH|GROUP_ID")]
+    pub target: String,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct NoteArgs {
+    /// Notes file
+    #[arg(long = "notes-file", value_name = "FILE", global = true, help = "Notes file (default: ~/.config/diranalyzer/notes.json)")]
+    pub file: Option<PathBuf>,
+
+    #[command(subcommand)]
+    pub command: NoteCommand,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum NoteCommand {
+    /// Attach a note to a path, replacing any note it has
+    Add(NoteAddArgs),
+    /// List every note, flagging those whose path is gone
+    List,
+    /// Remove the note on a path
+    Rm(NoteRmArgs),
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct NoteAddArgs {
+    /// Path the note concerns
+    #[arg(value_name = "PATH")]
+    pub path: PathBuf,
+
+    /// The note
+    #[arg(value_name = "TEXT")]
+    pub text: String,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct NoteRmArgs {
+    /// Path whose note to remove
+    #[arg(value_name = "PATH")]
+    pub path: PathBuf,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct CacheArgs {
+    #[command(subcommand)]
+    pub command: CacheCommand,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum CacheCommand {
+    /// Show the size of the hash cache and the hit rate of recent runs
+    Stats(CacheStatsArgs),
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct CacheStatsArgs {
+    /// Hash cache location
+    #[arg(long = "hash-cache-dir", value_name = "DIR", help = "Directory of the hash cache (default: ~/.cache/diranalyzer/hashes)")]
+    pub dir: Option<PathBuf>,
+
+    /// Number of recent runs listed
+    #[arg(short = 'n', long = "runs", default_value = "20", help = "Number of recent runs listed")]
+    pub runs: usize,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct MirrorCheckArgs {
+    /// Original tree
+    #[arg(value_name = "SRC")]
+    pub source: PathBuf,
+
+    /// Supposed copy
+    #[arg(value_name = "DST")]
+    pub destination: PathBuf,
+
+    /// Exclude globs applied to both trees
+    #[arg(
+        long = "exclude",
+        value_name = "GLOB",
+        action = clap::ArgAction::Append,
+        help = "Exclude files/directories matching a glob relative to each tree's root"
+    )]
+    pub exclude_patterns: Vec<String>,
+
+    /// Exclude regexes applied to both trees
+    #[arg(
+        long = "exclude-regex",
+        value_name = "REGEX",
+        action = clap::ArgAction::Append,
+        help = "Exclude files/directories whose full path matches a regular expression in both trees"
+    )]
+    pub exclude_regexes: Vec<String>,
+
+    /// Include hidden files and directories
+    #[arg(short = 'a', long = "all", help = "Include hidden files and directories in both trees")]
+    pub show_hidden: bool,
+
+    /// Hash every same-size pair instead of trusting equal modification times
+    #[arg(long = "paranoid", help = "Compare the contents of every file, even with equal size and mtime")]
+    pub paranoid: bool,
+
+    /// Write the differences as JSON
+    #[arg(short = 'o', long = "output", help = "Write the differences as JSON to a file")]
+    pub output: Option<PathBuf>,
+
+    /// Report output format
+    #[arg(long = "report-format", value_enum, default_value = "terminal", help = "Report output format")]
+    pub report_format: ReportFormat,
+
+    /// Number of differences listed per category
+    #[arg(short = 'n', long = "top", default_value = "20", help = "Number of differences listed per category")]
+    pub top_count: usize,
+    /// Language of the report
+    #[arg(long = "lang", value_enum, default_value = "en", help = "Language of the report")]
+    pub lang: crate::reporter::Lang,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct CompareArgs {
+    /// Earlier run
+    #[arg(value_name = "BEFORE", help = "Earlier run: a JSON export or a json-bundle directory")]
+    pub before: PathBuf,
+
+    /// Later run
+    #[arg(value_name = "AFTER", help = "Later run: a JSON export or a json-bundle directory")]
+    pub after: PathBuf,
+
+    /// Report output format
+    #[arg(long = "report-format", value_enum, default_value = "terminal", help = "Report output format")]
+    pub report_format: ReportFormat,
+
+    /// Number of entries compared per listing
+    #[arg(short = 'n', long = "top", default_value = "10", help = "Number of top entries of either run compared per listing")]
+    pub top_count: usize,
+
+    /// Columns to fit the side-by-side layout into
+    #[arg(long = "width", value_name = "COLUMNS", help = "Width of the side-by-side layout (default: $COLUMNS, or 100)")]
+    pub width: Option<usize>,
+
+    /// Language of the report
+    #[arg(long = "lang", value_enum, default_value = "en", help = "Language of the report")]
+    pub lang: crate::reporter::Lang,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct UndoArgs {
+    /// Journal written by --dedupe
+    #[arg(value_name = "JOURNAL")]
+    pub journal: PathBuf,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct HistoryArgs {
+    /// Directory whose history to show
+    #[arg(value_name = "PATH")]
+    pub path: PathBuf,
+
+    /// Print the series as CSV for plotting
+    #[arg(long = "csv", help = "Print the history series as CSV")]
+    pub csv: bool,
+
+    /// Write the output to a file instead of stdout
+    #[arg(short = 'o', long = "output", help = "Write the history to a file instead of stdout")]
+    pub output: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, ValueEnum)]
+pub enum ReportFormat {
+    /// Colored terminal output
+    Terminal,
+    /// Plain text without colors
+    Plain,
+    /// Markdown
+    Markdown,
+}
+
+/// Filters of what a report lists and totals, applied after the scan
+///
+/// Scan-time options such as `--exclude` or `--user` decide what the walk
+/// collects. These decide which collected files a report covers: a run
+/// applies them before anything is totalled, and `report` applies them to a
+/// full snapshot without scanning again.
+#[derive(clap::Args, Debug, Clone, Default)]
+pub struct ReportFilters {
+    /// Only files modified longer ago than this
+    #[arg(
+        long = "older-than",
+        value_name = "AGE",
+        value_parser = crate::utils::parse_duration,
+        help = "Report only files last modified more than AGE ago (e.g. 90d, 1y)"
+    )]
+    pub older_than: Option<std::time::Duration>,
+
+    /// Only files modified more recently than this
+    #[arg(
+        long = "newer-than",
+        value_name = "AGE",
+        value_parser = crate::utils::parse_duration,
+        help = "Report only files last modified less than AGE ago (e.g. 2w)"
+    )]
+    pub newer_than: Option<std::time::Duration>,
+
+    /// Only files matching one of these globs
+    #[arg(
+        long = "only",
+        value_name = "GLOB",
+        action = clap::ArgAction::Append,
+        help = "Report only files whose path matches GLOB (e.g. \"*.log\"); repeat for several"
+    )]
+    pub only: Vec<String>,
+
+    /// Only files at least this large
+    #[arg(long = "larger-than", value_name = "SIZE", value_parser = crate::utils::parse_size, help = "Report only files of at least SIZE")]
+    pub larger_than: Option<u64>,
+
+    /// Only files smaller than this
+    #[arg(long = "smaller-than", value_name = "SIZE", value_parser = crate::utils::parse_size, help = "Report only files smaller than SIZE")]
+    pub smaller_than: Option<u64>,
+}
+
+/// `--depth` when no limit is set, with `--depth 0` or `--depth unlimited`
+pub const UNLIMITED_DEPTH: usize = usize::MAX;
+
+impl Args {
+    /// The `--depth` limit, `None` when unlimited
+    pub fn depth_limit(&self) -> Option<usize> {
+        (self.max_depth != UNLIMITED_DEPTH).then_some(self.max_depth)
+    }
+
+    /// Every root given on the command line, PATH first
+    ///
+    /// With a path list PATH is left out unless it was given, that is, unless
+    /// it is something other than the default `.` or more paths follow it.
+    pub fn roots(&self) -> Vec<PathBuf> {
+        let path_defaulted = self.path_list().is_some() && self.path == Path::new(".") && self.more_paths.is_empty();
+        std::iter::once(&self.path).filter(|_| !path_defaulted).chain(&self.more_paths).cloned().collect()
+    }
+
+    /// The `--paths-from` or `--paths-from0` file, and whether its paths are NUL-separated
+    pub fn path_list(&self) -> Option<(&Path, bool)> {
+        match (&self.paths_from, &self.paths_from0) {
+            (Some(file), _) => Some((file, false)),
+            (None, Some(file)) => Some((file, true)),
+            (None, None) => None,
+        }
+    }
+}
+
+impl ReportFilters {
+    /// Whether every collected file passes
+    pub fn is_empty(&self) -> bool {
+        self.older_than.is_none()
+            && self.newer_than.is_none()
+            && self.only.is_empty()
+            && self.larger_than.is_none()
+            && self.smaller_than.is_none()
+    }
+}
+
+/// Where a run's files are listed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SourceKind {
+    /// Walk the directory tree at PATH
+    Local,
+    /// Read PATH as an S3 inventory listing in CSV, without network access
+    S3Inventory,
+}
+
+/// How much of the scan a JSON export keeps
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExportScope {
+    /// The listings, totals, and findings of the report
+    Summary,
+    /// Also every file and directory collected, for `report` to refilter
+    Full,
 }
 
 #[derive(Debug, Clone, ValueEnum)]
@@ -118,6 +1147,24 @@ pub enum ExportFormat {
     Json,
     /// Export as CSV
     Csv,
+    /// Export duplicate groups as rmlint JSON
+    RmlintJson,
+    /// Stream progress and then the results as JSON lines
+    Ndjson,
+    /// A directory with a manifest and size-capped NDJSON shards
+    JsonBundle,
+}
+
+impl ExportFormat {
+    /// File extension for generated output names
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Json | ExportFormat::RmlintJson => "json",
+            ExportFormat::Csv => "csv",
+            ExportFormat::Ndjson => "ndjson",
+            ExportFormat::JsonBundle => "bundle",
+        }
+    }
 }
 
 impl std::fmt::Display for ExportFormat {
@@ -125,6 +1172,21 @@ impl std::fmt::Display for ExportFormat {
         match self {
             ExportFormat::Json => write!(f, "json"),
             ExportFormat::Csv => write!(f, "csv"),
+            ExportFormat::RmlintJson => write!(f, "rmlint-json"),
+            ExportFormat::Ndjson => write!(f, "ndjson"),
+            ExportFormat::JsonBundle => write!(f, "json-bundle"),
         }
     }
 }
+
+#[derive(Debug, Clone, ValueEnum)]
+pub enum DuplicateSort {
+    /// Most wasted space first
+    Waste,
+    /// Highest cleanup priority score first
+    Priority,
+    /// Largest file size first
+    Size,
+    /// Most copies first
+    Count,
+}
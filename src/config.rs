@@ -0,0 +1,193 @@
+//! Configuration file support
+//!
+//! Settings that are too detailed for command-line flags live in a JSON
+//! configuration file. The file is optional: every field has a default and
+//! a missing default-location file simply yields the built-in settings.
+
+use crate::annotations::PathAnnotation;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+/// Settings loaded from the configuration file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub duplicate_priority: PriorityWeights,
+    /// Maximum number of run records kept per scan root
+    pub history_retention: usize,
+    /// Fraction of unreadable first-level entries that triggers a coverage warning
+    pub coverage_warning_threshold: f64,
+    /// Fraction of walked entries removed by exclusions that triggers a pattern warning
+    pub exclude_warning_threshold: f64,
+    /// Direct entries after which a single directory is reported as slow
+    pub slow_directory_entries: u64,
+    /// Seconds spent reading one directory's entries before it is reported as slow
+    pub slow_directory_secs: f64,
+    /// Trees with fewer files get the brief report unless `--full-report` is given
+    pub brief_report_max_files: u64,
+    /// Explanations for well-known directories, consulted before the built-in ones
+    pub path_annotations: Vec<PathAnnotation>,
+    /// Build outputs and caches to tag as regenerable, in addition to the built-in rules
+    pub regenerable: RegenerableRules,
+    /// Path-based category rules, tried in order before the built-in ones and the extension map
+    pub classification_rules: Vec<ClassificationRule>,
+    /// Directories always listed among the largest, in addition to those given with `--pin`
+    pub pinned_directories: Vec<PathBuf>,
+    /// Largest owners tracked per directory; each one costs memory on every directory
+    pub owners_per_directory: usize,
+    /// Megabytes the collected files may take in memory, checked by `--check`
+    pub memory_limit_mb: Option<u64>,
+    /// Keep every directory in memory after the scan, not only those the report lists
+    pub retain_all_directories: bool,
+    /// Named expressions over the run's figures, reported in the order given
+    pub custom_metrics: Vec<MetricDefinition>,
+    /// Bytes of "Data" files from which the report breaks the category down by format
+    pub data_formats_min_size: u64,
+    /// Ages at which the file age buckets start, such as "30d" or "1y"; the first starts at 0
+    pub age_buckets: Vec<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            duplicate_priority: PriorityWeights::default(),
+            history_retention: 1_000,
+            coverage_warning_threshold: 0.1,
+            exclude_warning_threshold: 0.95,
+            slow_directory_entries: 100_000,
+            slow_directory_secs: 10.0,
+            brief_report_max_files: 100,
+            path_annotations: Vec::new(),
+            regenerable: RegenerableRules::default(),
+            classification_rules: Vec::new(),
+            pinned_directories: Vec::new(),
+            owners_per_directory: crate::scanner::DEFAULT_OWNER_LIMIT,
+            memory_limit_mb: None,
+            retain_all_directories: false,
+            custom_metrics: Vec::new(),
+            data_formats_min_size: 1_024 * 1_024 * 1_024,
+            age_buckets: crate::ages::DEFAULT_AGE_BUCKETS.map(String::from).to_vec(),
+        }
+    }
+}
+
+/// Extra rules tagging files as regenerable; they extend the built-in lists
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RegenerableRules {
+    /// Extensions without the dot, such as "o" or "pyc"
+    pub extensions: Vec<String>,
+    /// Runs of path components, such as "__pycache__" or ".cache/pip"
+    pub paths: Vec<String>,
+}
+
+/// Assigns a category to files whose path matches a glob, regardless of their extension
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClassificationRule {
+    /// Glob over `/`-separated paths, such as "node_modules/**" or "data/**"; `*` stays within
+    /// one component and `**` spans any number. Unless it starts with `/`, it may match at any depth
+    pub path: String,
+    /// Extensions without the dot the rule is limited to; empty matches every file
+    #[serde(default)]
+    pub extensions: Vec<String>,
+    pub category: String,
+}
+
+/// A custom metric: a name and the expression computing it, such as `size(type:Images) / total_size`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MetricDefinition {
+    pub name: String,
+    pub expression: String,
+}
+
+/// Weights and scales used to compute the duplicate group priority score
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PriorityWeights {
+    /// Weight of the wasted-space component
+    pub waste: f64,
+    /// Weight of the age component (time since the newest copy was modified)
+    pub age: f64,
+    /// Weight of the location component (copies in cleanup-friendly places)
+    pub location: f64,
+    /// Wasted bytes at which the waste component reaches ~63%
+    pub waste_scale_bytes: u64,
+    /// Age in days at which the age component reaches ~63%
+    pub age_scale_days: f64,
+    /// Path components that mark a location as safe to clean up
+    pub safe_locations: Vec<String>,
+}
+
+impl Default for PriorityWeights {
+    fn default() -> Self {
+        Self {
+            waste: 0.5,
+            age: 0.3,
+            location: 0.2,
+            waste_scale_bytes: 1_024 * 1_024 * 1_024,
+            age_scale_days: 365.0,
+            safe_locations: ["Downloads", "Trash", ".Trash", "tmp", "temp", "backup", "backups", "old"]
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+        }
+    }
+}
+
+impl Config {
+    /// Load configuration from `path`, or from the default location when no
+    /// path is given. An explicitly requested file must exist.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        match path {
+            Some(path) => Self::from_file(path),
+            None => match default_config_path() {
+                Some(path) if path.is_file() => Self::from_file(&path),
+                _ => Ok(Self::default()),
+            },
+        }
+    }
+
+    fn from_file(path: &Path) -> Result<Self> {
+        let contents = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+        serde_json::from_str(&contents)
+            .with_context(|| format!("Failed to parse config file: {}", path.display()))
+    }
+}
+
+/// Directory holding diranalyzer's configuration (`$XDG_CONFIG_HOME/diranalyzer`)
+pub fn config_dir() -> Option<PathBuf> {
+    std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .map(|dir| dir.join("diranalyzer"))
+}
+
+/// Directory for cached data such as scan history (`$XDG_CACHE_HOME/diranalyzer`)
+pub fn cache_dir() -> Option<PathBuf> {
+    std::env::var_os("XDG_CACHE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+        .map(|dir| dir.join("diranalyzer"))
+}
+
+/// Point `$XDG_CACHE_HOME` at a temporary directory for the rest of the test process
+///
+/// Runs that search for duplicates journal into the cache; tests must not fill the user's.
+#[cfg(test)]
+pub(crate) fn isolate_cache_dir() {
+    static CACHE: std::sync::OnceLock<tempfile::TempDir> = std::sync::OnceLock::new();
+    CACHE.get_or_init(|| {
+        let dir = tempfile::tempdir().unwrap();
+        std::env::set_var("XDG_CACHE_HOME", dir.path());
+        dir
+    });
+}
+
+/// Default configuration file location
+pub fn default_config_path() -> Option<PathBuf> {
+    config_dir().map(|dir| dir.join("config.json"))
+}
@@ -1,24 +1,76 @@
 //! Duplicate file detection using hash comparison
 
 use anyhow::Result;
-use dashmap::DashMap;
+use chrono::{DateTime, Utc};
 use indicatif::{ProgressBar, ProgressStyle};
 use rayon::prelude::*;
 
 use sha2::{Digest, Sha256};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs::File;
 use std::io::{BufReader, Read};
-use std::path::PathBuf;
-use std::sync::Arc;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
 use crate::analyzer::DuplicateGroup;
-use crate::scanner::FileEntry;
+use crate::cli::DuplicateSort;
+use crate::config::PriorityWeights;
+use crate::export::stream::{HashTicker, RecordSender};
+use crate::hash_cache::{CacheKey, HashCache};
+use crate::interop;
+use crate::interrupt::Cancellation;
+use crate::scanner::{ErrorType, FileEntry, ScanError};
+use crate::utils::latency::{IoLatency, IoOperation};
+use crate::utils::panic_message;
+
+mod journal;
+
+pub use journal::ResultsJournal;
+
+/// Algorithm name of group hashes made of object etags
+pub const ETAG_ALGORITHM: &str = "etag";
+
+/// Content hashing algorithm used to confirm duplicates
+pub trait FileHasher: Send + Sync {
+    /// Hash the contents of the file at `path`
+    fn hash_file(&self, path: &Path) -> Result<String>;
+
+    /// Name of the algorithm, which keeps cached hashes of different hashers apart
+    fn algorithm(&self) -> &str {
+        interop::NATIVE_ALGORITHM
+    }
+}
+
+/// SHA-256 content hasher
+pub struct Sha256Hasher;
+
+impl FileHasher for Sha256Hasher {
+    fn hash_file(&self, path: &Path) -> Result<String> {
+        calculate_file_hash(path)
+    }
+}
 
 /// Duplicate file finder using SHA-256 hashing
 pub struct DuplicateFinder {
     min_size: u64,
     thread_count: usize,
+    hasher: Box<dyn FileHasher>,
+    strict: bool,
+    partition_depth: Option<usize>,
+    cache: Option<Arc<HashCache>>,
+    failures: Mutex<Vec<ScanError>>,
+    /// Whether `hasher` came from [`DuplicateFinder::with_hasher`]
+    custom_hasher: bool,
+    latency: IoLatency,
+    /// Where hashing progress goes with `--export ndjson`
+    progress: Option<RecordSender>,
+    /// Where completed size groups are journaled, and taken over from with `--resume`
+    journal: Option<ResultsJournal>,
+    cancellation: Option<Cancellation>,
+    /// When hashing stops, with `--max-duration`
+    deadline: Option<Instant>,
 }
 
 impl DuplicateFinder {
@@ -29,40 +81,132 @@ impl DuplicateFinder {
         Self {
             min_size,
             thread_count,
+            hasher: Box::new(Sha256Hasher),
+            strict: false,
+            partition_depth: None,
+            cache: None,
+            failures: Mutex::new(Vec::new()),
+            custom_hasher: false,
+            latency: IoLatency::Off,
+            progress: None,
+            journal: None,
+            cancellation: None,
+            deadline: None,
         }
     }
 
+    /// Use a different content hashing algorithm
+    pub fn with_hasher(mut self, hasher: Box<dyn FileHasher>) -> Self {
+        self.hasher = hasher;
+        self.custom_hasher = true;
+        self
+    }
+
+    /// Let panics while hashing propagate instead of recording them
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Only group files sharing their ancestor directory at `depth` below the scan root
+    pub fn within_depth(mut self, depth: Option<usize>) -> Self {
+        self.partition_depth = depth;
+        self
+    }
+
+    /// Look hashes up in `cache` before reading files, and remember the new ones there
+    pub fn with_cache(mut self, cache: Arc<HashCache>) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Time the stats, opens, and reads of hashing into `latency`
+    ///
+    /// A hasher set with [`DuplicateFinder::with_hasher`] is timed as one read per file.
+    pub(crate) fn with_io_latency(mut self, latency: IoLatency) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    /// Send the share of candidates hashed to `sender`, one record per percent
+    pub(crate) fn with_progress(mut self, sender: RecordSender) -> Self {
+        self.progress = Some(sender);
+        self
+    }
+
+    /// Journal each size group to `journal` as its hashing completes
+    ///
+    /// A resuming journal hands back the groups of the size groups it holds,
+    /// which are then not hashed again. A journal that cannot be written only
+    /// costs the next `--resume` the groups it missed.
+    pub fn with_journal(mut self, journal: ResultsJournal) -> Self {
+        self.journal = Some(journal);
+        self
+    }
+
+    /// Stop hashing once `cancellation` is set
+    ///
+    /// The groups confirmed until then are returned; size groups whose files
+    /// were not all hashed are dropped rather than reported with copies missing.
+    pub fn with_cancellation(mut self, cancellation: Cancellation) -> Self {
+        self.cancellation = Some(cancellation);
+        self
+    }
+
+    /// Stop hashing at `deadline`, as a cancellation would stop it
+    pub fn with_deadline(mut self, deadline: Option<Instant>) -> Self {
+        self.deadline = deadline;
+        self
+    }
+
+    /// Take the failures recorded during the last search (panics while hashing)
+    pub fn take_failures(&mut self) -> Vec<ScanError> {
+        std::mem::take(self.failures.get_mut().unwrap_or_else(|e| e.into_inner()))
+    }
+
     /// Find duplicate files in the given file list
     pub async fn find_duplicates(&self, files: &[FileEntry]) -> Result<Vec<DuplicateGroup>> {
-        // Filter files by minimum size
-        let candidates: Vec<&FileEntry> = files
-            .iter()
-            .filter(|file| file.size >= self.min_size && !file.is_symlink)
-            .collect();
+        self.find_duplicates_with(files, |_| {}).await
+    }
 
-        if candidates.is_empty() {
-            return Ok(Vec::new());
-        }
+    /// Find duplicate files, calling `on_group` as soon as each group is confirmed
+    ///
+    /// Size groups are hashed in descending order of their potential waste
+    /// (`size × (count - 1)`), so the groups holding most of the reclaimable
+    /// space are emitted first while smaller groups are still being hashed.
+    pub async fn find_duplicates_with<F>(&self, files: &[FileEntry], mut on_group: F) -> Result<Vec<DuplicateGroup>>
+    where
+        F: FnMut(&DuplicateGroup),
+    {
+        // Objects of a listing come with etags, which stand in for the contents it cannot reach
+        let mut duplicate_groups = self.etag_groups(files);
+        duplicate_groups.iter().for_each(&mut on_group);
 
-        // Group files by size first (optimization)
-        let mut size_groups: HashMap<u64, Vec<&FileEntry>> = HashMap::new();
-        for file in candidates {
-            size_groups.entry(file.size).or_default().push(file);
+        // Size groups journaled by an interrupted search are taken over as they are
+        let mut journal = self.journal.as_ref().and_then(|journal| journal.begin().ok());
+        let mut size_groups = Vec::new();
+        for group in self.candidate_size_groups(files, false) {
+            let fingerprint = journal.as_ref().map(|_| group.fingerprint(self.hasher.algorithm()));
+            match journal.as_mut().zip(fingerprint.as_deref()).and_then(|(journal, fingerprint)| journal.take_completed(fingerprint)) {
+                Some(groups) => {
+                    groups.iter().for_each(&mut on_group);
+                    duplicate_groups.extend(groups);
+                }
+                None => size_groups.push((group, fingerprint)),
+            }
         }
-
-        // Only process size groups with multiple files
-        let potential_duplicates: Vec<&FileEntry> = size_groups
-            .into_values()
-            .filter(|group| group.len() > 1)
-            .flatten()
-            .collect();
-
-        if potential_duplicates.is_empty() {
-            return Ok(Vec::new());
+        if size_groups.is_empty() {
+            if let Some(journal) = journal {
+                let _ = journal.finish(true);
+            }
+            duplicate_groups.sort_by_key(|g| std::cmp::Reverse(g.wasted_space));
+            return Ok(duplicate_groups);
         }
 
+        let candidate_count: usize = size_groups.iter().map(|(group, _)| group.files.len()).sum();
+
         // Set up progress bar
-        let progress_bar = ProgressBar::new(potential_duplicates.len() as u64);
+        let progress_bar = ProgressBar::new(candidate_count as u64);
         progress_bar.set_style(
             ProgressStyle::default_bar()
                 .template("{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} files ({eta})")
@@ -70,70 +214,385 @@ impl DuplicateFinder {
                 .progress_chars("#>-"),
         );
         progress_bar.set_message("Hashing files...");
+        let ticker = HashTicker::new(self.progress.as_ref(), candidate_count as u64);
 
         // Configure rayon thread pool
         let pool = rayon::ThreadPoolBuilder::new()
             .num_threads(self.thread_count)
             .build()?;
 
-        // Hash files in parallel
-        let hash_map: Arc<DashMap<String, Vec<PathBuf>>> = Arc::new(DashMap::new());
-        let progress_bar = Arc::new(progress_bar);
 
-        pool.install(|| {
-            potential_duplicates
-                .par_iter()
-                .for_each(|file| {
-                    if let Ok(hash) = calculate_file_hash(&file.path) {
-                        hash_map.entry(hash).or_default().push(file.path.clone());
-                    }
-                    progress_bar.inc(1);
-                });
-        });
+        // Hash size groups in priority order. Small groups are batched together
+        // so every worker thread has files to hash; each group is journaled as
+        // soon as its batch completes.
+        let min_batch_files = self.thread_count * 8;
+        let mut remaining = size_groups.as_slice();
+        while !remaining.is_empty() {
+            let mut batch_len = 0;
+            let mut batch_files = 0;
+            while batch_len < remaining.len() && (batch_len == 0 || batch_files < min_batch_files) {
+                batch_files += remaining[batch_len].0.files.len();
+                batch_len += 1;
+            }
+            let (batch, rest) = remaining.split_at(batch_len);
+            remaining = rest;
 
-        progress_bar.finish_with_message("Hashing complete!");
+            // A size group with a file left unhashed by a cancellation or the deadline has no hashes at all
+            let hashes: Vec<Option<Vec<Option<String>>>> = pool.install(|| {
+                batch
+                    .par_iter()
+                    .map(|(group, _)| {
+                        group
+                            .files
+                            .par_iter()
+                            .map(|file| {
+                                if self.stops_here() {
+                                    return None;
+                                }
+                                let hash = self.hash_file(&file.path);
+                                progress_bar.inc(1);
+                                ticker.tick();
+                                Some(hash)
+                            })
+                            .collect()
+                    })
+                    .collect()
+            });
 
-        // Convert to duplicate groups
-        let mut duplicate_groups = Vec::new();
-        
-        for entry in hash_map.iter() {
-            let (hash, file_paths) = (entry.key(), entry.value());
-            
-            if file_paths.len() > 1 {
-                // Get file size from the first file
-                let file_size = files
-                    .iter()
-                    .find(|f| f.path == file_paths[0])
-                    .map(|f| f.size)
-                    .unwrap_or(0);
-
-                let wasted_space = file_size * (file_paths.len() as u64 - 1);
-
-                duplicate_groups.push(DuplicateGroup {
-                    hash: hash.clone(),
-                    file_size,
-                    files: file_paths.clone(),
-                    wasted_space,
-                });
+            for ((group, fingerprint), group_hashes) in batch.iter().zip(hashes) {
+                // Confirmed from some of its files, the group would be missing copies
+                let Some(group_hashes) = group_hashes else { continue };
+                let confirmed = group.confirm(&group_hashes);
+                if let Some((session, fingerprint)) = journal.as_mut().zip(fingerprint.clone()) {
+                    if session.record(fingerprint, &confirmed).is_err() {
+                        journal = None;
+                    }
+                }
+                for duplicate_group in confirmed {
+                    on_group(&duplicate_group);
+                    duplicate_groups.push(duplicate_group);
+                }
             }
+            if self.stops_here() {
+                break;
+            }
+        }
+
+        let stopped = self.stops_here();
+        progress_bar.finish_with_message(if stopped { "Hashing stopped early" } else { "Hashing complete!" });
+        // A panic while hashing never gets here, and leaves the journal for --resume as well
+        if let Some(journal) = journal {
+            let _ = journal.finish(!stopped);
         }
 
         // Sort by wasted space (descending)
-        duplicate_groups.sort_by(|a, b| b.wasted_space.cmp(&a.wasted_space));
+        duplicate_groups.sort_by_key(|g| std::cmp::Reverse(g.wasted_space));
 
         Ok(duplicate_groups)
     }
+
+    /// Whether hashing has to end here, because the run was cancelled or the time is up
+    fn stops_here(&self) -> bool {
+        self.cancellation.as_ref().is_some_and(Cancellation::is_cancelled) || self.deadline.is_some_and(|deadline| Instant::now() >= deadline)
+    }
+
+    /// Hash one file, through the cache when there is one
+    fn hash_file(&self, path: &Path) -> Option<String> {
+        let Some(cache) = &self.cache else {
+            return self.read_hash(path);
+        };
+        let metadata = self.latency.time(IoOperation::Stat, path, || std::fs::metadata(path));
+        let Some(key) = metadata.ok().as_ref().and_then(CacheKey::of) else {
+            return self.read_hash(path);
+        };
+        let algorithm = self.hasher.algorithm();
+        if let Some(hash) = cache.get(&key, algorithm) {
+            return Some(hash);
+        }
+        let hash = self.read_hash(path)?;
+        // A cache that cannot be written only costs the next run a re-read
+        let _ = cache.put(&key, algorithm, &hash);
+        Some(hash)
+    }
+
+    /// Hash one file's contents; a panic is recorded as a failure unless running strict
+    fn read_hash(&self, path: &Path) -> Option<String> {
+        let hash = || {
+            if self.custom_hasher {
+                self.latency.time(IoOperation::Read, path, || self.hasher.hash_file(path))
+            } else {
+                hash_timed(path, &self.latency)
+            }
+        };
+        if self.strict {
+            return hash().ok();
+        }
+
+        match panic::catch_unwind(AssertUnwindSafe(hash)) {
+            Ok(result) => result.ok(),
+            Err(payload) => {
+                self.failures.lock().unwrap_or_else(|e| e.into_inner()).push(ScanError {
+                    path: path.to_path_buf(),
+                    error: format!("panic while hashing: {}", panic_message(payload.as_ref())),
+                    error_type: ErrorType::Other,
+                    errno: None,
+                });
+                None
+            }
+        }
+    }
+
+    /// Groups of files with the same size and etag, marked as not content-verified
+    ///
+    /// An etag is usually the MD5 of the object, but not for multipart uploads
+    /// or encrypted objects, so equal etags are taken as equal contents
+    /// without the certainty of a hash this finder computed.
+    fn etag_groups(&self, files: &[FileEntry]) -> Vec<DuplicateGroup> {
+        let mut groups = Vec::new();
+        for size_group in self.candidate_size_groups(files, true) {
+            let etags: Vec<Option<String>> = size_group
+                .files
+                .iter()
+                .map(|file| file.etag.as_deref().map(|etag| interop::join_hash(ETAG_ALGORITHM, etag)))
+                .collect();
+            groups.extend(size_group.confirm(&etags).into_iter().map(|group| DuplicateGroup { unverified: true, ..group }));
+        }
+        groups
+    }
+
+    /// Group candidates by partition and size, keeping only sizes shared by
+    /// several files, ordered by potential waste (descending)
+    ///
+    /// Files with an etag are grouped apart from those to hash, as `with_etag` asks.
+    /// Hard links to a file are one candidate, named by the first of their paths.
+    fn candidate_size_groups<'a>(&self, files: &'a [FileEntry], with_etag: bool) -> Vec<SizeGroup<'a>> {
+        let mut by_size: HashMap<(Option<&Path>, u64), Vec<&FileEntry>> = HashMap::new();
+        let candidates = files.iter().filter(|file| file.size >= self.min_size && !file.is_symlink && file.etag.is_some() == with_etag);
+        for file in candidates {
+            let partition = self.partition_depth.and_then(|depth| partition_of(file, depth));
+            by_size.entry((partition, file.size)).or_default().push(file);
+        }
+
+        let mut size_groups: Vec<SizeGroup> = by_size
+            .into_iter()
+            .map(|(key, files)| (key, without_hard_links(files)))
+            .filter(|(_, files)| files.len() > 1)
+            .map(|((partition, size), files)| SizeGroup { size, partition, files })
+            .collect();
+        size_groups.sort_by_key(|group| std::cmp::Reverse((group.potential_waste(), group.size)));
+        size_groups
+    }
+}
+
+/// `files` with only the first path, in path order, of each inode
+///
+/// Hard links share their contents and their storage, so they are neither
+/// hashed twice nor reported as copies of one another.
+fn without_hard_links(files: Vec<&FileEntry>) -> Vec<&FileEntry> {
+    let mut first: HashMap<(u64, u64), &Path> = HashMap::new();
+    for file in &files {
+        if let Some(inode) = file.inode() {
+            let path = first.entry(inode).or_insert(&file.path);
+            *path = (*path).min(&file.path);
+        }
+    }
+    files.into_iter().filter(|file| file.inode().is_none_or(|inode| first[&inode] == file.path)).collect()
+}
+
+/// Ancestor directory of `file` at `depth` below the scan root; files shallower
+/// than that belong to the partition of their own directory
+fn partition_of(file: &FileEntry, depth: usize) -> Option<&Path> {
+    let levels_up = if file.depth > depth { file.depth - depth } else { 1 };
+    file.path.ancestors().nth(levels_up)
+}
+
+/// Files sharing one size (within one partition): the unit of hashing work
+struct SizeGroup<'a> {
+    size: u64,
+    partition: Option<&'a Path>,
+    files: Vec<&'a FileEntry>,
+}
+
+impl SizeGroup<'_> {
+    /// Fingerprint under which the group is journaled
+    fn fingerprint(&self, algorithm: &str) -> String {
+        journal::fingerprint(algorithm, self.partition, self.size, &self.files)
+    }
+
+    /// Upper bound of the space this group could waste
+    fn potential_waste(&self) -> u64 {
+        self.size.saturating_mul(self.files.len() as u64 - 1)
+    }
+
+    /// Split the group by content hash into confirmed duplicate groups
+    fn confirm(&self, hashes: &[Option<String>]) -> Vec<DuplicateGroup> {
+        let mut by_hash: HashMap<&str, Vec<&FileEntry>> = HashMap::new();
+        for (file, hash) in self.files.iter().zip(hashes) {
+            if let Some(hash) = hash {
+                by_hash.entry(hash.as_str()).or_default().push(file);
+            }
+        }
+
+        let mut groups: Vec<DuplicateGroup> = by_hash
+            .into_iter()
+            .filter(|(_, members)| members.len() > 1)
+            .map(|(hash, members)| DuplicateGroup {
+                group_id: group_id(hash, self.partition),
+                hash: hash.to_string(),
+                file_size: self.size,
+                files: sorted_paths(&members),
+                wasted_space: self.size * (physical_copies(&members) - 1),
+                newest_modified: members.iter().filter_map(|file| file.modified).max(),
+                priority: 0.0,
+                partition: self.partition.map(Path::to_path_buf),
+                regenerable: false,
+                first_seen: None,
+                last_seen: None,
+                accepted: false,
+                unverified: false,
+            })
+            .collect();
+        groups.sort_by(|a, b| b.wasted_space.cmp(&a.wasted_space).then_with(|| a.hash.cmp(&b.hash)));
+        groups
+    }
+}
+
+/// Identifier of a duplicate group that stays the same across runs
+///
+/// Derived from the hash algorithm and the content digest alone, so it does
+/// not change when copies come, go, or are listed in another order. Groups
+/// confined to a subtree by `--duplicates-within-depth` fold in their
+/// partition, which keeps the same content in two subtrees apart.
+pub fn group_id(hash: &str, partition: Option<&Path>) -> String {
+    let (algorithm, digest) = interop::split_hash(hash);
+    let mut hasher = Sha256::new();
+    hasher.update(algorithm.as_bytes());
+    hasher.update(b":");
+    hasher.update(digest.as_bytes());
+    if let Some(partition) = partition {
+        hasher.update(b"\0");
+        hasher.update(partition.as_os_str().as_encoded_bytes());
+    }
+    let id: String = hasher.finalize().iter().take(8).map(|byte| format!("{:02x}", byte)).collect();
+    format!("dg-{}", id)
+}
+
+/// Paths of a group's members in a stable order, independent of the walk
+fn sorted_paths(members: &[&FileEntry]) -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = members.iter().map(|file| file.path.clone()).collect();
+    paths.sort();
+    paths
+}
+
+/// Redundant copies and reclaimable bytes over `groups`
+///
+/// Each group keeps one copy, so it has one redundant file fewer than
+/// members; its reclaimable bytes are its wasted space, which already leaves
+/// out hard links and fully shared copies.
+pub(crate) fn duplicate_totals(groups: &[DuplicateGroup]) -> (u64, u64) {
+    let redundant = groups.iter().map(|group| group.files.len().saturating_sub(1) as u64).sum();
+    let reclaimable = groups.iter().map(|group| group.wasted_space).sum();
+    (redundant, reclaimable)
+}
+
+/// Number of copies that occupy their own storage
+///
+/// Hard links to one inode count once, as deleting all but one frees nothing;
+/// the finder leaves them out of its groups, so this only matters for entries
+/// without a recorded inode, which are statted. Fully shared copies (reflinks)
+/// are assumed to share with each other, so together they count as one too.
+/// Members that cannot be statted count alone.
+fn physical_copies(members: &[&FileEntry]) -> u64 {
+    let mut inodes = HashSet::new();
+    let mut copies = 0;
+    let mut shared = 0;
+    for file in members {
+        if file.is_fully_shared() {
+            shared = 1;
+            continue;
+        }
+        let inode = file.inode().or_else(|| std::fs::metadata(&file.path).ok().as_ref().and_then(CacheKey::of).map(|key| (key.dev, key.ino)));
+        if inode.is_none_or(|inode| inodes.insert(inode)) {
+            copies += 1;
+        }
+    }
+    copies + shared
+}
+
+/// Compute the cleanup priority of a duplicate group on a 0-100 scale
+///
+/// The score blends three components, each normalised to `0.0..=1.0`: the
+/// wasted space, the age of the newest copy, and the fraction of copies that
+/// sit in cleanup-friendly locations. Higher scores mean bigger, safer wins.
+pub fn priority_score(group: &DuplicateGroup, weights: &PriorityWeights, now: DateTime<Utc>) -> f64 {
+    let total_weight = weights.waste + weights.age + weights.location;
+    if total_weight <= 0.0 {
+        return 0.0;
+    }
+
+    let waste = saturate(group.wasted_space as f64, weights.waste_scale_bytes as f64);
+
+    // Unknown modification times count as recent, which keeps the score conservative
+    let age_days = group
+        .newest_modified
+        .map(|modified| (now - modified).num_seconds().max(0) as f64 / 86_400.0)
+        .unwrap_or(0.0);
+    let age = saturate(age_days, weights.age_scale_days);
+
+    let location = if group.files.is_empty() {
+        0.0
+    } else {
+        let safe = group
+            .files
+            .iter()
+            .filter(|path| is_safe_location(path, &weights.safe_locations))
+            .count();
+        safe as f64 / group.files.len() as f64
+    };
+
+    let weighted = weights.waste * waste + weights.age * age + weights.location * location;
+    100.0 * weighted / total_weight
+}
+
+/// Order duplicate groups according to the requested sort key (descending)
+pub fn sort_groups(groups: &mut [DuplicateGroup], order: &DuplicateSort) {
+    match order {
+        DuplicateSort::Waste => groups.sort_by_key(|g| std::cmp::Reverse(g.wasted_space)),
+        DuplicateSort::Priority => groups.sort_by(|a, b| b.priority.total_cmp(&a.priority)),
+        DuplicateSort::Size => groups.sort_by_key(|g| std::cmp::Reverse(g.file_size)),
+        DuplicateSort::Count => groups.sort_by_key(|g| std::cmp::Reverse(g.files.len())),
+    }
+}
+
+/// Map a non-negative value onto `0.0..1.0` with an exponential approach
+fn saturate(value: f64, scale: f64) -> f64 {
+    if scale <= 0.0 {
+        return if value > 0.0 { 1.0 } else { 0.0 };
+    }
+    1.0 - (-value / scale).exp()
+}
+
+fn is_safe_location(path: &std::path::Path, safe_locations: &[String]) -> bool {
+    path.components().any(|component| {
+        let name = component.as_os_str().to_string_lossy();
+        safe_locations.iter().any(|safe| safe.eq_ignore_ascii_case(&name))
+    })
 }
 
 /// Calculate SHA-256 hash of a file
-fn calculate_file_hash(path: &PathBuf) -> Result<String> {
-    let file = File::open(path)?;
+fn calculate_file_hash(path: &Path) -> Result<String> {
+    hash_timed(path, &IoLatency::Off)
+}
+
+/// SHA-256 of a file, timing its open and every read into `latency`
+fn hash_timed(path: &Path, latency: &IoLatency) -> Result<String> {
+    let file = latency.time(IoOperation::Open, path, || File::open(path))?;
     let mut reader = BufReader::new(file);
     let mut hasher = Sha256::new();
     let mut buffer = [0; 8192]; // 8KB buffer
 
     loop {
-        let bytes_read = reader.read(&mut buffer)?;
+        let bytes_read = latency.time(IoOperation::Read, path, || reader.read(&mut buffer))?;
         if bytes_read == 0 {
             break;
         }
@@ -143,6 +602,20 @@ fn calculate_file_hash(path: &PathBuf) -> Result<String> {
     Ok(format!("{:x}", hasher.finalize()))
 }
 
+/// Hashes normally but panics on files whose name contains "poison", for tests of the hashing error path
+#[cfg(test)]
+pub(crate) struct PoisonedHasher;
+
+#[cfg(test)]
+impl FileHasher for PoisonedHasher {
+    fn hash_file(&self, path: &Path) -> Result<String> {
+        if path.to_string_lossy().contains("poison") {
+            panic!("pathological path");
+        }
+        calculate_file_hash(path)
+    }
+}
+
 // External dependency for CPU count detection
 mod num_cpus {
     pub fn get() -> usize {
@@ -151,3 +624,524 @@ mod num_cpus {
             .unwrap_or(4)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use std::path::PathBuf;
+
+    const MB: u64 = 1_024 * 1_024;
+
+    fn group(paths: &[&str], file_size: u64, newest_modified: Option<DateTime<Utc>>) -> DuplicateGroup {
+        DuplicateGroup {
+            group_id: String::new(),
+            hash: "abc".to_string(),
+            file_size,
+            files: paths.iter().map(PathBuf::from).collect(),
+            wasted_space: file_size * (paths.len() as u64 - 1),
+            newest_modified,
+            priority: 0.0,
+            partition: None,
+            regenerable: false,
+            first_seen: None,
+            last_seen: None,
+            accepted: false,
+            unverified: false,
+        }
+    }
+
+    #[test]
+    fn test_old_download_outranks_active_project() {
+        let now = Utc::now();
+        let weights = PriorityWeights::default();
+
+        let downloads = group(
+            &["/home/u/Downloads/a.iso", "/home/u/Downloads/b.iso"],
+            500 * MB,
+            Some(now - Duration::days(5 * 365)),
+        );
+        let project = group(
+            &["/home/u/project/data.bin", "/home/u/project/copy.bin"],
+            600 * MB,
+            Some(now - Duration::days(1)),
+        );
+
+        assert!(priority_score(&downloads, &weights, now) > priority_score(&project, &weights, now));
+    }
+
+    #[test]
+    fn test_priority_score_bounds() {
+        let now = Utc::now();
+        let weights = PriorityWeights::default();
+
+        let empty = group(&["/x/a", "/x/b"], 0, None);
+        assert_eq!(priority_score(&empty, &weights, now), 0.0);
+
+        let maximal = group(
+            &["/tmp/a", "/tmp/b"],
+            u64::MAX / 4,
+            Some(now - Duration::days(100 * 365)),
+        );
+        let score = priority_score(&maximal, &weights, now);
+        assert!(score > 99.0 && score <= 100.0);
+    }
+
+    #[test]
+    fn test_priority_score_respects_weights() {
+        let now = Utc::now();
+        let only_location = PriorityWeights {
+            waste: 0.0,
+            age: 0.0,
+            location: 1.0,
+            ..PriorityWeights::default()
+        };
+
+        let half_safe = group(&["/home/u/Downloads/a", "/home/u/work/a"], MB, None);
+        assert!((priority_score(&half_safe, &only_location, now) - 50.0).abs() < 1e-9);
+
+        let no_weights = PriorityWeights {
+            waste: 0.0,
+            age: 0.0,
+            location: 0.0,
+            ..PriorityWeights::default()
+        };
+        assert_eq!(priority_score(&half_safe, &no_weights, now), 0.0);
+    }
+
+    #[test]
+    fn test_future_modification_time_counts_as_recent() {
+        let now = Utc::now();
+        let weights = PriorityWeights {
+            waste: 0.0,
+            age: 1.0,
+            location: 0.0,
+            ..PriorityWeights::default()
+        };
+        let future = group(&["/a", "/b"], MB, Some(now + Duration::days(30)));
+        assert_eq!(priority_score(&future, &weights, now), 0.0);
+    }
+
+    fn entry(path: &std::path::Path) -> FileEntry {
+        let metadata = std::fs::metadata(path).unwrap();
+        let inode = CacheKey::of(&metadata);
+        FileEntry {
+            path: path.to_path_buf(),
+            size: metadata.len(),
+            depth: 1,
+            dev: inode.as_ref().map_or(0, |key| key.dev),
+            ino: inode.as_ref().map_or(0, |key| key.ino),
+            nlink: 1,
+            ..Default::default()
+        }
+    }
+
+    /// Write `copies` identical files of `size` bytes filled with `byte`
+    fn write_copies(dir: &std::path::Path, name: &str, byte: u8, size: usize, copies: usize) -> Vec<FileEntry> {
+        (0..copies)
+            .map(|i| {
+                let path = dir.join(format!("{}{}", name, i));
+                std::fs::write(&path, vec![byte; size]).unwrap();
+                entry(&path)
+            })
+            .collect()
+    }
+
+    /// Straightforward grouping of every candidate by hash, used as the reference result
+    fn reference_groups(files: &[FileEntry], min_size: u64) -> Vec<(String, Vec<PathBuf>)> {
+        let mut by_hash: HashMap<String, Vec<PathBuf>> = HashMap::new();
+        for file in files.iter().filter(|f| f.size >= min_size) {
+            by_hash.entry(calculate_file_hash(&file.path).unwrap()).or_default().push(file.path.clone());
+        }
+        let mut groups: Vec<_> = by_hash
+            .into_iter()
+            .filter(|(_, paths)| paths.len() > 1)
+            .map(|(hash, mut paths)| {
+                paths.sort();
+                (hash, paths)
+            })
+            .collect();
+        groups.sort();
+        groups
+    }
+
+    fn fixture(dir: &std::path::Path) -> Vec<FileEntry> {
+        let mut files = Vec::new();
+        files.extend(write_copies(dir, "big", b'a', 4_000, 2));      // potential waste 4000
+        files.extend(write_copies(dir, "many", b'b', 1_500, 4));     // potential waste 4500
+        files.extend(write_copies(dir, "small", b'c', 100, 3));
+        files.extend(write_copies(dir, "unique", b'd', 2_000, 1));
+        // Same size as "small" but different content: shares the size group, not the hash group
+        files.extend(write_copies(dir, "lookalike", b'e', 100, 1));      // potential waste 300
+        files
+    }
+
+    #[tokio::test]
+    async fn test_grouping_matches_reference() {
+        let dir = tempfile::tempdir().unwrap();
+        let files = fixture(dir.path());
+
+        let finder = DuplicateFinder::new(1, Some(2));
+        let mut found: Vec<_> = finder
+            .find_duplicates(&files)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|group| {
+                let mut paths = group.files;
+                paths.sort();
+                (group.hash, paths)
+            })
+            .collect();
+        found.sort();
+
+        assert_eq!(found, reference_groups(&files, 1));
+        assert_eq!(found.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_groups_are_emitted_in_priority_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let files = fixture(dir.path());
+
+        let finder = DuplicateFinder::new(1, Some(1));
+        let mut emitted = Vec::new();
+        let result = finder
+            .find_duplicates_with(&files, |group| emitted.push(group.wasted_space))
+            .await
+            .unwrap();
+
+        assert_eq!(emitted, vec![4_500, 4_000, 200]);
+        assert_eq!(result.len(), emitted.len());
+    }
+
+    #[tokio::test]
+    async fn test_reflinked_copies_do_not_waste_space() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut files = write_copies(dir.path(), "clone", b'r', 1_000, 4);
+        // Three copies are reflinks of each other; the fourth is a real copy
+        for file in &mut files[..3] {
+            file.shared_extents = Some(1_000);
+        }
+        files[3].shared_extents = Some(0);
+
+        let groups = DuplicateFinder::new(1, Some(2)).find_duplicates(&files).await.unwrap();
+        assert_eq!(groups[0].files.len(), 4);
+        assert_eq!(groups[0].wasted_space, 1_000);
+
+        files[3].shared_extents = Some(1_000);
+        let groups = DuplicateFinder::new(1, Some(2)).find_duplicates(&files).await.unwrap();
+        assert_eq!(groups[0].wasted_space, 0);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_hard_links_are_not_duplicates() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut files = write_copies(dir.path(), "copy", b'h', 1_000, 2);
+        // Two more names for the first copy's inode
+        for name in ["link0", "link1"] {
+            let path = dir.path().join(name);
+            std::fs::hard_link(&files[0].path, &path).unwrap();
+            files.push(entry(&path));
+        }
+
+        let groups = DuplicateFinder::new(1, Some(2)).find_duplicates(&files).await.unwrap();
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].files, [files[0].path.clone(), files[1].path.clone()]);
+        assert_eq!(groups[0].wasted_space, 1_000);
+
+        // Once the other copy is a link too, nothing is left to deduplicate
+        std::fs::remove_file(&files[1].path).unwrap();
+        std::fs::hard_link(&files[0].path, &files[1].path).unwrap();
+        files[1] = entry(&files[1].path);
+        assert!(DuplicateFinder::new(1, Some(2)).find_duplicates(&files).await.unwrap().is_empty());
+
+        // Without recorded inodes, links are grouped but still waste nothing
+        for file in &mut files {
+            file.ino = 0;
+        }
+        let groups = DuplicateFinder::new(1, Some(2)).find_duplicates(&files).await.unwrap();
+        assert_eq!((groups[0].files.len(), groups[0].wasted_space), (4, 0));
+    }
+
+    #[test]
+    fn test_duplicate_totals_keep_one_copy_per_group() {
+        let mut linked = group(&["/x/a", "/x/a-link", "/x/b"], 300, None);
+        // One member is a hard link to another, so only one copy takes space of its own
+        linked.wasted_space = 300;
+        let groups = [group(&["/p/1", "/p/2", "/p/3", "/p/4"], 1_000, None), linked, group(&["/q/1", "/q/2"], 5, None)];
+        assert_eq!(duplicate_totals(&groups), (3 + 2 + 1, 3_000 + 300 + 5));
+        assert_eq!(duplicate_totals(&[]), (0, 0));
+    }
+
+    #[tokio::test]
+    async fn test_min_size_excludes_small_groups() {
+        let dir = tempfile::tempdir().unwrap();
+        let files = fixture(dir.path());
+
+        let groups = DuplicateFinder::new(1_000, Some(2)).find_duplicates(&files).await.unwrap();
+        assert_eq!(groups.len(), 2);
+        assert!(groups.iter().all(|group| group.file_size >= 1_000));
+    }
+
+    #[tokio::test]
+    async fn test_hash_panics_are_recorded() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut files = fixture(dir.path());
+        files.extend(write_copies(dir.path(), "poison", b'p', 700, 2));
+
+        let mut finder = DuplicateFinder::new(1, Some(2)).with_hasher(Box::new(PoisonedHasher));
+        let groups = finder.find_duplicates(&files).await.unwrap();
+
+        assert_eq!(groups.len(), 3);
+        let failures = finder.take_failures();
+        assert_eq!(failures.len(), 2);
+        assert!(failures[0].error.contains("pathological path"));
+        assert!(finder.take_failures().is_empty());
+    }
+
+    #[tokio::test]
+    #[should_panic]
+    async fn test_strict_mode_propagates_hash_panics() {
+        let dir = tempfile::tempdir().unwrap();
+        let files = write_copies(dir.path(), "poison", b'p', 700, 2);
+
+        let finder = DuplicateFinder::new(1, Some(2)).with_hasher(Box::new(PoisonedHasher)).strict(true);
+        let _ = finder.find_duplicates(&files).await;
+    }
+
+    #[tokio::test]
+    async fn test_partitions_confine_groups() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let mut files = Vec::new();
+        // home/alice/{a,b} and home/bob/{c} hold identical content; bob also has a nested pair
+        for (relative, byte) in [
+            ("home/alice/a.bin", b'x'),
+            ("home/alice/docs/b.bin", b'x'),
+            ("home/bob/c.bin", b'x'),
+            ("home/bob/d.bin", b'y'),
+            ("home/bob/e.bin", b'y'),
+        ] {
+            let path = root.join(relative);
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(&path, vec![byte; 500]).unwrap();
+            files.push(FileEntry { depth: Path::new(relative).components().count(), ..entry(&path) });
+        }
+
+        let unpartitioned = DuplicateFinder::new(1, Some(2)).find_duplicates(&files).await.unwrap();
+        assert_eq!(unpartitioned.iter().map(|g| g.files.len()).max(), Some(3));
+
+        let groups = DuplicateFinder::new(1, Some(2))
+            .within_depth(Some(2))
+            .find_duplicates(&files)
+            .await
+            .unwrap();
+        let mut found: Vec<_> = groups
+            .iter()
+            .map(|g| (g.partition.clone().unwrap(), g.files.len()))
+            .collect();
+        found.sort();
+        assert_eq!(found, vec![(root.join("home/alice"), 2), (root.join("home/bob"), 2)]);
+    }
+
+    #[test]
+    fn test_partition_of_shallow_files_is_their_directory() {
+        let file = |path: &str, depth| FileEntry {
+            path: PathBuf::from(path),
+            size: 1,
+            depth,
+            ..Default::default()
+        };
+        assert_eq!(partition_of(&file("/root/top.bin", 1), 2), Some(Path::new("/root")));
+
+        let deep = file("/root/a/b/c/deep.bin", 4);
+        assert_eq!(partition_of(&deep, 2), Some(Path::new("/root/a/b")));
+    }
+
+    /// Panics on `interrupt_at`, counting the files it hashed
+    struct InterruptingHasher {
+        interrupt_at: Option<PathBuf>,
+        hashed: Arc<std::sync::atomic::AtomicUsize>,
+    }
+
+    impl FileHasher for InterruptingHasher {
+        fn hash_file(&self, path: &Path) -> Result<String> {
+            if self.interrupt_at.as_deref() == Some(path) {
+                panic!("interrupted");
+            }
+            self.hashed.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            calculate_file_hash(path)
+        }
+    }
+
+    fn sorted_groups(mut groups: Vec<DuplicateGroup>) -> Vec<(String, Vec<PathBuf>, u64)> {
+        groups.sort_by(|a, b| a.group_id.cmp(&b.group_id));
+        groups.into_iter().map(|group| (group.group_id, group.files, group.wasted_space)).collect()
+    }
+
+    #[test]
+    fn test_resume_takes_over_journaled_size_groups() {
+        let dir = tempfile::tempdir().unwrap();
+        let journal = ResultsJournal::at(dir.path().join("journal").join("root.ndjson"));
+        // Six size groups of two copies and one of two different files; one
+        // thread hashes them in a batch of the four largest, then the other three
+        let mut files = Vec::new();
+        for (i, byte) in (b'a'..=b'f').enumerate() {
+            files.extend(write_copies(dir.path(), &format!("copy{}-", i), byte, 600 + 100 * i, 2));
+        }
+        // Another size group whose files all differ, journaled without groups
+        files.extend(write_copies(dir.path(), "x", b'x', 1_200, 1));
+        files.extend(write_copies(dir.path(), "y", b'y', 1_200, 1));
+        let finder = |interrupt_at: Option<PathBuf>, hashed: &Arc<std::sync::atomic::AtomicUsize>, resume: bool| {
+            DuplicateFinder::new(1, Some(1))
+                .with_hasher(Box::new(InterruptingHasher { interrupt_at, hashed: Arc::clone(hashed) }))
+                .strict(true)
+                .with_journal(journal.clone().resuming(resume))
+        };
+        let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+
+        let hashed = Arc::default();
+        let fresh = runtime.block_on(finder(None, &hashed, false).find_duplicates(&files)).unwrap();
+        assert_eq!(hashed.load(std::sync::atomic::Ordering::SeqCst), 14);
+        // A search that completes leaves no journal behind
+        assert!(!journal.path().exists());
+
+        // Interrupt the second batch, on the smallest size group
+        let hashed = Arc::default();
+        let interrupted = finder(Some(files[0].path.clone()), &hashed, false);
+        let outcome = panic::catch_unwind(AssertUnwindSafe(|| runtime.block_on(interrupted.find_duplicates(&files))));
+        assert!(outcome.is_err());
+        // The interruption may cut a record short
+        let mut journal_file = File::options().append(true).open(journal.path()).unwrap();
+        std::io::Write::write_all(&mut journal_file, b"{\"record\":\"size_gr").unwrap();
+
+        // A size group of the first batch changed since it was journaled
+        let mut changed = files.clone();
+        changed[10].modified = Some(Utc::now());
+        let hashed = Arc::default();
+        let resumed = runtime.block_on(finder(None, &hashed, true).find_duplicates(&changed)).unwrap();
+        assert_eq!(hashed.load(std::sync::atomic::Ordering::SeqCst), 8, "only the second batch and the changed group are hashed again");
+        assert_eq!(sorted_groups(resumed), sorted_groups(fresh));
+        assert!(!journal.path().exists());
+    }
+
+    struct CancellingHasher {
+        cancel_at: PathBuf,
+        cancellation: Cancellation,
+        hashed: Arc<std::sync::atomic::AtomicUsize>,
+    }
+
+    impl FileHasher for CancellingHasher {
+        fn hash_file(&self, path: &Path) -> Result<String> {
+            if path == self.cancel_at {
+                self.cancellation.cancel();
+            }
+            self.hashed.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            calculate_file_hash(path)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cancellation_keeps_only_fully_hashed_groups() {
+        let dir = tempfile::tempdir().unwrap();
+        // Five size groups of two copies; one thread hashes the four largest first, largest first
+        let mut files = Vec::new();
+        for (i, byte) in (b'a'..=b'e').enumerate() {
+            files.extend(write_copies(dir.path(), &format!("copy{}-", i), byte, 600 + 100 * i, 2));
+        }
+        let journal = ResultsJournal::at(dir.path().join("journal.ndjson"));
+        let cancellation = Cancellation::new();
+        let hashed = Arc::default();
+        let finder = DuplicateFinder::new(1, Some(1))
+            .with_hasher(Box::new(CancellingHasher {
+                cancel_at: dir.path().join("copy4-1"),
+                cancellation: cancellation.clone(),
+                hashed: Arc::clone(&hashed),
+            }))
+            .with_cancellation(cancellation)
+            .with_journal(journal.clone());
+
+        // Cancelled on the second copy of the largest group, which still completes
+        let groups = finder.find_duplicates(&files).await.unwrap();
+        assert_eq!(hashed.load(std::sync::atomic::Ordering::SeqCst), 2);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].files, [dir.path().join("copy4-0"), dir.path().join("copy4-1")]);
+        // The journal stays for --resume
+        assert!(journal.path().exists());
+    }
+
+    #[tokio::test]
+    async fn test_etags_group_objects_without_reading_them() {
+        let object = |path: &str, size, etag: &str| FileEntry {
+            path: PathBuf::from(path),
+            size,
+            depth: 1,
+            etag: Some(etag.to_string()),
+            ..Default::default()
+        };
+        let files = [
+            object("s3://bucket/a.iso", 900, "9b2cf535f27731c9"),
+            object("s3://bucket/b.iso", 900, "9b2cf535f27731c9"),
+            object("s3://bucket/c.iso", 900, "0d6e4079e36703eb"),
+            object("s3://bucket/d.iso", 800, "9b2cf535f27731c9"),
+        ];
+
+        let groups = DuplicateFinder::new(1, Some(2)).find_duplicates(&files).await.unwrap();
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].files, [PathBuf::from("s3://bucket/a.iso"), PathBuf::from("s3://bucket/b.iso")]);
+        assert_eq!(groups[0].hash, "etag:9b2cf535f27731c9");
+        assert!(groups[0].unverified);
+    }
+
+    #[test]
+    fn test_group_id_depends_on_content_only() {
+        let id = group_id("abc123", None);
+        assert!(id.starts_with("dg-") && id.len() == 19, "{}", id);
+        assert_eq!(id, group_id("sha256:abc123", None));
+        assert_ne!(id, group_id("blake2b:abc123", None));
+        assert_ne!(id, group_id("abc123", Some(Path::new("/root/a"))));
+        assert_ne!(group_id("abc123", Some(Path::new("/root/a"))), group_id("abc123", Some(Path::new("/root/b"))));
+    }
+
+    #[tokio::test]
+    async fn test_group_ids_survive_reordered_copies() {
+        let dir = tempfile::tempdir().unwrap();
+        let files = fixture(dir.path());
+        let mut reversed = files.clone();
+        reversed.reverse();
+
+        let ids = |groups: Vec<DuplicateGroup>| {
+            let mut ids: Vec<String> = groups.into_iter().map(|group| group.group_id).collect();
+            ids.sort();
+            ids
+        };
+        let forward = ids(DuplicateFinder::new(1, Some(2)).find_duplicates(&files).await.unwrap());
+        let backward = ids(DuplicateFinder::new(1, Some(2)).find_duplicates(&reversed).await.unwrap());
+        assert!(!forward.is_empty());
+        assert_eq!(forward, backward);
+    }
+
+    #[test]
+    fn test_sort_groups() {
+        let mut groups = vec![
+            group(&["/a", "/b", "/c"], 10, None),
+            group(&["/d", "/e"], 100, None),
+        ];
+        groups[0].priority = 90.0;
+        groups[1].priority = 10.0;
+
+        sort_groups(&mut groups, &DuplicateSort::Waste);
+        assert_eq!(groups[0].file_size, 100);
+
+        sort_groups(&mut groups, &DuplicateSort::Count);
+        assert_eq!(groups[0].files.len(), 3);
+
+        sort_groups(&mut groups, &DuplicateSort::Size);
+        assert_eq!(groups[0].file_size, 100);
+
+        sort_groups(&mut groups, &DuplicateSort::Priority);
+        assert_eq!(groups[0].priority, 90.0);
+    }
+}
@@ -0,0 +1,224 @@
+//! Results journal of the duplicate search, read back by `--resume`
+//!
+//! A journal is newline-delimited JSON: a header line carrying the format
+//! version, then one line per size group whose hashing completed, holding the
+//! group's fingerprint and the duplicate groups confirmed in it (none, when
+//! its files all differ). Each line is written and flushed as its size group
+//! completes, and the file is synced every few seconds and when the search
+//! ends, so an interrupted search loses at most its last seconds of hashing.
+//! A search that completes removes its journal: there is nothing left to resume.
+//!
+//! The fingerprint covers the hash algorithm, the partition, the size, and
+//! every member's path and modification time: a size group whose files came,
+//! went, or changed since it was journaled is hashed again.
+
+use crate::analyzer::DuplicateGroup;
+use crate::config;
+use crate::history::root_file_name;
+use crate::scanner::FileEntry;
+use crate::writes::WriteGuard;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// Current results journal format version
+pub const JOURNAL_VERSION: u32 = 1;
+
+/// Longest stretch of journaled groups that may sit unsynced
+const SYNC_INTERVAL: Duration = Duration::from_secs(2);
+
+/// One line of a results journal
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "record", rename_all = "snake_case")]
+enum JournalRecord {
+    Header {
+        version: u32,
+        created: DateTime<Utc>,
+        tool_version: String,
+    },
+    /// A size group whose hashing completed
+    SizeGroup {
+        fingerprint: String,
+        groups: Vec<DuplicateGroup>,
+    },
+}
+
+/// Where the duplicate search of one root journals its completed size groups
+#[derive(Debug, Clone)]
+pub struct ResultsJournal {
+    path: PathBuf,
+    writes: WriteGuard,
+    resume: bool,
+}
+
+impl ResultsJournal {
+    /// The journal of `root` inside the cache directory
+    pub fn for_root(root: &Path) -> Option<Self> {
+        let dir = config::cache_dir()?.join("duplicates");
+        Some(Self::at(dir.join(root_file_name(root))))
+    }
+
+    /// A journal at an explicit path, started afresh by each search
+    pub fn at(path: PathBuf) -> Self {
+        Self { path, writes: WriteGuard::unrestricted(), resume: false }
+    }
+
+    /// Write the journal through `writes`
+    pub fn with_writes(mut self, writes: WriteGuard) -> Self {
+        self.writes = writes;
+        self
+    }
+
+    /// Take over the size groups already journaled instead of starting afresh
+    pub fn resuming(mut self, resume: bool) -> Self {
+        self.resume = resume;
+        self
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Open the journal for one search, loading what it holds when resuming
+    ///
+    /// A journal that is missing, of another version, or not a journal at all
+    /// is started afresh.
+    pub(crate) fn begin(&self) -> Result<JournalSession> {
+        let loaded = if self.resume { load(&self.path) } else { None };
+        if let Some(parent) = self.path.parent() {
+            self.writes.create_dir_all(parent)?;
+        }
+        let context = || format!("Failed to open duplicate journal: {}", self.path.display());
+
+        let appending = loaded.is_some();
+        let (file, completed) = match loaded {
+            Some((completed, torn)) => {
+                let mut file = self.writes.open(&self.path, File::options().append(true)).with_context(context)?;
+                // An interrupted write leaves a partial line, which must not swallow the next record
+                if torn {
+                    file.write_all(b"\n")?;
+                }
+                (file, completed)
+            }
+            None => {
+                let file = self
+                    .writes
+                    .open(&self.path, File::options().write(true).create(true).truncate(true))
+                    .with_context(context)?;
+                (file, HashMap::new())
+            }
+        };
+
+        let mut session = JournalSession {
+            path: self.path.clone(),
+            writer: BufWriter::new(file),
+            completed,
+            last_sync: Instant::now(),
+        };
+        if !appending {
+            session.write(&JournalRecord::Header {
+                version: JOURNAL_VERSION,
+                created: Utc::now(),
+                tool_version: env!("CARGO_PKG_VERSION").to_string(),
+            })?;
+        }
+        Ok(session)
+    }
+}
+
+/// A journal open for one search
+pub(crate) struct JournalSession {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    /// Groups of the size groups journaled before, by fingerprint
+    completed: HashMap<String, Vec<DuplicateGroup>>,
+    last_sync: Instant,
+}
+
+impl JournalSession {
+    /// Take the groups journaled for the size group with `fingerprint`, if it completed before
+    pub(crate) fn take_completed(&mut self, fingerprint: &str) -> Option<Vec<DuplicateGroup>> {
+        self.completed.remove(fingerprint)
+    }
+
+    /// Journal a completed size group, syncing when the last sync is long enough ago
+    pub(crate) fn record(&mut self, fingerprint: String, groups: &[DuplicateGroup]) -> Result<()> {
+        self.write(&JournalRecord::SizeGroup { fingerprint, groups: groups.to_vec() })?;
+        if self.last_sync.elapsed() >= SYNC_INTERVAL {
+            self.sync()?;
+        }
+        Ok(())
+    }
+
+    /// Remove the journal of a search that completed, or sync everything journaled so far
+    pub(crate) fn finish(mut self, complete: bool) -> Result<()> {
+        if !complete {
+            return self.sync();
+        }
+        drop(self.writer);
+        std::fs::remove_file(&self.path).with_context(|| format!("Failed to remove duplicate journal: {}", self.path.display()))
+    }
+
+    fn write(&mut self, record: &JournalRecord) -> Result<()> {
+        serde_json::to_writer(&mut self.writer, record)?;
+        self.writer.write_all(b"\n")?;
+        self.writer
+            .flush()
+            .with_context(|| format!("Failed to write duplicate journal: {}", self.path.display()))
+    }
+
+    fn sync(&mut self) -> Result<()> {
+        self.writer.get_ref().sync_data()?;
+        self.last_sync = Instant::now();
+        Ok(())
+    }
+}
+
+/// Completed size groups of the journal at `path`, and whether its last line is torn
+///
+/// Lines that do not parse are skipped: the last one may have been cut short
+/// by the interruption the journal is resumed from.
+fn load(path: &Path) -> Option<(HashMap<String, Vec<DuplicateGroup>>, bool)> {
+    let contents = std::fs::read_to_string(path).ok()?;
+    let mut lines = contents.lines();
+    match serde_json::from_str(lines.next()?) {
+        Ok(JournalRecord::Header { version, .. }) if version == JOURNAL_VERSION => {}
+        _ => return None,
+    }
+
+    let mut completed = HashMap::new();
+    for line in lines {
+        if let Ok(JournalRecord::SizeGroup { fingerprint, groups }) = serde_json::from_str(line) {
+            completed.insert(fingerprint, groups);
+        }
+    }
+    Some((completed, !contents.ends_with('\n')))
+}
+
+/// Fingerprint of a size group: what its hashing depended on
+pub(crate) fn fingerprint(algorithm: &str, partition: Option<&Path>, size: u64, files: &[&FileEntry]) -> String {
+    let mut members: Vec<(&Path, Option<DateTime<Utc>>)> = files.iter().map(|file| (file.path.as_path(), file.modified)).collect();
+    members.sort();
+
+    let mut hasher = Sha256::new();
+    hasher.update(algorithm.as_bytes());
+    hasher.update(b"\0");
+    if let Some(partition) = partition {
+        hasher.update(partition.as_os_str().as_encoded_bytes());
+    }
+    hasher.update(b"\0");
+    hasher.update(size.to_le_bytes());
+    for (path, modified) in members {
+        hasher.update(b"\0");
+        hasher.update(path.as_os_str().as_encoded_bytes());
+        hasher.update(b"\0");
+        hasher.update(modified.map_or(i64::MIN, |modified| modified.timestamp_nanos_opt().unwrap_or(i64::MAX)).to_le_bytes());
+    }
+    format!("{:x}", hasher.finalize())
+}
@@ -0,0 +1,399 @@
+//! Content entropy sampling of recently modified files
+//!
+//! Ransomware leaves behind encrypted files, and encrypted data looks like
+//! random bytes. With `--entropy-sample`, files modified within a recent
+//! window get a few kilobytes read at two offsets and the Shannon entropy of
+//! each sample measured. Directories are then rated by the share of their
+//! recent bytes that looked random, and a share that jumped since the last
+//! run is flagged.
+//!
+//! This is a heuristic. Compressed archives, media files, and packages are
+//! legitimately high-entropy, so a high share alone means little; a sudden
+//! jump in a directory of documents is the signal worth a look.
+
+use crate::scanner::FileEntry;
+use chrono::{DateTime, Duration, Utc};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+/// Bytes read per sample
+pub const SAMPLE_SIZE: u64 = 4096;
+
+/// Files smaller than this give too few bytes for a meaningful estimate
+pub const MIN_FILE_SIZE: u64 = 1024;
+
+/// Entropy in bits per byte from which a sample counts as random-looking
+///
+/// Text and executables stay well below 7; compressed and encrypted data
+/// measure above 7.9 on a full sample.
+pub const HIGH_ENTROPY_BITS: f64 = 7.5;
+
+/// Increase of a directory's high-entropy share over the last run that is flagged
+pub const JUMP_THRESHOLD: f64 = 0.25;
+
+/// Shannon entropy of `bytes` in bits per byte, from 0 (constant) to 8 (uniform)
+pub fn shannon_entropy(bytes: &[u8]) -> f64 {
+    if bytes.is_empty() {
+        return 0.0;
+    }
+    let mut counts = [0u64; 256];
+    for &byte in bytes {
+        counts[byte as usize] += 1;
+    }
+    let len = bytes.len() as f64;
+    counts
+        .iter()
+        .filter(|&&count| count > 0)
+        .map(|&count| {
+            let p = count as f64 / len;
+            -p * p.log2()
+        })
+        .sum()
+}
+
+/// Whether a sample's entropy makes it look encrypted or compressed
+pub fn is_high_entropy(bits: f64) -> bool {
+    bits >= HIGH_ENTROPY_BITS
+}
+
+/// Offsets and lengths of the samples read from a file of `size` bytes
+///
+/// The start and the middle: partial encryption often only touches the
+/// beginning, while container formats hide their payload behind a header.
+fn sample_ranges(size: u64) -> Vec<(u64, u64)> {
+    let first = (0, size.min(SAMPLE_SIZE));
+    if size >= 2 * SAMPLE_SIZE {
+        vec![first, (size / 2, SAMPLE_SIZE)]
+    } else {
+        vec![first]
+    }
+}
+
+/// The highest entropy among the samples of `path`
+fn sample_file(path: &Path, ranges: &[(u64, u64)]) -> std::io::Result<f64> {
+    let mut file = File::open(path)?;
+    let mut buffer = Vec::with_capacity(SAMPLE_SIZE as usize);
+    let mut highest: f64 = 0.0;
+    for &(offset, len) in ranges {
+        buffer.clear();
+        file.seek(SeekFrom::Start(offset))?;
+        (&mut file).take(len).read_to_end(&mut buffer)?;
+        highest = highest.max(shannon_entropy(&buffer));
+    }
+    Ok(highest)
+}
+
+/// Which files are sampled and how much may be read
+#[derive(Debug, Clone)]
+pub struct SampleOptions {
+    /// Only files modified within this many days are sampled
+    pub window_days: u64,
+    /// Maximum number of bytes read across all samples
+    pub budget: u64,
+}
+
+/// Sampled share of high-entropy bytes in one directory
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DirectoryEntropy {
+    pub path: PathBuf,
+    /// Sizes of the sampled files directly in the directory
+    pub sampled_size: u64,
+    /// Sizes of those that looked random
+    pub high_entropy_size: u64,
+    /// The share on the last run that sampled this directory
+    #[serde(default)]
+    pub previous_ratio: Option<f64>,
+    /// The share rose by at least [`JUMP_THRESHOLD`] since then
+    #[serde(default)]
+    pub jumped: bool,
+}
+
+impl DirectoryEntropy {
+    /// Fraction of the sampled bytes that looked random
+    pub fn ratio(&self) -> f64 {
+        if self.sampled_size == 0 {
+            0.0
+        } else {
+            self.high_entropy_size as f64 / self.sampled_size as f64
+        }
+    }
+}
+
+/// Outcome of an `--entropy-sample` run
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct EntropyReport {
+    pub window_days: u64,
+    pub budget: u64,
+    /// Bytes actually read, never more than the budget
+    pub bytes_read: u64,
+    pub sampled_files: u64,
+    /// Recent files left unsampled because the budget ran out
+    pub skipped_files: u64,
+    pub unreadable_files: u64,
+    pub sampled_size: u64,
+    pub high_entropy_size: u64,
+    /// Sampled directories, flagged ones first, then by high-entropy bytes
+    pub directories: Vec<DirectoryEntropy>,
+    /// Whether an earlier run's shares were available to compare against
+    pub compared: bool,
+}
+
+impl EntropyReport {
+    /// Fraction of all sampled bytes that looked random
+    pub fn ratio(&self) -> f64 {
+        if self.sampled_size == 0 {
+            0.0
+        } else {
+            self.high_entropy_size as f64 / self.sampled_size as f64
+        }
+    }
+
+    /// Flag directories whose share jumped since `previous`, and reorder them
+    pub fn compare_with(&mut self, previous: &[EntropySnapshot]) {
+        let previous: HashMap<&Path, f64> =
+            previous.iter().map(|snapshot| (snapshot.path.as_path(), snapshot.ratio)).collect();
+        for directory in &mut self.directories {
+            directory.previous_ratio = previous.get(directory.path.as_path()).copied();
+            directory.jumped = directory
+                .previous_ratio
+                .is_some_and(|before| directory.ratio() - before >= JUMP_THRESHOLD);
+        }
+        self.compared = true;
+        self.sort();
+    }
+
+    /// The shares to keep for the next run's comparison
+    pub fn snapshot(&self) -> Vec<EntropySnapshot> {
+        self.directories
+            .iter()
+            .map(|directory| EntropySnapshot { path: directory.path.clone(), ratio: directory.ratio() })
+            .collect()
+    }
+
+    fn sort(&mut self) {
+        self.directories.sort_by(|a, b| {
+            b.jumped
+                .cmp(&a.jumped)
+                .then(b.high_entropy_size.cmp(&a.high_entropy_size))
+                .then_with(|| a.path.cmp(&b.path))
+        });
+    }
+}
+
+/// A directory's share as recorded in the scan history
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EntropySnapshot {
+    pub path: PathBuf,
+    pub ratio: f64,
+}
+
+/// Sample the files modified within the window, largest first, until the budget is spent
+pub fn sample_recent(files: &[FileEntry], options: &SampleOptions, now: DateTime<Utc>) -> EntropyReport {
+    let since = now - Duration::days(options.window_days.min(i64::MAX as u64 / 86_400) as i64);
+    let mut candidates: Vec<&FileEntry> = files
+        .iter()
+        .filter(|file| !file.is_symlink && file.size >= MIN_FILE_SIZE)
+        .filter(|file| file.modified.is_some_and(|modified| modified >= since))
+        .collect();
+    // Big files carry most of the recent bytes, so they get the budget first
+    candidates.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.path.cmp(&b.path)));
+
+    let mut report = EntropyReport {
+        window_days: options.window_days,
+        budget: options.budget,
+        ..Default::default()
+    };
+    let mut planned = Vec::new();
+    for file in candidates {
+        let ranges = sample_ranges(file.size);
+        let cost: u64 = ranges.iter().map(|&(_, len)| len).sum();
+        if report.bytes_read + cost > options.budget {
+            report.skipped_files += 1;
+            continue;
+        }
+        report.bytes_read += cost;
+        planned.push((file, ranges));
+    }
+
+    let measured: Vec<(&FileEntry, Option<f64>)> = planned
+        .par_iter()
+        .map(|(file, ranges)| (*file, sample_file(&file.path, ranges).ok()))
+        .collect();
+
+    let mut directories: HashMap<&Path, (u64, u64)> = HashMap::new();
+    for (file, bits) in measured {
+        let Some(bits) = bits else {
+            report.unreadable_files += 1;
+            continue;
+        };
+        report.sampled_files += 1;
+        let high = if is_high_entropy(bits) { file.size } else { 0 };
+        report.sampled_size += file.size;
+        report.high_entropy_size += high;
+        let totals = directories.entry(file.path.parent().unwrap_or(Path::new(""))).or_default();
+        totals.0 += file.size;
+        totals.1 += high;
+    }
+
+    report.directories = directories
+        .into_iter()
+        .map(|(path, (sampled_size, high_entropy_size))| DirectoryEntropy {
+            path: path.to_path_buf(),
+            sampled_size,
+            high_entropy_size,
+            previous_ratio: None,
+            jumped: false,
+        })
+        .collect();
+    report.sort();
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::TempDir;
+
+    /// Deterministic pseudo-random bytes (xorshift), standing in for encrypted data
+    fn random_bytes(len: usize) -> Vec<u8> {
+        let mut state: u64 = 0x9E37_79B9_7F4A_7C15;
+        (0..len)
+            .map(|_| {
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                (state >> 24) as u8
+            })
+            .collect()
+    }
+
+    fn text_bytes(len: usize) -> Vec<u8> {
+        b"The quarterly report lists revenue, costs, and the outlook for next year.\n"
+            .iter()
+            .copied()
+            .cycle()
+            .take(len)
+            .collect()
+    }
+
+    fn entry(path: PathBuf, modified: DateTime<Utc>) -> FileEntry {
+        FileEntry {
+            size: fs::metadata(&path).unwrap().len(),
+            path,
+            modified: Some(modified),
+            depth: 1,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_entropy_bounds() {
+        assert_eq!(shannon_entropy(&[]), 0.0);
+        assert_eq!(shannon_entropy(&[7; 4096]), 0.0);
+        let uniform: Vec<u8> = (0..=255).cycle().take(4096).collect();
+        assert!((shannon_entropy(&uniform) - 8.0).abs() < 1e-9);
+        let two_symbols: Vec<u8> = [0, 1].iter().copied().cycle().take(4096).collect();
+        assert!((shannon_entropy(&two_symbols) - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_thresholds_separate_random_from_compressible() {
+        let random = shannon_entropy(&random_bytes(SAMPLE_SIZE as usize));
+        let text = shannon_entropy(&text_bytes(SAMPLE_SIZE as usize));
+        assert!(is_high_entropy(random), "random sample measured {random}");
+        assert!(!is_high_entropy(text), "text sample measured {text}");
+        assert!(text < 5.0);
+
+        // The smallest sampled file still separates the two
+        assert!(is_high_entropy(shannon_entropy(&random_bytes(MIN_FILE_SIZE as usize))));
+    }
+
+    #[test]
+    fn test_sample_ranges() {
+        assert_eq!(sample_ranges(2_000), vec![(0, 2_000)]);
+        assert_eq!(sample_ranges(2 * SAMPLE_SIZE), vec![(0, SAMPLE_SIZE), (SAMPLE_SIZE, SAMPLE_SIZE)]);
+        assert_eq!(sample_ranges(1_000_000), vec![(0, SAMPLE_SIZE), (500_000, SAMPLE_SIZE)]);
+    }
+
+    #[test]
+    fn test_sample_recent_rates_directories() {
+        let temp = TempDir::new().unwrap();
+        let docs = temp.path().join("docs");
+        let vault = temp.path().join("vault");
+        fs::create_dir_all(&docs).unwrap();
+        fs::create_dir_all(&vault).unwrap();
+        fs::write(docs.join("notes.txt"), text_bytes(20_000)).unwrap();
+        fs::write(docs.join("notes.txt.locked"), random_bytes(10_000)).unwrap();
+        fs::write(vault.join("old.bin"), random_bytes(10_000)).unwrap();
+        fs::write(vault.join("tiny.bin"), random_bytes(100)).unwrap();
+
+        let now = Utc::now();
+        let files = vec![
+            entry(docs.join("notes.txt"), now),
+            entry(docs.join("notes.txt.locked"), now - Duration::days(1)),
+            entry(vault.join("old.bin"), now - Duration::days(30)),
+            entry(vault.join("tiny.bin"), now),
+        ];
+        let report = sample_recent(&files, &SampleOptions { window_days: 7, budget: 1 << 20 }, now);
+
+        assert_eq!(report.sampled_files, 2, "old and tiny files are not sampled");
+        assert_eq!(report.bytes_read, 4 * SAMPLE_SIZE);
+        assert_eq!((report.sampled_size, report.high_entropy_size), (30_000, 10_000));
+        assert_eq!(report.directories.len(), 1);
+        assert_eq!(report.directories[0].path, docs);
+        assert!((report.directories[0].ratio() - 1.0 / 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_budget_bounds_bytes_read() {
+        let temp = TempDir::new().unwrap();
+        let now = Utc::now();
+        let files: Vec<FileEntry> = (0..5)
+            .map(|i| {
+                let path = temp.path().join(format!("file{i}.bin"));
+                fs::write(&path, random_bytes(3_000 + i * 100)).unwrap();
+                entry(path, now)
+            })
+            .collect();
+
+        let report = sample_recent(&files, &SampleOptions { window_days: 7, budget: 10_000 }, now);
+        assert!(report.bytes_read <= 10_000);
+        assert_eq!(report.sampled_files, 3);
+        assert_eq!(report.skipped_files, 2);
+        assert_eq!(report.high_entropy_size, 3_400 + 3_300 + 3_200, "the largest files are sampled first");
+    }
+
+    #[test]
+    fn test_jumps_against_previous_snapshot() {
+        let directory = |path: &str, sampled_size, high_entropy_size| DirectoryEntropy {
+            path: PathBuf::from(path),
+            sampled_size,
+            high_entropy_size,
+            previous_ratio: None,
+            jumped: false,
+        };
+        let mut report = EntropyReport {
+            directories: vec![directory("/media", 1_000, 900), directory("/docs", 1_000, 400), directory("/new", 100, 100)],
+            ..Default::default()
+        };
+        let previous = [
+            EntropySnapshot { path: PathBuf::from("/media"), ratio: 0.85 },
+            EntropySnapshot { path: PathBuf::from("/docs"), ratio: 0.1 },
+        ];
+
+        report.compare_with(&previous);
+        assert!(report.compared);
+        let flags: Vec<_> = report.directories.iter().map(|d| (d.path.to_str().unwrap(), d.jumped)).collect();
+        assert_eq!(flags, [("/docs", true), ("/media", false), ("/new", false)]);
+        assert_eq!(report.directories[0].previous_ratio, Some(0.1));
+        assert_eq!(report.directories[2].previous_ratio, None, "first sighting is not a jump");
+
+        let snapshot = report.snapshot();
+        assert_eq!(snapshot[0], EntropySnapshot { path: PathBuf::from("/docs"), ratio: 0.4 });
+    }
+}
@@ -1,102 +1,633 @@
 //! Export functionality for analysis results
 
+pub mod bundle;
+pub mod integrity;
+pub mod naming;
+pub mod stream;
+
+use crate::accepted::AcceptedTarget;
+use crate::ages::format_age;
 use crate::analyzer::AnalysisResults;
 use crate::cli::ExportFormat;
-use anyhow::{Context, Result};
-use serde_json;
+use crate::interop;
+use crate::metrics::MetricUnit;
+use crate::scanner::CapKind;
+use crate::writes::WriteGuard;
+use anyhow::{bail, Context, Result};
+use humansize::{format_size, DECIMAL};
+use integrity::CsvRows;
 use std::fs::File;
-use std::io::Write;
+use std::io::{BufWriter, Write};
+use naming::{OutputNaming, TemplateValues};
 use std::path::{Path, PathBuf};
+use stream::{RecordSink, StreamRecord};
+
+pub use bundle::read_bundle;
+pub use integrity::verify_export;
+
+/// Load the results of an earlier run from a JSON export or a json-bundle directory
+pub fn load_results(path: &Path) -> Result<AnalysisResults> {
+    if bundle::is_bundle(path) {
+        return read_bundle(path);
+    }
+    let file = std::fs::File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    serde_json::from_reader(std::io::BufReader::new(file))
+        .with_context(|| format!("{} is not a JSON export of diranalyzer results", path.display()))
+}
 
 /// Export analysis results to the specified format
+///
+/// Without an explicit `output_path` the file is named from `naming`'s
+/// template, and older auto-named exports are pruned to `keep_last`.
+/// Every file is written and removed through `writes`. `shard_size` caps
+/// the shards of a json-bundle export.
 pub fn export_results(
     results: &AnalysisResults,
     format: &ExportFormat,
     output_path: &Option<PathBuf>,
+    naming: &OutputNaming,
+    shard_size: u64,
+    writes: &WriteGuard,
 ) -> Result<()> {
-    let output_path = generate_output_path(format, output_path)?;
+    let path = generate_output_path(&results.scan_info.path, format, output_path, naming, writes)?;
     
     match format {
-        ExportFormat::Json => export_json(results, &output_path),
-        ExportFormat::Csv => export_csv(results, &output_path),
+        ExportFormat::Json => export_json(results, &path, writes)?,
+        ExportFormat::Csv => export_csv(results, &path, writes)?,
+        ExportFormat::RmlintJson => export_rmlint_json(results, &path, writes)?,
+        ExportFormat::JsonBundle => export_bundle(results, &path, shard_size, writes)?,
+        // Without progress to stream, the results alone
+        ExportFormat::Ndjson => return finish_stream(results, RecordSink::create(&path, writes)?, output_path, naming, writes),
     }
+
+    prune_older(&path, format, output_path, naming, writes)
 }
 
-fn generate_output_path(format: &ExportFormat, output_path: &Option<PathBuf>) -> Result<PathBuf> {
+/// Open the ndjson stream of a run over `root` before the run starts, so progress can go through it
+///
+/// `-` as `output_path` streams to stdout.
+pub fn open_stream(root: &Path, output_path: &Option<PathBuf>, naming: &OutputNaming, writes: &WriteGuard) -> Result<RecordSink> {
+    let path = generate_output_path(root, &ExportFormat::Ndjson, output_path, naming, writes)?;
+    RecordSink::create(&path, writes)
+}
+
+/// Send the results through a stream opened with [`open_stream`] and close it
+pub fn finish_stream(
+    results: &AnalysisResults,
+    sink: RecordSink,
+    output_path: &Option<PathBuf>,
+    naming: &OutputNaming,
+    writes: &WriteGuard,
+) -> Result<()> {
+    let sender = sink.sender();
+    // A refused record means the writer failed; finishing reports why
+    for record in StreamRecord::for_results(results) {
+        if sender.send(record).is_err() {
+            break;
+        }
+    }
+    let path = sink.path().map(Path::to_path_buf);
+    let lines = sink.finish()?;
+
+    if let Some(path) = path {
+        println!("📡 NDJSON stream of {} records exported to: {}", lines, path.display());
+        prune_older(&path, &ExportFormat::Ndjson, output_path, naming, writes)?;
+    }
+    Ok(())
+}
+
+/// With an auto-named export, remove older ones beyond `--keep-last`
+fn prune_older(
+    path: &Path,
+    format: &ExportFormat,
+    output_path: &Option<PathBuf>,
+    naming: &OutputNaming,
+    writes: &WriteGuard,
+) -> Result<()> {
+    if let (None, Some(keep_last)) = (output_path, naming.keep_last) {
+        let removed = naming::prune(naming, path, format.extension(), keep_last, writes)?;
+        if !removed.is_empty() {
+            println!("🧹 Removed {} older export(s) (--keep-last {})", removed.len(), keep_last);
+        }
+    }
+    Ok(())
+}
+
+fn generate_output_path(
+    root: &Path,
+    format: &ExportFormat,
+    output_path: &Option<PathBuf>,
+    naming: &OutputNaming,
+    writes: &WriteGuard,
+) -> Result<PathBuf> {
     if let Some(path) = output_path {
         Ok(path.clone())
     } else {
-        let now = chrono::Utc::now();
-        let timestamp = now.format("%Y%m%d_%H%M%S");
-        let filename = format!("diranalyzer_report_{}.{}", timestamp, format);
-        Ok(PathBuf::from(filename))
+        let values = TemplateValues::new(root, format.extension(), chrono::Utc::now());
+        let path = naming::auto_path(naming, &values)?;
+        if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+            writes.create_dir_all(parent)
+                .with_context(|| format!("Failed to create export directory: {}", parent.display()))?;
+        }
+        Ok(path)
     }
 }
 
-fn export_json(results: &AnalysisResults, output_path: &Path) -> Result<()> {
-    let json_data = serde_json::to_string_pretty(results)
-        .context("Failed to serialize results to JSON")?;
-    
-    let mut file = File::create(output_path)
-        .with_context(|| format!("Failed to create output file: {}", output_path.display()))?;
-    
-    file.write_all(json_data.as_bytes())
+fn export_json(results: &AnalysisResults, output_path: &Path, writes: &WriteGuard) -> Result<()> {
+    write_output(output_path, writes, |out| integrity::write_json(results, out).map(drop))
         .with_context(|| format!("Failed to write JSON data to: {}", output_path.display()))?;
     
     println!("📄 JSON report exported to: {}", output_path.display());
     Ok(())
 }
 
-fn export_csv(results: &AnalysisResults, output_path: &Path) -> Result<()> {
-    let file = File::create(output_path)
-        .with_context(|| format!("Failed to create output file: {}", output_path.display()))?;
-    
-    let mut writer = csv::Writer::from_writer(file);
-    
+fn export_bundle(results: &AnalysisResults, output_path: &Path, shard_size: u64, writes: &WriteGuard) -> Result<()> {
+    let manifest = bundle::write_bundle(results, output_path, shard_size, writes)
+        .with_context(|| format!("Failed to write JSON bundle to: {}", output_path.display()))?;
+
+    println!("📦 JSON bundle of {} shards exported to: {}", manifest.inventory.len(), output_path.display());
+    Ok(())
+}
+
+fn export_rmlint_json(results: &AnalysisResults, output_path: &Path, writes: &WriteGuard) -> Result<()> {
+    let groups = results.duplicate_groups.as_ref()
+        .context("rmlint-json export needs duplicate groups: use --duplicates or --import-duplicates")?;
+
+    write_output(output_path, writes, |out| interop::write_rmlint_json(groups, results.scan_info.partial.is_some(), out))
+        .with_context(|| format!("Failed to write rmlint JSON to: {}", output_path.display()))?;
+
+    println!("📄 rmlint JSON exported to: {}", output_path.display());
+    Ok(())
+}
+
+fn export_csv(results: &AnalysisResults, output_path: &Path, writes: &WriteGuard) -> Result<()> {
+    write_output(output_path, writes, |out| write_csv(results, out))
+        .with_context(|| format!("Failed to write CSV data to: {}", output_path.display()))?;
+    println!("📊 CSV report exported to: {}", output_path.display());
+    Ok(())
+}
+
+/// The CSV rows of `results`, ending in the integrity row
+fn write_csv(results: &AnalysisResults, out: impl Write) -> Result<()> {
+    let mut writer = CsvRows::new(out);
+
     // Export file information
-    writer.write_record(&[
-        "Type", "Path", "Size", "FileType", "Modified", "Depth"
+    writer.write_record([
+        "Type", "Path", "Size", "FileType", "Modified", "Created", "Depth", "Priority", "Annotation", "Regenerable"
     ])?;
+
+    // A scan stopped by a cap says so first; every row below covers only what was collected
+    if let Some(partial) = results.scan_info.partial {
+        writer.write_record([
+            "Partial",
+            "",
+            &partial.limit.to_string(),
+            "",
+            "",
+            "",
+            "",
+            "",
+            &match partial.cap {
+                CapKind::Interrupted => format!("scan interrupted after {} files", partial.limit),
+                CapKind::Duration => format!("scan stopped at {} {}", CapKind::Duration.flag(), format_age(partial.limit)),
+                cap => format!("scan stopped at {} {}", cap.flag(), partial.limit),
+            },
+            "",
+        ])?;
+    }
     
     for file in &results.largest_files {
-        writer.write_record(&[
+        writer.write_record([
             "File",
             &file.path.display().to_string(),
             &file.size.to_string(),
             &file.file_type,
             &file.modified.map(|m| m.to_rfc3339()).unwrap_or_default(),
+            &file.created.map(|c| c.to_rfc3339()).unwrap_or_default(),
             "", // Depth not available in FileInfo
+            "",
+            &file.preview.as_deref().map(|preview| preview.replace('\n', " / ")).unwrap_or_default(),
+            &file.regenerable.to_string(),
         ])?;
     }
     
     for dir in &results.largest_directories {
-        writer.write_record(&[
+        let note = match (dir.pinned, &dir.annotation) {
+            (true, Some(annotation)) => format!("pinned; {}", annotation),
+            (true, None) => "pinned".to_string(),
+            (false, annotation) => annotation.clone().unwrap_or_default(),
+        };
+        writer.write_record([
             "Directory",
             &dir.path.display().to_string(),
             &dir.size.to_string(),
             "Directory",
             "",
             "",
+            "",
+            "",
+            &note,
+            "",
         ])?;
     }
+
+    // The listed owners of each directory with their bytes; the note tells a clear owner from a mix
+    for dir in &results.largest_directories {
+        let Some(ref mix) = dir.owner_mix else { continue };
+        for owner in &mix.owners {
+            writer.write_record([
+                "Owner",
+                &dir.path.display().to_string(),
+                &owner.size.to_string(),
+                &owner.user,
+                "",
+                "",
+                "",
+                "",
+                if mix.clear_owner { "clear owner" } else { "mixed" },
+                "",
+            ])?;
+        }
+    }
+
+    // Every owning user with the bytes and files they own, largest first
+    let mut owners: Vec<_> = results.owners.iter().collect();
+    owners.sort_by(|a, b| b.1.total_size.cmp(&a.1.total_size).then_with(|| a.0.cmp(b.0)));
+    for (user, stats) in owners {
+        let note = format!("{} files, uid {}", stats.count, stats.uid);
+        writer.write_record(["User", "", &stats.total_size.to_string(), user, "", "", "", "", &note, ""])?;
+    }
+
+    // Files and bytes per age bucket, named by their range of ages; stale files follow with their last use
+    for bucket in &results.age_breakdown.buckets {
+        let from = if bucket.min_age_secs == 0 { "0".to_string() } else { format_age(bucket.min_age_secs) };
+        let range = match bucket.max_age_secs {
+            Some(to) => format!("{}-{}", from, format_age(to)),
+            None => format!("{}+", from),
+        };
+        let note = format!("{} files", bucket.count);
+        writer.write_record(["Age", "", &bucket.total_size.to_string(), &range, "", "", "", "", &note, ""])?;
+    }
+    if let Some(ref stale) = results.stale_files {
+        let note = format!("unused for {} or longer", format_age(stale.threshold_secs));
+        for file in &stale.largest {
+            let path = file.path.display().to_string();
+            writer.write_record(["Stale", &path, &file.size.to_string(), "", &file.last_used.to_rfc3339(), "", "", "", &note, ""])?;
+        }
+    }
+
+    // Every entry with risky permissions, by kind; the mode and owner go in the note
+    if let Some(ref audit) = results.security_audit {
+        let kinds = [
+            ("WorldWritable", &audit.world_writable_files),
+            ("Setuid", &audit.setuid_files),
+            ("Setgid", &audit.setgid_files),
+            ("OpenDirectory", &audit.open_directories),
+        ];
+        for (kind, findings) in kinds {
+            for finding in findings {
+                let mut note = format!("mode {:04o}", finding.mode);
+                if let Some(owner) = &finding.owner {
+                    note.push_str(&format!(", owner {}", owner));
+                }
+                writer.write_record(["Security", &finding.path.display().to_string(), &finding.size.to_string(), kind, "", "", "", "", &note, ""])?;
+            }
+        }
+    }
+
+    // Entries beyond --top, as one row per listing with their count and total size
+    let residuals = [("File", results.residuals.largest_files), ("Directory", results.residuals.largest_directories)];
+    for (kind, residual) in residuals.into_iter().filter(|(_, residual)| residual.count > 0) {
+        writer.write_record([
+            "Residual",
+            "",
+            &residual.size.to_string(),
+            kind,
+            "",
+            "",
+            "",
+            "",
+            &format!("{} more not listed", residual.count),
+            "",
+        ])?;
+    }
+
+    // Actionable and regenerable shares of the total size and of the duplicate waste
+    let regenerable = &results.regenerable;
+    let mut subtotals = vec![
+        ("Files", results.scan_info.total_size.saturating_sub(regenerable.size), false),
+        ("Files", regenerable.size, true),
+    ];
+    if results.duplicate_groups.is_some() {
+        subtotals.push(("Duplicate Waste", results.statistics.reclaimable_bytes.saturating_sub(regenerable.wasted_space), false));
+        subtotals.push(("Duplicate Waste", regenerable.wasted_space, true));
+    }
+    for (kind, size, is_regenerable) in subtotals {
+        writer.write_record(["Subtotal", "", &size.to_string(), kind, "", "", "", "", "", &is_regenerable.to_string()])?;
+    }
+
+    // Custom metrics by name; bytes go in the size column, every value in the note with its expression
+    for metric in &results.custom_metrics {
+        let size = match (metric.unit, metric.value) {
+            (MetricUnit::Bytes, Some(value)) if value >= 0.0 => (value.round() as u64).to_string(),
+            _ => String::new(),
+        };
+        let value = match (metric.unit, metric.value) {
+            (_, None) => "n/a".to_string(),
+            (MetricUnit::Bytes | MetricUnit::Count, Some(value)) => format!("{}", value.round()),
+            (MetricUnit::Percent, Some(value)) => format!("{:.1}%", value),
+            (MetricUnit::Number, Some(value)) => format!("{:.2}", value),
+        };
+        writer.write_record(["Metric", "", &size, &metric.name, "", "", "", "", &format!("{} = {}", metric.expression, value), ""])?;
+    }
+
+    // The Data category by format; the path column holds the largest file of each
+    for (format, stats) in results.data_formats.iter().flatten() {
+        let largest = stats.largest_file.as_ref().map(|file| file.path.display().to_string()).unwrap_or_default();
+        let note = format!("{} files", stats.count);
+        writer.write_record(["DataFormat", &largest, &stats.total_size.to_string(), format, "", "", "", "", &note, ""])?;
+    }
     
+    // Sampled directories with their high-entropy bytes; the share and any jump go in the note
+    if let Some(ref entropy) = results.entropy {
+        for directory in &entropy.directories {
+            let mut note = format!("{:.3} of {} sampled bytes high-entropy", directory.ratio(), directory.sampled_size);
+            if let Some(before) = directory.previous_ratio {
+                note.push_str(&format!(", {:.3} on the last run", before));
+            }
+            if directory.jumped {
+                note.push_str(", jumped");
+            }
+            writer.write_record([
+                "Entropy",
+                &directory.path.display().to_string(),
+                &directory.high_entropy_size.to_string(),
+                "Directory",
+                "",
+                "",
+                "",
+                "",
+                &note,
+                "",
+            ])?;
+        }
+    }
+
     // Export duplicate information if available
     if let Some(ref groups) = results.duplicate_groups {
         for group in groups {
             for file_path in &group.files {
-                writer.write_record(&[
+                writer.write_record([
                     "Duplicate",
                     &file_path.display().to_string(),
                     &group.file_size.to_string(),
-                    "Duplicate",
+                    if group.unverified { "Duplicate (not content-verified)" } else { "Duplicate" },
+                    "",
                     "",
                     "",
+                    &format!("{:.1}", group.priority),
+                    &group.group_id,
+                    &group.regenerable.to_string(),
                 ])?;
             }
         }
     }
+
+    // Groups that were present on the last run and are gone now, with the bytes their removal freed
+    if let Some(ref resolved) = results.resolved_groups {
+        for group in resolved {
+            writer.write_record([
+                "Resolved",
+                &group.path.display().to_string(),
+                &group.reclaimed_space.to_string(),
+                "Duplicate",
+                "",
+                "",
+                "",
+                "",
+                &format!("{}, {} copies, first seen {}", group.group_id, group.copies, group.first_seen.format("%Y-%m-%d")),
+                "",
+            ])?;
+        }
+    }
     
-    writer.flush()?;
-    println!("📊 CSV report exported to: {}", output_path.display());
+    // Names that collide when case is ignored, one row per name; the note ties each to its group
+    for collision in results.case_collisions.iter().flatten() {
+        for entry in &collision.entries {
+            writer.write_record([
+                "CaseCollision",
+                &collision.directory.join(&entry.name).display().to_string(),
+                &entry.size.to_string(),
+                if entry.is_dir { "Directory" } else { "File" },
+                &entry.modified.map(|m| m.to_rfc3339()).unwrap_or_default(),
+                "",
+                "",
+                "",
+                &format!("{} names fold to \"{}\"", collision.entries.len(), collision.folded),
+                "",
+            ])?;
+        }
+    }
+
+    // Probable versions by name, one row per member; the note marks the newest and says it is a guess
+    for group in results.heuristic_versions.iter().flat_map(|versions| &versions.groups) {
+        for (index, member) in group.members.iter().enumerate() {
+            let note = if index == 0 {
+                format!("heuristic: newest of {} versions of \"{}\"", group.members.len(), group.base_name)
+            } else {
+                format!("heuristic: probably superseded by \"{}\"", group.members[0].name)
+            };
+            writer.write_record([
+                "Version",
+                &group.directory.join(&member.name).display().to_string(),
+                &member.size.to_string(),
+                "File",
+                &member.modified.map(|m| m.to_rfc3339()).unwrap_or_default(),
+                "",
+                "",
+                "",
+                &note,
+                "",
+            ])?;
+        }
+    }
+
+    // Broken symlinks, with the target as stored and, for chains, where they end
+    for link in results.symlink_report.iter().flat_map(|links| &links.broken) {
+        let note = match &link.dangling {
+            Some(dangling) => format!("target {}; missing {}", link.target.display(), dangling.display()),
+            None => format!("target {}; loops or exceeds {} hops", link.target.display(), crate::scanner::MAX_HOPS),
+        };
+        writer.write_record([
+            "BrokenLink",
+            &link.path.display().to_string(),
+            "",
+            "Symlink",
+            "",
+            "",
+            "",
+            "",
+            &note,
+            "",
+        ])?;
+    }
+
+    // Scan errors: a row per cause, per subtree they gather in, and per error listed
+    let errors = &results.scan_errors;
+    for cause in &errors.causes {
+        writer.write_record([
+            "ErrorCause",
+            "",
+            "",
+            &format!("{:?}", cause.error_type),
+            "",
+            "",
+            "",
+            "",
+            &format!("{} errors: {}", cause.count, cause.description),
+            "",
+        ])?;
+    }
+    for rollup in &errors.rollups {
+        writer.write_record([
+            "ErrorRollup",
+            &rollup.directory.display().to_string(),
+            "",
+            &format!("{:?}", rollup.error_type),
+            "",
+            "",
+            "",
+            "",
+            &format!("{} errors below: {}", rollup.count, rollup.description),
+            "",
+        ])?;
+    }
+    for error in &errors.errors {
+        writer.write_record([
+            "Error",
+            &error.path.display().to_string(),
+            "",
+            &format!("{:?}", error.error_type),
+            "",
+            "",
+            "",
+            "",
+            &error.error,
+            "",
+        ])?;
+    }
+    if errors.unlisted > 0 {
+        writer.write_record([
+            "Error",
+            "",
+            "",
+            "",
+            "",
+            "",
+            "",
+            "",
+            &format!("{} more errors not listed; --errors-full lists them all", errors.unlisted),
+            "",
+        ])?;
+    }
+
+    // Operator notes on paths of the tree, from `note add`
+    for annotation in &results.annotations {
+        writer.write_record([
+            "Note",
+            &annotation.path.display().to_string(),
+            "",
+            "",
+            &annotation.added.to_rfc3339(),
+            "",
+            "",
+            "",
+            &annotation.text,
+            "",
+        ])?;
+    }
+
+    // Accepted findings, from `accept`; the group id stands in the path column for duplicate groups
+    for finding in &results.accepted.findings {
+        let (target, kind) = match &finding.target {
+            AcceptedTarget::Path(path) => (path.display().to_string(), ""),
+            AcceptedTarget::Group(id) => (id.clone(), "Duplicate Group"),
+        };
+        writer.write_record([
+            "Accepted",
+            &target,
+            "",
+            kind,
+            &finding.accepted.to_rfc3339(),
+            "",
+            "",
+            "",
+            &finding.reason,
+            "",
+        ])?;
+    }
+
+    // Sections contributed through the library API, one row per labelled value
+    for extension in &results.extensions {
+        for row in &extension.rows {
+            writer.write_record([
+                "Extension",
+                "",
+                &row.size.map(|size| size.to_string()).unwrap_or_default(),
+                &extension.title,
+                "",
+                "",
+                "",
+                "",
+                &format!("{}: {}", row.label, row.value),
+                "",
+            ])?;
+        }
+    }
+
+    writer.finish()?;
     Ok(())
 }
+
+/// Stream an export through `write` into a temporary sibling of `path`, then move it into place
+///
+/// A failed write leaves no partial file behind. When the disk fills up, the
+/// error says how much the export had written by then.
+fn write_output(path: &Path, writes: &WriteGuard, write: impl FnOnce(&mut BufWriter<File>) -> Result<()>) -> Result<()> {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    let temp = path.with_file_name(format!(".{}.partial", name));
+    let mut out = BufWriter::new(writes.create(&temp)?);
+    let written = write(&mut out).and_then(|()| {
+        let file = out.into_inner().map_err(|error| error.into_error())?;
+        file.sync_all()?;
+        Ok(())
+    });
+    if let Err(error) = written {
+        let partial = std::fs::metadata(&temp).map_or(0, |metadata| metadata.len());
+        let _ = writes.remove_file(&temp);
+        if is_storage_full(&error) {
+            let free = crate::space::available(path).map_or_else(
+                |_| "less".to_string(),
+                |space| format_size(space.available_bytes, DECIMAL),
+            );
+            bail!("Out of disk space: the export needs more than {} and {} is free", format_size(partial, DECIMAL), free);
+        }
+        return Err(error);
+    }
+    writes.rename(&temp, path)
+}
+
+/// Whether `error` comes from a full disk, wherever in the writers it was raised
+fn is_storage_full(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        let kind = cause
+            .downcast_ref::<std::io::Error>()
+            .map(std::io::Error::kind)
+            .or_else(|| cause.downcast_ref::<serde_json::Error>().and_then(serde_json::Error::io_error_kind))
+            .or_else(|| match cause.downcast_ref::<csv::Error>().map(csv::Error::kind) {
+                Some(csv::ErrorKind::Io(error)) => Some(error.kind()),
+                _ => None,
+            });
+        kind == Some(std::io::ErrorKind::StorageFull)
+    })
+}
@@ -0,0 +1,404 @@
+//! Results split over a directory of NDJSON shards
+//!
+//! `--export json-bundle` writes a directory holding `manifest.json` and
+//! sharded NDJSON files: `files-00001.ndjson`, `directories-00001.ndjson`,
+//! and `duplicates-00001.ndjson` carry the largest files, the largest
+//! directories, and the duplicate groups, one per line. With
+//! `--export-scope full`, `entries-00001.ndjson` and on carry every entry
+//! collected. A shard is closed
+//! once the next line would take it past the shard size, so each stays
+//! below it unless a single record is larger. The manifest carries the
+//! schema version, the inventory of shards with their record counts and
+//! SHA-256 digests, and every other part of the results, `scan_info` and
+//! `statistics` among them. Reading a shard checks both.
+//!
+//! The bundle is written into a hidden `.partial` sibling and renamed into
+//! place once every shard is synced, so a failed run leaves no bundle behind.
+
+use crate::analyzer::AnalysisResults;
+use crate::writes::WriteGuard;
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// Version of the bundle layout; readers refuse bundles of a later one
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// Name of the manifest inside a bundle
+pub const MANIFEST: &str = "manifest.json";
+
+/// Shard size without `--bundle-shard-size`
+pub const DEFAULT_SHARD_SIZE: u64 = 256_000_000;
+
+/// The part of the results a shard holds
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ShardKind {
+    Files,
+    Directories,
+    Duplicates,
+    Entries,
+}
+
+impl ShardKind {
+    const ALL: [ShardKind; 4] = [ShardKind::Files, ShardKind::Directories, ShardKind::Duplicates, ShardKind::Entries];
+
+    /// Field of [`AnalysisResults`] the shards of this kind hold
+    pub(crate) fn field(self) -> &'static str {
+        match self {
+            ShardKind::Files => "largest_files",
+            ShardKind::Directories => "largest_directories",
+            ShardKind::Duplicates => "duplicate_groups",
+            ShardKind::Entries => "entries",
+        }
+    }
+
+    fn prefix(self) -> &'static str {
+        match self {
+            ShardKind::Files => "files",
+            ShardKind::Directories => "directories",
+            ShardKind::Duplicates => "duplicates",
+            ShardKind::Entries => "entries",
+        }
+    }
+}
+
+/// One shard file in the inventory
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Shard {
+    pub kind: ShardKind,
+    /// Position among the shards of its kind, from 1
+    pub sequence: u32,
+    pub name: String,
+    pub records: u64,
+    pub bytes: u64,
+    /// SHA-256 of the shard file in hex; empty in bundles written before digests were kept
+    #[serde(default)]
+    pub sha256: String,
+}
+
+/// The contents of `manifest.json`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    pub schema_version: u32,
+    pub shard_size: u64,
+    /// Every shard, in sequence order within each kind
+    pub inventory: Vec<Shard>,
+    /// Whether the results had duplicate groups at all, so that none found reads back as none found
+    pub duplicate_groups_searched: bool,
+    /// Whether the results kept every entry, so that none collected reads back as an empty list
+    #[serde(default)]
+    pub entries_included: bool,
+    /// The rest of the results, `scan_info` and `statistics` among them
+    #[serde(flatten)]
+    pub results: Map<String, Value>,
+}
+
+/// Write `results` as a bundle at `path`, with shards of at most `shard_size` bytes
+///
+/// An existing bundle at `path` is replaced; any other existing file or
+/// directory is left alone and the export fails. Returns the manifest.
+pub fn write_bundle(results: &AnalysisResults, path: &Path, shard_size: u64, writes: &WriteGuard) -> Result<Manifest> {
+    if path.exists() && !is_bundle(path) {
+        bail!("{} exists and is not a bundle; refusing to replace it", path.display());
+    }
+    let temp = sibling(path, "partial");
+    if temp.exists() {
+        writes.remove_dir_all(&temp)?;
+    }
+    writes.create_dir_all(&temp)?;
+
+    let manifest = match write_contents(results, &temp, shard_size.max(1), writes) {
+        Ok(manifest) => manifest,
+        Err(error) => {
+            let _ = writes.remove_dir_all(&temp);
+            return Err(error);
+        }
+    };
+
+    // The old bundle moves aside only once the new one is complete
+    if path.exists() {
+        let old = sibling(path, "old");
+        writes.rename(path, &old)?;
+        writes.rename(&temp, path)?;
+        writes.remove_dir_all(&old)?;
+    } else {
+        writes.rename(&temp, path)?;
+    }
+    Ok(manifest)
+}
+
+fn write_contents(results: &AnalysisResults, directory: &Path, shard_size: u64, writes: &WriteGuard) -> Result<Manifest> {
+    let Value::Object(mut rest) = serde_json::to_value(results).context("Failed to serialize results")? else {
+        bail!("Results did not serialize to an object");
+    };
+
+    let mut inventory = Vec::new();
+    for kind in ShardKind::ALL {
+        let records = match rest.remove(kind.field()) {
+            Some(Value::Array(records)) => records,
+            _ => Vec::new(),
+        };
+        let mut shards = ShardWriter::new(kind, directory, shard_size, writes);
+        for record in &records {
+            shards.push(record)?;
+        }
+        inventory.extend(shards.finish()?);
+    }
+
+    let manifest = Manifest {
+        schema_version: SCHEMA_VERSION,
+        shard_size,
+        inventory,
+        duplicate_groups_searched: results.duplicate_groups.is_some(),
+        entries_included: results.entries.is_some(),
+        results: rest,
+    };
+    let mut file = writes.create(&directory.join(MANIFEST))?;
+    serde_json::to_writer_pretty(&mut file, &manifest).context("Failed to write the bundle manifest")?;
+    file.sync_all()?;
+    Ok(manifest)
+}
+
+/// Read the manifest of the bundle at `path`
+pub fn read_manifest(path: &Path) -> Result<Manifest> {
+    let manifest_path = path.join(MANIFEST);
+    let file = std::fs::File::open(&manifest_path).with_context(|| format!("Failed to open {}", manifest_path.display()))?;
+    let manifest: Manifest =
+        serde_json::from_reader(BufReader::new(file)).with_context(|| format!("Failed to parse {}", manifest_path.display()))?;
+    if manifest.schema_version > SCHEMA_VERSION {
+        bail!(
+            "{} uses bundle schema {}; this version reads up to {}",
+            path.display(),
+            manifest.schema_version,
+            SCHEMA_VERSION
+        );
+    }
+    Ok(manifest)
+}
+
+/// Stream the records of the shards of `kind` in the bundle at `path`, in order
+///
+/// Each shard must hold as many records as the manifest lists for it, and match its digest.
+pub fn read_records(path: &Path, manifest: &Manifest, kind: ShardKind, mut record: impl FnMut(Value) -> Result<()>) -> Result<()> {
+    for shard in manifest.inventory.iter().filter(|shard| shard.kind == kind) {
+        read_shard(path, shard, |line, count| {
+            let value = serde_json::from_slice(line)
+                .with_context(|| format!("Invalid record {} in {}", count, path.join(&shard.name).display()))?;
+            record(value)
+        })?;
+    }
+    Ok(())
+}
+
+/// Pass each line of `shard` to `line` with its number, then check the count and digest against the manifest
+pub(crate) fn read_shard(path: &Path, shard: &Shard, mut line: impl FnMut(&[u8], u64) -> Result<()>) -> Result<()> {
+    let shard_path = path.join(&shard.name);
+    let file = std::fs::File::open(&shard_path).with_context(|| format!("Failed to open {}", shard_path.display()))?;
+    let mut reader = BufReader::new(file);
+    let mut hasher = Sha256::new();
+    let mut buffer = Vec::new();
+    let mut count = 0;
+    loop {
+        buffer.clear();
+        if reader.read_until(b'\n', &mut buffer).with_context(|| format!("Failed to read {}", shard_path.display()))? == 0 {
+            break;
+        }
+        hasher.update(&buffer);
+        let record = buffer.trim_ascii_end();
+        if record.is_empty() {
+            continue;
+        }
+        count += 1;
+        line(record, count)?;
+    }
+    if count != shard.records {
+        bail!("{} holds {} records; the manifest lists {}", shard_path.display(), count, shard.records);
+    }
+    let sha256 = format!("{:x}", hasher.finalize());
+    if !shard.sha256.is_empty() && sha256 != shard.sha256 {
+        bail!("SHA-256 of {} is {}; the manifest lists {}", shard_path.display(), sha256, shard.sha256);
+    }
+    Ok(())
+}
+
+/// Reconstruct the results exported as the bundle at `path`
+pub fn read_bundle(path: &Path) -> Result<AnalysisResults> {
+    let manifest = read_manifest(path)?;
+    let mut results = manifest.results.clone();
+    for kind in ShardKind::ALL {
+        let mut records = Vec::new();
+        read_records(path, &manifest, kind, |record| {
+            records.push(record);
+            Ok(())
+        })?;
+        let present = match kind {
+            ShardKind::Duplicates => manifest.duplicate_groups_searched,
+            ShardKind::Entries => manifest.entries_included,
+            ShardKind::Files | ShardKind::Directories => true,
+        };
+        results.insert(kind.field().to_string(), if present { Value::Array(records) } else { Value::Null });
+    }
+    serde_json::from_value(Value::Object(results)).with_context(|| format!("Failed to read the results in {}", path.display()))
+}
+
+/// Whether `path` is a directory holding a bundle manifest
+pub fn is_bundle(path: &Path) -> bool {
+    path.is_dir() && path.join(MANIFEST).is_file()
+}
+
+/// A hidden sibling of `path` with `suffix`, as `.name.partial`
+fn sibling(path: &Path, suffix: &str) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!(".{}.{}", name, suffix))
+}
+
+/// Writes the records of one kind, opening a new shard whenever one is full
+struct ShardWriter<'a> {
+    kind: ShardKind,
+    directory: &'a Path,
+    shard_size: u64,
+    writes: &'a WriteGuard,
+    current: Option<(BufWriter<std::fs::File>, Shard, Sha256)>,
+    finished: Vec<Shard>,
+}
+
+impl<'a> ShardWriter<'a> {
+    fn new(kind: ShardKind, directory: &'a Path, shard_size: u64, writes: &'a WriteGuard) -> Self {
+        Self { kind, directory, shard_size, writes, current: None, finished: Vec::new() }
+    }
+
+    fn push(&mut self, record: &Value) -> Result<()> {
+        let mut line = serde_json::to_vec(record)?;
+        line.push(b'\n');
+        let len = line.len() as u64;
+
+        if self.current.as_ref().is_some_and(|(_, shard, _)| shard.bytes + len > self.shard_size) {
+            self.close()?;
+        }
+        if self.current.is_none() {
+            let sequence = self.finished.len() as u32 + 1;
+            let name = format!("{}-{:05}.ndjson", self.kind.prefix(), sequence);
+            let file = self.writes.create(&self.directory.join(&name))?;
+            let shard = Shard { kind: self.kind, sequence, name, records: 0, bytes: 0, sha256: String::new() };
+            self.current = Some((BufWriter::new(file), shard, Sha256::new()));
+        }
+
+        let (writer, shard, hasher) = self.current.as_mut().expect("a shard is open");
+        writer.write_all(&line).with_context(|| format!("Failed to write {}", shard.name))?;
+        hasher.update(&line);
+        shard.records += 1;
+        shard.bytes += len;
+        Ok(())
+    }
+
+    fn close(&mut self) -> Result<()> {
+        if let Some((writer, mut shard, hasher)) = self.current.take() {
+            let file = writer.into_inner().map_err(|error| error.into_error())?;
+            file.sync_all().with_context(|| format!("Failed to sync {}", shard.name))?;
+            shard.sha256 = format!("{:x}", hasher.finalize());
+            self.finished.push(shard);
+        }
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<Vec<Shard>> {
+        self.close()?;
+        Ok(self.finished)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzer::{DirectoryAnalyzer, FixedClock};
+    use crate::cli::Args;
+    use chrono::TimeZone;
+    use clap::Parser;
+
+    async fn medium_results(root: &Path) -> AnalysisResults {
+        // Directories come in pairs of identical contents
+        for group in 0..8 {
+            let directory = root.join(format!("d{}", group));
+            std::fs::create_dir_all(&directory).unwrap();
+            for index in 0..12 {
+                std::fs::write(directory.join(format!("f{}.bin", index)), vec![index as u8; 1_000 + group / 2 * 10]).unwrap();
+            }
+        }
+        let args = Args::parse_from([
+            "diranalyzer", "--quiet", "--no-history", "--duplicates", "--top", "50", "--export", "json-bundle", "--export-scope", "full",
+            root.to_str().unwrap(),
+        ]);
+        let clock = FixedClock(chrono::Utc.with_ymd_and_hms(2026, 1, 1, 0, 0, 0).unwrap());
+        DirectoryAnalyzer::new(args).unwrap().with_clock(clock).analyze().await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_results_round_trip_through_a_bundle() {
+        let dir = tempfile::tempdir().unwrap();
+        let results = medium_results(&dir.path().join("tree")).await;
+        assert!(results.duplicate_groups.as_ref().is_some_and(|groups| !groups.is_empty()));
+        assert!(results.entries.as_ref().is_some_and(|entries| entries.len() > 96));
+
+        let path = dir.path().join("out.bundle");
+        let manifest = write_bundle(&results, &path, DEFAULT_SHARD_SIZE, &WriteGuard::unrestricted()).unwrap();
+        assert_eq!(manifest.inventory.iter().map(|shard| shard.kind).collect::<Vec<_>>(), ShardKind::ALL);
+        assert!(!path.with_file_name(".out.bundle.partial").exists());
+
+        // Floats read back as from a JSON export, so that is what the bundle must match
+        let exported: AnalysisResults = serde_json::from_str(&serde_json::to_string(&results).unwrap()).unwrap();
+        let read = read_bundle(&path).unwrap();
+        assert_eq!(serde_json::to_value(&read).unwrap(), serde_json::to_value(&exported).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_shards_stay_below_the_shard_size() {
+        let dir = tempfile::tempdir().unwrap();
+        let results = medium_results(&dir.path().join("tree")).await;
+        let path = dir.path().join("out.bundle");
+        let manifest = write_bundle(&results, &path, 1_000, &WriteGuard::unrestricted()).unwrap();
+
+        let files: Vec<&Shard> = manifest.inventory.iter().filter(|shard| shard.kind == ShardKind::Files).collect();
+        assert!(files.len() > 1);
+        assert_eq!(files.iter().map(|shard| shard.sequence).collect::<Vec<_>>(), (1..=files.len() as u32).collect::<Vec<_>>());
+        assert_eq!(files.iter().map(|shard| shard.records).sum::<u64>(), results.largest_files.len() as u64);
+        for shard in &manifest.inventory {
+            let bytes = std::fs::metadata(path.join(&shard.name)).unwrap().len();
+            assert_eq!(bytes, shard.bytes);
+            assert!(bytes <= 1_000 || shard.records == 1, "{} holds {} bytes", shard.name, bytes);
+        }
+        assert_eq!(read_bundle(&path).unwrap().largest_files.len(), results.largest_files.len());
+
+        // Writing again replaces the bundle with one of the new shard size
+        let manifest = write_bundle(&results, &path, DEFAULT_SHARD_SIZE, &WriteGuard::unrestricted()).unwrap();
+        assert_eq!(manifest.inventory.len(), 4);
+        assert_eq!(std::fs::read_dir(&path).unwrap().count(), 5);
+    }
+
+    #[test]
+    fn test_refuses_to_replace_other_directories() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("keep.txt"), b"mine").unwrap();
+        let results: AnalysisResults = serde_json::from_str(include_str!("../../tests/golden/results.json")).unwrap();
+
+        let error = write_bundle(&results, dir.path(), DEFAULT_SHARD_SIZE, &WriteGuard::unrestricted()).unwrap_err();
+        assert!(error.to_string().contains("is not a bundle"));
+        assert!(dir.path().join("keep.txt").exists());
+    }
+
+    #[test]
+    fn test_shard_record_counts_are_checked() {
+        let dir = tempfile::tempdir().unwrap();
+        let results: AnalysisResults = serde_json::from_str(include_str!("../../tests/golden/results.json")).unwrap();
+        let path = dir.path().join("out.bundle");
+        let manifest = write_bundle(&results, &path, DEFAULT_SHARD_SIZE, &WriteGuard::unrestricted()).unwrap();
+
+        let shard = path.join(&manifest.inventory[0].name);
+        let contents = std::fs::read_to_string(&shard).unwrap();
+        std::fs::write(&shard, contents.lines().skip(1).map(|line| format!("{}\n", line)).collect::<String>()).unwrap();
+        assert!(read_bundle(&path).unwrap_err().to_string().contains("the manifest lists"));
+    }
+}
@@ -0,0 +1,671 @@
+//! Integrity footers of exports, and their verification
+//!
+//! A truncated or altered export should fail where it is read, not somewhere
+//! downstream. Every export therefore ends with how many records it holds and
+//! a SHA-256 of the bytes before the footer:
+//!
+//! - JSON: a last member `integrity` with the record counts of the listings,
+//!   `payload_bytes`, and the digest of the first `payload_bytes` bytes
+//! - CSV: a last `Integrity` row with the byte count in the size column, the
+//!   digest in the type column, and the rows per type in the note
+//! - NDJSON: the `end` record, with the lines per record kind and the digest
+//!   of every line before it
+//! - json-bundle: the digest of each shard, next to its record count in the
+//!   manifest's inventory
+//!
+//! Digests are taken as the bytes are written and checked as they are read,
+//! so neither side holds a whole export in memory. rmlint-json keeps
+//! rmlint's layout and has no footer.
+
+use super::bundle;
+use crate::analyzer::AnalysisResults;
+use crate::cli::ExportFormat;
+use anyhow::{bail, Context, Result};
+use serde::de::{self, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::path::Path;
+
+/// How the pretty JSON of the results ends; the footer goes in before it
+const JSON_CLOSING: &[u8] = b"\n}";
+
+/// Type of the CSV footer row
+const CSV_FOOTER: &str = "Integrity";
+
+/// Record counts and digest of an export, as its footer states them
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Integrity {
+    /// Records by section, CSV row type, or stream record kind
+    pub records: BTreeMap<String, u64>,
+    /// Bytes from the start of the export that the digest covers
+    pub payload_bytes: u64,
+    /// SHA-256 of those bytes, in hex
+    pub sha256: String,
+}
+
+/// Passes bytes on to `inner`, hashing and counting them
+pub struct DigestWriter<W> {
+    inner: W,
+    hasher: Sha256,
+    bytes: u64,
+}
+
+impl<W: Write> DigestWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self { inner, hasher: Sha256::new(), bytes: 0 }
+    }
+
+    /// Bytes written so far, with their SHA-256 in hex
+    pub fn digest(&self) -> (u64, String) {
+        (self.bytes, format!("{:x}", self.hasher.clone().finalize()))
+    }
+
+    /// The output itself, for bytes the digest does not cover
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+}
+
+impl<W: Write> Write for DigestWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.hasher.update(&buf[..written]);
+        self.bytes += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Passes bytes on to `inner` except the last `len`, which it keeps back
+struct HoldBack<W> {
+    inner: W,
+    held: Vec<u8>,
+    len: usize,
+}
+
+impl<W: Write> Write for HoldBack<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.held.extend_from_slice(buf);
+        if self.held.len() > self.len {
+            let passed = self.held.len() - self.len;
+            self.inner.write_all(&self.held[..passed])?;
+            self.held.drain(..passed);
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Write `results` as pretty JSON whose last member is its `integrity` footer
+pub fn write_json(results: &AnalysisResults, out: impl Write) -> Result<Integrity> {
+    let mut payload = HoldBack { inner: DigestWriter::new(out), held: Vec::new(), len: JSON_CLOSING.len() };
+    serde_json::to_writer_pretty(&mut payload, results).context("Failed to serialize results to JSON")?;
+    if payload.held != JSON_CLOSING {
+        bail!("Results did not serialize to an object");
+    }
+
+    let (payload_bytes, sha256) = payload.inner.digest();
+    let integrity = Integrity { records: json_records(results), payload_bytes, sha256 };
+    write!(payload.inner.get_mut(), ",\n  \"integrity\": {}\n}}\n", serde_json::to_string(&integrity)?)?;
+    Ok(integrity)
+}
+
+/// Lengths of the lists of the results, by field; lists that were not made are left out
+fn json_records(results: &AnalysisResults) -> BTreeMap<String, u64> {
+    let lists = [
+        ("largest_files", Some(results.largest_files.len())),
+        ("largest_directories", Some(results.largest_directories.len())),
+        ("duplicate_groups", results.duplicate_groups.as_ref().map(Vec::len)),
+        ("resolved_groups", results.resolved_groups.as_ref().map(Vec::len)),
+        ("case_collisions", results.case_collisions.as_ref().map(Vec::len)),
+        ("entries", results.entries.as_ref().map(Vec::len)),
+        ("phase_errors", Some(results.phase_errors.len())),
+        ("largest_xattr_files", Some(results.largest_xattr_files.len())),
+        ("annotations", Some(results.annotations.len())),
+        ("custom_metrics", Some(results.custom_metrics.len())),
+        ("extensions", Some(results.extensions.len())),
+    ];
+    lists.into_iter().filter_map(|(field, len)| Some((field.to_string(), len? as u64))).collect()
+}
+
+/// CSV rows counted by type as they are written, with the footer row last
+pub struct CsvRows<W: Write> {
+    writer: csv::Writer<DigestWriter<W>>,
+    rows: BTreeMap<String, u64>,
+    header_written: bool,
+}
+
+impl<W: Write> CsvRows<W> {
+    pub fn new(out: W) -> Self {
+        Self { writer: csv::Writer::from_writer(DigestWriter::new(out)), rows: BTreeMap::new(), header_written: false }
+    }
+
+    /// Write a row; the first one is the header and is not counted
+    pub fn write_record(&mut self, record: [&str; 10]) -> Result<()> {
+        self.writer.write_record(record)?;
+        if std::mem::replace(&mut self.header_written, true) {
+            *self.rows.entry(record[0].to_string()).or_default() += 1;
+        }
+        Ok(())
+    }
+
+    /// Write the footer row and flush
+    pub fn finish(mut self) -> Result<Integrity> {
+        self.writer.flush()?;
+        let (payload_bytes, sha256) = self.writer.get_ref().digest();
+        let integrity = Integrity { records: self.rows, payload_bytes, sha256 };
+        let rows: Vec<String> = integrity.records.iter().map(|(kind, count)| format!("{}={}", kind, count)).collect();
+        self.writer.write_record([
+            CSV_FOOTER,
+            "",
+            &integrity.payload_bytes.to_string(),
+            &format!("sha256:{}", integrity.sha256),
+            "",
+            "",
+            "",
+            "",
+            &rows.join(", "),
+            "",
+        ])?;
+        self.writer.flush()?;
+        Ok(integrity)
+    }
+}
+
+/// Running digest and counts of the lines of an NDJSON stream
+#[derive(Default)]
+pub(crate) struct LineTally {
+    hasher: Sha256,
+    bytes: u64,
+    records: BTreeMap<String, u64>,
+}
+
+impl LineTally {
+    pub fn add(&mut self, kind: &str, line: &[u8]) {
+        self.hasher.update(line);
+        self.bytes += line.len() as u64;
+        *self.records.entry(kind.to_string()).or_default() += 1;
+    }
+
+    pub fn integrity(&self) -> Integrity {
+        Integrity {
+            records: self.records.clone(),
+            payload_bytes: self.bytes,
+            sha256: format!("{:x}", self.hasher.clone().finalize()),
+        }
+    }
+}
+
+/// What `verify-export` found in an export
+#[derive(Debug, Clone)]
+pub struct Verification {
+    pub format: ExportFormat,
+    /// Records counted in the export, by section, row type, record kind, or shard kind
+    pub records: BTreeMap<String, u64>,
+    /// Where the export disagrees with its footer; empty when it is intact
+    pub problems: Vec<String>,
+}
+
+impl Verification {
+    pub fn is_intact(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+/// Check the export at `path` against its integrity footer
+///
+/// The format is told from the contents: a bundle directory, the CSV header,
+/// an NDJSON record, or else JSON. Fails only when `path` cannot be read.
+pub fn verify_export(path: &Path) -> Result<Verification> {
+    if bundle::is_bundle(path) {
+        return Ok(verify_bundle(path));
+    }
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut start = Vec::new();
+    BufReader::new(file).take(64).read_to_end(&mut start).with_context(|| format!("Failed to read {}", path.display()))?;
+
+    let verification = if start.starts_with(b"Type,Path,") {
+        verify_csv(path)?
+    } else if start.starts_with(b"{\"record\":") {
+        verify_ndjson(path)?
+    } else if start.starts_with(b"[") {
+        Verification {
+            format: ExportFormat::RmlintJson,
+            records: BTreeMap::new(),
+            problems: vec!["rmlint-json exports keep rmlint's layout and carry no integrity footer".to_string()],
+        }
+    } else {
+        verify_json(path)?
+    };
+    Ok(verification)
+}
+
+/// Compare what an export holds with what its footer states
+fn compare(found: &BTreeMap<String, u64>, payload: (u64, String), footer: &Integrity, problems: &mut Vec<String>) {
+    for (kind, &stated) in &footer.records {
+        let counted = found.get(kind).copied().unwrap_or(0);
+        if counted != stated {
+            problems.push(format!("{} holds {} records; the footer states {}", kind, counted, stated));
+        }
+    }
+    for (kind, counted) in found.iter().filter(|(kind, _)| !footer.records.contains_key(*kind)) {
+        problems.push(format!("{} holds {} records the footer does not state", kind, counted));
+    }
+    let (bytes, sha256) = payload;
+    if bytes != footer.payload_bytes {
+        problems.push(format!("{} bytes precede the footer; it states {}", bytes, footer.payload_bytes));
+    } else if sha256 != footer.sha256 {
+        problems.push(format!("SHA-256 of the contents is {}; the footer states {}", sha256, footer.sha256));
+    }
+}
+
+/// Digest of the first `len` bytes of `path`, and how many there were
+fn digest_prefix(path: &Path, len: u64) -> Result<(u64, String)> {
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut digest = DigestWriter::new(io::sink());
+    io::copy(&mut BufReader::new(file).take(len), &mut digest).with_context(|| format!("Failed to read {}", path.display()))?;
+    Ok(digest.digest())
+}
+
+fn verify_json(path: &Path) -> Result<Verification> {
+    let mut verification = Verification { format: ExportFormat::Json, records: BTreeMap::new(), problems: Vec::new() };
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let outline: JsonOutline = match serde_json::from_reader(BufReader::new(file)) {
+        Ok(outline) => outline,
+        Err(error) => {
+            verification.problems.push(format!("not a whole JSON document, so probably truncated: {}", error));
+            return Ok(verification);
+        }
+    };
+    let Some(footer) = outline.integrity else {
+        verification.problems.push("no integrity member; written by an older version or cut short".to_string());
+        return Ok(verification);
+    };
+    // Only the lists the footer counts are compared; other arrays of the results are not records
+    verification.records = outline.lists.into_iter().filter(|(field, _)| footer.records.contains_key(field)).collect();
+    let payload = digest_prefix(path, footer.payload_bytes)?;
+    compare(&verification.records, payload, &footer, &mut verification.problems);
+    Ok(verification)
+}
+
+fn verify_csv(path: &Path) -> Result<Verification> {
+    let mut verification = Verification { format: ExportFormat::Csv, records: BTreeMap::new(), problems: Vec::new() };
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut reader = csv::ReaderBuilder::new().has_headers(false).flexible(true).from_reader(BufReader::new(file));
+    let mut record = csv::StringRecord::new();
+    let mut footer: Option<(u64, Integrity)> = None;
+    let mut header = true;
+    loop {
+        let offset = reader.position().byte();
+        match reader.read_record(&mut record) {
+            Ok(false) => break,
+            Ok(true) => {}
+            Err(error) => {
+                verification.problems.push(format!("unreadable row at byte {}: {}", offset, error));
+                return Ok(verification);
+            }
+        }
+        if std::mem::replace(&mut header, false) {
+            continue;
+        }
+        if footer.is_some() {
+            verification.problems.push(format!("rows follow the {} row", CSV_FOOTER));
+            break;
+        }
+        let kind = record.get(0).unwrap_or_default();
+        if kind == CSV_FOOTER {
+            match parse_csv_footer(&record) {
+                Some(integrity) => footer = Some((offset, integrity)),
+                None => verification.problems.push(format!("the {} row at byte {} is malformed", CSV_FOOTER, offset)),
+            }
+        } else {
+            *verification.records.entry(kind.to_string()).or_default() += 1;
+        }
+    }
+
+    let Some((offset, integrity)) = footer else {
+        if verification.problems.is_empty() {
+            verification.problems.push(format!("no {} row; written by an older version or cut short", CSV_FOOTER));
+        }
+        return Ok(verification);
+    };
+    let payload = digest_prefix(path, offset)?;
+    compare(&verification.records, payload, &integrity, &mut verification.problems);
+    Ok(verification)
+}
+
+fn parse_csv_footer(record: &csv::StringRecord) -> Option<Integrity> {
+    let payload_bytes = record.get(2)?.parse().ok()?;
+    let sha256 = record.get(3)?.strip_prefix("sha256:")?.to_string();
+    let mut records = BTreeMap::new();
+    // The note is the column before Regenerable, also in exports written before the Created column
+    let note = record.get(record.len().checked_sub(2)?)?;
+    for row in note.split(", ").filter(|row| !row.is_empty()) {
+        let (kind, count) = row.split_once('=')?;
+        records.insert(kind.to_string(), count.parse().ok()?);
+    }
+    Some(Integrity { records, payload_bytes, sha256 })
+}
+
+fn verify_ndjson(path: &Path) -> Result<Verification> {
+    let mut verification = Verification { format: ExportFormat::Ndjson, records: BTreeMap::new(), problems: Vec::new() };
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    let mut reader = BufReader::new(file);
+    let mut tally = LineTally::default();
+    let mut line = Vec::new();
+    let mut number = 0;
+    let mut end: Option<(u64, String, Integrity, bool)> = None;
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line).with_context(|| format!("Failed to read {}", path.display()))? == 0 {
+            break;
+        }
+        number += 1;
+        if end.is_some() {
+            verification.problems.push(format!("line {} follows the end record", number));
+            break;
+        }
+        let record: serde_json::Value = match serde_json::from_slice(&line) {
+            Ok(record) if line.ends_with(b"\n") => record,
+            _ => {
+                verification.problems.push(format!("line {} is not a whole record, so the stream is probably truncated", number));
+                return Ok(verification);
+            }
+        };
+        let kind = record["record"].as_str().unwrap_or_default().to_string();
+        if kind == "end" {
+            let complete = record["complete"].as_bool().unwrap_or(false);
+            let Ok(integrity) = serde_json::from_value(record) else {
+                verification.problems.push(format!("the end record on line {} has no integrity footer", number));
+                return Ok(verification);
+            };
+            let (bytes, sha256) = (tally.bytes, tally.integrity().sha256);
+            end = Some((bytes, sha256, integrity, complete));
+        } else {
+            tally.add(&kind, &line);
+        }
+    }
+
+    verification.records = tally.records.clone();
+    let Some((bytes, sha256, integrity, complete)) = end else {
+        verification.problems.push("no end record; the stream was cut short".to_string());
+        return Ok(verification);
+    };
+    if !complete {
+        verification.problems.push("the end record marks the run as ended without its results".to_string());
+    }
+    compare(&verification.records, (bytes, sha256), &integrity, &mut verification.problems);
+    Ok(verification)
+}
+
+fn verify_bundle(path: &Path) -> Verification {
+    let mut verification = Verification { format: ExportFormat::JsonBundle, records: BTreeMap::new(), problems: Vec::new() };
+    let manifest = match bundle::read_manifest(path) {
+        Ok(manifest) => manifest,
+        Err(error) => {
+            verification.problems.push(format!("{:#}", error));
+            return verification;
+        }
+    };
+    for shard in &manifest.inventory {
+        match bundle::read_shard(path, shard, |_, _| Ok(())) {
+            Ok(()) => {
+                *verification.records.entry(shard.kind.field().to_string()).or_default() += shard.records;
+            }
+            Err(error) => verification.problems.push(format!("{:#}", error)),
+        }
+    }
+    verification
+}
+
+/// The lengths of the top-level lists of a JSON export, and its footer
+///
+/// Read with a streaming visitor, so that the records themselves are skipped
+/// rather than held.
+#[derive(Default)]
+struct JsonOutline {
+    lists: BTreeMap<String, u64>,
+    integrity: Option<Integrity>,
+}
+
+impl<'de> Deserialize<'de> for JsonOutline {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        struct OutlineVisitor;
+
+        impl<'de> Visitor<'de> for OutlineVisitor {
+            type Value = JsonOutline;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("an object of results")
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> std::result::Result<JsonOutline, A::Error> {
+                let mut outline = JsonOutline::default();
+                while let Some(key) = map.next_key::<String>()? {
+                    if key == "integrity" {
+                        outline.integrity = Some(map.next_value()?);
+                    } else if let ListLength(Some(len)) = map.next_value()? {
+                        outline.lists.insert(key, len);
+                    }
+                }
+                Ok(outline)
+            }
+        }
+
+        deserializer.deserialize_map(OutlineVisitor)
+    }
+}
+
+/// The number of elements of a list, or `None` for any other value
+struct ListLength(Option<u64>);
+
+impl<'de> Deserialize<'de> for ListLength {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        struct LengthVisitor;
+
+        impl<'de> Visitor<'de> for LengthVisitor {
+            type Value = ListLength;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("any JSON value")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> std::result::Result<ListLength, A::Error> {
+                let mut len = 0;
+                while seq.next_element::<IgnoredAny>()?.is_some() {
+                    len += 1;
+                }
+                Ok(ListLength(Some(len)))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> std::result::Result<ListLength, A::Error> {
+                while map.next_entry::<IgnoredAny, IgnoredAny>()?.is_some() {}
+                Ok(ListLength(None))
+            }
+
+            fn visit_bool<E: de::Error>(self, _: bool) -> std::result::Result<ListLength, E> {
+                Ok(ListLength(None))
+            }
+
+            fn visit_i64<E: de::Error>(self, _: i64) -> std::result::Result<ListLength, E> {
+                Ok(ListLength(None))
+            }
+
+            fn visit_u64<E: de::Error>(self, _: u64) -> std::result::Result<ListLength, E> {
+                Ok(ListLength(None))
+            }
+
+            fn visit_f64<E: de::Error>(self, _: f64) -> std::result::Result<ListLength, E> {
+                Ok(ListLength(None))
+            }
+
+            fn visit_str<E: de::Error>(self, _: &str) -> std::result::Result<ListLength, E> {
+                Ok(ListLength(None))
+            }
+
+            fn visit_unit<E: de::Error>(self) -> std::result::Result<ListLength, E> {
+                Ok(ListLength(None))
+            }
+        }
+
+        deserializer.deserialize_any(LengthVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::{export_results, naming::OutputNaming};
+    use crate::writes::WriteGuard;
+    use std::path::PathBuf;
+
+    fn results() -> AnalysisResults {
+        serde_json::from_str(include_str!("../../tests/golden/results.json")).unwrap()
+    }
+
+    fn export(dir: &Path, format: ExportFormat, name: &str) -> PathBuf {
+        let path = dir.join(name);
+        let shard_size = bundle::DEFAULT_SHARD_SIZE;
+        export_results(&results(), &format, &Some(path.clone()), &OutputNaming::default(), shard_size, &WriteGuard::unrestricted()).unwrap();
+        path
+    }
+
+    /// Replace the first `from` in the file at `path` by `to`
+    fn corrupt(path: &Path, from: &str, to: &str) {
+        let contents = std::fs::read_to_string(path).unwrap();
+        assert!(contents.contains(from), "{} not in {}", from, path.display());
+        std::fs::write(path, contents.replacen(from, to, 1)).unwrap();
+    }
+
+    fn truncate(path: &Path) {
+        let contents = std::fs::read(path).unwrap();
+        std::fs::write(path, &contents[..contents.len() * 2 / 3]).unwrap();
+    }
+
+    fn problems(path: &Path) -> Vec<String> {
+        verify_export(path).unwrap().problems
+    }
+
+    #[test]
+    fn test_intact_exports_verify() {
+        let dir = tempfile::tempdir().unwrap();
+        let expected = results();
+        for (format, name) in [
+            (ExportFormat::Json, "out.json"),
+            (ExportFormat::Csv, "out.csv"),
+            (ExportFormat::Ndjson, "out.ndjson"),
+            (ExportFormat::JsonBundle, "out.bundle"),
+        ] {
+            let verification = verify_export(&export(dir.path(), format.clone(), name)).unwrap();
+            assert!(verification.is_intact(), "{}: {:?}", name, verification.problems);
+            assert_eq!(verification.format.to_string(), format.to_string());
+            assert!(!verification.records.is_empty(), "{}", name);
+        }
+
+        let json = verify_export(&dir.path().join("out.json")).unwrap();
+        assert_eq!(json.records["largest_files"], expected.largest_files.len() as u64);
+        assert_eq!(json.records["duplicate_groups"], 2);
+        let csv = verify_export(&dir.path().join("out.csv")).unwrap();
+        assert_eq!(csv.records["Duplicate"], 4);
+        // Files carry their birth time in the last column
+        let contents = std::fs::read_to_string(dir.path().join("out.csv")).unwrap();
+        assert!(contents.starts_with("Type,Path,Size,FileType,Modified,Created,Depth,"));
+        let report = contents.lines().find(|line| line.starts_with("File,") && line.contains("report.pdf")).unwrap();
+        assert!(report.contains(",2024-01-11T00:00:00+00:00,2024-01-11T00:00:00+00:00,"), "{}", report);
+        // Exports still load with their footers
+        assert_eq!(crate::export::load_results(&dir.path().join("out.json")).unwrap().largest_files.len(), expected.largest_files.len());
+    }
+
+    #[test]
+    fn test_json_corruption_and_truncation_are_detected() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = export(dir.path(), ExportFormat::Json, "out.json");
+        corrupt(&path, "report.pdf", "report.pdg");
+        assert!(problems(&path)[0].contains("SHA-256"));
+
+        let path = export(dir.path(), ExportFormat::Json, "cut.json");
+        truncate(&path);
+        assert!(problems(&path)[0].contains("truncated"));
+
+        // A record dropped along with the digest still disagrees with the counts
+        let path = export(dir.path(), ExportFormat::Json, "emptied.json");
+        let mut value: serde_json::Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        value["largest_files"].as_array_mut().unwrap().pop();
+        std::fs::write(&path, serde_json::to_string_pretty(&value).unwrap()).unwrap();
+        assert!(problems(&path).iter().any(|problem| problem.starts_with("largest_files holds")));
+    }
+
+    #[test]
+    fn test_csv_corruption_and_truncation_are_detected() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = export(dir.path(), ExportFormat::Csv, "out.csv");
+        corrupt(&path, "report.pdf", "report.pdg");
+        assert!(problems(&path)[0].contains("SHA-256"));
+
+        let path = export(dir.path(), ExportFormat::Csv, "cut.csv");
+        truncate(&path);
+        assert!(problems(&path)[0].contains("no Integrity row"));
+
+        let path = export(dir.path(), ExportFormat::Csv, "extra.csv");
+        let contents = std::fs::read_to_string(&path).unwrap();
+        let (header, rest) = contents.split_once('\n').unwrap();
+        std::fs::write(&path, format!("{}\nFile,/extra,1,,,,,,,\n{}", header, rest)).unwrap();
+        let found = problems(&path);
+        assert!(found.iter().any(|problem| problem.starts_with("File holds")), "{:?}", found);
+    }
+
+    #[test]
+    fn test_ndjson_corruption_and_truncation_are_detected() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = export(dir.path(), ExportFormat::Ndjson, "out.ndjson");
+        corrupt(&path, "report.pdf", "report.pdg");
+        assert!(problems(&path)[0].contains("SHA-256"));
+
+        let path = export(dir.path(), ExportFormat::Ndjson, "cut.ndjson");
+        truncate(&path);
+        let found = problems(&path);
+        assert!(found[0].contains("truncated") || found[0].contains("cut short"), "{:?}", found);
+
+        // Whole lines cut off still lack the end record
+        let path = export(dir.path(), ExportFormat::Ndjson, "lines.ndjson");
+        let contents = std::fs::read_to_string(&path).unwrap();
+        let kept: Vec<&str> = contents.lines().collect();
+        std::fs::write(&path, kept[..kept.len() - 2].iter().map(|line| format!("{}\n", line)).collect::<String>()).unwrap();
+        assert_eq!(problems(&path), ["no end record; the stream was cut short"]);
+    }
+
+    #[test]
+    fn test_bundle_corruption_and_truncation_are_detected() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = export(dir.path(), ExportFormat::JsonBundle, "out.bundle");
+        corrupt(&path.join("files-00001.ndjson"), "report.pdf", "report.pdg");
+        let found = problems(&path);
+        assert_eq!(found.len(), 1);
+        assert!(found[0].contains("files-00001.ndjson") && found[0].contains("SHA-256"), "{:?}", found);
+        assert!(crate::export::load_results(&path).is_err());
+
+        let path = export(dir.path(), ExportFormat::JsonBundle, "cut.bundle");
+        truncate(&path.join("directories-00001.ndjson"));
+        assert!(problems(&path)[0].contains("directories-00001.ndjson"));
+    }
+
+    #[test]
+    fn test_digest_writer_matches_a_digest_of_the_whole() {
+        let mut writer = DigestWriter::new(Vec::new());
+        writer.write_all(b"hello ").unwrap();
+        writer.write_all(b"world").unwrap();
+        let (bytes, digest) = writer.digest();
+        assert_eq!(bytes, 11);
+        assert_eq!(digest, format!("{:x}", Sha256::digest(b"hello world")));
+    }
+}
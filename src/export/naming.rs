@@ -0,0 +1,422 @@
+//! Auto-generated export filenames
+//!
+//! Without `--output`, exports are named from a template such as
+//! `report_{root}_{date}_{seq}.{ext}`. Placeholder values are sanitized so a
+//! hostile root or host name cannot escape the target directory, a counter
+//! keeps repeated runs within the same second from overwriting each other, and
+//! `--keep-last N` prunes older exports that match the template.
+
+use crate::writes::WriteGuard;
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use std::path::{Path, PathBuf};
+
+/// Template used when `--output-template` is not given
+pub const DEFAULT_TEMPLATE: &str = "diranalyzer_report_{root}_{date}_{time}.{ext}";
+
+const PLACEHOLDERS: [&str; 6] = ["root", "date", "time", "seq", "ext", "hostname"];
+
+/// Give up on finding a free name after this many collisions
+const MAX_SEQ: u32 = 10_000;
+
+/// Longest sanitized placeholder value
+const MAX_COMPONENT_LEN: usize = 64;
+
+/// How auto-named exports are named and retained
+#[derive(Debug, Clone, Default)]
+pub struct OutputNaming {
+    pub template: Option<String>,
+    pub keep_last: Option<u64>,
+}
+
+impl OutputNaming {
+    pub fn from_args(args: &crate::cli::Args) -> Self {
+        Self { template: args.output_template.clone(), keep_last: args.keep_last }
+    }
+
+    fn template(&self) -> &str {
+        self.template.as_deref().unwrap_or(DEFAULT_TEMPLATE)
+    }
+}
+
+/// Values substituted into a template
+#[derive(Debug, Clone)]
+pub struct TemplateValues {
+    pub root: String,
+    pub date: String,
+    pub time: String,
+    pub ext: String,
+    pub hostname: String,
+}
+
+impl TemplateValues {
+    pub fn new(root: &Path, ext: &str, now: DateTime<Utc>) -> Self {
+        Self {
+            root: sanitize_component(&root_name(root)),
+            date: now.format("%Y%m%d").to_string(),
+            time: now.format("%H%M%S").to_string(),
+            ext: sanitize_component(ext),
+            hostname: sanitize_component(&hostname()),
+        }
+    }
+}
+
+/// One piece of a parsed template
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Literal(String),
+    Placeholder(String),
+}
+
+/// Check a template for `--output-template`
+pub fn parse_template(template: &str) -> Result<String, String> {
+    let tokens = tokenize(template).map_err(|error| error.to_string())?;
+    if !tokens.iter().any(|token| matches!(token, Token::Placeholder(_))) {
+        return Err(format!("template '{}' has no placeholders; use --output for a fixed name", template));
+    }
+    Ok(template.to_string())
+}
+
+fn tokenize(template: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut literal = String::new();
+    let mut chars = template.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                literal.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                literal.push('}');
+            }
+            '{' => {
+                let mut name = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => name.push(c),
+                        None => bail!("unclosed '{{' in '{}'", template),
+                    }
+                }
+                if !PLACEHOLDERS.contains(&name.as_str()) {
+                    bail!(
+                        "unknown placeholder '{{{}}}' in '{}' (expected one of {{{}}})",
+                        name,
+                        template,
+                        PLACEHOLDERS.join("}, {")
+                    );
+                }
+                if !literal.is_empty() {
+                    tokens.push(Token::Literal(std::mem::take(&mut literal)));
+                }
+                tokens.push(Token::Placeholder(name));
+            }
+            '}' => bail!("unmatched '}}' in '{}' (write '}}}}' for a literal brace)", template),
+            c => literal.push(c),
+        }
+    }
+    if !literal.is_empty() {
+        tokens.push(Token::Literal(literal));
+    }
+    Ok(tokens)
+}
+
+/// Expand a template for the given sequence number
+pub fn expand(template: &str, values: &TemplateValues, seq: u32) -> Result<String> {
+    let expanded = tokenize(template)?
+        .into_iter()
+        .map(|token| expand_token(token, values, seq))
+        .collect();
+    Ok(expanded)
+}
+
+fn expand_token(token: Token, values: &TemplateValues, seq: u32) -> String {
+    match token {
+        Token::Literal(text) => text,
+        Token::Placeholder(name) => match name.as_str() {
+            "root" => values.root.clone(),
+            "date" => values.date.clone(),
+            "time" => values.time.clone(),
+            "seq" => seq.to_string(),
+            "ext" => values.ext.clone(),
+            _ => values.hostname.clone(),
+        },
+    }
+}
+
+/// First free path for an auto-named export
+///
+/// Templates with `{seq}` continue after the highest number already on disk,
+/// so pruned exports are never reused; other templates get a `_N` suffix
+/// before the extension once the plain name is taken.
+pub fn auto_path(naming: &OutputNaming, values: &TemplateValues) -> Result<PathBuf> {
+    let template = naming.template();
+    let has_seq = template.contains("{seq}");
+    let first = if has_seq { last_seq(template, values)? + 1 } else { 1 };
+
+    for seq in first..first.saturating_add(MAX_SEQ) {
+        let name = expand(template, values, seq)?;
+        let candidate = if has_seq || seq == 1 {
+            PathBuf::from(name)
+        } else {
+            PathBuf::from(with_collision_suffix(&name, &values.ext, seq - 1))
+        };
+        if !candidate.exists() {
+            return Ok(candidate);
+        }
+    }
+    bail!("No free export filename for template '{}' after {} attempts", template, MAX_SEQ)
+}
+
+/// Highest `{seq}` among existing exports with otherwise identical names (0 if none)
+fn last_seq(template: &str, values: &TemplateValues) -> Result<u32> {
+    let (directory, file_template) = match template.rsplit_once('/') {
+        Some((directory, file)) => (expand(directory, values, 0)?, file),
+        None => (".".to_string(), template),
+    };
+
+    let mut pattern = String::from("^");
+    for token in tokenize(file_template)? {
+        match token {
+            Token::Placeholder(name) if name == "seq" => pattern.push_str(r"(\d+)"),
+            token => pattern.push_str(&regex::escape(&expand_token(token, values, 0))),
+        }
+    }
+    pattern.push('$');
+    let pattern = Regex::new(&pattern)?;
+
+    let Ok(entries) = std::fs::read_dir(if directory.is_empty() { "/" } else { &directory }) else {
+        return Ok(0);
+    };
+    Ok(entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            pattern.captures(&name)?[1].parse().ok()
+        })
+        .max()
+        .unwrap_or(0))
+}
+
+/// Insert `_N` before `.ext`, or append it when the name has another ending
+fn with_collision_suffix(name: &str, ext: &str, n: u32) -> String {
+    match name.strip_suffix(&format!(".{}", ext)) {
+        Some(stem) => format!("{}_{}.{}", stem, n, ext),
+        None => format!("{}_{}", name, n),
+    }
+}
+
+/// Delete all but the newest `keep_last` exports matching the template next to `written`
+///
+/// Returns the removed paths. Bundle directories count as exports alongside
+/// files. The export just written is never removed.
+pub fn prune(naming: &OutputNaming, written: &Path, ext: &str, keep_last: u64, writes: &WriteGuard) -> Result<Vec<PathBuf>> {
+    let pattern = retention_pattern(naming.template(), ext)?;
+    let directory = match written.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+
+    let mut exports: Vec<(std::time::SystemTime, PathBuf)> = std::fs::read_dir(&directory)
+        .with_context(|| format!("Failed to list {}", directory.display()))?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_ok_and(|file_type| file_type.is_file()) || super::bundle::is_bundle(&entry.path()))
+        .filter(|entry| pattern.is_match(&entry.file_name().to_string_lossy()))
+        .filter(|entry| entry.file_name() != written.file_name().unwrap_or_default())
+        .map(|entry| {
+            let modified = entry.metadata().and_then(|m| m.modified()).unwrap_or(std::time::UNIX_EPOCH);
+            (modified, entry.path())
+        })
+        .collect();
+
+    // Newest first; names break ties so same-second exports prune deterministically
+    exports.sort_by(|a, b| b.0.cmp(&a.0).then_with(|| b.1.cmp(&a.1)));
+
+    let mut removed = Vec::new();
+    for (_, path) in exports.into_iter().skip(keep_last.saturating_sub(1) as usize) {
+        let removed_export = if path.is_dir() { writes.remove_dir_all(&path) } else { writes.remove_file(&path) };
+        removed_export.with_context(|| format!("Failed to remove old export {}", path.display()))?;
+        removed.push(path);
+    }
+    Ok(removed)
+}
+
+/// Regex matching file names the template can produce, including collision suffixes
+pub(crate) fn retention_pattern(template: &str, ext: &str) -> Result<Regex> {
+    let file_template = template.rsplit('/').next().unwrap_or(template);
+    let ext_suffix = ".{ext}";
+    let (body, ending) = match file_template.strip_suffix(ext_suffix) {
+        Some(body) => (body, format!(r"(?:_\d+)?\.{}", regex::escape(ext))),
+        None => (file_template, r"(?:_\d+)?".to_string()),
+    };
+
+    let mut pattern = String::from("^");
+    for token in tokenize(body)? {
+        match token {
+            Token::Literal(text) => pattern.push_str(&regex::escape(&text)),
+            Token::Placeholder(name) if name == "ext" => pattern.push_str(&regex::escape(ext)),
+            Token::Placeholder(_) => pattern.push_str(".+"),
+        }
+    }
+    pattern.push_str(&ending);
+    pattern.push('$');
+    Ok(Regex::new(&pattern)?)
+}
+
+/// Reduce a placeholder value to a single safe path component
+pub fn sanitize_component(value: &str) -> String {
+    let mut sanitized: String = value
+        .chars()
+        .map(|c| if c.is_alphanumeric() || matches!(c, '-' | '_' | '.') { c } else { '_' })
+        .take(MAX_COMPONENT_LEN)
+        .collect();
+    // No hidden files, and no `.`/`..` components
+    while sanitized.starts_with('.') {
+        sanitized.remove(0);
+    }
+    if sanitized.is_empty() {
+        sanitized.push_str("root");
+    }
+    sanitized
+}
+
+/// Basename of the scan root, resolving `.` and other relative roots where possible
+fn root_name(root: &Path) -> String {
+    let resolved = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+    resolved
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+fn hostname() -> String {
+    #[cfg(unix)]
+    {
+        let mut buffer = [0u8; 256];
+        // SAFETY: gethostname writes at most `buffer.len()` bytes into the buffer
+        if unsafe { libc::gethostname(buffer.as_mut_ptr().cast(), buffer.len()) } == 0 {
+            let len = buffer.iter().position(|&b| b == 0).unwrap_or(buffer.len());
+            return String::from_utf8_lossy(&buffer[..len]).into_owned();
+        }
+    }
+    "localhost".to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn values(root: &str) -> TemplateValues {
+        TemplateValues {
+            root: sanitize_component(root),
+            date: "20240301".to_string(),
+            time: "120000".to_string(),
+            ext: "json".to_string(),
+            hostname: "build-01".to_string(),
+        }
+    }
+
+    fn naming(template: &str, dir: &Path) -> OutputNaming {
+        OutputNaming { template: Some(format!("{}/{}", dir.display(), template)), keep_last: None }
+    }
+
+    #[test]
+    fn test_expand_placeholders() {
+        let expanded = expand("{hostname}/report_{root}_{date}_{time}_{seq}.{ext}", &values("photos"), 3).unwrap();
+        assert_eq!(expanded, "build-01/report_photos_20240301_120000_3.json");
+        assert_eq!(expand("{{literal}}_{seq}", &values("x"), 1).unwrap(), "{literal}_1");
+    }
+
+    #[test]
+    fn test_template_errors() {
+        assert!(parse_template("report_{root}.{ext}").is_ok());
+        assert!(parse_template("report_{bogus}.{ext}").unwrap_err().contains("unknown placeholder '{bogus}'"));
+        assert!(parse_template("report_{root.{ext}").is_err());
+        assert!(parse_template("report_{root").unwrap_err().contains("unclosed"));
+        assert!(parse_template("report}.json").unwrap_err().contains("unmatched"));
+        assert!(parse_template("report.json").unwrap_err().contains("no placeholders"));
+    }
+
+    #[test]
+    fn test_hostile_root_names_stay_in_one_component() {
+        for hostile in ["../../etc/passwd", "a/b\\c", "..", "/", "", "name\0with nul", ".hidden"] {
+            let name = expand("report_{root}.{ext}", &values(hostile), 1).unwrap();
+            assert!(!name.contains('/') && !name.contains('\\') && !name.contains('\0'), "{:?} -> {}", hostile, name);
+            assert_eq!(Path::new(&name).components().count(), 1, "{:?} -> {}", hostile, name);
+        }
+        assert_eq!(sanitize_component("../../etc/passwd"), "_.._etc_passwd");
+        assert_eq!(sanitize_component(".."), "root");
+        assert_eq!(sanitize_component(&"x".repeat(500)).len(), MAX_COMPONENT_LEN);
+    }
+
+    #[test]
+    fn test_root_name_of_relative_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("My Photos");
+        std::fs::create_dir(&root).unwrap();
+        let values = TemplateValues::new(&root.join("."), "csv", Utc.with_ymd_and_hms(2024, 3, 1, 9, 5, 0).unwrap());
+        assert_eq!(values.root, "My_Photos");
+        assert_eq!((values.date.as_str(), values.time.as_str()), ("20240301", "090500"));
+    }
+
+    #[test]
+    fn test_collisions_get_a_counter() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let plain = naming("report_{root}.{ext}", dir.path());
+        let first = auto_path(&plain, &values("data")).unwrap();
+        assert_eq!(first, dir.path().join("report_data.json"));
+        std::fs::write(&first, "{}").unwrap();
+        let second = auto_path(&plain, &values("data")).unwrap();
+        assert_eq!(second, dir.path().join("report_data_1.json"));
+        std::fs::write(&second, "{}").unwrap();
+        assert_eq!(auto_path(&plain, &values("data")).unwrap(), dir.path().join("report_data_2.json"));
+
+        // Sequence numbers keep counting past gaps left by pruning
+        let sequenced = naming("run_{seq}.{ext}", dir.path());
+        assert_eq!(auto_path(&sequenced, &values("data")).unwrap(), dir.path().join("run_1.json"));
+        std::fs::write(dir.path().join("run_7.json"), "{}").unwrap();
+        assert_eq!(auto_path(&sequenced, &values("data")).unwrap(), dir.path().join("run_8.json"));
+    }
+
+    #[test]
+    fn test_retention_keeps_newest_matching_exports() {
+        let dir = tempfile::tempdir().unwrap();
+        let naming = naming("report_{root}_{seq}.{ext}", dir.path());
+        for seq in 1..=5 {
+            let path = dir.path().join(format!("report_data_{}.json", seq));
+            std::fs::write(&path, "{}").unwrap();
+            let modified = std::time::UNIX_EPOCH + std::time::Duration::from_secs(1_000 * seq);
+            std::fs::File::options().write(true).open(&path).unwrap().set_modified(modified).unwrap();
+        }
+        // Files the template cannot produce are left alone
+        std::fs::write(dir.path().join("report_data_1.csv"), "").unwrap();
+        std::fs::write(dir.path().join("notes.json"), "").unwrap();
+
+        let removed = prune(&naming, &dir.path().join("report_data_5.json"), "json", 2, &WriteGuard::unrestricted()).unwrap();
+        let mut removed: Vec<_> = removed.iter().map(|p| p.file_name().unwrap().to_string_lossy().into_owned()).collect();
+        removed.sort();
+        assert_eq!(removed, ["report_data_1.json", "report_data_2.json", "report_data_3.json"]);
+
+        let mut left: Vec<_> = std::fs::read_dir(dir.path())
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        left.sort();
+        assert_eq!(left, ["notes.json", "report_data_1.csv", "report_data_4.json", "report_data_5.json"]);
+    }
+
+    #[test]
+    fn test_retention_pattern_covers_collision_suffixes() {
+        let pattern = retention_pattern(DEFAULT_TEMPLATE, "json").unwrap();
+        assert!(pattern.is_match("diranalyzer_report_data_20240301_120000.json"));
+        assert!(pattern.is_match("diranalyzer_report_data_20240301_120000_3.json"));
+        assert!(!pattern.is_match("diranalyzer_report_data_20240301_120000.csv"));
+        assert!(!pattern.is_match("unrelated.json"));
+    }
+}
@@ -0,0 +1,453 @@
+//! One NDJSON stream of progress records followed by the results
+//!
+//! `--export ndjson` writes one JSON object per line: interim scan totals and
+//! hashing progress while the run goes on, then the scan info, the listings,
+//! the duplicate groups, case collisions, version groups, broken symlinks,
+//! scan errors by cause and subtree and one by one, operator notes, accepted
+//! findings, and the statistics, and last an `end` record. Each record names its kind in
+//! `"record"`. The `end` record counts the lines before it by kind and carries
+//! the SHA-256 of their bytes (see [`super::integrity`]). Producers on any thread hand typed records to a
+//! [`RecordSender`]; a single writer thread owns the output and serializes
+//! them in arrival order, so lines never interleave. The queue
+//! between them is bounded, so a slow consumer makes producers wait rather
+//! than the queue grow. The output is flushed whenever the writer catches up,
+//! and flushed and synced when the stream is finished or dropped early; a run
+//! that fails leaves whole lines ending in an `end` record with `"complete":false`.
+
+use crate::analyzer::{AnalysisResults, DirectoryInfo, DuplicateGroup, FileInfo, ScanInfo, Statistics};
+use super::integrity::{Integrity, LineTally};
+use crate::accepted::AcceptedFinding;
+use crate::metrics::CustomMetric;
+use crate::notes::Annotation;
+use crate::scanner::{BrokenLink, CaseCollision, ErrorCause, ErrorRollup, InterimSnapshot, ScanError};
+use crate::versions::VersionGroup;
+use crate::writes::WriteGuard;
+use anyhow::{anyhow, bail, Context, Result};
+use serde::Serialize;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, SyncSender, TryRecvError};
+use std::sync::Mutex;
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+/// Records queued between the producers and the writer before producers wait
+pub const QUEUE_CAPACITY: usize = 1024;
+
+/// How often interim scan totals are streamed without `--interim-every`
+pub const PROGRESS_EVERY: Duration = Duration::from_secs(1);
+
+/// Whether `path` names stdout rather than a file
+pub fn is_stdout(path: &Path) -> bool {
+    path == Path::new("-")
+}
+
+/// One line of the stream
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "record", rename_all = "snake_case")]
+pub enum StreamRecord {
+    ScanProgress(ScanProgress),
+    HashProgress(HashProgress),
+    ScanInfo(Box<ScanInfo>),
+    LargestFile(FileInfo),
+    LargestDirectory(Box<DirectoryInfo>),
+    DuplicateGroup(Box<DuplicateGroup>),
+    CaseCollision(Box<CaseCollision>),
+    /// Found by name alone with `--find-versions`, so a heuristic
+    VersionGroup(Box<VersionGroup>),
+    /// A symlink with a missing target, with `--find-broken-links`
+    BrokenLink(Box<BrokenLink>),
+    /// Scan errors sharing a type and errno
+    ErrorCause(Box<ErrorCause>),
+    /// Scan errors of one cause gathered below a directory
+    ErrorRollup(Box<ErrorRollup>),
+    /// One of the scan errors listed, all of them with `--errors-full`
+    ScanError(Box<ScanError>),
+    /// An operator note on a path of the tree
+    Annotation(Box<Annotation>),
+    /// A finding accepted with `accept`; the listings flag what it covers
+    Accepted(Box<AcceptedFinding>),
+    /// A metric defined in the configuration file
+    CustomMetric(Box<CustomMetric>),
+    Statistics(Box<Statistics>),
+    /// Always the last line; `complete` is false when the run ended without its results
+    End {
+        complete: bool,
+        #[serde(flatten)]
+        integrity: Integrity,
+    },
+}
+
+impl StreamRecord {
+    /// The records of the final results, in the order they are streamed
+    pub fn for_results(results: &AnalysisResults) -> Vec<StreamRecord> {
+        let mut records = vec![StreamRecord::ScanInfo(Box::new(results.scan_info.clone()))];
+        records.extend(results.largest_files.iter().cloned().map(StreamRecord::LargestFile));
+        records.extend(results.largest_directories.iter().cloned().map(|directory| StreamRecord::LargestDirectory(Box::new(directory))));
+        records.extend(results.duplicate_groups.iter().flatten().cloned().map(|group| StreamRecord::DuplicateGroup(Box::new(group))));
+        records.extend(results.case_collisions.iter().flatten().cloned().map(|collision| StreamRecord::CaseCollision(Box::new(collision))));
+        records.extend(results.heuristic_versions.iter().flat_map(|versions| versions.groups.iter().cloned()).map(|group| StreamRecord::VersionGroup(Box::new(group))));
+        records.extend(results.symlink_report.iter().flat_map(|links| links.broken.iter().cloned()).map(|link| StreamRecord::BrokenLink(Box::new(link))));
+        records.extend(results.scan_errors.causes.iter().cloned().map(|cause| StreamRecord::ErrorCause(Box::new(cause))));
+        records.extend(results.scan_errors.rollups.iter().cloned().map(|rollup| StreamRecord::ErrorRollup(Box::new(rollup))));
+        records.extend(results.scan_errors.errors.iter().cloned().map(|error| StreamRecord::ScanError(Box::new(error))));
+        records.extend(results.annotations.iter().cloned().map(|annotation| StreamRecord::Annotation(Box::new(annotation))));
+        records.extend(results.accepted.findings.iter().cloned().map(|finding| StreamRecord::Accepted(Box::new(finding))));
+        records.extend(results.custom_metrics.iter().cloned().map(|metric| StreamRecord::CustomMetric(Box::new(metric))));
+        records.push(StreamRecord::Statistics(Box::new(results.statistics.clone())));
+        records
+    }
+
+    /// The kind named in `"record"`
+    pub fn kind(&self) -> &'static str {
+        match self {
+            StreamRecord::ScanProgress(_) => "scan_progress",
+            StreamRecord::HashProgress(_) => "hash_progress",
+            StreamRecord::ScanInfo(_) => "scan_info",
+            StreamRecord::LargestFile(_) => "largest_file",
+            StreamRecord::LargestDirectory(_) => "largest_directory",
+            StreamRecord::DuplicateGroup(_) => "duplicate_group",
+            StreamRecord::CaseCollision(_) => "case_collision",
+            StreamRecord::VersionGroup(_) => "version_group",
+            StreamRecord::BrokenLink(_) => "broken_link",
+            StreamRecord::ErrorCause(_) => "error_cause",
+            StreamRecord::ErrorRollup(_) => "error_rollup",
+            StreamRecord::ScanError(_) => "scan_error",
+            StreamRecord::Annotation(_) => "annotation",
+            StreamRecord::Accepted(_) => "accepted",
+            StreamRecord::CustomMetric(_) => "custom_metric",
+            StreamRecord::Statistics(_) => "statistics",
+            StreamRecord::End { .. } => "end",
+        }
+    }
+}
+
+/// Interim totals of the scan, as in an interim report
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ScanProgress {
+    pub elapsed_ms: u64,
+    pub files: u64,
+    pub bytes: u64,
+    pub errors: u64,
+    /// Largest directories so far among the first levels, largest first
+    pub largest_directories: Vec<ProgressEntry>,
+}
+
+/// A path with its size so far
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ProgressEntry {
+    pub path: PathBuf,
+    pub size: u64,
+}
+
+impl From<&InterimSnapshot> for ScanProgress {
+    fn from(snapshot: &InterimSnapshot) -> Self {
+        Self {
+            elapsed_ms: snapshot.elapsed.as_millis() as u64,
+            files: snapshot.files,
+            bytes: snapshot.bytes,
+            errors: snapshot.errors,
+            largest_directories: snapshot
+                .directories
+                .iter()
+                .map(|(path, size)| ProgressEntry { path: path.clone(), size: *size })
+                .collect(),
+        }
+    }
+}
+
+/// Files hashed so far out of the duplicate candidates
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct HashProgress {
+    pub hashed: u64,
+    pub candidates: u64,
+}
+
+#[derive(Debug)]
+enum Message {
+    Record(StreamRecord),
+    Close { complete: bool },
+}
+
+/// Hands records to the stream's writer, from any thread
+#[derive(Debug, Clone)]
+pub struct RecordSender(SyncSender<Message>);
+
+impl RecordSender {
+    /// Queue `record`, waiting while the queue is full; fails once the stream is closed
+    pub fn send(&self, record: StreamRecord) -> Result<()> {
+        self.0.send(Message::Record(record)).map_err(|_| anyhow!("The record stream is closed"))
+    }
+}
+
+/// Counts hashed files and sends a record each time another percent of them is done
+///
+/// The count and the send happen under one lock, so the stream sees the count rise.
+pub(crate) struct HashTicker<'a> {
+    sender: Option<&'a RecordSender>,
+    hashed: Mutex<u64>,
+    candidates: u64,
+}
+
+impl<'a> HashTicker<'a> {
+    pub fn new(sender: Option<&'a RecordSender>, candidates: u64) -> Self {
+        Self { sender, hashed: Mutex::new(0), candidates }
+    }
+
+    pub fn tick(&self) {
+        let Some(sender) = self.sender else { return };
+        let mut hashed = self.hashed.lock().unwrap_or_else(|e| e.into_inner());
+        *hashed += 1;
+        let percent = |count: u64| count * 100 / self.candidates.max(1);
+        if percent(*hashed) != percent(*hashed - 1) {
+            let _ = sender.send(StreamRecord::HashProgress(HashProgress { hashed: *hashed, candidates: self.candidates }));
+        }
+    }
+}
+
+/// The output of a stream with the thread that writes it
+///
+/// Dropping an unfinished sink closes the stream as incomplete.
+pub struct RecordSink {
+    sender: RecordSender,
+    writer: Option<JoinHandle<io::Result<u64>>>,
+    path: Option<PathBuf>,
+}
+
+impl RecordSink {
+    /// Stream to `path`, or to stdout for `-`; a file is created through `writes`
+    pub fn create(path: &Path, writes: &WriteGuard) -> Result<Self> {
+        if is_stdout(path) {
+            return Ok(Self::spawn(Box::new(io::stdout()), None, None, QUEUE_CAPACITY));
+        }
+        let file = writes.create(path)?;
+        let sync = file.try_clone().with_context(|| format!("Failed to open {}", path.display()))?;
+        Ok(Self::spawn(Box::new(file), Some(sync), Some(path.to_path_buf()), QUEUE_CAPACITY))
+    }
+
+    /// Stream to `writer`, queueing at most `capacity` records
+    pub fn to_writer(writer: impl Write + Send + 'static, capacity: usize) -> Self {
+        Self::spawn(Box::new(writer), None, None, capacity)
+    }
+
+    fn spawn(output: Box<dyn Write + Send>, sync: Option<File>, path: Option<PathBuf>, capacity: usize) -> Self {
+        let (sender, receiver) = mpsc::sync_channel(capacity);
+        let writer = std::thread::spawn(move || write_records(receiver, BufWriter::new(output), sync));
+        Self { sender: RecordSender(sender), writer: Some(writer), path }
+    }
+
+    /// A sender for another producer
+    pub fn sender(&self) -> RecordSender {
+        self.sender.clone()
+    }
+
+    /// The file being written, `None` for stdout and other writers
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Write the `end` record after everything queued, then flush and sync the output
+    ///
+    /// Returns the number of lines written. Records sent afterwards are refused.
+    pub fn finish(mut self) -> Result<u64> {
+        self.close(true)
+    }
+
+    fn close(&mut self, complete: bool) -> Result<u64> {
+        let Some(writer) = self.writer.take() else { return Ok(0) };
+        // A writer that failed has dropped the queue; its error is the one to report
+        let _ = self.sender.0.send(Message::Close { complete });
+        match writer.join() {
+            Ok(written) => written.context("Failed to write the record stream"),
+            Err(_) => bail!("The record stream's writer panicked"),
+        }
+    }
+}
+
+impl Drop for RecordSink {
+    fn drop(&mut self) {
+        let _ = self.close(false);
+    }
+}
+
+/// The writer thread: one line per record in arrival order, until the stream is closed
+fn write_records(receiver: Receiver<Message>, mut output: BufWriter<Box<dyn Write + Send>>, sync: Option<File>) -> io::Result<u64> {
+    let mut lines = 0;
+    let mut tally = LineTally::default();
+    let mut write_all = || -> io::Result<()> {
+        loop {
+            let message = match receiver.try_recv() {
+                Ok(message) => message,
+                // Caught up: let the consumer see what is written before waiting for more
+                Err(TryRecvError::Empty) => {
+                    output.flush()?;
+                    match receiver.recv() {
+                        Ok(message) => message,
+                        Err(_) => return Ok(()),
+                    }
+                }
+                Err(TryRecvError::Disconnected) => return Ok(()),
+            };
+            let (record, last) = match message {
+                Message::Record(record) => (record, false),
+                Message::Close { complete } => (StreamRecord::End { complete, integrity: tally.integrity() }, true),
+            };
+            // Serialized whole before writing, so a failure cannot leave half a line
+            let mut line = serde_json::to_vec(&record)?;
+            line.push(b'\n');
+            output.write_all(&line)?;
+            tally.add(record.kind(), &line);
+            lines += 1;
+            if last {
+                return Ok(());
+            }
+        }
+    };
+    let written = write_all();
+    // Producers waiting on a full queue fail instead of waiting forever
+    drop(receiver);
+    let flushed = output.flush().and_then(|()| sync.map_or(Ok(()), |file| file.sync_all()));
+    written.and(flushed).map(|()| lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sha2::Digest;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    fn read_lines(path: &Path) -> Vec<serde_json::Value> {
+        std::fs::read_to_string(path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap_or_else(|error| panic!("not JSON ({}): {}", error, line)))
+            .collect()
+    }
+
+    #[test]
+    fn test_many_producers_write_whole_lines_in_their_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("stream.ndjson");
+        let sink = RecordSink::create(&path, &WriteGuard::unrestricted()).unwrap();
+
+        const THREADS: u64 = 16;
+        const RECORDS: u64 = 500;
+        let producers: Vec<_> = (0..THREADS)
+            .map(|thread| {
+                let sender = sink.sender();
+                std::thread::spawn(move || {
+                    for sequence in 0..RECORDS {
+                        // Long enough that unsynchronized writes would tear
+                        let path = PathBuf::from(format!("/{}", "x".repeat(4_096 + sequence as usize)));
+                        sender.send(StreamRecord::ScanProgress(ScanProgress {
+                            elapsed_ms: sequence,
+                            files: thread,
+                            bytes: 0,
+                            errors: 0,
+                            largest_directories: vec![ProgressEntry { path, size: sequence }],
+                        })).unwrap();
+                    }
+                })
+            })
+            .collect();
+        producers.into_iter().for_each(|producer| producer.join().unwrap());
+        assert_eq!(sink.finish().unwrap(), THREADS * RECORDS + 1);
+
+        let lines = read_lines(&path);
+        assert_eq!(lines.len() as u64, THREADS * RECORDS + 1);
+        let mut next = vec![0; THREADS as usize];
+        for line in &lines[..lines.len() - 1] {
+            assert_eq!(line["record"], "scan_progress");
+            let thread = line["files"].as_u64().unwrap() as usize;
+            assert_eq!(line["elapsed_ms"].as_u64().unwrap(), next[thread]);
+            next[thread] += 1;
+        }
+        let end = lines.last().unwrap();
+        assert_eq!((&end["record"], &end["complete"]), (&serde_json::json!("end"), &serde_json::json!(true)));
+        assert_eq!(end["records"], serde_json::json!({"scan_progress": THREADS * RECORDS}));
+        let contents = std::fs::read(&path).unwrap();
+        let payload_bytes = end["payload_bytes"].as_u64().unwrap() as usize;
+        assert_eq!(end["sha256"], format!("{:x}", sha2::Sha256::digest(&contents[..payload_bytes])));
+    }
+
+    /// A writer that blocks until the test lets it go
+    struct Gated(Arc<Mutex<()>>);
+
+    impl Write for Gated {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            drop(self.0.lock().unwrap());
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_a_slow_consumer_blocks_producers() {
+        let gate = Arc::new(Mutex::new(()));
+        let closed = gate.lock().unwrap();
+        let sink = RecordSink::to_writer(Gated(Arc::clone(&gate)), 4);
+
+        let sent = Arc::new(AtomicUsize::new(0));
+        let producer = {
+            let (sender, sent) = (sink.sender(), Arc::clone(&sent));
+            std::thread::spawn(move || {
+                for sequence in 0..100 {
+                    // Larger than the output buffer, so every record reaches the stalled writer
+                    let path = PathBuf::from("x".repeat(16 * 1024));
+                    let largest_directories = vec![ProgressEntry { path, size: sequence }];
+                    let progress = ScanProgress { elapsed_ms: sequence, files: 0, bytes: 0, errors: 0, largest_directories };
+                    sender.send(StreamRecord::ScanProgress(progress)).unwrap();
+                    sent.fetch_add(1, Ordering::SeqCst);
+                }
+            })
+        };
+        std::thread::sleep(Duration::from_millis(200));
+        // The stalled writer holds 1, the queue 4, and the producer waits on the next
+        assert_eq!(sent.load(Ordering::SeqCst), 5);
+
+        drop(closed);
+        producer.join().unwrap();
+        assert_eq!(sink.finish().unwrap(), 101);
+    }
+
+    #[test]
+    fn test_dropping_the_sink_closes_the_stream_as_incomplete() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("stream.ndjson");
+        let sink = RecordSink::create(&path, &WriteGuard::unrestricted()).unwrap();
+        let sender = sink.sender();
+        sender.send(StreamRecord::HashProgress(HashProgress { hashed: 1, candidates: 2 })).unwrap();
+        drop(sink);
+
+        let lines = read_lines(&path);
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0], serde_json::json!({"record": "hash_progress", "hashed": 1, "candidates": 2}));
+        assert_eq!(lines[1]["record"], "end");
+        assert_eq!(lines[1]["complete"], false);
+        assert_eq!(lines[1]["records"], serde_json::json!({"hash_progress": 1}));
+        assert!(sender.send(StreamRecord::End { complete: true, integrity: Integrity::default() }).is_err());
+    }
+
+    #[test]
+    fn test_hash_ticker_sends_each_percent_once() {
+        let (sender, receiver) = mpsc::sync_channel(1_000);
+        let sender = RecordSender(sender);
+        let ticker = HashTicker::new(Some(&sender), 250);
+        (0..250).for_each(|_| ticker.tick());
+        let counts: Vec<u64> = receiver
+            .try_iter()
+            .map(|message| match message {
+                Message::Record(StreamRecord::HashProgress(progress)) => progress.hashed,
+                other => panic!("unexpected {:?}", other),
+            })
+            .collect();
+        assert_eq!(counts.len(), 100);
+        assert!(counts.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(counts.last(), Some(&250));
+    }
+}
@@ -0,0 +1,51 @@
+//! Report sections contributed by library users
+//!
+//! A [`SectionProvider`] registered with
+//! [`DirectoryAnalyzer::with_section_provider`](crate::analyzer::DirectoryAnalyzer::with_section_provider)
+//! sees the finished results and contributes an [`ExtensionSection`]. The
+//! sections are kept in the results under `extensions`, so JSON and CSV exports
+//! carry them, and every report format renders them after the built-in sections.
+
+use crate::analyzer::AnalysisResults;
+use serde::{Deserialize, Serialize};
+
+/// Builds a custom report section from the results of a run
+pub trait SectionProvider: Send + Sync {
+    /// The section for these results, or `None` to leave it out of this run
+    fn section(&self, results: &AnalysisResults) -> Option<ExtensionSection>;
+}
+
+/// A titled list of labelled values, each with an optional size
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExtensionSection {
+    pub title: String,
+    pub rows: Vec<ExtensionRow>,
+}
+
+/// One line of an extension section
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExtensionRow {
+    pub label: String,
+    pub value: String,
+    /// Bytes the row accounts for, rendered in the report's size format
+    #[serde(default)]
+    pub size: Option<u64>,
+}
+
+impl ExtensionSection {
+    pub fn new(title: impl Into<String>) -> Self {
+        Self { title: title.into(), rows: Vec::new() }
+    }
+
+    /// Add a row with a value only
+    pub fn row(mut self, label: impl Into<String>, value: impl Into<String>) -> Self {
+        self.rows.push(ExtensionRow { label: label.into(), value: value.into(), size: None });
+        self
+    }
+
+    /// Add a row with a value and the bytes it accounts for
+    pub fn sized_row(mut self, label: impl Into<String>, value: impl Into<String>, size: u64) -> Self {
+        self.rows.push(ExtensionRow { label: label.into(), value: value.into(), size: Some(size) });
+        self
+    }
+}
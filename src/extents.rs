@@ -0,0 +1,380 @@
+//! Copy-on-write aware size accounting
+//!
+//! On Btrfs, XFS, and bcachefs, reflinked copies (`cp --reflink`) share their
+//! extents, so apparent sizes overstate how much storage a tree really uses.
+//! The scanner maps the extents of larger files on those filesystems with
+//! FIEMAP to tell shared bytes from exclusive ones. Other filesystems and
+//! platforms are skipped after one `statfs` per device.
+
+use std::collections::{HashMap, HashSet};
+use std::io;
+use std::path::Path;
+use std::sync::Mutex;
+
+/// Files smaller than this are usually inline or single-extent and rarely reflinked
+pub const MIN_PROBE_SIZE: u64 = 64 * 1024;
+
+/// One mapped extent of a file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Extent {
+    pub logical: u64,
+    pub physical: u64,
+    pub length: u64,
+    /// The extent is also referenced by another file or snapshot
+    pub shared: bool,
+}
+
+/// Source of extent maps; the FIEMAP ioctl outside of tests
+pub trait ExtentMapper: Send + Sync {
+    /// Whether the filesystem holding `path` can share extents between files
+    fn supports_sharing(&self, path: &Path) -> bool;
+
+    /// All extents of the file at `path`
+    fn extents(&self, path: &Path) -> io::Result<Vec<Extent>>;
+}
+
+/// Shared data of one probed file
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FileExtents {
+    /// Bytes that are also referenced elsewhere (at most the file size)
+    pub shared_bytes: u64,
+    /// `(physical, length)` of each shared extent
+    pub shared_ranges: Vec<(u64, u64)>,
+}
+
+/// Probes files for shared extents, remembering which devices support sharing
+pub struct ExtentProbe {
+    mapper: Box<dyn ExtentMapper>,
+    min_size: u64,
+    devices: Mutex<HashMap<u64, bool>>,
+}
+
+impl Default for ExtentProbe {
+    fn default() -> Self {
+        Self::with_mapper(Box::new(FiemapMapper))
+    }
+}
+
+impl ExtentProbe {
+    /// Probe through a custom mapper (e.g. a mock in tests)
+    pub fn with_mapper(mapper: Box<dyn ExtentMapper>) -> Self {
+        Self { mapper, min_size: MIN_PROBE_SIZE, devices: Mutex::new(HashMap::new()) }
+    }
+
+    /// Shared extents of a file on `device`, or `None` when it was not probed
+    pub fn probe(&self, path: &Path, size: u64, device: u64) -> Option<FileExtents> {
+        if size < self.min_size || size == 0 {
+            return None;
+        }
+
+        let supported = *self
+            .devices
+            .lock()
+            .unwrap()
+            .entry(device)
+            .or_insert_with(|| self.mapper.supports_sharing(path));
+        if !supported {
+            return None;
+        }
+
+        let extents = self.mapper.extents(path).ok()?;
+        let shared_ranges: Vec<_> = extents
+            .iter()
+            .filter(|extent| extent.shared)
+            .map(|extent| (extent.physical, extent.length))
+            .collect();
+        let shared_bytes = shared_ranges.iter().map(|(_, length)| length).sum::<u64>().min(size);
+        Some(FileExtents { shared_bytes, shared_ranges })
+    }
+}
+
+/// Accumulates the physically unique size of a set of files
+///
+/// Exclusive bytes count once per file; each shared extent counts once no
+/// matter how many files reference it.
+#[derive(Debug, Default)]
+pub struct PhysicalUsage {
+    seen: HashSet<(u64, u64)>,
+    total: u64,
+    probed: bool,
+}
+
+impl PhysicalUsage {
+    pub fn add(&mut self, size: u64, extents: Option<&FileExtents>) {
+        let Some(extents) = extents else {
+            self.total += size;
+            return;
+        };
+
+        self.probed = true;
+        let new_shared: u64 = extents
+            .shared_ranges
+            .iter()
+            .filter(|range| self.seen.insert(**range))
+            .map(|(_, length)| length)
+            .sum();
+        let exclusive = size.saturating_sub(extents.shared_bytes);
+        self.total += (exclusive + new_shared).min(size);
+    }
+
+    /// Take in the files of another part of the same scan
+    ///
+    /// Shared extents both parts met count once.
+    pub fn absorb(&mut self, other: PhysicalUsage) {
+        let counted_twice: u64 = other.seen.iter().filter(|range| self.seen.contains(*range)).map(|(_, length)| length).sum();
+        self.total += other.total.saturating_sub(counted_twice);
+        self.seen.extend(other.seen);
+        self.probed |= other.probed;
+    }
+
+    /// Physically unique bytes, if any file could be probed
+    pub fn total(&self) -> Option<u64> {
+        self.probed.then_some(self.total)
+    }
+}
+
+/// Extent maps from the FIEMAP ioctl
+pub struct FiemapMapper;
+
+impl ExtentMapper for FiemapMapper {
+    fn supports_sharing(&self, path: &Path) -> bool {
+        imp::supports_sharing(path)
+    }
+
+    fn extents(&self, path: &Path) -> io::Result<Vec<Extent>> {
+        imp::extents(path)
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod imp {
+    use super::Extent;
+    use std::ffi::CString;
+    use std::io;
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::io::AsRawFd;
+    use std::path::Path;
+
+    /// `_IOWR('f', 11, struct fiemap)`
+    const FS_IOC_FIEMAP: u32 = 0xC020_660B;
+    const FIEMAP_FLAG_SYNC: u32 = 0x1;
+    const FIEMAP_EXTENT_LAST: u32 = 0x1;
+    const FIEMAP_EXTENT_SHARED: u32 = 0x2000;
+
+    const BTRFS_SUPER_MAGIC: u32 = 0x9123_683E;
+    const XFS_SUPER_MAGIC: u32 = 0x5846_5342;
+    const BCACHEFS_SUPER_MAGIC: u32 = 0xCA45_1A4E;
+
+    /// Extents fetched per ioctl call
+    const BATCH: usize = 128;
+
+    #[repr(C)]
+    #[derive(Clone, Copy, Default)]
+    struct FiemapExtent {
+        fe_logical: u64,
+        fe_physical: u64,
+        fe_length: u64,
+        fe_reserved64: [u64; 2],
+        fe_flags: u32,
+        fe_reserved: [u32; 3],
+    }
+
+    #[repr(C)]
+    struct Fiemap {
+        fm_start: u64,
+        fm_length: u64,
+        fm_flags: u32,
+        fm_mapped_extents: u32,
+        fm_extent_count: u32,
+        fm_reserved: u32,
+        fm_extents: [FiemapExtent; BATCH],
+    }
+
+    pub fn supports_sharing(path: &Path) -> bool {
+        let Ok(path) = CString::new(path.as_os_str().as_bytes()) else {
+            return false;
+        };
+        // SAFETY: statfs only writes into the zeroed struct we pass
+        let magic = unsafe {
+            let mut stats: libc::statfs = std::mem::zeroed();
+            if libc::statfs(path.as_ptr(), &mut stats) != 0 {
+                return false;
+            }
+            stats.f_type as u32
+        };
+        matches!(magic, BTRFS_SUPER_MAGIC | XFS_SUPER_MAGIC | BCACHEFS_SUPER_MAGIC)
+    }
+
+    pub fn extents(path: &Path) -> io::Result<Vec<Extent>> {
+        let file = std::fs::File::open(path)?;
+        let mut extents = Vec::new();
+        let mut start = 0u64;
+
+        loop {
+            let mut map = Fiemap {
+                fm_start: start,
+                fm_length: u64::MAX - start,
+                fm_flags: FIEMAP_FLAG_SYNC,
+                fm_mapped_extents: 0,
+                fm_extent_count: BATCH as u32,
+                fm_reserved: 0,
+                fm_extents: [FiemapExtent::default(); BATCH],
+            };
+            // SAFETY: `map` is a valid fiemap header followed by room for BATCH extents
+            let result = unsafe { libc::ioctl(file.as_raw_fd(), FS_IOC_FIEMAP as _, &mut map) };
+            if result != 0 {
+                return Err(io::Error::last_os_error());
+            }
+
+            let mapped = &map.fm_extents[..map.fm_mapped_extents as usize];
+            for extent in mapped {
+                extents.push(Extent {
+                    logical: extent.fe_logical,
+                    physical: extent.fe_physical,
+                    length: extent.fe_length,
+                    shared: extent.fe_flags & FIEMAP_EXTENT_SHARED != 0,
+                });
+            }
+
+            match mapped.last() {
+                Some(last) if last.fe_flags & FIEMAP_EXTENT_LAST == 0 => {
+                    start = last.fe_logical + last.fe_length;
+                }
+                _ => return Ok(extents),
+            }
+        }
+    }
+}
+
+#[cfg(not(target_os = "linux"))]
+mod imp {
+    use super::Extent;
+    use std::io;
+    use std::path::Path;
+
+    pub fn supports_sharing(_path: &Path) -> bool {
+        false
+    }
+
+    pub fn extents(_path: &Path) -> io::Result<Vec<Extent>> {
+        Err(io::Error::new(io::ErrorKind::Unsupported, "FIEMAP is Linux-only"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    /// Serves canned extent maps and counts filesystem checks
+    #[derive(Default)]
+    struct MockMapper {
+        sharing: bool,
+        files: HashMap<PathBuf, Vec<Extent>>,
+        checks: Arc<AtomicUsize>,
+    }
+
+    impl ExtentMapper for MockMapper {
+        fn supports_sharing(&self, _path: &Path) -> bool {
+            self.checks.fetch_add(1, Ordering::Relaxed);
+            self.sharing
+        }
+
+        fn extents(&self, path: &Path) -> io::Result<Vec<Extent>> {
+            self.files.get(path).cloned().ok_or_else(|| io::ErrorKind::NotFound.into())
+        }
+    }
+
+    fn extent(physical: u64, length: u64, shared: bool) -> Extent {
+        Extent { logical: 0, physical, length, shared }
+    }
+
+    #[test]
+    fn test_probe_splits_shared_and_exclusive() {
+        let mapper = MockMapper {
+            sharing: true,
+            files: HashMap::from([(
+                PathBuf::from("/vol/a"),
+                vec![extent(0, 100_000, true), extent(500_000, 28_000, false)],
+            )]),
+            ..Default::default()
+        };
+        let probe = ExtentProbe::with_mapper(Box::new(mapper));
+
+        let extents = probe.probe(Path::new("/vol/a"), 128_000, 1).unwrap();
+        assert_eq!(extents.shared_bytes, 100_000);
+        assert_eq!(extents.shared_ranges, [(0, 100_000)]);
+        // Small files and mapping failures are not probed
+        assert_eq!(probe.probe(Path::new("/vol/a"), 1_000, 1), None);
+        assert_eq!(probe.probe(Path::new("/vol/missing"), 128_000, 1), None);
+    }
+
+    #[test]
+    fn test_unsupported_device_is_checked_once() {
+        let checks = Arc::new(AtomicUsize::new(0));
+        let mapper = MockMapper { checks: checks.clone(), ..Default::default() };
+        let probe = ExtentProbe::with_mapper(Box::new(mapper));
+
+        for i in 0..10 {
+            assert_eq!(probe.probe(Path::new(&format!("/ext4/{}", i)), 1 << 20, 7), None);
+        }
+        assert_eq!(checks.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn test_shared_extents_count_once_physically() {
+        let clone = FileExtents { shared_bytes: 100, shared_ranges: vec![(4096, 100)] };
+        let mut usage = PhysicalUsage::default();
+        assert_eq!(usage.total(), None);
+
+        usage.add(100, Some(&clone));
+        usage.add(100, Some(&clone));
+        usage.add(100, Some(&FileExtents::default()));
+        usage.add(50, None);
+        assert_eq!(usage.total(), Some(250));
+    }
+
+    #[test]
+    fn test_parts_of_a_scan_share_their_extents() {
+        let clone = FileExtents { shared_bytes: 100, shared_ranges: vec![(4096, 100)] };
+        let (mut first, mut second) = (PhysicalUsage::default(), PhysicalUsage::default());
+        first.add(100, Some(&clone));
+        second.add(100, Some(&clone));
+        second.add(50, None);
+
+        first.absorb(second);
+        assert_eq!(first.total(), Some(150));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_reflinked_copy_on_capable_filesystem() {
+        use std::os::unix::io::AsRawFd;
+        /// `_IOW(0x94, 9, int)`
+        const FICLONE: u32 = 0x4004_9409;
+
+        let dir = tempfile::tempdir().unwrap();
+        if !FiemapMapper.supports_sharing(dir.path()) {
+            eprintln!("skipping: temp dir is not on a reflink-capable filesystem");
+            return;
+        }
+        let original = dir.path().join("original");
+        let copy = dir.path().join("copy");
+        std::fs::write(&original, vec![7u8; 256 * 1024]).unwrap();
+        let source = std::fs::File::open(&original).unwrap();
+        let target = std::fs::File::create(&copy).unwrap();
+        // SAFETY: both descriptors are open for the duration of the call
+        if unsafe { libc::ioctl(target.as_raw_fd(), FICLONE as _, source.as_raw_fd()) } != 0 {
+            eprintln!("skipping: reflinks are disabled on this filesystem");
+            return;
+        }
+        drop(target);
+
+        let probe = ExtentProbe::default();
+        let extents = probe.probe(&copy, 256 * 1024, 0).unwrap();
+        assert_eq!(extents.shared_bytes, 256 * 1024);
+    }
+}
@@ -0,0 +1,544 @@
+//! Content hash cache shared between runs and between concurrent processes
+//!
+//! A cached hash is keyed by the file's device, inode, size, and modification
+//! time; a file that keeps all four is taken to be unchanged. The cache is a
+//! directory of shards, and every process appends to segment files of its own
+//! in the shards it writes, so concurrent writers never wait on each other or
+//! interleave their records. Readers merge every segment of a shard, and the
+//! record written last wins.
+//!
+//! A writer holds an advisory lock on each of its segments while it is open.
+//! Compaction folds the segments nobody holds into one, under a per-shard lock,
+//! so it never loses records a running process is still appending. A record
+//! torn by a crash is skipped on load, which only costs a cache miss.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{File, Metadata, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+use crate::config;
+use crate::writes::{self, FileLock, WriteGuard};
+
+/// Shards the cache is split into, each compacted on its own
+const SHARDS: usize = 16;
+
+/// Segments a shard may collect before a finishing run compacts it
+const COMPACT_SEGMENTS: usize = 8;
+
+const SEGMENT_EXTENSION: &str = "seg";
+
+/// Listings of a shard tried while compaction keeps removing the segments read
+const LOAD_ATTEMPTS: usize = 3;
+
+/// Caches opened by this process, so two of them never pick the same segment names
+static INSTANCES: AtomicU64 = AtomicU64::new(0);
+
+/// Identity of a file's contents as far as the cache can tell without reading them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CacheKey {
+    pub dev: u64,
+    pub ino: u64,
+    pub size: u64,
+    /// Modification time in nanoseconds since the epoch
+    pub mtime: i64,
+}
+
+impl CacheKey {
+    /// Key of a file from its metadata; `None` where inodes are not available
+    pub fn of(metadata: &Metadata) -> Option<Self> {
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            Some(Self {
+                dev: metadata.dev(),
+                ino: metadata.ino(),
+                size: metadata.size(),
+                mtime: metadata.mtime().saturating_mul(1_000_000_000).saturating_add(metadata.mtime_nsec()),
+            })
+        }
+        #[cfg(not(unix))]
+        {
+            let _ = metadata;
+            None
+        }
+    }
+
+    fn shard(&self) -> usize {
+        (self.dev ^ self.ino) as usize % SHARDS
+    }
+}
+
+/// One line of a segment file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheRecord {
+    dev: u64,
+    ino: u64,
+    size: u64,
+    mtime: i64,
+    algorithm: String,
+    hash: String,
+    /// When the record was written, in nanoseconds since the epoch; the latest one wins
+    written: i64,
+}
+
+impl CacheRecord {
+    /// Records replace each other per file and algorithm: a file rewritten in place supersedes its old hash
+    fn slot(&self) -> (u64, u64, String) {
+        (self.dev, self.ino, self.algorithm.clone())
+    }
+
+    fn matches(&self, key: &CacheKey) -> bool {
+        self.size == key.size && self.mtime == key.mtime
+    }
+}
+
+/// Lookups, hits, and stored hashes of one run
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct CacheUsage {
+    pub lookups: u64,
+    pub hits: u64,
+    pub stored: u64,
+}
+
+impl CacheUsage {
+    /// Share of lookups answered from the cache, between 0 and 1
+    pub fn hit_rate(&self) -> f64 {
+        if self.lookups == 0 {
+            0.0
+        } else {
+            self.hits as f64 / self.lookups as f64
+        }
+    }
+}
+
+/// A run's use of the cache, as recorded for `diranalyzer cache stats`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheRun {
+    pub timestamp: DateTime<Utc>,
+    pub root: PathBuf,
+    #[serde(flatten)]
+    pub usage: CacheUsage,
+}
+
+/// Size of the cache on disk
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CacheSummary {
+    pub entries: usize,
+    pub segments: usize,
+    pub bytes: u64,
+}
+
+#[derive(Default)]
+struct Shard {
+    /// Merged records, loaded on first use
+    entries: Option<HashMap<(u64, u64, String), CacheRecord>>,
+    /// This process's segment in the shard, locked while open
+    segment: Option<File>,
+}
+
+/// Hash cache backed by a directory that several processes may use at once
+pub struct HashCache {
+    dir: PathBuf,
+    writes: WriteGuard,
+    writable: bool,
+    instance: String,
+    segments_opened: AtomicU64,
+    shards: Vec<Mutex<Shard>>,
+    lookups: AtomicU64,
+    hits: AtomicU64,
+    stored: AtomicU64,
+}
+
+impl HashCache {
+    /// Default location, inside the cache directory
+    pub fn default_dir() -> Option<PathBuf> {
+        Some(config::cache_dir()?.join("hashes"))
+    }
+
+    /// Open the cache in `dir`; it is only read when `writes` forbids writing there
+    pub fn open(dir: PathBuf, writes: WriteGuard) -> Self {
+        let writable = writes.permits(&dir);
+        let started = Utc::now().timestamp_nanos_opt().unwrap_or_default();
+        Self {
+            instance: format!("{}-{:x}-{}", std::process::id(), started, INSTANCES.fetch_add(1, Ordering::Relaxed)),
+            dir,
+            writes,
+            writable,
+            segments_opened: AtomicU64::new(0),
+            shards: (0..SHARDS).map(|_| Mutex::default()).collect(),
+            lookups: AtomicU64::new(0),
+            hits: AtomicU64::new(0),
+            stored: AtomicU64::new(0),
+        }
+    }
+
+    /// Directory backing the cache
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Cached hash of the file with `key`, computed with `algorithm`
+    pub fn get(&self, key: &CacheKey, algorithm: &str) -> Option<String> {
+        self.lookups.fetch_add(1, Ordering::Relaxed);
+        let mut shard = self.shards[key.shard()].lock().unwrap_or_else(|e| e.into_inner());
+        let entries = shard.entries.get_or_insert_with(|| load_shard(&shard_dir(&self.dir, key.shard())));
+        let record = entries.get(&(key.dev, key.ino, algorithm.to_string())).filter(|record| record.matches(key))?;
+        self.hits.fetch_add(1, Ordering::Relaxed);
+        Some(record.hash.clone())
+    }
+
+    /// Remember the hash of the file with `key`
+    ///
+    /// The record is appended to this process's segment at once, so other
+    /// processes opening the cache later see it even if this run is killed.
+    pub fn put(&self, key: &CacheKey, algorithm: &str, hash: &str) -> Result<()> {
+        let record = CacheRecord {
+            dev: key.dev,
+            ino: key.ino,
+            size: key.size,
+            mtime: key.mtime,
+            algorithm: algorithm.to_string(),
+            hash: hash.to_string(),
+            written: Utc::now().timestamp_nanos_opt().unwrap_or_default(),
+        };
+        let index = key.shard();
+        let mut shard = self.shards[index].lock().unwrap_or_else(|e| e.into_inner());
+        shard.entries.get_or_insert_with(|| load_shard(&shard_dir(&self.dir, index))).insert(record.slot(), record.clone());
+        self.stored.fetch_add(1, Ordering::Relaxed);
+        if !self.writable {
+            return Ok(());
+        }
+
+        if shard.segment.is_none() {
+            shard.segment = Some(self.open_segment(index)?);
+        }
+        let mut line = serde_json::to_string(&record)?;
+        line.push('\n');
+        // One write per record: a crash tears at most the last line
+        let mut segment = shard.segment.as_ref().expect("segment was just opened");
+        segment.write_all(line.as_bytes()).context("Failed to append to the hash cache")?;
+        Ok(())
+    }
+
+    /// Lookups, hits, and stored hashes so far
+    pub fn usage(&self) -> CacheUsage {
+        CacheUsage {
+            lookups: self.lookups.load(Ordering::Relaxed),
+            hits: self.hits.load(Ordering::Relaxed),
+            stored: self.stored.load(Ordering::Relaxed),
+        }
+    }
+
+    /// Close this run's segments, record its usage, and compact crowded shards
+    pub fn finish(&self, root: &Path) -> Result<CacheUsage> {
+        let usage = self.usage();
+        if !self.writable {
+            return Ok(usage);
+        }
+        for shard in &self.shards {
+            shard.lock().unwrap_or_else(|e| e.into_inner()).segment = None;
+        }
+
+        let run = CacheRun {
+            timestamp: Utc::now(),
+            root: std::path::absolute(root).unwrap_or_else(|_| root.to_path_buf()),
+            usage,
+        };
+        self.writes.create_dir_all(&self.dir)?;
+        let _lock = FileLock::acquire(&self.dir.join("runs.lock"), &self.writes)?;
+        let mut runs = self.writes.open(&runs_path(&self.dir), OpenOptions::new().create(true).append(true))?;
+        writeln!(runs, "{}", serde_json::to_string(&run)?)?;
+
+        for index in 0..SHARDS {
+            self.compact_shard(index, COMPACT_SEGMENTS)?;
+        }
+        Ok(usage)
+    }
+
+    /// Fold every segment no process is writing into one per shard
+    ///
+    /// Returns the number of segments folded away.
+    pub fn compact(&self) -> Result<usize> {
+        let mut folded = 0;
+        for index in 0..SHARDS {
+            folded += self.compact_shard(index, 2)?;
+        }
+        Ok(folded)
+    }
+
+    /// Compact one shard when at least `min_segments` of its segments are free
+    fn compact_shard(&self, index: usize, min_segments: usize) -> Result<usize> {
+        let dir = shard_dir(&self.dir, index);
+        if !self.writable || !dir.is_dir() {
+            return Ok(0);
+        }
+        // Another process compacting the shard is as good as doing it here
+        let Some(_lock) = FileLock::try_acquire(&dir.join("compact.lock"), &self.writes)? else {
+            return Ok(0);
+        };
+
+        let mut free = Vec::new();
+        for path in segment_paths(&dir) {
+            let Ok(file) = File::open(&path) else { continue };
+            // A segment still locked by its writer may gain records any moment
+            if writes::lock_exclusive(&file, false)? {
+                free.push((path, file));
+            }
+        }
+        if free.len() < min_segments {
+            return Ok(0);
+        }
+
+        let mut merged = HashMap::new();
+        for (_, file) in &free {
+            merge_records(&mut merged, BufReader::new(file));
+        }
+        let name = self.segment_name();
+        let temp_path = dir.join(format!("{}.tmp", name));
+        let mut temp = self.writes.create(&temp_path)?;
+        for record in merged.values() {
+            writeln!(temp, "{}", serde_json::to_string(record)?)?;
+        }
+        temp.sync_all()?;
+        // The merged segment is in place before its sources go, so a reader never misses a record
+        self.writes.rename(&temp_path, &dir.join(format!("{}.{}", name, SEGMENT_EXTENSION)))?;
+        for (path, _) in &free {
+            self.writes.remove_file(path)?;
+        }
+        Ok(free.len())
+    }
+
+    /// Create this process's next segment in shard `index`, locked before anyone can see it
+    fn open_segment(&self, index: usize) -> Result<File> {
+        let dir = shard_dir(&self.dir, index);
+        self.writes.create_dir_all(&dir)
+            .with_context(|| format!("Failed to create hash cache directory: {}", dir.display()))?;
+        let name = self.segment_name();
+        let temp_path = dir.join(format!("{}.tmp", name));
+        let file = self.writes.open(&temp_path, OpenOptions::new().create_new(true).append(true))?;
+        writes::lock_exclusive(&file, true)?;
+        // Only the locked file takes a segment name, so compaction never folds it away half-written
+        self.writes.rename(&temp_path, &dir.join(format!("{}.{}", name, SEGMENT_EXTENSION)))?;
+        Ok(file)
+    }
+
+    fn segment_name(&self) -> String {
+        format!("{}-{}", self.instance, self.segments_opened.fetch_add(1, Ordering::Relaxed))
+    }
+}
+
+/// Runs recorded in the cache at `dir`, oldest first
+pub fn load_runs(dir: &Path) -> Result<Vec<CacheRun>> {
+    let path = runs_path(dir);
+    let file = match File::open(&path) {
+        Ok(file) => file,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(error) => return Err(error).with_context(|| format!("Failed to open {}", path.display())),
+    };
+    let mut runs: Vec<CacheRun> = BufReader::new(file)
+        .lines()
+        .map_while(|line| line.ok())
+        .filter_map(|line| serde_json::from_str(&line).ok())
+        .collect();
+    runs.sort_by_key(|run| run.timestamp);
+    Ok(runs)
+}
+
+/// Entries, segments, and bytes of the cache at `dir`
+pub fn summarize(dir: &Path) -> CacheSummary {
+    let mut summary = CacheSummary::default();
+    for index in 0..SHARDS {
+        let shard = shard_dir(dir, index);
+        for path in segment_paths(&shard) {
+            summary.segments += 1;
+            summary.bytes += std::fs::metadata(&path).map_or(0, |metadata| metadata.len());
+        }
+        summary.entries += load_shard(&shard).len();
+    }
+    summary
+}
+
+fn shard_dir(dir: &Path, index: usize) -> PathBuf {
+    dir.join(format!("{:02x}", index))
+}
+
+fn runs_path(dir: &Path) -> PathBuf {
+    dir.join("runs.ndjson")
+}
+
+fn segment_paths(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == SEGMENT_EXTENSION))
+        .collect()
+}
+
+/// Merged records of every segment in a shard
+fn load_shard(dir: &Path) -> HashMap<(u64, u64, String), CacheRecord> {
+    let mut merged = HashMap::new();
+    for _ in 0..LOAD_ATTEMPTS {
+        merged.clear();
+        let mut complete = true;
+        for path in segment_paths(dir) {
+            match File::open(&path) {
+                Ok(file) => merge_records(&mut merged, BufReader::new(file)),
+                // Compacted away since the listing: its records are in a segment the next listing shows
+                Err(error) if error.kind() == std::io::ErrorKind::NotFound => complete = false,
+                Err(_) => {}
+            }
+        }
+        if complete {
+            break;
+        }
+    }
+    merged
+}
+
+/// Add the records of one segment, keeping the latest per file
+fn merge_records(merged: &mut HashMap<(u64, u64, String), CacheRecord>, reader: impl BufRead) {
+    for line in reader.lines().map_while(|line| line.ok()) {
+        // Skip lines torn by an interrupted write
+        let Ok(record) = serde_json::from_str::<CacheRecord>(&line) else { continue };
+        match merged.get(&record.slot()) {
+            Some(existing) if existing.written > record.written => {}
+            _ => {
+                merged.insert(record.slot(), record);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    fn key(ino: u64) -> CacheKey {
+        CacheKey { dev: 1, ino, size: ino * 10, mtime: 1_700_000_000_000_000_000 }
+    }
+
+    #[test]
+    fn test_hashes_survive_reopening() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = HashCache::open(dir.path().join("hashes"), WriteGuard::unrestricted());
+        assert_eq!(cache.get(&key(1), "sha256"), None);
+        cache.put(&key(1), "sha256", "aa").unwrap();
+        cache.put(&key(2), "sha256", "bb").unwrap();
+        assert_eq!(cache.get(&key(1), "sha256").as_deref(), Some("aa"));
+        assert_eq!(cache.finish(dir.path()).unwrap(), CacheUsage { lookups: 2, hits: 1, stored: 2 });
+
+        let reopened = HashCache::open(dir.path().join("hashes"), WriteGuard::unrestricted());
+        assert_eq!(reopened.get(&key(2), "sha256").as_deref(), Some("bb"));
+        // Another algorithm, or a changed file, misses
+        assert_eq!(reopened.get(&key(2), "blake3"), None);
+        assert_eq!(reopened.get(&CacheKey { mtime: 1, ..key(2) }, "sha256"), None);
+
+        let runs = load_runs(&dir.path().join("hashes")).unwrap();
+        assert_eq!(runs.len(), 1);
+        assert_eq!(runs[0].usage.hit_rate(), 0.5);
+    }
+
+    #[test]
+    fn test_last_writer_wins() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = HashCache::open(dir.path().to_path_buf(), WriteGuard::unrestricted());
+        let second = HashCache::open(dir.path().to_path_buf(), WriteGuard::unrestricted());
+        first.put(&key(1), "sha256", "old").unwrap();
+        second.put(&CacheKey { mtime: 5, ..key(1) }, "sha256", "new").unwrap();
+
+        let reader = HashCache::open(dir.path().to_path_buf(), WriteGuard::unrestricted());
+        assert_eq!(reader.get(&CacheKey { mtime: 5, ..key(1) }, "sha256").as_deref(), Some("new"));
+        assert_eq!(reader.get(&key(1), "sha256"), None);
+    }
+
+    #[test]
+    fn test_torn_records_are_skipped() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = HashCache::open(dir.path().to_path_buf(), WriteGuard::unrestricted());
+        cache.put(&key(1), "sha256", "aa").unwrap();
+        cache.finish(dir.path()).unwrap();
+        let shard = shard_dir(dir.path(), key(1).shard());
+        std::fs::write(shard.join("torn.seg"), "{\"dev\":1,\"ino\":").unwrap();
+
+        let reopened = HashCache::open(dir.path().to_path_buf(), WriteGuard::unrestricted());
+        assert_eq!(reopened.get(&key(1), "sha256").as_deref(), Some("aa"));
+    }
+
+    #[test]
+    fn test_compaction_leaves_open_segments_alone() {
+        let dir = tempfile::tempdir().unwrap();
+        for ino in 0..4 {
+            let cache = HashCache::open(dir.path().to_path_buf(), WriteGuard::unrestricted());
+            cache.put(&key(ino * SHARDS as u64), "sha256", &format!("h{}", ino)).unwrap();
+            cache.finish(dir.path()).unwrap();
+        }
+        let running = HashCache::open(dir.path().to_path_buf(), WriteGuard::unrestricted());
+        running.put(&key(4 * SHARDS as u64), "sha256", "h4").unwrap();
+        assert_eq!(summarize(dir.path()).segments, 5);
+
+        let compactor = HashCache::open(dir.path().to_path_buf(), WriteGuard::unrestricted());
+        assert_eq!(compactor.compact().unwrap(), 4);
+        assert_eq!(summarize(dir.path()), CacheSummary { entries: 5, segments: 2, ..summarize(dir.path()) });
+
+        // The running writer keeps appending to its segment after the compaction
+        running.put(&key(5 * SHARDS as u64), "sha256", "h5").unwrap();
+        running.finish(dir.path()).unwrap();
+        assert_eq!(summarize(dir.path()).entries, 6);
+    }
+
+    #[test]
+    fn test_read_only_cache_is_not_written() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = HashCache::open(dir.path().join("hashes"), WriteGuard::read_only(dir.path()));
+        cache.put(&key(1), "sha256", "aa").unwrap();
+        assert_eq!(cache.get(&key(1), "sha256").as_deref(), Some("aa"));
+        cache.finish(dir.path()).unwrap();
+        assert!(!dir.path().join("hashes").exists());
+    }
+
+    #[test]
+    fn test_concurrent_writers_lose_no_updates() {
+        let dir = Arc::new(tempfile::tempdir().unwrap());
+        let handles: Vec<_> = (0..8u64)
+            .map(|writer| {
+                let dir = Arc::clone(&dir);
+                std::thread::spawn(move || {
+                    for round in 0..3u64 {
+                        let cache = HashCache::open(dir.path().to_path_buf(), WriteGuard::unrestricted());
+                        for ino in 0..200 {
+                            let key = key(writer * 1_000 + ino);
+                            if cache.get(&key, "sha256").is_none() || round == 2 {
+                                cache.put(&key, "sha256", &format!("{}-{}", writer, ino)).unwrap();
+                            }
+                        }
+                        cache.finish(dir.path()).unwrap();
+                        cache.compact().unwrap();
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let cache = HashCache::open(dir.path().to_path_buf(), WriteGuard::unrestricted());
+        for writer in 0..8u64 {
+            for ino in 0..200 {
+                let expected = format!("{}-{}", writer, ino);
+                assert_eq!(cache.get(&key(writer * 1_000 + ino), "sha256"), Some(expected));
+            }
+        }
+        assert_eq!(summarize(dir.path()).entries, 1_600);
+        assert_eq!(load_runs(dir.path()).unwrap().len(), 24);
+    }
+}
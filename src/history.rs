@@ -0,0 +1,641 @@
+//! Per-root scan history and trend computation
+//!
+//! Every analysis appends one summary record to a small NDJSON file kept in
+//! the cache directory, keyed by the canonical scan root. The records feed the
+//! report's trend line and the `history` subcommand.
+//!
+//! Runs that searched for duplicates also record their unique bytes and the
+//! hashes of their largest duplicate groups. Between two such runs, growth is
+//! split into unique content and additional duplication, and groups the
+//! previous run did not track are recognized as new.
+//!
+//! Next to the history file, a group ledger lists every duplicate group of
+//! the latest such run by its stable id. It carries each group's first
+//! sighting forward, and groups it lists that a new run no longer finds are
+//! reported as resolved, with the space their removal reclaimed.
+
+use crate::analyzer::DuplicateGroup;
+use crate::config;
+use crate::entropy::EntropySnapshot;
+use crate::writes::{FileLock, WriteGuard};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+/// Number of days covered by the long-range trend comparison
+pub const TREND_WINDOW_DAYS: i64 = 30;
+
+/// Duplicate groups recorded per run, largest waste first
+pub const TRACKED_GROUPS: usize = 100;
+
+/// Summary of a single analysis run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryRecord {
+    pub timestamp: DateTime<Utc>,
+    pub total_size: u64,
+    pub total_files: u64,
+    pub duplicate_waste: u64,
+    /// Per-directory high-entropy shares, recorded by `--entropy-sample` runs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub entropy: Option<Vec<EntropySnapshot>>,
+    /// Unique bytes and known groups, recorded by runs that searched for duplicates
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duplicates: Option<DuplicateSnapshot>,
+}
+
+/// What a run knew about its duplicates
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DuplicateSnapshot {
+    /// Total size with every duplicate counted once
+    pub unique_size: u64,
+    /// Hashes of the [`TRACKED_GROUPS`] groups with the most waste
+    pub groups: Vec<String>,
+}
+
+impl DuplicateSnapshot {
+    /// Snapshot of a run of `total_size` bytes that found `groups`
+    pub fn of(total_size: u64, groups: &[DuplicateGroup]) -> Self {
+        let mut largest: Vec<&DuplicateGroup> = groups.iter().collect();
+        largest.sort_by(|a, b| b.wasted_space.cmp(&a.wasted_space).then_with(|| a.hash.cmp(&b.hash)));
+        let waste: u64 = groups.iter().map(|group| group.wasted_space).sum();
+        Self {
+            unique_size: total_size.saturating_sub(waste),
+            groups: largest.into_iter().take(TRACKED_GROUPS).map(|group| group.hash.clone()).collect(),
+        }
+    }
+}
+
+/// Every duplicate group of the latest run that searched for duplicates
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GroupLedger {
+    pub timestamp: DateTime<Utc>,
+    pub groups: Vec<GroupSighting>,
+}
+
+/// A duplicate group as the ledger remembers it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GroupSighting {
+    pub group_id: String,
+    pub hash: String,
+    pub first_seen: DateTime<Utc>,
+    pub file_size: u64,
+    pub copies: usize,
+    pub wasted_space: u64,
+    /// First member, to tell where the group was
+    pub path: PathBuf,
+}
+
+/// A group the previous run found that is gone now
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResolvedGroup {
+    pub group_id: String,
+    pub hash: String,
+    pub path: PathBuf,
+    pub copies: usize,
+    pub file_size: u64,
+    /// Waste the group had when last seen
+    pub reclaimed_space: u64,
+    pub first_seen: DateTime<Utc>,
+    pub last_seen: DateTime<Utc>,
+}
+
+/// Size and file count changes relative to earlier runs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Trend {
+    pub previous_timestamp: DateTime<Utc>,
+    pub size_change_since_last: i64,
+    pub files_change_since_last: i64,
+    pub window: Option<TrendWindow>,
+    /// How the change since the last run splits, when both runs searched for duplicates
+    #[serde(default)]
+    pub growth: Option<GrowthAttribution>,
+}
+
+/// The size change since the last run, split into unique and duplicated bytes
+///
+/// `unique_change + duplication_change == total_change`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GrowthAttribution {
+    pub total_change: i64,
+    pub unique_change: i64,
+    /// Change in duplicate waste
+    pub duplication_change: i64,
+    /// The group with the most waste that the last run did not track
+    pub top_new_group: Option<NewDuplicateGroup>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NewDuplicateGroup {
+    pub hash: String,
+    /// First copy in path order
+    pub path: PathBuf,
+    pub wasted_space: u64,
+}
+
+/// Change over the long-range window, measured against the run closest to its start
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrendWindow {
+    pub days: i64,
+    pub size_change: i64,
+}
+
+/// Append-only history file for one scan root
+pub struct HistoryStore {
+    path: PathBuf,
+    retention: usize,
+    writes: WriteGuard,
+}
+
+impl HistoryStore {
+    /// Open the history store for `root` inside the cache directory
+    pub fn for_root(root: &Path, retention: usize) -> Option<Self> {
+        let dir = config::cache_dir()?.join("history");
+        Some(Self::at(dir.join(root_file_name(root)), retention))
+    }
+
+    /// Open a history store backed by an explicit file
+    pub fn at(path: PathBuf, retention: usize) -> Self {
+        Self { path, retention, writes: WriteGuard::unrestricted() }
+    }
+
+    /// Write the history file and its lock through `writes`
+    pub fn with_writes(mut self, writes: WriteGuard) -> Self {
+        self.writes = writes;
+        self
+    }
+
+    /// Location of the backing file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Load all records, oldest first. A missing file yields an empty history.
+    pub fn load(&self) -> Result<Vec<HistoryRecord>> {
+        let file = match File::open(&self.path) {
+            Ok(file) => file,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(error) => {
+                return Err(error)
+                    .with_context(|| format!("Failed to open history file: {}", self.path.display()))
+            }
+        };
+
+        let mut records = Vec::new();
+        for line in BufReader::new(file).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            // Skip lines torn by an interrupted write rather than losing the whole history
+            if let Ok(record) = serde_json::from_str(&line) {
+                records.push(record);
+            }
+        }
+        records.sort_by_key(|record: &HistoryRecord| record.timestamp);
+        Ok(records)
+    }
+
+    /// Location of the group ledger kept next to the history file
+    pub fn ledger_path(&self) -> PathBuf {
+        self.path.with_extension("groups.json")
+    }
+
+    /// Load the group ledger, if a run that searched for duplicates left one
+    pub fn load_ledger(&self) -> Result<Option<GroupLedger>> {
+        let path = self.ledger_path();
+        let data = match std::fs::read(&path) {
+            Ok(data) => data,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(error).with_context(|| format!("Failed to read group ledger: {}", path.display())),
+        };
+        // A torn ledger only costs the first-seen times and one run's resolved groups
+        Ok(serde_json::from_slice(&data).ok())
+    }
+
+    /// Replace the group ledger
+    pub fn save_ledger(&self, ledger: &GroupLedger) -> Result<()> {
+        let path = self.ledger_path();
+        if let Some(parent) = path.parent() {
+            self.writes.create_dir_all(parent)
+                .with_context(|| format!("Failed to create history directory: {}", parent.display()))?;
+        }
+
+        let _lock = FileLock::acquire(&self.path.with_extension("lock"), &self.writes)?;
+        let temp_path = path.with_extension("tmp");
+        let mut temp = self.writes.create(&temp_path)?;
+        serde_json::to_writer(&mut temp, ledger)?;
+        temp.sync_all()?;
+        self.writes.rename(&temp_path, &path)
+            .with_context(|| format!("Failed to update group ledger: {}", path.display()))?;
+        Ok(())
+    }
+
+    /// Append a record, pruning the oldest entries beyond the retention cap
+    pub fn append(&self, record: &HistoryRecord) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            self.writes.create_dir_all(parent)
+                .with_context(|| format!("Failed to create history directory: {}", parent.display()))?;
+        }
+
+        let _lock = FileLock::acquire(&self.path.with_extension("lock"), &self.writes)?;
+
+        let mut records = self.load()?;
+        records.push(record.clone());
+        records.sort_by_key(|record| record.timestamp);
+        if records.len() > self.retention {
+            records.drain(..records.len() - self.retention);
+        }
+
+        let temp_path = self.path.with_extension("tmp");
+        let mut temp = self.writes.create(&temp_path)
+            .with_context(|| format!("Failed to create history file: {}", temp_path.display()))?;
+        for record in &records {
+            writeln!(temp, "{}", serde_json::to_string(record)?)?;
+        }
+        temp.sync_all()?;
+        self.writes.rename(&temp_path, &self.path)
+            .with_context(|| format!("Failed to update history file: {}", self.path.display()))?;
+
+        Ok(())
+    }
+}
+
+/// The run `current` is compared with: the newest one recorded before it
+pub fn last_run<'a>(previous: &'a [HistoryRecord], current: &HistoryRecord) -> Option<&'a HistoryRecord> {
+    previous.iter().rfind(|r| r.timestamp <= current.timestamp)
+}
+
+/// Compute the trend of `current` against the previously recorded runs
+pub fn compute_trend(previous: &[HistoryRecord], current: &HistoryRecord) -> Option<Trend> {
+    let last = last_run(previous, current)?;
+
+    // Prefer the newest run that is at least a full window old; otherwise use
+    // the oldest run we have so the window covers as much history as possible.
+    let window_start = current.timestamp - Duration::days(TREND_WINDOW_DAYS);
+    let baseline = previous
+        .iter()
+        .rfind(|r| r.timestamp <= window_start)
+        .or_else(|| previous.first())
+        .filter(|baseline| baseline.timestamp < last.timestamp);
+
+    let window = baseline.map(|baseline| TrendWindow {
+        days: (current.timestamp - baseline.timestamp).num_days(),
+        size_change: current.total_size as i64 - baseline.total_size as i64,
+    });
+
+    Some(Trend {
+        previous_timestamp: last.timestamp,
+        size_change_since_last: current.total_size as i64 - last.total_size as i64,
+        files_change_since_last: current.total_files as i64 - last.total_files as i64,
+        window,
+        growth: None,
+    })
+}
+
+/// Split the growth from `last` to `current`, whose run found `groups`
+///
+/// Both runs need a [`DuplicateSnapshot`]. Groups are matched by hash. As only
+/// the largest groups of a run are tracked, a group that was too small to be
+/// tracked last time also counts as new.
+pub fn attribute_growth(last: &HistoryRecord, current: &HistoryRecord, groups: &[DuplicateGroup]) -> Option<GrowthAttribution> {
+    let (before, now) = (last.duplicates.as_ref()?, current.duplicates.as_ref()?);
+    let unique_change = now.unique_size as i64 - before.unique_size as i64;
+    let waste = |record: &HistoryRecord, unique_size: u64| record.total_size.saturating_sub(unique_size) as i64;
+    let duplication_change = waste(current, now.unique_size) - waste(last, before.unique_size);
+
+    let top_new_group = groups
+        .iter()
+        .filter(|group| !before.groups.contains(&group.hash))
+        .max_by(|a, b| a.wasted_space.cmp(&b.wasted_space).then_with(|| b.hash.cmp(&a.hash)))
+        .map(|group| NewDuplicateGroup {
+            hash: group.hash.clone(),
+            path: group.files.iter().min().cloned().unwrap_or_default(),
+            wasted_space: group.wasted_space,
+        });
+
+    Some(GrowthAttribution {
+        total_change: unique_change + duplication_change,
+        unique_change,
+        duplication_change,
+        top_new_group,
+    })
+}
+
+/// Stamp `groups` with their first and last sightings and find the groups of `ledger` now gone
+///
+/// Returns the ledger to keep for the next run and the resolved groups,
+/// largest reclaimed space first.
+pub fn track_groups(
+    ledger: Option<&GroupLedger>,
+    groups: &mut [DuplicateGroup],
+    now: DateTime<Utc>,
+) -> (GroupLedger, Vec<ResolvedGroup>) {
+    let known: HashMap<&str, &GroupSighting> = ledger
+        .map(|ledger| ledger.groups.iter().map(|sighting| (sighting.group_id.as_str(), sighting)).collect())
+        .unwrap_or_default();
+
+    let mut sightings = Vec::with_capacity(groups.len());
+    for group in groups.iter_mut() {
+        let first_seen = known.get(group.group_id.as_str()).map_or(now, |sighting| sighting.first_seen);
+        group.first_seen = Some(first_seen);
+        group.last_seen = Some(now);
+        sightings.push(GroupSighting {
+            group_id: group.group_id.clone(),
+            hash: group.hash.clone(),
+            first_seen,
+            file_size: group.file_size,
+            copies: group.files.len(),
+            wasted_space: group.wasted_space,
+            path: group.files.iter().min().cloned().unwrap_or_default(),
+        });
+    }
+
+    let present: HashSet<&str> = groups.iter().map(|group| group.group_id.as_str()).collect();
+    let mut resolved: Vec<ResolvedGroup> = ledger
+        .into_iter()
+        .flat_map(|ledger| ledger.groups.iter().map(move |sighting| (ledger.timestamp, sighting)))
+        .filter(|(_, sighting)| !present.contains(sighting.group_id.as_str()))
+        .map(|(last_seen, sighting)| ResolvedGroup {
+            group_id: sighting.group_id.clone(),
+            hash: sighting.hash.clone(),
+            path: sighting.path.clone(),
+            copies: sighting.copies,
+            file_size: sighting.file_size,
+            reclaimed_space: sighting.wasted_space,
+            first_seen: sighting.first_seen,
+            last_seen,
+        })
+        .collect();
+    resolved.sort_by(|a, b| b.reclaimed_space.cmp(&a.reclaimed_space).then_with(|| a.group_id.cmp(&b.group_id)));
+
+    sightings.sort_by(|a, b| a.group_id.cmp(&b.group_id));
+    (GroupLedger { timestamp: now, groups: sightings }, resolved)
+}
+
+/// Write the history series as CSV
+pub fn write_csv<W: Write>(records: &[HistoryRecord], writer: W) -> Result<()> {
+    let mut writer = csv::Writer::from_writer(writer);
+    writer.write_record(["Timestamp", "TotalSize", "TotalFiles", "DuplicateWaste", "UniqueSize"])?;
+    for record in records {
+        writer.write_record([
+            record.timestamp.to_rfc3339(),
+            record.total_size.to_string(),
+            record.total_files.to_string(),
+            record.duplicate_waste.to_string(),
+            record.duplicates.as_ref().map(|duplicates| duplicates.unique_size.to_string()).unwrap_or_default(),
+        ])?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Stable file name for a root: readable basename plus a digest of the canonical path
+pub(crate) fn root_file_name(root: &Path) -> String {
+    let canonical = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+    let digest = Sha256::digest(canonical.to_string_lossy().as_bytes());
+    let short_hash: String = digest.iter().take(8).map(|b| format!("{:02x}", b)).collect();
+
+    let basename: String = canonical
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "root".to_string())
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+
+    format!("{}-{}.ndjson", basename, short_hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    const GB: u64 = 1_000_000_000;
+
+    fn record(days_ago: i64, total_size: u64, now: DateTime<Utc>) -> HistoryRecord {
+        HistoryRecord {
+            timestamp: now - Duration::days(days_ago),
+            total_size,
+            total_files: total_size / GB,
+            duplicate_waste: 0,
+            entropy: None,
+            duplicates: None,
+        }
+    }
+
+    #[test]
+    fn test_trend_deltas_from_simulated_runs() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = HistoryStore::at(dir.path().join("history.ndjson"), 100);
+        let now = Utc::now();
+
+        for (days_ago, size) in [(45, 100 * GB), (31, 120 * GB), (10, 250 * GB), (1, 296 * GB)] {
+            store.append(&record(days_ago, size, now)).unwrap();
+        }
+
+        let current = record(0, 330 * GB, now);
+        let trend = compute_trend(&store.load().unwrap(), &current).unwrap();
+
+        assert_eq!(trend.size_change_since_last, 34 * GB as i64);
+        assert_eq!(trend.files_change_since_last, 34);
+        let window = trend.window.unwrap();
+        assert_eq!(window.days, 31);
+        assert_eq!(window.size_change, 210 * GB as i64);
+    }
+
+    #[test]
+    fn test_trend_with_short_history() {
+        let now = Utc::now();
+        assert!(compute_trend(&[], &record(0, GB, now)).is_none());
+
+        let single = [record(2, 3 * GB, now)];
+        let trend = compute_trend(&single, &record(0, 2 * GB, now)).unwrap();
+        assert_eq!(trend.size_change_since_last, -(GB as i64));
+        assert!(trend.window.is_none());
+
+        let recent = [record(5, GB, now), record(2, 3 * GB, now)];
+        let window = compute_trend(&recent, &record(0, 4 * GB, now)).unwrap().window.unwrap();
+        assert_eq!(window.days, 5);
+        assert_eq!(window.size_change, 3 * GB as i64);
+    }
+
+    fn group(hash: &str, wasted_space: u64, files: &[&str]) -> DuplicateGroup {
+        DuplicateGroup {
+            group_id: crate::duplicates::group_id(hash, None),
+            hash: hash.to_string(),
+            file_size: wasted_space,
+            files: files.iter().map(PathBuf::from).collect(),
+            wasted_space,
+            newest_modified: None,
+            priority: 0.0,
+            partition: None,
+            regenerable: false,
+            first_seen: None,
+            last_seen: None,
+            accepted: false,
+            unverified: false,
+        }
+    }
+
+    fn snapshot_run(days_ago: i64, total_size: u64, groups: &[DuplicateGroup], now: DateTime<Utc>) -> HistoryRecord {
+        HistoryRecord {
+            duplicate_waste: groups.iter().map(|group| group.wasted_space).sum(),
+            duplicates: Some(DuplicateSnapshot::of(total_size, groups)),
+            ..record(days_ago, total_size, now)
+        }
+    }
+
+    #[test]
+    fn test_growth_split_between_unique_and_duplicated_bytes() {
+        let now = Utc::now();
+        let before = [group("a", 30 * GB, &["/v/a1", "/v/a2"]), group("b", 20 * GB, &["/v/b1", "/v/b2"])];
+        // "a" gained a copy, "b" is unchanged, "c" is new
+        let after = [
+            group("a", 40 * GB, &["/v/a1", "/v/a2", "/v/a3"]),
+            group("b", 20 * GB, &["/v/b1", "/v/b2"]),
+            group("c", 50 * GB, &["/v/new/c2", "/v/new/c1"]),
+        ];
+        let last = snapshot_run(1, 1_000 * GB, &before, now);
+        let current = snapshot_run(0, 1_200 * GB, &after, now);
+        assert_eq!(current.duplicates.as_ref().unwrap().unique_size, 1_090 * GB);
+
+        let growth = attribute_growth(&last, &current, &after).unwrap();
+        assert_eq!(growth.total_change, 200 * GB as i64);
+        assert_eq!(growth.unique_change, 140 * GB as i64);
+        assert_eq!(growth.duplication_change, 60 * GB as i64);
+        let new_group = growth.top_new_group.unwrap();
+        assert_eq!((new_group.hash.as_str(), new_group.wasted_space), ("c", 50 * GB));
+        assert_eq!(new_group.path, PathBuf::from("/v/new/c1"));
+
+        // Shrinking duplication with no new group
+        let growth = attribute_growth(&current, &snapshot_run(0, 1_140 * GB, &before, now), &before).unwrap();
+        assert_eq!((growth.unique_change, growth.duplication_change), (0, -60 * GB as i64));
+        assert_eq!(growth.top_new_group, None);
+    }
+
+    #[test]
+    fn test_growth_needs_duplicates_on_both_runs() {
+        let now = Utc::now();
+        let groups = [group("a", GB, &["/v/a1", "/v/a2"])];
+        let with = snapshot_run(0, 10 * GB, &groups, now);
+        let without = record(1, 8 * GB, now);
+        assert_eq!(attribute_growth(&without, &with, &groups), None);
+        assert_eq!(attribute_growth(&with, &without, &[]), None);
+    }
+
+    #[test]
+    fn test_tracked_groups_are_the_largest() {
+        let groups: Vec<_> = (0..TRACKED_GROUPS as u64 + 10).map(|i| group(&format!("h{}", i), i, &["/x", "/y"])).collect();
+        let snapshot = DuplicateSnapshot::of(u64::MAX, &groups);
+        assert_eq!(snapshot.groups.len(), TRACKED_GROUPS);
+        assert_eq!(snapshot.groups[0], format!("h{}", TRACKED_GROUPS + 9));
+        assert!(!snapshot.groups.contains(&"h0".to_string()));
+    }
+
+    #[test]
+    fn test_tracked_groups_keep_their_first_sighting() {
+        let now = Utc::now();
+        let mut first = [group("a", 30 * GB, &["/v/a1", "/v/a2"]), group("b", 20 * GB, &["/v/b2", "/v/b1"])];
+        let (ledger, resolved) = track_groups(None, &mut first, now - Duration::days(7));
+        assert!(resolved.is_empty());
+        assert_eq!(first[0].first_seen, Some(now - Duration::days(7)));
+        assert_eq!(ledger.groups.iter().find(|sighting| sighting.hash == "b").unwrap().path, PathBuf::from("/v/b1"));
+
+        // "a" is still there with its copies listed in another order, "b" was cleaned up, "c" is new
+        let mut second = [group("c", 5 * GB, &["/v/c1", "/v/c2"]), group("a", 30 * GB, &["/v/a2", "/v/a1"])];
+        let (ledger, resolved) = track_groups(Some(&ledger), &mut second, now);
+        assert_eq!(second[1].group_id, first[0].group_id);
+        assert_eq!(second[1].first_seen, Some(now - Duration::days(7)));
+        assert_eq!(second[1].last_seen, Some(now));
+        assert_eq!(second[0].first_seen, Some(now));
+        assert_eq!(ledger.groups.len(), 2);
+
+        assert_eq!(resolved.len(), 1);
+        assert_eq!(resolved[0].group_id, first[1].group_id);
+        assert_eq!(resolved[0].reclaimed_space, 20 * GB);
+        assert_eq!(resolved[0].last_seen, now - Duration::days(7));
+    }
+
+    #[test]
+    fn test_group_ledger_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = HistoryStore::at(dir.path().join("history.ndjson"), 100);
+        assert!(store.load_ledger().unwrap().is_none());
+
+        let (ledger, _) = track_groups(None, &mut [group("a", GB, &["/v/a1", "/v/a2"])], Utc::now());
+        store.save_ledger(&ledger).unwrap();
+        let loaded = store.load_ledger().unwrap().unwrap();
+        assert_eq!(loaded.groups.len(), 1);
+        assert_eq!(loaded.groups[0].group_id, ledger.groups[0].group_id);
+
+        // A torn ledger starts tracking over instead of failing the run
+        std::fs::write(store.ledger_path(), "{\"timestamp\":").unwrap();
+        assert!(store.load_ledger().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_retention_cap_keeps_newest_records() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = HistoryStore::at(dir.path().join("history.ndjson"), 3);
+        let now = Utc::now();
+
+        for days_ago in (0..6).rev() {
+            store.append(&record(days_ago, days_ago as u64 * GB, now)).unwrap();
+        }
+
+        let records = store.load().unwrap();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].timestamp, now - Duration::days(2));
+        assert_eq!(records[2].timestamp, now);
+    }
+
+    #[test]
+    fn test_concurrent_appends_are_not_lost() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = Arc::new(dir.path().join("history.ndjson"));
+        let now = Utc::now();
+
+        let handles: Vec<_> = (0..8)
+            .map(|i| {
+                let path = Arc::clone(&path);
+                std::thread::spawn(move || {
+                    let store = HistoryStore::at(path.as_ref().clone(), 100);
+                    for j in 0..5 {
+                        store.append(&record(i * 10 + j, GB, now)).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let records = HistoryStore::at(path.as_ref().clone(), 100).load().unwrap();
+        assert_eq!(records.len(), 40);
+    }
+
+    #[test]
+    fn test_csv_output() {
+        let now = Utc::now();
+        let mut output = Vec::new();
+        write_csv(&[record(0, 2 * GB, now)], &mut output).unwrap();
+
+        let text = String::from_utf8(output).unwrap();
+        assert!(text.starts_with("Timestamp,TotalSize,TotalFiles,DuplicateWaste,UniqueSize\n"));
+        assert!(text.contains(",2000000000,2,0,\n"));
+    }
+
+    #[test]
+    fn test_history_file_name_is_sanitized() {
+        let name = root_file_name(Path::new("/nonexistent/my dir:x"));
+        assert!(name.starts_with("my_dir_x-"));
+        assert!(name.ends_with(".ndjson"));
+        assert!(!name.contains('/'));
+    }
+}
@@ -0,0 +1,395 @@
+//! Interoperability with other duplicate finders
+//!
+//! Duplicate groups can be exported as rmlint JSON, so rmlint's action engine
+//! (`rmlint --replay`) can act on them, and findings from rmlint or jdupes can
+//! be imported for reporting without re-hashing anything.
+//!
+//! Group hashes carry their algorithm as a prefix (`blake2b:…`) unless they
+//! are SHA-256, the algorithm diranalyzer uses itself. That keeps native
+//! hashes unchanged and lets an export/import cycle reproduce the same groups.
+
+use crate::analyzer::DuplicateGroup;
+use crate::duplicates;
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Hash algorithm of unprefixed group hashes
+pub const NATIVE_ALGORITHM: &str = "sha256";
+
+/// Algorithm name used for jdupes groups, which carry no checksum
+const JDUPES_ALGORITHM: &str = "jdupes";
+
+/// One lint record of an rmlint JSON document
+#[derive(Debug, Serialize, Deserialize)]
+struct RmlintRecord {
+    #[serde(default)]
+    id: u64,
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    progress: u32,
+    checksum: String,
+    path: PathBuf,
+    size: u64,
+    #[serde(default)]
+    depth: usize,
+    #[serde(default)]
+    inode: u64,
+    #[serde(default)]
+    disk_id: u64,
+    #[serde(default)]
+    is_original: bool,
+    #[serde(default)]
+    mtime: f64,
+}
+
+#[derive(Debug, Deserialize)]
+struct JdupesDocument {
+    #[serde(rename = "matchSets")]
+    match_sets: Vec<JdupesMatchSet>,
+}
+
+#[derive(Debug, Deserialize)]
+struct JdupesMatchSet {
+    #[serde(rename = "fileSize")]
+    file_size: u64,
+    #[serde(rename = "fileList")]
+    file_list: Vec<JdupesFile>,
+}
+
+#[derive(Debug, Deserialize)]
+struct JdupesFile {
+    #[serde(rename = "filePath")]
+    file_path: PathBuf,
+}
+
+/// Split a group hash into its algorithm and digest
+pub fn split_hash(hash: &str) -> (&str, &str) {
+    hash.split_once(':').unwrap_or((NATIVE_ALGORITHM, hash))
+}
+
+/// Join an algorithm and digest into a group hash
+pub fn join_hash(algorithm: &str, digest: &str) -> String {
+    if algorithm.eq_ignore_ascii_case(NATIVE_ALGORITHM) {
+        digest.to_string()
+    } else {
+        format!("{}:{}", algorithm, digest)
+    }
+}
+
+/// Write duplicate groups as an rmlint JSON document.
+///
+/// The first file of each group is marked as the original. `aborted` marks
+/// groups found in a scan that stopped early, as rmlint marks an interrupted run.
+pub fn write_rmlint_json<W: Write>(groups: &[DuplicateGroup], aborted: bool, mut writer: W) -> Result<()> {
+    let checksum_type = groups
+        .first()
+        .map(|group| split_hash(&group.hash).0)
+        .unwrap_or(NATIVE_ALGORITHM);
+
+    let mut documents = vec![serde_json::json!({
+        "description": "rmlint json-dump of lint files",
+        "cwd": std::env::current_dir().unwrap_or_default(),
+        "args": format!("diranalyzer {}", env!("CARGO_PKG_VERSION")),
+        "version": env!("CARGO_PKG_VERSION"),
+        "rev": "diranalyzer",
+        "progress": 0,
+        "checksum_type": checksum_type,
+    })];
+
+    let mut id = 0;
+    for group in groups {
+        let (_, digest) = split_hash(&group.hash);
+        for (index, path) in group.files.iter().enumerate() {
+            id += 1;
+            let (inode, disk_id, mtime) = file_identity(path);
+            documents.push(serde_json::to_value(RmlintRecord {
+                id,
+                kind: "duplicate_file".to_string(),
+                progress: 100,
+                checksum: digest.to_string(),
+                path: path.clone(),
+                size: group.file_size,
+                depth: path.components().count().saturating_sub(1),
+                inode,
+                disk_id,
+                is_original: index == 0,
+                mtime,
+            })?);
+        }
+    }
+
+    documents.push(serde_json::json!({
+        "aborted": aborted,
+        "progress": 100,
+        "total_files": id,
+        "ignored_files": 0,
+        "ignored_folders": 0,
+        "duplicates": groups.iter().map(|g| g.files.len().saturating_sub(1)).sum::<usize>(),
+        "duplicate_sets": groups.len(),
+        "total_lint_size": groups.iter().map(|g| g.wasted_space).sum::<u64>(),
+    }));
+
+    serde_json::to_writer_pretty(&mut writer, &documents)?;
+    writeln!(writer)?;
+    Ok(())
+}
+
+/// Import duplicate groups from rmlint JSON, jdupes JSON (`-j`), or jdupes text output
+pub fn import_duplicates(path: &Path) -> Result<Vec<DuplicateGroup>> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read duplicate findings: {}", path.display()))?;
+    parse_duplicates(&content)
+        .with_context(|| format!("Failed to parse duplicate findings: {}", path.display()))
+}
+
+/// Parse duplicate findings, detecting the producing tool from the content
+pub fn parse_duplicates(content: &str) -> Result<Vec<DuplicateGroup>> {
+    match content.trim_start().chars().next() {
+        Some('[') => parse_rmlint(serde_json::from_str(content)?),
+        Some('{') => parse_jdupes_json(serde_json::from_str(content)?),
+        _ => parse_jdupes_text(content),
+    }
+}
+
+fn parse_rmlint(documents: Vec<Value>) -> Result<Vec<DuplicateGroup>> {
+    let checksum_type = documents
+        .iter()
+        .find_map(|doc| doc.get("checksum_type")?.as_str().map(str::to_string))
+        .unwrap_or_else(|| "unknown".to_string());
+
+    // Group by checksum and size, keeping the order groups first appear in
+    let mut order = Vec::new();
+    let mut members: HashMap<(String, u64), Vec<RmlintRecord>> = HashMap::new();
+    for doc in documents {
+        if doc.get("type").and_then(Value::as_str) != Some("duplicate_file") {
+            continue;
+        }
+        let record: RmlintRecord = serde_json::from_value(doc)?;
+        let key = (record.checksum.clone(), record.size);
+        if !members.contains_key(&key) {
+            order.push(key.clone());
+        }
+        members.entry(key).or_default().push(record);
+    }
+
+    let groups = order
+        .into_iter()
+        .filter_map(|key| {
+            let mut records = members.remove(&key)?;
+            // Originals first; the sort is stable so rmlint's order is kept otherwise
+            records.sort_by_key(|record| !record.is_original);
+            let newest_modified = records
+                .iter()
+                .filter_map(|record| DateTime::from_timestamp(record.mtime as i64, 0))
+                .max();
+            let files = records.into_iter().map(|record| record.path).collect();
+            group(join_hash(&checksum_type, &key.0), key.1, files, newest_modified)
+        })
+        .collect();
+    Ok(groups)
+}
+
+fn parse_jdupes_json(document: JdupesDocument) -> Result<Vec<DuplicateGroup>> {
+    Ok(document
+        .match_sets
+        .into_iter()
+        .enumerate()
+        .filter_map(|(index, set)| {
+            let files: Vec<PathBuf> = set.file_list.into_iter().map(|file| file.file_path).collect();
+            let newest_modified = newest_modified(&files);
+            group(jdupes_hash(index), set.file_size, files, newest_modified)
+        })
+        .collect())
+}
+
+/// Plain jdupes output: one path per line, groups separated by blank lines,
+/// optionally preceded by a "N bytes each:" line (`-S`)
+fn parse_jdupes_text(content: &str) -> Result<Vec<DuplicateGroup>> {
+    let mut sets: Vec<(Option<u64>, Vec<PathBuf>)> = Vec::new();
+    let mut current: (Option<u64>, Vec<PathBuf>) = (None, Vec::new());
+
+    for line in content.lines().chain(std::iter::once("")) {
+        if line.trim().is_empty() {
+            if !current.1.is_empty() {
+                sets.push(std::mem::take(&mut current));
+            }
+            current.0 = None;
+        } else if let Some(size) = line.strip_suffix(" bytes each:").or_else(|| line.strip_suffix(" byte each:")) {
+            current.0 = Some(size.trim().parse().with_context(|| format!("Invalid size line: {}", line))?);
+        } else {
+            current.1.push(PathBuf::from(line));
+        }
+    }
+
+    if sets.is_empty() && !content.trim().is_empty() {
+        bail!("No duplicate groups found in input");
+    }
+
+    Ok(sets
+        .into_iter()
+        .enumerate()
+        .filter_map(|(index, (size, files))| {
+            let size = size.unwrap_or_else(|| fs::metadata(&files[0]).map(|m| m.len()).unwrap_or(0));
+            let newest_modified = newest_modified(&files);
+            group(jdupes_hash(index), size, files, newest_modified)
+        })
+        .collect())
+}
+
+fn group(
+    hash: String,
+    file_size: u64,
+    files: Vec<PathBuf>,
+    newest_modified: Option<DateTime<Utc>>,
+) -> Option<DuplicateGroup> {
+    if files.len() < 2 {
+        return None;
+    }
+    Some(DuplicateGroup {
+        group_id: duplicates::group_id(&hash, None),
+        hash,
+        file_size,
+        wasted_space: file_size * (files.len() as u64 - 1),
+        files,
+        newest_modified,
+        priority: 0.0,
+        partition: None,
+        regenerable: false,
+        first_seen: None,
+        last_seen: None,
+        accepted: false,
+        unverified: false,
+    })
+}
+
+fn jdupes_hash(index: usize) -> String {
+    join_hash(JDUPES_ALGORITHM, &format!("set{}", index + 1))
+}
+
+/// Newest modification time among files that still exist
+fn newest_modified(files: &[PathBuf]) -> Option<DateTime<Utc>> {
+    files
+        .iter()
+        .filter_map(|path| fs::metadata(path).and_then(|m| m.modified()).ok())
+        .map(DateTime::<Utc>::from)
+        .max()
+}
+
+/// Inode, device, and mtime of a file as rmlint records them (zeros when unavailable)
+fn file_identity(path: &Path) -> (u64, u64, f64) {
+    let Ok(metadata) = fs::metadata(path) else {
+        return (0, 0, 0.0);
+    };
+    let mtime = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|duration| duration.as_secs_f64())
+        .unwrap_or(0.0);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        (metadata.ino(), metadata.dev(), mtime)
+    }
+    #[cfg(not(unix))]
+    {
+        (0, 0, mtime)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RMLINT: &str = include_str!("interop/fixtures/rmlint.json");
+    const JDUPES_JSON: &str = include_str!("interop/fixtures/jdupes.json");
+    const JDUPES_TEXT: &str = include_str!("interop/fixtures/jdupes.txt");
+
+    fn summary(groups: &[DuplicateGroup]) -> Vec<(u64, Vec<PathBuf>)> {
+        groups.iter().map(|g| (g.file_size, g.files.clone())).collect()
+    }
+
+    #[test]
+    fn test_import_rmlint() {
+        let groups = parse_duplicates(RMLINT).unwrap();
+
+        assert_eq!(groups.len(), 2);
+        assert_eq!(
+            groups[0].hash,
+            "blake2b:7c0b2e1f9d4a66e3a1e09c3bd56f6fbb1d2b0f3b7a3e9c4f0d1e2a3b4c5d6e7f"
+        );
+        assert_eq!(groups[0].files.len(), 3);
+        assert_eq!(groups[0].wasted_space, 4_096_000);
+        assert_eq!(groups[0].newest_modified, DateTime::from_timestamp(1_696_118_400, 0));
+        // The original is listed first even though rmlint wrote it second
+        assert_eq!(groups[1].files[0], PathBuf::from("/home/alice/photos/todo.txt"));
+    }
+
+    #[test]
+    fn test_import_jdupes_formats_agree() {
+        let from_json = parse_duplicates(JDUPES_JSON).unwrap();
+        let from_text = parse_duplicates(JDUPES_TEXT).unwrap();
+
+        assert_eq!(from_json.len(), 2);
+        assert_eq!(summary(&from_json), summary(&from_text));
+        assert_eq!(from_json[0].hash, "jdupes:set1");
+        assert_eq!(from_json[1].wasted_space, 4096);
+    }
+
+    #[test]
+    fn test_rmlint_round_trip() {
+        let imported = parse_duplicates(RMLINT).unwrap();
+
+        let mut exported = Vec::new();
+        write_rmlint_json(&imported, false, &mut exported).unwrap();
+        let reimported = parse_duplicates(std::str::from_utf8(&exported).unwrap()).unwrap();
+
+        assert_eq!(summary(&reimported), summary(&imported));
+        let hashes: Vec<_> = reimported.iter().map(|g| &g.hash).collect();
+        assert_eq!(hashes, imported.iter().map(|g| &g.hash).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_native_hashes_export_as_sha256() {
+        let native = DuplicateGroup {
+            group_id: String::new(),
+            hash: "ab".repeat(32),
+            file_size: 10,
+            files: vec![PathBuf::from("/a"), PathBuf::from("/b")],
+            wasted_space: 10,
+            newest_modified: None,
+            priority: 0.0,
+            partition: None,
+            regenerable: false,
+            first_seen: None,
+            last_seen: None,
+            accepted: false,
+            unverified: false,
+        };
+
+        let mut exported = Vec::new();
+        write_rmlint_json(std::slice::from_ref(&native), false, &mut exported).unwrap();
+        let documents: Vec<Value> = serde_json::from_slice(&exported).unwrap();
+
+        assert_eq!(documents[0]["checksum_type"], "sha256");
+        assert_eq!(documents[1]["is_original"], true);
+        assert_eq!(documents[2]["is_original"], false);
+        assert_eq!(documents.last().unwrap()["duplicate_sets"], 1);
+        let reimported = parse_duplicates(std::str::from_utf8(&exported).unwrap()).unwrap();
+        assert_eq!(reimported[0].hash, native.hash);
+    }
+
+    #[test]
+    fn test_import_rejects_garbage() {
+        assert!(parse_duplicates("[{\"type\": \"duplicate_file\"}]").is_err());
+        assert!(parse_duplicates("{\"nope\": 1}").is_err());
+    }
+}
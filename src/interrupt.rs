@@ -0,0 +1,84 @@
+//! Stopping a run early without losing what it found
+//!
+//! A [`Cancellation`] is a flag shared by the parts of a run that take long:
+//! the walk checks it at every entry and the duplicate search before every
+//! file it hashes. Once it is set, the walk stops as a `--max-files` cap would
+//! stop it, and the search drops the size groups it has not finished hashing,
+//! so that no group is reported with copies missing. What was collected until
+//! then is analyzed, reported, and exported as usual, marked as partial.
+//!
+//! The binary sets the flag on the first Ctrl-C; a second one ends the
+//! process at once, as Ctrl-C does without the handler.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, OnceLock};
+
+/// Exit status of a process ended by SIGINT
+const INTERRUPTED_STATUS: i32 = 130;
+
+/// The cancellation Ctrl-C sets, once [`Cancellation::on_ctrl_c`] installed the handler
+static CTRL_C: OnceLock<Cancellation> = OnceLock::new();
+
+/// Asks a scan or a duplicate search to stop; clones share the flag
+#[derive(Debug, Clone, Default)]
+pub struct Cancellation(Arc<AtomicBool>);
+
+impl Cancellation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ask everything holding this cancellation to stop
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// The cancellation Ctrl-C sets, handling SIGINT from now on
+    ///
+    /// The first Ctrl-C cancels; the second ends the process with status 130.
+    pub fn on_ctrl_c() -> Self {
+        let cancellation = CTRL_C.get_or_init(Cancellation::default).clone();
+        #[cfg(unix)]
+        {
+            static INSTALLED: std::sync::Once = std::sync::Once::new();
+            INSTALLED.call_once(|| {
+                extern "C" fn interrupt(_signal: libc::c_int) {
+                    const NOTICE: &[u8] = b"\nInterrupted: finishing with what was found so far; press Ctrl-C again to quit at once.\n";
+                    // SAFETY: the handler only touches atomics and calls write and _exit, which are async-signal-safe
+                    unsafe {
+                        match CTRL_C.get() {
+                            Some(cancellation) if !cancellation.0.swap(true, Ordering::Relaxed) => {
+                                libc::write(libc::STDERR_FILENO, NOTICE.as_ptr().cast(), NOTICE.len());
+                            }
+                            _ => libc::_exit(INTERRUPTED_STATUS),
+                        }
+                    }
+                }
+                // SAFETY: see the handler
+                unsafe {
+                    libc::signal(libc::SIGINT, interrupt as extern "C" fn(libc::c_int) as libc::sighandler_t);
+                }
+            });
+        }
+        cancellation
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_clones_share_the_flag() {
+        let cancellation = Cancellation::new();
+        let held = cancellation.clone();
+        assert!(!held.is_cancelled());
+        cancellation.cancel();
+        assert!(held.is_cancelled());
+        assert!(!Cancellation::new().is_cancelled());
+    }
+}
@@ -1,15 +1,64 @@
 //! DirAnalyzer library providing directory analysis functionality
-//! 
+//!
 //! This library offers comprehensive directory scanning, file type analysis,
 //! and duplicate detection capabilities with high performance.
+//!
+//! The supported API is [`prelude`]. The public modules below it exist for the
+//! diranalyzer binary; they are hidden from the documentation and may change
+//! in any release.
 
+pub mod prelude;
+
+pub(crate) mod ages;
+pub(crate) mod annotations;
+pub(crate) mod config;
+pub(crate) mod scanner;
+pub(crate) mod duplicates;
+pub(crate) mod entropy;
+pub(crate) mod space;
+pub(crate) mod interop;
+pub(crate) mod xattr;
+pub(crate) mod extents;
+pub(crate) mod preview;
+pub(crate) mod versions;
+pub(crate) mod refilter;
+pub(crate) mod metrics;
+pub(crate) mod security;
+pub(crate) mod interrupt;
+
+#[doc(hidden)]
+pub mod actions;
+#[doc(hidden)]
+pub mod accepted;
+#[doc(hidden)]
 pub mod cli;
+#[doc(hidden)]
 pub mod analyzer;
-pub mod scanner;
-pub mod duplicates;
+#[doc(hidden)]
+pub mod estimate;
+#[doc(hidden)]
+pub mod extensions;
+#[doc(hidden)]
+pub mod hash_cache;
+#[doc(hidden)]
+pub mod history;
+#[doc(hidden)]
 pub mod reporter;
+#[doc(hidden)]
+pub mod selftest;
+#[doc(hidden)]
 pub mod export;
+#[doc(hidden)]
+pub mod mirror;
+#[doc(hidden)]
+pub mod notes;
+#[cfg(feature = "syslog")]
+#[doc(hidden)]
+pub mod syslog;
+#[doc(hidden)]
 pub mod utils;
+#[doc(hidden)]
+pub mod writes;
 
 pub use analyzer::{DirectoryAnalyzer, AnalysisResults};
 pub use cli::Args;
@@ -8,28 +8,83 @@ use clap::Parser;
 use colored::Colorize;
 use std::time::Instant;
 
-mod cli;
-mod analyzer;
-mod scanner;
-mod duplicates;
-mod reporter;
-mod export;
-mod utils;
-
-use cli::Args;
-use analyzer::DirectoryAnalyzer;
+use diranalyzer::actions::{self, journal::JournalWriter, DedupeOptions};
+use diranalyzer::accepted::{AcceptanceStore, AcceptedTarget};
+use diranalyzer::cli::{AcceptArgs, AcceptCommand, Args, CacheCommand, ExportFormat, CacheStatsArgs, Command, CompareArgs, HistoryArgs, MirrorCheckArgs, NoteArgs, NoteCommand, ReportArgs, SelfTestArgs, SourceKind, UndoArgs, VerifyExportArgs};
+use diranalyzer::analyzer::{self, DirectoryAnalyzer};
+use diranalyzer::estimate::Estimator;
+use diranalyzer::hash_cache::{self, HashCache};
+use diranalyzer::history::{self, HistoryStore};
+use diranalyzer::mirror::MirrorCheck;
+use diranalyzer::notes::NoteStore;
+use diranalyzer::selftest::SelfTest;
+use diranalyzer::export::naming::OutputNaming;
+use diranalyzer::prelude::{Cancellation, CapKind, ExclusionStats, TraceOutcome};
+use diranalyzer::writes::WriteGuard;
+use diranalyzer::{export, reporter, utils};
+use humansize::{format_size, DECIMAL};
+use std::io::{IsTerminal, Write};
+use std::path::Path;
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let args = Args::parse();
+    let mut args = Args::parse();
+
+    if let Some(command) = &args.command {
+        return run_command(command).await;
+    }
+
+    if args.check {
+        check(&args);
+    }
+    if let Some(Some(path)) = &args.explain_exclusions {
+        explain_path(&args, path);
+    }
     
-    // Print banner
-    print_banner();
+    // Fail before the banner when there is nothing to scan; other sources check their listing when opened
+    if args.source == SourceKind::Local {
+        for root in args.roots() {
+            utils::validate_directory(&root)?;
+        }
+    }
+
+    // An ndjson stream or the list of empty directories on stdout leaves no room for anything else there
+    let streams_to_stdout = matches!(args.export, Some(ExportFormat::Ndjson))
+        && args.output.as_deref().is_some_and(export::stream::is_stdout);
+    if streams_to_stdout && args.print_empty_dirs {
+        anyhow::bail!("--print-empty-dirs and an ndjson stream cannot both write to stdout");
+    }
+    let stdout_taken = streams_to_stdout || args.print_empty_dirs;
+    if stdout_taken {
+        args.quiet = true;
+    } else {
+        print_banner();
+    }
     
+    let seed = *args.seed.get_or_insert_with(utils::random_seed);
+    // On stderr, so it stays out of whatever stdout carries
+    if args.verbose {
+        eprintln!("{} {} (replay with --seed {})", "🎲 Seed:".bold(), seed, seed);
+    }
+
+    if args.estimate && !estimate(&args)? {
+        return Ok(());
+    }
+
     let start_time = Instant::now();
     
-    // Initialize analyzer with configuration
-    let mut analyzer = DirectoryAnalyzer::new(args.clone())?;
+    // Initialize analyzer with configuration; Ctrl-C ends the run early with what it found
+    let mut analyzer = DirectoryAnalyzer::new(args.clone())?.with_cancellation(Cancellation::on_ctrl_c());
+    let naming = OutputNaming::from_args(&args);
+
+    // The stream is opened first so that progress goes through it; a failed run drops it as incomplete
+    let stream = match args.export {
+        Some(ExportFormat::Ndjson) => Some(export::open_stream(&args.path, &args.output, &naming, analyzer.writes())?),
+        _ => None,
+    };
+    if let Some(sink) = &stream {
+        analyzer = analyzer.with_stream(sink.sender());
+    }
     
     // Perform analysis
     let results = analyzer.analyze().await?;
@@ -37,17 +92,495 @@ async fn main() -> Result<()> {
     let duration = start_time.elapsed();
     
     // Generate and display report
-    reporter::generate_report(&results, &args, duration)?;
+    if args.print_empty_dirs {
+        print_empty_directories(&results)?;
+    } else if !streams_to_stdout {
+        reporter::generate_report(&results, &args, analyzer.config(), duration)?;
+    }
     
     // Export results if requested
     if let Some(export_format) = &args.export {
-        export::export_results(&results, export_format, &args.output)?;
-        println!("{} Results exported successfully!", "✓".green().bold());
+        match stream {
+            Some(sink) => export::finish_stream(&results, sink, &args.output, &naming, analyzer.writes())?,
+            None => export::export_results(&results, export_format, &args.output, &naming, args.bundle_shard_size, analyzer.writes())?,
+        }
+        if !stdout_taken {
+            println!("{} Results exported successfully!", "✓".green().bold());
+        }
+    }
+
+    if matches!(args.explain_exclusions, Some(None)) && !stdout_taken {
+        explain_exclusions(&results.diagnostics.exclusions);
+    }
+
+    if args.log_summary_to_syslog {
+        log_summary(&results, args.quiet);
+    }
+
+    if let Some(action) = args.dedupe {
+        // Files are only changed on the user's word, which Ctrl-C takes back
+        if results.scan_info.partial.is_some_and(|partial| partial.cap == CapKind::Interrupted) {
+            println!("{}", "⚠ The run was interrupted; --dedupe changes nothing.".yellow().bold());
+        } else {
+            dedupe(&results, &args, action, analyzer.writes())?;
+        }
+    }
+
+    if args.fail_on_errors && results.scan_errors.total > 0 {
+        anyhow::bail!("{} paths could not be read and --fail-on-errors is set", utils::format_count(results.scan_errors.total));
+    }
+    // A limit hit is a failure only when asked for; an interrupted run is not a limit
+    if let Some(partial) = results.scan_info.partial.filter(|partial| args.strict_limits && partial.cap != CapKind::Interrupted) {
+        anyhow::bail!("The run stopped at {} and --strict-limits is set; the results above are partial", partial.cap.flag());
     }
     
     Ok(())
 }
 
+/// Print the empty directories one per line, children before their parents so that `rmdir` can take them in order
+fn print_empty_directories(results: &diranalyzer::AnalysisResults) -> Result<()> {
+    if results.scan_info.partial.is_some() {
+        eprintln!("{}", "⚠ The scan is partial; no directory is listed as empty.".yellow().bold());
+    }
+    let mut directories: Vec<_> = results.empty_directories.iter().collect();
+    directories.sort_by_key(|path| std::cmp::Reverse(path.components().count()));
+    let mut out = std::io::stdout().lock();
+    for directory in directories {
+        writeln!(out, "{}", directory.display())?;
+    }
+    out.flush()?;
+    Ok(())
+}
+
+/// Print the cost estimate, and on a terminal ask whether to go on with the full run
+fn estimate(args: &Args) -> Result<bool> {
+    println!("{}", "⏱  Sampling the tree to estimate the run...".cyan().bold());
+    let estimate = Estimator::new(args)?.estimate()?;
+    for line in estimate.lines() {
+        println!("{}", line);
+    }
+
+    if !std::io::stdin().is_terminal() {
+        return Ok(false);
+    }
+    print!("Continue with the full run? [y/N] ");
+    std::io::stdout().flush()?;
+    let mut answer = String::new();
+    std::io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim(), "y" | "Y" | "yes"))
+}
+
+/// Exits with 0 when the run's configuration checks out and 1 with the findings otherwise
+fn check(args: &Args) -> ! {
+    println!("{}", "🔎 Checking the run without scanning...".cyan().bold());
+    let report = analyzer::check_run(args);
+    for finding in &report.findings {
+        println!("{} [{}] {}", "✗".red().bold(), finding.category.label(), finding.message);
+    }
+    if report.findings.is_empty() {
+        let files = match report.expected_files {
+            Some(files) if report.precount_stopped() => format!("over {}", utils::format_count(files)),
+            Some(files) => utils::format_count(files),
+            None => "no".to_string(),
+        };
+        println!("{} Ready to run: {} files to scan", "✓".green().bold(), files);
+    } else {
+        println!("{} Check failed with {} finding(s)", "⚠".yellow().bold(), report.findings.len());
+    }
+    std::process::exit(report.exit_code());
+}
+
+/// Exits with 0 when PATH is part of the results, 1 when the filters or the walk leave it out and 2 when it cannot be traced
+fn explain_path(args: &Args, path: &Path) -> ! {
+    let trace = match DirectoryAnalyzer::new(args.clone()).and_then(|analyzer| analyzer.explain_path(path)) {
+        Ok(trace) => trace,
+        Err(error) => {
+            eprintln!("{} {:#}", "✗".red().bold(), error);
+            std::process::exit(2);
+        }
+    };
+    println!("{} {}", "🔎 Tracing".cyan().bold(), trace.path.display());
+    for step in &trace.steps {
+        let kind = if step.directory { "dir " } else { "file" };
+        match &step.cause {
+            Some(cause) => println!("  {} {} {}  ({})", "✗".red(), kind, step.path.display(), cause),
+            None => println!("  {} {} {}", "✓".green(), kind, step.path.display()),
+        }
+    }
+    match &trace.outcome {
+        TraceOutcome::Included => println!("{} Included: no rule leaves it out", "✓".green().bold()),
+        TraceOutcome::Excluded { path, cause } if *path == trace.path => {
+            println!("{} Excluded by {}", "✗".red().bold(), cause)
+        }
+        TraceOutcome::Excluded { path, cause } => {
+            println!("{} Excluded: {} is pruned by {}, so nothing below it is walked", "✗".red().bold(), path.display(), cause)
+        }
+        TraceOutcome::BeyondDepth { path, limit } => {
+            println!("{} Not reached: {} lies below --depth {} (see --auto-depth)", "✗".red().bold(), path.display(), limit)
+        }
+        TraceOutcome::Link { path } => {
+            println!("{} Not reached: {} is a symbolic link (see --follow-links)", "✗".red().bold(), path.display())
+        }
+        TraceOutcome::AboveMinDepth { path, min_depth } => {
+            println!("{} Not collected: {} lies above --min-depth {}; only what is deeper counts", "✗".red().bold(), path.display(), min_depth)
+        }
+    }
+    std::process::exit(if trace.is_included() { 0 } else { 1 });
+}
+
+/// Print what each rule kept out of the run, in the order the filters apply
+fn explain_exclusions(exclusions: &ExclusionStats) {
+    println!("\n{}", "🔎 Exclusions by cause".cyan().bold());
+    if exclusions.causes.is_empty() {
+        println!("  Nothing was left out of {} entries", utils::format_count(exclusions.encountered));
+        return;
+    }
+    for count in &exclusions.causes {
+        println!(
+            "  {:>10} entries  {:>10}  {}",
+            utils::format_count(count.entries),
+            format_size(count.size, DECIMAL),
+            count.cause
+        );
+    }
+    println!(
+        "  {} of {} entries left out. A pruned directory counts once, and sizes cover skipped files only.",
+        utils::format_count(exclusions.causes.iter().map(|count| count.entries).sum()),
+        utils::format_count(exclusions.encountered)
+    );
+    println!("  Trace one path with --explain-exclusions=PATH.");
+}
+
+fn dedupe(results: &diranalyzer::AnalysisResults, args: &Args, action: actions::DedupeAction, writes: &WriteGuard) -> Result<()> {
+    let groups = results.duplicate_groups.as_deref()
+        .ok_or_else(|| anyhow::anyhow!("--dedupe needs duplicate groups: use --duplicates or --import-duplicates"))?;
+    let journal_path = args.journal.clone().unwrap_or_else(|| {
+        format!("diranalyzer-undo-{}.ndjson", chrono::Utc::now().format("%Y%m%d_%H%M%S")).into()
+    });
+
+    let mut journal = JournalWriter::create(&journal_path, writes)?;
+    let options = DedupeOptions { action, preserve_dir_mtime: args.preserve_dir_mtime };
+    let report = actions::dedupe(groups, options, &mut journal, writes)?;
+
+    println!("{} Deduplicated {} copies, reclaiming {}",
+        "✓".green().bold(), report.replaced, format_size(report.reclaimed_bytes, DECIMAL));
+    if args.verbose {
+        for (path, reason) in &report.skipped {
+            println!("  skipped {}: {}", path.display(), reason);
+        }
+    } else if !report.skipped.is_empty() {
+        println!("  {} copies skipped (use --verbose for details)", report.skipped.len());
+    }
+    println!("📝 Undo journal: {} (revert with `diranalyzer undo {}`)",
+        journal.path().display(), journal.path().display());
+    Ok(())
+}
+
+/// Send the run summary to the system log; failures only produce a warning
+#[cfg(feature = "syslog")]
+fn log_summary(results: &diranalyzer::AnalysisResults, quiet: bool) {
+    if let Err(error) = diranalyzer::syslog::log_summary(results) {
+        if !quiet {
+            eprintln!("⚠ Could not log summary: {:#}", error);
+        }
+    }
+}
+
+#[cfg(not(feature = "syslog"))]
+fn log_summary(_results: &diranalyzer::AnalysisResults, quiet: bool) {
+    if !quiet {
+        eprintln!("⚠ Could not log summary: built without the `syslog` feature");
+    }
+}
+
+async fn run_command(command: &Command) -> Result<()> {
+    match command {
+        Command::History(history_args) => show_history(history_args),
+        Command::Undo(undo_args) => undo(undo_args),
+        Command::MirrorCheck(mirror_args) => mirror_check(mirror_args).await,
+        Command::Cache(cache_args) => match &cache_args.command {
+            CacheCommand::Stats(stats_args) => cache_stats(stats_args),
+        },
+        Command::Compare(compare_args) => compare(compare_args),
+        Command::Note(note_args) => note(note_args),
+        Command::Accept(accept_args) => accept(accept_args),
+        Command::Report(report_args) => report(report_args).await,
+        Command::VerifyExport(verify_args) => verify_export(verify_args),
+        Command::SelfTest(self_test_args) => self_test(self_test_args),
+    }
+}
+
+/// Exits with 0 when the export matches its footer, 1 when it does not and 2 when it could not be read
+fn verify_export(args: &VerifyExportArgs) -> Result<()> {
+    let verification = match export::verify_export(&args.path) {
+        Ok(verification) => verification,
+        Err(error) => {
+            eprintln!("{} {:#}", "Error:".red().bold(), error);
+            std::process::exit(2);
+        }
+    };
+
+    for problem in &verification.problems {
+        println!("{} {}", "✗".red().bold(), problem);
+    }
+    let records: Vec<String> = verification.records.iter()
+        .map(|(kind, count)| format!("{} {}", utils::format_count(*count), kind))
+        .collect();
+    if verification.is_intact() {
+        println!("{} {} is an intact {} export: {}", "✓".green().bold(), args.path.display(), verification.format, records.join(", "));
+        Ok(())
+    } else {
+        println!("{} {} failed verification as a {} export", "⚠".yellow().bold(), args.path.display(), verification.format);
+        std::process::exit(1);
+    }
+}
+
+/// Exits with 0 when every run held its invariants and 1 otherwise
+fn self_test(args: &SelfTestArgs) -> Result<()> {
+    let self_test = SelfTest::new(args.seed).duration(args.duration);
+    println!("{} seed {}, for {}",
+        "🧪 Self-test:".cyan().bold(), self_test.seed(), utils::format_duration(args.duration));
+    let report = self_test.run()?;
+
+    println!("{} runs ({} with two at once) against {} changes to the tree in {}",
+        utils::format_count(report.runs),
+        utils::format_count(report.concurrent_runs),
+        utils::format_count(report.churn_operations),
+        utils::format_duration(report.elapsed)
+    );
+    for failure in &report.failures {
+        println!("{} run {} [{}]: {}", "✗".red().bold(), failure.run, failure.options, failure.problem);
+    }
+    let replay = format!("replay with `diranalyzer self-test --seed {}`", report.seed);
+    if report.passed() {
+        println!("{} Passed ({})", "✓".green().bold(), replay);
+    } else {
+        println!("{} Failed with {} problem(s) ({})", "⚠".yellow().bold(), report.failures.len(), replay);
+    }
+    std::process::exit(if report.passed() { 0 } else { 1 });
+}
+
+/// Report on a full snapshot again, under the given report-time filters
+async fn report(args: &ReportArgs) -> Result<()> {
+    let snapshot = export::load_results(&args.snapshot)?;
+
+    // The options of the snapshot's run that its listings depend on
+    let mut run_args = Args::parse_from(["diranalyzer", "--quiet", "--no-history"]);
+    run_args.path = snapshot.scan_info.path.clone();
+    run_args.top_count = args.top_count;
+    run_args.report_filters = args.filters.clone();
+    run_args.find_versions = snapshot.heuristic_versions.is_some();
+    run_args.hide_regenerable = snapshot.regenerable.hidden;
+    run_args.min_dir_size = snapshot.directory_filter.as_ref().map(|filter| filter.min_size);
+    let analyzer = DirectoryAnalyzer::new(run_args.clone())?;
+    let results = analyzer.refilter(&snapshot).await?;
+
+    run_args.quiet = false;
+    run_args.report_format = args.report_format.clone();
+    run_args.lang = args.lang;
+    run_args.full_report = args.full_report;
+    run_args.show_accepted = args.show_accepted;
+    let duration = std::time::Duration::from_millis(results.scan_info.scan_duration_ms);
+    reporter::generate_report(&results, &run_args, analyzer.config(), duration)?;
+
+    if let Some(format) = &args.export {
+        let naming = OutputNaming::from_args(&run_args);
+        export::export_results(&results, format, &args.output, &naming, run_args.bundle_shard_size, analyzer.writes())?;
+        println!("{} Results exported successfully!", "✓".green().bold());
+    }
+    Ok(())
+}
+
+fn note(args: &NoteArgs) -> Result<()> {
+    let store = match &args.file {
+        Some(path) => NoteStore::at(path.clone()),
+        None => NoteStore::for_current_user()
+            .ok_or_else(|| anyhow::anyhow!("Could not determine the config directory"))?,
+    };
+
+    match &args.command {
+        NoteCommand::Add(add) => {
+            let replaced = store.add(&add.path, &add.text)?;
+            let verb = if replaced.is_some() { "Replaced" } else { "Added" };
+            println!("{} note on {}", verb, add.path.display());
+        }
+        NoteCommand::Rm(rm) => match store.remove(&rm.path)? {
+            Some(_) => println!("Removed note on {}", rm.path.display()),
+            None => anyhow::bail!("No note on {}", rm.path.display()),
+        },
+        NoteCommand::List => {
+            let notes = store.list()?;
+            if notes.is_empty() {
+                println!("No notes in {}", store.path().display());
+                return Ok(());
+            }
+            println!("{:<17} {:<6} {:<40} Note", "Added", "State", "Path");
+            for listed in &notes {
+                println!("{:<17} {:<6} {:<40} {}",
+                    listed.note.added.format("%Y-%m-%d %H:%M"),
+                    if listed.stale { "stale" } else { "ok" },
+                    listed.path.display(),
+                    listed.note.text
+                );
+            }
+            let stale = notes.iter().filter(|listed| listed.stale).count();
+            if stale > 0 {
+                println!();
+                println!("{} of {} notes are stale: their paths no longer exist. Remove them with `note rm PATH`.", stale, notes.len());
+            }
+        }
+    }
+    Ok(())
+}
+
+fn accept(args: &AcceptArgs) -> Result<()> {
+    let store = match &args.file {
+        Some(path) => AcceptanceStore::at(path.clone()),
+        None => AcceptanceStore::for_current_user()
+            .ok_or_else(|| anyhow::anyhow!("Could not determine the config directory"))?,
+    };
+
+    match (&args.command, &args.target, &args.reason) {
+        (None, Some(target), Some(reason)) => {
+            let target = AcceptedTarget::parse(target);
+            let replaced = store.accept(&target, reason)?;
+            let verb = if replaced.is_some() { "Replaced the acceptance of" } else { "Accepted" };
+            println!("{} {}", verb, target);
+        }
+        (Some(AcceptCommand::Rm(rm)), _, _) => {
+            let target = AcceptedTarget::parse(&rm.target);
+            match store.revoke(&target)? {
+                Some(_) => println!("Withdrew the acceptance of {}", target),
+                None => anyhow::bail!("{} is not accepted", target),
+            }
+        }
+        (Some(AcceptCommand::List), _, _) => {
+            let accepted = store.list()?;
+            if accepted.is_empty() {
+                println!("No accepted findings in {}", store.path().display());
+                return Ok(());
+            }
+            println!("{:<17} {:<50} Reason", "Accepted", "Finding");
+            for (target, acceptance) in &accepted {
+                println!("{:<17} {:<50} {}", acceptance.accepted.format("%Y-%m-%d %H:%M"), target.to_string(), acceptance.reason);
+            }
+        }
+        _ => anyhow::bail!("Name a path or duplicate group id with --reason, or use `accept list` or `accept rm`"),
+    }
+    Ok(())
+}
+
+fn compare(args: &CompareArgs) -> Result<()> {
+    let before = export::load_results(&args.before)?;
+    let after = export::load_results(&args.after)?;
+    let width = args.width
+        .or_else(|| std::env::var("COLUMNS").ok()?.parse().ok())
+        .unwrap_or(100);
+
+    let messages = reporter::Messages::new(args.lang);
+    let mut out = std::io::stdout().lock();
+    reporter::comparison_renderer_for(&args.report_format, width)
+        .render(&reporter::build_comparison(&before, &after, args.top_count, &messages), &mut out)?;
+    out.flush()?;
+    Ok(())
+}
+
+/// Exits with 0 when the trees are identical, 1 when they differ and 2 when the check failed
+async fn mirror_check(args: &MirrorCheckArgs) -> Result<()> {
+    let check = MirrorCheck::new(args.exclude_patterns.clone())
+        .exclude_regexes(args.exclude_regexes.clone())
+        .show_hidden(args.show_hidden)
+        .paranoid(args.paranoid);
+    let diff = match check.compare(&args.source, &args.destination).await {
+        Ok(diff) => diff,
+        Err(error) => {
+            eprintln!("{} {:#}", "Error:".red().bold(), error);
+            std::process::exit(2);
+        }
+    };
+
+    let messages = reporter::Messages::new(args.lang);
+    let mut out = std::io::stdout().lock();
+    reporter::renderer_for(&args.report_format)
+        .render(&reporter::build_mirror_report(&diff, args.top_count, &messages), &mut out)?;
+    out.flush()?;
+
+    if let Some(path) = &args.output {
+        serde_json::to_writer_pretty(WriteGuard::unrestricted().create(path)?, &diff)?;
+    }
+    std::process::exit(if diff.is_identical() { 0 } else { 1 });
+}
+
+fn undo(args: &UndoArgs) -> Result<()> {
+    let report = actions::undo(&args.journal, &WriteGuard::unrestricted())?;
+    println!("{} Restored {} files", "✓".green().bold(), report.restored);
+    if !report.unrestorable.is_empty() {
+        println!("{}", format!("⚠ {} operations could not be undone:", report.unrestorable.len()).yellow().bold());
+        for (path, reason) in &report.unrestorable {
+            println!("  {}: {}", path.display(), reason);
+        }
+    }
+    Ok(())
+}
+
+fn show_history(args: &HistoryArgs) -> Result<()> {
+    let store = HistoryStore::for_root(&args.path, usize::MAX)
+        .ok_or_else(|| anyhow::anyhow!("Could not determine the cache directory"))?;
+    let records = store.load()?;
+
+    let mut output: Box<dyn Write> = match &args.output {
+        Some(path) => Box::new(WriteGuard::unrestricted().create(path)?),
+        None => Box::new(std::io::stdout()),
+    };
+
+    if args.csv {
+        return history::write_csv(&records, output);
+    }
+
+    if records.is_empty() {
+        writeln!(output, "No history recorded for {}", args.path.display())?;
+        return Ok(());
+    }
+
+    writeln!(output, "{:<20} {:>12} {:>12} {:>14}", "Timestamp", "Total Size", "Files", "Dup. Waste")?;
+    for record in &records {
+        writeln!(output, "{:<20} {:>12} {:>12} {:>14}",
+            record.timestamp.format("%Y-%m-%d %H:%M:%S"),
+            format_size(record.total_size, DECIMAL),
+            record.total_files,
+            format_size(record.duplicate_waste, DECIMAL)
+        )?;
+    }
+    Ok(())
+}
+
+fn cache_stats(args: &CacheStatsArgs) -> Result<()> {
+    let dir = args.dir.clone().or_else(HashCache::default_dir)
+        .ok_or_else(|| anyhow::anyhow!("Could not determine the cache directory"))?;
+    let summary = hash_cache::summarize(&dir);
+    println!("Hash cache: {}", dir.display());
+    println!("  {} entries in {} segments ({})", summary.entries, summary.segments, format_size(summary.bytes, DECIMAL));
+
+    let runs = hash_cache::load_runs(&dir)?;
+    if runs.is_empty() {
+        println!("No runs recorded");
+        return Ok(());
+    }
+    println!();
+    println!("{:<20} {:>10} {:>10} {:>9} {:>10}  Root", "Timestamp", "Lookups", "Hits", "Hit Rate", "Stored");
+    for run in &runs[runs.len().saturating_sub(args.runs)..] {
+        println!("{:<20} {:>10} {:>10} {:>8.1}% {:>10}  {}",
+            run.timestamp.format("%Y-%m-%d %H:%M:%S"),
+            run.usage.lookups,
+            run.usage.hits,
+            run.usage.hit_rate() * 100.0,
+            run.usage.stored,
+            run.root.display()
+        );
+    }
+    Ok(())
+}
+
 fn print_banner() {
     println!("{}", "
 ██████╗ ██╗██████╗  █████╗ ███╗   ██╗ █████╗ ██╗  ██╗   ██╗███████╗███████╗██████╗ 
@@ -0,0 +1,725 @@
+//! Custom metrics defined in the configuration file
+//!
+//! A metric is a named expression over what the analysis pass collected:
+//! `size(...)` and `count(...)` of the files matching globs and filters, the
+//! run's totals, metrics defined before it, arithmetic, and `percent(a, b)`.
+//! Expressions are parsed and checked when the analyzer is created, so a
+//! misspelled name or a sum of bytes and counts fails the run before the
+//! scan. They can name nothing beyond these figures.
+//!
+//! Every unit is tracked: bytes and counts add to their own kind, a plain
+//! number scales either, and dividing by a count gives a value per file,
+//! such as `total_size / total_files`. A division by zero leaves the metric
+//! without a value rather than failing the run.
+//!
+//! The `size()` and `count()` selectors of all metrics are tallied during the
+//! analysis pass, the way the classification rules count their hits: each
+//! file is matched against every glob at once and added to each selector it
+//! passes, so no metric walks the files again.
+
+mod parse;
+
+pub use parse::SyntaxError;
+
+use crate::config::MetricDefinition;
+use crate::scanner::FileEntry;
+use crate::utils::{self, glob_to_regex, FileTypeClassifier, TypeId};
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, TimeDelta, Utc};
+use parse::{Argument, Node, NodeKind, Operator};
+use regex::RegexSet;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::time::Duration;
+
+/// What a metric's value measures
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MetricUnit {
+    Bytes,
+    Count,
+    /// A percentage (0-100), from `percent()`
+    Percent,
+    Number,
+}
+
+impl MetricUnit {
+    fn noun(self) -> &'static str {
+        match self {
+            MetricUnit::Bytes => "bytes",
+            MetricUnit::Count => "a count",
+            MetricUnit::Percent => "a percentage",
+            MetricUnit::Number => "a plain number",
+        }
+    }
+}
+
+/// A custom metric evaluated over a run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CustomMetric {
+    pub name: String,
+    pub expression: String,
+    pub unit: MetricUnit,
+    /// `None` when the expression divides by zero
+    pub value: Option<f64>,
+}
+
+/// The run totals metrics may name
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MetricInputs {
+    pub total_size: u64,
+    pub total_files: u64,
+    pub total_directories: u64,
+    pub reclaimable_bytes: u64,
+    pub regenerable_size: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Variable {
+    TotalSize,
+    TotalFiles,
+    TotalDirectories,
+    ReclaimableBytes,
+    RegenerableSize,
+}
+
+impl Variable {
+    const ALL: [Variable; 5] = [
+        Variable::TotalSize,
+        Variable::TotalFiles,
+        Variable::TotalDirectories,
+        Variable::ReclaimableBytes,
+        Variable::RegenerableSize,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Variable::TotalSize => "total_size",
+            Variable::TotalFiles => "total_files",
+            Variable::TotalDirectories => "total_directories",
+            Variable::ReclaimableBytes => "reclaimable_bytes",
+            Variable::RegenerableSize => "regenerable_size",
+        }
+    }
+
+    fn unit(self) -> MetricUnit {
+        match self {
+            Variable::TotalSize | Variable::ReclaimableBytes | Variable::RegenerableSize => MetricUnit::Bytes,
+            Variable::TotalFiles | Variable::TotalDirectories => MetricUnit::Count,
+        }
+    }
+
+    fn value(self, inputs: &MetricInputs) -> u64 {
+        match self {
+            Variable::TotalSize => inputs.total_size,
+            Variable::TotalFiles => inputs.total_files,
+            Variable::TotalDirectories => inputs.total_directories,
+            Variable::ReclaimableBytes => inputs.reclaimable_bytes,
+            Variable::RegenerableSize => inputs.regenerable_size,
+        }
+    }
+}
+
+/// Functions an expression may call
+const FUNCTIONS: &[&str] = &["size", "count", "percent"];
+
+/// The files a `size()` or `count()` covers: those matching any of its globs and every other filter
+#[derive(Debug, Clone, Default, PartialEq)]
+struct Selector {
+    /// Indexes into the set's globs; empty matches every path
+    globs: Vec<usize>,
+    file_type: Option<TypeId>,
+    older: Option<Duration>,
+    newer: Option<Duration>,
+    larger: Option<u64>,
+    smaller: Option<u64>,
+}
+
+/// A checked expression
+#[derive(Debug, Clone)]
+enum Expr {
+    Constant(f64),
+    Variable(Variable),
+    /// A metric defined before, by index
+    Metric(usize),
+    /// Bytes of the files of a selector, by index
+    Size(usize),
+    /// Files of a selector, by index
+    Count(usize),
+    Percent(Box<Expr>, Box<Expr>),
+    Negate(Box<Expr>),
+    Binary(Operator, Box<Expr>, Box<Expr>),
+}
+
+#[derive(Debug, Clone)]
+struct CompiledMetric {
+    name: String,
+    expression: String,
+    unit: MetricUnit,
+    expr: Expr,
+}
+
+/// The custom metrics of a configuration, ready to tally and evaluate
+#[derive(Debug, Clone)]
+pub struct MetricSet {
+    metrics: Vec<CompiledMetric>,
+    selectors: Vec<Selector>,
+    globs: RegexSet,
+}
+
+impl Default for MetricSet {
+    fn default() -> Self {
+        Self { metrics: Vec::new(), selectors: Vec::new(), globs: RegexSet::empty() }
+    }
+}
+
+/// What one analysis pass counted for each selector of a [`MetricSet`]
+///
+/// The default tally counted nothing: metrics reading it have no value.
+#[derive(Debug, Clone, Default)]
+pub struct MetricTally {
+    /// Per selector, files modified at or before this are older than its `older:`
+    older_cutoffs: Vec<Option<DateTime<Utc>>>,
+    /// Per selector, files modified after this are newer than its `newer:`
+    newer_cutoffs: Vec<Option<DateTime<Utc>>>,
+    sizes: Vec<u64>,
+    counts: Vec<u64>,
+}
+
+impl MetricSet {
+    /// Parse and check `definitions`, resolving type names with `classifier`
+    pub fn compile(definitions: &[MetricDefinition], classifier: &FileTypeClassifier) -> Result<Self> {
+        let mut compiler = Compiler { classifier, globs: Vec::new(), selectors: Vec::new(), metrics: Vec::new() };
+        for definition in definitions {
+            compiler.check_name(&definition.name)?;
+            let (expr, unit) = parse::parse(&definition.expression)
+                .and_then(|node| compiler.compile(&node))
+                .map_err(|error| anyhow::anyhow!(describe(definition, &error)))?;
+            compiler.metrics.push(CompiledMetric {
+                name: definition.name.clone(),
+                expression: definition.expression.clone(),
+                unit,
+                expr,
+            });
+        }
+        let patterns = compiler
+            .globs
+            .iter()
+            .map(|glob| glob_to_regex(glob).with_context(|| format!("Invalid custom metric glob {:?}", glob)))
+            .collect::<Result<Vec<_>>>()?;
+        let globs = RegexSet::new(&patterns).context("Invalid custom metric glob")?;
+        Ok(Self { metrics: compiler.metrics, selectors: compiler.selectors, globs })
+    }
+
+    /// An empty tally for a pass measuring ages from `ages_from`
+    pub fn tally(&self, ages_from: DateTime<Utc>) -> MetricTally {
+        let cutoff = |age: Option<Duration>| {
+            age.map(|age| TimeDelta::from_std(age).ok().and_then(|age| ages_from.checked_sub_signed(age)).unwrap_or(DateTime::<Utc>::MIN_UTC))
+        };
+        MetricTally {
+            older_cutoffs: self.selectors.iter().map(|selector| cutoff(selector.older)).collect(),
+            newer_cutoffs: self.selectors.iter().map(|selector| cutoff(selector.newer)).collect(),
+            sizes: vec![0; self.selectors.len()],
+            counts: vec![0; self.selectors.len()],
+        }
+    }
+
+    /// Add `file`, classified as `type_id`, to every selector it passes
+    pub fn add(&self, tally: &mut MetricTally, file: &FileEntry, type_id: TypeId) {
+        if self.selectors.is_empty() {
+            return;
+        }
+        let matched = (!self.globs.is_empty()).then(|| {
+            let path = file.path.to_string_lossy();
+            let path = match std::path::MAIN_SEPARATOR {
+                '/' => path,
+                separator => Cow::Owned(path.replace(separator, "/")),
+            };
+            self.globs.matches(&path)
+        });
+        for (index, selector) in self.selectors.iter().enumerate() {
+            let globbed = selector.globs.is_empty()
+                || matched.as_ref().is_some_and(|matched| selector.globs.iter().any(|&glob| matched.matched(glob)));
+            if !globbed
+                || selector.file_type.is_some_and(|id| id != type_id)
+                || selector.larger.is_some_and(|size| file.counted_size() < size)
+                || selector.smaller.is_some_and(|size| file.counted_size() >= size)
+            {
+                continue;
+            }
+            let (older, newer) = (tally.older_cutoffs[index], tally.newer_cutoffs[index]);
+            if older.is_some() || newer.is_some() {
+                let Some(modified) = file.modified else { continue };
+                if older.is_some_and(|cutoff| modified > cutoff) || newer.is_some_and(|cutoff| modified <= cutoff) {
+                    continue;
+                }
+            }
+            tally.sizes[index] += file.counted_size();
+            tally.counts[index] += 1;
+        }
+    }
+
+    /// Every metric's value over `tally` and the run's totals, in the order they were defined
+    pub fn evaluate(&self, tally: &MetricTally, inputs: &MetricInputs) -> Vec<CustomMetric> {
+        let mut values: Vec<Option<f64>> = Vec::with_capacity(self.metrics.len());
+        for metric in &self.metrics {
+            let value = evaluate(&metric.expr, tally, inputs, &values).filter(|value| value.is_finite());
+            values.push(value);
+        }
+        self.metrics
+            .iter()
+            .zip(values)
+            .map(|(metric, value)| CustomMetric {
+                name: metric.name.clone(),
+                expression: metric.expression.clone(),
+                unit: metric.unit,
+                value,
+            })
+            .collect()
+    }
+}
+
+fn evaluate(expr: &Expr, tally: &MetricTally, inputs: &MetricInputs, metrics: &[Option<f64>]) -> Option<f64> {
+    let value = |expr| evaluate(expr, tally, inputs, metrics);
+    match expr {
+        Expr::Constant(constant) => Some(*constant),
+        Expr::Variable(variable) => Some(variable.value(inputs) as f64),
+        Expr::Metric(index) => metrics[*index],
+        Expr::Size(index) => tally.sizes.get(*index).map(|&size| size as f64),
+        Expr::Count(index) => tally.counts.get(*index).map(|&count| count as f64),
+        Expr::Percent(part, whole) => {
+            let whole = value(whole)?;
+            (whole != 0.0).then_some(value(part)? / whole * 100.0)
+        }
+        Expr::Negate(operand) => Some(-value(operand)?),
+        Expr::Binary(operator, left, right) => {
+            let (left, right) = (value(left)?, value(right)?);
+            match operator {
+                Operator::Add => Some(left + right),
+                Operator::Subtract => Some(left - right),
+                Operator::Multiply => Some(left * right),
+                Operator::Divide => (right != 0.0).then_some(left / right),
+            }
+        }
+    }
+}
+
+/// The error of `definition` with the expression beneath it and a caret at the column
+fn describe(definition: &MetricDefinition, error: &SyntaxError) -> String {
+    format!(
+        "Invalid custom metric \"{}\" at column {}: {}\n  {}\n  {}^",
+        definition.name,
+        error.column,
+        error.message,
+        definition.expression,
+        " ".repeat(error.column - 1)
+    )
+}
+
+/// The unit of `left operator right`, or why the two do not combine
+fn combine(operator: Operator, left: MetricUnit, right: MetricUnit) -> Result<MetricUnit, String> {
+    use MetricUnit::{Count, Number};
+    match operator {
+        Operator::Add | Operator::Subtract => match (left, right) {
+            (left, right) if left == right => Ok(left),
+            (Number, unit) | (unit, Number) => Ok(unit),
+            _ if operator == Operator::Add => Err(format!("cannot add {} and {}", left.noun(), right.noun())),
+            _ => Err(format!("cannot subtract {} from {}", right.noun(), left.noun())),
+        },
+        Operator::Multiply => match (left, right) {
+            (Number, unit) | (unit, Number) => Ok(unit),
+            _ => Err(format!("cannot multiply {} by {}", left.noun(), right.noun())),
+        },
+        Operator::Divide => match (left, right) {
+            (unit, Number) => Ok(unit),
+            (left, right) if left == right => Ok(Number),
+            // Per file, such as the average file size
+            (unit, Count) => Ok(unit),
+            _ => Err(format!("cannot divide {} by {}", left.noun(), right.noun())),
+        },
+    }
+}
+
+struct Compiler<'a> {
+    classifier: &'a FileTypeClassifier,
+    globs: Vec<String>,
+    selectors: Vec<Selector>,
+    metrics: Vec<CompiledMetric>,
+}
+
+impl Compiler<'_> {
+    fn check_name(&self, name: &str) -> Result<()> {
+        let valid = name.chars().next().is_some_and(|c| c.is_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_alphanumeric() || c == '_');
+        if !valid {
+            bail!("Invalid custom metric name \"{}\": use letters, digits, and underscores, not starting with a digit", name);
+        }
+        if Variable::ALL.iter().any(|variable| variable.name() == name) || FUNCTIONS.contains(&name) {
+            bail!("Invalid custom metric name \"{}\": it is a built-in name", name);
+        }
+        if self.metrics.iter().any(|metric| metric.name == name) {
+            bail!("Invalid custom metric name \"{}\": it is defined twice", name);
+        }
+        Ok(())
+    }
+
+    fn compile(&mut self, node: &Node) -> Result<(Expr, MetricUnit), SyntaxError> {
+        let error = |message: String| SyntaxError { column: node.column, message };
+        match &node.kind {
+            NodeKind::Number(number) => {
+                if let Ok(value) = number.parse::<f64>() {
+                    return Ok((Expr::Constant(value), MetricUnit::Number));
+                }
+                let size = utils::parse_size(number).map_err(error)?;
+                Ok((Expr::Constant(size as f64), MetricUnit::Bytes))
+            }
+            NodeKind::Name(name) => {
+                if let Some(variable) = Variable::ALL.into_iter().find(|variable| variable.name() == name) {
+                    return Ok((Expr::Variable(variable), variable.unit()));
+                }
+                if let Some(index) = self.metrics.iter().position(|metric| &metric.name == name) {
+                    return Ok((Expr::Metric(index), self.metrics[index].unit));
+                }
+                if FUNCTIONS.contains(&name.as_str()) {
+                    return Err(error(format!("'{}' is a function; call it as {}(...)", name, name)));
+                }
+                let variables: Vec<&str> = Variable::ALL.iter().map(|variable| variable.name()).collect();
+                Err(error(format!("unknown name '{}'; expected {}, or a metric defined above", name, variables.join(", "))))
+            }
+            NodeKind::Negate(operand) => {
+                let (operand, unit) = self.compile(operand)?;
+                Ok((Expr::Negate(Box::new(operand)), unit))
+            }
+            NodeKind::Binary { operator, left, right } => {
+                let (left, left_unit) = self.compile(left)?;
+                let (right, right_unit) = self.compile(right)?;
+                let unit = combine(*operator, left_unit, right_unit).map_err(error)?;
+                Ok((Expr::Binary(*operator, Box::new(left), Box::new(right)), unit))
+            }
+            NodeKind::Call { function, arguments } => match function.as_str() {
+                "size" => Ok((Expr::Size(self.selector(function, arguments, node.column)?), MetricUnit::Bytes)),
+                "count" => Ok((Expr::Count(self.selector(function, arguments, node.column)?), MetricUnit::Count)),
+                "percent" => {
+                    let [Argument::Value(part), Argument::Value(whole)] = arguments.as_slice() else {
+                        return Err(error("percent() takes two values, such as percent(size(type:Images), total_size)".to_string()));
+                    };
+                    let (part, part_unit) = self.compile(part)?;
+                    let (whole, whole_unit) = self.compile(whole)?;
+                    if part_unit != whole_unit {
+                        return Err(error(format!(
+                            "percent() needs two values of the same unit, not {} and {}",
+                            part_unit.noun(),
+                            whole_unit.noun()
+                        )));
+                    }
+                    Ok((Expr::Percent(Box::new(part), Box::new(whole)), MetricUnit::Percent))
+                }
+                _ => Err(error(format!("unknown function '{}'; expected {}", function, FUNCTIONS.join(", ")))),
+            },
+        }
+    }
+
+    /// The index of the selector `arguments` describe, shared with every identical one
+    fn selector(&mut self, function: &str, arguments: &[Argument], column: usize) -> Result<usize, SyntaxError> {
+        let mut selector = Selector::default();
+        for argument in arguments {
+            match argument {
+                Argument::Glob { pattern, .. } => {
+                    let index = self.globs.iter().position(|glob| glob == pattern).unwrap_or_else(|| {
+                        self.globs.push(pattern.clone());
+                        self.globs.len() - 1
+                    });
+                    if !selector.globs.contains(&index) {
+                        selector.globs.push(index);
+                    }
+                }
+                Argument::Filter { column, key, value_column, value } => {
+                    let at_value = |message: String| SyntaxError { column: *value_column, message };
+                    let given_twice = match key.as_str() {
+                        "type" => {
+                            let id = self
+                                .classifier
+                                .types()
+                                .find(|(_, name)| name.eq_ignore_ascii_case(value))
+                                .map(|(id, _)| id)
+                                .ok_or_else(|| {
+                                    let names: Vec<&str> = self.classifier.types().map(|(_, name)| name).collect();
+                                    at_value(format!("unknown file type '{}'; expected one of {}", value, names.join(", ")))
+                                })?;
+                            selector.file_type.replace(id).is_some()
+                        }
+                        "older" => selector.older.replace(utils::parse_duration(value).map_err(at_value)?).is_some(),
+                        "newer" => selector.newer.replace(utils::parse_duration(value).map_err(at_value)?).is_some(),
+                        "larger" => selector.larger.replace(utils::parse_size(value).map_err(at_value)?).is_some(),
+                        "smaller" => selector.smaller.replace(utils::parse_size(value).map_err(at_value)?).is_some(),
+                        _ => {
+                            return Err(SyntaxError {
+                                column: *column,
+                                message: format!("unknown filter '{}:'; expected type:, older:, newer:, larger:, or smaller:", key),
+                            })
+                        }
+                    };
+                    if given_twice {
+                        return Err(SyntaxError { column: *column, message: format!("'{}:' is given twice", key) });
+                    }
+                }
+                Argument::Value(_) => {
+                    return Err(SyntaxError {
+                        column,
+                        message: format!("{}() takes quoted globs and filters such as type:Images, not values", function),
+                    })
+                }
+            }
+        }
+        selector.globs.sort_unstable();
+        Ok(self.selectors.iter().position(|existing| existing == &selector).unwrap_or_else(|| {
+            self.selectors.push(selector);
+            self.selectors.len() - 1
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ClassificationRule;
+    use chrono::TimeZone;
+    use std::path::PathBuf;
+
+    fn definitions(metrics: &[(&str, &str)]) -> Vec<MetricDefinition> {
+        metrics
+            .iter()
+            .map(|(name, expression)| MetricDefinition { name: name.to_string(), expression: expression.to_string() })
+            .collect()
+    }
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 6, 1, 0, 0, 0).unwrap()
+    }
+
+    fn file(path: &str, size: u64, days_old: i64) -> FileEntry {
+        FileEntry {
+            path: PathBuf::from(path),
+            size,
+            modified: Some(now() - TimeDelta::days(days_old)),
+            depth: path.matches('/').count(),
+            ..Default::default()
+        }
+    }
+
+    /// A small home directory: photos, a video, caches, and a build tree
+    fn files() -> Vec<FileEntry> {
+        vec![
+            file("/home/ann/Pictures/a.jpg", 3_000, 400),
+            file("/home/ann/Pictures/b.png", 2_000, 10),
+            file("/home/ann/Videos/trip.mp4", 50_000, 30),
+            file("/home/ann/.cache/pip/wheel.whl", 8_000, 90),
+            file("/home/ann/.cache/thumbs/a.png", 500, 1),
+            file("/home/ann/src/app/main.rs", 1_000, 2),
+            file("/home/ann/src/app/target/debug/app", 20_000, 2),
+            FileEntry { modified: None, ..file("/home/ann/notes.txt", 500, 0) },
+        ]
+    }
+
+    fn inputs(files: &[FileEntry]) -> MetricInputs {
+        MetricInputs {
+            total_size: files.iter().map(|file| file.size).sum(),
+            total_files: files.len() as u64,
+            total_directories: 10,
+            reclaimable_bytes: 4_000,
+            regenerable_size: 28_000,
+        }
+    }
+
+    /// The values of `metrics` over [`files`], classified by the built-in rules
+    fn evaluate_all(metrics: &[(&str, &str)]) -> Vec<Option<f64>> {
+        let classifier = FileTypeClassifier::new();
+        let set = MetricSet::compile(&definitions(metrics), &classifier).unwrap_or_else(|error| panic!("{:#}", error));
+        let files = files();
+        let mut tally = set.tally(now());
+        for file in &files {
+            set.add(&mut tally, file, classifier.classify_id(&file.path));
+        }
+        set.evaluate(&tally, &inputs(&files)).into_iter().map(|metric| metric.value).collect()
+    }
+
+    fn value(expression: &str) -> Option<f64> {
+        evaluate_all(&[("metric", expression)])[0]
+    }
+
+    fn compile_error(expression: &str) -> String {
+        let error = MetricSet::compile(&definitions(&[("metric", expression)]), &FileTypeClassifier::new()).expect_err(expression);
+        format!("{:#}", error)
+    }
+
+    #[test]
+    fn test_sizes_and_counts_by_glob() {
+        assert_eq!(value(r#"size("**/.cache/**")"#), Some(8_500.0));
+        assert_eq!(value("count('*.png')"), Some(2.0));
+        assert_eq!(value("size('/home/ann/src/**')"), Some(21_000.0));
+        // Globs anchored at the root do not match deeper
+        assert_eq!(value("count('/Pictures/**')"), Some(0.0));
+        // A file counts once, whichever of the globs it matches
+        assert_eq!(value("count('*.png', '**/.cache/**')"), Some(3.0));
+        assert_eq!(value("size()"), Some(85_000.0));
+    }
+
+    #[test]
+    fn test_sizes_and_counts_by_type() {
+        assert_eq!(value("size(type:Images) + size(type:Videos)"), Some(55_000.0));
+        assert_eq!(value("count(type:Cache)"), Some(2.0));
+        // Type names ignore case and may be quoted
+        assert_eq!(value("count(type:images)"), Some(2.0), "the thumbnail is in the cache");
+        assert_eq!(value("count(type:\"Other\")"), Some(1.0));
+    }
+
+    #[test]
+    fn test_configured_categories_can_be_selected() {
+        let rule = ClassificationRule { path: "src/**".to_string(), extensions: Vec::new(), category: "Projects".to_string() };
+        let classifier = FileTypeClassifier::new().with_rules(&[rule]).unwrap();
+        let set = MetricSet::compile(&definitions(&[("projects", "size(type:Projects)")]), &classifier).unwrap();
+        let mut tally = set.tally(now());
+        for file in &files() {
+            set.add(&mut tally, file, classifier.classify_id(&file.path));
+        }
+        assert_eq!(set.evaluate(&tally, &MetricInputs::default())[0].value, Some(21_000.0));
+    }
+
+    #[test]
+    fn test_age_and_size_filters() {
+        assert_eq!(value("count(older:30d)"), Some(3.0), "30 days old counts as older; no time never does");
+        assert_eq!(value("count(newer:30d)"), Some(4.0));
+        assert_eq!(value("count(older:1y)"), Some(1.0));
+        assert_eq!(value("size(larger:8kB)"), Some(78_000.0));
+        assert_eq!(value("count(smaller:1000)"), Some(2.0));
+        assert_eq!(value("count(type:Images, older:30d)"), Some(1.0));
+        assert_eq!(value("count('**/Pictures/**', newer:1w, larger:1k)"), Some(0.0));
+        assert_eq!(value("count('**/Pictures/**', newer:2w, larger:1k)"), Some(1.0));
+    }
+
+    #[test]
+    fn test_arithmetic_and_totals() {
+        assert_eq!(value("total_size / total_files"), Some(10_625.0));
+        assert_eq!(value("total_size - regenerable_size - reclaimable_bytes"), Some(53_000.0));
+        assert_eq!(value("(1 + 2) * 3 - 4 / 2"), Some(7.0));
+        assert_eq!(value("-total_directories + 1"), Some(-9.0));
+        assert_eq!(value("2 * 1kB"), Some(2_000.0));
+        assert_eq!(value("1KiB + 1"), Some(1_025.0));
+        assert_eq!(value("size(type:Videos) / size()"), Some(50_000.0 / 85_000.0));
+    }
+
+    #[test]
+    fn test_percentages() {
+        assert_eq!(value("percent(size(type:Videos), total_size)").map(|p| (p * 100.0).round() / 100.0), Some(58.82));
+        assert_eq!(value("percent(count(type:Cache), total_files)"), Some(25.0));
+        assert_eq!(value("100 - percent(regenerable_size, 28000 * 1B)"), Some(0.0));
+    }
+
+    #[test]
+    fn test_division_by_zero_leaves_no_value() {
+        assert_eq!(value("total_size / count('*.iso')"), None);
+        assert_eq!(value("percent(size('*.iso'), size('*.iso'))"), None);
+        assert_eq!(value("1 / (total_files - 8)"), None);
+        // And neither has a metric built on it
+        assert_eq!(evaluate_all(&[("empty", "size('*.iso') / count('*.iso')"), ("twice", "empty * 2")]), [None, None]);
+    }
+
+    #[test]
+    fn test_metrics_name_earlier_metrics() {
+        let values = evaluate_all(&[
+            ("media", "size(type:Images) + size(type:Videos)"),
+            ("media_share", "percent(media, total_size)"),
+            ("other", "total_size - media"),
+        ]);
+        assert_eq!(values[0], Some(55_000.0));
+        assert_eq!(values[2], Some(30_000.0));
+        let set = MetricSet::compile(&definitions(&[("media", "size(type:Images)"), ("share", "percent(media, total_size)")]), &FileTypeClassifier::new()).unwrap();
+        let units: Vec<MetricUnit> = set.evaluate(&set.tally(now()), &MetricInputs::default()).iter().map(|metric| metric.unit).collect();
+        assert_eq!(units, [MetricUnit::Bytes, MetricUnit::Percent]);
+    }
+
+    #[test]
+    fn test_units_follow_the_arithmetic() {
+        let unit = |expression: &str| {
+            let set = MetricSet::compile(&definitions(&[("metric", expression)]), &FileTypeClassifier::new()).unwrap();
+            set.metrics[0].unit
+        };
+        assert_eq!(unit("total_size / total_files"), MetricUnit::Bytes);
+        assert_eq!(unit("count(type:Images) * 2"), MetricUnit::Count);
+        assert_eq!(unit("total_size / size(type:Images)"), MetricUnit::Number);
+        assert_eq!(unit("total_files / total_directories"), MetricUnit::Number);
+        assert_eq!(unit("1 + total_size"), MetricUnit::Bytes);
+        assert_eq!(unit("10GB"), MetricUnit::Bytes);
+        assert_eq!(unit("10"), MetricUnit::Number);
+        assert_eq!(unit("percent(1, 3) / 2"), MetricUnit::Percent);
+    }
+
+    #[test]
+    fn test_selectors_and_globs_are_shared() {
+        let set = MetricSet::compile(
+            &definitions(&[
+                ("a", "size('*.png', type:Images)"),
+                ("b", "count(type:images, '*.png')"),
+                ("c", "size('*.png') + size('*.jpg', '*.png')"),
+            ]),
+            &FileTypeClassifier::new(),
+        )
+        .unwrap();
+        assert_eq!(set.globs.len(), 2);
+        assert_eq!(set.selectors.len(), 3);
+    }
+
+    #[test]
+    fn test_errors_point_at_the_column() {
+        let cases = [
+            ("size(type:Imagez)", "at column 11: unknown file type 'Imagez'; expected one of Other, Documents, Images, Videos"),
+            ("total_size + total_files", "at column 12: cannot add bytes and a count"),
+            ("total_files - 1kB", "at column 13: cannot subtract bytes from a count"),
+            ("total_size * total_size", "at column 12: cannot multiply bytes by bytes"),
+            ("1 / total_size", "at column 3: cannot divide a plain number by bytes"),
+            ("percent(total_size, total_files)", "at column 1: percent() needs two values of the same unit, not bytes and a count"),
+            ("percent(total_size)", "at column 1: percent() takes two values"),
+            ("percent('*.png', total_size)", "at column 1: percent() takes two values"),
+            ("size(total_size)", "at column 1: size() takes quoted globs and filters such as type:Images, not values"),
+            ("size(colour:red)", "at column 6: unknown filter 'colour:'"),
+            ("size(older:soon)", "at column 12: invalid duration 'soon'"),
+            ("size(larger:5 * 2)", "at column 15: expected ')' to close the '(' at column 5, found '*'"),
+            ("count(larger:3 parsecs)", "at column 16: expected ')'"),
+            ("size(larger:3pc)", "at column 13: invalid size unit 'pc' in '3pc'"),
+            ("size(type:Images, type:Videos)", "at column 19: 'type:' is given twice"),
+            ("total_bytes", "at column 1: unknown name 'total_bytes'; expected total_size, total_files"),
+            ("metric * 2", "at column 1: unknown name 'metric'"),
+            ("size + 1", "at column 1: 'size' is a function; call it as size(...)"),
+            ("median(total_size)", "at column 1: unknown function 'median'; expected size, count, percent"),
+            ("3apples", "at column 1: invalid size unit 'apples' in '3apples'"),
+            ("3 apples", "at column 3: expected an operator, found 'apples'"),
+            ("(total_size", "at column 12: expected ')' to close the '(' at column 1"),
+        ];
+        for (expression, expected) in cases {
+            let message = compile_error(expression);
+            assert!(message.starts_with("Invalid custom metric \"metric\" "), "{}", message);
+            assert!(message.contains(expected), "{:?}: {}", expression, message);
+        }
+    }
+
+    #[test]
+    fn test_errors_show_the_expression_with_a_caret() {
+        assert_eq!(
+            compile_error("total_size + total_files"),
+            "Invalid custom metric \"metric\" at column 12: cannot add bytes and a count\n  total_size + total_files\n             ^"
+        );
+    }
+
+    #[test]
+    fn test_names_are_checked() {
+        let error = |metrics: &[(&str, &str)]| format!("{:#}", MetricSet::compile(&definitions(metrics), &FileTypeClassifier::new()).unwrap_err());
+        assert!(error(&[("cache ratio", "1")]).contains("letters, digits, and underscores"));
+        assert!(error(&[("2x", "1")]).contains("letters, digits, and underscores"));
+        assert!(error(&[("total_size", "1")]).contains("built-in name"));
+        assert!(error(&[("count", "1")]).contains("built-in name"));
+        assert!(error(&[("a", "1"), ("a", "2")]).contains("defined twice"));
+        // A metric only sees those defined before it, so none can refer to itself
+        assert!(error(&[("a", "a + 1")]).contains("unknown name 'a'"));
+        assert!(error(&[("a", "b"), ("b", "1")]).contains("unknown name 'b'"));
+    }
+}
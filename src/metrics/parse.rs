@@ -0,0 +1,488 @@
+//! Parser of custom metric expressions
+//!
+//! The grammar is small and closed: numbers, names, calls, the four
+//! arithmetic operators, and parentheses. Nothing is looked up or evaluated
+//! here; [`MetricSet::compile`](super::MetricSet::compile) resolves the
+//! tree and checks its units.
+//!
+//! ```text
+//! expression := term (("+" | "-") term)*
+//! term       := unary (("*" | "/") unary)*
+//! unary      := "-" unary | primary
+//! primary    := NUMBER | NAME | NAME "(" arguments? ")" | "(" expression ")"
+//! arguments  := argument ("," argument)*
+//! argument   := STRING | NAME ":" (NUMBER | NAME | STRING) | expression
+//! ```
+//!
+//! Columns count characters from 1.
+
+use std::fmt;
+
+/// Parentheses and signs nested deeper than this are refused rather than recursed into
+const MAX_DEPTH: usize = 64;
+
+/// What is wrong with an expression, and where
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntaxError {
+    pub column: usize,
+    pub message: String,
+}
+
+impl SyntaxError {
+    fn new(column: usize, message: impl Into<String>) -> Self {
+        Self { column, message: message.into() }
+    }
+}
+
+impl fmt::Display for SyntaxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "column {}: {}", self.column, self.message)
+    }
+}
+
+impl std::error::Error for SyntaxError {}
+
+/// A parsed expression, with the column it starts at (its operator's, for a binary one)
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Node {
+    pub column: usize,
+    pub kind: NodeKind,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum NodeKind {
+    /// A number as written, unit included, such as `2` or `1.5GiB`
+    Number(String),
+    Name(String),
+    Call { function: String, arguments: Vec<Argument> },
+    Negate(Box<Node>),
+    Binary { operator: Operator, left: Box<Node>, right: Box<Node> },
+}
+
+/// One argument of a call
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Argument {
+    /// A quoted path glob
+    Glob { column: usize, pattern: String },
+    /// A `key:value` filter, such as `type:Images` or `older:30d`
+    Filter { column: usize, key: String, value_column: usize, value: String },
+    Value(Node),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Operator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(String),
+    Name(String),
+    Str(String),
+    LeftParen,
+    RightParen,
+    Comma,
+    Colon,
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    End,
+}
+
+impl Token {
+    fn describe(&self) -> String {
+        match self {
+            Token::Number(number) => format!("'{}'", number),
+            Token::Name(name) => format!("'{}'", name),
+            Token::Str(_) => "a quoted glob".to_string(),
+            Token::LeftParen => "'('".to_string(),
+            Token::RightParen => "')'".to_string(),
+            Token::Comma => "','".to_string(),
+            Token::Colon => "':'".to_string(),
+            Token::Plus => "'+'".to_string(),
+            Token::Minus => "'-'".to_string(),
+            Token::Star => "'*'".to_string(),
+            Token::Slash => "'/'".to_string(),
+            Token::End => "the end".to_string(),
+        }
+    }
+}
+
+/// Split `source` into tokens with their columns, ending with [`Token::End`]
+fn tokenize(source: &str) -> Result<Vec<(Token, usize)>, SyntaxError> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let column = i + 1;
+        let single = match c {
+            '(' => Some(Token::LeftParen),
+            ')' => Some(Token::RightParen),
+            ',' => Some(Token::Comma),
+            ':' => Some(Token::Colon),
+            '+' => Some(Token::Plus),
+            '-' => Some(Token::Minus),
+            '*' => Some(Token::Star),
+            '/' => Some(Token::Slash),
+            _ => None,
+        };
+        if let Some(token) = single {
+            tokens.push((token, column));
+            i += 1;
+        } else if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit() || (c == '.' && chars.get(i + 1).is_some_and(char::is_ascii_digit)) {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            let digits: String = chars[start..i].iter().collect();
+            if digits.parse::<f64>().is_err() {
+                return Err(SyntaxError::new(column, format!("malformed number '{}'", digits)));
+            }
+            // A unit such as `GiB` or `d` belongs to the number
+            while i < chars.len() && chars[i].is_ascii_alphabetic() {
+                i += 1;
+            }
+            tokens.push((Token::Number(chars[start..i].iter().collect()), column));
+        } else if c.is_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            tokens.push((Token::Name(chars[start..i].iter().collect()), column));
+        } else if c == '"' || c == '\'' {
+            let mut text = String::new();
+            i += 1;
+            loop {
+                match chars.get(i) {
+                    None => return Err(SyntaxError::new(column, "unterminated string")),
+                    Some(&close) if close == c => break,
+                    Some('\\') if chars.get(i + 1).is_some_and(|&next| next == c || next == '\\') => {
+                        text.push(chars[i + 1]);
+                        i += 2;
+                    }
+                    Some(&other) => {
+                        text.push(other);
+                        i += 1;
+                    }
+                }
+            }
+            i += 1;
+            tokens.push((Token::Str(text), column));
+        } else {
+            return Err(SyntaxError::new(column, format!("unexpected character '{}'", c)));
+        }
+    }
+    tokens.push((Token::End, chars.len() + 1));
+    Ok(tokens)
+}
+
+/// Parse a whole expression
+pub(crate) fn parse(source: &str) -> Result<Node, SyntaxError> {
+    let mut parser = Parser { tokens: tokenize(source)?, position: 0, depth: 0 };
+    if parser.peek() == &Token::End {
+        return Err(SyntaxError::new(1, "the expression is empty"));
+    }
+    let node = parser.expression()?;
+    match parser.peek() {
+        Token::End => Ok(node),
+        Token::RightParen => Err(parser.error("')' has no matching '('")),
+        token => Err(parser.error(format!("expected an operator, found {}", token.describe()))),
+    }
+}
+
+struct Parser {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+    depth: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.position].0
+    }
+
+    fn peek_second(&self) -> &Token {
+        self.tokens.get(self.position + 1).map_or(&Token::End, |(token, _)| token)
+    }
+
+    fn column(&self) -> usize {
+        self.tokens[self.position].1
+    }
+
+    /// The current token and its column, moving past it; [`Token::End`] stays
+    fn next(&mut self) -> (Token, usize) {
+        let current = self.tokens[self.position].clone();
+        if current.0 != Token::End {
+            self.position += 1;
+        }
+        current
+    }
+
+    fn error(&self, message: impl Into<String>) -> SyntaxError {
+        SyntaxError::new(self.column(), message)
+    }
+
+    fn expression(&mut self) -> Result<Node, SyntaxError> {
+        let mut left = self.term()?;
+        loop {
+            let operator = match self.peek() {
+                Token::Plus => Operator::Add,
+                Token::Minus => Operator::Subtract,
+                _ => return Ok(left),
+            };
+            let (_, column) = self.next();
+            let right = self.term()?;
+            left = Node { column, kind: NodeKind::Binary { operator, left: Box::new(left), right: Box::new(right) } };
+        }
+    }
+
+    fn term(&mut self) -> Result<Node, SyntaxError> {
+        let mut left = self.unary()?;
+        loop {
+            let operator = match self.peek() {
+                Token::Star => Operator::Multiply,
+                Token::Slash => Operator::Divide,
+                _ => return Ok(left),
+            };
+            let (_, column) = self.next();
+            let right = self.unary()?;
+            left = Node { column, kind: NodeKind::Binary { operator, left: Box::new(left), right: Box::new(right) } };
+        }
+    }
+
+    fn unary(&mut self) -> Result<Node, SyntaxError> {
+        if self.peek() != &Token::Minus {
+            return self.primary();
+        }
+        let (_, column) = self.next();
+        let operand = self.nested(Self::unary)?;
+        Ok(Node { column, kind: NodeKind::Negate(Box::new(operand)) })
+    }
+
+    /// Run `parse` one level deeper, refusing to go past [`MAX_DEPTH`]
+    fn nested(&mut self, parse: fn(&mut Self) -> Result<Node, SyntaxError>) -> Result<Node, SyntaxError> {
+        if self.depth >= MAX_DEPTH {
+            return Err(self.error(format!("the expression nests deeper than {} levels", MAX_DEPTH)));
+        }
+        self.depth += 1;
+        let node = parse(self);
+        self.depth -= 1;
+        node
+    }
+
+    fn primary(&mut self) -> Result<Node, SyntaxError> {
+        let column = self.column();
+        match self.next().0 {
+            Token::Number(number) => Ok(Node { column, kind: NodeKind::Number(number) }),
+            Token::Name(name) if self.peek() == &Token::LeftParen => {
+                self.next();
+                let arguments = self.arguments()?;
+                Ok(Node { column, kind: NodeKind::Call { function: name, arguments } })
+            }
+            Token::Name(name) => Ok(Node { column, kind: NodeKind::Name(name) }),
+            Token::LeftParen => {
+                let inner = self.nested(Self::expression)?;
+                self.close(column)?;
+                Ok(inner)
+            }
+            Token::Str(_) => Err(SyntaxError::new(column, "a quoted glob can only be an argument of size() or count()")),
+            token => Err(SyntaxError::new(column, format!("expected a value, found {}", token.describe()))),
+        }
+    }
+
+    /// The arguments of a call whose `(` was just read, up to and including its `)`
+    fn arguments(&mut self) -> Result<Vec<Argument>, SyntaxError> {
+        let open = self.tokens[self.position - 1].1;
+        let mut arguments = Vec::new();
+        if self.peek() == &Token::RightParen {
+            self.next();
+            return Ok(arguments);
+        }
+        loop {
+            arguments.push(self.argument()?);
+            if self.peek() != &Token::Comma {
+                break;
+            }
+            self.next();
+        }
+        self.close(open)?;
+        Ok(arguments)
+    }
+
+    fn argument(&mut self) -> Result<Argument, SyntaxError> {
+        let column = self.column();
+        let keyed = self.peek_second() == &Token::Colon;
+        match self.peek().clone() {
+            Token::Str(pattern) => {
+                self.next();
+                Ok(Argument::Glob { column, pattern })
+            }
+            Token::Name(key) if keyed => {
+                self.next();
+                self.next();
+                let value_column = self.column();
+                match self.next().0 {
+                    Token::Number(value) | Token::Name(value) | Token::Str(value) => {
+                        Ok(Argument::Filter { column, key, value_column, value })
+                    }
+                    token => Err(SyntaxError::new(
+                        value_column,
+                        format!("expected a value after '{}:', found {}", key, token.describe()),
+                    )),
+                }
+            }
+            _ => Ok(Argument::Value(self.nested(Self::expression)?)),
+        }
+    }
+
+    /// Read the `)` closing the `(` at column `open`
+    fn close(&mut self, open: usize) -> Result<(), SyntaxError> {
+        match self.peek() {
+            Token::RightParen => {
+                self.next();
+                Ok(())
+            }
+            token => Err(self.error(format!("expected ')' to close the '(' at column {}, found {}", open, token.describe()))),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The tree of `source` in prefix notation, for comparing shapes at a glance
+    fn shape(source: &str) -> String {
+        fn show(node: &Node) -> String {
+            match &node.kind {
+                NodeKind::Number(number) => number.clone(),
+                NodeKind::Name(name) => name.clone(),
+                NodeKind::Negate(operand) => format!("(neg {})", show(operand)),
+                NodeKind::Binary { operator, left, right } => {
+                    let symbol = match operator {
+                        Operator::Add => "+",
+                        Operator::Subtract => "-",
+                        Operator::Multiply => "*",
+                        Operator::Divide => "/",
+                    };
+                    format!("({} {} {})", symbol, show(left), show(right))
+                }
+                NodeKind::Call { function, arguments } => {
+                    let arguments: Vec<String> = arguments
+                        .iter()
+                        .map(|argument| match argument {
+                            Argument::Glob { pattern, .. } => format!("{:?}", pattern),
+                            Argument::Filter { key, value, .. } => format!("{}:{}", key, value),
+                            Argument::Value(node) => show(node),
+                        })
+                        .collect();
+                    format!("{}[{}]", function, arguments.join(" "))
+                }
+            }
+        }
+        show(&parse(source).unwrap_or_else(|error| panic!("{}: {}", source, error)))
+    }
+
+    fn error(source: &str) -> (usize, String) {
+        let error = parse(source).expect_err(source);
+        (error.column, error.message)
+    }
+
+    #[test]
+    fn test_precedence_and_associativity() {
+        let cases = [
+            ("1 + 2 * 3", "(+ 1 (* 2 3))"),
+            ("(1 + 2) * 3", "(* (+ 1 2) 3)"),
+            ("8 - 4 - 2", "(- (- 8 4) 2)"),
+            ("8 / 4 / 2", "(/ (/ 8 4) 2)"),
+            ("1 - 2 + 3", "(+ (- 1 2) 3)"),
+            ("-a * b", "(* (neg a) b)"),
+            ("a - -b", "(- a (neg b))"),
+            ("--a", "(neg (neg a))"),
+            ("((total_size))", "total_size"),
+        ];
+        for (source, expected) in cases {
+            assert_eq!(shape(source), expected, "{}", source);
+        }
+    }
+
+    #[test]
+    fn test_numbers_keep_their_units() {
+        assert_eq!(shape("1.5GiB + 10MB"), "(+ 1.5GiB 10MB)");
+        assert_eq!(shape(".5 * 2"), "(* .5 2)");
+        assert_eq!(shape("100"), "100");
+    }
+
+    #[test]
+    fn test_calls_and_their_arguments() {
+        let cases = [
+            (r#"size("**/.cache/**")"#, r#"size["**/.cache/**"]"#),
+            ("size('*.log', type:Documents)", r#"size["*.log" type:Documents]"#),
+            (r#"count(type:"Version Control", older:30d, larger:1.5GiB)"#, "count[type:Version Control older:30d larger:1.5GiB]"),
+            ("percent(size(type:Images), total_size)", "percent[size[type:Images] total_size]"),
+            ("size()", "size[]"),
+            (r#"size("a\"b")"#, r#"size["a\"b"]"#),
+            ("size(type:Images) + size(type:Videos)", "(+ size[type:Images] size[type:Videos])"),
+        ];
+        for (source, expected) in cases {
+            assert_eq!(shape(source), expected, "{}", source);
+        }
+    }
+
+    #[test]
+    fn test_columns_point_at_the_part() {
+        let node = parse("total_size / size(\"*.log\", older:7d)").unwrap();
+        assert_eq!(node.column, 12, "a binary node sits at its operator");
+        let NodeKind::Binary { right, .. } = node.kind else { panic!("not binary") };
+        assert_eq!(right.column, 14);
+        let NodeKind::Call { arguments, .. } = right.kind else { panic!("not a call") };
+        assert_eq!(arguments[0], Argument::Glob { column: 19, pattern: "*.log".to_string() });
+        assert_eq!(arguments[1], Argument::Filter { column: 28, key: "older".to_string(), value_column: 34, value: "7d".to_string() });
+    }
+
+    #[test]
+    fn test_syntax_errors_name_their_column() {
+        let cases = [
+            ("", 1, "the expression is empty"),
+            ("1 +", 4, "expected a value, found the end"),
+            ("(1 + 2", 7, "expected ')' to close the '(' at column 1, found the end"),
+            ("1 + 2)", 6, "')' has no matching '('"),
+            ("size(\"*.log\"", 13, "expected ')' to close the '(' at column 5, found the end"),
+            ("1 2", 3, "expected an operator, found '2'"),
+            ("total_size $ 2", 12, "unexpected character '$'"),
+            ("size(\"*.log)", 6, "unterminated string"),
+            ("\"*.log\" + 1", 1, "a quoted glob can only be an argument of size() or count()"),
+            ("1.2.3", 1, "malformed number '1.2.3'"),
+            ("size(type:)", 11, "expected a value after 'type:', found ')'"),
+            ("size(,)", 6, "expected a value, found ','"),
+            ("* 2", 1, "expected a value, found '*'"),
+            ("a + (b * ) ", 10, "expected a value, found ')'"),
+        ];
+        for (source, column, message) in cases {
+            assert_eq!(error(source), (column, message.to_string()), "{:?}", source);
+        }
+    }
+
+    #[test]
+    fn test_columns_count_characters() {
+        assert_eq!(error("size('größe') ?"), (15, "unexpected character '?'".to_string()));
+    }
+
+    #[test]
+    fn test_deep_nesting_is_refused() {
+        let deep = format!("{}1{}", "(".repeat(MAX_DEPTH + 1), ")".repeat(MAX_DEPTH + 1));
+        assert!(error(&deep).1.contains("nests deeper"));
+        let negated = format!("{}1", "-".repeat(10_000));
+        assert!(error(&negated).1.contains("nests deeper"));
+
+        let fine = format!("{}1{}", "(".repeat(MAX_DEPTH - 1), ")".repeat(MAX_DEPTH - 1));
+        assert_eq!(shape(&fine), "1");
+    }
+}
@@ -0,0 +1,259 @@
+//! Verification that one directory tree is an exact copy of another
+//!
+//! Both trees are walked with the regular scanner, so exclude patterns and
+//! the hidden-file rule apply to them alike. Files are matched by their path
+//! relative to the tree root. Sizes are always compared; contents are hashed
+//! only when the modification times disagree, or for every pair when paranoid.
+
+use crate::cli::Args;
+use crate::duplicates::{FileHasher, Sha256Hasher};
+use crate::scanner::{DirectoryScanner, FileEntry};
+use anyhow::{Context, Result};
+use clap::Parser;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// Compares a source tree with its supposed copy
+pub struct MirrorCheck {
+    exclude_patterns: Vec<String>,
+    exclude_regexes: Vec<String>,
+    show_hidden: bool,
+    paranoid: bool,
+    hasher: Box<dyn FileHasher>,
+}
+
+/// Differences between two trees; paths are relative to the tree roots
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MirrorDiff {
+    pub source: PathBuf,
+    pub destination: PathBuf,
+    /// Files in the source that the destination lacks
+    pub missing: Vec<PathBuf>,
+    /// Files only in the destination
+    pub extra: Vec<PathBuf>,
+    pub mismatched: Vec<Mismatch>,
+    /// Files present in both trees
+    pub compared: u64,
+    /// Pairs whose contents were hashed
+    pub hashed: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Mismatch {
+    pub path: PathBuf,
+    pub difference: Difference,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Difference {
+    Size { source: u64, destination: u64 },
+    Content { source: String, destination: String },
+    /// One of the copies could not be read
+    Unreadable { error: String },
+}
+
+impl MirrorDiff {
+    /// Whether the destination holds exactly the source's files and contents
+    pub fn is_identical(&self) -> bool {
+        self.missing.is_empty() && self.extra.is_empty() && self.mismatched.is_empty()
+    }
+}
+
+impl MirrorCheck {
+    /// Compare trees leaving out what matches the `--exclude` globs
+    pub fn new(exclude_patterns: Vec<String>) -> Self {
+        Self {
+            exclude_patterns,
+            exclude_regexes: Vec::new(),
+            show_hidden: false,
+            paranoid: false,
+            hasher: Box::new(Sha256Hasher),
+        }
+    }
+
+    /// Also leave out full paths matching these regexes, as `--exclude-regex` does
+    pub fn exclude_regexes(mut self, exclude_regexes: Vec<String>) -> Self {
+        self.exclude_regexes = exclude_regexes;
+        self
+    }
+
+    /// Include hidden files, as `--all` does for a scan
+    pub fn show_hidden(mut self, show_hidden: bool) -> Self {
+        self.show_hidden = show_hidden;
+        self
+    }
+
+    /// Hash every pair of same-size files, not only those with differing modification times
+    pub fn paranoid(mut self, paranoid: bool) -> Self {
+        self.paranoid = paranoid;
+        self
+    }
+
+    /// Use a different content hashing algorithm
+    pub fn with_hasher(mut self, hasher: Box<dyn FileHasher>) -> Self {
+        self.hasher = hasher;
+        self
+    }
+
+    /// Walk both trees and compare them
+    pub async fn compare(&self, source: &Path, destination: &Path) -> Result<MirrorDiff> {
+        let source_files = self.scan(source).await?;
+        let destination_files = self.scan(destination).await?;
+
+        let mut diff = MirrorDiff {
+            source: source.to_path_buf(),
+            destination: destination.to_path_buf(),
+            ..Default::default()
+        };
+        let mut to_hash = Vec::new();
+        for (path, file) in &source_files {
+            let Some(copy) = destination_files.get(path) else {
+                diff.missing.push(path.clone());
+                continue;
+            };
+            diff.compared += 1;
+            if file.size != copy.size {
+                diff.mismatched.push(Mismatch {
+                    path: path.clone(),
+                    difference: Difference::Size { source: file.size, destination: copy.size },
+                });
+            } else if self.paranoid || file.modified != copy.modified {
+                to_hash.push((path, file, copy));
+            }
+        }
+        diff.extra = destination_files.keys().filter(|path| !source_files.contains_key(*path)).cloned().collect();
+
+        diff.hashed = to_hash.len() as u64;
+        let mut mismatched: Vec<Mismatch> = to_hash
+            .par_iter()
+            .filter_map(|(path, file, copy)| {
+                let difference = match (self.hasher.hash_file(&file.path), self.hasher.hash_file(&copy.path)) {
+                    (Ok(source), Ok(destination)) if source == destination => return None,
+                    (Ok(source), Ok(destination)) => Difference::Content { source, destination },
+                    (Err(error), _) | (_, Err(error)) => Difference::Unreadable { error: format!("{:#}", error) },
+                };
+                Some(Mismatch { path: (*path).clone(), difference })
+            })
+            .collect();
+        diff.mismatched.append(&mut mismatched);
+        diff.mismatched.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(diff)
+    }
+
+    /// Files of one tree keyed by their path relative to its root
+    async fn scan(&self, root: &Path) -> Result<BTreeMap<PathBuf, FileEntry>> {
+        let mut argv = vec!["diranalyzer".into(), "--quiet".into()];
+        if self.show_hidden {
+            argv.push("--all".into());
+        }
+        for pattern in &self.exclude_patterns {
+            argv.extend(["--exclude".into(), pattern.clone()]);
+        }
+        for pattern in &self.exclude_regexes {
+            argv.extend(["--exclude-regex".into(), pattern.clone()]);
+        }
+        argv.push(root.to_string_lossy().into_owned());
+
+        let args = Args::try_parse_from(argv)?;
+        let mut scanner = DirectoryScanner::new(&args)?;
+        scanner.probe_coverage()?;
+        let results = scanner.scan().await.with_context(|| format!("Failed to scan {}", root.display()))?;
+
+        Ok(results
+            .files
+            .into_iter()
+            .filter_map(|file| Some((file.path.strip_prefix(root).ok()?.to_path_buf(), file)))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    /// A source tree and an identical copy, both visible (not dot-named)
+    fn mirrored_trees() -> (tempfile::TempDir, PathBuf, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let (source, destination) = (dir.path().join("source"), dir.path().join("destination"));
+        for root in [&source, &destination] {
+            fs::create_dir_all(root.join("docs")).unwrap();
+            fs::write(root.join("docs/report.txt"), "quarterly numbers").unwrap();
+            fs::write(root.join("photo.jpg"), vec![7u8; 4096]).unwrap();
+        }
+        (dir, source, destination)
+    }
+
+    #[tokio::test]
+    async fn test_identical_trees() {
+        let (_dir, source, destination) = mirrored_trees();
+        let diff = MirrorCheck::new(Vec::new()).paranoid(true).compare(&source, &destination).await.unwrap();
+
+        assert!(diff.is_identical(), "{:?}", diff);
+        assert_eq!((diff.compared, diff.hashed), (2, 2));
+    }
+
+    #[tokio::test]
+    async fn test_mismatching_byte() {
+        let (_dir, source, destination) = mirrored_trees();
+        let mut bytes = vec![7u8; 4096];
+        bytes[2048] = 8;
+        fs::write(destination.join("photo.jpg"), bytes).unwrap();
+
+        let diff = MirrorCheck::new(Vec::new()).paranoid(true).compare(&source, &destination).await.unwrap();
+
+        assert!(!diff.is_identical());
+        assert_eq!(diff.mismatched.len(), 1);
+        assert_eq!(diff.mismatched[0].path, Path::new("photo.jpg"));
+        assert!(matches!(diff.mismatched[0].difference, Difference::Content { .. }));
+    }
+
+    #[tokio::test]
+    async fn test_only_differing_mtimes_are_hashed() {
+        let (_dir, source, destination) = mirrored_trees();
+        let old = std::time::SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1_700_000_000);
+        for root in [&source, &destination] {
+            fs::File::options().write(true).open(root.join("photo.jpg")).unwrap().set_modified(old).unwrap();
+        }
+        let copy = destination.join("docs/report.txt");
+        fs::write(&copy, "quarterly NUMBERS").unwrap();
+        fs::File::options().write(true).open(&copy).unwrap().set_modified(old).unwrap();
+
+        let diff = MirrorCheck::new(Vec::new()).compare(&source, &destination).await.unwrap();
+
+        assert_eq!(diff.hashed, 1);
+        assert_eq!(diff.mismatched.len(), 1);
+        assert_eq!(diff.mismatched[0].path, Path::new("docs/report.txt"));
+    }
+
+    #[tokio::test]
+    async fn test_missing_and_extra_files() {
+        let (_dir, source, destination) = mirrored_trees();
+        fs::remove_file(destination.join("docs/report.txt")).unwrap();
+        fs::write(destination.join("docs/notes.txt"), "only here").unwrap();
+        fs::write(destination.join("photo.jpg"), vec![7u8; 100]).unwrap();
+
+        let diff = MirrorCheck::new(Vec::new()).compare(&source, &destination).await.unwrap();
+
+        assert_eq!(diff.missing, [Path::new("docs/report.txt")]);
+        assert_eq!(diff.extra, [Path::new("docs/notes.txt")]);
+        assert_eq!(
+            diff.mismatched[0].difference,
+            Difference::Size { source: 4096, destination: 100 }
+        );
+        assert_eq!(diff.hashed, 0, "size mismatches need no hashing");
+    }
+
+    #[tokio::test]
+    async fn test_excludes_apply_to_both_trees() {
+        let (_dir, source, destination) = mirrored_trees();
+        fs::write(destination.join("cache.tmp"), "scratch").unwrap();
+
+        let diff = MirrorCheck::new(vec!["*.tmp".to_string()]).compare(&source, &destination).await.unwrap();
+
+        assert!(diff.is_identical(), "{:?}", diff);
+    }
+}
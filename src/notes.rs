@@ -0,0 +1,220 @@
+//! Notes that operators attach to paths
+//!
+//! Review sessions end in decisions such as "keep until Q3" or "owned by the
+//! data team, do not touch". `diranalyzer note add` records such a decision
+//! against the canonical path it concerns, in `notes.json` in the config
+//! directory. Every later run of a tree holding that path carries the note in
+//! its results as an annotation, and the report shows it next to the path
+//! wherever a section lists it.
+//!
+//! Each change rewrites the whole store under a lock, so `note` commands run
+//! at the same time do not lose each other's changes.
+
+use crate::config;
+use crate::writes::{FileLock, WriteGuard};
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A note as kept in the store
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Note {
+    pub text: String,
+    pub added: DateTime<Utc>,
+}
+
+/// A note on a path of the scanned tree, as carried by the results
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Annotation {
+    /// The path as the scan spells it, under the scan root as given
+    pub path: PathBuf,
+    pub text: String,
+    pub added: DateTime<Utc>,
+}
+
+/// A stored note as `note list` shows it
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListedNote {
+    pub path: PathBuf,
+    pub note: Note,
+    /// The path no longer exists
+    pub stale: bool,
+}
+
+/// The contents of the store file
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct NoteFile {
+    /// Keyed by canonical path
+    notes: BTreeMap<PathBuf, Note>,
+}
+
+/// The notes file of the current user
+pub struct NoteStore {
+    path: PathBuf,
+    writes: WriteGuard,
+}
+
+impl NoteStore {
+    /// Open the store in the config directory
+    pub fn for_current_user() -> Option<Self> {
+        Some(Self::at(config::config_dir()?.join("notes.json")))
+    }
+
+    /// Open a store backed by an explicit file
+    pub fn at(path: PathBuf) -> Self {
+        Self { path, writes: WriteGuard::unrestricted() }
+    }
+
+    /// Location of the backing file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// All notes by canonical path. A missing file yields no notes.
+    pub fn load(&self) -> Result<BTreeMap<PathBuf, Note>> {
+        let data = match fs::read(&self.path) {
+            Ok(data) => data,
+            Err(error) if error.kind() == std::io::ErrorKind::NotFound => return Ok(BTreeMap::new()),
+            Err(error) => return Err(error).with_context(|| format!("Failed to read notes: {}", self.path.display())),
+        };
+        let file: NoteFile = serde_json::from_slice(&data)
+            .with_context(|| format!("Failed to parse notes: {}", self.path.display()))?;
+        Ok(file.notes)
+    }
+
+    /// Every note, flagging those whose path no longer exists
+    pub fn list(&self) -> Result<Vec<ListedNote>> {
+        Ok(self
+            .load()?
+            .into_iter()
+            .map(|(path, note)| {
+                let stale = fs::symlink_metadata(&path).is_err();
+                ListedNote { path, note, stale }
+            })
+            .collect())
+    }
+
+    /// Attach `text` to the existing `path`, returning the note it replaces
+    pub fn add(&self, path: &Path, text: &str) -> Result<Option<Note>> {
+        let canonical = fs::canonicalize(path).with_context(|| format!("Cannot annotate {}", path.display()))?;
+        let note = Note { text: text.to_string(), added: Utc::now() };
+        self.update(|notes| notes.insert(canonical, note))
+    }
+
+    /// Remove the note on `path`, which need not exist any more
+    pub fn remove(&self, path: &Path) -> Result<Option<Note>> {
+        // A stale note's path cannot be canonicalized; its absolute spelling is what was stored
+        let key = fs::canonicalize(path).or_else(|_| std::path::absolute(path))?;
+        self.update(|notes| notes.remove(&key))
+    }
+
+    /// The notes on `root` and the paths beneath it, spelled as a scan of `root` spells them
+    pub fn annotations_under(&self, root: &Path) -> Result<Vec<Annotation>> {
+        let Ok(canonical_root) = fs::canonicalize(root) else {
+            return Ok(Vec::new());
+        };
+        Ok(self
+            .load()?
+            .into_iter()
+            .filter_map(|(path, note)| {
+                let relative = path.strip_prefix(&canonical_root).ok()?;
+                let path = if relative.as_os_str().is_empty() { root.to_path_buf() } else { root.join(relative) };
+                Some(Annotation { path, text: note.text, added: note.added })
+            })
+            .collect())
+    }
+
+    /// Change the notes under the store's lock and write them back
+    fn update<T>(&self, change: impl FnOnce(&mut BTreeMap<PathBuf, Note>) -> T) -> Result<T> {
+        if let Some(parent) = self.path.parent() {
+            self.writes.create_dir_all(parent)
+                .with_context(|| format!("Failed to create config directory: {}", parent.display()))?;
+        }
+
+        let _lock = FileLock::acquire(&self.path.with_extension("lock"), &self.writes)?;
+        let mut file = NoteFile { notes: self.load()? };
+        let outcome = change(&mut file.notes);
+
+        let temp_path = self.path.with_extension("tmp");
+        let mut temp = self.writes.create(&temp_path)?;
+        serde_json::to_writer_pretty(&mut temp, &file)?;
+        temp.sync_all()?;
+        self.writes.rename(&temp_path, &self.path)
+            .with_context(|| format!("Failed to update notes: {}", self.path.display()))?;
+        Ok(outcome)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_add_list_remove() {
+        let dir = tempfile::tempdir().unwrap();
+        let tree = dir.path().join("tree");
+        fs::create_dir_all(tree.join("data")).unwrap();
+        let store = NoteStore::at(dir.path().join("config/notes.json"));
+
+        assert!(store.list().unwrap().is_empty());
+        assert_eq!(store.add(&tree.join("data"), "owned by data team").unwrap(), None);
+        let replaced = store.add(&tree.join("./data"), "keep until Q3").unwrap();
+        assert_eq!(replaced.map(|note| note.text).as_deref(), Some("owned by data team"));
+        assert!(store.add(&tree.join("missing"), "x").is_err());
+
+        let listed = store.list().unwrap();
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].path, fs::canonicalize(tree.join("data")).unwrap());
+        assert_eq!((listed[0].note.text.as_str(), listed[0].stale), ("keep until Q3", false));
+
+        // A removed directory leaves a stale note, which can still be removed
+        fs::remove_dir(tree.join("data")).unwrap();
+        assert!(store.list().unwrap()[0].stale);
+        assert!(store.remove(&tree.join("data")).unwrap().is_some());
+        assert!(store.remove(&tree.join("data")).unwrap().is_none());
+        assert!(store.list().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_annotations_follow_the_scan_root_spelling() {
+        let dir = tempfile::tempdir().unwrap();
+        let tree = dir.path().join("tree");
+        fs::create_dir_all(tree.join("a/b")).unwrap();
+        fs::create_dir_all(dir.path().join("elsewhere")).unwrap();
+        let store = NoteStore::at(dir.path().join("notes.json"));
+        store.add(&tree, "the root").unwrap();
+        store.add(&tree.join("a/b"), "nested").unwrap();
+        store.add(&dir.path().join("elsewhere"), "outside").unwrap();
+
+        let spelled = tree.join("a/..");
+        let annotations = store.annotations_under(&spelled).unwrap();
+        let paths: Vec<(&Path, &str)> = annotations.iter().map(|a| (a.path.as_path(), a.text.as_str())).collect();
+        assert_eq!(paths, [(spelled.as_path(), "the root"), (spelled.join("a/b").as_path(), "nested")]);
+    }
+
+    #[test]
+    fn test_concurrent_adds_are_not_lost() {
+        let dir = tempfile::tempdir().unwrap();
+        let store_path = Arc::new(dir.path().join("notes.json"));
+        for i in 0..8 {
+            fs::create_dir(dir.path().join(i.to_string())).unwrap();
+        }
+
+        let handles: Vec<_> = (0..8)
+            .map(|i| {
+                let store_path = Arc::clone(&store_path);
+                let target = dir.path().join(i.to_string());
+                std::thread::spawn(move || NoteStore::at(store_path.as_ref().clone()).add(&target, "note").unwrap())
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        assert_eq!(NoteStore::at(store_path.as_ref().clone()).load().unwrap().len(), 8);
+    }
+}
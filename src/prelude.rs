@@ -0,0 +1,73 @@
+//! The supported library API
+//!
+//! Everything a library user needs is re-exported here: the analyzer and its
+//! configuration, the results with every type they are made of, the scanner
+//! with its interim progress reports, the standalone duplicate finder, and the
+//! extension points for filters, file sources, and report sections. These names follow
+//! semver; the modules they come from are laid out for the diranalyzer binary
+//! and may be reorganized in any release.
+//!
+//! ```no_run
+//! use diranalyzer::prelude::*;
+//! use clap::Parser;
+//!
+//! # async fn run() -> anyhow::Result<()> {
+//! let args = Args::parse_from(["diranalyzer", "--quiet", "/data"]);
+//! let results: AnalysisResults = DirectoryAnalyzer::new(args)?.analyze().await?;
+//! println!("{} files", results.scan_info.total_files);
+//! # Ok(())
+//! # }
+//! ```
+
+// Running an analysis
+pub use crate::analyzer::{Clock, DirectoryAnalyzer, FixedClock, SystemClock};
+pub use crate::cli::Args;
+pub use crate::config::{ClassificationRule, Config, MetricDefinition, PriorityWeights, RegenerableRules};
+pub use crate::reporter::generate_report;
+
+// Stopping a run early, such as on Ctrl-C
+pub use crate::interrupt::Cancellation;
+
+// The results and what they are made of
+pub use crate::accepted::{AcceptedFinding, AcceptedFindings, AcceptedTarget};
+pub use crate::ages::{AgeBreakdown, AgeBucket, AgeSource, StaleFile, StaleFiles};
+pub use crate::analyzer::{
+    AnalysisResults, Diagnostics, DirectoryFilter, DirectoryInfo, DirectoryOwner, DuplicateGroup, FileInfo, OwnerMix, OwnerStats,
+    PhaseError, RegenerableTotals, Residual, Residuals, ScanInfo, Statistics, TypeStats, XattrFile,
+};
+pub use crate::entropy::{DirectoryEntropy, EntropyReport, JUMP_THRESHOLD};
+pub use crate::hash_cache::CacheUsage;
+pub use crate::metrics::{CustomMetric, MetricUnit};
+pub use crate::history::{GrowthAttribution, NewDuplicateGroup, ResolvedGroup, Trend, TrendWindow};
+pub use crate::notes::Annotation;
+pub use crate::refilter::{AppliedFilters, AppliedSizeRange, SizeRange};
+pub use crate::security::{PermissionFinding, SecurityAudit};
+pub use crate::scanner::{
+    Aliases, BrokenLink, CapKind, CaseCollision, CollidingEntry, Coverage, DateFilter, DateSpec, DepthBoundary, DirectoryAlias, DiskUsage, ErrorCause, ErrorRollup, ErrorSummary,
+    ExclusionCause, ExclusionCount, ExclusionStats, ExclusionTrace, GroupFilter, ModifiedFilter, Ownership, PartialScan, PatternHits, SkippedMount, SlowDirectory,
+    SymlinkReport, TraceOutcome, TraceStep, parse_date_spec,
+};
+pub use crate::utils::latency::{IoLatencyReport, IoOperation, LatencyHistogram, OperationLatency, SlowOperation};
+pub use crate::utils::resource::{PhaseUsage, ResourceUsage};
+pub use crate::utils::time::ClockSkew;
+pub use crate::utils::{RuleHits, SizeBreakdown};
+pub use crate::versions::{VersionGroup, VersionMember, VersionReport};
+
+// Scanning on its own, with interim progress reports
+pub use crate::scanner::{
+    DirectoryEntry, DirectoryRetention, DirectoryScanner, DroppedDirectories, ErrorLog, ErrorType, FileEntry, FileSink, InterimSink,
+    InterimSnapshot, InventorySource, OwnerShare, ScanEntry, ScanError, ScanResults, SlowThresholds, CANDIDATE_DEPTH,
+};
+
+// Streaming progress and results as JSON lines
+pub use crate::export::stream::{HashProgress, ProgressEntry, RecordSender, RecordSink, ScanProgress, StreamRecord};
+
+// Reading results back from a json-bundle export
+pub use crate::export::bundle::{read_bundle, Manifest, Shard, ShardKind};
+
+// Finding duplicates among files from any source
+pub use crate::duplicates::{DuplicateFinder, FileHasher, ResultsJournal, Sha256Hasher};
+
+// Extension points
+pub use crate::extensions::{ExtensionRow, ExtensionSection, SectionProvider};
+pub use crate::scanner::{FileFilter, FileSource};
@@ -0,0 +1,294 @@
+//! Content previews of the largest files, with `--previews`
+//!
+//! A size alone rarely settles whether a file can go. For the listed largest
+//! files only, a preview reads a little of each: the first lines of a text
+//! file, the dimensions of an image from its header, or the entry count of a
+//! ZIP or tar archive from its directory or headers. Every file gets at most
+//! [`PREVIEW_BYTES`] of header and [`PREVIEW_TIME`] of work; a file that is
+//! unreadable, unrecognised, or over budget simply has no preview.
+
+use crate::analyzer::FileInfo;
+use rayon::prelude::*;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+/// Bytes read from the start of a file to recognise it
+pub const PREVIEW_BYTES: usize = 16 * 1024;
+
+/// Work allowed per file; archives whose entries take longer are left without a preview
+pub const PREVIEW_TIME: Duration = Duration::from_millis(50);
+
+/// Lines shown from a text file
+pub const PREVIEW_LINES: usize = 3;
+
+/// Characters kept of each preview line
+const LINE_CHARS: usize = 80;
+
+/// ZIP end of central directory record, without its trailing comment
+const ZIP_END_LEN: u64 = 22;
+
+/// Tar headers read at most, whatever the time budget
+const MAX_TAR_HEADERS: u64 = 100_000;
+
+/// Attach a preview to each of `files`, reading them in parallel; links are not previewed
+pub fn attach(files: &mut [FileInfo]) {
+    files.par_iter_mut().filter(|file| file.symlink_target.is_none()).for_each(|file| file.preview = preview(&file.path));
+}
+
+/// A one-glance description of the file's contents, if it is of a recognised kind
+///
+/// Text previews hold up to [`PREVIEW_LINES`] lines separated by `\n`.
+pub fn preview(path: &Path) -> Option<String> {
+    let deadline = Instant::now() + PREVIEW_TIME;
+    let mut file = File::open(path).ok()?;
+    let mut head = Vec::with_capacity(PREVIEW_BYTES);
+    (&mut file).take(PREVIEW_BYTES as u64).read_to_end(&mut head).ok()?;
+
+    if let Some((kind, width, height)) = image_dimensions(&head) {
+        return Some(format!("{} image, {}×{}", kind, width, height));
+    }
+    if head.starts_with(b"PK\x03\x04") || head.starts_with(b"PK\x05\x06") {
+        return zip_entries(&mut file).map(|entries| format!("ZIP archive, {} entries", entries));
+    }
+    if head.get(257..262) == Some(b"ustar") {
+        return tar_entries(&mut file, deadline).map(|entries| format!("tar archive, {} entries", entries));
+    }
+    text_lines(&head)
+}
+
+/// Kind, width, and height of a PNG, GIF, BMP, or JPEG image, from its header
+fn image_dimensions(head: &[u8]) -> Option<(&'static str, u32, u32)> {
+    let be16 = |at: usize| head.get(at..at + 2).map(|b| u16::from_be_bytes([b[0], b[1]]) as u32);
+    let le16 = |at: usize| head.get(at..at + 2).map(|b| u16::from_le_bytes([b[0], b[1]]) as u32);
+    let be32 = |at: usize| head.get(at..at + 4).map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]));
+    let le32 = |at: usize| head.get(at..at + 4).map(|b| i32::from_le_bytes([b[0], b[1], b[2], b[3]]));
+
+    if head.starts_with(b"\x89PNG\r\n\x1a\n") && head.get(12..16) == Some(b"IHDR") {
+        return Some(("PNG", be32(16)?, be32(20)?));
+    }
+    if head.starts_with(b"GIF87a") || head.starts_with(b"GIF89a") {
+        return Some(("GIF", le16(6)?, le16(8)?));
+    }
+    if head.starts_with(b"BM") && head.len() >= 26 {
+        // Bottom-up bitmaps store a negative height
+        return Some(("BMP", le32(18)?.unsigned_abs(), le32(22)?.unsigned_abs()));
+    }
+    if head.starts_with(b"\xFF\xD8") {
+        // Walk the segments up to the frame header, which holds the dimensions
+        let mut at = 2;
+        while head.get(at) == Some(&0xFF) {
+            let marker = *head.get(at + 1)?;
+            let is_frame = matches!(marker, 0xC0..=0xCF) && !matches!(marker, 0xC4 | 0xC8 | 0xCC);
+            if is_frame {
+                return Some(("JPEG", be16(at + 7)?, be16(at + 5)?));
+            }
+            at += 2 + be16(at + 2)? as usize;
+        }
+    }
+    None
+}
+
+/// Entry count from the ZIP end of central directory record, found from the file's end
+fn zip_entries(file: &mut File) -> Option<u64> {
+    let len = file.metadata().ok()?.len();
+    // The record ends the file, after a comment of at most 64 KiB
+    let tail_len = len.min(ZIP_END_LEN + u16::MAX as u64);
+    file.seek(SeekFrom::Start(len - tail_len)).ok()?;
+    let mut tail = Vec::with_capacity(tail_len as usize);
+    file.take(tail_len).read_to_end(&mut tail).ok()?;
+    let start = tail.len().checked_sub(ZIP_END_LEN as usize)?;
+    let record = (0..=start).rev().find(|&at| tail[at..].starts_with(b"PK\x05\x06"))?;
+    let entries = u16::from_le_bytes([tail[record + 10], tail[record + 11]]);
+    // Archives past 65535 entries keep the count in a ZIP64 record instead
+    (entries != u16::MAX).then_some(entries as u64)
+}
+
+/// Entry count of a tar archive, seeking from header to header
+fn tar_entries(file: &mut File, deadline: Instant) -> Option<u64> {
+    let mut header = [0u8; 512];
+    let mut offset = 0;
+    let mut entries = 0;
+    loop {
+        if entries >= MAX_TAR_HEADERS || Instant::now() > deadline {
+            return None;
+        }
+        file.seek(SeekFrom::Start(offset)).ok()?;
+        if file.read_exact(&mut header).is_err() {
+            // Archives may end without the two zero blocks
+            return Some(entries);
+        }
+        if header.iter().all(|&byte| byte == 0) {
+            return Some(entries);
+        }
+        if &header[257..262] != b"ustar" {
+            return None;
+        }
+        let size = tar_size(&header[124..136])?;
+        offset += 512 + size.div_ceil(512) * 512;
+        // GNU long names and pax headers describe the next entry rather than being one
+        if !matches!(header[156], b'L' | b'K' | b'x' | b'g') {
+            entries += 1;
+        }
+    }
+}
+
+/// The octal size field of a tar header, or the base-256 form GNU tar uses past 8 GiB
+fn tar_size(field: &[u8]) -> Option<u64> {
+    if field[0] & 0x80 != 0 {
+        return field[1..].iter().try_fold(0u64, |size, &byte| size.checked_mul(256)?.checked_add(byte as u64));
+    }
+    let digits = std::str::from_utf8(field).ok()?.trim_matches(|c: char| c == '\0' || c == ' ');
+    if digits.is_empty() {
+        return Some(0);
+    }
+    u64::from_str_radix(digits, 8).ok()
+}
+
+/// The first lines of text, with control characters stripped; `None` for binary data
+fn text_lines(head: &[u8]) -> Option<String> {
+    if head.is_empty() || head.contains(&0) {
+        return None;
+    }
+    let text = match std::str::from_utf8(head) {
+        Ok(text) => text,
+        // The read may have cut the last character in two
+        Err(error) if error.error_len().is_none() && error.valid_up_to() > 0 => {
+            std::str::from_utf8(&head[..error.valid_up_to()]).ok()?
+        }
+        Err(_) => return None,
+    };
+    let lines: Vec<String> = text
+        .lines()
+        .map(|line| {
+            line.chars()
+                .map(|c| if c == '\t' { ' ' } else { c })
+                .filter(|c| !c.is_control())
+                .take(LINE_CHARS)
+                .collect::<String>()
+                .trim_end()
+                .to_string()
+        })
+        .filter(|line| !line.trim().is_empty())
+        .take(PREVIEW_LINES)
+        .collect();
+    (!lines.is_empty()).then(|| lines.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn preview_of(dir: &Path, name: &str, contents: &[u8]) -> Option<String> {
+        let path = dir.join(name);
+        fs::write(&path, contents).unwrap();
+        preview(&path)
+    }
+
+    /// A minimal ustar header for a regular file of `size` bytes
+    fn tar_header(name: &str, size: usize) -> Vec<u8> {
+        let mut header = vec![0u8; 512];
+        header[..name.len()].copy_from_slice(name.as_bytes());
+        header[124..135].copy_from_slice(format!("{:011o}", size).as_bytes());
+        header[156] = b'0';
+        header[257..263].copy_from_slice(b"ustar\0");
+        header
+    }
+
+    #[test]
+    fn test_text_preview_keeps_three_clean_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let text = "\n# Notes\x1b[31m\n\tindented\r\nthird line\nfourth line\n";
+        assert_eq!(preview_of(dir.path(), "notes.txt", text.as_bytes()).unwrap(), "# Notes[31m\n indented\nthird line");
+
+        let long = "x".repeat(500);
+        assert_eq!(preview_of(dir.path(), "long.txt", long.as_bytes()).unwrap().len(), LINE_CHARS);
+        // A multibyte character cut by the read budget still leaves the text readable
+        let mut cut = vec![b'a'; PREVIEW_BYTES - 1];
+        cut.extend_from_slice("é".as_bytes());
+        assert!(preview_of(dir.path(), "cut.txt", &cut).is_some());
+
+        assert_eq!(preview_of(dir.path(), "binary.bin", b"ELF\0\x01\x02"), None);
+        assert_eq!(preview_of(dir.path(), "latin1.txt", b"caf\xe9 au lait"), None);
+        assert_eq!(preview_of(dir.path(), "empty.txt", b""), None);
+    }
+
+    #[test]
+    fn test_image_dimensions_come_from_headers() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+        png.extend_from_slice(&1920u32.to_be_bytes());
+        png.extend_from_slice(&1080u32.to_be_bytes());
+        png.extend_from_slice(&[8, 6, 0, 0, 0]);
+        assert_eq!(preview_of(dir.path(), "a.png", &png).unwrap(), "PNG image, 1920×1080");
+
+        let gif = [b"GIF89a".as_slice(), &640u16.to_le_bytes(), &480u16.to_le_bytes(), &[0; 3]].concat();
+        assert_eq!(preview_of(dir.path(), "a.gif", &gif).unwrap(), "GIF image, 640×480");
+
+        let mut bmp = vec![0u8; 54];
+        bmp[..2].copy_from_slice(b"BM");
+        bmp[18..22].copy_from_slice(&800i32.to_le_bytes());
+        bmp[22..26].copy_from_slice(&(-600i32).to_le_bytes());
+        assert_eq!(preview_of(dir.path(), "a.bmp", &bmp).unwrap(), "BMP image, 800×600");
+
+        // An APP0 segment before the baseline frame header
+        let mut jpeg = b"\xFF\xD8\xFF\xE0\x00\x10JFIF\0\x01\x01\0\0\x01\0\x01\0\0".to_vec();
+        jpeg.extend_from_slice(b"\xFF\xC0\x00\x11\x08");
+        jpeg.extend_from_slice(&768u16.to_be_bytes());
+        jpeg.extend_from_slice(&1024u16.to_be_bytes());
+        jpeg.extend_from_slice(&[3, 1, 0x22, 0, 2, 0x11, 1, 3, 0x11, 1]);
+        assert_eq!(preview_of(dir.path(), "a.jpg", &jpeg).unwrap(), "JPEG image, 1024×768");
+
+        // A truncated header is no image, and not text either
+        assert_eq!(preview_of(dir.path(), "cut.png", &png[..18]), None);
+    }
+
+    #[test]
+    fn test_zip_entries_come_from_the_end_record() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut zip = b"PK\x03\x04".to_vec();
+        zip.extend_from_slice(&[0u8; 300]);
+        zip.extend_from_slice(b"PK\x05\x06\0\0\0\0");
+        zip.extend_from_slice(&12u16.to_le_bytes());
+        zip.extend_from_slice(&12u16.to_le_bytes());
+        zip.extend_from_slice(&[0u8; 8]);
+        zip.extend_from_slice(&7u16.to_le_bytes());
+        zip.extend_from_slice(b"comment");
+        assert_eq!(preview_of(dir.path(), "a.zip", &zip).unwrap(), "ZIP archive, 12 entries");
+
+        // Without an end record the archive is damaged, and gets no preview
+        assert_eq!(preview_of(dir.path(), "broken.zip", &zip[..200]), None);
+    }
+
+    #[test]
+    fn test_tar_entries_are_counted_by_header() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut tar = Vec::new();
+        for (name, size) in [("a.txt", 10), ("b.bin", 1_500), ("empty", 0)] {
+            tar.extend(tar_header(name, size));
+            tar.extend(vec![b'x'; size.div_ceil(512) * 512]);
+        }
+        let mut long_name = tar_header("././@LongLink", 3);
+        long_name[156] = b'L';
+        tar.extend(long_name);
+        tar.extend(vec![b'n'; 512]);
+        tar.extend(tar_header("long", 0));
+        tar.extend(vec![0u8; 1024]);
+        assert_eq!(preview_of(dir.path(), "a.tar", &tar).unwrap(), "tar archive, 4 entries");
+
+        // Garbage where the next header should be
+        let mut broken = tar_header("a.txt", 10);
+        broken.extend(vec![b'x'; 512]);
+        broken.extend(vec![b'?'; 512]);
+        assert_eq!(preview_of(dir.path(), "broken.tar", &broken), None);
+    }
+
+    #[test]
+    fn test_unreadable_files_have_no_preview() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(preview(&dir.path().join("missing")), None);
+        assert_eq!(preview(dir.path()), None);
+    }
+}
@@ -0,0 +1,220 @@
+//! Report-time filters
+//!
+//! `--exclude`, `--user`, and the other scan options decide what the walk
+//! collects. `--older-than`, `--newer-than`, `--only`, `--larger-than`, and
+//! `--smaller-than` decide which of the collected files a report covers. A
+//! run applies them before anything is totalled, and `diranalyzer report`
+//! applies them to a full snapshot (`--export-scope full`), recomputing the
+//! totals and listings from the stored entries instead of scanning again.
+//!
+//! Ages are measured from the time of the scan, so a snapshot refiltered
+//! later gives the same answer as the run would have.
+//!
+//! `--min-file-size` and `--max-file-size` narrow the file listings alone:
+//! file types, largest files, and duplicates. Directory sizes and the scan
+//! totals still count every file unless `--filter-affects-dirs`.
+
+use crate::cli::{Args, ReportFilters};
+use crate::scanner::FileEntry;
+use crate::utils::glob_to_regex;
+use anyhow::{bail, Context, Result};
+use chrono::{DateTime, Utc};
+use humansize::{format_size, DECIMAL};
+use regex::RegexSet;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Decides which collected files pass the report-time filters
+#[derive(Debug)]
+pub struct FileMatcher {
+    /// Files must be modified at or before this
+    modified_before: Option<DateTime<Utc>>,
+    /// Files must be modified after this
+    modified_after: Option<DateTime<Utc>>,
+    only: Option<RegexSet>,
+    larger_than: Option<u64>,
+    smaller_than: Option<u64>,
+}
+
+/// The report-time filters a set of results reflects
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AppliedFilters {
+    /// Each filter as its option spells it, as `--older-than 1y`
+    pub filters: Vec<String>,
+    pub matched_files: u64,
+    /// Files collected before filtering
+    pub collected_files: u64,
+}
+
+/// The `--min-file-size` and `--max-file-size` bounds, both inclusive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SizeRange {
+    pub min: Option<u64>,
+    pub max: Option<u64>,
+}
+
+/// The size range a set of results reflects
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AppliedSizeRange {
+    pub range: SizeRange,
+    /// Whether directory sizes and totals were narrowed too, with `--filter-affects-dirs`
+    pub affects_directories: bool,
+    pub matched_files: u64,
+    /// Files collected before filtering
+    pub collected_files: u64,
+}
+
+impl SizeRange {
+    /// The range of `args`, if either bound is given; an upper bound below the lower one is an error
+    pub fn from_args(args: &Args) -> Result<Option<Self>> {
+        if let (Some(min), Some(max)) = (args.min_file_size, args.max_file_size) {
+            if max < min {
+                bail!(
+                    "--max-file-size ({}) is smaller than --min-file-size ({})",
+                    format_size(max, DECIMAL),
+                    format_size(min, DECIMAL)
+                );
+            }
+        }
+        let range = Self { min: args.min_file_size, max: args.max_file_size };
+        Ok((range.min.is_some() || range.max.is_some()).then_some(range))
+    }
+
+    pub fn contains(&self, size: u64) -> bool {
+        self.min.is_none_or(|min| size >= min) && self.max.is_none_or(|max| size <= max)
+    }
+}
+
+impl FileMatcher {
+    /// Compile `filters`, measuring ages from `ages_from`
+    pub fn new(filters: &ReportFilters, ages_from: DateTime<Utc>) -> Result<Self> {
+        let cutoff = |age: Duration| chrono::Duration::from_std(age).ok().and_then(|age| ages_from.checked_sub_signed(age));
+        let only = if filters.only.is_empty() {
+            None
+        } else {
+            let patterns = filters
+                .only
+                .iter()
+                .map(|glob| glob_to_regex(glob).with_context(|| format!("Invalid --only pattern {:?}", glob)))
+                .collect::<Result<Vec<_>>>()?;
+            Some(RegexSet::new(patterns).context("Invalid --only pattern")?)
+        };
+        Ok(Self {
+            // An age beyond the calendar leaves nothing old enough
+            modified_before: filters.older_than.map(|age| cutoff(age).unwrap_or(DateTime::<Utc>::MIN_UTC)),
+            modified_after: filters.newer_than.and_then(cutoff),
+            only,
+            larger_than: filters.larger_than,
+            smaller_than: filters.smaller_than,
+        })
+    }
+
+    /// Whether `file` passes every filter; a file without a modification time fails the age filters
+    pub fn matches(&self, file: &FileEntry) -> bool {
+        if self.larger_than.is_some_and(|size| file.counted_size() < size) || self.smaller_than.is_some_and(|size| file.counted_size() >= size) {
+            return false;
+        }
+        if self.modified_before.is_some() || self.modified_after.is_some() {
+            let Some(modified) = file.modified else { return false };
+            if self.modified_before.is_some_and(|before| modified > before) || self.modified_after.is_some_and(|after| modified <= after) {
+                return false;
+            }
+        }
+        match &self.only {
+            Some(only) => only.is_match(&file.path.to_string_lossy()),
+            None => true,
+        }
+    }
+}
+
+impl AppliedFilters {
+    pub fn new(filters: &ReportFilters, matched_files: u64, collected_files: u64) -> Self {
+        let mut described = Vec::new();
+        if let Some(age) = filters.older_than {
+            described.push(format!("--older-than {}", format_age(age)));
+        }
+        if let Some(age) = filters.newer_than {
+            described.push(format!("--newer-than {}", format_age(age)));
+        }
+        described.extend(filters.only.iter().map(|glob| format!("--only {}", glob)));
+        if let Some(size) = filters.larger_than {
+            described.push(format!("--larger-than {}", format_size(size, DECIMAL)));
+        }
+        if let Some(size) = filters.smaller_than {
+            described.push(format!("--smaller-than {}", format_size(size, DECIMAL)));
+        }
+        Self { filters: described, matched_files, collected_files }
+    }
+}
+
+/// An age in the largest unit `parse_duration` reads that divides it evenly
+fn format_age(age: Duration) -> String {
+    const UNITS: [(&str, u64); 5] = [("y", 365 * 86_400), ("w", 7 * 86_400), ("d", 86_400), ("h", 3_600), ("m", 60)];
+    let seconds = age.as_secs();
+    UNITS
+        .iter()
+        .find(|(_, unit)| seconds > 0 && seconds.is_multiple_of(*unit))
+        .map(|(suffix, unit)| format!("{}{}", seconds / unit, suffix))
+        .unwrap_or_else(|| format!("{}s", seconds))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use std::path::PathBuf;
+
+    fn file(path: &str, size: u64, modified: Option<DateTime<Utc>>) -> FileEntry {
+        FileEntry { path: PathBuf::from(path), size, modified, depth: 1, ..Default::default() }
+    }
+
+    #[test]
+    fn test_filters_combine() {
+        let now = Utc.with_ymd_and_hms(2024, 6, 1, 0, 0, 0).unwrap();
+        let day = |days: i64| Some(now - chrono::Duration::days(days));
+        let filters = ReportFilters {
+            older_than: Some(Duration::from_secs(30 * 86_400)),
+            only: vec!["*.log".to_string(), "cache/**".to_string()],
+            larger_than: Some(100),
+            ..Default::default()
+        };
+        let matcher = FileMatcher::new(&filters, now).unwrap();
+
+        assert!(matcher.matches(&file("/srv/app.log", 500, day(40))));
+        assert!(matcher.matches(&file("/srv/cache/blob", 500, day(400))));
+        // Too recent, too small, not matching, or of unknown age
+        assert!(!matcher.matches(&file("/srv/app.log", 500, day(3))));
+        assert!(!matcher.matches(&file("/srv/app.log", 50, day(40))));
+        assert!(!matcher.matches(&file("/srv/app.txt", 500, day(40))));
+        assert!(!matcher.matches(&file("/srv/app.log", 500, None)));
+
+        let newer = FileMatcher::new(&ReportFilters { newer_than: Some(Duration::from_secs(86_400)), smaller_than: Some(10), ..Default::default() }, now).unwrap();
+        assert!(newer.matches(&file("/a", 9, Some(now))));
+        assert!(!newer.matches(&file("/a", 10, Some(now))));
+        assert!(!newer.matches(&file("/a", 9, day(2))));
+
+        let applied = AppliedFilters::new(&filters, 2, 6);
+        assert_eq!(applied.filters, ["--older-than 30d", "--only *.log", "--only cache/**", "--larger-than 100 B"]);
+        assert_eq!(format_age(Duration::from_secs(365 * 86_400)), "1y");
+        assert_eq!(format_age(Duration::from_secs(90)), "90s");
+    }
+
+    #[test]
+    fn test_size_range_bounds_are_inclusive() {
+        use clap::Parser;
+        let range = |flags: &[&str]| SizeRange::from_args(&Args::parse_from([&["diranalyzer"], flags].concat()));
+
+        let both = range(&["--min-file-size", "50M", "--max-file-size", "1.5G"]).unwrap().unwrap();
+        assert_eq!(both, SizeRange { min: Some(50_000_000), max: Some(1_500_000_000) });
+        assert!(both.contains(50_000_000) && both.contains(1_500_000_000));
+        assert!(!both.contains(49_999_999) && !both.contains(1_500_000_001));
+
+        let exact = range(&["--min-file-size", "4k", "--max-file-size", "4k"]).unwrap().unwrap();
+        assert!(exact.contains(4_000) && !exact.contains(4_001));
+        assert!(range(&["--max-file-size", "1k"]).unwrap().unwrap().contains(0));
+        assert_eq!(range(&[]).unwrap(), None);
+
+        let error = range(&["--min-file-size", "2M", "--max-file-size", "1M"]).unwrap_err();
+        assert_eq!(error.to_string(), "--max-file-size (1 MB) is smaller than --min-file-size (2 MB)");
+    }
+}
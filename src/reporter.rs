@@ -1,210 +1,3038 @@
 //! Report generation and formatting
+//!
+//! Reports are built in two steps: section builders turn [`AnalysisResults`]
+//! into the typed [`model`], then a [`ReportRenderer`] formats the model for
+//! the selected output. All user-facing strings come from [`Messages`].
 
-use crate::analyzer::AnalysisResults;
-use crate::cli::Args;
+pub mod brief;
+pub mod compare;
+pub mod markdown;
+pub mod messages;
+pub mod model;
+pub mod render;
+
+use crate::accepted::{AcceptedFinding, AcceptedTarget};
+use crate::ages::{format_age, AgeSource};
+use crate::analyzer::{AnalysisResults, DirectoryInfo, DirectoryOwner, DuplicateGroup, FileInfo, OwnerMix, OwnerStats, Residual, TypeStats};
+use crate::cli::{Args, ReportFormat};
+use crate::config::Config;
+use crate::duplicates::duplicate_totals;
+use crate::entropy::EntropyReport;
+use crate::metrics::{CustomMetric, MetricUnit};
+use crate::extensions::ExtensionSection;
+use crate::mirror::{Difference, MirrorDiff};
+use crate::notes::Annotation;
+use crate::security::SecurityAudit;
+use crate::scanner::{Aliases, BrokenLink, CapKind, CaseCollision, ErrorSummary, SkippedMount, SymlinkReport, MAX_HOPS};
+use crate::utils::{calculate_percentage, format_duration};
+use crate::utils::latency::format_latency;
+use crate::utils::resource::ResourceUsage;
+use crate::versions::VersionReport;
 use anyhow::Result;
-use colored::Colorize;
-use humansize::{format_size, DECIMAL};
+use model::{Category, Cell, Report, Row, Section, SectionKind, Summary, TimestampStyle, Tone, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::io::Write;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 
+/// Types drawn in composition bars; the rest are drawn together as Other
+const COMPOSITION_CATEGORIES: usize = 4;
+
+/// The classifier's category for unknown types, which also collects the rest in bars
+const OTHER_CATEGORY: &str = "Other";
+
+pub use brief::BriefRenderer;
+pub use compare::{build_comparison, comparison_renderer_for, Comparison, ComparisonRenderer};
+pub use markdown::MarkdownRenderer;
+pub use messages::{Lang, Messages};
+pub use render::{PlainRenderer, ReportRenderer, TerminalRenderer};
+
 /// Generate and display comprehensive analysis report
-pub fn generate_report(results: &AnalysisResults, args: &Args, duration: Duration) -> Result<()> {
+pub fn generate_report(results: &AnalysisResults, args: &Args, config: &Config, duration: Duration) -> Result<()> {
+    let renderer = if wants_brief(results, args, config) {
+        Box::new(BriefRenderer { color: matches!(args.report_format, ReportFormat::Terminal) })
+    } else {
+        renderer_for(&args.report_format)
+    };
+    let messages = Messages::new(args.lang);
+    let stdout = std::io::stdout();
+    let mut out = stdout.lock();
+
     if args.quiet {
-        print_summary_only(results);
-        return Ok(());
+        renderer.render_summary(&build_summary(results, &messages), &mut out)?;
+    } else {
+        renderer.render(&build_report(results, args.top_count, duration, &messages), &mut out)?;
+    }
+    out.flush()?;
+
+    Ok(())
+}
+
+/// Whether to show the brief report: when asked to, or by default for small terminal reports
+pub fn wants_brief(results: &AnalysisResults, args: &Args, config: &Config) -> bool {
+    args.brief
+        || (!args.full_report
+            && !matches!(args.report_format, ReportFormat::Markdown)
+            && results.scan_info.total_files < config.brief_report_max_files)
+}
+
+/// Select the renderer for an output format
+pub fn renderer_for(format: &ReportFormat) -> Box<dyn ReportRenderer> {
+    match format {
+        ReportFormat::Terminal => Box::new(TerminalRenderer),
+        ReportFormat::Plain => Box::new(PlainRenderer),
+        ReportFormat::Markdown => Box::new(MarkdownRenderer),
+    }
+}
+
+/// Build the quiet-mode summary model
+pub fn build_summary(results: &AnalysisResults, messages: &Messages) -> Summary {
+    Summary {
+        total_files: results.scan_info.total_files,
+        total_directories: results.scan_info.total_directories,
+        total_size: results.scan_info.total_size,
+        unreadable_paths: results.scan_errors.total,
+        messages: *messages,
+    }
+}
+
+/// Build the full report model from analysis results
+pub fn build_report(results: &AnalysisResults, top_count: usize, duration: Duration, messages: &Messages) -> Report {
+    let mut sections = Vec::new();
+    if results.is_degraded() {
+        sections.push(degraded_section(results, messages));
+    }
+
+    sections.extend([scan_info_section(results, duration, messages), overview_section(results, messages)]);
+    // Risky permissions come before the space figures: they are what a reader must not miss
+    if let Some(audit) = &results.security_audit {
+        sections.push(security_section(audit, top_count, messages));
+    }
+
+    sections.push(size_breakdown_section(results, messages));
+    // Exports of earlier versions carry no ages
+    if !results.age_breakdown.buckets.is_empty() {
+        sections.push(age_section(results, top_count, messages));
+    }
+
+    sections.extend([
+        file_type_section(results, top_count, messages),
+        largest_files_section(results, top_count, messages),
+        largest_directories_section(results, top_count, messages),
+    ]);
+
+    if !results.owners.is_empty() {
+        sections.push(owners_section(results, top_count, messages));
+    }
+
+    if !results.custom_metrics.is_empty() {
+        sections.push(custom_metrics_section(&results.custom_metrics, messages));
+    }
+
+    if results.scan_info.xattr_total.is_some() {
+        sections.push(xattr_section(results, top_count, messages));
     }
 
-    print_header(results, duration);
-    print_size_breakdown(results, args.top_count);
-    print_file_type_distribution(results, args.top_count);
-    print_largest_files(results, args.top_count);
-    print_largest_directories(results, args.top_count);
-    
     if results.duplicate_groups.is_some() {
-        print_duplicate_analysis(results, args.top_count);
+        sections.push(duplicates_section(results, top_count, messages));
     }
-    
-    print_performance_statistics(results);
-    print_footer();
 
-    Ok(())
+    if let Some(report) = &results.entropy {
+        sections.push(entropy_section(report, top_count, messages));
+    }
+
+    if let Some(collisions) = &results.case_collisions {
+        sections.push(case_collisions_section(collisions, top_count, messages));
+    }
+
+    if let Some(versions) = &results.heuristic_versions {
+        sections.push(versions_section(versions, top_count, messages));
+    }
+
+    if let Some(links) = &results.symlink_report {
+        sections.push(broken_links_section(links, top_count, messages));
+    }
+
+    if let Some(mounts) = &results.skipped_mounts {
+        sections.push(skipped_mounts_section(mounts, top_count, messages));
+    }
+
+    if !results.empty_directories.is_empty() || !results.empty_files.is_empty() {
+        sections.push(cleanup_section(results, top_count, messages));
+    }
+
+    if results.accepted_count() > 0 {
+        sections.push(accepted_section(results, messages));
+    }
+
+    if results.scan_errors.total > 0 {
+        sections.push(errors_section(&results.scan_errors, top_count, messages));
+    }
+
+    let aliases = &results.diagnostics.aliases;
+    if !aliases.directories.is_empty() || aliases.unrecorded > 0 {
+        sections.push(diagnostics_section(aliases, messages));
+    }
+
+    sections.push(performance_section(results, messages));
+    sections.extend(results.extensions.iter().map(extension_section));
+    annotate(&mut sections, &results.annotations);
+
+    let mut footer = Vec::new();
+    if let Some(filter) = &results.directory_filter {
+        footer.push(messages.format("report.hidden_directories", &[
+            ("size", &messages.bytes(filter.min_size)),
+            ("count", &messages.grouped(filter.hidden_directories)),
+        ]));
+    }
+    footer.push(messages.get("report.export_hint").to_string());
+
+    Report {
+        title: messages.get("report.title").to_string(),
+        sections,
+        footer,
+        messages: *messages,
+    }
+}
+
+/// Append each operator note to the rows that list its path, in every section
+fn annotate(sections: &mut [Section], annotations: &[Annotation]) {
+    if annotations.is_empty() {
+        return;
+    }
+    let notes: HashMap<&Path, &str> = annotations.iter().map(|annotation| (annotation.path.as_path(), annotation.text.as_str())).collect();
+    for section in sections {
+        annotate_rows(&mut section.rows, &notes);
+    }
+}
+
+fn annotate_rows(rows: &mut [Row], notes: &HashMap<&Path, &str>) {
+    for row in rows {
+        let cells = match row {
+            Row::Field { value, .. } => value,
+            Row::Ranked { cells, details, .. } => {
+                annotate_rows(details, notes);
+                cells
+            }
+            Row::Branch { cells, .. } | Row::Text { cells } => cells,
+            Row::Group { rows, .. } => {
+                annotate_rows(rows, notes);
+                continue;
+            }
+        };
+        let texts: Vec<&str> = cells
+            .iter()
+            .filter_map(|cell| match &cell.value {
+                Value::Path(path) => notes.get(path.as_path()).copied(),
+                _ => None,
+            })
+            .collect();
+        cells.extend(texts.into_iter().map(|text| Cell::text(format!("  📝 {}", text)).tone(Tone::Notice)));
+    }
+}
+
+fn degraded_section(results: &AnalysisResults, messages: &Messages) -> Section {
+    let mut section = Section::new(SectionKind::Degraded, "⚠️ ", messages.get("degraded.title"));
+
+    section.rows.push(Row::Text {
+        cells: vec![Cell::text(messages.get("degraded.notice"))
+            .tone(Tone::Danger)
+            .strong()],
+    });
+    for error in &results.phase_errors {
+        let mut cells = vec![Cell::text(&error.message)];
+        if let Some(path) = &error.path {
+            cells.push(Cell::text(" - "));
+            cells.push(Cell::path(path));
+        }
+        section.rows.push(Row::Field { label: error.phase.clone(), value: cells });
+    }
+    section
+}
+
+fn scan_info_section(results: &AnalysisResults, duration: Duration, messages: &Messages) -> Section {
+    let info = &results.scan_info;
+    let mut section = Section::new(SectionKind::ScanInfo, "📁", messages.get("scan_info.title"));
+
+    let mut path = vec![Cell::path(&info.path).tone(Tone::Good)];
+    if !info.path_given.as_os_str().is_empty() && info.path_given != info.path {
+        let given = info.path_given.display().to_string();
+        path.push(Cell::text(format!(" ({})", messages.format("scan_info.path_given", &[("path", &given)]))));
+    }
+    section.rows = vec![
+        Row::Field { label: messages.get("scan_info.path").to_string(), value: path },
+        Row::field(messages.get("scan_info.timestamp"), Cell::new(Value::Timestamp(info.timestamp, TimestampStyle::Seconds))),
+        Row::Field {
+            label: messages.get("scan_info.duration").to_string(),
+            value: vec![Cell::new(Value::Number(duration.as_secs_f64(), 2)), Cell::text("s")],
+        },
+        Row::field(
+            messages.get("scan_info.depth_limit"),
+            info.depth_limit.map_or_else(|| Cell::text(messages.get("scan_info.unlimited")), Cell::count),
+        ),
+    ];
+    if info.min_depth > 0 {
+        section.rows.push(Row::field(messages.get("scan_info.min_depth"), Cell::count(info.min_depth).tone(Tone::Notice)));
+    }
+    if info.roots.len() > 1 {
+        let mut roots = Vec::new();
+        for (i, root) in info.roots.iter().skip(1).enumerate() {
+            if i > 0 {
+                roots.push(Cell::text(", "));
+            }
+            roots.push(Cell::path(root).tone(Tone::Good));
+        }
+        section.rows.insert(1, Row::Field { label: messages.get("scan_info.more_roots").to_string(), value: roots });
+    }
+    if let Some(ignored) = results.diagnostics.exclusions.ignored {
+        section.rows.push(Row::Field {
+            label: messages.get("scan_info.ignored").to_string(),
+            value: vec![Cell::count(ignored), Cell::text(format!(" {}", messages.get("scan_info.ignored_entries")))],
+        });
+    }
+    if let Some(date_filter) = &info.date_filter {
+        let bounds = [
+            ("scan_info.modified_before", date_filter.before),
+            ("scan_info.modified_after", date_filter.after),
+            ("scan_info.created_after", date_filter.created_after),
+        ];
+        for (key, bound) in bounds {
+            if let Some(bound) = bound {
+                section.rows.push(Row::field(messages.get(key), Cell::new(Value::Timestamp(bound, TimestampStyle::Minutes)).tone(Tone::Notice)));
+            }
+        }
+        if date_filter.undated_files > 0 {
+            let undated_files = if date_filter.created_after.is_some() { "scan_info.undated_files_created" } else { "scan_info.undated_files" };
+            section.rows.push(Row::Field {
+                label: messages.get("scan_info.undated").to_string(),
+                value: vec![Cell::count(date_filter.undated_files).tone(Tone::Warning), Cell::text(format!(" {}", messages.get(undated_files)))],
+            });
+        }
+    }
+    section
 }
 
-fn print_summary_only(results: &AnalysisResults) {
-    println!("{}: {} files, {} directories, {} total",
-        "Summary".bold(),
-        results.scan_info.total_files,
-        results.scan_info.total_directories,
-        format_size(results.scan_info.total_size, DECIMAL).cyan()
-    );
-}
-
-fn print_header(results: &AnalysisResults, duration: Duration) {
-    println!("\n{}", "📋 ANALYSIS REPORT".cyan().bold());
-    println!("{}", "=".repeat(50).cyan());
-    
-    println!("\n{}", "📁 Scan Information".yellow().bold());
-    println!("  Path: {}", results.scan_info.path.display().to_string().green());
-    println!("  Timestamp: {}", results.scan_info.timestamp.format("%Y-%m-%d %H:%M:%S UTC"));
-    println!("  Duration: {:.2}s", duration.as_secs_f64());
-    println!("  Depth Limit: {}", results.scan_info.depth_limit);
-    
-    println!("\n{}", "📊 Overview".yellow().bold());
-    println!("  Total Files: {}", results.scan_info.total_files.to_string().cyan());
-    println!("  Total Directories: {}", results.scan_info.total_directories.to_string().cyan());
-    println!("  Total Size: {}", format_size(results.scan_info.total_size, DECIMAL).cyan().bold());
-    
+fn overview_section(results: &AnalysisResults, messages: &Messages) -> Section {
+    let info = &results.scan_info;
+    let mut section = Section::new(SectionKind::Overview, "📊", messages.get("overview.title"));
+
+    section.rows.push(Row::field(messages.get("overview.total_files"), Cell::count(info.total_files).tone(Tone::Accent)));
+    section.rows.push(Row::field(messages.get("overview.total_directories"), Cell::count(info.total_directories).tone(Tone::Accent)));
+    match &info.disk_usage {
+        // The totals count allocated bytes; the apparent size shows how far the two are apart
+        Some(usage) => {
+            section.rows.push(Row::field(messages.get("overview.disk_usage"), Cell::bytes(info.total_size).tone(Tone::Accent).strong()));
+            section.rows.push(Row::field(messages.get("overview.apparent_size"), Cell::bytes(usage.apparent_size).tone(Tone::Accent)));
+            if usage.sparse_files > 0 {
+                section.rows.push(Row::field(messages.get("overview.sparse_files"), Cell::text(messages.format("overview.sparse_files_value", &[
+                    ("count", &messages.grouped(usage.sparse_files)),
+                    ("size", &messages.bytes(usage.sparse_size)),
+                ])).tone(Tone::Notice)));
+            }
+        }
+        None => section.rows.push(Row::field(messages.get("overview.total_size"), Cell::bytes(info.total_size).tone(Tone::Accent).strong())),
+    }
+    if let Some(physical_size) = info.physical_size {
+        section.rows.push(Row::field(messages.get("overview.physical_size"), Cell::bytes(physical_size).tone(Tone::Accent)));
+    }
+
+    if let Some(ownership) = &info.ownership {
+        section.rows.push(Row::field(messages.get("overview.ownership"), Cell::text(messages.format("overview.ownership_value", &[
+            ("user", &ownership.user),
+            ("uid", &ownership.uid),
+            ("percent", &messages.percent(ownership.covered_fraction() * 100.0, 1)),
+            ("size", &messages.bytes(ownership.encountered_size)),
+        ])).tone(Tone::Notice)));
+    }
+
+    let coverage = &info.coverage;
+    if !coverage.is_complete() {
+        section.rows.push(Row::field(messages.get("overview.coverage"), Cell::text(messages.format("overview.coverage_value", &[
+            ("percent", &messages.percent(coverage.readable_fraction() * 100.0, 0)),
+            ("count", &coverage.unreadable_entries),
+        ])).tone(Tone::Warning).strong()));
+    }
+
+    if let Some(partial) = info.partial {
+        let value = match partial.cap {
+            CapKind::Files | CapKind::Bytes | CapKind::Duration => messages.format("overview.partial_value", &[
+                ("flag", &partial.cap.flag()),
+                ("limit", &match partial.cap {
+                    CapKind::Bytes => messages.bytes(partial.limit),
+                    CapKind::Duration => format_age(partial.limit),
+                    _ => messages.grouped(partial.limit),
+                }),
+            ]),
+            CapKind::Interrupted => messages.format("overview.interrupted_value", &[("count", &messages.grouped(partial.limit))]),
+        };
+        section.rows.push(Row::field(messages.get("overview.partial"), Cell::text(value).tone(Tone::Warning).strong()));
+    }
+
+    if let Some(applied) = &results.report_filters {
+        section.rows.push(Row::field(messages.get("overview.report_filters"), Cell::text(messages.format("overview.report_filters_value", &[
+            ("filters", &applied.filters.join(", ")),
+            ("matched", &messages.grouped(applied.matched_files)),
+            ("collected", &messages.grouped(applied.collected_files)),
+        ])).tone(Tone::Notice)));
+    }
+
+    if let Some(applied) = &results.file_size_range {
+        let range = match (applied.range.min, applied.range.max) {
+            (Some(min), Some(max)) => messages.format("overview.file_sizes_between", &[("min", &messages.bytes(min)), ("max", &messages.bytes(max))]),
+            (Some(min), None) => messages.format("overview.file_sizes_at_least", &[("min", &messages.bytes(min))]),
+            (None, max) => messages.format("overview.file_sizes_at_most", &[("max", &messages.bytes(max.unwrap_or(u64::MAX)))]),
+        };
+        let key = if applied.affects_directories { "overview.file_sizes_everywhere" } else { "overview.file_sizes_listings" };
+        section.rows.push(Row::field(messages.get("overview.file_sizes"), Cell::text(messages.format(key, &[
+            ("range", &range),
+            ("matched", &messages.grouped(applied.matched_files)),
+            ("collected", &messages.grouped(applied.collected_files)),
+        ])).tone(Tone::Notice)));
+    }
+
+    if info.clock_skew.is_some() || info.assumed_clock_skew_secs.is_some() {
+        let mut parts = Vec::new();
+        if let Some(skew) = info.clock_skew {
+            parts.push(messages.format("overview.clock_skew_ahead", &[
+                ("files", &messages.grouped(skew.files_ahead)),
+                ("share", &messages.percent(skew.share() * 100.0, 0)),
+                ("ahead", &format_duration(skew.median_ahead())),
+            ]));
+        }
+        if let Some(secs) = info.assumed_clock_skew_secs {
+            parts.push(messages.format("overview.clock_skew_corrected", &[("skew", &format_duration(Duration::from_secs(secs)))]));
+        }
+        let tone = if info.assumed_clock_skew_secs.is_some() { Tone::Notice } else { Tone::Warning };
+        section.rows.push(Row::field(messages.get("overview.clock_skew"), Cell::text(parts.join("; ")).tone(tone).strong()));
+    }
+
+    let boundary = &info.depth_boundary;
+    if !boundary.directories.is_empty() {
+        let count = messages.grouped(boundary.directories.len() as u64);
+        let cell = if boundary.expanded {
+            Cell::text(messages.format("overview.depth_expanded", &[("count", &count)])).tone(Tone::Notice)
+        } else {
+            Cell::text(messages.format("overview.depth_hidden", &[
+                ("count", &count),
+                ("size", &messages.bytes(boundary.immediate_size)),
+            ])).tone(Tone::Warning).strong()
+        };
+        section.rows.push(Row::field(messages.get("overview.depth_limit"), cell));
+    }
+
+    if !results.empty_directories.is_empty() || !results.empty_files.is_empty() {
+        section.rows.push(Row::field(messages.get("overview.cleanup"), Cell::text(messages.format("overview.cleanup_value", &[
+            ("directories", &messages.grouped(results.empty_directories.len() as u64)),
+            ("files", &messages.grouped(results.empty_files.len() as u64)),
+        ]))));
+    }
+
+    if let Some(ref collisions) = results.case_collisions {
+        let names: usize = collisions.iter().map(|collision| collision.entries.len()).sum();
+        let tone = if collisions.is_empty() { Tone::Good } else { Tone::Warning };
+        section.rows.push(Row::field(messages.get("overview.case_collisions"), Cell::text(messages.format("overview.case_collisions_value", &[
+            ("groups", &messages.grouped(collisions.len() as u64)),
+            ("names", &messages.grouped(names as u64)),
+        ])).tone(tone)));
+    }
+
+    if let Some(ref versions) = results.heuristic_versions {
+        let tone = if versions.groups.is_empty() { Tone::Good } else { Tone::Warning };
+        section.rows.push(Row::field(messages.get("overview.superseded"), Cell::text(messages.format("overview.superseded_value", &[
+            ("size", &messages.bytes(versions.superseded_bytes)),
+            ("files", &messages.grouped(versions.superseded_files)),
+            ("groups", &messages.grouped(versions.groups.len() as u64)),
+        ])).tone(tone)));
+    }
+
+    if let Some(ref links) = results.symlink_report {
+        let tone = if links.broken.is_empty() { Tone::Good } else { Tone::Warning };
+        section.rows.push(Row::field(messages.get("overview.symlinks"), Cell::text(messages.format("overview.symlinks_value", &[
+            ("broken", &messages.grouped(links.broken.len() as u64)),
+            ("out_of_tree", &messages.grouped(links.out_of_tree)),
+            ("valid", &messages.grouped(links.valid)),
+        ])).tone(tone)));
+    }
+
     if let Some(ref groups) = results.duplicate_groups {
-        let duplicate_files = groups.iter().map(|g| g.files.len()).sum::<usize>();
-        let wasted_space: u64 = groups.iter().map(|g| g.wasted_space).sum();
-        println!("  Duplicate Files: {}", duplicate_files.to_string().red());
-        println!("  Wasted Space: {}", format_size(wasted_space, DECIMAL).red().bold());
+        let (redundant_files, reclaimable_bytes) = duplicate_totals(groups);
+        section.rows.push(Row::field(messages.get("overview.redundant_files"), Cell::count(redundant_files).tone(Tone::Danger)));
+        section.rows.push(Row::field(messages.get("overview.reclaimable"), Cell::bytes(reclaimable_bytes).tone(Tone::Danger).strong()));
+    }
+
+    if let Some(ref trend) = results.trend {
+        let mut line = messages.format("overview.trend_since_last", &[
+            ("change", &format_size_change(trend.size_change_since_last, messages)),
+        ]);
+        if let Some(ref window) = trend.window {
+            line.push_str(&messages.format("overview.trend_window", &[
+                ("change", &format_size_change(window.size_change, messages)),
+                ("days", &window.days),
+            ]));
+        }
+        section.rows.push(Row::field(messages.get("overview.trend"), Cell::text(line).tone(Tone::Notice)));
+
+        if let Some(ref growth) = trend.growth {
+            let mut line = messages.format("overview.growth_value", &[
+                ("total", &format_size_change(growth.total_change, messages)),
+                ("unique", &format_size_change(growth.unique_change, messages)),
+                ("duplication", &format_size_change(growth.duplication_change, messages)),
+            ]);
+            if let Some(ref group) = growth.top_new_group {
+                line.push_str(&messages.format("overview.growth_new_group", &[
+                    ("path", &group.path.display()),
+                    ("size", &messages.bytes(group.wasted_space)),
+                ]));
+            }
+            section.rows.push(Row::field(messages.get("overview.growth"), Cell::text(line).tone(Tone::Notice)));
+        }
     }
+
+    section
+}
+
+/// Placeholder line for a section without entries
+fn nothing_to_report(message: &str) -> Row {
+    Row::Text { cells: vec![Cell::text(message).tone(Tone::Notice)] }
+}
+
+/// The "... and N more" text of a listing's left-out entries, if any
+fn residual_text(key: &'static str, residual: Residual, messages: &Messages) -> Option<String> {
+    (residual.count > 0).then(|| {
+        messages.format(key, &[("count", &messages.grouped(residual.count)), ("size", &messages.bytes(residual.size))])
+    })
+}
+
+fn residual_row(key: &'static str, residual: Residual, messages: &Messages) -> Option<Row> {
+    residual_text(key, residual, messages).map(|text| nothing_to_report(&text))
+}
+
+fn format_size_change(change: i64, messages: &Messages) -> String {
+    let sign = if change < 0 { '-' } else { '+' };
+    format!("{}{}", sign, messages.bytes(change.unsigned_abs()))
 }
 
-fn print_size_breakdown(results: &AnalysisResults, top_count: usize) {
-    println!("\n{}", "📏 Size Breakdown".yellow().bold());
-    
+fn size_breakdown_section(results: &AnalysisResults, messages: &Messages) -> Section {
     let breakdown = &results.size_breakdown;
-    
-    println!("  Small files (<1MB): {} files, {}",
-        breakdown.small_files_count.to_string().cyan(),
-        format_size(breakdown.small_files_size, DECIMAL).cyan()
-    );
-    println!("  Medium files (1MB-100MB): {} files, {}",
-        breakdown.medium_files_count.to_string().cyan(),
-        format_size(breakdown.medium_files_size, DECIMAL).cyan()
-    );
-    println!("  Large files (>100MB): {} files, {}",
-        breakdown.large_files_count.to_string().cyan(),
-        format_size(breakdown.large_files_size, DECIMAL).cyan()
-    );
-}
-
-fn print_file_type_distribution(results: &AnalysisResults, top_count: usize) {
-    println!("\n{}", "📄 File Type Distribution".yellow().bold());
-    
+    let mut section = Section::new(SectionKind::SizeBreakdown, "📏", messages.get("size_breakdown.title"));
+
+    let bucket = |label: &str, count: u64, size: u64| Row::Field {
+        label: label.to_string(),
+        value: vec![
+            Cell::count(count).tone(Tone::Accent),
+            Cell::text(messages.get("size_breakdown.files")),
+            Cell::bytes(size).tone(Tone::Accent),
+        ],
+    };
+
+    section.rows = vec![
+        bucket(messages.get("size_breakdown.small"), breakdown.small_files_count, breakdown.small_files_size),
+        bucket(messages.get("size_breakdown.medium"), breakdown.medium_files_count, breakdown.medium_files_size),
+        bucket(messages.get("size_breakdown.large"), breakdown.large_files_count, breakdown.large_files_size),
+    ];
+    section
+}
+
+fn age_section(results: &AnalysisResults, top_count: usize, messages: &Messages) -> Section {
+    let breakdown = &results.age_breakdown;
+    let mut section = Section::new(SectionKind::Ages, "⏳", messages.get("ages.title"));
+    let source = match results.scan_info.age_source {
+        AgeSource::Accessed => "ages.accessed",
+        AgeSource::Modified => "ages.modified",
+    };
+    section.rows.push(Row::field(messages.get("ages.source"), Cell::text(messages.get(source))));
+
+    let total = breakdown.total_size().max(1) as f64;
+    let share = |label: String, count: u64, size: u64| Row::Field {
+        label,
+        value: vec![
+            Cell::count(count).tone(Tone::Accent),
+            Cell::text(messages.get("size_breakdown.files")),
+            Cell::bytes(size).tone(Tone::Accent),
+            Cell::text(messages.format("ages.share", &[("percent", &messages.percent(size as f64 / total * 100.0, 1))])),
+        ],
+    };
+    for bucket in &breakdown.buckets {
+        let from = format_age(bucket.min_age_secs);
+        let label = match bucket.max_age_secs {
+            Some(to) if bucket.min_age_secs == 0 => messages.format("ages.under", &[("age", &format_age(to))]),
+            Some(to) => messages.format("ages.between", &[("from", &from), ("to", &format_age(to))]),
+            None => messages.format("ages.over", &[("age", &from)]),
+        };
+        section.rows.push(share(label, bucket.count, bucket.total_size));
+    }
+    if breakdown.undated_files > 0 {
+        section.rows.push(share(messages.get("ages.undated").to_string(), breakdown.undated_files, breakdown.undated_size));
+    }
+
+    if let Some(stale) = &results.stale_files {
+        let age = format_age(stale.threshold_secs);
+        section.rows.push(share(messages.format("ages.stale", &[("age", &age)]), stale.count, stale.total_size));
+        let mut rows: Vec<Row> = stale
+            .largest
+            .iter()
+            .take(top_count)
+            .enumerate()
+            .map(|(i, file)| Row::Ranked {
+                rank: i + 1,
+                cells: vec![
+                    Cell::bytes(file.size).tone(Tone::Accent),
+                    Cell::text(" - "),
+                    Cell::path(&file.path).tone(Tone::Good),
+                    Cell::text(messages.get("ages.last_used")),
+                    Cell::new(Value::Timestamp(file.last_used, TimestampStyle::Minutes)),
+                ],
+                details: Vec::new(),
+            })
+            .collect();
+        let listed: u64 = stale.largest.iter().take(top_count).map(|file| file.size).sum();
+        let hidden = Residual { count: stale.count.saturating_sub(rows.len() as u64), size: stale.total_size - listed };
+        rows.extend(residual_row("ages.stale_more", hidden, messages));
+        section.rows.push(Row::Group { title: messages.get("ages.stale_title").to_string(), rows });
+    }
+    section
+}
+
+fn file_type_section(results: &AnalysisResults, top_count: usize, messages: &Messages) -> Section {
+    let mut section = Section::new(SectionKind::FileTypes, "📄", messages.get("file_types.title"));
+
     let mut types: Vec<_> = results.file_type_distribution.iter().collect();
-    types.sort_by(|a, b| b.1.total_size.cmp(&a.1.total_size));
-    
+    types.sort_by_key(|(_, stats)| std::cmp::Reverse(stats.total_size));
+
     let total_size = results.scan_info.total_size;
-    
+    if types.is_empty() {
+        section.rows.push(nothing_to_report(messages.get("common.no_files")));
+    }
+
     for (i, (file_type, stats)) in types.iter().take(top_count).enumerate() {
         let percentage = if total_size > 0 {
             (stats.total_size as f64 / total_size as f64) * 100.0
         } else {
             0.0
         };
-        
-        println!("  {}. {} files ({}) - {} ({:.1}%)",
-            (i + 1).to_string().cyan(),
-            file_type.green().bold(),
-            stats.count,
-            format_size(stats.total_size, DECIMAL).cyan(),
-            percentage
-        );
-        
+
+        let mut details = Vec::new();
         if let Some(ref largest) = stats.largest_file {
-            println!("     Largest: {} ({})",
-                largest.path.display(),
-                format_size(largest.size, DECIMAL)
-            );
+            details.push(Row::Field {
+                label: messages.get("file_types.largest").to_string(),
+                value: vec![
+                    Cell::path(&largest.path),
+                    Cell::text(" ("),
+                    Cell::bytes(largest.size),
+                    Cell::text(")"),
+                ],
+            });
         }
+
+        section.rows.push(Row::Ranked {
+            rank: i + 1,
+            cells: vec![
+                Cell::text(messages.file_type(file_type)).tone(Tone::Good).strong(),
+                Cell::text(messages.get("file_types.count_open")),
+                Cell::count(stats.count),
+                Cell::text(messages.get("file_types.count_close")),
+                Cell::bytes(stats.total_size).tone(Tone::Accent),
+                Cell::text(" ("),
+                Cell::new(Value::Percent(percentage, 1)),
+                Cell::text(")"),
+            ],
+            details,
+        });
     }
+    let hidden = Residual::of(types.iter().skip(top_count).map(|(_, stats)| stats.total_size));
+    section.rows.extend(residual_row("file_types.more", hidden, messages));
+    if let Some(formats) = &results.data_formats {
+        section.rows.push(Row::Group { title: messages.get("file_types.data_formats").to_string(), rows: data_format_rows(formats, top_count, messages) });
+    }
+    section
 }
 
-fn print_largest_files(results: &AnalysisResults, top_count: usize) {
-    println!("\n{}", "🗂️  Largest Files".yellow().bold());
-    
-    for (i, file) in results.largest_files.iter().take(top_count).enumerate() {
-        println!("  {}. {} - {}",
-            (i + 1).to_string().cyan(),
-            format_size(file.size, DECIMAL).red().bold(),
-            file.path.display().to_string().green()
-        );
-        
+/// The "Data" category by format, largest first, each with its largest file
+fn data_format_rows(formats: &BTreeMap<String, TypeStats>, top_count: usize, messages: &Messages) -> Vec<Row> {
+    let mut formats: Vec<_> = formats.iter().collect();
+    formats.sort_by_key(|(_, stats)| std::cmp::Reverse(stats.total_size));
+    let data_size: u64 = formats.iter().map(|(_, stats)| stats.total_size).sum();
+
+    let mut rows: Vec<Row> = formats
+        .iter()
+        .take(top_count)
+        .enumerate()
+        .map(|(i, (format, stats))| Row::Ranked {
+            rank: i + 1,
+            cells: vec![
+                Cell::text(format!(".{}", format)).strong(),
+                Cell::text(messages.get("file_types.count_open")),
+                Cell::count(stats.count),
+                Cell::text(messages.get("file_types.count_close")),
+                Cell::bytes(stats.total_size).tone(Tone::Accent),
+                Cell::text(" ("),
+                Cell::new(Value::Percent(calculate_percentage(stats.total_size, data_size), 1)),
+                Cell::text(")"),
+            ],
+            details: stats
+                .largest_file
+                .iter()
+                .map(|largest| Row::Field {
+                    label: messages.get("file_types.largest").to_string(),
+                    value: vec![Cell::path(&largest.path), Cell::text(" ("), Cell::bytes(largest.size), Cell::text(")")],
+                })
+                .collect(),
+        })
+        .collect();
+    let hidden = Residual::of(formats.iter().skip(top_count).map(|(_, stats)| stats.total_size));
+    rows.extend(residual_row("file_types.more_formats", hidden, messages));
+    rows
+}
+
+fn largest_files_section(results: &AnalysisResults, top_count: usize, messages: &Messages) -> Section {
+    let mut section = Section::new(SectionKind::LargestFiles, "🗂️ ", messages.get("largest_files.title"));
+    if results.largest_files.is_empty() {
+        section.rows.push(nothing_to_report(messages.get("common.no_files")));
+    }
+
+    let regenerable = &results.regenerable;
+    if regenerable.files > 0 {
+        let subtotal = |size: u64, files: u64| {
+            vec![
+                Cell::bytes(size).tone(Tone::Accent),
+                Cell::text(messages.get("common.in")),
+                Cell::count(files),
+                Cell::text(messages.get("largest_files.files")),
+            ]
+        };
+        let actionable_files = results.scan_info.total_files.saturating_sub(regenerable.files);
+        let actionable_size = results.scan_info.total_size.saturating_sub(regenerable.size);
+        section.rows.push(Row::Field {
+            label: messages.get("largest_files.actionable").to_string(),
+            value: subtotal(actionable_size, actionable_files),
+        });
+        section.rows.push(Row::Field {
+            label: messages.get("largest_files.regenerable").to_string(),
+            value: subtotal(regenerable.size, regenerable.files),
+        });
+    }
+
+    let listed: Vec<&FileInfo> = results.largest_files.iter().filter(|file| results.accepted.lists(file.accepted)).collect();
+    for (i, file) in listed.iter().take(top_count).enumerate() {
+        let mut details = Vec::new();
         if let Some(modified) = file.modified {
-            println!("     Modified: {} | Type: {}",
-                modified.format("%Y-%m-%d %H:%M"),
-                file.file_type
-            );
+            let mut value = vec![Cell::new(Value::Timestamp(modified, TimestampStyle::Minutes))];
+            if let Some(created) = file.created {
+                value.extend([Cell::text(messages.get("largest_files.created")), Cell::new(Value::Timestamp(created, TimestampStyle::Minutes))]);
+            }
+            value.extend([Cell::text(messages.get("largest_files.type")), Cell::text(messages.file_type(&file.file_type))]);
+            details.push(Row::Field { label: messages.get("largest_files.modified").to_string(), value });
+        }
+        if let Some(target) = &file.symlink_target {
+            details.push(Row::field(messages.get("largest_files.link_target"), Cell::path(target)));
         }
+        if let Some(apparent) = file.apparent_size.filter(|_| file.sparse) {
+            let value = messages.format("largest_files.sparse_value", &[("size", &messages.bytes(apparent))]);
+            details.push(Row::field(messages.get("largest_files.sparse"), Cell::text(value).tone(Tone::Notice)));
+        }
+        if let Some(preview) = &file.preview {
+            let mut lines = preview.lines();
+            if let Some(first) = lines.next() {
+                details.push(Row::field(messages.get("largest_files.preview"), Cell::text(first)));
+            }
+            details.extend(lines.map(|line| Row::Text { cells: vec![Cell::text(line)] }));
+        }
+
+        let mut cells = vec![
+            Cell::bytes(file.size).tone(Tone::Danger).strong(),
+            Cell::text(" - "),
+            Cell::path(&file.path).tone(Tone::Good),
+        ];
+        if file.regenerable {
+            cells.push(Cell::text(messages.get("common.regenerable_tag")).tone(Tone::Notice));
+        }
+        cells.extend(accepted_tag(results.accepted.for_path(&file.path), messages));
+        section.rows.push(Row::Ranked { rank: i + 1, cells, details });
+    }
+    let hidden = Residual::of(listed.iter().skip(top_count).map(|file| file.size))
+        + results.residuals.largest_files;
+    section.rows.extend(residual_row("largest_files.more", hidden, messages));
+    if regenerable.hidden {
+        let collapsed = Residual { count: regenerable.files, size: regenerable.size };
+        section.rows.extend(residual_row("largest_files.regenerable_hidden", collapsed, messages));
     }
+    section
 }
 
-fn print_largest_directories(results: &AnalysisResults, top_count: usize) {
-    println!("\n{}", "📁 Largest Directories".yellow().bold());
-    
-    for (i, dir) in results.largest_directories.iter().take(top_count).enumerate() {
-        println!("  {}. {} - {}",
-            (i + 1).to_string().cyan(),
-            format_size(dir.size, DECIMAL).red().bold(),
-            dir.path.display().to_string().green()
-        );
-        
-        println!("     {} files, {} subdirectories",
-            dir.file_count,
-            dir.subdirectory_count
-        );
+fn largest_directories_section(results: &AnalysisResults, top_count: usize, messages: &Messages) -> Section {
+    let mut section = Section::new(SectionKind::LargestDirectories, "📁", messages.get("largest_directories.title"));
+    if results.largest_directories.is_empty() {
+        let message = if results.directory_filter.as_ref().is_some_and(|f| f.hidden_directories > 0) {
+            messages.get("largest_directories.below_floor")
+        } else {
+            messages.get("largest_directories.none")
+        };
+        section.rows.push(nothing_to_report(message));
+    }
+
+    // One legend for all bars, so a category keeps its letter and color throughout
+    let categories = composition_categories(&results.largest_directories, &results.file_type_distribution);
+    if !categories.is_empty() {
+        section.rows.push(Row::field(messages.get("largest_directories.composition"), Cell::new(Value::Legend(categories.clone()))));
+    }
+
+    let listed: Vec<&DirectoryInfo> = results.largest_directories.iter().filter(|dir| results.accepted.lists(dir.accepted)).collect();
+    for (i, dir) in listed.iter().take(top_count).enumerate() {
+        let mut details = vec![Row::Text { cells: count_cells(dir, messages) }];
+        if let Some(annotation) = &dir.annotation {
+            details.push(Row::Text { cells: vec![Cell::text(format!("ℹ {}", annotation)).tone(Tone::Notice)] });
+        }
+        if let Some(mix) = &dir.owner_mix {
+            details.push(owner_mix_row(mix, dir.size, messages));
+        }
+        if dir.pinned {
+            details.push(Row::Text { cells: vec![Cell::text(messages.get("largest_directories.pinned")).tone(Tone::Notice)] });
+        }
+        // Paths alone do not say which root a directory counts toward when one root lies beside another
+        if let Some(root) = results.scan_info.roots.get(dir.root).filter(|_| results.scan_info.roots.len() > 1) {
+            let root = root.display().to_string();
+            details.push(Row::Text { cells: vec![Cell::text(messages.format("largest_directories.root", &[("path", &root)]))] });
+        }
+        let mut cells = directory_cells(dir, &categories);
+        cells.extend(accepted_tag(results.accepted.for_path(&dir.path), messages));
+        section.rows.push(Row::Ranked { rank: i + 1, cells, details });
+    }
+    let (pinned, unpinned): (Vec<&&DirectoryInfo>, Vec<_>) = listed.iter().skip(top_count).partition(|dir| dir.pinned);
+    let hidden = Residual::of(unpinned.iter().map(|dir| dir.size)) + results.residuals.largest_directories;
+    section.rows.extend(residual_row("largest_directories.more", hidden, messages));
+
+    // Pinned directories outside the top keep their place in every report
+    if !pinned.is_empty() {
+        let rows = pinned
+            .iter()
+            .map(|dir| {
+                let mut cells = directory_cells(dir, &categories);
+                cells.push(Cell::text(" ("));
+                cells.extend(count_cells(dir, messages));
+                cells.push(Cell::text(")"));
+                Row::Text { cells }
+            })
+            .collect();
+        section.rows.push(Row::Group { title: messages.get("largest_directories.pinned_title").to_string(), rows });
     }
+    section
 }
 
-fn print_duplicate_analysis(results: &AnalysisResults, top_count: usize) {
-    if let Some(ref groups) = results.duplicate_groups {
-        println!("\n{}", "🔍 Duplicate File Analysis".yellow().bold());
-        
-        if groups.is_empty() {
-            println!("  {} No duplicate files found!", "✓".green());
-            return;
-        }
-        
-        let total_groups = groups.len();
-        let total_duplicates: usize = groups.iter().map(|g| g.files.len()).sum();
-        let total_wasted: u64 = groups.iter().map(|g| g.wasted_space).sum();
-        
-        println!("  Duplicate Groups: {}", total_groups.to_string().red());
-        println!("  Total Duplicate Files: {}", total_duplicates.to_string().red());
-        println!("  Total Wasted Space: {}", format_size(total_wasted, DECIMAL).red().bold());
-        
-        println!("\n  Top Duplicate Groups:");
-        
-        for (i, group) in groups.iter().take(top_count).enumerate() {
-            println!("    {}. {} ({} files) - {} wasted",
-                (i + 1).to_string().cyan(),
-                format_size(group.file_size, DECIMAL).yellow(),
-                group.files.len(),
-                format_size(group.wasted_space, DECIMAL).red()
-            );
-            
-            for (j, file_path) in group.files.iter().take(3).enumerate() {
-                let prefix = if j == group.files.len() - 1 || j == 2 { "└─" } else { "├─" };
-                println!("       {} {}", prefix, file_path.display());
-            }
-            
-            if group.files.len() > 3 {
-                println!("       └─ ... and {} more files", (group.files.len() - 3));
-            }
+/// Files and subdirectories of a listed directory, with those below it where there are more
+fn count_cells(dir: &DirectoryInfo, messages: &Messages) -> Vec<Cell> {
+    let mut cells = vec![Cell::count(dir.file_count)];
+    if dir.recursive_file_count > dir.file_count {
+        cells.extend([
+            Cell::text(messages.get("largest_directories.direct_files")),
+            Cell::count(dir.recursive_file_count),
+            Cell::text(messages.get("largest_directories.recursive_files")),
+        ]);
+    } else {
+        cells.push(Cell::text(messages.get("largest_directories.files")));
+    }
+    cells.push(Cell::count(dir.subdirectory_count));
+    if dir.recursive_subdirectory_count > dir.subdirectory_count {
+        cells.extend([
+            Cell::text(messages.get("largest_directories.direct_subdirectories")),
+            Cell::count(dir.recursive_subdirectory_count),
+            Cell::text(messages.get("largest_directories.recursive_subdirectories")),
+        ]);
+    } else {
+        cells.push(Cell::text(messages.get("largest_directories.subdirectories")));
+    }
+    cells
+}
+
+/// Size, composition bar, and path of a listed directory
+fn directory_cells(dir: &DirectoryInfo, categories: &[Category]) -> Vec<Cell> {
+    let mut cells = vec![Cell::bytes(dir.size).tone(Tone::Danger).strong()];
+    if !categories.is_empty() {
+        let total: u64 = dir.composition.values().sum();
+        let named = |name: &str| categories.iter().any(|category| category.name == name && name != OTHER_CATEGORY);
+        let shares = categories
+            .iter()
+            .map(|category| {
+                let size: u64 = if category.name == OTHER_CATEGORY {
+                    dir.composition.iter().filter(|(name, _)| !named(name)).map(|(_, size)| size).sum()
+                } else {
+                    dir.composition.get(&category.name).copied().unwrap_or(0)
+                };
+                (category.clone(), size as f64 / total.max(1) as f64)
+            })
+            .collect();
+        cells.extend([Cell::text(" "), Cell::new(Value::Composition(shares))]);
+    }
+    cells.extend([Cell::text(" - "), Cell::path(&dir.path).tone(Tone::Good)]);
+    cells
+}
+
+/// Categories of the composition bars: the largest types of the whole tree, then the rest as Other
+///
+/// Only types found below a listed directory take part, and each gets its
+/// letter and color slot once for the whole report.
+fn composition_categories(directories: &[DirectoryInfo], distribution: &HashMap<String, TypeStats>) -> Vec<Category> {
+    let present = |name: &str| directories.iter().any(|dir| dir.composition.get(name).is_some_and(|size| *size > 0));
+    let mut ranked: Vec<(&str, u64)> = distribution
+        .iter()
+        .filter(|(name, _)| name.as_str() != OTHER_CATEGORY && present(name))
+        .map(|(name, stats)| (name.as_str(), stats.total_size))
+        .collect();
+    ranked.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+    let mut names: Vec<&str> = ranked.into_iter().take(COMPOSITION_CATEGORIES).map(|(name, _)| name).collect();
+    let rest = directories
+        .iter()
+        .any(|dir| dir.composition.iter().any(|(name, size)| *size > 0 && !names.contains(&name.as_str())));
+    if rest {
+        names.push(OTHER_CATEGORY);
+    }
+
+    let mut letters = Vec::new();
+    names
+        .into_iter()
+        .enumerate()
+        .map(|(slot, name)| {
+            // The initial where it is free, else another letter of the name, else any
+            let letter = name
+                .chars()
+                .map(|c| c.to_ascii_uppercase())
+                .chain('A'..='Z')
+                .find(|c| c.is_ascii_alphabetic() && !letters.contains(c))
+                .unwrap_or('?');
+            letters.push(letter);
+            Category { name: name.to_string(), letter, slot }
+        })
+        .collect()
+}
+
+/// The clear owner of a directory, or its largest owners when there is none
+fn owner_mix_row(mix: &OwnerMix, size: u64, messages: &Messages) -> Row {
+    let share = |owner: &DirectoryOwner| messages.percent(owner.size as f64 / size.max(1) as f64 * 100.0, 1);
+    let cell = match mix.owners.first() {
+        Some(owner) if mix.clear_owner => {
+            Cell::text(messages.format("largest_directories.clear_owner", &[("user", &owner.user), ("percent", &share(owner))]))
+        }
+        _ => {
+            let owners: Vec<String> = mix
+                .owners
+                .iter()
+                .map(|owner| messages.format("largest_directories.owner_share", &[("user", &owner.user), ("percent", &share(owner))]))
+                .collect();
+            Cell::text(messages.format("largest_directories.mixed_owners", &[("owners", &owners.join(", "))])).tone(Tone::Notice)
+        }
+    };
+    Row::Text { cells: vec![cell] }
+}
+
+fn custom_metrics_section(metrics: &[CustomMetric], messages: &Messages) -> Section {
+    let mut section = Section::new(SectionKind::CustomMetrics, "🧮", messages.get("custom_metrics.title"));
+    for metric in metrics {
+        let Some(value) = metric.value else {
+            section.rows.push(Row::field(&metric.name, Cell::text(messages.get("custom_metrics.undefined"))));
+            continue;
+        };
+        // Bytes and counts may come out negative or fractional from the arithmetic
+        let magnitude = value.abs().round() as u64;
+        let cell = match metric.unit {
+            MetricUnit::Bytes => Cell::bytes(magnitude),
+            MetricUnit::Count => Cell::count(magnitude),
+            MetricUnit::Percent => Cell::new(Value::Percent(value.abs(), 1)),
+            MetricUnit::Number => Cell::new(Value::Number(value.abs(), 2)),
+        };
+        let mut value_cells = vec![cell.tone(Tone::Accent)];
+        if value < 0.0 {
+            value_cells.insert(0, Cell::text("-"));
         }
+        section.rows.push(Row::Field { label: metric.name.clone(), value: value_cells });
     }
+    section
 }
 
-fn print_performance_statistics(results: &AnalysisResults) {
-    println!("\n{}", "⚡ Performance Statistics".yellow().bold());
-    
-    let stats = &results.statistics;
-    
-    println!("  Scanning Speed: {:.0} files/sec", stats.files_per_second);
-    println!("  Throughput: {}/sec", format_size(stats.bytes_per_second, DECIMAL));
-    println!("  Memory Usage: {:.1} MB", stats.memory_usage_mb);
-    
-    if stats.duplicate_files > 0 {
-        println!("  Duplicate Detection: {} files analyzed", stats.duplicate_files);
-        println!("  Space Efficiency: {:.1}%", stats.compression_ratio * 100.0);
+fn owners_section(results: &AnalysisResults, top_count: usize, messages: &Messages) -> Section {
+    let mut section = Section::new(SectionKind::Owners, "👤", messages.get("owners.title"));
+    let mut owners: Vec<(&String, &OwnerStats)> = results.owners.iter().collect();
+    owners.sort_by(|a, b| b.1.total_size.cmp(&a.1.total_size).then_with(|| a.0.cmp(b.0)));
+
+    let total = results.scan_info.total_size.max(1) as f64;
+    for (i, (user, stats)) in owners.iter().take(top_count).enumerate() {
+        let share = messages.format("owners.share", &[
+            ("count", &messages.grouped(stats.count)),
+            ("percent", &messages.percent(stats.total_size as f64 / total * 100.0, 1)),
+        ]);
+        section.rows.push(Row::Ranked {
+            rank: i + 1,
+            cells: vec![
+                Cell::bytes(stats.total_size).tone(Tone::Accent),
+                Cell::text(" - "),
+                Cell::text(user.as_str()).tone(Tone::Good),
+                Cell::text(share),
+            ],
+            details: Vec::new(),
+        });
+    }
+    let hidden = Residual::of(owners.iter().skip(top_count).map(|(_, stats)| stats.total_size));
+    section.rows.extend(residual_row("owners.more", hidden, messages));
+    section
+}
+
+fn security_section(audit: &SecurityAudit, top_count: usize, messages: &Messages) -> Section {
+    let mut section = Section::new(SectionKind::SecurityAudit, "🔒", messages.get("security.title"));
+    let flagged = |count: usize| {
+        let cell = Cell::count(count);
+        if count > 0 { cell.tone(Tone::Danger).strong() } else { cell.tone(Tone::Good) }
+    };
+    let kinds = [
+        ("security.world_writable", &audit.world_writable_files),
+        ("security.setuid", &audit.setuid_files),
+        ("security.setgid", &audit.setgid_files),
+        ("security.open_directories", &audit.open_directories),
+    ];
+
+    section.rows.push(Row::field(messages.get("security.audited"), Cell::count(audit.audited_entries).tone(Tone::Accent)));
+    for (key, findings) in kinds {
+        section.rows.push(Row::field(messages.get(key), flagged(findings.len())));
+    }
+    if audit.findings() == 0 {
+        section.rows.push(nothing_to_report(messages.get("security.none")));
+        return section;
+    }
+
+    for (key, findings) in kinds.into_iter().filter(|(_, findings)| !findings.is_empty()) {
+        let mut rows: Vec<Row> = findings
+            .iter()
+            .take(top_count)
+            .map(|finding| {
+                let mode = format!(" - {:04o}", finding.mode);
+                let mut cells = vec![Cell::path(&finding.path).tone(Tone::Warning), Cell::text(mode)];
+                if let Some(owner) = &finding.owner {
+                    cells.push(Cell::text(messages.format("security.owner", &[("owner", owner)])));
+                }
+                Row::Text { cells }
+            })
+            .collect();
+        let hidden = Residual::of(findings.iter().skip(top_count).map(|finding| finding.size));
+        rows.extend(residual_row("security.more", hidden, messages));
+        section.rows.push(Row::Group { title: messages.get(key).to_string(), rows });
+    }
+    section
+}
+
+fn xattr_section(results: &AnalysisResults, top_count: usize, messages: &Messages) -> Section {
+    let mut section = Section::new(SectionKind::ExtendedAttributes, "🏷️ ", messages.get("xattr.title"));
+    let total = results.scan_info.xattr_total.unwrap_or(0);
+
+    section.rows.push(Row::field(messages.get("xattr.total"), Cell::bytes(total).tone(Tone::Accent).strong()));
+    if results.largest_xattr_files.is_empty() {
+        section.rows.push(nothing_to_report(messages.get("xattr.none")));
+        return section;
+    }
+
+    for (i, file) in results.largest_xattr_files.iter().take(top_count).enumerate() {
+        section.rows.push(Row::Ranked {
+            rank: i + 1,
+            cells: vec![
+                Cell::bytes(file.xattr_size).tone(Tone::Warning),
+                Cell::text(" - "),
+                Cell::path(&file.path).tone(Tone::Good),
+            ],
+            details: Vec::new(),
+        });
+    }
+    let hidden = Residual::of(results.largest_xattr_files.iter().skip(top_count).map(|file| file.xattr_size))
+        + results.residuals.largest_xattr_files;
+    section.rows.extend(residual_row("xattr.more", hidden, messages));
+    section
+}
+
+fn entropy_section(report: &EntropyReport, top_count: usize, messages: &Messages) -> Section {
+    let mut section = Section::new(SectionKind::Entropy, "🧪", messages.get("entropy.title"));
+    section.rows.push(nothing_to_report(messages.get("entropy.heuristic")));
+    section.rows.push(Row::field(
+        messages.get("entropy.sampled"),
+        Cell::text(messages.format("entropy.sampled_value", &[
+            ("files", &messages.grouped(report.sampled_files)),
+            ("days", &report.window_days),
+            ("read", &messages.bytes(report.bytes_read)),
+            ("budget", &messages.bytes(report.budget)),
+        ])),
+    ));
+    if report.skipped_files > 0 {
+        section.rows.push(Row::field(
+            messages.get("entropy.skipped"),
+            Cell::text(messages.format("entropy.skipped_value", &[("count", &messages.grouped(report.skipped_files))]))
+                .tone(Tone::Warning),
+        ));
+    }
+    if report.unreadable_files > 0 {
+        section.rows.push(Row::field(messages.get("entropy.unreadable"), Cell::count(report.unreadable_files).tone(Tone::Warning)));
+    }
+    if report.directories.is_empty() {
+        section.rows.push(nothing_to_report(messages.get("entropy.none")));
+        return section;
+    }
+
+    section.rows.push(Row::Field {
+        label: messages.get("entropy.share").to_string(),
+        value: vec![
+            Cell::new(Value::Percent(report.ratio() * 100.0, 1)).tone(Tone::Accent).strong(),
+            Cell::text(messages.get("entropy.of")),
+            Cell::bytes(report.sampled_size),
+        ],
+    });
+    if !report.compared {
+        section.rows.push(nothing_to_report(messages.get("entropy.no_baseline")));
+    }
+
+    for (i, directory) in report.directories.iter().take(top_count).enumerate() {
+        let tone = if directory.jumped { Tone::Danger } else { Tone::Warning };
+        let mut details = vec![Row::Text {
+            cells: vec![
+                Cell::bytes(directory.high_entropy_size),
+                Cell::text(messages.get("entropy.of")),
+                Cell::bytes(directory.sampled_size),
+                Cell::text(messages.get("entropy.high_suffix")),
+            ],
+        }];
+        if let Some(before) = directory.previous_ratio {
+            let key = if directory.jumped { "entropy.jumped" } else { "entropy.previous" };
+            let text = messages.format(key, &[("ratio", &messages.percent(before * 100.0, 1))]);
+            details.push(Row::Text { cells: vec![Cell::text(text).tone(if directory.jumped { Tone::Danger } else { Tone::Plain })] });
+        }
+        section.rows.push(Row::Ranked {
+            rank: i + 1,
+            cells: vec![
+                Cell::new(Value::Percent(directory.ratio() * 100.0, 1)).tone(tone).strong(),
+                Cell::text(" - "),
+                Cell::path(&directory.path).tone(Tone::Good),
+            ],
+            details,
+        });
+    }
+    let hidden = Residual::of(report.directories.iter().skip(top_count).map(|directory| directory.sampled_size));
+    section.rows.extend(residual_row("entropy.more", hidden, messages));
+    section
+}
+
+fn duplicates_section(results: &AnalysisResults, top_count: usize, messages: &Messages) -> Section {
+    let mut section = Section::new(SectionKind::Duplicates, "🔍", messages.get("duplicates.title"));
+    let Some(ref groups) = results.duplicate_groups else {
+        return section;
+    };
+
+    // Shown even when no group is left, which is when it matters most
+    if let Some(resolved) = results.resolved_groups.as_ref().filter(|resolved| !resolved.is_empty()) {
+        let reclaimed: u64 = resolved.iter().map(|group| group.reclaimed_space).sum();
+        let line = messages.format("duplicates.resolved_value", &[
+            ("count", &messages.grouped(resolved.len() as u64)),
+            ("size", &messages.bytes(reclaimed)),
+        ]);
+        section.rows.push(Row::field(messages.get("duplicates.resolved"), Cell::text(line).tone(Tone::Good)));
+    }
+
+    if groups.is_empty() {
+        section.rows.push(Row::Text {
+            cells: vec![Cell::text("✓").tone(Tone::Good), Cell::text(messages.get("duplicates.none"))],
+        });
+        return section;
+    }
+
+    let total_groups = groups.len();
+    let (total_redundant, total_wasted) = duplicate_totals(groups);
+
+    section.rows.push(Row::field(messages.get("duplicates.groups"), Cell::count(total_groups).tone(Tone::Danger)));
+    section.rows.push(Row::field(messages.get("duplicates.redundant"), Cell::count(total_redundant).tone(Tone::Danger)));
+    section.rows.push(Row::field(messages.get("duplicates.reclaimable"), Cell::bytes(total_wasted).tone(Tone::Danger).strong()));
+    let regenerable = &results.regenerable;
+    if regenerable.duplicate_groups > 0 {
+        let actionable = total_wasted.saturating_sub(regenerable.wasted_space);
+        section.rows.push(Row::field(messages.get("duplicates.actionable"), Cell::bytes(actionable).tone(Tone::Danger)));
+        section.rows.push(Row::Field {
+            label: messages.get("duplicates.regenerable").to_string(),
+            value: vec![
+                Cell::bytes(regenerable.wasted_space).tone(Tone::Notice),
+                Cell::text(messages.get("common.in")),
+                Cell::count(regenerable.duplicate_groups),
+                Cell::text(messages.get("duplicates.groups_suffix")),
+            ],
+        });
+    }
+
+    // Hidden regenerable groups are summed up in one line instead, accepted ones in their own section
+    let listed: Vec<&DuplicateGroup> = groups
+        .iter()
+        .filter(|group| !(regenerable.hidden && group.regenerable) && results.accepted.lists(group.accepted))
+        .collect();
+    let mut top_groups = Vec::new();
+    for (i, group) in listed.iter().take(top_count).enumerate() {
+        let mut members = Vec::new();
+        for (j, file_path) in group.files.iter().take(3).enumerate() {
+            members.push(Row::Branch {
+                last: j == group.files.len() - 1 || j == 2,
+                cells: vec![Cell::path(file_path)],
+            });
+        }
+
+        let hidden = Residual::of(group.files.iter().skip(3).map(|_| group.file_size));
+        if let Some(text) = residual_text("duplicates.more", hidden, messages) {
+            members.push(Row::Branch { last: true, cells: vec![Cell::text(text)] });
+        }
+
+        let mut cells = vec![
+            Cell::bytes(group.file_size).tone(Tone::Warning),
+            Cell::text(" ("),
+            Cell::count(group.files.len()),
+            Cell::text(messages.get("duplicates.group_files")),
+            Cell::bytes(group.wasted_space).tone(Tone::Danger),
+            Cell::text(messages.get("duplicates.wasted_priority")),
+            Cell::new(Value::Number(group.priority, 0)),
+        ];
+        if group.regenerable {
+            cells.push(Cell::text(messages.get("common.regenerable_tag")).tone(Tone::Notice));
+        }
+        if group.unverified {
+            cells.push(Cell::text(messages.get("duplicates.unverified_tag")).tone(Tone::Notice));
+        }
+        cells.extend(accepted_tag(results.accepted.for_group(&group.group_id), messages));
+        top_groups.push(Row::Ranked { rank: i + 1, cells, details: members });
+    }
+
+    let hidden = Residual::of(listed.iter().skip(top_count).map(|group| group.wasted_space));
+    top_groups.extend(residual_row("duplicates.more_groups", hidden, messages));
+    if regenerable.hidden {
+        let collapsed = Residual { count: regenerable.duplicate_groups, size: regenerable.wasted_space };
+        top_groups.extend(residual_row("duplicates.regenerable_hidden", collapsed, messages));
     }
+    section.rows.push(Row::Group { title: messages.get("duplicates.top").to_string(), rows: top_groups });
+
+    let partitions = waste_by_partition(groups);
+    if !partitions.is_empty() {
+        let hidden = Residual::of(partitions.iter().skip(top_count).map(|(_, wasted, _)| *wasted));
+        let mut rows: Vec<Row> = partitions
+            .into_iter()
+            .take(top_count)
+            .enumerate()
+            .map(|(i, (partition, wasted, group_count))| Row::Ranked {
+                rank: i + 1,
+                cells: vec![
+                    Cell::path(partition).tone(Tone::Accent),
+                    Cell::text(" - "),
+                    Cell::bytes(wasted).tone(Tone::Danger),
+                    Cell::text(messages.get("duplicates.wasted_in")),
+                    Cell::count(group_count),
+                    Cell::text(messages.get("duplicates.groups_suffix")),
+                ],
+                details: Vec::new(),
+            })
+            .collect();
+        rows.extend(residual_row("duplicates.more_partitions", hidden, messages));
+        section.rows.push(Row::Group { title: messages.get("duplicates.by_partition").to_string(), rows });
+    }
+    section
 }
 
-fn print_footer() {
-    println!("\n{}", "=".repeat(50).cyan());
-    println!("{}", "Analysis complete! 🎉".green().bold());
-    println!("{}", "Use --export to save results to file.");
+/// Build the report of a `mirror-check` comparison
+pub fn build_mirror_report(diff: &MirrorDiff, top_count: usize, messages: &Messages) -> Report {
+    let mut section = Section::new(SectionKind::MirrorCheck, "🪞", messages.get("mirror.section"));
+    let flagged = |count: usize| {
+        let cell = Cell::count(count);
+        if count > 0 { cell.tone(Tone::Danger).strong() } else { cell.tone(Tone::Good) }
+    };
+
+    section.rows = vec![
+        Row::field(messages.get("mirror.source"), Cell::path(&diff.source).tone(Tone::Good)),
+        Row::field(messages.get("mirror.destination"), Cell::path(&diff.destination).tone(Tone::Good)),
+        Row::field(messages.get("mirror.compared"), Cell::count(diff.compared).tone(Tone::Accent)),
+        Row::field(messages.get("mirror.hashed"), Cell::count(diff.hashed)),
+        Row::field(messages.get("mirror.missing"), flagged(diff.missing.len())),
+        Row::field(messages.get("mirror.extra"), flagged(diff.extra.len())),
+        Row::field(messages.get("mirror.mismatched"), flagged(diff.mismatched.len())),
+    ];
+
+    let listing = |paths: Vec<(&Path, Option<String>)>| {
+        let total = paths.len();
+        let mut rows: Vec<Row> = paths
+            .into_iter()
+            .take(top_count)
+            .map(|(path, detail)| {
+                let mut cells = vec![Cell::path(path)];
+                cells.extend(detail.map(Cell::text));
+                Row::Text { cells }
+            })
+            .collect();
+        if total > top_count {
+            rows.push(nothing_to_report(&messages.format("mirror.more", &[("count", &(total - top_count))])));
+        }
+        rows
+    };
+    if !diff.missing.is_empty() {
+        let rows = listing(diff.missing.iter().map(|path| (path.as_path(), None)).collect());
+        section.rows.push(Row::Group { title: messages.get("mirror.missing").to_string(), rows });
+    }
+    if !diff.extra.is_empty() {
+        let rows = listing(diff.extra.iter().map(|path| (path.as_path(), None)).collect());
+        section.rows.push(Row::Group { title: messages.get("mirror.extra").to_string(), rows });
+    }
+    if !diff.mismatched.is_empty() {
+        let rows = listing(diff.mismatched.iter().map(|mismatch| {
+            let detail = match &mismatch.difference {
+                Difference::Size { source, destination } => messages.format("mirror.size_differs", &[
+                    ("source", &messages.bytes(*source)),
+                    ("destination", &messages.bytes(*destination)),
+                ]),
+                Difference::Content { .. } => messages.get("mirror.content_differs").to_string(),
+                Difference::Unreadable { error } => messages.format("mirror.unreadable", &[("error", error)]),
+            };
+            (mismatch.path.as_path(), Some(detail))
+        }).collect());
+        section.rows.push(Row::Group { title: messages.get("mirror.mismatched").to_string(), rows });
+    }
+
+    let verdict = if diff.is_identical() {
+        Cell::text(messages.get("mirror.identical")).tone(Tone::Good).strong()
+    } else {
+        Cell::text(messages.get("mirror.differs")).tone(Tone::Danger).strong()
+    };
+    section.rows.push(Row::Text { cells: vec![verdict] });
+
+    Report {
+        title: messages.get("mirror.title").to_string(),
+        sections: vec![section],
+        footer: Vec::new(),
+        messages: *messages,
+    }
+}
+
+/// Wasted space and group count per partition, largest first
+fn waste_by_partition(groups: &[DuplicateGroup]) -> Vec<(&Path, u64, usize)> {
+    let mut totals: HashMap<&Path, (u64, usize)> = HashMap::new();
+    for group in groups {
+        if let Some(partition) = &group.partition {
+            let entry = totals.entry(partition.as_path()).or_default();
+            entry.0 += group.wasted_space;
+            entry.1 += 1;
+        }
+    }
+
+    let mut totals: Vec<_> = totals.into_iter().map(|(path, (wasted, count))| (path, wasted, count)).collect();
+    totals.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+    totals
+}
+
+/// A section contributed by a [`SectionProvider`](crate::extensions::SectionProvider), shown as provided
+fn extension_section(extension: &ExtensionSection) -> Section {
+    let mut section = Section::new(SectionKind::Extension, "🧩", extension.title.clone());
+    for row in &extension.rows {
+        let mut value = Vec::new();
+        if !row.value.is_empty() {
+            value.push(Cell::text(row.value.clone()));
+        }
+        match (row.size, value.is_empty()) {
+            (Some(size), true) => value.push(Cell::bytes(size).tone(Tone::Accent)),
+            (Some(size), false) => value.extend([Cell::text(" ("), Cell::bytes(size).tone(Tone::Accent), Cell::text(")")]),
+            (None, _) => {}
+        }
+        section.rows.push(Row::Field { label: row.label.clone(), value });
+    }
+    section
+}
+
+fn case_collisions_section(collisions: &[CaseCollision], top_count: usize, messages: &Messages) -> Section {
+    let mut section = Section::new(SectionKind::CaseCollisions, "🔤", messages.get("case_collisions.title"));
+    if collisions.is_empty() {
+        section.rows.push(nothing_to_report(messages.get("case_collisions.none")));
+        return section;
+    }
+
+    let group_size = |collision: &CaseCollision| collision.entries.iter().map(|entry| entry.size).sum::<u64>();
+    for (i, collision) in collisions.iter().take(top_count).enumerate() {
+        let details = collision
+            .entries
+            .iter()
+            .enumerate()
+            .map(|(j, entry)| {
+                let name = if entry.is_dir { format!("{}/", entry.name) } else { entry.name.clone() };
+                let mut cells = vec![Cell::text(name).tone(Tone::Good), Cell::text(" - "), Cell::bytes(entry.size).tone(Tone::Warning)];
+                if let Some(modified) = entry.modified {
+                    cells.push(Cell::text(" - "));
+                    cells.push(Cell::new(Value::Timestamp(modified, TimestampStyle::Minutes)));
+                }
+                Row::Branch { last: j == collision.entries.len() - 1, cells }
+            })
+            .collect();
+        section.rows.push(Row::Ranked {
+            rank: i + 1,
+            cells: vec![
+                Cell::path(&collision.directory).tone(Tone::Accent),
+                Cell::text(" - "),
+                Cell::text(messages.format("case_collisions.group", &[
+                    ("count", &collision.entries.len()),
+                    ("folded", &collision.folded),
+                ])),
+            ],
+            details,
+        });
+    }
+    let hidden = Residual::of(collisions.iter().skip(top_count).map(group_size));
+    section.rows.extend(residual_row("case_collisions.more", hidden, messages));
+    section
+}
+
+fn versions_section(report: &VersionReport, top_count: usize, messages: &Messages) -> Section {
+    let mut section = Section::new(SectionKind::Versions, "🗂", messages.get("versions.title"));
+    section.rows.push(nothing_to_report(messages.get("versions.heuristic")));
+    if report.groups.is_empty() {
+        section.rows.push(nothing_to_report(messages.get("versions.none")));
+        return section;
+    }
+
+    section.rows.push(Row::field(
+        messages.get("versions.superseded"),
+        Cell::text(messages.format("versions.superseded_value", &[
+            ("size", &messages.bytes(report.superseded_bytes)),
+            ("files", &messages.grouped(report.superseded_files)),
+        ])).tone(Tone::Warning).strong(),
+    ));
+    for (i, group) in report.groups.iter().take(top_count).enumerate() {
+        let details = group
+            .members
+            .iter()
+            .enumerate()
+            .map(|(j, member)| {
+                // The newest member is the one the others are probably superseded by
+                let tone = if j == 0 { Tone::Good } else { Tone::Warning };
+                let mut cells = vec![Cell::text(&member.name).tone(tone), Cell::text(" - "), Cell::bytes(member.size).tone(Tone::Accent)];
+                if let Some(modified) = member.modified {
+                    cells.push(Cell::text(" - "));
+                    cells.push(Cell::new(Value::Timestamp(modified, TimestampStyle::Minutes)));
+                }
+                if j == 0 {
+                    cells.push(Cell::text(format!(" ({})", messages.get("versions.newest"))).tone(Tone::Notice));
+                }
+                Row::Branch { last: j == group.members.len() - 1, cells }
+            })
+            .collect();
+        section.rows.push(Row::Ranked {
+            rank: i + 1,
+            cells: vec![
+                Cell::path(group.directory.join(&group.base_name)).tone(Tone::Accent),
+                Cell::text(" - "),
+                Cell::text(messages.format("versions.group", &[
+                    ("count", &group.members.len()),
+                    ("size", &messages.bytes(group.superseded_bytes)),
+                ])),
+            ],
+            details,
+        });
+    }
+    let hidden = Residual::of(report.groups.iter().skip(top_count).map(|group| group.superseded_bytes));
+    section.rows.extend(residual_row("versions.more", hidden, messages));
+    section
+}
+
+/// Broken symlinks by directory, most first, each with its target and where the chain ends
+fn broken_links_section(report: &SymlinkReport, top_count: usize, messages: &Messages) -> Section {
+    let mut section = Section::new(SectionKind::BrokenLinks, "🔗", messages.get("broken_links.title"));
+    if report.broken.is_empty() {
+        section.rows.push(nothing_to_report(messages.get("broken_links.none")));
+        return section;
+    }
+
+    let mut directories: Vec<(&Path, Vec<&BrokenLink>)> = Vec::new();
+    for link in &report.broken {
+        let directory = link.path.parent().unwrap_or(Path::new(""));
+        match directories.iter_mut().find(|(path, _)| *path == directory) {
+            Some((_, links)) => links.push(link),
+            None => directories.push((directory, vec![link])),
+        }
+    }
+    directories.sort_by(|a, b| b.1.len().cmp(&a.1.len()).then_with(|| a.0.cmp(b.0)));
+
+    for (i, (directory, links)) in directories.iter().take(top_count).enumerate() {
+        let mut details: Vec<Row> = links
+            .iter()
+            .take(top_count)
+            .enumerate()
+            .map(|(j, link)| {
+                let name = link.path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default();
+                let mut cells = vec![Cell::text(name).tone(Tone::Warning), Cell::text(" -> "), Cell::path(&link.target)];
+                match &link.dangling {
+                    // Only a chain ends somewhere other than its own target
+                    Some(dangling) if *dangling != directory.join(&link.target) => {
+                        cells.push(Cell::text(messages.format("broken_links.dangling", &[("path", &dangling.display())])).tone(Tone::Notice));
+                    }
+                    Some(_) => {}
+                    None => cells.push(Cell::text(messages.format("broken_links.endless", &[("hops", &MAX_HOPS)])).tone(Tone::Notice)),
+                }
+                Row::Branch { last: j + 1 == links.len().min(top_count), cells }
+            })
+            .collect();
+        if links.len() > top_count {
+            details.push(nothing_to_report(&messages.format("broken_links.more_links", &[("count", &(links.len() - top_count))])));
+        }
+        section.rows.push(Row::Ranked {
+            rank: i + 1,
+            cells: vec![
+                Cell::path(*directory).tone(Tone::Accent),
+                Cell::text(" - "),
+                Cell::text(messages.format("broken_links.group", &[("count", &links.len())])),
+            ],
+            details,
+        });
+    }
+    if directories.len() > top_count {
+        let hidden: usize = directories.iter().skip(top_count).map(|(_, links)| links.len()).sum();
+        section.rows.push(nothing_to_report(&messages.format("broken_links.more", &[
+            ("count", &messages.grouped((directories.len() - top_count) as u64)),
+            ("links", &messages.grouped(hidden as u64)),
+        ])));
+    }
+    section
+}
+
+fn skipped_mounts_section(mounts: &[SkippedMount], top_count: usize, messages: &Messages) -> Section {
+    let mut section = Section::new(SectionKind::SkippedMounts, "⛔", messages.get("skipped_mounts.title"));
+    if mounts.is_empty() {
+        section.rows.push(nothing_to_report(messages.get("skipped_mounts.none")));
+        return section;
+    }
+
+    section.rows.push(nothing_to_report(&messages.format("skipped_mounts.summary", &[("count", &messages.grouped(mounts.len() as u64))])));
+    for (i, mount) in mounts.iter().take(top_count).enumerate() {
+        let mut cells = vec![Cell::path(&mount.path).tone(Tone::Accent)];
+        if let Some(filesystem) = &mount.filesystem {
+            cells.push(Cell::text(" - "));
+            cells.push(Cell::text(filesystem));
+        }
+        section.rows.push(Row::Ranked { rank: i + 1, cells, details: Vec::new() });
+    }
+    if mounts.len() > top_count {
+        section.rows.push(nothing_to_report(&messages.format("skipped_mounts.more", &[("count", &messages.grouped((mounts.len() - top_count) as u64))])));
+    }
+    section
+}
+
+/// The first empty directories and files, each under its own heading
+fn cleanup_section(results: &AnalysisResults, top_count: usize, messages: &Messages) -> Section {
+    let mut section = Section::new(SectionKind::CleanupCandidates, "🧹", messages.get("cleanup.title"));
+    let listing = |paths: &[PathBuf], title: &'static str, more: &'static str| {
+        let mut rows: Vec<Row> = paths.iter().take(top_count).map(|path| Row::Text { cells: vec![Cell::path(path)] }).collect();
+        if paths.len() > top_count {
+            rows.push(nothing_to_report(&messages.format(more, &[("count", &messages.grouped((paths.len() - top_count) as u64))])));
+        }
+        Row::Group { title: messages.format(title, &[("count", &messages.grouped(paths.len() as u64))]), rows }
+    };
+    if !results.empty_directories.is_empty() {
+        section.rows.push(listing(&results.empty_directories, "cleanup.empty_directories", "cleanup.more_directories"));
+    }
+    if !results.empty_files.is_empty() {
+        section.rows.push(listing(&results.empty_files, "cleanup.empty_files", "cleanup.more"));
+    }
+    section
+}
+
+/// The accepted findings among the listings: counted when the sections leave them out, else with their reasons
+fn accepted_section(results: &AnalysisResults, messages: &Messages) -> Section {
+    let mut section = Section::new(SectionKind::Accepted, "✔", messages.get("accepted.title"));
+    let count = results.accepted_count();
+    if !results.accepted.shown {
+        section.rows.push(nothing_to_report(&messages.format("accepted.collapsed", &[("count", &messages.grouped(count as u64))])));
+        return section;
+    }
+
+    let listed = |finding: &&AcceptedFinding| match &finding.target {
+        AcceptedTarget::Path(path) => {
+            results.largest_files.iter().any(|file| file.path == *path) || results.largest_directories.iter().any(|dir| dir.path == *path)
+        }
+        AcceptedTarget::Group(id) => results.duplicate_groups.iter().flatten().any(|group| group.group_id == *id),
+    };
+    for (i, finding) in results.accepted.findings.iter().filter(listed).enumerate() {
+        let target = match &finding.target {
+            AcceptedTarget::Path(path) => Cell::path(path),
+            AcceptedTarget::Group(id) => Cell::text(messages.format("accepted.group", &[("id", id)])),
+        };
+        section.rows.push(Row::Ranked {
+            rank: i + 1,
+            cells: vec![target.tone(Tone::Accent), Cell::text(" - "), Cell::text(&finding.reason)],
+            details: vec![Row::field(messages.get("accepted.since"), Cell::new(Value::Timestamp(finding.accepted, TimestampStyle::Minutes)))],
+        });
+    }
+    section
+}
+
+/// The marker of a listed finding that was accepted, with the reason
+fn accepted_tag(finding: Option<&AcceptedFinding>, messages: &Messages) -> Option<Cell> {
+    finding.map(|finding| Cell::text(messages.format("accepted.tag", &[("reason", &finding.reason)])).tone(Tone::Good))
+}
+
+/// Scan errors by cause, the subtrees they gather in, and the first of them one by one
+fn errors_section(summary: &ErrorSummary, top_count: usize, messages: &Messages) -> Section {
+    let mut section = Section::new(SectionKind::Errors, "⛔", messages.get("errors.title"));
+    for cause in &summary.causes {
+        section.rows.push(Row::Field {
+            label: cause.description.clone(),
+            value: vec![Cell::count(cause.count).tone(Tone::Warning), Cell::text(messages.get("errors.count"))],
+        });
+    }
+
+    let rollups = summary.rollups.iter().take(top_count).enumerate().map(|(i, rollup)| Row::Ranked {
+        rank: i + 1,
+        cells: vec![
+            Cell::path(&rollup.directory).tone(Tone::Accent),
+            Cell::text(" - "),
+            Cell::text(messages.format("errors.rollup", &[("count", &messages.grouped(rollup.count)), ("cause", &rollup.description)])),
+        ],
+        details: Vec::new(),
+    });
+    let rows: Vec<Row> = rollups.collect();
+    if !rows.is_empty() {
+        section.rows.push(Row::Group { title: messages.get("errors.subtrees").to_string(), rows });
+    }
+
+    // The complete list was asked for, so all of it is shown
+    let shown = if summary.complete { summary.errors.len() } else { top_count.min(summary.errors.len()) };
+    let rows: Vec<Row> = summary.errors[..shown]
+        .iter()
+        .map(|error| Row::Text { cells: vec![Cell::path(&error.path), Cell::text(" - "), Cell::text(&error.error).tone(Tone::Notice)] })
+        .collect();
+    section.rows.push(Row::Group { title: messages.get("errors.listed").to_string(), rows });
+    let hidden = summary.total - shown as u64;
+    if hidden > 0 {
+        section.rows.push(nothing_to_report(&messages.format("errors.more", &[("count", &messages.grouped(hidden))])));
+    }
+    section
+}
+
+fn diagnostics_section(aliases: &Aliases, messages: &Messages) -> Section {
+    let mut section = Section::new(SectionKind::Diagnostics, "🩺", messages.get("diagnostics.title"));
+
+    for alias in &aliases.directories {
+        section.rows.push(Row::Text {
+            cells: vec![
+                Cell::path(&alias.original),
+                Cell::text(messages.get("diagnostics.alias_and")),
+                Cell::path(&alias.alias),
+                Cell::text(messages.get("diagnostics.alias_same")),
+            ],
+        });
+    }
+    if aliases.unrecorded > 0 {
+        section.rows.push(Row::Field {
+            label: messages.get("diagnostics.unrecorded").to_string(),
+            value: vec![Cell::count(aliases.unrecorded), Cell::text(messages.get("diagnostics.unrecorded_note"))],
+        });
+    }
+    section
+}
+
+fn performance_section(results: &AnalysisResults, messages: &Messages) -> Section {
+    let stats = &results.statistics;
+    let mut section = Section::new(SectionKind::Performance, "⚡", messages.get("performance.title"));
+
+    section.rows.push(Row::Field {
+        label: messages.get("performance.speed").to_string(),
+        value: vec![Cell::new(Value::Number(stats.files_per_second, 0)), Cell::text(messages.get("performance.files_per_sec"))],
+    });
+    section.rows.push(Row::Field {
+        label: messages.get("performance.throughput").to_string(),
+        value: vec![Cell::bytes(stats.bytes_per_second), Cell::text(messages.get("performance.per_sec"))],
+    });
+    section.rows.push(Row::Field {
+        label: messages.get("performance.memory").to_string(),
+        value: vec![Cell::new(Value::Number(stats.memory_usage_mb, 1)), Cell::text(" MB")],
+    });
+
+    if !stats.resource_usage.is_empty() {
+        let total = ResourceUsage::total(stats.resource_usage.iter().map(|phase| &phase.usage));
+        section.rows.push(Row::Field {
+            label: messages.get("performance.cpu_time").to_string(),
+            value: vec![
+                Cell::new(Value::Number(total.user_cpu.as_secs_f64(), 2)),
+                Cell::text(messages.get("performance.cpu_user")),
+                Cell::new(Value::Number(total.system_cpu.as_secs_f64(), 2)),
+                Cell::text(messages.get("performance.cpu_system")),
+            ],
+        });
+        section.rows.push(Row::Field {
+            label: messages.get("performance.switches").to_string(),
+            value: vec![
+                Cell::count(total.voluntary_switches),
+                Cell::text(messages.get("performance.voluntary")),
+                Cell::count(total.involuntary_switches),
+                Cell::text(messages.get("performance.involuntary")),
+            ],
+        });
+        if let Some(bytes_read) = total.bytes_read {
+            section.rows.push(Row::field(messages.get("performance.read"), Cell::bytes(bytes_read)));
+        }
+
+        let phases = stats
+            .resource_usage
+            .iter()
+            .map(|phase| {
+                let mut value = vec![Cell::new(Value::Number(phase.usage.cpu_time().as_secs_f64(), 2)), Cell::text(messages.get("performance.phase_cpu"))];
+                if let Some(bytes_read) = phase.usage.bytes_read {
+                    value.push(Cell::text(", "));
+                    value.push(Cell::bytes(bytes_read));
+                    value.push(Cell::text(messages.get("performance.phase_read")));
+                }
+                Row::Field { label: phase.phase.clone(), value }
+            })
+            .collect();
+        section.rows.push(Row::Group { title: messages.get("performance.by_phase").to_string(), rows: phases });
+    }
+
+    if stats.duplicate_groups > 0 {
+        section.rows.push(Row::Field {
+            label: messages.get("performance.duplicate_detection").to_string(),
+            value: vec![Cell::count(stats.redundant_files), Cell::text(messages.get("performance.redundant_found"))],
+        });
+        section.rows.push(Row::field(
+            messages.get("performance.efficiency"),
+            Cell::new(Value::Percent(stats.compression_ratio * 100.0, 1)),
+        ));
+    }
+
+    if let Some(latency) = &stats.io_latency {
+        let operations = latency
+            .operations
+            .iter()
+            .map(|entry| {
+                let histogram = &entry.histogram;
+                Row::field(entry.operation.label(), Cell::text(messages.format("performance.latency_value", &[
+                    ("p50", &format_latency(histogram.percentile(0.50))),
+                    ("p95", &format_latency(histogram.percentile(0.95))),
+                    ("p99", &format_latency(histogram.percentile(0.99))),
+                    ("max", &format_latency(histogram.max_ns)),
+                    ("count", &messages.grouped(histogram.count)),
+                ])))
+            })
+            .collect();
+        section.rows.push(Row::Group { title: messages.get("performance.io_latency").to_string(), rows: operations });
+
+        if !latency.slowest.is_empty() {
+            let slowest = latency
+                .slowest
+                .iter()
+                .map(|operation| {
+                    Row::field(operation.operation.label(), Cell::text(messages.format("performance.slowest_value", &[
+                        ("latency", &format_latency(operation.nanos)),
+                        ("path", &operation.path.display()),
+                    ])).tone(Tone::Warning))
+                })
+                .collect();
+            section.rows.push(Row::Group { title: messages.get("performance.slowest_io").to_string(), rows: slowest });
+        }
+    }
+    section
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzer::{Diagnostics, DirectoryFilter, DirectoryInfo, DuplicateGroup, FileInfo, PhaseError, RegenerableTotals, Residuals, ScanInfo, Statistics, TypeStats, XattrFile};
+    use crate::entropy::DirectoryEntropy;
+    use crate::accepted::{AcceptedFinding, AcceptedTarget};
+    use crate::ages::{AgeBreakdown, AgeBucket, StaleFile, StaleFiles};
+    use crate::history::{GrowthAttribution, NewDuplicateGroup, ResolvedGroup, Trend, TrendWindow};
+    use crate::refilter::{AppliedSizeRange, SizeRange};
+    use crate::security::PermissionFinding;
+    use crate::scanner::{CollidingEntry, Coverage, DateFilter, DepthBoundary, DirectoryAlias, DiskUsage, ErrorLog, ErrorType, Ownership, PartialScan, ScanError};
+    use crate::utils::latency::{IoLatencyReport, IoOperation, LatencyHistogram, OperationLatency, SlowOperation};
+    use crate::utils::resource::PhaseUsage;
+    use crate::utils::time::ClockSkew;
+    use crate::utils::SizeBreakdown;
+    use crate::versions::{VersionGroup, VersionMember};
+    use chrono::{TimeZone, Utc};
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+    use colored::Colorize;
+
+    fn fixture_results() -> AnalysisResults {
+        let at = |d: u32| Utc.with_ymd_and_hms(2024, 3, d, 12, 30, 0).unwrap();
+        let file = |path: &str, size: u64, file_type: &str, modified: Option<chrono::DateTime<Utc>>| FileInfo {
+            path: PathBuf::from(path),
+            size,
+            file_type: file_type.to_string(),
+            modified,
+            created: None,
+            regenerable: false,
+            preview: None,
+            accepted: false,
+            symlink_target: None,
+            apparent_size: None,
+            sparse: false,
+        };
+
+        let mut file_type_distribution = HashMap::new();
+        file_type_distribution.insert("Videos".to_string(), TypeStats {
+            count: 2,
+            total_size: 3_000_000_000,
+            average_size: 1_500_000_000,
+            largest_file: Some(file("/data/media/movie.mkv", 2_000_000_000, "Videos", Some(at(1)))),
+        });
+        file_type_distribution.insert("Documents".to_string(), TypeStats {
+            count: 4,
+            total_size: 40_000_000,
+            average_size: 10_000_000,
+            largest_file: Some(file("/data/docs/report.pdf", 25_000_000, "Documents", Some(at(2)))),
+        });
+        file_type_distribution.insert("Other".to_string(), TypeStats {
+            count: 3,
+            total_size: 1_200,
+            average_size: 400,
+            largest_file: None,
+        });
+
+        AnalysisResults {
+            scan_info: ScanInfo {
+                path: PathBuf::from("/data"),
+                path_given: PathBuf::from("/data"),
+                path_resolved: PathBuf::from("/data"),
+                roots: vec![PathBuf::from("/data")],
+                timestamp: at(5),
+                depth_limit: Some(10),
+                min_depth: 0,
+                total_files: 9,
+                total_directories: 4,
+                total_size: 3_040_001_200,
+                scan_duration_ms: 2450,
+                coverage: Coverage { readable_entries: 3, unreadable_entries: 1 },
+                ownership: None,
+                date_filter: None,
+                error_count: 0,
+                xattr_total: None,
+                physical_size: None,
+                disk_usage: None,
+                depth_boundary: Default::default(),
+                partial: None,
+                seed: None,
+                clock_skew: None,
+                assumed_clock_skew_secs: None,
+                age_source: AgeSource::Modified,
+            },
+            size_breakdown: SizeBreakdown {
+                small_files_count: 3,
+                small_files_size: 1_200,
+                medium_files_count: 4,
+                medium_files_size: 40_000_000,
+                large_files_count: 2,
+                large_files_size: 3_000_000_000,
+            },
+            file_type_distribution,
+            largest_files: vec![
+                file("/data/media/movie.mkv", 2_000_000_000, "Videos", Some(at(1))),
+                file("/data/media/clip.mp4", 1_000_000_000, "Videos", None),
+                file("/data/docs/report.pdf", 25_000_000, "Documents", Some(at(2))),
+            ],
+            largest_directories: vec![
+                DirectoryInfo { path: PathBuf::from("/data"), size: 3_040_001_200, file_count: 3, recursive_file_count: 9, subdirectory_count: 2, recursive_subdirectory_count: 3, annotation: None, owner_mix: None, pinned: false, composition: Default::default(), accepted: false, root: 0 },
+                DirectoryInfo {
+                    path: PathBuf::from("/data/media"),
+                    size: 3_000_000_000,
+                    file_count: 2,
+                    recursive_file_count: 2,
+                    subdirectory_count: 0,
+                    recursive_subdirectory_count: 0,
+                    annotation: Some("Holiday footage. Cleanup: `make archive`".to_string()),
+                    owner_mix: None,
+                    pinned: false,
+                    composition: Default::default(),
+                    accepted: false,
+                    root: 0,
+                },
+            ],
+            duplicate_groups: Some(vec![
+                DuplicateGroup {
+                    group_id: String::new(),
+                    hash: "aa".to_string(),
+                    file_size: 5_000_000,
+                    files: vec![
+                        PathBuf::from("/data/docs/a.pdf"),
+                        PathBuf::from("/data/docs/b.pdf"),
+                        PathBuf::from("/data/docs/c.pdf"),
+                        PathBuf::from("/data/docs/d.pdf"),
+                    ],
+                    wasted_space: 15_000_000,
+                    newest_modified: Some(at(2)),
+                    priority: 41.7,
+                    partition: None,
+                    regenerable: false,
+                    first_seen: None,
+                    last_seen: None,
+                    accepted: false,
+                    unverified: false,
+                },
+                DuplicateGroup {
+                    group_id: String::new(),
+                    hash: "bb".to_string(),
+                    file_size: 400,
+                    files: vec![PathBuf::from("/data/x.txt"), PathBuf::from("/data/y.txt")],
+                    wasted_space: 400,
+                    newest_modified: None,
+                    priority: 3.2,
+                    partition: None,
+                    regenerable: false,
+                    first_seen: None,
+                    last_seen: None,
+                    accepted: false,
+                    unverified: false,
+                },
+            ]),
+            statistics: Statistics {
+                files_per_second: 3.67,
+                bytes_per_second: 1_240_816_816,
+                memory_usage_mb: 50.0,
+                duplicate_groups: 2,
+                redundant_files: 4,
+                reclaimable_bytes: 15_000_400,
+                compression_ratio: 0.995,
+                resource_usage: Vec::new(),
+                io_latency: None,
+            },
+            trend: Some(Trend {
+                previous_timestamp: at(4),
+                size_change_since_last: 34_000_000_000,
+                files_change_since_last: 3,
+                window: Some(TrendWindow { days: 30, size_change: -2_000_000 }),
+                growth: None,
+            }),
+            phase_errors: Vec::new(),
+            scan_errors: Default::default(),
+            directory_filter: None,
+            largest_xattr_files: Vec::new(),
+            diagnostics: Diagnostics::default(),
+            residuals: Residuals {
+                largest_files: Residual { count: 6, size: 15_001_200 },
+                largest_directories: Residual { count: 2, size: 40_001_200 },
+                ..Default::default()
+            },
+            regenerable: RegenerableTotals::default(),
+            entropy: None,
+            resolved_groups: None,
+            case_collisions: None,
+            heuristic_versions: None,
+            symlink_report: None,
+            skipped_mounts: None,
+            empty_directories: Vec::new(),
+            empty_files: Vec::new(),
+            annotations: Vec::new(),
+            accepted: Default::default(),
+            report_filters: None,
+            file_size_range: None,
+            entries: None,
+            custom_metrics: Vec::new(),
+            extensions: Vec::new(),
+            data_formats: None,
+            owners: HashMap::new(),
+            security_audit: None,
+            age_breakdown: AgeBreakdown::default(),
+            stale_files: None,
+        }
+    }
+
+    fn render_to_string(renderer: &dyn ReportRenderer, report: &Report) -> String {
+        let mut out = Vec::new();
+        renderer.render(report, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_terminal_renderer_matches_snapshot() {
+        colored::control::set_override(true);
+        let report = build_report(&fixture_results(), 3, Duration::from_millis(2450), &Messages::default());
+        let output = render_to_string(&TerminalRenderer, &report);
+        assert_eq!(output, include_str!("reporter/snapshots/terminal.txt"));
+    }
+
+    #[test]
+    fn test_summary_matches_snapshot() {
+        colored::control::set_override(true);
+        let mut out = Vec::new();
+        TerminalRenderer.render_summary(&build_summary(&fixture_results(), &Messages::default()), &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), include_str!("reporter/snapshots/summary.txt"));
+    }
+
+    #[test]
+    fn test_summary_notes_unreadable_paths() {
+        let mut results = fixture_results();
+        results.scan_errors.total = 1_204;
+        let summary = build_summary(&results, &Messages::default());
+        let mut out = Vec::new();
+        PlainRenderer.render_summary(&summary, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "Summary: 9 files, 4 directories, 3.04 GB total\n⚠ 1,204 paths could not be read; the totals leave them out\n"
+        );
+
+        let mut out = Vec::new();
+        MarkdownRenderer.render_summary(&summary, &mut out).unwrap();
+        assert!(String::from_utf8(out).unwrap().ends_with("\n\n⚠ 1,204 paths could not be read; the totals leave them out\n"));
+    }
+
+    #[test]
+    fn test_overview_reports_clock_skew() {
+        let mut results = fixture_results();
+        results.scan_info.clock_skew = Some(ClockSkew { files_ahead: 3_000, files_dated: 12_000, median_ahead_secs: 7_260 });
+        let text = render_to_string(&PlainRenderer, &build_report(&results, 3, Duration::ZERO, &Messages::default()));
+        assert!(text.contains("  Clock Skew: 3,000 files (25%) dated a median 2h 1m 0s in the future\n"));
+
+        results.scan_info.assumed_clock_skew_secs = Some(7_200);
+        let text = render_to_string(&PlainRenderer, &build_report(&results, 3, Duration::ZERO, &Messages::default()));
+        assert!(text.contains("in the future; ages corrected by 2h 0m 0s\n"));
+    }
+
+    #[test]
+    fn test_previews_are_shown_under_their_files() {
+        let mut results = fixture_results();
+        results.largest_files[0].preview = Some("PNG image, 1920×1080".to_string());
+        results.largest_files[2].preview = Some("# Report\nQ1 figures".to_string());
+
+        let text = render_to_string(&PlainRenderer, &build_report(&results, 3, Duration::from_millis(2450), &Messages::default()));
+        assert!(text.contains("     Modified: 2024-03-01 12:30 | Type: Videos\n     Preview: PNG image, 1920×1080\n"));
+        assert!(text.contains("     Preview: # Report\n     Q1 figures\n"));
+    }
+
+    #[test]
+    fn test_plain_renderer_has_no_escape_codes() {
+        let report = build_report(&fixture_results(), 3, Duration::from_millis(2450), &Messages::default());
+        let output = render_to_string(&PlainRenderer, &report);
+        assert!(!output.contains('\x1b'));
+        assert!(output.contains("  Total Size: 3.04 GB\n"));
+        assert!(output.contains("  1. 2 GB - /data/media/movie.mkv\n"));
+    }
+
+    #[test]
+    fn test_markdown_renderer() {
+        let report = build_report(&fixture_results(), 3, Duration::from_millis(2450), &Messages::default());
+        let output = render_to_string(&MarkdownRenderer, &report);
+        assert!(output.starts_with("# ANALYSIS REPORT\n"));
+        assert!(output.contains("\n## 📊 Overview\n"));
+        assert!(output.contains("- **Total Size:** **3.04 GB**\n"));
+        assert!(output.contains("1. **2 GB** - `/data/media/movie.mkv`\n"));
+        assert!(output.contains("   - `/data/docs/a.pdf`\n"));
+    }
+
+    #[test]
+    fn test_section_models_without_rendering() {
+        let report = build_report(&fixture_results(), 3, Duration::from_millis(2450), &Messages::default());
+
+        let overview = report.section(SectionKind::Overview).unwrap();
+        assert_eq!(overview.field("Total Files").unwrap()[0].value, Value::Count(9));
+        assert_eq!(overview.field("Reclaimable Space").unwrap()[0].value, Value::Bytes(15_000_400));
+
+        let files = report.section(SectionKind::LargestFiles).unwrap();
+        // Three entries and the line accounting for the rest
+        assert_eq!(files.rows.len(), 4);
+
+        let no_duplicates = AnalysisResults { duplicate_groups: None, ..fixture_results() };
+        let report = build_report(&no_duplicates, 3, Duration::from_millis(2450), &Messages::default());
+        assert!(report.section(SectionKind::Duplicates).is_none());
+        assert!(report.section(SectionKind::Degraded).is_none());
+        assert!(overview.field("Ownership Filter").is_none());
+    }
+
+    #[test]
+    fn test_residual_lines_complete_the_totals() {
+        let results = fixture_results();
+        let text = render_to_string(&PlainRenderer, &build_report(&results, 1, Duration::from_millis(2450), &Messages::default()));
+
+        // 3 GB of videos are listed; documents and other files make up the rest
+        assert!(text.contains("  ... and 2 more types totalling 40.00 MB\n"));
+        // The residual of the analysis adds to the two files cut by the report
+        assert!(text.contains("  ... and 8 more files totalling 1.04 GB\n"));
+        assert!(text.contains("    ... and 1 more groups wasting 400 B\n"));
+
+        let files = Residual::of(results.largest_files.iter().skip(1).map(|f| f.size)) + results.residuals.largest_files;
+        assert_eq!(results.largest_files[0].size + files.size, results.scan_info.total_size);
+        assert_eq!(1 + files.count, results.scan_info.total_files);
+    }
+
+    #[test]
+    fn test_regenerable_subtotals() {
+        let mut results = fixture_results();
+        let groups = results.duplicate_groups.as_mut().unwrap();
+        groups[0].regenerable = true;
+        results.largest_files[2].regenerable = true;
+        results.regenerable = RegenerableTotals {
+            files: 5,
+            size: 40_000_000,
+            duplicate_groups: 1,
+            wasted_space: 15_000_000,
+            hidden: false,
+        };
+
+        let text = render_to_string(&PlainRenderer, &build_report(&results, 3, Duration::from_millis(2450), &Messages::default()));
+        assert!(text.contains("  Actionable: 3.00 GB in 4 files\n"));
+        assert!(text.contains("  Regenerable: 40 MB in 5 files\n"));
+        assert!(text.contains("  3. 25 MB - /data/docs/report.pdf (regenerable)\n"));
+        assert!(text.contains("  Actionable Waste: 400 B\n"));
+        assert!(text.contains("  Regenerable Waste: 15 MB in 1 groups\n"));
+
+        results.regenerable.hidden = true;
+        let text = render_to_string(&PlainRenderer, &build_report(&results, 3, Duration::from_millis(2450), &Messages::default()));
+        assert!(text.contains("    1. 400 B (2 files) - 400 B wasted - priority 3\n"));
+        assert!(!text.contains("/data/docs/a.pdf"));
+        assert!(text.contains("    1 regenerable groups wasting 15 MB hidden\n"));
+        assert!(text.contains("  5 regenerable files totalling 40 MB hidden\n"));
+    }
+
+    #[test]
+    fn test_footer_notes_hidden_directories() {
+        let results = AnalysisResults {
+            directory_filter: Some(DirectoryFilter { min_size: 1_000_000, hidden_directories: 4213 }),
+            ..fixture_results()
+        };
+        let report = build_report(&results, 3, Duration::from_millis(2450), &Messages::default());
+        assert_eq!(report.footer[0], "Directories below 1 MB hidden: 4,213");
+    }
+
+    #[test]
+    fn test_scan_info_names_a_symlinked_root() {
+        let mut results = fixture_results();
+        let plain = render_to_string(&PlainRenderer, &build_report(&results, 3, Duration::from_millis(2450), &Messages::default()));
+        assert!(plain.contains("Path: /data\n"), "{}", plain);
+
+        results.scan_info.path_given = PathBuf::from("/mnt/link");
+        let plain = render_to_string(&PlainRenderer, &build_report(&results, 3, Duration::from_millis(2450), &Messages::default()));
+        assert!(plain.contains("Path: /data (via link /mnt/link)\n"), "{}", plain);
+    }
+
+    #[test]
+    fn test_scan_info_notes_entries_skipped_by_ignore_files() {
+        let mut results = fixture_results();
+        let plain = render_to_string(&PlainRenderer, &build_report(&results, 3, Duration::from_millis(2450), &Messages::default()));
+        assert!(!plain.contains("Ignore Files"), "{}", plain);
+
+        results.diagnostics.exclusions.ignored = Some(12_345);
+        let plain = render_to_string(&PlainRenderer, &build_report(&results, 3, Duration::from_millis(2450), &Messages::default()));
+        assert!(plain.contains("Ignore Files: 12345 entries skipped\n"), "{}", plain);
+    }
+
+    #[test]
+    fn test_overview_notes_the_file_size_range() {
+        let mut results = fixture_results();
+        results.file_size_range = Some(AppliedSizeRange {
+            range: SizeRange { min: Some(50_000_000), max: None },
+            affects_directories: false,
+            matched_files: 12,
+            collected_files: 3_400,
+        });
+        let plain = render_to_string(&PlainRenderer, &build_report(&results, 3, Duration::from_millis(2450), &Messages::default()));
+        assert!(plain.contains("File Sizes: at least 50 MB; listings cover 12 of 3,400 files, directory sizes count all\n"), "{}", plain);
+
+        let applied = results.file_size_range.as_mut().unwrap();
+        (applied.range.max, applied.affects_directories) = (Some(1_500_000_000), true);
+        let plain = render_to_string(&PlainRenderer, &build_report(&results, 3, Duration::from_millis(2450), &Messages::default()));
+        assert!(plain.contains("File Sizes: 50 MB to 1.50 GB; totals and listings cover 12 of 3,400 files collected\n"), "{}", plain);
+    }
+
+    #[test]
+    fn test_scan_info_lists_the_date_filter() {
+        let mut results = fixture_results();
+        results.scan_info.date_filter = Some(DateFilter {
+            before: Some("2024-01-01T00:00:00Z".parse().unwrap()),
+            after: None,
+            created_after: None,
+            undated_files: 2,
+            undated_examples: vec![PathBuf::from("/data/broken")],
+        });
+        let plain = render_to_string(&PlainRenderer, &build_report(&results, 3, Duration::from_millis(2450), &Messages::default()));
+        assert!(plain.contains("Modified Before: 2024-01-01 00:00\n"), "{}", plain);
+        assert!(!plain.contains("Modified After"), "{}", plain);
+        assert!(plain.contains("Undated Files: 2 left out without a readable modification time\n"), "{}", plain);
+    }
+
+    #[test]
+    fn test_disk_usage_shows_both_sizes_and_sparse_files() {
+        let mut results = fixture_results();
+        let allocated_size = results.scan_info.total_size;
+        results.scan_info.disk_usage = Some(DiskUsage { apparent_size: allocated_size + 7_000_000, allocated_size, sparse_files: 1, sparse_size: 8_000_000 });
+        results.largest_files[0].apparent_size = Some(8_000_000);
+        results.largest_files[0].sparse = true;
+        let plain = render_to_string(&PlainRenderer, &build_report(&results, 3, Duration::from_millis(2450), &Messages::default()));
+        assert!(plain.contains("Disk Usage: ") && !plain.contains("Total Size: "), "{}", plain);
+        assert!(plain.contains("Apparent Size: 3.05 GB\n"), "{}", plain);
+        assert!(plain.contains("Sparse Files: 1 with less than half of their 8 MB allocated\n"), "{}", plain);
+        assert!(plain.contains("Sparse: 8 MB apparent, mostly unallocated\n"), "{}", plain);
+    }
+
+    #[test]
+    fn test_scan_info_shows_unlimited_and_min_depth() {
+        let mut results = fixture_results();
+        results.scan_info.depth_limit = None;
+        results.scan_info.min_depth = 2;
+        let plain = render_to_string(&PlainRenderer, &build_report(&results, 3, Duration::from_millis(2450), &Messages::default()));
+        assert!(plain.contains("Depth Limit: unlimited\n"), "{}", plain);
+        assert!(plain.contains("Min Depth: 2\n"), "{}", plain);
+    }
+
+    #[test]
+    fn test_several_roots_are_listed_and_named_per_directory() {
+        let mut results = fixture_results();
+        let plain = render_to_string(&PlainRenderer, &build_report(&results, 3, Duration::from_millis(2450), &Messages::default()));
+        assert!(!plain.contains("Also Scanned") && !plain.contains("under root"), "{}", plain);
+
+        results.scan_info.roots = vec![PathBuf::from("/data"), PathBuf::from("/srv"), PathBuf::from("/var")];
+        results.largest_directories[1].root = 1;
+        let plain = render_to_string(&PlainRenderer, &build_report(&results, 3, Duration::from_millis(2450), &Messages::default()));
+        assert!(plain.contains("Also Scanned: /srv, /var\n"), "{}", plain);
+        assert!(plain.contains("under root /data\n") && plain.contains("under root /srv\n"), "{}", plain);
+    }
+
+    #[test]
+    fn test_largest_files_show_where_links_point() {
+        let mut results = fixture_results();
+        results.largest_files[0].symlink_target = Some(PathBuf::from("../shared/movie.mkv"));
+        let plain = render_to_string(&PlainRenderer, &build_report(&results, 3, Duration::from_millis(2450), &Messages::default()));
+        assert!(plain.contains("Links to: ../shared/movie.mkv\n"), "{}", plain);
+    }
+
+    #[test]
+    fn test_skipped_mounts_section() {
+        let mut results = fixture_results();
+        let report = build_report(&results, 1, Duration::from_millis(2450), &Messages::default());
+        assert!(report.section(SectionKind::SkippedMounts).is_none());
+
+        results.skipped_mounts = Some(Vec::new());
+        let plain = render_to_string(&PlainRenderer, &build_report(&results, 1, Duration::from_millis(2450), &Messages::default()));
+        assert!(plain.contains("No other filesystems below the root."), "{}", plain);
+
+        let mount = |path: &str, filesystem: Option<&str>| SkippedMount { path: PathBuf::from(path), device: 7, filesystem: filesystem.map(str::to_string) };
+        results.skipped_mounts = Some(vec![mount("/proc", Some("proc")), mount("/mnt/nas", None)]);
+        let plain = render_to_string(&PlainRenderer, &build_report(&results, 1, Duration::from_millis(2450), &Messages::default()));
+        for line in ["Skipped Mounts", "2 mount points and files on other filesystems were not scanned", "/proc - proc\n", "... and 1 more"] {
+            assert!(plain.contains(line), "missing {:?} in {}", line, plain);
+        }
+        assert!(!plain.contains("/mnt/nas"), "{}", plain);
+    }
+
+    #[test]
+    fn test_cleanup_candidates_section() {
+        let mut results = fixture_results();
+        let report = build_report(&results, 1, Duration::from_millis(2450), &Messages::default());
+        assert!(report.section(SectionKind::CleanupCandidates).is_none());
+
+        results.empty_directories = vec![PathBuf::from("/data/sync/a"), PathBuf::from("/data/sync/b")];
+        results.empty_files = vec![PathBuf::from("/data/sync/.lock")];
+        let plain = render_to_string(&PlainRenderer, &build_report(&results, 1, Duration::from_millis(2450), &Messages::default()));
+        for line in [
+            "Cleanup Candidates: 2 empty directories, 1 empty files",
+            "Empty Directories (2)",
+            "/data/sync/a\n",
+            "... and 1 more; --print-empty-dirs lists them all",
+            "Empty Files (1)",
+            "/data/sync/.lock\n",
+        ] {
+            assert!(plain.contains(line), "missing {:?} in {}", line, plain);
+        }
+        assert!(!plain.contains("/data/sync/b"), "{}", plain);
+    }
+
+    #[test]
+    fn test_custom_metrics_section() {
+        let mut results = fixture_results();
+        let report = build_report(&results, 3, Duration::from_millis(2450), &Messages::default());
+        assert!(report.section(SectionKind::CustomMetrics).is_none());
+
+        let metric = |name: &str, unit, value| CustomMetric { name: name.to_string(), expression: String::new(), unit, value };
+        results.custom_metrics = vec![
+            metric("media_bytes", MetricUnit::Bytes, Some(2_500_000_000.0)),
+            metric("cache_share", MetricUnit::Percent, Some(12.345)),
+            metric("logs", MetricUnit::Count, Some(4_213.0)),
+            metric("growth", MetricUnit::Bytes, Some(-1_500.0)),
+            metric("ratio", MetricUnit::Number, Some(0.5)),
+            metric("empty_average", MetricUnit::Bytes, None),
+        ];
+        let report = build_report(&results, 3, Duration::from_millis(2450), &Messages::default());
+        let plain = render_to_string(&PlainRenderer, &report);
+        for line in [
+            "Custom Metrics",
+            "media_bytes: 2.50 GB\n",
+            "cache_share: 12.3%\n",
+            "logs: 4213\n",
+            "growth: -1.50 kB\n",
+            "ratio: 0.50\n",
+            "empty_average: n/a\n",
+        ] {
+            assert!(plain.contains(line), "{:?} in {}", line, plain);
+        }
+    }
+
+    #[test]
+    fn test_diagnostics_list_directory_aliases() {
+        let mut results = fixture_results();
+        let report = build_report(&results, 3, Duration::from_millis(2450), &Messages::default());
+        assert!(report.section(SectionKind::Diagnostics).is_none());
+
+        results.diagnostics.aliases = Aliases {
+            directories: vec![DirectoryAlias { original: PathBuf::from("/data"), alias: PathBuf::from("/srv/export") }],
+            unrecorded: 0,
+        };
+        let report = build_report(&results, 3, Duration::from_millis(2450), &Messages::default());
+        let plain = render_to_string(&PlainRenderer, &report);
+        assert!(plain.contains("/data and /srv/export are the same filesystem subtree, counted once"), "{}", plain);
+        assert!(report.section(SectionKind::Diagnostics).unwrap().field("Alias Check").is_none());
+    }
+
+    #[test]
+    fn test_largest_directories_show_their_composition() {
+        colored::control::set_override(true);
+        let mut results = fixture_results();
+        let composition = |entries: &[(&str, u64)]| entries.iter().map(|(name, size)| (name.to_string(), *size)).collect();
+        results.largest_directories[0].composition =
+            composition(&[("Videos", 2_000_000_000), ("Documents", 1_000_000_000), ("Code", 40_001_200)]);
+        results.largest_directories[1].composition = composition(&[("Videos", 3_000_000_000)]);
+        let report = build_report(&results, 3, Duration::from_millis(2450), &Messages::default());
+
+        // Code is not among the tree's largest types, so it is drawn as Other
+        let plain = render_to_string(&PlainRenderer, &report);
+        assert_eq!(plain.matches("Composition: ").count(), 1, "{}", plain);
+        assert!(plain.contains("  Composition: V=Videos, D=Documents, O=Other\n"), "{}", plain);
+        assert!(plain.contains("1. 3.04 GB [VVVVVVVVDDDD] - /data\n"), "{}", plain);
+        assert!(plain.contains("2. 3 GB [VVVVVVVVVVVV] - /data/media\n"), "{}", plain);
+
+        let markdown = render_to_string(&MarkdownRenderer, &report);
+        assert!(markdown.contains("1. **3.04 GB** `[VVVVVVVVDDDD]` - `/data`\n"), "{}", markdown);
+
+        // Videos is blue wherever it is drawn
+        let terminal = render_to_string(&TerminalRenderer, &report);
+        assert!(terminal.contains(&"█".repeat(12).blue().to_string()), "{}", terminal);
+        assert!(terminal.contains(&format!("{} Videos", "██".blue())), "{}", terminal);
+    }
+
+    #[test]
+    fn test_xattr_section() {
+        let report = build_report(&fixture_results(), 3, Duration::from_millis(2450), &Messages::default());
+        assert!(report.section(SectionKind::ExtendedAttributes).is_none());
+
+        let mut results = fixture_results();
+        results.scan_info.xattr_total = Some(4_200);
+        results.largest_xattr_files = vec![XattrFile { path: PathBuf::from("/data/photo.jpg"), xattr_size: 4_000 }];
+        let report = build_report(&results, 3, Duration::from_millis(2450), &Messages::default());
+
+        let section = report.section(SectionKind::ExtendedAttributes).unwrap();
+        assert_eq!(section.field("Total Attribute Size").unwrap()[0].value, Value::Bytes(4_200));
+        let text = render_to_string(&PlainRenderer, &report);
+        assert!(text.contains("1. 4 kB - /data/photo.jpg"));
+    }
+
+    #[test]
+    fn test_entropy_section() {
+        let report = build_report(&fixture_results(), 3, Duration::from_millis(2450), &Messages::default());
+        assert!(report.section(SectionKind::Entropy).is_none());
+
+        let directory = |path: &str, high_entropy_size, previous_ratio: Option<f64>, jumped| DirectoryEntropy {
+            path: PathBuf::from(path),
+            sampled_size: 1_000_000,
+            high_entropy_size,
+            previous_ratio,
+            jumped,
+        };
+        let mut results = fixture_results();
+        results.entropy = Some(EntropyReport {
+            window_days: 7,
+            budget: 64_000_000,
+            bytes_read: 16_384,
+            sampled_files: 3,
+            skipped_files: 2,
+            sampled_size: 2_000_000,
+            high_entropy_size: 1_500_000,
+            directories: vec![
+                directory("/data/docs", 900_000, Some(0.05), true),
+                directory("/data/media", 600_000, Some(0.6), false),
+            ],
+            compared: true,
+            ..Default::default()
+        });
+        let text = render_to_string(&PlainRenderer, &build_report(&results, 3, Duration::from_millis(2450), &Messages::default()));
+
+        assert!(text.contains("3 files modified in the last 7 days, 16.38 kB of 64 MB read"), "{text}");
+        assert!(text.contains("Not Sampled: 2 recent files, read budget exhausted"), "{text}");
+        assert!(text.contains("High-Entropy Share: 75.0% of 2 MB"), "{text}");
+        assert!(text.contains("1. 90.0% - /data/docs"), "{text}");
+        assert!(text.contains("⚠ jumped from 5.0% on the last run"), "{text}");
+        assert!(text.contains("was 60.0% on the last run"), "{text}");
+        assert!(!text.contains("No earlier sampled run"), "{text}");
+    }
+
+    #[test]
+    fn test_resource_usage_rows() {
+        let plain = render_to_string(&PlainRenderer, &build_report(&fixture_results(), 3, Duration::from_millis(2450), &Messages::default()));
+        assert!(!plain.contains("CPU Time"));
+
+        let mut results = fixture_results();
+        let usage = |cpu_ms, bytes_read| ResourceUsage {
+            user_cpu: Duration::from_millis(cpu_ms),
+            system_cpu: Duration::from_millis(250),
+            voluntary_switches: 1_200,
+            involuntary_switches: 30,
+            bytes_read,
+        };
+        results.statistics.resource_usage = vec![
+            PhaseUsage { phase: "scan".to_string(), usage: usage(500, Some(2_000_000)) },
+            PhaseUsage { phase: "duplicates".to_string(), usage: usage(1_750, Some(40_000_000)) },
+        ];
+        let report = build_report(&results, 3, Duration::from_millis(2450), &Messages::default());
+
+        let section = report.section(SectionKind::Performance).unwrap();
+        assert_eq!(section.field("Read From Storage").unwrap()[0].value, Value::Bytes(42_000_000));
+        let text = render_to_string(&PlainRenderer, &report);
+        assert!(text.contains("CPU Time: 2.25s user, 0.50s system"));
+        assert!(text.contains("Context Switches: 2400 voluntary, 60 involuntary"));
+        assert!(text.contains("duplicates: 2.00s CPU, 40 MB read"));
+    }
+
+    #[test]
+    fn test_io_latency_rows() {
+        let plain = render_to_string(&PlainRenderer, &build_report(&fixture_results(), 3, Duration::from_millis(2450), &Messages::default()));
+        assert!(!plain.contains("I/O Latency"));
+
+        let mut stat = LatencyHistogram::default();
+        for _ in 0..99 {
+            stat.record(4_000);
+        }
+        stat.record(2_500_000_000);
+        let mut results = fixture_results();
+        results.statistics.io_latency = Some(IoLatencyReport {
+            operations: vec![OperationLatency { operation: IoOperation::Stat, histogram: stat }],
+            slowest: vec![SlowOperation { operation: IoOperation::Stat, path: PathBuf::from("/data/nfs/stuck"), nanos: 2_500_000_000 }],
+        });
+
+        let text = render_to_string(&PlainRenderer, &build_report(&results, 3, Duration::from_millis(2450), &Messages::default()));
+        assert!(text.contains("I/O Latency (p50 / p95 / p99 / max):"));
+        assert!(text.contains("stat: 4.1 µs / 4.1 µs / 4.1 µs / 2.50 s over 100 operations"));
+        assert!(text.contains("stat: 2.50 s on /data/nfs/stuck"));
+    }
+
+    #[test]
+    fn test_case_collision_rows() {
+        let mut results = fixture_results();
+        results.case_collisions = Some(Vec::new());
+        let text = render_to_string(&PlainRenderer, &build_report(&results, 1, Duration::from_millis(2450), &Messages::default()));
+        assert!(text.contains("Case Collisions: 0 groups of 0 sibling names that collide when case is ignored"));
+        assert!(text.contains("No sibling names collide when case is ignored."));
+
+        let modified = Utc.with_ymd_and_hms(2024, 3, 1, 9, 5, 0).unwrap();
+        let entry = |name: &str, is_dir: bool, size: u64| CollidingEntry { name: name.to_string(), is_dir, size, modified: Some(modified) };
+        results.case_collisions = Some(vec![
+            CaseCollision {
+                directory: PathBuf::from("/data/docs"),
+                folded: "report.pdf".to_string(),
+                entries: vec![entry("Report.pdf", false, 1_500), entry("report.pdf", false, 2_000)],
+            },
+            CaseCollision {
+                directory: PathBuf::from("/data"),
+                folded: "photos".to_string(),
+                entries: vec![entry("Photos", true, 4_000), entry("photos", true, 1_000)],
+            },
+        ]);
+        let text = render_to_string(&PlainRenderer, &build_report(&results, 1, Duration::from_millis(2450), &Messages::default()));
+        assert!(text.contains("Case Collisions: 2 groups of 4 sibling names that collide when case is ignored"));
+        assert!(text.contains("1. /data/docs - 2 names folding to \"report.pdf\""));
+        assert!(text.contains("Report.pdf - 1.50 kB - 2024-03-01 09:05"));
+        assert!(text.contains("... and 1 more groups with 5 kB"));
+    }
+
+    #[test]
+    fn test_annotations_are_shown_inline() {
+        let mut results = fixture_results();
+        let note = |path: &str, text: &str| Annotation { path: PathBuf::from(path), text: text.to_string(), added: Utc::now() };
+        results.annotations = vec![
+            note("/data/media", "owned by data team"),
+            note("/data/docs/report.pdf", "keep until Q3"),
+            note("/data/elsewhere", "listed nowhere"),
+        ];
+        let text = render_to_string(&PlainRenderer, &build_report(&results, 3, Duration::from_millis(2450), &Messages::default()));
+
+        // In the directory listing, and next to the same file in each section listing it
+        assert!(text.lines().any(|line| line.contains("/data/media  📝 owned by data team")));
+        let file_lines = text.lines().filter(|line| line.contains("/data/docs/report.pdf")).collect::<Vec<_>>();
+        assert!(file_lines.len() >= 2, "{:?}", file_lines);
+        assert!(file_lines.iter().all(|line| line.ends_with("📝 keep until Q3")));
+        assert!(!text.contains("listed nowhere"));
+        assert!(!text.contains("/data/media/movie.mkv  📝"));
+    }
+
+    #[test]
+    fn test_accepted_findings_are_counted_unless_shown() {
+        let mut results = fixture_results();
+        let finding = |target: AcceptedTarget, reason: &str| AcceptedFinding { target, reason: reason.to_string(), accepted: Utc::now() };
+        results.accepted.findings = vec![
+            finding(AcceptedTarget::Path(PathBuf::from("/data/media")), "supposed to be there"),
+            finding(AcceptedTarget::Path(PathBuf::from("/data/media/movie.mkv")), "master copy"),
+            finding(AcceptedTarget::Group("dg-00000000000000aa".to_string()), "kept on purpose"),
+        ];
+        results.largest_directories[1].accepted = true;
+        results.largest_files[0].accepted = true;
+        let groups = results.duplicate_groups.as_mut().unwrap();
+        groups[0].group_id = "dg-00000000000000aa".to_string();
+        groups[0].accepted = true;
+
+        let text = render_to_string(&PlainRenderer, &build_report(&results, 3, Duration::from_millis(2450), &Messages::default()));
+        assert!(text.contains("3 accepted findings are not listed; --show-accepted lists them"));
+        assert!(!text.contains("accepted:"));
+        // The rest move up, and the totals keep what was accepted
+        assert!(text.contains("1. 1 GB - /data/media/clip.mp4"));
+        assert!(!text.contains("- /data/media\n"));
+        assert!(!text.contains("/data/docs/a.pdf"));
+        assert!(text.contains("Total Size: 3.04 GB"));
+
+        results.accepted.shown = true;
+        let text = render_to_string(&PlainRenderer, &build_report(&results, 3, Duration::from_millis(2450), &Messages::default()));
+        assert!(!text.contains("not listed; --show-accepted"));
+        assert!(text.contains("/data/media/movie.mkv  ✔ accepted: master copy"));
+        assert!(text.contains("/data/docs/a.pdf"));
+        assert!(text.contains("✔ accepted: kept on purpose"));
+        assert!(text.contains("3. duplicate group dg-00000000000000aa - kept on purpose"));
+    }
+
+    #[test]
+    fn test_broken_link_rows() {
+        let mut results = fixture_results();
+        results.symlink_report = Some(SymlinkReport { valid: 12, out_of_tree: 3, broken: Vec::new() });
+        let text = render_to_string(&PlainRenderer, &build_report(&results, 1, Duration::from_millis(2450), &Messages::default()));
+        assert!(text.contains("Symlinks: 0 broken, 3 pointing outside the tree, 12 valid"));
+        assert!(text.contains("No broken symlinks."));
+
+        let link = |path: &str, target: &str, dangling: Option<&str>| BrokenLink {
+            path: PathBuf::from(path),
+            target: PathBuf::from(target),
+            dangling: dangling.map(PathBuf::from),
+        };
+        results.symlink_report.as_mut().unwrap().broken = vec![
+            link("/data/bin/tool", "/opt/tool/bin/tool", Some("/opt/tool/bin/tool")),
+            link("/data/www/current", "releases/42", Some("/data/www/releases/42")),
+            link("/data/www/latest", "current", Some("/data/www/releases/42")),
+            link("/data/www/loop", "loop", None),
+        ];
+        let text = render_to_string(&PlainRenderer, &build_report(&results, 1, Duration::from_millis(2450), &Messages::default()));
+        assert!(text.contains("Symlinks: 4 broken, 3 pointing outside the tree, 12 valid"));
+        // The directory with the most broken links first, listing as many as the top count
+        assert!(text.contains("  1. /data/www - 3 broken links\n     └─ current -> releases/42\n     ... and 2 more\n"));
+        assert!(text.contains("... and 1 more directories with 1 broken links"));
+
+        let text = render_to_string(&PlainRenderer, &build_report(&results, 3, Duration::from_millis(2450), &Messages::default()));
+        assert!(text.contains("├─ latest -> current (chain ends at missing /data/www/releases/42)\n"));
+        assert!(text.contains("└─ loop -> loop (loops or exceeds 40 hops)\n"));
+        assert!(text.contains("2. /data/bin - 1 broken links\n     └─ tool -> /opt/tool/bin/tool\n"));
+    }
+
+    #[test]
+    fn test_scan_error_rows() {
+        let mut results = fixture_results();
+        let text = render_to_string(&PlainRenderer, &build_report(&results, 2, Duration::from_millis(2450), &Messages::default()));
+        assert!(!text.contains("Scan Errors"));
+
+        let error = |path: String, errno: i32| ScanError {
+            path: PathBuf::from(path),
+            error: "failed".to_string(),
+            error_type: ErrorType::PermissionDenied,
+            errno: Some(errno),
+        };
+        let mut log = ErrorLog::new(false);
+        for i in 0..12 {
+            log.push(error(format!("/data/locked/user{}/private", i), libc::EACCES));
+        }
+        log.push(error("/data/tmp/gone".to_string(), libc::ENOENT));
+        let listed = log.take_listed();
+        results.scan_errors = log.summarize(&listed);
+        let text = render_to_string(&PlainRenderer, &build_report(&results, 2, Duration::from_millis(2450), &Messages::default()));
+        assert!(text.contains("Permission denied (os error 13): 12 errors"));
+        assert!(text.contains("1. /data/locked - 12 errors (Permission denied (os error 13))"));
+        assert!(text.contains("/data/locked/user1/private - failed\n"));
+        assert!(!text.contains("/data/locked/user2/private"));
+        assert!(text.contains("... and 11 more; --errors-full lists them all"));
+
+        results.scan_errors.complete = true;
+        let text = render_to_string(&PlainRenderer, &build_report(&results, 2, Duration::from_millis(2450), &Messages::default()));
+        assert!(text.contains("/data/tmp/gone - failed\n"));
+        assert!(!text.contains("more; --errors-full"));
+    }
+
+    #[test]
+    fn test_version_rows() {
+        let mut results = fixture_results();
+        results.heuristic_versions = Some(VersionReport::default());
+        let text = render_to_string(&PlainRenderer, &build_report(&results, 1, Duration::from_millis(2450), &Messages::default()));
+        assert!(text.contains("Probably Superseded: 0 B in 0 older versions across 0 groups (heuristic, by name)"));
+        assert!(text.contains("Probable Versions (heuristic)"));
+        assert!(text.contains("No files look like versions of one another."));
+
+        let member = |name: &str, size: u64, day: u32| VersionMember {
+            name: name.to_string(),
+            size,
+            modified: Some(Utc.with_ymd_and_hms(2024, 3, day, 9, 5, 0).unwrap()),
+        };
+        results.heuristic_versions = Some(VersionReport {
+            groups: vec![
+                VersionGroup {
+                    directory: PathBuf::from("/data/docs"),
+                    base_name: "report.docx".to_string(),
+                    members: vec![member("report_v2.docx", 3_000, 9), member("report (copy).docx", 2_000, 1)],
+                    superseded_bytes: 2_000,
+                },
+                VersionGroup {
+                    directory: PathBuf::from("/data"),
+                    base_name: "photo.jpg".to_string(),
+                    members: vec![member("photo.jpg", 500, 2), member("photo (1).jpg", 500, 1)],
+                    superseded_bytes: 500,
+                },
+            ],
+            superseded_files: 2,
+            superseded_bytes: 2_500,
+        });
+        let text = render_to_string(&PlainRenderer, &build_report(&results, 1, Duration::from_millis(2450), &Messages::default()));
+        assert!(text.contains("Probably Superseded: 2.50 kB in 2 older versions across 2 groups (heuristic, by name)"));
+        assert!(text.contains("1. /data/docs/report.docx - 2 versions, 2 kB in the older ones"));
+        assert!(text.contains("report_v2.docx - 3 kB - 2024-03-09 09:05 (newest)"));
+        assert!(text.contains("report (copy).docx - 2 kB - 2024-03-01 09:05"));
+        assert!(text.contains("... and 1 more groups with 500 B in older versions"));
+    }
+
+    #[test]
+    fn test_waste_by_partition() {
+        let mut results = fixture_results();
+        let groups = results.duplicate_groups.as_mut().unwrap();
+        groups[0].partition = Some(PathBuf::from("/data/docs"));
+        groups[1].partition = Some(PathBuf::from("/data"));
+        let report = build_report(&results, 3, Duration::from_millis(2450), &Messages::default());
+
+        let text = render_to_string(&PlainRenderer, &report);
+        assert!(text.contains("Waste by Partition:"));
+        assert!(text.contains("1. /data/docs - 15 MB wasted in 1 groups"));
+        assert!(text.contains("2. /data - 400 B wasted in 1 groups"));
+
+        let unpartitioned = render_to_string(&PlainRenderer, &build_report(&fixture_results(), 3, Duration::from_millis(2450), &Messages::default()));
+        assert!(!unpartitioned.contains("Waste by Partition"));
+    }
+
+    #[test]
+    fn test_overview_notes_depth_boundary() {
+        let mut results = fixture_results();
+        results.scan_info.depth_boundary = DepthBoundary {
+            directories: vec![PathBuf::from("/data/a/b"), PathBuf::from("/data/c/d")],
+            immediate_size: 2_500_000_000,
+            expanded: false,
+        };
+        let report = build_report(&results, 3, Duration::from_millis(2450), &Messages::default());
+        assert_eq!(
+            report.section(SectionKind::Overview).unwrap().field("Depth Limit").unwrap()[0].value,
+            Value::Text(
+                "depth limit hid at least 2 directories (~2.50 GB at the boundary); rerun with --auto-depth for complete totals"
+                    .to_string()
+            )
+        );
+
+        results.scan_info.depth_boundary.expanded = true;
+        let report = build_report(&results, 3, Duration::from_millis(2450), &Messages::default());
+        let cells = report.section(SectionKind::Overview).unwrap().field("Depth Limit").unwrap();
+        assert_eq!(cells[0].tone, Tone::Notice);
+        assert!(matches!(&cells[0].value, Value::Text(text) if text.starts_with("walked 2 directories below")));
+
+        results.scan_info.depth_boundary = DepthBoundary::default();
+        let report = build_report(&results, 3, Duration::from_millis(2450), &Messages::default());
+        assert!(report.section(SectionKind::Overview).unwrap().field("Depth Limit").is_none());
+    }
+
+    #[test]
+    fn test_overview_marks_partial_scans() {
+        let mut results = fixture_results();
+        results.scan_info.partial = Some(PartialScan { cap: CapKind::Bytes, limit: 50_000_000_000 });
+        let report = build_report(&results, 3, Duration::from_millis(2450), &Messages::default());
+        let cells = report.section(SectionKind::Overview).unwrap().field("Partial Scan").unwrap();
+        assert_eq!(cells[0].tone, Tone::Warning);
+        assert_eq!(
+            cells[0].value,
+            Value::Text("stopped at --max-bytes 50 GB; totals, listings, and duplicates cover only what was collected".to_string())
+        );
+
+        results.scan_info.partial = Some(PartialScan { cap: CapKind::Duration, limit: 90 });
+        let report = build_report(&results, 3, Duration::from_millis(2450), &Messages::default());
+        let cells = report.section(SectionKind::Overview).unwrap().field("Partial Scan").unwrap();
+        assert!(matches!(&cells[0].value, Value::Text(text) if text.starts_with("stopped at --max-duration 90s;")));
+
+        let complete = build_report(&fixture_results(), 3, Duration::from_millis(2450), &Messages::default());
+        assert!(complete.section(SectionKind::Overview).unwrap().field("Partial Scan").is_none());
+    }
+
+    #[test]
+    fn test_overview_notes_ownership_filter() {
+        let mut results = fixture_results();
+        results.scan_info.ownership = Some(Ownership {
+            user: "alice".to_string(),
+            uid: 1000,
+            matched_size: 250_000_000,
+            encountered_size: 1_000_000_000,
+        });
+        let report = build_report(&results, 3, Duration::from_millis(2450), &Messages::default());
+
+        let overview = report.section(SectionKind::Overview).unwrap();
+        assert_eq!(
+            overview.field("Ownership Filter").unwrap()[0].value,
+            Value::Text("files owned by alice (uid 1000) - 25.0% of 1 GB encountered".to_string())
+        );
+    }
+
+    #[test]
+    fn test_largest_directories_show_owner_mix() {
+        let owner = |user: &str, uid, size| DirectoryOwner { user: user.to_string(), uid, size };
+        let mut results = fixture_results();
+        results.largest_directories[0].owner_mix = Some(OwnerMix {
+            owners: vec![owner("alice", 1000, 1_824_000_720), owner("bob", 1001, 912_000_360), owner("1002", 1002, 304_000_120)],
+            clear_owner: false,
+        });
+        results.largest_directories[1].owner_mix =
+            Some(OwnerMix { owners: vec![owner("carol", 1003, 2_850_000_000)], clear_owner: true });
+
+        let text = render_to_string(&PlainRenderer, &build_report(&results, 3, Duration::from_millis(2450), &Messages::default()));
+        assert!(text.contains("mixed owners: alice 60.0%, bob 30.0%, 1002 10.0%"), "{}", text);
+        assert!(text.contains("owner: carol (95.0%)"), "{}", text);
+    }
+
+    #[test]
+    fn test_owners_section_ranks_users_by_size() {
+        let stats = |uid, count, total_size| OwnerStats { uid, count, total_size };
+        let mut results = fixture_results();
+        results.owners = HashMap::from([
+            ("alice".to_string(), stats(1000, 40, 1_500_000_000)),
+            ("bob".to_string(), stats(1001, 5, 3_000_000_000)),
+            ("1002".to_string(), stats(1002, 1, 20_000)),
+            ("carol".to_string(), stats(1003, 2, 10_000)),
+        ]);
+
+        let text = render_to_string(&PlainRenderer, &build_report(&results, 3, Duration::from_millis(2450), &Messages::default()));
+        assert!(text.contains("Disk Usage by Owner\n  1. 3 GB - bob (5 files, "), "{}", text);
+        assert!(text.contains("2. 1.50 GB - alice (40 files, "), "{}", text);
+        assert!(text.contains("... and 1 more owners with 10 kB"), "{}", text);
+
+        results.owners.clear();
+        assert!(build_report(&results, 3, Duration::ZERO, &Messages::default()).section(SectionKind::Owners).is_none());
+    }
+
+    #[test]
+    fn test_security_audit_follows_the_overview() {
+        let finding = |path: &str, mode, owner: Option<&str>| PermissionFinding {
+            path: PathBuf::from(path),
+            mode,
+            owner: owner.map(str::to_string),
+            size: 1_000,
+        };
+        let mut results = fixture_results();
+        results.security_audit = Some(SecurityAudit {
+            audited_entries: 1_200,
+            world_writable_files: vec![finding("/data/a.log", 0o666, Some("alice")), finding("/data/b.log", 0o666, None), finding("/data/c.log", 0o777, None)],
+            setuid_files: vec![finding("/data/bin/tool", 0o4755, Some("root"))],
+            setgid_files: Vec::new(),
+            open_directories: vec![finding("/data/drop", 0o777, None)],
+        });
+
+        let report = build_report(&results, 2, Duration::from_millis(2450), &Messages::default());
+        let kinds: Vec<_> = report.sections.iter().map(|section| section.kind).take(3).collect();
+        assert_eq!(kinds, [SectionKind::ScanInfo, SectionKind::Overview, SectionKind::SecurityAudit]);
+
+        let text = render_to_string(&PlainRenderer, &report);
+        assert!(text.contains("World-Writable Files: 3"), "{}", text);
+        assert!(text.contains("Setgid Files: 0"), "{}", text);
+        assert!(text.contains("/data/a.log - 0666, owner alice"), "{}", text);
+        assert!(text.contains("/data/bin/tool - 4755, owner root"), "{}", text);
+        assert!(text.contains("... and 1 more with 1 kB"), "{}", text);
+
+        results.security_audit = Some(SecurityAudit { audited_entries: 1_200, ..Default::default() });
+        let text = render_to_string(&PlainRenderer, &build_report(&results, 2, Duration::ZERO, &Messages::default()));
+        assert!(text.contains("No risky permissions found."), "{}", text);
+    }
+
+    #[test]
+    fn test_age_section_shows_buckets_and_stale_files() {
+        const DAY: u64 = 86_400;
+        let bucket = |min_days: u64, max_days: Option<u64>, count, total_size| AgeBucket {
+            min_age_secs: min_days * DAY,
+            max_age_secs: max_days.map(|days| days * DAY),
+            count,
+            total_size,
+        };
+        let stale = |path: &str, size| StaleFile { path: PathBuf::from(path), size, last_used: Utc.with_ymd_and_hms(2021, 5, 4, 10, 0, 0).unwrap() };
+        let mut results = fixture_results();
+        results.scan_info.age_source = AgeSource::Accessed;
+        results.age_breakdown = AgeBreakdown {
+            buckets: vec![bucket(0, Some(30), 2, 250_000), bucket(30, Some(365), 3, 250_000), bucket(365, None, 4, 400_000)],
+            undated_files: 1,
+            undated_size: 100_000,
+        };
+        results.stale_files = Some(StaleFiles {
+            threshold_secs: 365 * DAY,
+            count: 4,
+            total_size: 400_000,
+            largest: vec![stale("/data/old/a.iso", 300_000), stale("/data/old/b.iso", 60_000)],
+        });
+
+        let text = render_to_string(&PlainRenderer, &build_report(&results, 1, Duration::from_millis(2450), &Messages::default()));
+        assert!(text.contains("Based On: last access or modification, whichever is later"), "{}", text);
+        assert!(text.contains("Used within 30d: 2 files, 250 kB (25.0%)"), "{}", text);
+        assert!(text.contains("Unused for 30d to 1y: 3 files, 250 kB (25.0%)"), "{}", text);
+        assert!(text.contains("Unused for 1y or longer: 4 files, 400 kB (40.0%)"), "{}", text);
+        assert!(text.contains("Undated: 1 files, 100 kB (10.0%)"), "{}", text);
+        assert!(text.contains("1. 300 kB - /data/old/a.iso - last used 2021-05-04 10:00"), "{}", text);
+        assert!(text.contains("... and 3 more stale files with 100 kB"), "{}", text);
+    }
+
+    #[test]
+    fn test_pinned_directories_are_listed_past_the_top() {
+        let mut results = fixture_results();
+        results.largest_directories[1].pinned = true;
+        results.largest_directories.push(DirectoryInfo {
+            path: PathBuf::from("/data/docs"),
+            size: 25_000_000,
+            file_count: 1,
+            recursive_file_count: 1,
+            subdirectory_count: 0,
+            recursive_subdirectory_count: 0,
+            annotation: None,
+            owner_mix: None,
+            pinned: true,
+            composition: Default::default(),
+            accepted: false,
+            root: 0,
+        });
+
+        let text = render_to_string(&PlainRenderer, &build_report(&results, 2, Duration::from_millis(2450), &Messages::default()));
+        assert!(text.contains("📌 pinned"), "{}", text);
+        assert!(text.contains("Pinned Directories:\n    25 MB - /data/docs (1 files, 0 subdirectories)"), "{}", text);
+        // The residual still counts only what is not listed
+        assert!(text.contains("... and 2 more directories totalling 40.00 MB"), "{}", text);
+    }
+
+    #[test]
+    fn test_data_formats_are_listed_under_the_file_types() {
+        let mut results = fixture_results();
+        let text = render_to_string(&PlainRenderer, &build_report(&results, 2, Duration::from_millis(2450), &Messages::default()));
+        assert!(!text.contains("Data formats"), "{}", text);
+
+        let stats = |count: u64, total_size: u64, largest: &str| TypeStats {
+            count,
+            total_size,
+            average_size: total_size / count,
+            largest_file: Some(FileInfo {
+                path: PathBuf::from(largest),
+                size: total_size / count,
+                file_type: "Data".to_string(),
+                modified: None,
+                created: None,
+                regenerable: false,
+                preview: None,
+                accepted: false,
+                symlink_target: None,
+                apparent_size: None,
+                sparse: false,
+            }),
+        };
+        results.data_formats = Some(BTreeMap::from([
+            ("csv.gz".to_string(), stats(4, 4_000_000, "/data/lake/users.csv.gz")),
+            ("parquet".to_string(), stats(2, 12_000_000, "/data/lake/runs.parquet")),
+            ("npz".to_string(), stats(1, 1_000, "/data/arrays.npz")),
+        ]));
+
+        let text = render_to_string(&PlainRenderer, &build_report(&results, 2, Duration::from_millis(2450), &Messages::default()));
+        assert!(text.contains("Data formats:\n    1. .parquet files (2) - 12 MB (75.0%)\n       Largest: /data/lake/runs.parquet (6 MB)\n"), "{}", text);
+        assert!(text.contains("2. .csv.gz files (4) - 4 MB (25.0%)"), "{}", text);
+        assert!(text.contains("... and 1 more formats totalling 1 kB"), "{}", text);
+    }
+
+    #[test]
+    fn test_duplicates_report_resolved_groups() {
+        let mut results = fixture_results();
+        let resolved = |id: &str, reclaimed_space| ResolvedGroup {
+            group_id: id.to_string(),
+            hash: "cc".to_string(),
+            path: PathBuf::from("/data/old/a.iso"),
+            copies: 2,
+            file_size: reclaimed_space,
+            reclaimed_space,
+            first_seen: Utc.with_ymd_and_hms(2024, 1, 9, 0, 0, 0).unwrap(),
+            last_seen: Utc.with_ymd_and_hms(2024, 3, 1, 0, 0, 0).unwrap(),
+        };
+        results.resolved_groups = Some(vec![resolved("dg-1", 2_000_000_000), resolved("dg-2", 500_000_000)]);
+
+        let report = build_report(&results, 3, Duration::from_millis(2450), &Messages::default());
+        let field = report.section(SectionKind::Duplicates).unwrap().field("Resolved").unwrap();
+        assert_eq!(field[0].value, Value::Text("2 groups since the last run, 2.50 GB reclaimed".to_string()));
+
+        results.duplicate_groups = Some(Vec::new());
+        let report = build_report(&results, 3, Duration::from_millis(2450), &Messages::default());
+        assert!(report.section(SectionKind::Duplicates).unwrap().field("Resolved").is_some());
+    }
+
+    #[test]
+    fn test_overview_attributes_growth() {
+        let mut results = fixture_results();
+        results.trend.as_mut().unwrap().growth = Some(GrowthAttribution {
+            total_change: 200_000_000_000,
+            unique_change: 140_000_000_000,
+            duplication_change: 60_000_000_000,
+            top_new_group: Some(NewDuplicateGroup {
+                hash: "cc".to_string(),
+                path: PathBuf::from("/data/new/render.mov"),
+                wasted_space: 25_000_000_000,
+            }),
+        });
+        let report = build_report(&results, 3, Duration::from_millis(2450), &Messages::default());
+
+        let overview = report.section(SectionKind::Overview).unwrap();
+        assert_eq!(
+            overview.field("Growth").unwrap()[0].value,
+            Value::Text(
+                "+200 GB total: +140 GB unique, +60 GB duplication, top new duplicate group: /data/new/render.mov (25 GB wasted)"
+                    .to_string()
+            )
+        );
+        assert!(build_report(&fixture_results(), 3, Duration::ZERO, &Messages::default())
+            .section(SectionKind::Overview)
+            .unwrap()
+            .field("Growth")
+            .is_none());
+    }
+
+    #[test]
+    fn test_overview_shows_physically_unique_data() {
+        let overview = build_report(&fixture_results(), 3, Duration::from_millis(2450), &Messages::default());
+        assert!(overview.section(SectionKind::Overview).unwrap().field("Physically Unique Data").is_none());
+
+        let mut results = fixture_results();
+        results.scan_info.physical_size = Some(600_000_000);
+        let report = build_report(&results, 3, Duration::from_millis(2450), &Messages::default());
+        let overview = report.section(SectionKind::Overview).unwrap();
+        assert_eq!(overview.field("Physically Unique Data").unwrap()[0].value, Value::Bytes(600_000_000));
+    }
+
+    #[test]
+    fn test_degraded_results_are_marked_first() {
+        let degraded = AnalysisResults {
+            phase_errors: vec![PhaseError {
+                phase: "duplicates".to_string(),
+                message: "panic while hashing: boom".to_string(),
+                path: Some(PathBuf::from("/data/bad.bin")),
+            }],
+            ..fixture_results()
+        };
+        let report = build_report(&degraded, 3, Duration::from_millis(2450), &Messages::default());
+        assert_eq!(report.sections[0].kind, SectionKind::Degraded);
+
+        let text = render_to_string(&PlainRenderer, &report);
+        assert!(text.contains("Degraded Results"));
+        assert!(text.contains("duplicates: panic while hashing: boom - /data/bad.bin"));
+    }
+
+    #[test]
+    fn test_german_report() {
+        let messages = Messages::new(Lang::De);
+        let report = build_report(&fixture_results(), 3, Duration::from_millis(2450), &messages);
+        assert_eq!(report.title, "ANALYSEBERICHT");
+
+        let text = render_to_string(&PlainRenderer, &report);
+        assert!(text.contains("  Gesamtgröße: 3,04 GB\n"));
+        assert!(text.contains("  Zeitpunkt: 05.03.2024 12:30:00 UTC\n"));
+        assert!(text.contains("  1. Videos (2 Dateien) - 3 GB (98,7 %)\n"));
+        assert!(text.contains("Analyse abgeschlossen!"));
+
+        let markdown = render_to_string(&MarkdownRenderer, &report);
+        assert!(markdown.contains("\n## 📊 Überblick\n"));
+
+        let mut out = Vec::new();
+        PlainRenderer.render_summary(&build_summary(&fixture_results(), &messages), &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "Zusammenfassung: 9 Dateien, 4 Verzeichnisse, 3,04 GB insgesamt\n");
+    }
+
+    #[test]
+    fn test_mirror_report() {
+        use crate::mirror::Mismatch;
+
+        let diff = MirrorDiff {
+            source: PathBuf::from("/data"),
+            destination: PathBuf::from("/backup"),
+            missing: vec![PathBuf::from("a.txt"), PathBuf::from("b.txt"), PathBuf::from("c.txt")],
+            mismatched: vec![Mismatch {
+                path: PathBuf::from("d.bin"),
+                difference: Difference::Size { source: 2000, destination: 1000 },
+            }],
+            compared: 5,
+            ..Default::default()
+        };
+        let text = render_to_string(&PlainRenderer, &build_mirror_report(&diff, 2, &Messages::default()));
+
+        assert!(text.contains("  Missing in Destination: 3\n"));
+        assert!(text.contains("    b.txt\n    ... and 1 more\n"));
+        assert!(text.contains("    d.bin - size 2 kB vs 1 kB\n"));
+        assert!(text.contains("The trees differ."));
+
+        let identical = MirrorDiff { compared: 5, ..Default::default() };
+        let text = render_to_string(&PlainRenderer, &build_mirror_report(&identical, 2, &Messages::default()));
+        assert!(text.contains("The destination is an identical copy."));
+    }
+
+    #[test]
+    fn test_brief_renderer_matches_snapshot() {
+        let report = build_report(&fixture_results(), 20, Duration::from_millis(2450), &Messages::default());
+        let output = render_to_string(&BriefRenderer { color: false }, &report);
+        assert_eq!(output, include_str!("reporter/snapshots/brief.txt"));
+        assert!(output.lines().count() <= 10);
+
+        let no_duplicates = AnalysisResults { duplicate_groups: None, ..fixture_results() };
+        let report = build_report(&no_duplicates, 20, Duration::from_millis(2450), &Messages::default());
+        let output = render_to_string(&BriefRenderer { color: false }, &report);
+        assert!(!output.contains("Duplicates"));
+        assert!(output.ends_with("docs/report.pdf\n"));
+    }
+
+    #[test]
+    fn test_small_trees_default_to_brief() {
+        use clap::Parser;
+
+        let args = |extra: &[&str]| {
+            let mut argv = vec!["diranalyzer"];
+            argv.extend_from_slice(extra);
+            Args::parse_from(argv)
+        };
+        let small = fixture_results();
+        let mut large = fixture_results();
+        large.scan_info.total_files = 5_000;
+        let config = Config::default();
+
+        assert!(wants_brief(&small, &args(&[]), &config));
+        assert!(!wants_brief(&small, &args(&["--full-report"]), &config));
+        assert!(!wants_brief(&small, &args(&["--report-format", "markdown"]), &config));
+        assert!(!wants_brief(&large, &args(&[]), &config));
+        assert!(wants_brief(&large, &args(&["--brief"]), &config));
+        assert!(Args::try_parse_from(["diranalyzer", "--brief", "--full-report"]).is_err());
+    }
+
+    #[test]
+    fn test_extension_sections_follow_the_built_ins() {
+        colored::control::set_override(true);
+        let mut results = fixture_results();
+        results.extensions.push(
+            ExtensionSection::new("Chargeback")
+                .sized_row("analytics", "$46.00 per month", 2_000_000_000)
+                .row("web", "not scanned"),
+        );
+        let report = build_report(&results, 3, Duration::from_millis(2450), &Messages::default());
+
+        assert_eq!(report.sections.last().unwrap().kind, SectionKind::Extension);
+        let section = report.extension("Chargeback").unwrap();
+        assert_eq!(section.field("web").unwrap(), [Cell::text("not scanned")]);
+
+        let terminal = render_to_string(&TerminalRenderer, &report);
+        let performance = terminal.find("Performance Statistics").unwrap();
+        let chargeback = terminal.find("🧩 Chargeback").unwrap();
+        assert!(performance < chargeback, "{}", terminal);
+        assert!(terminal.contains(&format!("  analytics: $46.00 per month ({})\n", "2 GB".cyan())), "{}", terminal);
+
+        let markdown = render_to_string(&MarkdownRenderer, &report);
+        assert!(markdown.contains("\n## 🧩 Chargeback\n"), "{}", markdown);
+        assert!(markdown.contains("- **analytics:** $46.00 per month (2 GB)\n"), "{}", markdown);
+        assert!(markdown.contains("- **web:** not scanned\n"), "{}", markdown);
+
+        let json: serde_json::Value = serde_json::to_value(&results).unwrap();
+        assert_eq!(json["extensions"][0]["title"], "Chargeback");
+        assert_eq!(json["extensions"][0]["rows"][0]["size"], 2_000_000_000u64);
+        assert_eq!(json["extensions"][0]["rows"][1]["size"], serde_json::Value::Null);
+    }
 }
@@ -0,0 +1,113 @@
+//! Compact "biggest offenders" renderer
+//!
+//! Picks the totals, the top three directories and files, and the duplicate
+//! waste out of an already built report, so it needs no analysis of its own.
+
+use super::messages::Messages;
+use super::model::{Cell, Report, Row, Section, SectionKind, Summary, Value};
+use super::render::{format_value, PlainRenderer, ReportRenderer, TerminalRenderer};
+use colored::Colorize;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// Entries listed per ranking
+const BRIEF_TOP: usize = 3;
+
+/// About ten lines of the essentials, with paths relative to the scan root
+pub struct BriefRenderer {
+    pub color: bool,
+}
+
+impl ReportRenderer for BriefRenderer {
+    fn render(&self, report: &Report, out: &mut dyn Write) -> io::Result<()> {
+        let messages = &report.messages;
+        let root = report
+            .section(SectionKind::ScanInfo)
+            .and_then(|section| section.field(messages.get("scan_info.path")))
+            .and_then(first_path)
+            .unwrap_or_default();
+
+        if let Some(overview) = report.section(SectionKind::Overview) {
+            let value = |key| overview.field(messages.get(key)).and_then(|cells| cells.first());
+            let text = |cell: Option<&Cell>| cell.map(|cell| format_value(&cell.value, messages)).unwrap_or_default();
+            let header = messages.format("brief.header", &[
+                ("path", &root.display()),
+                ("size", &text(value("overview.total_size"))),
+                ("files", &text(value("overview.total_files"))),
+            ]);
+            writeln!(out, "{}", if self.color { header.bold().to_string() } else { header })?;
+        }
+
+        let listings: Vec<_> = [SectionKind::LargestDirectories, SectionKind::LargestFiles]
+            .into_iter()
+            .filter_map(|kind| report.section(kind))
+            .map(|section| (section.title.as_str(), top_entries(section, &root, messages)))
+            .filter(|(_, entries)| !entries.is_empty())
+            .collect();
+        let width = listings
+            .iter()
+            .flat_map(|(_, entries)| entries.iter().map(|(size, _)| size.chars().count()))
+            .max()
+            .unwrap_or(0);
+        for (title, entries) in &listings {
+            writeln!(out, "{}", title)?;
+            for (size, path) in entries {
+                writeln!(out, "  {:>width$}  {}", size, path, width = width)?;
+            }
+        }
+
+        if let Some(duplicates) = report.section(SectionKind::Duplicates) {
+            let wasted = duplicates.field(messages.get("duplicates.reclaimable")).and_then(|cells| cells.first());
+            let groups = duplicates.field(messages.get("duplicates.groups")).and_then(|cells| cells.first());
+            let line = match (wasted, groups) {
+                (Some(wasted), Some(groups)) => messages.format("brief.duplicates", &[
+                    ("size", &format_value(&wasted.value, messages)),
+                    ("groups", &format_value(&groups.value, messages)),
+                ]),
+                _ => messages.get("duplicates.none").trim().to_string(),
+            };
+            writeln!(out, "{}", line)?;
+        }
+        Ok(())
+    }
+
+    fn render_summary(&self, summary: &Summary, out: &mut dyn Write) -> io::Result<()> {
+        if self.color {
+            TerminalRenderer.render_summary(summary, out)
+        } else {
+            PlainRenderer.render_summary(summary, out)
+        }
+    }
+}
+
+/// Size and root-relative path of the first ranked entries of a listing
+fn top_entries(section: &Section, root: &Path, messages: &Messages) -> Vec<(String, String)> {
+    section
+        .rows
+        .iter()
+        .filter_map(|row| match row {
+            Row::Ranked { cells, .. } => {
+                let size = cells.iter().find(|cell| matches!(cell.value, Value::Bytes(_)))?;
+                let path = first_path(cells)?;
+                Some((format_value(&size.value, messages), relative(&path, root)))
+            }
+            _ => None,
+        })
+        .take(BRIEF_TOP)
+        .collect()
+}
+
+fn first_path(cells: &[Cell]) -> Option<PathBuf> {
+    cells.iter().find_map(|cell| match &cell.value {
+        Value::Path(path) => Some(path.clone()),
+        _ => None,
+    })
+}
+
+fn relative(path: &Path, root: &Path) -> String {
+    match path.strip_prefix(root) {
+        Ok(relative) if relative.as_os_str().is_empty() => ".".to_string(),
+        Ok(relative) => relative.display().to_string(),
+        Err(_) => path.display().to_string(),
+    }
+}
@@ -0,0 +1,587 @@
+//! Two runs side by side
+//!
+//! [`build_comparison`] lines up the overview numbers, the largest
+//! directories and files, and the file type distribution of an earlier and a
+//! later [`AnalysisResults`], with the change of every entry and, in ranked
+//! listings, how far it climbed or fell. Ranks are shared between entries of
+//! equal size, so ties that merely swapped places do not show as movement.
+//! An entry listed in only one run is marked new or gone; that says it left
+//! or entered the listing, not necessarily the tree.
+
+use super::messages::Messages;
+use super::model::{Cell, Tone, TimestampStyle, Value};
+use super::render::{format_value, paint};
+use super::format_size_change;
+use crate::analyzer::AnalysisResults;
+use crate::cli::ReportFormat;
+use crate::duplicates::duplicate_totals;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+/// Narrowest label column, whatever the width asked for
+const MIN_LABEL_WIDTH: usize = 12;
+
+/// Two runs compared section by section
+#[derive(Debug, Clone)]
+pub struct Comparison {
+    pub title: String,
+    pub before: RunLabel,
+    pub after: RunLabel,
+    pub sections: Vec<ComparisonSection>,
+    pub messages: Messages,
+}
+
+/// Which run a column shows
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunLabel {
+    pub path: PathBuf,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// A titled block of compared rows
+#[derive(Debug, Clone)]
+pub struct ComparisonSection {
+    pub icon: &'static str,
+    pub title: String,
+    /// Whether the rows carry ranks, as listings do
+    pub ranked: bool,
+    pub rows: Vec<ComparisonRow>,
+}
+
+/// One entry in both runs
+#[derive(Debug, Clone)]
+pub struct ComparisonRow {
+    /// Ranks in the earlier and the later run, for ranked sections
+    pub ranks: Option<(Option<usize>, Option<usize>)>,
+    pub label: Cell,
+    /// Value cells in the earlier run; `None` where the entry is not listed
+    pub before: Option<Vec<Cell>>,
+    /// Value cells in the later run; `None` where the entry is not listed
+    pub after: Option<Vec<Cell>>,
+    pub change: Change,
+}
+
+/// How an entry changed from the earlier run to the later one
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change {
+    /// Grew by the signed amount given
+    Grew(String),
+    /// Shrank by the signed amount given
+    Shrank(String),
+    Unchanged,
+    /// Listed only in the later run
+    Added,
+    /// Listed only in the earlier run
+    Removed,
+    /// Missing from a run that did not collect it, as duplicates without --duplicates
+    Unknown,
+}
+
+/// Formats a comparison for one output
+pub trait ComparisonRenderer {
+    fn render(&self, comparison: &Comparison, out: &mut dyn Write) -> io::Result<()>;
+}
+
+/// Two value columns next to each other, fitted into a terminal width
+pub struct SideBySideRenderer {
+    pub color: bool,
+    /// Columns available; long labels are shortened to fit
+    pub width: usize,
+}
+
+/// Markdown tables, one per section
+pub struct MarkdownComparisonRenderer;
+
+/// Select the comparison renderer for an output format
+pub fn comparison_renderer_for(format: &ReportFormat, width: usize) -> Box<dyn ComparisonRenderer> {
+    match format {
+        ReportFormat::Terminal => Box::new(SideBySideRenderer { color: true, width }),
+        ReportFormat::Plain => Box::new(SideBySideRenderer { color: false, width }),
+        ReportFormat::Markdown => Box::new(MarkdownComparisonRenderer),
+    }
+}
+
+/// Compare an earlier run with a later one, listing the top `top_count` entries of either
+pub fn build_comparison(before: &AnalysisResults, after: &AnalysisResults, top_count: usize, messages: &Messages) -> Comparison {
+    let label = |results: &AnalysisResults| RunLabel {
+        path: results.scan_info.path.clone(),
+        timestamp: results.scan_info.timestamp,
+    };
+    Comparison {
+        title: messages.get("compare.title").to_string(),
+        before: label(before),
+        after: label(after),
+        sections: vec![
+            overview_section(before, after, messages),
+            largest_directories_section(before, after, top_count, messages),
+            file_type_section(before, after, top_count, messages),
+            largest_files_section(before, after, top_count, messages),
+        ],
+        messages: *messages,
+    }
+}
+
+fn overview_section(before: &AnalysisResults, after: &AnalysisResults, messages: &Messages) -> ComparisonSection {
+    let count_row = |key, value: fn(&AnalysisResults) -> Option<u64>| {
+        let (old, new) = (value(before), value(after));
+        ComparisonRow {
+            ranks: None,
+            label: Cell::text(messages.get(key)),
+            before: old.map(|count| vec![Cell::count(count)]),
+            after: new.map(|count| vec![Cell::count(count)]),
+            change: change(old, new, |delta| signed(delta, messages.grouped(delta.unsigned_abs()))),
+        }
+    };
+    let bytes_row = |key, value: fn(&AnalysisResults) -> Option<u64>| {
+        let (old, new) = (value(before), value(after));
+        ComparisonRow {
+            ranks: None,
+            label: Cell::text(messages.get(key)),
+            before: old.map(|bytes| vec![Cell::bytes(bytes)]),
+            after: new.map(|bytes| vec![Cell::bytes(bytes)]),
+            change: change(old, new, |delta| format_size_change(delta, messages)),
+        }
+    };
+
+    let mut rows = vec![
+        count_row("overview.total_files", |results| Some(results.scan_info.total_files)),
+        count_row("overview.total_directories", |results| Some(results.scan_info.total_directories)),
+        bytes_row("overview.total_size", |results| Some(results.scan_info.total_size)),
+    ];
+    if before.scan_info.physical_size.is_some() || after.scan_info.physical_size.is_some() {
+        rows.push(bytes_row("overview.physical_size", |results| results.scan_info.physical_size));
+    }
+    if before.duplicate_groups.is_some() || after.duplicate_groups.is_some() {
+        let reclaimable = |results: &AnalysisResults| results.duplicate_groups.as_deref().map(|groups| duplicate_totals(groups).1);
+        rows.push(bytes_row("overview.reclaimable", reclaimable));
+    }
+
+    ComparisonSection { icon: "📊", title: messages.get("overview.title").to_string(), ranked: false, rows }
+}
+
+fn largest_directories_section(before: &AnalysisResults, after: &AnalysisResults, top_count: usize, messages: &Messages) -> ComparisonSection {
+    let sizes = |results: &AnalysisResults| {
+        ranked(results.largest_directories.iter().map(|directory| (directory.path.clone(), directory.size)).collect())
+    };
+    ComparisonSection {
+        icon: "📁",
+        title: messages.get("largest_directories.title").to_string(),
+        ranked: true,
+        rows: ranked_rows(&sizes(before), &sizes(after), top_count, |path, old, new| sized_row(Cell::path(path), old, new, messages)),
+    }
+}
+
+fn largest_files_section(before: &AnalysisResults, after: &AnalysisResults, top_count: usize, messages: &Messages) -> ComparisonSection {
+    let sizes = |results: &AnalysisResults| ranked(results.largest_files.iter().map(|file| (file.path.clone(), file.size)).collect());
+    ComparisonSection {
+        icon: "🗂️ ",
+        title: messages.get("largest_files.title").to_string(),
+        ranked: true,
+        rows: ranked_rows(&sizes(before), &sizes(after), top_count, |path, old, new| sized_row(Cell::path(path), old, new, messages)),
+    }
+}
+
+/// Types by total size, with their share of the run and how that share shifted
+fn file_type_section(before: &AnalysisResults, after: &AnalysisResults, top_count: usize, messages: &Messages) -> ComparisonSection {
+    let sizes = |results: &AnalysisResults| {
+        ranked(results.file_type_distribution.iter().map(|(name, stats)| (name.clone(), stats.total_size)).collect())
+    };
+    let share = |results: &AnalysisResults, size: u64| match results.scan_info.total_size {
+        0 => 0.0,
+        total => size as f64 / total as f64 * 100.0,
+    };
+    let cells = |results: &AnalysisResults, size: u64| {
+        vec![Cell::bytes(size), Cell::text(" ("), Cell::new(Value::Percent(share(results, size), 1)), Cell::text(")")]
+    };
+
+    let rows = ranked_rows(&sizes(before), &sizes(after), top_count, |name, old, new| {
+        let change = match (old, new) {
+            (Some(old), Some(new)) => {
+                let shift = share(after, new) - share(before, old);
+                let points = messages.format("compare.points", &[("points", &signed_decimal(shift, messages))]);
+                match change(Some(old), Some(new), |delta| format!("{}, {}", format_size_change(delta, messages), points)) {
+                    // An unchanged size still shifts its share when the total moved
+                    Change::Unchanged if (shift * 10.0).round() > 0.0 => Change::Grew(points),
+                    Change::Unchanged if (shift * 10.0).round() < 0.0 => Change::Shrank(points),
+                    change => change,
+                }
+            }
+            (old, new) => change(old, new, |_| String::new()),
+        };
+        ComparisonRow {
+            ranks: None,
+            label: Cell::text(messages.file_type(name)),
+            before: old.map(|size| cells(before, size)),
+            after: new.map(|size| cells(after, size)),
+            change,
+        }
+    });
+
+    ComparisonSection { icon: "📄", title: messages.get("file_types.title").to_string(), ranked: true, rows }
+}
+
+fn sized_row(label: Cell, old: Option<u64>, new: Option<u64>, messages: &Messages) -> ComparisonRow {
+    ComparisonRow {
+        ranks: None,
+        label,
+        before: old.map(|size| vec![Cell::bytes(size)]),
+        after: new.map(|size| vec![Cell::bytes(size)]),
+        change: change(old, new, |delta| format_size_change(delta, messages)),
+    }
+}
+
+/// The change between two values, with `delta` formatting the difference
+fn change(old: Option<u64>, new: Option<u64>, delta: impl Fn(i64) -> String) -> Change {
+    match (old, new) {
+        (Some(old), Some(new)) if new > old => Change::Grew(delta(new.abs_diff(old).try_into().unwrap_or(i64::MAX))),
+        (Some(old), Some(new)) if new < old => Change::Shrank(delta(-new.abs_diff(old).try_into().unwrap_or(i64::MAX))),
+        (Some(_), Some(_)) => Change::Unchanged,
+        _ => Change::Unknown,
+    }
+}
+
+fn signed(delta: i64, magnitude: String) -> String {
+    format!("{}{}", if delta < 0 { '-' } else { '+' }, magnitude)
+}
+
+fn signed_decimal(value: f64, messages: &Messages) -> String {
+    format!("{}{}", if value < 0.0 { '-' } else { '+' }, messages.decimal(value.abs(), 1))
+}
+
+/// Competition ranks by size, largest first: equal sizes share a rank
+fn ranked<K: Eq + Hash + Ord>(mut entries: Vec<(K, u64)>) -> HashMap<K, (usize, u64)> {
+    entries.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    let mut ranks = HashMap::with_capacity(entries.len());
+    let mut previous = None;
+    let mut rank = 0;
+    for (index, (key, size)) in entries.into_iter().enumerate() {
+        if previous != Some(size) {
+            (rank, previous) = (index + 1, Some(size));
+        }
+        ranks.entry(key).or_insert((rank, size));
+    }
+    ranks
+}
+
+/// Rows of the entries ranked within `top_count` in either run, by their later rank
+///
+/// Entries that dropped out of the later run come last, by their earlier rank.
+/// Ties at the cutoff are all kept.
+fn ranked_rows<K: Clone + Eq + Hash + Ord>(
+    before: &HashMap<K, (usize, u64)>,
+    after: &HashMap<K, (usize, u64)>,
+    top_count: usize,
+    row: impl Fn(&K, Option<u64>, Option<u64>) -> ComparisonRow,
+) -> Vec<ComparisonRow> {
+    let mut keys: Vec<&K> = before
+        .iter()
+        .chain(after.iter())
+        .filter(|(_, (rank, _))| *rank <= top_count)
+        .map(|(key, _)| key)
+        .collect();
+    keys.sort();
+    keys.dedup();
+    let rank = |listing: &HashMap<K, (usize, u64)>, key: &K| listing.get(key).map(|(rank, _)| *rank);
+    keys.sort_by_key(|key| (rank(after, key).unwrap_or(usize::MAX), rank(before, key).unwrap_or(usize::MAX), *key));
+
+    keys.into_iter()
+        .map(|key| {
+            let (old, new) = (before.get(key).map(|(_, size)| *size), after.get(key).map(|(_, size)| *size));
+            let mut row = row(key, old, new);
+            row.change = match (old, new) {
+                (None, Some(_)) => Change::Added,
+                (Some(_), None) => Change::Removed,
+                _ => row.change,
+            };
+            row.ranks = Some((rank(before, key), rank(after, key)));
+            row
+        })
+        .collect()
+}
+
+/// The rank column: the later rank, or a dash for entries gone from the listing
+fn rank_text(ranks: (Option<usize>, Option<usize>)) -> String {
+    match ranks.1 {
+        Some(rank) => format!("{}.", rank),
+        None => "–".to_string(),
+    }
+}
+
+/// Rank movement arrows for entries listed in both runs
+fn movement(ranks: (Option<usize>, Option<usize>)) -> String {
+    match ranks {
+        (Some(old), Some(new)) if new < old => format!("↑{}", old - new),
+        (Some(old), Some(new)) if new > old => format!("↓{}", new - old),
+        _ => String::new(),
+    }
+}
+
+fn change_text(change: &Change, messages: &Messages) -> (String, Tone) {
+    match change {
+        Change::Grew(amount) => (format!("▲ {}", amount), Tone::Warning),
+        Change::Shrank(amount) => (format!("▼ {}", amount), Tone::Good),
+        Change::Unchanged => ("=".to_string(), Tone::Plain),
+        Change::Added => (messages.get("compare.added").to_string(), Tone::Notice),
+        Change::Removed => (messages.get("compare.removed").to_string(), Tone::Notice),
+        Change::Unknown => (String::new(), Tone::Plain),
+    }
+}
+
+fn value_text(cells: &Option<Vec<Cell>>, messages: &Messages) -> String {
+    match cells {
+        Some(cells) => cells.iter().map(|cell| format_value(&cell.value, messages)).collect(),
+        None => "—".to_string(),
+    }
+}
+
+fn width(text: &str) -> usize {
+    text.chars().count()
+}
+
+/// Shorten `text` to `width` columns with an ellipsis; paths keep their end
+fn fit(text: &str, width: usize, keep_end: bool) -> String {
+    let length = self::width(text);
+    if length <= width {
+        return text.to_string();
+    }
+    let kept = width.saturating_sub(1);
+    if keep_end {
+        format!("…{}", text.chars().skip(length - kept).collect::<String>())
+    } else {
+        format!("{}…", text.chars().take(kept).collect::<String>())
+    }
+}
+
+/// A row of the side-by-side layout as plain text
+struct Line {
+    prefix: String,
+    label: String,
+    is_path: bool,
+    before: String,
+    after: String,
+    change: String,
+    tone: Tone,
+}
+
+impl SideBySideRenderer {
+    const RULE_WIDTH: usize = 50;
+    const INDENT: &'static str = "  ";
+    const GAP: &'static str = "  ";
+
+    fn paint(&self, text: String, tone: Tone, strong: bool) -> String {
+        paint(text, tone, strong, self.color)
+    }
+
+    fn lines(section: &ComparisonSection, messages: &Messages) -> Vec<Line> {
+        let ranks: Vec<_> = section.rows.iter().filter_map(|row| row.ranks).collect();
+        let rank_width = ranks.iter().map(|ranks| width(&rank_text(*ranks))).max().unwrap_or(0);
+        let movement_width = ranks.iter().map(|ranks| width(&movement(*ranks))).max().unwrap_or(0);
+        section
+            .rows
+            .iter()
+            .map(|row| {
+                let prefix = match row.ranks {
+                    Some(ranks) if movement_width > 0 => format!(
+                        "{:>rank_width$} {:<movement_width$} ",
+                        rank_text(ranks),
+                        movement(ranks),
+                    ),
+                    Some(ranks) => format!("{:>rank_width$} ", rank_text(ranks)),
+                    None => String::new(),
+                };
+                let (change, tone) = change_text(&row.change, messages);
+                Line {
+                    prefix,
+                    label: format_value(&row.label.value, messages),
+                    is_path: matches!(row.label.value, Value::Path(_)),
+                    before: value_text(&row.before, messages),
+                    after: value_text(&row.after, messages),
+                    change,
+                    tone,
+                }
+            })
+            .collect()
+    }
+}
+
+impl ComparisonRenderer for SideBySideRenderer {
+    fn render(&self, comparison: &Comparison, out: &mut dyn Write) -> io::Result<()> {
+        let messages = &comparison.messages;
+        writeln!(out, "\n{}", self.paint(format!("📋 {}", comparison.title), Tone::Accent, true))?;
+        writeln!(out, "{}", self.paint("=".repeat(Self::RULE_WIDTH), Tone::Accent, false))?;
+        let (before, after) = (messages.get("compare.before"), messages.get("compare.after"));
+        let run_width = width(before).max(width(after)) + 1;
+        for (label, run) in [(before, &comparison.before), (after, &comparison.after)] {
+            writeln!(out, "{}{:<run_width$} {} ({})", Self::INDENT, format!("{}:", label), run.path.display(),
+                messages.timestamp(&run.timestamp, TimestampStyle::Minutes))?;
+        }
+
+        let sections: Vec<_> = comparison.sections.iter().map(|section| (section, Self::lines(section, messages))).collect();
+        let all = || sections.iter().flat_map(|(_, lines)| lines.iter());
+        let before_width = all().map(|line| width(&line.before)).chain([width(before)]).max().unwrap_or(0);
+        let after_width = all().map(|line| width(&line.after)).chain([width(after)]).max().unwrap_or(0);
+        let change_width = all().map(|line| width(&line.change)).chain([width(messages.get("compare.change"))]).max().unwrap_or(0);
+
+        // The label column takes what the value columns leave, so that they line up across sections
+        let fixed = width(Self::INDENT) + 3 * width(Self::GAP) + before_width + after_width + change_width;
+        let longest_prefix = all().map(|line| width(&line.prefix)).max().unwrap_or(0);
+        let natural = all().map(|line| width(&line.prefix) + width(&line.label)).max().unwrap_or(0);
+        let label_width = natural.min(self.width.saturating_sub(fixed)).max(longest_prefix + MIN_LABEL_WIDTH);
+
+        for (section, lines) in &sections {
+            writeln!(out, "\n{}", self.paint(format!("{} {}", section.icon, section.title), Tone::Warning, true))?;
+            if lines.is_empty() {
+                writeln!(out, "{}{}", Self::INDENT, self.paint(messages.get("compare.none").to_string(), Tone::Notice, false))?;
+                continue;
+            }
+            let header = format!("{:label_width$}{gap}{:>before_width$}{gap}{:>after_width$}{gap}{}",
+                "", before, after, messages.get("compare.change"), gap = Self::GAP);
+            writeln!(out, "{}{}", Self::INDENT, self.paint(header, Tone::Plain, true))?;
+
+            for line in lines {
+                let label = fit(&line.label, label_width - width(&line.prefix), line.is_path);
+                let padding = label_width - width(&line.prefix) - width(&label);
+                let row = format!("{}{}{}{gap}{:>before_width$}{gap}{:>after_width$}{gap}",
+                    line.prefix, label, " ".repeat(padding), line.before, line.after, gap = Self::GAP);
+                writeln!(out, "{}{}{}", Self::INDENT, row, self.paint(line.change.clone(), line.tone, false).trim_end())?;
+            }
+        }
+
+        writeln!(out, "\n{}", self.paint("=".repeat(Self::RULE_WIDTH), Tone::Accent, false))
+    }
+}
+
+impl ComparisonRenderer for MarkdownComparisonRenderer {
+    fn render(&self, comparison: &Comparison, out: &mut dyn Write) -> io::Result<()> {
+        let messages = &comparison.messages;
+        writeln!(out, "# {}\n", comparison.title)?;
+        for (key, run) in [("compare.before", &comparison.before), ("compare.after", &comparison.after)] {
+            writeln!(out, "- **{}:** `{}` ({})", messages.get(key), run.path.display(),
+                messages.timestamp(&run.timestamp, TimestampStyle::Minutes))?;
+        }
+
+        let (before, after, change) = (messages.get("compare.before"), messages.get("compare.after"), messages.get("compare.change"));
+        for section in &comparison.sections {
+            writeln!(out, "\n## {} {}\n", section.icon.trim_end(), section.title)?;
+            if section.rows.is_empty() {
+                writeln!(out, "_{}_", messages.get("compare.none"))?;
+                continue;
+            }
+            if section.ranked {
+                writeln!(out, "| # | | {} | {} | {} |\n| ---: | --- | ---: | ---: | --- |", before, after, change)?;
+            } else {
+                writeln!(out, "| | {} | {} | {} |\n| --- | ---: | ---: | --- |", before, after, change)?;
+            }
+
+            for row in &section.rows {
+                let label = format_value(&row.label.value, messages).replace('|', "\\|");
+                let label = if matches!(row.label.value, Value::Path(_)) { format!("`{}`", label) } else { label };
+                let cells = format!("{} | {} | {} | {} |", label, value_text(&row.before, messages),
+                    value_text(&row.after, messages), change_text(&row.change, messages).0);
+                match row.ranks {
+                    Some(ranks) => writeln!(out, "| {} | {}", format!("{} {}", rank_text(ranks), movement(ranks)).trim_end(), cells)?,
+                    None => writeln!(out, "| {}", cells)?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    /// The golden run and a week later: docs grew past media, a new directory
+    /// tied with media, archive and the code files are gone
+    fn fixture_pair() -> (AnalysisResults, AnalysisResults) {
+        let before: AnalysisResults = serde_json::from_str(include_str!("../../tests/golden/results.json")).unwrap();
+        let mut after = before.clone();
+        after.scan_info.timestamp = Utc.with_ymd_and_hms(2024, 3, 8, 12, 0, 0).unwrap();
+        (after.scan_info.total_files, after.scan_info.total_size) = (12, 700_000);
+        let directories = &mut after.largest_directories;
+        directories[0].size = 700_000;
+        directories[1].size = 120_000;
+        directories[2].size = 400_000;
+        directories[4].path = PathBuf::from("<root>/downloads/installers/2024/march/vendor-tools/linux");
+        directories[4].size = 120_000;
+        after.file_type_distribution.get_mut("Documents").unwrap().total_size = 390_000;
+        after.file_type_distribution.remove("Code");
+        (before, after)
+    }
+
+    fn render(renderer: &dyn ComparisonRenderer, comparison: &Comparison) -> String {
+        let mut out = Vec::new();
+        renderer.render(comparison, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_side_by_side_matches_snapshot() {
+        let (before, after) = fixture_pair();
+        let comparison = build_comparison(&before, &after, 10, &Messages::default());
+        let output = render(&SideBySideRenderer { color: false, width: 100 }, &comparison);
+        assert_eq!(output, include_str!("snapshots/compare.txt"));
+    }
+
+    #[test]
+    fn test_markdown_matches_snapshot() {
+        let (before, after) = fixture_pair();
+        let comparison = build_comparison(&before, &after, 10, &Messages::default());
+        assert_eq!(render(&MarkdownComparisonRenderer, &comparison), include_str!("snapshots/compare.md"));
+    }
+
+    #[test]
+    fn test_ties_share_a_rank() {
+        let ranks = ranked(vec![("a", 5), ("b", 9), ("c", 5), ("d", 1)]);
+        assert_eq!(ranks["b"], (1, 9));
+        assert_eq!((ranks["a"], ranks["c"]), ((2, 5), (2, 5)));
+        assert_eq!(ranks["d"], (4, 1));
+
+        // Two files of equal size swapping places do not move
+        let (before, mut after) = fixture_pair();
+        after.largest_files.swap(2, 3);
+        let comparison = build_comparison(&before, &after, 10, &Messages::default());
+        let files = &comparison.sections[3];
+        assert!(files.rows.iter().all(|row| row.change == Change::Unchanged && movement(row.ranks.unwrap()).is_empty()));
+    }
+
+    #[test]
+    fn test_entries_of_one_run_and_the_cutoff() {
+        let (before, after) = fixture_pair();
+        let comparison = build_comparison(&before, &after, 2, &Messages::default());
+        let directories = &comparison.sections[1];
+        let labels: Vec<String> = directories.rows.iter().map(|row| format_value(&row.label.value, &comparison.messages)).collect();
+        // Media fell to a shared third place but was second before, so it stays
+        assert_eq!(labels, ["<root>", "<root>/docs", "<root>/media"]);
+        assert_eq!(directories.rows[2].ranks, Some((Some(2), Some(3))));
+
+        let comparison = build_comparison(&before, &after, 10, &Messages::default());
+        let rows = &comparison.sections[1].rows;
+        let new = rows.iter().find(|row| row.change == Change::Added).unwrap();
+        assert_eq!((new.ranks, new.before.is_none()), (Some((None, Some(3))), true));
+        let gone = rows.last().unwrap();
+        assert_eq!((gone.ranks, &gone.change), (Some((Some(5), None)), &Change::Removed));
+    }
+
+    #[test]
+    fn test_narrow_terminals_shorten_labels() {
+        let (before, after) = fixture_pair();
+        let comparison = build_comparison(&before, &after, 10, &Messages::default());
+        let output = render(&SideBySideRenderer { color: false, width: 80 }, &comparison);
+        for line in output.lines().filter(|line| line.starts_with("  ")) {
+            assert!(line.chars().count() <= 80, "too wide: {:?}", line);
+        }
+        // Paths keep their end, other labels their start
+        assert!(output.contains(" …ndor-tools/linux "));
+        assert!(output.contains(" …oot>/media/clip.mp4 "));
+
+        // Below the narrowest label column, lines overflow rather than lose the label
+        let output = render(&SideBySideRenderer { color: false, width: 20 }, &comparison);
+        assert!(output.contains("Total Directories"));
+    }
+}
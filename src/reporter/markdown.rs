@@ -0,0 +1,79 @@
+//! Markdown report renderer
+
+use super::messages::Messages;
+use super::model::{Cell, Report, Row, Summary, Value};
+use super::render::{format_value, ReportRenderer};
+use std::io::{self, Write};
+
+/// Markdown output suitable for wikis, issues, and chat
+pub struct MarkdownRenderer;
+
+impl ReportRenderer for MarkdownRenderer {
+    fn render(&self, report: &Report, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "# {}", report.title)?;
+
+        for section in &report.sections {
+            writeln!(out, "\n## {} {}\n", section.icon.trim_end(), section.title)?;
+            for row in &section.rows {
+                render_row(row, 0, &report.messages, out)?;
+            }
+        }
+
+        if !report.footer.is_empty() {
+            writeln!(out, "\n---\n")?;
+            for line in &report.footer {
+                writeln!(out, "_{}_", line)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn render_summary(&self, summary: &Summary, out: &mut dyn Write) -> io::Result<()> {
+        let messages = &summary.messages;
+        writeln!(out, "**{}**: {}", messages.get("summary.label"), messages.format("summary.line", &[
+            ("files", &summary.total_files),
+            ("directories", &summary.total_directories),
+            ("size", &messages.bytes(summary.total_size)),
+        ]))?;
+        if summary.unreadable_paths > 0 {
+            writeln!(out, "\n{}", messages.format("summary.unreadable", &[("count", &messages.grouped(summary.unreadable_paths))]))?;
+        }
+        Ok(())
+    }
+}
+
+fn render_row(row: &Row, indent: usize, messages: &Messages, out: &mut dyn Write) -> io::Result<()> {
+    let pad = " ".repeat(indent);
+    match row {
+        Row::Field { label, value } => {
+            writeln!(out, "{}- **{}:** {}", pad, label, cells(value, messages))?;
+        }
+        Row::Ranked { rank, cells: row_cells, details } => {
+            writeln!(out, "{}{}. {}", pad, rank, cells(row_cells, messages))?;
+            for detail in details {
+                render_row(detail, indent + 3, messages, out)?;
+            }
+        }
+        Row::Branch { cells: row_cells, .. } | Row::Text { cells: row_cells } => {
+            writeln!(out, "{}- {}", pad, cells(row_cells, messages))?;
+        }
+        Row::Group { title, rows } => {
+            writeln!(out, "\n{}**{}:**\n", pad, title)?;
+            for nested in rows {
+                render_row(nested, indent, messages, out)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn cells(cells: &[Cell], messages: &Messages) -> String {
+    cells
+        .iter()
+        .map(|cell| match &cell.value {
+            Value::Path(_) | Value::Composition(_) => format!("`{}`", format_value(&cell.value, messages)),
+            value if cell.strong => format!("**{}**", format_value(value, messages)),
+            value => format_value(value, messages),
+        })
+        .collect()
+}
@@ -0,0 +1,1348 @@
+//! Translated report strings and locale-aware formatting
+//!
+//! Every user-facing string of a report is looked up by key in a per-language
+//! catalog. Keys missing from a catalog fall back to English, so a partial
+//! translation still produces a complete report.
+
+use super::model::TimestampStyle;
+use crate::utils::format_count;
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+use humansize::{format_size, DECIMAL};
+use std::fmt::Display;
+
+/// Report language
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+pub enum Lang {
+    /// English
+    #[default]
+    En,
+    /// German
+    De,
+    /// French
+    Fr,
+    /// Spanish
+    Es,
+}
+
+/// Looks up report strings and formats values for one language
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Messages {
+    lang: Lang,
+}
+
+impl Messages {
+    pub fn new(lang: Lang) -> Self {
+        Self { lang }
+    }
+
+    pub fn lang(&self) -> Lang {
+        self.lang
+    }
+
+    /// The string for `key`, falling back to English and then to the key itself
+    pub fn get(&self, key: &'static str) -> &'static str {
+        self.lookup(key).unwrap_or(key)
+    }
+
+    /// The string for `key` with `{name}` placeholders replaced
+    pub fn format(&self, key: &'static str, args: &[(&str, &dyn Display)]) -> String {
+        args.iter().fold(self.get(key).to_string(), |text, (name, value)| {
+            text.replace(&format!("{{{}}}", name), &value.to_string())
+        })
+    }
+
+    /// Translated name of a file type category, or the name itself
+    pub fn file_type(&self, name: &str) -> String {
+        let key = format!("file_type.{}", name.to_lowercase());
+        self.lookup(&key).map_or_else(|| name.to_string(), str::to_string)
+    }
+
+    fn lookup(&self, key: &str) -> Option<&'static str> {
+        lookup(catalog(self.lang), key).or_else(|| lookup(EN, key))
+    }
+
+    /// A number with a fixed number of decimals and the locale's decimal separator
+    pub fn decimal(&self, value: f64, decimals: usize) -> String {
+        self.localize_decimal(format!("{:.*}", decimals, value))
+    }
+
+    pub fn percent(&self, value: f64, decimals: usize) -> String {
+        match self.lang {
+            Lang::En => format!("{}%", self.decimal(value, decimals)),
+            _ => format!("{} %", self.decimal(value, decimals)),
+        }
+    }
+
+    pub fn bytes(&self, bytes: u64) -> String {
+        self.localize_decimal(format_size(bytes, DECIMAL))
+    }
+
+    /// A count with the locale's thousands separator
+    pub fn grouped(&self, count: u64) -> String {
+        let separator = match self.lang {
+            Lang::En => return format_count(count),
+            Lang::De | Lang::Es => ".",
+            Lang::Fr => "\u{202f}",
+        };
+        format_count(count).replace(',', separator)
+    }
+
+    pub fn timestamp(&self, timestamp: &DateTime<Utc>, style: TimestampStyle) -> String {
+        let format = match (self.lang, style) {
+            (Lang::En, TimestampStyle::Seconds) => "%Y-%m-%d %H:%M:%S UTC",
+            (Lang::En, TimestampStyle::Minutes) => "%Y-%m-%d %H:%M",
+            (Lang::De, TimestampStyle::Seconds) => "%d.%m.%Y %H:%M:%S UTC",
+            (Lang::De, TimestampStyle::Minutes) => "%d.%m.%Y %H:%M",
+            (Lang::Fr | Lang::Es, TimestampStyle::Seconds) => "%d/%m/%Y %H:%M:%S UTC",
+            (Lang::Fr | Lang::Es, TimestampStyle::Minutes) => "%d/%m/%Y %H:%M",
+        };
+        timestamp.format(format).to_string()
+    }
+
+    fn localize_decimal(&self, text: String) -> String {
+        match self.lang {
+            Lang::En => text,
+            _ => text.replace('.', ","),
+        }
+    }
+}
+
+type Catalog = &'static [(&'static str, &'static str)];
+
+fn catalog(lang: Lang) -> Catalog {
+    match lang {
+        Lang::En => EN,
+        Lang::De => DE,
+        Lang::Fr => FR,
+        Lang::Es => ES,
+    }
+}
+
+fn lookup(catalog: Catalog, key: &str) -> Option<&'static str> {
+    catalog.iter().find(|(k, _)| *k == key).map(|(_, text)| *text)
+}
+
+const EN: Catalog = &[
+    ("report.title", "ANALYSIS REPORT"),
+    ("report.complete", "Analysis complete! 🎉"),
+    ("report.export_hint", "Use --export to save results to file."),
+    ("report.hidden_directories", "Directories below {size} hidden: {count}"),
+    ("summary.label", "Summary"),
+    ("summary.line", "{files} files, {directories} directories, {size} total"),
+    ("summary.unreadable", "⚠ {count} paths could not be read; the totals leave them out"),
+    ("common.no_files", "No files found."),
+    ("common.in", " in "),
+    ("common.regenerable_tag", " (regenerable)"),
+    ("degraded.title", "Degraded Results"),
+    ("degraded.notice", "Some analysis steps failed; the figures below are incomplete."),
+    ("scan_info.title", "Scan Information"),
+    ("scan_info.path", "Path"),
+    ("scan_info.more_roots", "Also Scanned"),
+    ("scan_info.path_given", "via link {path}"),
+    ("scan_info.timestamp", "Timestamp"),
+    ("scan_info.duration", "Duration"),
+    ("scan_info.depth_limit", "Depth Limit"),
+    ("scan_info.unlimited", "unlimited"),
+    ("scan_info.min_depth", "Min Depth"),
+    ("scan_info.ignored", "Ignore Files"),
+    ("scan_info.ignored_entries", "entries skipped"),
+    ("scan_info.modified_before", "Modified Before"),
+    ("scan_info.modified_after", "Modified After"),
+    ("scan_info.created_after", "Created After"),
+    ("scan_info.undated", "Undated Files"),
+    ("scan_info.undated_files", "left out without a readable modification time"),
+    ("scan_info.undated_files_created", "left out without a readable modification or birth time"),
+    ("overview.title", "Overview"),
+    ("overview.total_files", "Total Files"),
+    ("overview.total_directories", "Total Directories"),
+    ("overview.total_size", "Total Size"),
+    ("overview.physical_size", "Physically Unique Data"),
+    ("overview.disk_usage", "Disk Usage"),
+    ("overview.apparent_size", "Apparent Size"),
+    ("overview.sparse_files", "Sparse Files"),
+    ("overview.sparse_files_value", "{count} with less than half of their {size} allocated"),
+    ("overview.ownership", "Ownership Filter"),
+    ("overview.ownership_value", "files owned by {user} (uid {uid}) - {percent} of {size} encountered"),
+    ("overview.coverage", "Coverage"),
+    ("overview.coverage_value", "scanned {percent} of first-level entries ({count} unreadable)"),
+    ("overview.partial", "Partial Scan"),
+    ("overview.partial_value", "stopped at {flag} {limit}; totals, listings, and duplicates cover only what was collected"),
+    ("overview.interrupted_value", "interrupted after {count} files; totals, listings, and duplicates cover only what was found until then"),
+    ("overview.report_filters", "Report Filters"),
+    ("overview.report_filters_value", "{filters}; totals and listings cover {matched} of {collected} files collected"),
+    ("overview.file_sizes", "File Sizes"),
+    ("overview.file_sizes_between", "{min} to {max}"),
+    ("overview.file_sizes_at_least", "at least {min}"),
+    ("overview.file_sizes_at_most", "at most {max}"),
+    ("overview.file_sizes_listings", "{range}; listings cover {matched} of {collected} files, directory sizes count all"),
+    ("overview.file_sizes_everywhere", "{range}; totals and listings cover {matched} of {collected} files collected"),
+    ("overview.clock_skew", "Clock Skew"),
+    ("overview.clock_skew_ahead", "{files} files ({share}) dated a median {ahead} in the future"),
+    ("overview.clock_skew_corrected", "ages corrected by {skew}"),
+    ("overview.depth_limit", "Depth Limit"),
+    ("overview.depth_hidden", "depth limit hid at least {count} directories (~{size} at the boundary); rerun with --auto-depth for complete totals"),
+    ("overview.depth_expanded", "walked {count} directories below the depth limit with --auto-depth; totals are complete"),
+    ("overview.case_collisions", "Case Collisions"),
+    ("overview.case_collisions_value", "{groups} groups of {names} sibling names that collide when case is ignored"),
+    ("overview.cleanup", "Cleanup Candidates"),
+    ("overview.cleanup_value", "{directories} empty directories, {files} empty files"),
+    ("overview.superseded", "Probably Superseded"),
+    ("overview.superseded_value", "{size} in {files} older versions across {groups} groups (heuristic, by name)"),
+    ("overview.redundant_files", "Redundant Copies"),
+    ("overview.reclaimable", "Reclaimable Space"),
+    ("overview.trend", "Trend"),
+    ("overview.trend_since_last", "{change} since last run"),
+    ("overview.trend_window", ", {change} over {days} days"),
+    ("overview.growth", "Growth"),
+    ("overview.growth_value", "{total} total: {unique} unique, {duplication} duplication"),
+    ("overview.growth_new_group", ", top new duplicate group: {path} ({size} wasted)"),
+    ("size_breakdown.title", "Size Breakdown"),
+    ("size_breakdown.small", "Small files (<1MB)"),
+    ("size_breakdown.medium", "Medium files (1MB-100MB)"),
+    ("size_breakdown.large", "Large files (>100MB)"),
+    ("size_breakdown.files", " files, "),
+    ("file_types.title", "File Type Distribution"),
+    ("file_types.largest", "Largest"),
+    ("file_types.count_open", " files ("),
+    ("file_types.count_close", ") - "),
+    ("file_types.more", "... and {count} more types totalling {size}"),
+    ("file_types.data_formats", "Data formats"),
+    ("file_types.more_formats", "... and {count} more formats totalling {size}"),
+    ("file_type.documents", "Documents"),
+    ("file_type.images", "Images"),
+    ("file_type.videos", "Videos"),
+    ("file_type.audio", "Audio"),
+    ("file_type.archives", "Archives"),
+    ("file_type.code", "Code"),
+    ("file_type.executables", "Executables"),
+    ("file_type.data", "Data"),
+    ("file_type.other", "Other"),
+    ("largest_files.title", "Largest Files"),
+    ("largest_files.modified", "Modified"),
+    ("largest_files.type", " | Type: "),
+    ("largest_files.created", ", created "),
+    ("largest_files.preview", "Preview"),
+    ("largest_files.link_target", "Links to"),
+    ("largest_files.sparse", "Sparse"),
+    ("largest_files.sparse_value", "{size} apparent, mostly unallocated"),
+    ("largest_files.more", "... and {count} more files totalling {size}"),
+    ("largest_files.actionable", "Actionable"),
+    ("largest_files.regenerable", "Regenerable"),
+    ("largest_files.files", " files"),
+    ("largest_files.regenerable_hidden", "{count} regenerable files totalling {size} hidden"),
+    ("largest_directories.title", "Largest Directories"),
+    ("largest_directories.below_floor", "All directories are below the size floor."),
+    ("largest_directories.none", "No directories found."),
+    ("largest_directories.files", " files, "),
+    ("largest_directories.direct_files", " files ("),
+    ("largest_directories.recursive_files", " including subdirectories), "),
+    ("largest_directories.subdirectories", " subdirectories"),
+    ("largest_directories.direct_subdirectories", " subdirectories ("),
+    ("largest_directories.recursive_subdirectories", " including nested ones)"),
+    ("largest_directories.more", "... and {count} more directories totalling {size}"),
+    ("largest_directories.clear_owner", "owner: {user} ({percent})"),
+    ("largest_directories.mixed_owners", "mixed owners: {owners}"),
+    ("largest_directories.owner_share", "{user} {percent}"),
+    ("largest_directories.pinned", "📌 pinned"),
+    ("largest_directories.root", "under root {path}"),
+    ("largest_directories.pinned_title", "Pinned Directories"),
+    ("largest_directories.composition", "Composition"),
+    ("owners.title", "Disk Usage by Owner"),
+    ("owners.share", " ({count} files, {percent})"),
+    ("owners.more", "... and {count} more owners with {size}"),
+    ("security.title", "Security Audit"),
+    ("security.audited", "Entries Audited"),
+    ("security.world_writable", "World-Writable Files"),
+    ("security.setuid", "Setuid Files"),
+    ("security.setgid", "Setgid Files"),
+    ("security.open_directories", "World-Writable Directories Without Sticky Bit"),
+    ("security.none", "No risky permissions found."),
+    ("security.owner", ", owner {owner}"),
+    ("security.more", "... and {count} more with {size}"),
+    ("ages.title", "File Ages"),
+    ("ages.source", "Based On"),
+    ("ages.accessed", "last access or modification, whichever is later"),
+    ("ages.modified", "last modification (access times are not kept here)"),
+    ("ages.under", "Used within {age}"),
+    ("ages.between", "Unused for {from} to {to}"),
+    ("ages.over", "Unused for {age} or longer"),
+    ("ages.undated", "Undated"),
+    ("ages.share", " ({percent})"),
+    ("ages.stale", "Stale (unused for {age} or longer)"),
+    ("ages.stale_title", "Largest Stale Files"),
+    ("ages.last_used", " - last used "),
+    ("ages.stale_more", "... and {count} more stale files with {size}"),
+    ("xattr.title", "Extended Attributes"),
+    ("xattr.total", "Total Attribute Size"),
+    ("xattr.none", "No files carry extended attributes."),
+    ("xattr.more", "... and {count} more files with {size} of attributes"),
+    ("entropy.title", "Entropy (heuristic)"),
+    ("entropy.heuristic", "Archives and media are high-entropy too; a jump since the last run is the signal, not a high share."),
+    ("entropy.sampled", "Sampled"),
+    ("entropy.sampled_value", "{files} files modified in the last {days} days, {read} of {budget} read"),
+    ("entropy.skipped", "Not Sampled"),
+    ("entropy.skipped_value", "{count} recent files, read budget exhausted"),
+    ("entropy.unreadable", "Unreadable"),
+    ("entropy.none", "No recently modified files to sample."),
+    ("entropy.share", "High-Entropy Share"),
+    ("entropy.of", " of "),
+    ("entropy.high_suffix", " looked random"),
+    ("entropy.no_baseline", "No earlier sampled run to compare against; jumps are flagged from the next run on."),
+    ("entropy.previous", "was {ratio} on the last run"),
+    ("entropy.jumped", "⚠ jumped from {ratio} on the last run"),
+    ("entropy.more", "... and {count} more directories with {size} sampled"),
+    ("case_collisions.title", "Case-Insensitive Name Collisions"),
+    ("case_collisions.none", "No sibling names collide when case is ignored."),
+    ("case_collisions.group", "{count} names folding to \"{folded}\""),
+    ("case_collisions.more", "... and {count} more groups with {size}"),
+    ("versions.title", "Probable Versions (heuristic)"),
+    ("versions.heuristic", "Grouped by name alone: copy markers, (N), _vN, .bak/.old, and dates. Check a group before deleting its older members."),
+    ("versions.none", "No files look like versions of one another."),
+    ("versions.superseded", "Probably Superseded"),
+    ("versions.superseded_value", "{size} in {files} older versions"),
+    ("versions.group", "{count} versions, {size} in the older ones"),
+    ("versions.newest", "newest"),
+    ("versions.more", "... and {count} more groups with {size} in older versions"),
+    ("duplicates.title", "Duplicate File Analysis"),
+    ("duplicates.none", " No duplicate files found!"),
+    ("duplicates.groups", "Duplicate Groups"),
+    ("duplicates.redundant", "Total Redundant Copies"),
+    ("duplicates.reclaimable", "Total Reclaimable Space"),
+    ("duplicates.resolved", "Resolved"),
+    ("duplicates.resolved_value", "{count} groups since the last run, {size} reclaimed"),
+    ("duplicates.more", "... and {count} more files totalling {size}"),
+    ("duplicates.group_files", " files) - "),
+    ("duplicates.wasted_priority", " wasted - priority "),
+    ("duplicates.top", "Top Duplicate Groups"),
+    ("duplicates.by_partition", "Waste by Partition"),
+    ("duplicates.wasted_in", " wasted in "),
+    ("duplicates.groups_suffix", " groups"),
+    ("duplicates.more_groups", "... and {count} more groups wasting {size}"),
+    ("duplicates.more_partitions", "... and {count} more subtrees wasting {size}"),
+    ("duplicates.actionable", "Actionable Waste"),
+    ("duplicates.regenerable", "Regenerable Waste"),
+    ("duplicates.regenerable_hidden", "{count} regenerable groups wasting {size} hidden"),
+    ("duplicates.unverified_tag", " (not content-verified: same size and etag)"),
+    ("performance.title", "Performance Statistics"),
+    ("performance.speed", "Scanning Speed"),
+    ("performance.files_per_sec", " files/sec"),
+    ("performance.throughput", "Throughput"),
+    ("performance.per_sec", "/sec"),
+    ("performance.memory", "Memory Usage"),
+    ("performance.cpu_time", "CPU Time"),
+    ("performance.cpu_user", "s user, "),
+    ("performance.cpu_system", "s system"),
+    ("performance.switches", "Context Switches"),
+    ("performance.voluntary", " voluntary, "),
+    ("performance.involuntary", " involuntary"),
+    ("performance.read", "Read From Storage"),
+    ("performance.by_phase", "Resource Usage by Phase"),
+    ("performance.phase_cpu", "s CPU"),
+    ("performance.phase_read", " read"),
+    ("performance.duplicate_detection", "Duplicate Detection"),
+    ("performance.redundant_found", " redundant copies found"),
+    ("performance.efficiency", "Space Efficiency"),
+    ("performance.io_latency", "I/O Latency (p50 / p95 / p99 / max)"),
+    ("performance.latency_value", "{p50} / {p95} / {p99} / {max} over {count} operations"),
+    ("performance.slowest_io", "Slowest Operations"),
+    ("performance.slowest_value", "{latency} on {path}"),
+    ("brief.header", "{path}: {size} in {files} files"),
+    ("brief.duplicates", "Duplicates: {size} reclaimable in {groups} groups"),
+    ("mirror.title", "MIRROR CHECK"),
+    ("mirror.section", "Mirror Check"),
+    ("mirror.source", "Source"),
+    ("mirror.destination", "Destination"),
+    ("mirror.compared", "Files Compared"),
+    ("mirror.hashed", "Contents Hashed"),
+    ("mirror.missing", "Missing in Destination"),
+    ("mirror.extra", "Only in Destination"),
+    ("mirror.mismatched", "Mismatched Files"),
+    ("mirror.more", "... and {count} more"),
+    ("mirror.size_differs", " - size {source} vs {destination}"),
+    ("mirror.content_differs", " - contents differ"),
+    ("diagnostics.title", "Diagnostics"),
+    ("diagnostics.alias_and", " and "),
+    ("diagnostics.alias_same", " are the same filesystem subtree, counted once"),
+    ("diagnostics.unrecorded", "Alias Check"),
+    ("diagnostics.unrecorded_note", " directories past the identity limit were not remembered"),
+    ("mirror.unreadable", " - unreadable: {error}"),
+    ("mirror.identical", "The destination is an identical copy."),
+    ("mirror.differs", "The trees differ."),
+    ("compare.title", "RUN COMPARISON"),
+    ("compare.before", "Before"),
+    ("compare.after", "After"),
+    ("compare.change", "Change"),
+    ("compare.added", "new"),
+    ("compare.removed", "gone"),
+    ("compare.none", "Nothing listed in either run."),
+    ("compare.points", "{points} pp"),
+    ("overview.symlinks", "Symlinks"),
+    ("overview.symlinks_value", "{broken} broken, {out_of_tree} pointing outside the tree, {valid} valid"),
+    ("custom_metrics.title", "Custom Metrics"),
+    ("custom_metrics.undefined", "n/a"),
+    ("broken_links.title", "Broken Symlinks"),
+    ("broken_links.none", "No broken symlinks."),
+    ("broken_links.group", "{count} broken links"),
+    ("broken_links.dangling", " (chain ends at missing {path})"),
+    ("broken_links.endless", " (loops or exceeds {hops} hops)"),
+    ("broken_links.more_links", "... and {count} more"),
+    ("broken_links.more", "... and {count} more directories with {links} broken links"),
+    ("skipped_mounts.title", "Skipped Mounts"),
+    ("skipped_mounts.none", "No other filesystems below the root."),
+    ("skipped_mounts.summary", "{count} mount points and files on other filesystems were not scanned"),
+    ("skipped_mounts.more", "... and {count} more"),
+    ("cleanup.title", "Cleanup Candidates"),
+    ("cleanup.empty_directories", "Empty Directories ({count})"),
+    ("cleanup.empty_files", "Empty Files ({count})"),
+    ("cleanup.more", "... and {count} more"),
+    ("cleanup.more_directories", "... and {count} more; --print-empty-dirs lists them all"),
+    ("errors.title", "Scan Errors"),
+    ("errors.count", " errors"),
+    ("errors.subtrees", "Where they gather"),
+    ("errors.rollup", "{count} errors ({cause})"),
+    ("errors.listed", "Errors met"),
+    ("errors.more", "... and {count} more; --errors-full lists them all"),
+    ("accepted.title", "Accepted Findings"),
+    ("accepted.collapsed", "{count} accepted findings are not listed; --show-accepted lists them"),
+    ("accepted.group", "duplicate group {id}"),
+    ("accepted.since", "Accepted"),
+    ("accepted.tag", "  ✔ accepted: {reason}"),
+];
+
+const DE: Catalog = &[
+    ("report.title", "ANALYSEBERICHT"),
+    ("report.complete", "Analyse abgeschlossen! 🎉"),
+    ("report.export_hint", "Mit --export werden die Ergebnisse in einer Datei gespeichert."),
+    ("report.hidden_directories", "Verzeichnisse unter {size} ausgeblendet: {count}"),
+    ("summary.label", "Zusammenfassung"),
+    ("summary.line", "{files} Dateien, {directories} Verzeichnisse, {size} insgesamt"),
+    ("summary.unreadable", "⚠ {count} Pfade konnten nicht gelesen werden; die Summen lassen sie aus"),
+    ("common.no_files", "Keine Dateien gefunden."),
+    ("common.in", " in "),
+    ("common.regenerable_tag", " (regenerierbar)"),
+    ("degraded.title", "Unvollständige Ergebnisse"),
+    ("degraded.notice", "Einige Analyseschritte sind fehlgeschlagen; die folgenden Zahlen sind unvollständig."),
+    ("scan_info.title", "Scan-Informationen"),
+    ("scan_info.path", "Pfad"),
+    ("scan_info.more_roots", "Ebenfalls durchsucht"),
+    ("scan_info.path_given", "über Link {path}"),
+    ("scan_info.timestamp", "Zeitpunkt"),
+    ("scan_info.duration", "Dauer"),
+    ("scan_info.depth_limit", "Maximale Tiefe"),
+    ("scan_info.unlimited", "unbegrenzt"),
+    ("scan_info.min_depth", "Mindesttiefe"),
+    ("scan_info.ignored", "Ignore-Dateien"),
+    ("scan_info.ignored_entries", "Einträge übersprungen"),
+    ("scan_info.modified_before", "Geändert vor"),
+    ("scan_info.modified_after", "Geändert nach"),
+    ("scan_info.created_after", "Erstellt nach"),
+    ("scan_info.undated", "Undatierte Dateien"),
+    ("scan_info.undated_files", "ohne lesbare Änderungszeit ausgelassen"),
+    ("scan_info.undated_files_created", "ohne lesbare Änderungs- oder Erstellungszeit ausgelassen"),
+    ("overview.title", "Überblick"),
+    ("overview.total_files", "Dateien gesamt"),
+    ("overview.total_directories", "Verzeichnisse gesamt"),
+    ("overview.total_size", "Gesamtgröße"),
+    ("overview.physical_size", "Physisch belegte Daten"),
+    ("overview.disk_usage", "Belegter Speicher"),
+    ("overview.apparent_size", "Scheinbare Größe"),
+    ("overview.sparse_files", "Dünn belegte Dateien"),
+    ("overview.sparse_files_value", "{count} mit weniger als der Hälfte ihrer {size} belegt"),
+    ("overview.ownership", "Besitzerfilter"),
+    ("overview.ownership_value", "Dateien von {user} (UID {uid}) - {percent} von {size} gefunden"),
+    ("overview.coverage", "Abdeckung"),
+    ("overview.coverage_value", "{percent} der Einträge der ersten Ebene gescannt ({count} nicht lesbar)"),
+    ("overview.partial", "Teilscan"),
+    ("overview.partial_value", "bei {flag} {limit} angehalten; Summen, Listen und Duplikate umfassen nur das Erfasste"),
+    ("overview.interrupted_value", "nach {count} Dateien unterbrochen; Summen, Listen und Duplikate umfassen nur das bis dahin Gefundene"),
+    ("overview.report_filters", "Berichtsfilter"),
+    ("overview.report_filters_value", "{filters}; Summen und Listen umfassen {matched} von {collected} erfassten Dateien"),
+    ("overview.file_sizes", "Dateigrößen"),
+    ("overview.file_sizes_between", "{min} bis {max}"),
+    ("overview.file_sizes_at_least", "mindestens {min}"),
+    ("overview.file_sizes_at_most", "höchstens {max}"),
+    ("overview.file_sizes_listings", "{range}; Listen umfassen {matched} von {collected} Dateien, Verzeichnisgrößen zählen alle"),
+    ("overview.file_sizes_everywhere", "{range}; Summen und Listen umfassen {matched} von {collected} erfassten Dateien"),
+    ("overview.clock_skew", "Uhrzeitabweichung"),
+    ("overview.clock_skew_ahead", "{files} Dateien ({share}) im Median {ahead} in der Zukunft datiert"),
+    ("overview.clock_skew_corrected", "Alter um {skew} korrigiert"),
+    ("overview.depth_limit", "Tiefenlimit"),
+    ("overview.depth_hidden", "das Tiefenlimit verbarg mindestens {count} Verzeichnisse (~{size} an der Grenze); für vollständige Summen mit --auto-depth erneut ausführen"),
+    ("overview.depth_expanded", "{count} Verzeichnisse unterhalb des Tiefenlimits mit --auto-depth durchsucht; die Summen sind vollständig"),
+    ("overview.case_collisions", "Groß-/Kleinschreibungskonflikte"),
+    ("overview.case_collisions_value", "{groups} Gruppen mit {names} Geschwisternamen, die ohne Groß-/Kleinschreibung kollidieren"),
+    ("overview.cleanup", "Aufräumkandidaten"),
+    ("overview.cleanup_value", "{directories} leere Verzeichnisse, {files} leere Dateien"),
+    ("overview.superseded", "Vermutlich überholt"),
+    ("overview.superseded_value", "{size} in {files} älteren Versionen in {groups} Gruppen (heuristisch, nach Namen)"),
+    ("overview.redundant_files", "Redundante Kopien"),
+    ("overview.reclaimable", "Freigebbarer Speicher"),
+    ("overview.trend", "Trend"),
+    ("overview.trend_since_last", "{change} seit dem letzten Lauf"),
+    ("overview.trend_window", ", {change} in {days} Tagen"),
+    ("overview.growth", "Wachstum"),
+    ("overview.growth_value", "{total} gesamt: {unique} eindeutig, {duplication} Duplikate"),
+    ("overview.growth_new_group", ", größte neue Duplikatgruppe: {path} ({size} verschwendet)"),
+    ("size_breakdown.title", "Größenverteilung"),
+    ("size_breakdown.small", "Kleine Dateien (<1MB)"),
+    ("size_breakdown.medium", "Mittlere Dateien (1MB-100MB)"),
+    ("size_breakdown.large", "Große Dateien (>100MB)"),
+    ("size_breakdown.files", " Dateien, "),
+    ("file_types.title", "Verteilung nach Dateityp"),
+    ("file_types.largest", "Größte"),
+    ("file_types.count_open", " ("),
+    ("file_types.count_close", " Dateien) - "),
+    ("file_types.more", "... und {count} weitere Typen mit insgesamt {size}"),
+    ("file_types.data_formats", "Datenformate"),
+    ("file_types.more_formats", "... und {count} weitere Formate mit insgesamt {size}"),
+    ("file_type.documents", "Dokumente"),
+    ("file_type.images", "Bilder"),
+    ("file_type.videos", "Videos"),
+    ("file_type.audio", "Audio"),
+    ("file_type.archives", "Archive"),
+    ("file_type.code", "Quellcode"),
+    ("file_type.executables", "Programme"),
+    ("file_type.data", "Daten"),
+    ("file_type.other", "Sonstige"),
+    ("largest_files.title", "Größte Dateien"),
+    ("largest_files.modified", "Geändert"),
+    ("largest_files.type", " | Typ: "),
+    ("largest_files.created", ", erstellt "),
+    ("largest_files.preview", "Vorschau"),
+    ("largest_files.link_target", "Verweist auf"),
+    ("largest_files.sparse", "Dünn belegt"),
+    ("largest_files.sparse_value", "{size} scheinbar, größtenteils nicht belegt"),
+    ("largest_files.more", "... und {count} weitere Dateien mit insgesamt {size}"),
+    ("largest_files.actionable", "Bereinigbar"),
+    ("largest_files.regenerable", "Regenerierbar"),
+    ("largest_files.files", " Dateien"),
+    ("largest_files.regenerable_hidden", "{count} regenerierbare Dateien mit insgesamt {size} ausgeblendet"),
+    ("largest_directories.title", "Größte Verzeichnisse"),
+    ("largest_directories.below_floor", "Alle Verzeichnisse liegen unter der Mindestgröße."),
+    ("largest_directories.none", "Keine Verzeichnisse gefunden."),
+    ("largest_directories.files", " Dateien, "),
+    ("largest_directories.direct_files", " Dateien ("),
+    ("largest_directories.recursive_files", " mit Unterverzeichnissen), "),
+    ("largest_directories.subdirectories", " Unterverzeichnisse"),
+    ("largest_directories.direct_subdirectories", " Unterverzeichnisse ("),
+    ("largest_directories.recursive_subdirectories", " mit verschachtelten)"),
+    ("largest_directories.more", "... und {count} weitere Verzeichnisse mit insgesamt {size}"),
+    ("largest_directories.clear_owner", "Besitzer: {user} ({percent})"),
+    ("largest_directories.mixed_owners", "gemischte Besitzer: {owners}"),
+    ("largest_directories.owner_share", "{user} {percent}"),
+    ("largest_directories.pinned", "📌 angeheftet"),
+    ("largest_directories.root", "unter der Wurzel {path}"),
+    ("largest_directories.pinned_title", "Angeheftete Verzeichnisse"),
+    ("largest_directories.composition", "Zusammensetzung"),
+    ("owners.title", "Speicherbelegung nach Besitzer"),
+    ("owners.share", " ({count} Dateien, {percent})"),
+    ("owners.more", "... und {count} weitere Besitzer mit {size}"),
+    ("security.title", "Sicherheitsprüfung"),
+    ("security.audited", "Geprüfte Einträge"),
+    ("security.world_writable", "Für alle beschreibbare Dateien"),
+    ("security.setuid", "Setuid-Dateien"),
+    ("security.setgid", "Setgid-Dateien"),
+    ("security.open_directories", "Für alle beschreibbare Verzeichnisse ohne Sticky-Bit"),
+    ("security.none", "Keine riskanten Berechtigungen gefunden."),
+    ("security.owner", ", Besitzer {owner}"),
+    ("security.more", "... und {count} weitere mit {size}"),
+    ("ages.title", "Dateialter"),
+    ("ages.source", "Grundlage"),
+    ("ages.accessed", "letzter Zugriff oder letzte Änderung, je nachdem, was später ist"),
+    ("ages.modified", "letzte Änderung (Zugriffszeiten werden hier nicht geführt)"),
+    ("ages.under", "Genutzt innerhalb von {age}"),
+    ("ages.between", "Ungenutzt seit {from} bis {to}"),
+    ("ages.over", "Ungenutzt seit {age} oder länger"),
+    ("ages.undated", "Ohne Datum"),
+    ("ages.share", " ({percent})"),
+    ("ages.stale", "Veraltet (ungenutzt seit {age} oder länger)"),
+    ("ages.stale_title", "Größte veraltete Dateien"),
+    ("ages.last_used", " - zuletzt genutzt "),
+    ("ages.stale_more", "... und {count} weitere veraltete Dateien mit {size}"),
+    ("xattr.title", "Erweiterte Attribute"),
+    ("xattr.total", "Gesamtgröße der Attribute"),
+    ("xattr.none", "Keine Datei hat erweiterte Attribute."),
+    ("xattr.more", "... und {count} weitere Dateien mit {size} an Attributen"),
+    ("entropy.title", "Entropie (heuristisch)"),
+    ("entropy.heuristic", "Archive und Medien haben ebenfalls hohe Entropie; aussagekräftig ist ein Sprung seit dem letzten Lauf, nicht ein hoher Anteil."),
+    ("entropy.sampled", "Stichproben"),
+    ("entropy.sampled_value", "{files} in den letzten {days} Tagen geänderte Dateien, {read} von {budget} gelesen"),
+    ("entropy.skipped", "Nicht geprüft"),
+    ("entropy.skipped_value", "{count} kürzlich geänderte Dateien, Lesebudget erschöpft"),
+    ("entropy.unreadable", "Nicht lesbar"),
+    ("entropy.none", "Keine kürzlich geänderten Dateien zu prüfen."),
+    ("entropy.share", "Anteil hoher Entropie"),
+    ("entropy.of", " von "),
+    ("entropy.high_suffix", " wirkten zufällig"),
+    ("entropy.no_baseline", "Kein früherer Lauf mit Stichproben zum Vergleich; Sprünge werden ab dem nächsten Lauf markiert."),
+    ("entropy.previous", "beim letzten Lauf {ratio}"),
+    ("entropy.jumped", "⚠ gesprungen von {ratio} beim letzten Lauf"),
+    ("entropy.more", "... und {count} weitere Verzeichnisse mit {size} geprüft"),
+    ("case_collisions.title", "Namenskonflikte ohne Groß-/Kleinschreibung"),
+    ("case_collisions.none", "Keine Geschwisternamen kollidieren ohne Groß-/Kleinschreibung."),
+    ("case_collisions.group", "{count} Namen, gefaltet zu \"{folded}\""),
+    ("case_collisions.more", "... und {count} weitere Gruppen mit {size}"),
+    ("versions.title", "Vermutliche Versionen (heuristisch)"),
+    ("versions.heuristic", "Nur nach Namen gruppiert: Kopievermerke, (N), _vN, .bak/.old und Datumsangaben. Prüfen Sie eine Gruppe, bevor Sie ihre älteren Mitglieder löschen."),
+    ("versions.none", "Keine Dateien sehen wie Versionen voneinander aus."),
+    ("versions.superseded", "Vermutlich überholt"),
+    ("versions.superseded_value", "{size} in {files} älteren Versionen"),
+    ("versions.group", "{count} Versionen, {size} in den älteren"),
+    ("versions.newest", "neueste"),
+    ("versions.more", "... und {count} weitere Gruppen mit {size} in älteren Versionen"),
+    ("duplicates.title", "Analyse doppelter Dateien"),
+    ("duplicates.none", " Keine doppelten Dateien gefunden!"),
+    ("duplicates.groups", "Duplikatgruppen"),
+    ("duplicates.redundant", "Redundante Kopien gesamt"),
+    ("duplicates.reclaimable", "Freigebbarer Speicher gesamt"),
+    ("duplicates.resolved", "Aufgelöst"),
+    ("duplicates.resolved_value", "{count} Gruppen seit dem letzten Lauf, {size} freigegeben"),
+    ("duplicates.more", "... und {count} weitere Dateien mit insgesamt {size}"),
+    ("duplicates.group_files", " Dateien) - "),
+    ("duplicates.wasted_priority", " verschwendet - Priorität "),
+    ("duplicates.top", "Größte Duplikatgruppen"),
+    ("duplicates.by_partition", "Verschwendung nach Bereich"),
+    ("duplicates.wasted_in", " verschwendet in "),
+    ("duplicates.groups_suffix", " Gruppen"),
+    ("duplicates.more_groups", "... und {count} weitere Gruppen, die {size} verschwenden"),
+    ("duplicates.more_partitions", "... und {count} weitere Teilbäume, die {size} verschwenden"),
+    ("duplicates.actionable", "Bereinigbare Verschwendung"),
+    ("duplicates.regenerable", "Regenerierbare Verschwendung"),
+    ("duplicates.regenerable_hidden", "{count} regenerierbare Gruppen mit {size} Verschwendung ausgeblendet"),
+    ("duplicates.unverified_tag", " (Inhalt nicht geprüft: gleiche Größe und ETag)"),
+    ("performance.title", "Leistungsstatistik"),
+    ("performance.speed", "Scan-Geschwindigkeit"),
+    ("performance.files_per_sec", " Dateien/s"),
+    ("performance.throughput", "Durchsatz"),
+    ("performance.per_sec", "/s"),
+    ("performance.memory", "Speicherverbrauch"),
+    ("performance.cpu_time", "CPU-Zeit"),
+    ("performance.cpu_user", "s Benutzer, "),
+    ("performance.cpu_system", "s System"),
+    ("performance.switches", "Kontextwechsel"),
+    ("performance.voluntary", " freiwillig, "),
+    ("performance.involuntary", " unfreiwillig"),
+    ("performance.read", "Vom Speicher gelesen"),
+    ("performance.by_phase", "Ressourcen nach Phase"),
+    ("performance.phase_cpu", "s CPU"),
+    ("performance.phase_read", " gelesen"),
+    ("performance.duplicate_detection", "Duplikaterkennung"),
+    ("performance.redundant_found", " redundante Kopien gefunden"),
+    ("performance.efficiency", "Speichereffizienz"),
+    ("performance.io_latency", "E/A-Latenz (p50 / p95 / p99 / max)"),
+    ("performance.latency_value", "{p50} / {p95} / {p99} / {max} über {count} Operationen"),
+    ("performance.slowest_io", "Langsamste Operationen"),
+    ("performance.slowest_value", "{latency} bei {path}"),
+    ("brief.header", "{path}: {size} in {files} Dateien"),
+    ("brief.duplicates", "Duplikate: {size} freigebbar in {groups} Gruppen"),
+    ("mirror.title", "SPIEGELPRÜFUNG"),
+    ("mirror.section", "Spiegelprüfung"),
+    ("mirror.source", "Quelle"),
+    ("mirror.destination", "Ziel"),
+    ("mirror.compared", "Verglichene Dateien"),
+    ("mirror.hashed", "Inhalte geprüft"),
+    ("mirror.missing", "Fehlen im Ziel"),
+    ("mirror.extra", "Nur im Ziel"),
+    ("mirror.mismatched", "Abweichende Dateien"),
+    ("mirror.more", "... und {count} weitere"),
+    ("mirror.size_differs", " - Größe {source} statt {destination}"),
+    ("mirror.content_differs", " - Inhalt weicht ab"),
+    ("diagnostics.title", "Diagnose"),
+    ("diagnostics.alias_and", " und "),
+    ("diagnostics.alias_same", " sind derselbe Dateisystem-Teilbaum, einmal gezählt"),
+    ("diagnostics.unrecorded", "Alias-Prüfung"),
+    ("diagnostics.unrecorded_note", " Verzeichnisse jenseits des Identitätslimits wurden nicht vorgemerkt"),
+    ("mirror.unreadable", " - nicht lesbar: {error}"),
+    ("mirror.identical", "Das Ziel ist eine identische Kopie."),
+    ("mirror.differs", "Die Verzeichnisbäume unterscheiden sich."),
+    ("compare.title", "LAUFVERGLEICH"),
+    ("compare.before", "Vorher"),
+    ("compare.after", "Nachher"),
+    ("compare.change", "Änderung"),
+    ("compare.added", "neu"),
+    ("compare.removed", "entfallen"),
+    ("compare.none", "In keinem der Läufe etwas aufgeführt."),
+    ("compare.points", "{points} Pp."),
+    ("overview.symlinks", "Symlinks"),
+    ("overview.symlinks_value", "{broken} defekt, {out_of_tree} zeigen aus dem Baum heraus, {valid} gültig"),
+    ("custom_metrics.title", "Eigene Kennzahlen"),
+    ("custom_metrics.undefined", "k. A."),
+    ("broken_links.title", "Defekte Symlinks"),
+    ("broken_links.none", "Keine defekten Symlinks."),
+    ("broken_links.group", "{count} defekte Links"),
+    ("broken_links.dangling", " (Kette endet beim fehlenden {path})"),
+    ("broken_links.endless", " (Schleife oder mehr als {hops} Schritte)"),
+    ("broken_links.more_links", "... und {count} weitere"),
+    ("broken_links.more", "... und {count} weitere Verzeichnisse mit {links} defekten Links"),
+    ("skipped_mounts.title", "Übersprungene Einhängepunkte"),
+    ("skipped_mounts.none", "Keine anderen Dateisysteme unterhalb des Startpfads."),
+    ("skipped_mounts.summary", "{count} Einhängepunkte und Dateien auf anderen Dateisystemen wurden nicht gescannt"),
+    ("skipped_mounts.more", "... und {count} weitere"),
+    ("cleanup.title", "Aufräumkandidaten"),
+    ("cleanup.empty_directories", "Leere Verzeichnisse ({count})"),
+    ("cleanup.empty_files", "Leere Dateien ({count})"),
+    ("cleanup.more", "... und {count} weitere"),
+    ("cleanup.more_directories", "... und {count} weitere; --print-empty-dirs listet alle auf"),
+    ("errors.title", "Scanfehler"),
+    ("errors.count", " Fehler"),
+    ("errors.subtrees", "Wo sie sich häufen"),
+    ("errors.rollup", "{count} Fehler ({cause})"),
+    ("errors.listed", "Aufgetretene Fehler"),
+    ("errors.more", "... und {count} weitere; --errors-full listet alle auf"),
+    ("accepted.title", "Akzeptierte Befunde"),
+    ("accepted.collapsed", "{count} akzeptierte Befunde werden nicht aufgeführt; --show-accepted führt sie auf"),
+    ("accepted.group", "Duplikatgruppe {id}"),
+    ("accepted.since", "Akzeptiert"),
+    ("accepted.tag", "  ✔ akzeptiert: {reason}"),
+];
+
+const FR: Catalog = &[
+    ("report.title", "RAPPORT D'ANALYSE"),
+    ("report.complete", "Analyse terminée ! 🎉"),
+    ("report.export_hint", "Utilisez --export pour enregistrer les résultats dans un fichier."),
+    ("report.hidden_directories", "Répertoires de moins de {size} masqués : {count}"),
+    ("summary.label", "Résumé"),
+    ("summary.line", "{files} fichiers, {directories} répertoires, {size} au total"),
+    ("summary.unreadable", "⚠ {count} chemins n'ont pas pu être lus ; les totaux les omettent"),
+    ("common.no_files", "Aucun fichier trouvé."),
+    ("common.in", " dans "),
+    ("common.regenerable_tag", " (régénérable)"),
+    ("degraded.title", "Résultats incomplets"),
+    ("degraded.notice", "Certaines étapes de l'analyse ont échoué ; les chiffres ci-dessous sont incomplets."),
+    ("scan_info.title", "Informations sur l'analyse"),
+    ("scan_info.path", "Chemin"),
+    ("scan_info.more_roots", "Également analysé"),
+    ("scan_info.path_given", "via le lien {path}"),
+    ("scan_info.timestamp", "Horodatage"),
+    ("scan_info.duration", "Durée"),
+    ("scan_info.depth_limit", "Profondeur maximale"),
+    ("scan_info.unlimited", "illimitée"),
+    ("scan_info.min_depth", "Profondeur minimale"),
+    ("scan_info.ignored", "Fichiers d'exclusion"),
+    ("scan_info.ignored_entries", "entrées ignorées"),
+    ("scan_info.modified_before", "Modifié avant"),
+    ("scan_info.modified_after", "Modifié après"),
+    ("scan_info.created_after", "Créé après"),
+    ("scan_info.undated", "Fichiers non datés"),
+    ("scan_info.undated_files", "écartés faute de date de modification lisible"),
+    ("scan_info.undated_files_created", "écartés faute de date de modification ou de création lisible"),
+    ("overview.title", "Vue d'ensemble"),
+    ("overview.total_files", "Nombre de fichiers"),
+    ("overview.total_directories", "Nombre de répertoires"),
+    ("overview.total_size", "Taille totale"),
+    ("overview.physical_size", "Données physiquement uniques"),
+    ("overview.disk_usage", "Espace disque occupé"),
+    ("overview.apparent_size", "Taille apparente"),
+    ("overview.sparse_files", "Fichiers creux"),
+    ("overview.sparse_files_value", "{count} dont moins de la moitié des {size} est allouée"),
+    ("overview.ownership", "Filtre de propriétaire"),
+    ("overview.ownership_value", "fichiers appartenant à {user} (uid {uid}) - {percent} des {size} rencontrés"),
+    ("overview.coverage", "Couverture"),
+    ("overview.coverage_value", "{percent} des entrées de premier niveau analysées ({count} illisibles)"),
+    ("overview.partial", "Analyse partielle"),
+    ("overview.partial_value", "arrêtée à {flag} {limit} ; les totaux, listes et doublons ne couvrent que ce qui a été collecté"),
+    ("overview.interrupted_value", "interrompue après {count} fichiers ; les totaux, listes et doublons ne couvrent que ce qui a été trouvé jusque-là"),
+    ("overview.report_filters", "Filtres du rapport"),
+    ("overview.report_filters_value", "{filters} ; les totaux et listes couvrent {matched} des {collected} fichiers collectés"),
+    ("overview.file_sizes", "Tailles de fichier"),
+    ("overview.file_sizes_between", "de {min} à {max}"),
+    ("overview.file_sizes_at_least", "au moins {min}"),
+    ("overview.file_sizes_at_most", "au plus {max}"),
+    ("overview.file_sizes_listings", "{range} ; les listes couvrent {matched} des {collected} fichiers, les tailles de répertoire les comptent tous"),
+    ("overview.file_sizes_everywhere", "{range} ; les totaux et listes couvrent {matched} des {collected} fichiers collectés"),
+    ("overview.clock_skew", "Décalage d'horloge"),
+    ("overview.clock_skew_ahead", "{files} fichiers ({share}) datés en médiane {ahead} dans le futur"),
+    ("overview.clock_skew_corrected", "âges corrigés de {skew}"),
+    ("overview.depth_limit", "Limite de profondeur"),
+    ("overview.depth_hidden", "la limite de profondeur a masqué au moins {count} répertoires (~{size} à la frontière) ; relancez avec --auto-depth pour des totaux complets"),
+    ("overview.depth_expanded", "{count} répertoires sous la limite de profondeur parcourus avec --auto-depth ; les totaux sont complets"),
+    ("overview.case_collisions", "Collisions de casse"),
+    ("overview.case_collisions_value", "{groups} groupes de {names} noms voisins qui entrent en collision sans tenir compte de la casse"),
+    ("overview.cleanup", "Candidats au nettoyage"),
+    ("overview.cleanup_value", "{directories} répertoires vides, {files} fichiers vides"),
+    ("overview.superseded", "Probablement obsolètes"),
+    ("overview.superseded_value", "{size} dans {files} versions plus anciennes réparties en {groups} groupes (heuristique, par nom)"),
+    ("overview.redundant_files", "Copies redondantes"),
+    ("overview.reclaimable", "Espace récupérable"),
+    ("overview.trend", "Tendance"),
+    ("overview.trend_since_last", "{change} depuis la dernière exécution"),
+    ("overview.trend_window", ", {change} sur {days} jours"),
+    ("overview.growth", "Croissance"),
+    ("overview.growth_value", "{total} au total : {unique} unique, {duplication} en doublons"),
+    ("overview.growth_new_group", ", principal nouveau groupe de doublons : {path} ({size} gaspillés)"),
+    ("size_breakdown.title", "Répartition par taille"),
+    ("size_breakdown.small", "Petits fichiers (<1MB)"),
+    ("size_breakdown.medium", "Fichiers moyens (1MB-100MB)"),
+    ("size_breakdown.large", "Gros fichiers (>100MB)"),
+    ("size_breakdown.files", " fichiers, "),
+    ("file_types.title", "Répartition par type de fichier"),
+    ("file_types.largest", "Le plus gros"),
+    ("file_types.count_open", " ("),
+    ("file_types.count_close", " fichiers) - "),
+    ("file_types.more", "... et {count} autres types totalisant {size}"),
+    ("file_types.data_formats", "Formats de données"),
+    ("file_types.more_formats", "... et {count} autres formats totalisant {size}"),
+    ("file_type.documents", "Documents"),
+    ("file_type.images", "Images"),
+    ("file_type.videos", "Vidéos"),
+    ("file_type.audio", "Audio"),
+    ("file_type.archives", "Archives"),
+    ("file_type.code", "Code source"),
+    ("file_type.executables", "Exécutables"),
+    ("file_type.data", "Données"),
+    ("file_type.other", "Autres"),
+    ("largest_files.title", "Plus gros fichiers"),
+    ("largest_files.modified", "Modifié"),
+    ("largest_files.type", " | Type : "),
+    ("largest_files.created", ", créé le "),
+    ("largest_files.preview", "Aperçu"),
+    ("largest_files.link_target", "Pointe vers"),
+    ("largest_files.sparse", "Creux"),
+    ("largest_files.sparse_value", "{size} apparents, en grande partie non alloués"),
+    ("largest_files.more", "... et {count} autres fichiers totalisant {size}"),
+    ("largest_files.actionable", "Récupérable"),
+    ("largest_files.regenerable", "Régénérable"),
+    ("largest_files.files", " fichiers"),
+    ("largest_files.regenerable_hidden", "{count} fichiers régénérables totalisant {size} masqués"),
+    ("largest_directories.title", "Plus gros répertoires"),
+    ("largest_directories.below_floor", "Tous les répertoires sont sous la taille minimale."),
+    ("largest_directories.none", "Aucun répertoire trouvé."),
+    ("largest_directories.files", " fichiers, "),
+    ("largest_directories.direct_files", " fichiers ("),
+    ("largest_directories.recursive_files", " avec les sous-répertoires), "),
+    ("largest_directories.subdirectories", " sous-répertoires"),
+    ("largest_directories.direct_subdirectories", " sous-répertoires ("),
+    ("largest_directories.recursive_subdirectories", " avec les imbriqués)"),
+    ("largest_directories.more", "... et {count} autres répertoires totalisant {size}"),
+    ("largest_directories.clear_owner", "propriétaire : {user} ({percent})"),
+    ("largest_directories.mixed_owners", "propriétaires multiples : {owners}"),
+    ("largest_directories.owner_share", "{user} {percent}"),
+    ("largest_directories.pinned", "📌 épinglé"),
+    ("largest_directories.root", "sous la racine {path}"),
+    ("largest_directories.pinned_title", "Répertoires épinglés"),
+    ("largest_directories.composition", "Composition"),
+    ("owners.title", "Espace disque par propriétaire"),
+    ("owners.share", " ({count} fichiers, {percent})"),
+    ("owners.more", "... et {count} autres propriétaires avec {size}"),
+    ("security.title", "Audit de sécurité"),
+    ("security.audited", "Entrées auditées"),
+    ("security.world_writable", "Fichiers modifiables par tous"),
+    ("security.setuid", "Fichiers setuid"),
+    ("security.setgid", "Fichiers setgid"),
+    ("security.open_directories", "Répertoires modifiables par tous sans sticky bit"),
+    ("security.none", "Aucune permission risquée trouvée."),
+    ("security.owner", ", propriétaire {owner}"),
+    ("security.more", "... et {count} autres avec {size}"),
+    ("ages.title", "Âge des fichiers"),
+    ("ages.source", "Basé sur"),
+    ("ages.accessed", "le dernier accès ou la dernière modification, selon le plus récent"),
+    ("ages.modified", "la dernière modification (les dates d'accès ne sont pas tenues ici)"),
+    ("ages.under", "Utilisés depuis moins de {age}"),
+    ("ages.between", "Inutilisés depuis {from} à {to}"),
+    ("ages.over", "Inutilisés depuis {age} ou plus"),
+    ("ages.undated", "Sans date"),
+    ("ages.share", " ({percent})"),
+    ("ages.stale", "Obsolètes (inutilisés depuis {age} ou plus)"),
+    ("ages.stale_title", "Plus gros fichiers obsolètes"),
+    ("ages.last_used", " - dernière utilisation "),
+    ("ages.stale_more", "... et {count} autres fichiers obsolètes avec {size}"),
+    ("xattr.title", "Attributs étendus"),
+    ("xattr.total", "Taille totale des attributs"),
+    ("xattr.none", "Aucun fichier ne porte d'attributs étendus."),
+    ("xattr.more", "... et {count} autres fichiers avec {size} d'attributs"),
+    ("entropy.title", "Entropie (heuristique)"),
+    ("entropy.heuristic", "Les archives et médias ont aussi une entropie élevée ; le signal est un saut depuis la dernière exécution, pas une part élevée."),
+    ("entropy.sampled", "Échantillons"),
+    ("entropy.sampled_value", "{files} fichiers modifiés ces {days} derniers jours, {read} lus sur {budget}"),
+    ("entropy.skipped", "Non échantillonnés"),
+    ("entropy.skipped_value", "{count} fichiers récents, budget de lecture épuisé"),
+    ("entropy.unreadable", "Illisibles"),
+    ("entropy.none", "Aucun fichier récemment modifié à échantillonner."),
+    ("entropy.share", "Part d'entropie élevée"),
+    ("entropy.of", " sur "),
+    ("entropy.high_suffix", " d'aspect aléatoire"),
+    ("entropy.no_baseline", "Aucune exécution échantillonnée antérieure à comparer ; les sauts seront signalés dès la prochaine."),
+    ("entropy.previous", "{ratio} lors de la dernière exécution"),
+    ("entropy.jumped", "⚠ en hausse depuis {ratio} lors de la dernière exécution"),
+    ("entropy.more", "... et {count} autres répertoires avec {size} échantillonnés"),
+    ("case_collisions.title", "Collisions de noms insensibles à la casse"),
+    ("case_collisions.none", "Aucun nom voisin n'entre en collision sans tenir compte de la casse."),
+    ("case_collisions.group", "{count} noms repliés en \"{folded}\""),
+    ("case_collisions.more", "... et {count} autres groupes avec {size}"),
+    ("versions.title", "Versions probables (heuristique)"),
+    ("versions.heuristic", "Regroupées par nom uniquement : mentions de copie, (N), _vN, .bak/.old et dates. Vérifiez un groupe avant d'en supprimer les membres plus anciens."),
+    ("versions.none", "Aucun fichier ne ressemble à une version d'un autre."),
+    ("versions.superseded", "Probablement obsolètes"),
+    ("versions.superseded_value", "{size} dans {files} versions plus anciennes"),
+    ("versions.group", "{count} versions, {size} dans les plus anciennes"),
+    ("versions.newest", "la plus récente"),
+    ("versions.more", "... et {count} autres groupes avec {size} dans des versions plus anciennes"),
+    ("duplicates.title", "Analyse des fichiers en double"),
+    ("duplicates.none", " Aucun fichier en double trouvé !"),
+    ("duplicates.groups", "Groupes de doublons"),
+    ("duplicates.redundant", "Total des copies redondantes"),
+    ("duplicates.reclaimable", "Espace récupérable total"),
+    ("duplicates.resolved", "Résolus"),
+    ("duplicates.resolved_value", "{count} groupes depuis la dernière exécution, {size} récupérés"),
+    ("duplicates.more", "... et {count} autres fichiers totalisant {size}"),
+    ("duplicates.group_files", " fichiers) - "),
+    ("duplicates.wasted_priority", " gaspillés - priorité "),
+    ("duplicates.top", "Principaux groupes de doublons"),
+    ("duplicates.by_partition", "Gaspillage par partition"),
+    ("duplicates.wasted_in", " gaspillés dans "),
+    ("duplicates.groups_suffix", " groupes"),
+    ("duplicates.more_groups", "... et {count} autres groupes gaspillant {size}"),
+    ("duplicates.more_partitions", "... et {count} autres sous-arborescences gaspillant {size}"),
+    ("duplicates.actionable", "Espace récupérable"),
+    ("duplicates.regenerable", "Espace régénérable"),
+    ("duplicates.regenerable_hidden", "{count} groupes régénérables gaspillant {size} masqués"),
+    ("duplicates.unverified_tag", " (contenu non vérifié : même taille et ETag)"),
+    ("performance.title", "Statistiques de performance"),
+    ("performance.speed", "Vitesse d'analyse"),
+    ("performance.files_per_sec", " fichiers/s"),
+    ("performance.throughput", "Débit"),
+    ("performance.per_sec", "/s"),
+    ("performance.memory", "Mémoire utilisée"),
+    ("performance.cpu_time", "Temps CPU"),
+    ("performance.cpu_user", "s utilisateur, "),
+    ("performance.cpu_system", "s système"),
+    ("performance.switches", "Changements de contexte"),
+    ("performance.voluntary", " volontaires, "),
+    ("performance.involuntary", " involontaires"),
+    ("performance.read", "Lu depuis le stockage"),
+    ("performance.by_phase", "Ressources par phase"),
+    ("performance.phase_cpu", "s CPU"),
+    ("performance.phase_read", " lus"),
+    ("performance.duplicate_detection", "Détection des doublons"),
+    ("performance.redundant_found", " copies redondantes trouvées"),
+    ("performance.efficiency", "Efficacité de l'espace"),
+    ("performance.io_latency", "Latence E/S (p50 / p95 / p99 / max)"),
+    ("performance.latency_value", "{p50} / {p95} / {p99} / {max} sur {count} opérations"),
+    ("performance.slowest_io", "Opérations les plus lentes"),
+    ("performance.slowest_value", "{latency} sur {path}"),
+    ("brief.header", "{path} : {size} dans {files} fichiers"),
+    ("brief.duplicates", "Doublons : {size} récupérables dans {groups} groupes"),
+    ("mirror.title", "VÉRIFICATION DE COPIE"),
+    ("mirror.section", "Vérification de copie"),
+    ("mirror.source", "Source"),
+    ("mirror.destination", "Destination"),
+    ("mirror.compared", "Fichiers comparés"),
+    ("mirror.hashed", "Contenus vérifiés"),
+    ("mirror.missing", "Absents de la destination"),
+    ("mirror.extra", "Uniquement dans la destination"),
+    ("mirror.mismatched", "Fichiers différents"),
+    ("mirror.more", "... et {count} autres"),
+    ("mirror.size_differs", " - taille {source} contre {destination}"),
+    ("mirror.content_differs", " - contenus différents"),
+    ("diagnostics.title", "Diagnostic"),
+    ("diagnostics.alias_and", " et "),
+    ("diagnostics.alias_same", " sont la même arborescence, comptée une fois"),
+    ("diagnostics.unrecorded", "Détection des alias"),
+    ("diagnostics.unrecorded_note", " répertoires au-delà de la limite d'identités n'ont pas été mémorisés"),
+    ("mirror.unreadable", " - illisible : {error}"),
+    ("mirror.identical", "La destination est une copie identique."),
+    ("mirror.differs", "Les arborescences diffèrent."),
+    ("compare.title", "COMPARAISON DES ANALYSES"),
+    ("compare.before", "Avant"),
+    ("compare.after", "Après"),
+    ("compare.change", "Variation"),
+    ("compare.added", "nouveau"),
+    ("compare.removed", "disparu"),
+    ("compare.none", "Rien n'est listé dans aucune des analyses."),
+    ("compare.points", "{points} pts"),
+    ("overview.symlinks", "Liens symboliques"),
+    ("overview.symlinks_value", "{broken} cassés, {out_of_tree} pointant hors de l'arborescence, {valid} valides"),
+    ("custom_metrics.title", "Métriques personnalisées"),
+    ("custom_metrics.undefined", "n/d"),
+    ("broken_links.title", "Liens symboliques cassés"),
+    ("broken_links.none", "Aucun lien symbolique cassé."),
+    ("broken_links.group", "{count} liens cassés"),
+    ("broken_links.dangling", " (la chaîne aboutit à {path}, absent)"),
+    ("broken_links.endless", " (boucle ou plus de {hops} sauts)"),
+    ("broken_links.more_links", "... et {count} autres"),
+    ("broken_links.more", "... et {count} autres répertoires avec {links} liens cassés"),
+    ("skipped_mounts.title", "Points de montage ignorés"),
+    ("skipped_mounts.none", "Aucun autre système de fichiers sous la racine."),
+    ("skipped_mounts.summary", "{count} points de montage et fichiers sur d'autres systèmes de fichiers n'ont pas été analysés"),
+    ("skipped_mounts.more", "... et {count} autres"),
+    ("cleanup.title", "Candidats au nettoyage"),
+    ("cleanup.empty_directories", "Répertoires vides ({count})"),
+    ("cleanup.empty_files", "Fichiers vides ({count})"),
+    ("cleanup.more", "... et {count} autres"),
+    ("cleanup.more_directories", "... et {count} autres ; --print-empty-dirs les liste tous"),
+    ("errors.title", "Erreurs d'analyse"),
+    ("errors.count", " erreurs"),
+    ("errors.subtrees", "Où elles se concentrent"),
+    ("errors.rollup", "{count} erreurs ({cause})"),
+    ("errors.listed", "Erreurs rencontrées"),
+    ("errors.more", "... et {count} autres ; --errors-full les liste toutes"),
+    ("accepted.title", "Constats acceptés"),
+    ("accepted.collapsed", "{count} constats acceptés ne sont pas listés ; --show-accepted les liste"),
+    ("accepted.group", "groupe de doublons {id}"),
+    ("accepted.since", "Accepté le"),
+    ("accepted.tag", "  ✔ accepté : {reason}"),
+];
+
+const ES: Catalog = &[
+    ("report.title", "INFORME DE ANÁLISIS"),
+    ("report.complete", "¡Análisis completado! 🎉"),
+    ("report.export_hint", "Use --export para guardar los resultados en un archivo."),
+    ("report.hidden_directories", "Directorios de menos de {size} ocultos: {count}"),
+    ("summary.label", "Resumen"),
+    ("summary.line", "{files} archivos, {directories} directorios, {size} en total"),
+    ("summary.unreadable", "⚠ {count} rutas no se pudieron leer; los totales las omiten"),
+    ("common.no_files", "No se encontraron archivos."),
+    ("common.in", " en "),
+    ("common.regenerable_tag", " (regenerable)"),
+    ("degraded.title", "Resultados incompletos"),
+    ("degraded.notice", "Algunos pasos del análisis fallaron; las cifras siguientes están incompletas."),
+    ("scan_info.title", "Información del análisis"),
+    ("scan_info.path", "Ruta"),
+    ("scan_info.more_roots", "También analizado"),
+    ("scan_info.path_given", "a través del enlace {path}"),
+    ("scan_info.timestamp", "Fecha"),
+    ("scan_info.duration", "Duración"),
+    ("scan_info.depth_limit", "Profundidad máxima"),
+    ("scan_info.unlimited", "ilimitada"),
+    ("scan_info.min_depth", "Profundidad mínima"),
+    ("scan_info.ignored", "Archivos de exclusión"),
+    ("scan_info.ignored_entries", "entradas omitidas"),
+    ("scan_info.modified_before", "Modificado antes de"),
+    ("scan_info.modified_after", "Modificado después de"),
+    ("scan_info.created_after", "Creado después de"),
+    ("scan_info.undated", "Archivos sin fecha"),
+    ("scan_info.undated_files", "omitidos por no tener fecha de modificación legible"),
+    ("scan_info.undated_files_created", "omitidos por no tener fecha de modificación o de creación legible"),
+    ("overview.title", "Resumen general"),
+    ("overview.total_files", "Total de archivos"),
+    ("overview.total_directories", "Total de directorios"),
+    ("overview.total_size", "Tamaño total"),
+    ("overview.physical_size", "Datos físicamente únicos"),
+    ("overview.disk_usage", "Uso de disco"),
+    ("overview.apparent_size", "Tamaño aparente"),
+    ("overview.sparse_files", "Archivos dispersos"),
+    ("overview.sparse_files_value", "{count} con menos de la mitad de sus {size} asignada"),
+    ("overview.ownership", "Filtro de propietario"),
+    ("overview.ownership_value", "archivos de {user} (uid {uid}) - {percent} de {size} encontrados"),
+    ("overview.coverage", "Cobertura"),
+    ("overview.coverage_value", "{percent} de las entradas de primer nivel analizadas ({count} ilegibles)"),
+    ("overview.partial", "Análisis parcial"),
+    ("overview.partial_value", "detenido en {flag} {limit}; los totales, listados y duplicados solo cubren lo recopilado"),
+    ("overview.interrupted_value", "interrumpido tras {count} archivos; los totales, listados y duplicados solo cubren lo encontrado hasta entonces"),
+    ("overview.report_filters", "Filtros del informe"),
+    ("overview.report_filters_value", "{filters}; los totales y listados cubren {matched} de {collected} archivos recopilados"),
+    ("overview.file_sizes", "Tamaños de archivo"),
+    ("overview.file_sizes_between", "de {min} a {max}"),
+    ("overview.file_sizes_at_least", "al menos {min}"),
+    ("overview.file_sizes_at_most", "como máximo {max}"),
+    ("overview.file_sizes_listings", "{range}; los listados cubren {matched} de {collected} archivos, los tamaños de directorio cuentan todos"),
+    ("overview.file_sizes_everywhere", "{range}; los totales y listados cubren {matched} de {collected} archivos recopilados"),
+    ("overview.clock_skew", "Desfase de reloj"),
+    ("overview.clock_skew_ahead", "{files} archivos ({share}) fechados con una mediana de {ahead} en el futuro"),
+    ("overview.clock_skew_corrected", "edades corregidas en {skew}"),
+    ("overview.depth_limit", "Límite de profundidad"),
+    ("overview.depth_hidden", "el límite de profundidad ocultó al menos {count} directorios (~{size} en la frontera); vuelva a ejecutar con --auto-depth para obtener totales completos"),
+    ("overview.depth_expanded", "se recorrieron {count} directorios bajo el límite de profundidad con --auto-depth; los totales están completos"),
+    ("overview.case_collisions", "Colisiones de mayúsculas"),
+    ("overview.case_collisions_value", "{groups} grupos de {names} nombres hermanos que colisionan sin distinguir mayúsculas"),
+    ("overview.cleanup", "Candidatos a limpieza"),
+    ("overview.cleanup_value", "{directories} directorios vacíos, {files} archivos vacíos"),
+    ("overview.superseded", "Probablemente obsoletas"),
+    ("overview.superseded_value", "{size} en {files} versiones anteriores en {groups} grupos (heurístico, por nombre)"),
+    ("overview.redundant_files", "Copias redundantes"),
+    ("overview.reclaimable", "Espacio recuperable"),
+    ("overview.trend", "Tendencia"),
+    ("overview.trend_since_last", "{change} desde la última ejecución"),
+    ("overview.trend_window", ", {change} en {days} días"),
+    ("overview.growth", "Crecimiento"),
+    ("overview.growth_value", "{total} en total: {unique} únicos, {duplication} en duplicados"),
+    ("overview.growth_new_group", ", mayor grupo nuevo de duplicados: {path} ({size} desperdiciados)"),
+    ("size_breakdown.title", "Desglose por tamaño"),
+    ("size_breakdown.small", "Archivos pequeños (<1MB)"),
+    ("size_breakdown.medium", "Archivos medianos (1MB-100MB)"),
+    ("size_breakdown.large", "Archivos grandes (>100MB)"),
+    ("size_breakdown.files", " archivos, "),
+    ("file_types.title", "Distribución por tipo de archivo"),
+    ("file_types.largest", "Más grande"),
+    ("file_types.count_open", " ("),
+    ("file_types.count_close", " archivos) - "),
+    ("file_types.more", "... y {count} tipos más que suman {size}"),
+    ("file_types.data_formats", "Formatos de datos"),
+    ("file_types.more_formats", "... y {count} formatos más que suman {size}"),
+    ("file_type.documents", "Documentos"),
+    ("file_type.images", "Imágenes"),
+    ("file_type.videos", "Vídeos"),
+    ("file_type.audio", "Audio"),
+    ("file_type.archives", "Archivos comprimidos"),
+    ("file_type.code", "Código"),
+    ("file_type.executables", "Ejecutables"),
+    ("file_type.data", "Datos"),
+    ("file_type.other", "Otros"),
+    ("largest_files.title", "Archivos más grandes"),
+    ("largest_files.modified", "Modificado"),
+    ("largest_files.type", " | Tipo: "),
+    ("largest_files.created", ", creado el "),
+    ("largest_files.preview", "Vista previa"),
+    ("largest_files.link_target", "Apunta a"),
+    ("largest_files.sparse", "Disperso"),
+    ("largest_files.sparse_value", "{size} aparentes, en su mayoría sin asignar"),
+    ("largest_files.more", "... y {count} archivos más que suman {size}"),
+    ("largest_files.actionable", "Recuperable"),
+    ("largest_files.regenerable", "Regenerable"),
+    ("largest_files.files", " archivos"),
+    ("largest_files.regenerable_hidden", "{count} archivos regenerables que suman {size} ocultos"),
+    ("largest_directories.title", "Directorios más grandes"),
+    ("largest_directories.below_floor", "Todos los directorios están por debajo del tamaño mínimo."),
+    ("largest_directories.none", "No se encontraron directorios."),
+    ("largest_directories.files", " archivos, "),
+    ("largest_directories.direct_files", " archivos ("),
+    ("largest_directories.recursive_files", " con subdirectorios), "),
+    ("largest_directories.subdirectories", " subdirectorios"),
+    ("largest_directories.direct_subdirectories", " subdirectorios ("),
+    ("largest_directories.recursive_subdirectories", " con los anidados)"),
+    ("largest_directories.more", "... y {count} directorios más que suman {size}"),
+    ("largest_directories.clear_owner", "propietario: {user} ({percent})"),
+    ("largest_directories.mixed_owners", "propietarios mezclados: {owners}"),
+    ("largest_directories.owner_share", "{user} {percent}"),
+    ("largest_directories.pinned", "📌 fijado"),
+    ("largest_directories.root", "bajo la raíz {path}"),
+    ("largest_directories.pinned_title", "Directorios fijados"),
+    ("largest_directories.composition", "Composición"),
+    ("owners.title", "Uso de disco por propietario"),
+    ("owners.share", " ({count} archivos, {percent})"),
+    ("owners.more", "... y {count} propietarios más con {size}"),
+    ("security.title", "Auditoría de seguridad"),
+    ("security.audited", "Entradas auditadas"),
+    ("security.world_writable", "Archivos modificables por todos"),
+    ("security.setuid", "Archivos setuid"),
+    ("security.setgid", "Archivos setgid"),
+    ("security.open_directories", "Directorios modificables por todos sin sticky bit"),
+    ("security.none", "No se encontraron permisos de riesgo."),
+    ("security.owner", ", propietario {owner}"),
+    ("security.more", "... y {count} más con {size}"),
+    ("ages.title", "Antigüedad de los archivos"),
+    ("ages.source", "Basado en"),
+    ("ages.accessed", "el último acceso o la última modificación, lo que sea posterior"),
+    ("ages.modified", "la última modificación (aquí no se registran los accesos)"),
+    ("ages.under", "Usados en los últimos {age}"),
+    ("ages.between", "Sin usar de {from} a {to}"),
+    ("ages.over", "Sin usar desde hace {age} o más"),
+    ("ages.undated", "Sin fecha"),
+    ("ages.share", " ({percent})"),
+    ("ages.stale", "Obsoletos (sin usar desde hace {age} o más)"),
+    ("ages.stale_title", "Archivos obsoletos más grandes"),
+    ("ages.last_used", " - último uso "),
+    ("ages.stale_more", "... y {count} archivos obsoletos más con {size}"),
+    ("xattr.title", "Atributos extendidos"),
+    ("xattr.total", "Tamaño total de atributos"),
+    ("xattr.none", "Ningún archivo tiene atributos extendidos."),
+    ("xattr.more", "... y {count} archivos más con {size} de atributos"),
+    ("entropy.title", "Entropía (heurística)"),
+    ("entropy.heuristic", "Los archivos comprimidos y multimedia también tienen entropía alta; la señal es un salto desde la última ejecución, no una proporción alta."),
+    ("entropy.sampled", "Muestreados"),
+    ("entropy.sampled_value", "{files} archivos modificados en los últimos {days} días, {read} de {budget} leídos"),
+    ("entropy.skipped", "Sin muestrear"),
+    ("entropy.skipped_value", "{count} archivos recientes, presupuesto de lectura agotado"),
+    ("entropy.unreadable", "Ilegibles"),
+    ("entropy.none", "No hay archivos modificados recientemente que muestrear."),
+    ("entropy.share", "Proporción de entropía alta"),
+    ("entropy.of", " de "),
+    ("entropy.high_suffix", " parecían aleatorios"),
+    ("entropy.no_baseline", "No hay una ejecución muestreada anterior con la que comparar; los saltos se marcarán a partir de la próxima."),
+    ("entropy.previous", "era {ratio} en la última ejecución"),
+    ("entropy.jumped", "⚠ subió desde {ratio} en la última ejecución"),
+    ("entropy.more", "... y {count} directorios más con {size} muestreados"),
+    ("case_collisions.title", "Colisiones de nombres sin distinguir mayúsculas"),
+    ("case_collisions.none", "Ningún nombre hermano colisiona sin distinguir mayúsculas."),
+    ("case_collisions.group", "{count} nombres plegados a \"{folded}\""),
+    ("case_collisions.more", "... y {count} grupos más con {size}"),
+    ("versions.title", "Versiones probables (heurístico)"),
+    ("versions.heuristic", "Agrupadas solo por nombre: marcas de copia, (N), _vN, .bak/.old y fechas. Revise un grupo antes de borrar sus miembros más antiguos."),
+    ("versions.none", "Ningún archivo parece una versión de otro."),
+    ("versions.superseded", "Probablemente obsoletas"),
+    ("versions.superseded_value", "{size} en {files} versiones anteriores"),
+    ("versions.group", "{count} versiones, {size} en las anteriores"),
+    ("versions.newest", "la más reciente"),
+    ("versions.more", "... y {count} grupos más con {size} en versiones anteriores"),
+    ("duplicates.title", "Análisis de archivos duplicados"),
+    ("duplicates.none", " ¡No se encontraron archivos duplicados!"),
+    ("duplicates.groups", "Grupos de duplicados"),
+    ("duplicates.redundant", "Total de copias redundantes"),
+    ("duplicates.reclaimable", "Espacio recuperable total"),
+    ("duplicates.resolved", "Resueltos"),
+    ("duplicates.resolved_value", "{count} grupos desde la última ejecución, {size} recuperados"),
+    ("duplicates.more", "... y {count} archivos más que suman {size}"),
+    ("duplicates.group_files", " archivos) - "),
+    ("duplicates.wasted_priority", " desperdiciados - prioridad "),
+    ("duplicates.top", "Principales grupos de duplicados"),
+    ("duplicates.by_partition", "Desperdicio por partición"),
+    ("duplicates.wasted_in", " desperdiciados en "),
+    ("duplicates.groups_suffix", " grupos"),
+    ("duplicates.more_groups", "... y {count} grupos más que desperdician {size}"),
+    ("duplicates.more_partitions", "... y {count} subárboles más que desperdician {size}"),
+    ("duplicates.actionable", "Espacio recuperable"),
+    ("duplicates.regenerable", "Espacio regenerable"),
+    ("duplicates.regenerable_hidden", "{count} grupos regenerables que desperdician {size} ocultos"),
+    ("duplicates.unverified_tag", " (contenido no verificado: mismo tamaño y ETag)"),
+    ("performance.title", "Estadísticas de rendimiento"),
+    ("performance.speed", "Velocidad de análisis"),
+    ("performance.files_per_sec", " archivos/s"),
+    ("performance.throughput", "Caudal"),
+    ("performance.per_sec", "/s"),
+    ("performance.memory", "Uso de memoria"),
+    ("performance.cpu_time", "Tiempo de CPU"),
+    ("performance.cpu_user", "s usuario, "),
+    ("performance.cpu_system", "s sistema"),
+    ("performance.switches", "Cambios de contexto"),
+    ("performance.voluntary", " voluntarios, "),
+    ("performance.involuntary", " involuntarios"),
+    ("performance.read", "Leído del almacenamiento"),
+    ("performance.by_phase", "Recursos por fase"),
+    ("performance.phase_cpu", "s CPU"),
+    ("performance.phase_read", " leídos"),
+    ("performance.duplicate_detection", "Detección de duplicados"),
+    ("performance.redundant_found", " copias redundantes encontradas"),
+    ("performance.efficiency", "Eficiencia de espacio"),
+    ("performance.io_latency", "Latencia de E/S (p50 / p95 / p99 / máx.)"),
+    ("performance.latency_value", "{p50} / {p95} / {p99} / {max} en {count} operaciones"),
+    ("performance.slowest_io", "Operaciones más lentas"),
+    ("performance.slowest_value", "{latency} en {path}"),
+    ("brief.header", "{path}: {size} en {files} archivos"),
+    ("brief.duplicates", "Duplicados: {size} recuperables en {groups} grupos"),
+    ("mirror.title", "VERIFICACIÓN DE COPIA"),
+    ("mirror.section", "Verificación de copia"),
+    ("mirror.source", "Origen"),
+    ("mirror.destination", "Destino"),
+    ("mirror.compared", "Archivos comparados"),
+    ("mirror.hashed", "Contenidos verificados"),
+    ("mirror.missing", "Faltan en el destino"),
+    ("mirror.extra", "Solo en el destino"),
+    ("mirror.mismatched", "Archivos distintos"),
+    ("mirror.more", "... y {count} más"),
+    ("mirror.size_differs", " - tamaño {source} frente a {destination}"),
+    ("mirror.content_differs", " - el contenido difiere"),
+    ("diagnostics.title", "Diagnóstico"),
+    ("diagnostics.alias_and", " y "),
+    ("diagnostics.alias_same", " son el mismo subárbol del sistema de archivos, contado una vez"),
+    ("diagnostics.unrecorded", "Detección de alias"),
+    ("diagnostics.unrecorded_note", " directorios más allá del límite de identidades no se recordaron"),
+    ("mirror.unreadable", " - ilegible: {error}"),
+    ("mirror.identical", "El destino es una copia idéntica."),
+    ("mirror.differs", "Los árboles difieren."),
+    ("compare.title", "COMPARACIÓN DE ANÁLISIS"),
+    ("compare.before", "Antes"),
+    ("compare.after", "Después"),
+    ("compare.change", "Cambio"),
+    ("compare.added", "nuevo"),
+    ("compare.removed", "desaparecido"),
+    ("compare.none", "Nada listado en ninguno de los análisis."),
+    ("compare.points", "{points} pp"),
+    ("overview.symlinks", "Enlaces simbólicos"),
+    ("overview.symlinks_value", "{broken} rotos, {out_of_tree} apuntan fuera del árbol, {valid} válidos"),
+    ("custom_metrics.title", "Métricas personalizadas"),
+    ("custom_metrics.undefined", "n/d"),
+    ("broken_links.title", "Enlaces simbólicos rotos"),
+    ("broken_links.none", "No hay enlaces simbólicos rotos."),
+    ("broken_links.group", "{count} enlaces rotos"),
+    ("broken_links.dangling", " (la cadena termina en {path}, que no existe)"),
+    ("broken_links.endless", " (bucle o más de {hops} saltos)"),
+    ("broken_links.more_links", "... y {count} más"),
+    ("broken_links.more", "... y {count} directorios más con {links} enlaces rotos"),
+    ("skipped_mounts.title", "Puntos de montaje omitidos"),
+    ("skipped_mounts.none", "No hay otros sistemas de archivos bajo la raíz."),
+    ("skipped_mounts.summary", "{count} puntos de montaje y archivos en otros sistemas de archivos no se analizaron"),
+    ("skipped_mounts.more", "... y {count} más"),
+    ("cleanup.title", "Candidatos a limpieza"),
+    ("cleanup.empty_directories", "Directorios vacíos ({count})"),
+    ("cleanup.empty_files", "Archivos vacíos ({count})"),
+    ("cleanup.more", "... y {count} más"),
+    ("cleanup.more_directories", "... y {count} más; --print-empty-dirs los lista todos"),
+    ("errors.title", "Errores del análisis"),
+    ("errors.count", " errores"),
+    ("errors.subtrees", "Dónde se concentran"),
+    ("errors.rollup", "{count} errores ({cause})"),
+    ("errors.listed", "Errores encontrados"),
+    ("errors.more", "... y {count} más; --errors-full los lista todos"),
+    ("accepted.title", "Hallazgos aceptados"),
+    ("accepted.collapsed", "{count} hallazgos aceptados no se listan; --show-accepted los lista"),
+    ("accepted.group", "grupo de duplicados {id}"),
+    ("accepted.since", "Aceptado"),
+    ("accepted.tag", "  ✔ aceptado: {reason}"),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use regex::Regex;
+
+    /// Sources of the section builders and renderers
+    const SOURCES: [&str; 5] = [
+        include_str!("../reporter.rs"),
+        include_str!("render.rs"),
+        include_str!("markdown.rs"),
+        include_str!("brief.rs"),
+        include_str!("compare.rs"),
+    ];
+
+    #[test]
+    fn test_every_used_key_is_in_the_english_catalog() {
+        let key_use = Regex::new(r#"messages\s*\.\s*(?:get|format)\(\s*"([^"]+)""#).unwrap();
+        let used: Vec<_> = SOURCES.iter().flat_map(|source| key_use.captures_iter(source)).map(|c| c[1].to_string()).collect();
+
+        assert!(used.len() > 50, "found only {} keys; did the lookup call change?", used.len());
+        for key in &used {
+            assert!(lookup(EN, key).is_some(), "`{}` is missing from the English catalog", key);
+        }
+    }
+
+    #[test]
+    fn test_translations_only_use_known_keys() {
+        let placeholders = Regex::new(r"\{\w+\}").unwrap();
+        let names = |text| placeholders.find_iter(text).map(|m| m.as_str()).collect::<Vec<_>>();
+        for lang in [Lang::De, Lang::Fr, Lang::Es] {
+            for (key, text) in catalog(lang) {
+                let english = lookup(EN, key).unwrap_or_else(|| panic!("{:?} has unknown key `{}`", lang, key));
+                let (mut ours, mut theirs) = (names(text), names(english));
+                ours.sort();
+                theirs.sort();
+                assert_eq!(ours, theirs, "{:?} `{}` changes the placeholders", lang, key);
+            }
+        }
+    }
+
+    #[test]
+    fn test_missing_keys_fall_back_to_english() {
+        let german = Messages::new(Lang::De);
+        assert_eq!(german.get("overview.total_size"), "Gesamtgröße");
+        assert_eq!(lookup(DE, "not.translated"), None);
+        assert_eq!(german.get("not.translated"), "not.translated");
+        assert_eq!(german.file_type("Videos"), "Videos");
+        assert_eq!(german.file_type("Spreadsheets"), "Spreadsheets");
+        assert_eq!(
+            german.format("duplicates.more", &[("count", &7), ("size", &"2 MB")]),
+            "... und 7 weitere Dateien mit insgesamt 2 MB"
+        );
+    }
+
+    #[test]
+    fn test_locale_number_and_date_formats() {
+        let at = Utc.with_ymd_and_hms(2024, 3, 5, 12, 30, 0).unwrap();
+        let english = Messages::default();
+        assert_eq!(english.bytes(3_040_001_200), "3.04 GB");
+        assert_eq!(english.percent(98.66, 1), "98.7%");
+        assert_eq!(english.grouped(4213), "4,213");
+        assert_eq!(english.timestamp(&at, TimestampStyle::Minutes), "2024-03-05 12:30");
+
+        let german = Messages::new(Lang::De);
+        assert_eq!(german.bytes(3_040_001_200), "3,04 GB");
+        assert_eq!(german.percent(98.66, 1), "98,7 %");
+        assert_eq!(german.grouped(4213), "4.213");
+        assert_eq!(german.timestamp(&at, TimestampStyle::Seconds), "05.03.2024 12:30:00 UTC");
+
+        let french = Messages::new(Lang::Fr);
+        assert_eq!(french.grouped(1_234_567), "1\u{202f}234\u{202f}567");
+        assert_eq!(french.timestamp(&at, TimestampStyle::Minutes), "05/03/2024 12:30");
+    }
+}
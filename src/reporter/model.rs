@@ -0,0 +1,215 @@
+//! Typed intermediate model of a report
+//!
+//! Section builders describe *what* to show using these types; renderers decide
+//! *how* it looks. Values keep their units (bytes, counts, percentages) until
+//! rendering so every output format can present them appropriately.
+
+use super::messages::Messages;
+use chrono::{DateTime, Utc};
+use std::path::PathBuf;
+
+/// A complete report: a title, ordered sections, and closing hints
+#[derive(Debug, Clone, Default)]
+pub struct Report {
+    pub title: String,
+    pub sections: Vec<Section>,
+    pub footer: Vec<String>,
+    /// Language the report was built in, used for renderer strings and value formatting
+    pub messages: Messages,
+}
+
+/// Which part of the analysis a section presents
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SectionKind {
+    Degraded,
+    ScanInfo,
+    Overview,
+    /// Risky permissions, with `--security-audit`
+    SecurityAudit,
+    SizeBreakdown,
+    /// Files by how long ago they were last used
+    Ages,
+    FileTypes,
+    LargestFiles,
+    LargestDirectories,
+    /// Files and bytes per owning user
+    Owners,
+    /// Metrics defined in the configuration file
+    CustomMetrics,
+    ExtendedAttributes,
+    Entropy,
+    Duplicates,
+    /// Sibling names equal under case folding
+    CaseCollisions,
+    /// Files that look like versions of one another by name
+    Versions,
+    /// Symlinks whose targets are missing
+    BrokenLinks,
+    /// Other filesystems left out with `--one-file-system`
+    SkippedMounts,
+    /// Empty directories and files
+    CleanupCandidates,
+    /// Findings accepted with `accept`
+    Accepted,
+    /// Errors the walk met, by cause and subtree
+    Errors,
+    /// Anomalies of the walk, such as directories reached twice
+    Diagnostics,
+    Performance,
+    MirrorCheck,
+    /// Contributed through the library API; a report may hold several
+    Extension,
+}
+
+/// A titled block of rows
+#[derive(Debug, Clone)]
+pub struct Section {
+    pub kind: SectionKind,
+    pub icon: &'static str,
+    pub title: String,
+    pub rows: Vec<Row>,
+}
+
+/// One logical line of a section, possibly with nested rows
+#[derive(Debug, Clone)]
+pub enum Row {
+    /// A labelled value ("Label: value")
+    Field { label: String, value: Vec<Cell> },
+    /// An entry of a ranked list with detail rows beneath it
+    Ranked { rank: usize, cells: Vec<Cell>, details: Vec<Row> },
+    /// A member of a tree listing under a ranked entry
+    Branch { last: bool, cells: Vec<Cell> },
+    /// A free-form line
+    Text { cells: Vec<Cell> },
+    /// A titled sub-list
+    Group { title: String, rows: Vec<Row> },
+}
+
+/// A value together with its presentation tone
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cell {
+    pub value: Value,
+    pub tone: Tone,
+    pub strong: bool,
+}
+
+/// A typed value; renderers choose the textual representation
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Text(String),
+    Count(u64),
+    Bytes(u64),
+    /// A percentage (0-100) with a fixed number of decimals
+    Percent(f64, usize),
+    /// A plain number with a fixed number of decimals
+    Number(f64, usize),
+    Path(PathBuf),
+    Timestamp(DateTime<Utc>, TimestampStyle),
+    /// Shares of a total by category, drawn as a short bar
+    Composition(Vec<(Category, f64)>),
+    /// The categories of the report's composition bars, with how each is drawn
+    Legend(Vec<Category>),
+}
+
+/// A category of composition bars, drawn the same way throughout a report
+#[derive(Debug, Clone, PartialEq)]
+pub struct Category {
+    pub name: String,
+    /// Stands for the category where bars are drawn without color
+    pub letter: char,
+    /// Picks the category's color
+    pub slot: usize,
+}
+
+/// How precisely a timestamp is shown; the locale picks the layout
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimestampStyle {
+    Seconds,
+    Minutes,
+}
+
+/// Semantic emphasis; terminal renderers map tones to colors
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tone {
+    Plain,
+    Accent,
+    Good,
+    Warning,
+    Danger,
+    Notice,
+}
+
+/// Data for the one-line summary printed in quiet mode
+#[derive(Debug, Clone, PartialEq)]
+pub struct Summary {
+    pub total_files: u64,
+    pub total_directories: u64,
+    pub total_size: u64,
+    /// Paths the walk could not read, left out of the totals
+    pub unreadable_paths: u64,
+    pub messages: Messages,
+}
+
+impl Cell {
+    pub fn new(value: Value) -> Self {
+        Self { value, tone: Tone::Plain, strong: false }
+    }
+
+    pub fn text(text: impl Into<String>) -> Self {
+        Self::new(Value::Text(text.into()))
+    }
+
+    pub fn count(count: impl TryInto<u64>) -> Self {
+        Self::new(Value::Count(count.try_into().unwrap_or(u64::MAX)))
+    }
+
+    pub fn bytes(bytes: u64) -> Self {
+        Self::new(Value::Bytes(bytes))
+    }
+
+    pub fn path(path: impl Into<PathBuf>) -> Self {
+        Self::new(Value::Path(path.into()))
+    }
+
+    pub fn tone(mut self, tone: Tone) -> Self {
+        self.tone = tone;
+        self
+    }
+
+    pub fn strong(mut self) -> Self {
+        self.strong = true;
+        self
+    }
+}
+
+impl Row {
+    pub fn field(label: impl Into<String>, value: Cell) -> Self {
+        Row::Field { label: label.into(), value: vec![value] }
+    }
+}
+
+impl Section {
+    pub fn new(kind: SectionKind, icon: &'static str, title: impl Into<String>) -> Self {
+        Self { kind, icon, title: title.into(), rows: Vec::new() }
+    }
+
+    /// Look up the value cells of a top-level field by label
+    pub fn field(&self, label: &str) -> Option<&[Cell]> {
+        self.rows.iter().find_map(|row| match row {
+            Row::Field { label: l, value } if l == label => Some(value.as_slice()),
+            _ => None,
+        })
+    }
+}
+
+impl Report {
+    /// Find a section by kind
+    pub fn section(&self, kind: SectionKind) -> Option<&Section> {
+        self.sections.iter().find(|section| section.kind == kind)
+    }
+
+    /// Find a contributed section by title
+    pub fn extension(&self, title: &str) -> Option<&Section> {
+        self.sections.iter().find(|section| section.kind == SectionKind::Extension && section.title == title)
+    }
+}
@@ -0,0 +1,289 @@
+//! Renderers turning the report model into text
+
+use super::messages::Messages;
+use super::model::{Category, Cell, Report, Row, Summary, Tone, Value};
+use colored::{Color, ColoredString, Colorize};
+use std::io::{self, Write};
+
+/// Output format implementation for reports
+pub trait ReportRenderer {
+    /// Render a full report
+    fn render(&self, report: &Report, out: &mut dyn Write) -> io::Result<()>;
+
+    /// Render the one-line summary used in quiet mode
+    fn render_summary(&self, summary: &Summary, out: &mut dyn Write) -> io::Result<()>;
+}
+
+/// Colored terminal output
+pub struct TerminalRenderer;
+
+/// Terminal layout without any color escape codes
+pub struct PlainRenderer;
+
+impl ReportRenderer for TerminalRenderer {
+    fn render(&self, report: &Report, out: &mut dyn Write) -> io::Result<()> {
+        TextRenderer { color: true }.render(report, out)
+    }
+
+    fn render_summary(&self, summary: &Summary, out: &mut dyn Write) -> io::Result<()> {
+        TextRenderer { color: true }.render_summary(summary, out)
+    }
+}
+
+impl ReportRenderer for PlainRenderer {
+    fn render(&self, report: &Report, out: &mut dyn Write) -> io::Result<()> {
+        TextRenderer { color: false }.render(report, out)
+    }
+
+    fn render_summary(&self, summary: &Summary, out: &mut dyn Write) -> io::Result<()> {
+        TextRenderer { color: false }.render_summary(summary, out)
+    }
+}
+
+/// Cells in a composition bar
+pub const COMPOSITION_WIDTH: usize = 12;
+
+/// Colors of composition categories by slot
+const PALETTE: [Color; 6] = [Color::Blue, Color::Green, Color::Yellow, Color::Magenta, Color::Cyan, Color::Red];
+
+/// Lay out a composition bar: how many of its `width` cells each fraction gets
+///
+/// Fractions are taken relative to their sum, and the cells left over after
+/// rounding down go to the largest remainders, so the segments always fill the
+/// bar exactly. A share too small for a cell of its own may get none. With
+/// nothing to divide, as for an empty directory, every segment is empty.
+pub fn render_composition_bar(fractions: &[f64], width: usize) -> Vec<usize> {
+    let share = |fraction: f64| if fraction.is_finite() && fraction > 0.0 { fraction } else { 0.0 };
+    let total: f64 = fractions.iter().map(|fraction| share(*fraction)).sum();
+    if total <= 0.0 {
+        return vec![0; fractions.len()];
+    }
+
+    let exact: Vec<f64> = fractions.iter().map(|fraction| share(*fraction) / total * width as f64).collect();
+    let mut cells: Vec<usize> = exact.iter().map(|cells| cells.floor() as usize).collect();
+    let remainder = |index: usize| exact[index] - exact[index].floor();
+    let mut order: Vec<usize> = (0..fractions.len()).collect();
+    // Ties go to the larger share, then to the earlier one
+    order.sort_by(|&a, &b| {
+        remainder(b).total_cmp(&remainder(a)).then_with(|| exact[b].total_cmp(&exact[a])).then_with(|| a.cmp(&b))
+    });
+    let short = width.saturating_sub(cells.iter().sum());
+    for &index in order.iter().take(short) {
+        cells[index] += 1;
+    }
+    cells
+}
+
+/// A composition bar in letters, padded with dots where there is nothing to show
+fn letter_bar(shares: &[(Category, f64)]) -> String {
+    let fractions: Vec<f64> = shares.iter().map(|(_, fraction)| *fraction).collect();
+    let bar: String = shares
+        .iter()
+        .zip(render_composition_bar(&fractions, COMPOSITION_WIDTH))
+        .map(|((category, _), cells)| category.letter.to_string().repeat(cells))
+        .collect();
+    format!("[{:.<width$}]", bar, width = COMPOSITION_WIDTH)
+}
+
+/// Format a value without any styling, following the report's locale
+pub fn format_value(value: &Value, messages: &Messages) -> String {
+    match value {
+        Value::Text(text) => text.clone(),
+        Value::Count(count) => count.to_string(),
+        Value::Bytes(bytes) => messages.bytes(*bytes),
+        Value::Percent(percent, decimals) => messages.percent(*percent, *decimals),
+        Value::Number(number, decimals) => messages.decimal(*number, *decimals),
+        Value::Path(path) => path.display().to_string(),
+        Value::Timestamp(timestamp, style) => messages.timestamp(timestamp, *style),
+        Value::Composition(shares) => letter_bar(shares),
+        Value::Legend(categories) => categories
+            .iter()
+            .map(|category| format!("{}={}", category.letter, category.name))
+            .collect::<Vec<_>>()
+            .join(", "),
+    }
+}
+
+/// Shared implementation of the terminal and plain layouts
+struct TextRenderer {
+    color: bool,
+}
+
+impl TextRenderer {
+    const RULE_WIDTH: usize = 50;
+
+    fn render(&self, report: &Report, out: &mut dyn Write) -> io::Result<()> {
+        writeln!(out, "\n{}", self.paint(format!("📋 {}", report.title), Tone::Accent, true))?;
+        writeln!(out, "{}", self.paint("=".repeat(Self::RULE_WIDTH), Tone::Accent, false))?;
+
+        let messages = &report.messages;
+        for section in &report.sections {
+            let heading = format!("{} {}", section.icon, section.title);
+            writeln!(out, "\n{}", self.paint(heading, Tone::Warning, true))?;
+            for row in &section.rows {
+                self.render_row(row, 2, messages, out)?;
+            }
+        }
+
+        writeln!(out, "\n{}", self.paint("=".repeat(Self::RULE_WIDTH), Tone::Accent, false))?;
+        writeln!(out, "{}", self.paint(messages.get("report.complete").to_string(), Tone::Good, true))?;
+        for line in &report.footer {
+            writeln!(out, "{}", line)?;
+        }
+        Ok(())
+    }
+
+    fn render_summary(&self, summary: &Summary, out: &mut dyn Write) -> io::Result<()> {
+        let messages = &summary.messages;
+        let line = messages.format("summary.line", &[
+            ("files", &summary.total_files),
+            ("directories", &summary.total_directories),
+            ("size", &self.paint(messages.bytes(summary.total_size), Tone::Accent, false)),
+        ]);
+        writeln!(out, "{}: {}", self.paint(messages.get("summary.label").to_string(), Tone::Plain, true), line)?;
+        if summary.unreadable_paths > 0 {
+            let note = messages.format("summary.unreadable", &[("count", &messages.grouped(summary.unreadable_paths))]);
+            writeln!(out, "{}", self.paint(note, Tone::Warning, false))?;
+        }
+        Ok(())
+    }
+
+    fn render_row(&self, row: &Row, indent: usize, messages: &Messages, out: &mut dyn Write) -> io::Result<()> {
+        let pad = " ".repeat(indent);
+        match row {
+            Row::Field { label, value } => {
+                writeln!(out, "{}{}: {}", pad, label, self.cells(value, messages))?;
+            }
+            Row::Ranked { rank, cells, details } => {
+                writeln!(out, "{}{}. {}", pad, self.paint(rank.to_string(), Tone::Accent, false), self.cells(cells, messages))?;
+                for detail in details {
+                    self.render_row(detail, indent + 3, messages, out)?;
+                }
+            }
+            Row::Branch { last, cells } => {
+                let prefix = if *last { "└─" } else { "├─" };
+                writeln!(out, "{}{} {}", pad, prefix, self.cells(cells, messages))?;
+            }
+            Row::Text { cells } => {
+                writeln!(out, "{}{}", pad, self.cells(cells, messages))?;
+            }
+            Row::Group { title, rows } => {
+                writeln!(out, "\n{}{}:", pad, title)?;
+                for nested in rows {
+                    self.render_row(nested, indent + 2, messages, out)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn cells(&self, cells: &[Cell], messages: &Messages) -> String {
+        cells
+            .iter()
+            .map(|cell| match &cell.value {
+                Value::Composition(shares) if self.color => Self::color_bar(shares),
+                Value::Legend(categories) if self.color => categories
+                    .iter()
+                    .map(|category| format!("{} {}", "██".color(PALETTE[category.slot % PALETTE.len()]), category.name))
+                    .collect::<Vec<_>>()
+                    .join("  "),
+                value => self.paint(format_value(value, messages), cell.tone, cell.strong),
+            })
+            .collect()
+    }
+
+    /// A composition bar in the categories' colors
+    fn color_bar(shares: &[(Category, f64)]) -> String {
+        let fractions: Vec<f64> = shares.iter().map(|(_, fraction)| *fraction).collect();
+        let cells = render_composition_bar(&fractions, COMPOSITION_WIDTH);
+        if cells.iter().all(|cells| *cells == 0) {
+            return "░".repeat(COMPOSITION_WIDTH);
+        }
+        shares
+            .iter()
+            .zip(cells)
+            .filter(|(_, cells)| *cells > 0)
+            .map(|((category, _), cells)| "█".repeat(cells).color(PALETTE[category.slot % PALETTE.len()]).to_string())
+            .collect()
+    }
+
+    fn paint(&self, text: String, tone: Tone, strong: bool) -> String {
+        paint(text, tone, strong, self.color)
+    }
+}
+
+/// Color `text` by tone, or leave it as is without color
+pub(super) fn paint(text: String, tone: Tone, strong: bool, color: bool) -> String {
+    if !color || (tone == Tone::Plain && !strong) {
+        return text;
+    }
+
+    let colored: ColoredString = match tone {
+        Tone::Plain => text.normal(),
+        Tone::Accent => text.cyan(),
+        Tone::Good => text.green(),
+        Tone::Warning => text.yellow(),
+        Tone::Danger => text.red(),
+        Tone::Notice => text.magenta(),
+    };
+    if strong {
+        colored.bold().to_string()
+    } else {
+        colored.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn category(name: &str, letter: char, slot: usize) -> Category {
+        Category { name: name.to_string(), letter, slot }
+    }
+
+    #[test]
+    fn test_segments_fill_the_width() {
+        assert_eq!(render_composition_bar(&[0.5, 0.25, 0.25], 12), [6, 3, 3]);
+        // 4.8, 4.8, 2.4: both 0.8 remainders round up
+        assert_eq!(render_composition_bar(&[0.4, 0.4, 0.2], 12), [5, 5, 2]);
+        // Fractions need not add up to one
+        assert_eq!(render_composition_bar(&[3.0, 1.0], 12), [9, 3]);
+    }
+
+    #[test]
+    fn test_many_tiny_categories() {
+        let fractions = [0.05; 20];
+        let cells = render_composition_bar(&fractions, 12);
+        assert_eq!(cells.iter().sum::<usize>(), 12);
+        // Equal shares: the earlier categories get the cells
+        assert_eq!(cells, [1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 0, 0, 0, 0, 0, 0, 0, 0]);
+
+        let cells = render_composition_bar(&[0.9, 0.02, 0.03, 0.01, 0.04], 12);
+        assert_eq!(cells, [11, 0, 0, 0, 1]);
+    }
+
+    #[test]
+    fn test_one_dominant_category() {
+        assert_eq!(render_composition_bar(&[0.999, 0.001], 12), [12, 0]);
+        assert_eq!(render_composition_bar(&[0.0, 1.0, 0.0], 12), [0, 12, 0]);
+    }
+
+    #[test]
+    fn test_zero_size_directory() {
+        assert_eq!(render_composition_bar(&[0.0, 0.0], 12), [0, 0]);
+        assert_eq!(render_composition_bar(&[], 12), Vec::<usize>::new());
+        assert_eq!(render_composition_bar(&[f64::NAN, -1.0], 12), [0, 0]);
+
+        let shares = vec![(category("Images", 'I', 0), 0.0), (category("Other", 'O', 1), 0.0)];
+        assert_eq!(letter_bar(&shares), "[............]");
+    }
+
+    #[test]
+    fn test_letter_bar_and_legend() {
+        let (images, videos) = (category("Images", 'I', 0), category("Videos", 'V', 1));
+        let messages = Messages::default();
+        let bar = Value::Composition(vec![(images.clone(), 0.25), (videos.clone(), 0.75)]);
+        assert_eq!(format_value(&bar, &messages), "[IIIVVVVVVVVV]");
+        assert_eq!(format_value(&Value::Legend(vec![images, videos]), &messages), "I=Images, V=Videos");
+    }
+}
@@ -1,61 +1,774 @@
 //! File system scanning functionality
 
+mod aggregate;
+mod alias;
+mod cap;
+mod case;
+mod empty;
+mod errors;
+mod explain;
+mod filter;
+mod ignores;
+mod interim;
+mod inventory;
+mod links;
+mod modified;
+mod mounts;
+mod retention;
+mod slow;
+mod source;
+
+pub use alias::{Aliases, DirectoryAlias};
+pub use cap::{CapKind, PartialScan, ScanCaps};
+pub use case::{CaseCollision, CollidingEntry};
+pub use errors::{ErrorCause, ErrorLog, ErrorRollup, ErrorSummary};
+pub use explain::{ExclusionCause, ExclusionCount, ExclusionTrace, TraceOutcome, TraceStep};
+pub(crate) use filter::ExcludePattern;
+pub use filter::FileFilter;
+pub use interim::{InterimSink, InterimSnapshot, CANDIDATE_DEPTH};
+pub use inventory::InventorySource;
+pub use links::{BrokenLink, SymlinkReport, MAX_HOPS};
+pub use modified::{parse_date_spec, DateFilter, DateSpec, ModifiedFilter};
+pub use mounts::SkippedMount;
+pub(crate) use mounts::atime_disabled;
+pub use retention::{DirectoryRetention, DroppedDirectories};
+pub use slow::{SlowDirectory, SlowThresholds};
+pub use source::FileSource;
+
 use crate::cli::Args;
+use crate::extents::{ExtentProbe, FileExtents, PhysicalUsage};
+use crate::interrupt::Cancellation;
+use crate::utils::latency::{IoLatency, IoOperation};
+use crate::xattr;
+use crate::writes::WriteTargets;
 use anyhow::{Context, Result};
 use chrono::{DateTime, Utc};
 use indicatif::{ProgressBar, ProgressStyle};
-use regex::Regex;
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
+use filter::FilterPipeline;
 use std::collections::HashMap;
-
+use std::fs;
 use std::path::{Path, PathBuf};
-use std::time::SystemTime;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, SystemTime};
 use walkdir::{DirEntry, WalkDir};
 
+/// Owners kept per directory unless configured otherwise
+pub const DEFAULT_OWNER_LIMIT: usize = 5;
+
+/// Receives each file of a streamed scan as the walk finds it (see [`DirectoryScanner::scan_streaming`])
+pub type FileSink<'a> = &'a mut dyn FnMut(FileEntry);
+
+/// Files read together at most; a run of files in one directory is read as a whole up to this many
+const FILE_BATCH: usize = 4096;
+
 /// Directory scanner that traverses the file system
 pub struct DirectoryScanner {
     args: Args,
-    exclude_patterns: Vec<Regex>,
+    filters: FilterPipeline,
+    extent_probe: ExtentProbe,
+    slow_thresholds: SlowThresholds,
+    owner_limit: usize,
     progress_bar: Option<ProgressBar>,
+    interim: Option<interim::Interim>,
+    /// Directory identities of the last scan, kept for [`DirectoryScanner::scan_below`]
+    visited: alias::VisitedDirectories,
+    /// What the last scan collected against `--max-files` and `--max-bytes`
+    budget: cap::Budget,
+    latency: IoLatency,
+    /// Group sibling names that are equal under case folding
+    detect_case_collisions: bool,
+    /// Resolve every symlink met and classify its target
+    find_broken_links: bool,
+    /// List every scan error, not only the first [`errors::RETAINED_ERRORS`]
+    errors_full: bool,
+    /// Workers that walk subtrees and stat and measure files, with more than one `--threads`
+    threads: Option<rayon::ThreadPool>,
+    /// Files stated so far, counted by the workers for the progress spinner
+    files_read: AtomicU64,
+    /// Device of the root, with `--one-file-system`; entries on others are skipped
+    root_device: Option<u64>,
+}
+
+/// What reading a file during the walk came to
+///
+/// Most reads keep their file, so boxing the entry would only add an allocation per file.
+#[allow(clippy::large_enum_variant)]
+enum FileRead {
+    /// Passed the filters, with its extents where they were probed
+    Kept(FileEntry, Option<FileExtents>),
+    Filtered,
+    /// On another filesystem than the root, with `--one-file-system`
+    OtherDevice { device: u64, size: u64 },
 }
 
 /// Results from scanning the directory structure
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct ScanResults {
     pub files: Vec<FileEntry>,
     pub directories: Vec<DirectoryEntry>,
     pub total_files: u64,
     pub total_directories: u64,
     pub total_size: u64,
+    /// Errors listed one by one, as many as [`ErrorLog::listing_limit`] allows
     pub errors: Vec<ScanError>,
+    /// Every error counted by cause and directory
+    #[serde(skip)]
+    pub error_log: ErrorLog,
+    pub ownership: Option<Ownership>,
+    /// The `--modified-before`/`--modified-after` bounds applied, and the files they could not judge
+    #[serde(default)]
+    pub date_filter: Option<DateFilter>,
+    pub exclusions: ExclusionStats,
+    /// Size with shared extents counted once, when any file could be probed
+    pub physical_size: Option<u64>,
+    /// Directories whose own entries took unusually long or were unusually many
+    pub slow_directories: Vec<SlowDirectory>,
+    /// Directories at the depth limit with contents the walk did not enter
+    pub depth_boundary: DepthBoundary,
+    /// Directories reached a second time, as through bind mounts, and skipped
+    #[serde(default)]
+    pub aliases: Aliases,
+    /// The cap that stopped the walk early, leaving part of the tree out
+    #[serde(default)]
+    pub partial: Option<PartialScan>,
+    /// Sibling names equal under case folding, with `--detect-case-collisions`
+    #[serde(default)]
+    pub case_collisions: Vec<CaseCollision>,
+    /// Symlinks by what their targets are, with `--find-broken-links`
+    #[serde(default)]
+    pub symlink_report: SymlinkReport,
+    /// What [`ScanResults::retain_directories`] dropped of `directories`
+    #[serde(default)]
+    pub dropped_directories: DroppedDirectories,
+    /// Entries on other filesystems, with `--one-file-system`
+    #[serde(default)]
+    pub skipped_mounts: Vec<SkippedMount>,
+    /// Directories with no files anywhere below them, sorted; none when the scan is partial
+    #[serde(default)]
+    pub empty_directories: Vec<PathBuf>,
+    /// The files of a streamed scan, summed per directory in place of `files`
+    #[serde(skip)]
+    folded: Option<aggregate::FoldedFiles>,
+}
+
+/// Directories at `--depth` whose contents are missing from the totals
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DepthBoundary {
+    pub directories: Vec<PathBuf>,
+    /// Sizes of the files directly inside them, a lower bound of what is missing
+    pub immediate_size: u64,
+    /// Whether `--auto-depth` walked them afterwards, making the totals complete
+    #[serde(default)]
+    pub expanded: bool,
+}
+
+/// What a walk collected before directory totals are computed
+#[derive(Default)]
+struct Walk<'s> {
+    files: Vec<FileEntry>,
+    /// Whether the files go to a sink in place of `files`, which then stays empty
+    streamed: bool,
+    /// What the files handed to the sink add to their directories
+    folded: aggregate::FoldedFiles,
+    /// Files handed to the sink
+    handed_out: u64,
+    directories: HashMap<PathBuf, DirectoryEntry>,
+    errors: ErrorLog,
+    total_size: u64,
+    physical: PhysicalUsage,
+    encountered: u64,
+    boundary: DepthBoundary,
+    slow_directories: Vec<SlowDirectory>,
+    /// Whether the walk's entries count toward the interim totals
+    tracked: bool,
+    /// Identities of the directories walked, when aliases are skipped
+    visited: Option<&'s alias::VisitedDirectories>,
+    /// Files and bytes collected against the caps; unlimited unless set
+    budget: cap::Budget,
+    /// Colliding sibling names, when they are looked for
+    case_collisions: Option<case::Found>,
+    /// Symlinks met, when their targets are checked
+    links: Option<links::Classifier>,
+    /// Directories and files on other devices than the root's, and the bytes of those files
+    skipped_mounts: Vec<SkippedMount>,
+    skipped_mount_size: u64,
+    /// The directories nothing was found in so far
+    empty: empty::EmptyDirectories,
+    /// The subdirectories of the root, left to walks of their own when the walk is split
+    subtrees: Option<Vec<PathBuf>>,
+}
+
+impl<'s> Walk<'s> {
+    /// Keep a file that passed the filters, or hand it to `sink` when the walk streams
+    fn keep(&mut self, file: FileEntry, sink: Option<&mut (dyn FnMut(FileEntry) + '_)>) {
+        match sink {
+            Some(sink) => {
+                self.folded.add(&file);
+                self.handed_out += 1;
+                sink(file);
+            }
+            None => self.files.push(file),
+        }
+    }
+
+    /// Files kept or handed out so far
+    fn collected(&self) -> u64 {
+        self.files.len() as u64 + self.handed_out
+    }
+
+    /// Fill in the sizes and counts of the walked directories
+    fn directory_totals(&mut self, owner_limit: usize) -> Vec<DirectoryEntry> {
+        let directories = std::mem::take(&mut self.directories);
+        match self.streamed {
+            true => self.folded.directory_totals(directories, owner_limit),
+            false => aggregate::directory_totals(&self.files, directories, owner_limit),
+        }
+    }
+
+    /// The sums that stand in for the files handed out, if the walk streamed
+    fn take_folded(&mut self) -> Option<aggregate::FoldedFiles> {
+        self.streamed.then(|| std::mem::take(&mut self.folded))
+    }
+
+    /// An empty walk of `directory`, one of the subtrees this walk left, collecting what this one does
+    fn part(&mut self, directory: &Path) -> Walk<'s> {
+        Walk {
+            tracked: self.tracked,
+            visited: self.visited,
+            budget: self.budget.fresh(),
+            case_collisions: self.case_collisions.as_ref().map(|_| case::Found::default()),
+            links: self.links.as_ref().map(links::Classifier::fresh),
+            errors: self.errors.fresh(),
+            empty: self.empty.split(directory),
+            ..Walk::default()
+        }
+    }
+
+    /// Take in a walk made by [`Walk::part`]
+    fn absorb(&mut self, part: Walk<'_>) {
+        self.files.extend(part.files);
+        self.directories.extend(part.directories);
+        self.errors.absorb(part.errors);
+        self.total_size += part.total_size;
+        self.physical.absorb(part.physical);
+        self.encountered += part.encountered;
+        self.boundary.directories.extend(part.boundary.directories);
+        self.boundary.immediate_size += part.boundary.immediate_size;
+        self.slow_directories.extend(part.slow_directories);
+        self.budget.absorb(part.budget);
+        if let (Some(found), Some(more)) = (self.case_collisions.as_mut(), part.case_collisions) {
+            found.extend(more);
+        }
+        if let (Some(links), Some(more)) = (self.links.as_mut(), part.links) {
+            links.absorb(more);
+        }
+        self.skipped_mounts.extend(part.skipped_mounts);
+        self.skipped_mount_size += part.skipped_mount_size;
+        self.empty.absorb(part.empty);
+    }
+
+    /// The entries left on other filesystems, counted among the exclusions
+    fn take_skipped_mounts(&mut self, exclusions: &mut ExclusionStats) -> Vec<SkippedMount> {
+        let mut skipped = std::mem::take(&mut self.skipped_mounts);
+        if !skipped.is_empty() {
+            exclusions.excluded += skipped.len() as u64;
+            exclusions.causes.push(ExclusionCount {
+                cause: ExclusionCause::OtherFilesystem,
+                entries: skipped.len() as u64,
+                size: std::mem::take(&mut self.skipped_mount_size),
+            });
+        }
+        mounts::describe(&mut skipped);
+        skipped
+    }
+
+    /// The empty directories walked, unless a cap left some of their contents unseen
+    fn take_empty_directories(&mut self, partial: Option<PartialScan>) -> Vec<PathBuf> {
+        let empty = std::mem::take(&mut self.empty);
+        match partial {
+            Some(_) => Vec::new(),
+            None => empty.finish(&self.directories),
+        }
+    }
+}
+
+impl ScanResults {
+    /// Fold in what [`DirectoryScanner::scan_below`] found past the depth boundary
+    ///
+    /// Directory totals are recomputed, so the boundary directories and their
+    /// ancestors include everything below them.
+    pub fn absorb(&mut self, below: ScanResults, owner_limit: usize) {
+        self.merge(below, owner_limit);
+        self.depth_boundary.expanded = true;
+    }
+
+    /// Add the results of the walk of another root, tagging its entries with the root's index
+    ///
+    /// The roots must not overlap (see [`distinct_roots`]).
+    pub fn add_root(&mut self, mut other: ScanResults, root: usize, owner_limit: usize) {
+        for file in &mut other.files {
+            file.root = root;
+        }
+        for directory in &mut other.directories {
+            directory.root = root;
+        }
+        let boundary = std::mem::take(&mut other.depth_boundary);
+        self.depth_boundary.directories.extend(boundary.directories);
+        self.depth_boundary.immediate_size += boundary.immediate_size;
+        self.merge(other, owner_limit);
+    }
+
+    fn merge(&mut self, below: ScanResults, owner_limit: usize) {
+        self.files.extend(below.files);
+        let directories = std::mem::take(&mut self.directories)
+            .into_iter()
+            .chain(below.directories)
+            .map(|directory| (directory.path.clone(), directory))
+            .collect();
+        self.directories = match (&mut self.folded, below.folded) {
+            (Some(folded), Some(more)) => {
+                folded.absorb(more);
+                folded.directory_totals(directories, owner_limit)
+            }
+            _ => aggregate::directory_totals(&self.files, directories, owner_limit),
+        };
+
+        self.total_files += below.total_files;
+        self.total_directories = self.directories.len() as u64;
+        // A side without probed files is physically as large as it is logically
+        self.physical_size = (self.physical_size.is_some() || below.physical_size.is_some()).then(|| {
+            self.physical_size.unwrap_or(self.total_size) + below.physical_size.unwrap_or(below.total_size)
+        });
+        self.total_size += below.total_size;
+        self.errors.extend(below.errors);
+        self.errors.truncate(self.error_log.listing_limit());
+        self.error_log.absorb(below.error_log);
+        self.exclusions.absorb(&below.exclusions);
+        if let (Some(ownership), Some(more)) = (&mut self.ownership, below.ownership) {
+            ownership.matched_size += more.matched_size;
+            ownership.encountered_size += more.encountered_size;
+        }
+        if let (Some(date_filter), Some(more)) = (&mut self.date_filter, below.date_filter) {
+            date_filter.absorb(more);
+        }
+        self.slow_directories.extend(below.slow_directories);
+        self.aliases.directories.extend(below.aliases.directories);
+        self.aliases.unrecorded += below.aliases.unrecorded;
+        self.partial = self.partial.or(below.partial);
+        self.case_collisions.extend(below.case_collisions);
+        case::size_directories(&mut self.case_collisions, &self.directories);
+        self.case_collisions.sort_by(|a, b| a.directory.cmp(&b.directory).then_with(|| a.folded.cmp(&b.folded)));
+        self.symlink_report.absorb(below.symlink_report);
+        self.skipped_mounts.extend(below.skipped_mounts);
+        self.empty_directories.extend(below.empty_directories);
+        self.empty_directories.sort();
+    }
+
+    /// Keep only the directories `retention` asks for, counting the others
+    ///
+    /// Totals are untouched, so call this once nothing recomputes them from
+    /// the directories any more: after [`ScanResults::absorb`] and
+    /// [`ScanResults::retain_files`].
+    pub fn retain_directories(&mut self, retention: &DirectoryRetention) {
+        let directories = std::mem::take(&mut self.directories);
+        self.directories = retention.retain(directories, &mut self.dropped_directories);
+    }
+
+    /// Whether the files were handed out as they were found, leaving `files` empty
+    ///
+    /// The totals and directories are complete either way.
+    pub fn is_streamed(&self) -> bool {
+        self.folded.is_some()
+    }
+
+    /// Hand the collected files to `sink`, keeping only what they add to their directories
+    fn hand_out(&mut self, sink: FileSink<'_>) {
+        let mut folded = aggregate::FoldedFiles::default();
+        for file in std::mem::take(&mut self.files) {
+            folded.add(&file);
+            sink(file);
+        }
+        self.folded = Some(folded);
+    }
+
+    /// Every directory and file collected, as a full snapshot keeps them
+    pub fn entries(&self) -> Vec<ScanEntry> {
+        let directories = self.directories.iter().cloned().map(ScanEntry::Directory);
+        directories.chain(self.files.iter().cloned().map(ScanEntry::File)).collect()
+    }
+
+    /// Results made of the entries of a full snapshot, with totals computed afresh
+    ///
+    /// What only the walk could tell, such as errors and exclusions, is left empty.
+    pub fn from_entries(entries: &[ScanEntry], owner_limit: usize) -> Self {
+        let mut results = Self::default();
+        for entry in entries {
+            match entry {
+                ScanEntry::File(file) => results.files.push(file.clone()),
+                ScanEntry::Directory(directory) => results.directories.push(directory.clone()),
+            }
+        }
+        results.total_directories = results.directories.len() as u64;
+        results.retain_files(|_| true, owner_limit);
+        results
+    }
+
+    /// Keep only the files `keep` accepts, recomputing every total from them
+    ///
+    /// Directories stay, holding only what was kept. Which of the kept bytes
+    /// share extents is not known per directory, so the physical size is dropped.
+    /// The results of a streamed scan have no files to keep.
+    pub fn retain_files(&mut self, keep: impl FnMut(&FileEntry) -> bool, owner_limit: usize) {
+        self.files.retain(keep);
+        let directories = std::mem::take(&mut self.directories)
+            .into_iter()
+            .map(|directory| (directory.path.clone(), directory))
+            .collect();
+        self.directories = aggregate::directory_totals(&self.files, directories, owner_limit);
+        self.total_files = self.files.len() as u64;
+        self.total_size = self.files.iter().map(FileEntry::counted_size).sum();
+        self.physical_size = None;
+    }
 }
 
+/// One entry of a full snapshot, as `--export-scope full` exports it
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "entry", rename_all = "snake_case")]
+pub enum ScanEntry {
+    Directory(DirectoryEntry),
+    File(FileEntry),
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct FileEntry {
     pub path: PathBuf,
     pub size: u64,
     pub modified: Option<DateTime<Utc>>,
+    /// Last access, where the platform records one; see [`AgeSource`](crate::ages::AgeSource) for when it counts
+    #[serde(default)]
+    pub accessed: Option<DateTime<Utc>>,
+    /// Birth time, where the platform and filesystem record one
+    #[serde(default)]
+    pub created: Option<DateTime<Utc>>,
+    /// A symbolic link; measured as the link itself unless `--follow-links`, then as its target
     pub is_symlink: bool,
     pub depth: usize,
+    #[serde(default)]
+    pub uid: Option<u32>,
+    /// Owning group, where the platform has one
+    #[serde(default)]
+    pub gid: Option<u32>,
+    /// Total size of extended attribute values (only measured with `--include-xattrs`)
+    #[serde(default)]
+    pub xattr_size: u64,
+    /// Bytes whose extents are shared with other files (reflinks, snapshots), if probed
+    #[serde(default)]
+    pub shared_extents: Option<u64>,
+    /// Entity tag from an object-storage listing, a cheap stand-in for a content hash
+    #[serde(default)]
+    pub etag: Option<String>,
+    /// Where a symbolic link points, as written in the link
+    #[serde(default)]
+    pub symlink_target: Option<PathBuf>,
+    /// Which of the run's roots the file was found under, counted from 0 for PATH
+    #[serde(default)]
+    pub root: usize,
+    /// Bytes allocated on disk, measured with `--disk-usage`; the totals then count these
+    #[serde(default)]
+    pub disk_usage: Option<u64>,
+    /// Permission bits, recorded with `--security-audit` where the platform has them
+    #[serde(default)]
+    pub mode: Option<u32>,
+    /// Device holding the file; 0 where the platform has none, as for `ino` and `nlink`
+    #[serde(default)]
+    pub dev: u64,
+    /// Inode number, which hard links to the file share
+    #[serde(default)]
+    pub ino: u64,
+    /// Names the file has, hard links included
+    #[serde(default)]
+    pub nlink: u64,
+}
+
+/// Smallest file [`FileEntry::is_sparse`] considers, so block rounding of small files is no sign
+pub const SPARSE_MIN_SIZE: u64 = 1024 * 1024;
+
+impl FileEntry {
+    /// Whether every byte of the file is shared with another file
+    pub fn is_fully_shared(&self) -> bool {
+        self.size > 0 && self.shared_extents == Some(self.size)
+    }
+
+    /// The bytes the totals count: allocated ones with `--disk-usage`, the apparent size otherwise
+    ///
+    /// `size` stays the file's length, which is what reading its contents sees.
+    pub fn counted_size(&self) -> u64 {
+        self.disk_usage.unwrap_or(self.size)
+    }
+
+    /// (device, inode) of the file, where the walk recorded them
+    ///
+    /// Hard links to one file share it. Objects of a listing and entries of
+    /// exports made before it was recorded have none.
+    pub fn inode(&self) -> Option<(u64, u64)> {
+        (self.ino != 0).then_some((self.dev, self.ino))
+    }
+
+    /// Whether less than half of the file's length is allocated, as in VM images and core dumps
+    pub fn is_sparse(&self) -> bool {
+        self.size >= SPARSE_MIN_SIZE && self.disk_usage.is_some_and(|allocated| allocated < self.size / 2)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DirectoryEntry {
     pub path: PathBuf,
     pub total_size: u64,
+    /// Files directly in the directory
     pub file_count: u64,
+    /// Files anywhere below the directory, its own included
+    #[serde(default)]
+    pub recursive_file_count: u64,
+    /// Walked directories directly in the directory
     pub subdirectory_count: u64,
+    /// Walked directories anywhere below the directory
+    #[serde(default)]
+    pub recursive_subdirectory_count: u64,
     pub depth: usize,
+    /// Owners of the bytes below, largest first and at most the scanner's owner limit
+    #[serde(default)]
+    pub owners: Vec<OwnerShare>,
+    /// Which of the run's roots the directory is under, counted from 0 for PATH
+    #[serde(default)]
+    pub root: usize,
+    /// Permission bits, recorded with `--security-audit` where the platform has them
+    #[serde(default)]
+    pub mode: Option<u32>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Bytes below a directory owned by one uid
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OwnerShare {
+    pub uid: u32,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ScanError {
     pub path: PathBuf,
     pub error: String,
     pub error_type: ErrorType,
+    /// The OS error number behind the error, when there is one
+    #[serde(default)]
+    pub errno: Option<i32>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Readability of the scan root's immediate children
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Coverage {
+    pub readable_entries: u64,
+    pub unreadable_entries: u64,
+}
+
+impl Coverage {
+    /// Fraction of first-level entries that could be read (1.0 when there are none)
+    pub fn readable_fraction(&self) -> f64 {
+        let total = self.readable_entries + self.unreadable_entries;
+        if total == 0 {
+            1.0
+        } else {
+            self.readable_entries as f64 / total as f64
+        }
+    }
+
+    /// Whether every first-level entry was readable
+    pub fn is_complete(&self) -> bool {
+        self.unreadable_entries == 0
+    }
+}
+
+/// Apparent and allocated sizes of the collected files side by side, with `--disk-usage`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DiskUsage {
+    /// What the files' lengths add up to
+    pub apparent_size: u64,
+    /// Bytes allocated to them on disk, which the totals count
+    pub allocated_size: u64,
+    /// Files with less than half their length allocated (see [`FileEntry::is_sparse`])
+    pub sparse_files: u64,
+    /// The apparent size of those files
+    pub sparse_size: u64,
+}
+
+impl DiskUsage {
+    pub fn of(files: &[FileEntry]) -> Self {
+        let mut usage = Self::default();
+        files.iter().for_each(|file| usage.add(file));
+        usage
+    }
+
+    /// Count one file
+    pub fn add(&mut self, file: &FileEntry) {
+        self.apparent_size += file.size;
+        self.allocated_size += file.counted_size();
+        if file.is_sparse() {
+            self.sparse_files += 1;
+            self.sparse_size += file.size;
+        }
+    }
+}
+
+/// Restricts a scan to files owned by one user
+#[derive(Debug, Clone, PartialEq)]
+pub struct OwnerFilter {
+    pub user: String,
+    pub uid: u32,
+}
+
+/// How much of the encountered volume an ownership filter kept
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Ownership {
+    pub user: String,
+    pub uid: u32,
+    pub matched_size: u64,
+    pub encountered_size: u64,
+}
+
+impl OwnerFilter {
+    /// Resolve `--mine` / `--user` into a filter, if either was given
+    pub fn from_args(args: &Args) -> Result<Option<Self>> {
+        if args.mine {
+            let uid = current_uid().context("--mine is only supported on Unix systems")?;
+            let user = user_name(uid).unwrap_or_else(|| uid.to_string());
+            Ok(Some(Self { user, uid }))
+        } else if let Some(user) = &args.user {
+            let uid = lookup_uid(user)
+                .or_else(|| user.parse().ok())
+                .with_context(|| format!("Unknown user: {}", user))?;
+            Ok(Some(Self { user: user.clone(), uid }))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Whether a file with this owner passes the filter
+    pub fn matches(&self, uid: Option<u32>) -> bool {
+        uid == Some(self.uid)
+    }
+}
+
+/// Restricts a scan to files of one group
+#[derive(Debug, Clone, PartialEq)]
+pub struct GroupFilter {
+    pub group: String,
+    pub gid: u32,
+}
+
+impl GroupFilter {
+    /// Resolve `--group` into a filter, if it was given
+    pub fn from_args(args: &Args) -> Result<Option<Self>> {
+        let Some(group) = &args.group else {
+            return Ok(None);
+        };
+        let gid = lookup_gid(group)
+            .or_else(|| group.parse().ok())
+            .with_context(|| format!("Unknown group: {}", group))?;
+        Ok(Some(Self { group: group.clone(), gid }))
+    }
+
+    /// Whether a file of this group passes the filter
+    pub fn matches(&self, gid: Option<u32>) -> bool {
+        gid == Some(self.gid)
+    }
+}
+
+impl Ownership {
+    /// Fraction of the encountered bytes owned by the filtered user (1.0 when nothing was seen)
+    pub fn covered_fraction(&self) -> f64 {
+        if self.encountered_size == 0 {
+            1.0
+        } else {
+            self.matched_size as f64 / self.encountered_size as f64
+        }
+    }
+}
+
+/// How many walked entries the hidden-file rule and each exclude pattern removed
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ExclusionStats {
+    /// Entries the walk produced, including excluded ones
+    pub encountered: u64,
+    pub excluded: u64,
+    /// Entries skipped because they are hidden (without `--show-hidden`)
+    pub hidden: u64,
+    /// Hits per pattern, the `--exclude` globs followed by the `--exclude-regex`
+    /// patterns, each in command-line order. An entry matching several
+    /// patterns counts once for each of them.
+    pub patterns: Vec<PatternHits>,
+    /// Patterns that match the scan root itself
+    pub root_matches: Vec<String>,
+    /// Exports, journals, and cache files of diranalyzer itself, left out of every count
+    #[serde(default)]
+    pub own_outputs: u64,
+    /// Entries skipped by `.gitignore` and `.ignore` rules, with `--respect-gitignore`
+    #[serde(default)]
+    pub ignored: Option<u64>,
+    /// What each rule kept out, in pipeline order: an entry counts for the
+    /// first rule that rejected it only, unlike the pattern hits above
+    #[serde(default)]
+    pub causes: Vec<ExclusionCount>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PatternHits {
+    pub pattern: String,
+    pub hits: u64,
+}
+
+impl ExclusionStats {
+    /// Add the counts of a later walk with the same patterns
+    fn absorb(&mut self, other: &ExclusionStats) {
+        self.encountered += other.encountered;
+        self.excluded += other.excluded;
+        self.hidden += other.hidden;
+        self.own_outputs += other.own_outputs;
+        if let (Some(ignored), Some(more)) = (self.ignored.as_mut(), other.ignored) {
+            *ignored += more;
+        }
+        for (pattern, more) in self.patterns.iter_mut().zip(&other.patterns) {
+            pattern.hits += more.hits;
+        }
+        for more in &other.causes {
+            match self.causes.iter_mut().find(|count| count.cause == more.cause) {
+                Some(count) => {
+                    count.entries += more.entries;
+                    count.size += more.size;
+                }
+                None => self.causes.push(more.clone()),
+            }
+        }
+    }
+
+    /// Fraction of encountered entries that were excluded (0.0 when nothing was walked)
+    pub fn excluded_fraction(&self) -> f64 {
+        if self.encountered == 0 {
+            0.0
+        } else {
+            self.excluded as f64 / self.encountered as f64
+        }
+    }
+
+    /// Patterns that excluded anything, most hits first
+    pub fn responsible_patterns(&self) -> Vec<&PatternHits> {
+        let mut patterns: Vec<_> = self.patterns.iter().filter(|p| p.hits > 0).collect();
+        patterns.sort_by_key(|p| std::cmp::Reverse(p.hits));
+        patterns
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum ErrorType {
     PermissionDenied,
     FileNotFound,
@@ -65,13 +778,9 @@ pub enum ErrorType {
 
 impl DirectoryScanner {
     /// Create a new scanner with the given configuration
+    ///
+    /// A root that is a symlink is scanned as its target (see [`resolve_root`]).
     pub fn new(args: &Args) -> Result<Self> {
-        let exclude_patterns = args.exclude_patterns
-            .iter()
-            .map(|pattern| Regex::new(pattern))
-            .collect::<Result<Vec<_>, _>>()
-            .context("Failed to compile exclude patterns")?;
-
         let progress_bar = if !args.quiet {
             let pb = ProgressBar::new_spinner();
             pb.set_style(
@@ -86,176 +795,1922 @@ impl DirectoryScanner {
             None
         };
 
+        // Interactive runs answer SIGUSR1 with an interim report; --interim-every adds a schedule
+        let interim = (!args.quiet || args.interim_every.is_some()).then(|| {
+            interim::install_signal_handler();
+            let progress_bar = progress_bar.clone();
+            let sink: InterimSink = Box::new(move |snapshot: &InterimSnapshot| {
+                let print = || snapshot.lines().iter().for_each(|line| println!("{}", line));
+                match &progress_bar {
+                    Some(pb) => pb.suspend(print),
+                    None => print(),
+                }
+            });
+            interim::Interim::new(args.interim_every, sink)
+        });
+
+        let root = resolve_root(&args.path);
+        let mut filters = FilterPipeline::new(args.show_hidden, exclude_filter(args, &root)?, OwnerFilter::from_args(args)?);
+        if args.respect_gitignore {
+            filters.respect_ignore_files(ignores::IgnoreFiles::new(&root, !args.no_global_gitignore));
+        }
+        filters.include_only(root.clone(), include_patterns(args)?);
+        if let Some(filter) = GroupFilter::from_args(args)? {
+            filters.filter_group(filter);
+        }
+        if let Some(filter) = ModifiedFilter::from_args(args, Utc::now()) {
+            filters.filter_modified(filter);
+        }
+        let root_device = args.one_file_system.then(|| fs::metadata(&root).ok().map(|metadata| file_device(&metadata))).flatten();
         Ok(Self {
-            args: args.clone(),
-            exclude_patterns,
+            filters,
+            args: Args { path: root, ..args.clone() },
+            extent_probe: ExtentProbe::default(),
+            slow_thresholds: SlowThresholds::default(),
+            owner_limit: DEFAULT_OWNER_LIMIT,
             progress_bar,
+            interim,
+            visited: alias::VisitedDirectories::default(),
+            budget: cap::Budget::new(ScanCaps { files: args.max_files, bytes: args.max_bytes, duration: args.max_duration }),
+            latency: IoLatency::Off,
+            detect_case_collisions: args.detect_case_collisions,
+            find_broken_links: args.find_broken_links,
+            errors_full: args.errors_full,
+            threads: walk_threads(args.threads)?,
+            files_read: AtomicU64::new(0),
+            root_device,
         })
     }
 
-    /// Scan the directory structure
-    pub async fn scan(&mut self) -> Result<ScanResults> {
-        let mut files = Vec::new();
-        let mut directories = HashMap::new();
-        let mut errors = Vec::new();
-        let mut total_size = 0u64;
+    /// Report interim totals to `sink` every `every` (and on SIGUSR1) instead of printing them
+    pub fn with_interim(mut self, every: Option<Duration>, sink: impl Fn(&InterimSnapshot) + Send + Sync + 'static) -> Self {
+        self.interim = Some(interim::Interim::new(every, Box::new(sink)));
+        self
+    }
 
-        let walker = WalkDir::new(&self.args.path)
-            .max_depth(self.args.max_depth)
-            .follow_links(self.args.follow_links);
+    /// End the scan once `cancellation` is set, keeping what was collected as a partial result
+    pub fn with_cancellation(mut self, cancellation: Cancellation) -> Self {
+        self.budget = std::mem::take(&mut self.budget).cancelled_by(cancellation);
+        self
+    }
 
-        for entry in walker {
-            match entry {
-                Ok(entry) => {
-                    if let Some(ref pb) = self.progress_bar {
-                        pb.inc(1);
-                        if files.len() % 100 == 0 {
-                            pb.set_message(format!("Scanning... {} files found", files.len()));
-                        }
-                    }
+    /// Totals the scan has reached so far, when interim reporting is on
+    pub fn interim_snapshot(&self) -> Option<InterimSnapshot> {
+        self.interim.as_ref().map(interim::Interim::snapshot)
+    }
 
-                    if self.should_exclude(&entry) {
-                        continue;
-                    }
+    /// Add a filter, run after the built-in ones and those added before it
+    pub fn with_filter(mut self, filter: impl FileFilter + 'static) -> Self {
+        self.filters.push(Box::new(filter));
+        self
+    }
 
-                    if entry.file_type().is_file() {
-                        if let Ok(file_entry) = self.process_file_entry(&entry) {
-                            total_size += file_entry.size;
-                            files.push(file_entry);
-                        } else {
-                            errors.push(ScanError {
-                                path: entry.path().to_path_buf(),
-                                error: "Failed to process file".to_string(),
-                                error_type: ErrorType::IoError,
-                            });
-                        }
-                    } else if entry.file_type().is_dir() {
-                        self.process_directory_entry(&entry, &mut directories);
-                    }
-                }
-                Err(error) => {
-                    let error_type = if error.to_string().contains("Permission denied") {
-                        ErrorType::PermissionDenied
-                    } else {
-                        ErrorType::Other
-                    };
+    /// Leave out the files the run writes (see [`WriteTargets::within`])
+    pub(crate) fn with_own_outputs(mut self, targets: WriteTargets) -> Self {
+        self.filters.skip_own_outputs(targets);
+        self
+    }
 
-                    errors.push(ScanError {
-                        path: error.path().unwrap_or_else(|| Path::new("unknown")).to_path_buf(),
-                        error: error.to_string(),
-                        error_type,
-                    });
-                }
+    /// Time the walk's directory reads and stats into `latency`
+    pub(crate) fn with_io_latency(mut self, latency: IoLatency) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    /// Report directories past these limits as slow
+    pub fn with_slow_thresholds(mut self, thresholds: SlowThresholds) -> Self {
+        self.slow_thresholds = thresholds;
+        self
+    }
+
+    /// Remember at most this many directory identities when looking for aliases
+    pub fn with_identity_limit(mut self, limit: usize) -> Self {
+        self.visited = alias::VisitedDirectories::new(limit);
+        self
+    }
+
+    /// Keep at most this many owners per directory
+    pub fn with_owner_limit(mut self, limit: usize) -> Self {
+        self.owner_limit = limit;
+        self
+    }
+
+    /// Replace the shared-extent probe, as with a mock in tests
+    #[cfg(test)]
+    fn with_extent_probe(mut self, probe: ExtentProbe) -> Self {
+        self.extent_probe = probe;
+        self
+    }
+
+    /// Pretend the root lies on `device`, so that everything below it is on another filesystem
+    #[cfg(test)]
+    fn with_root_device(mut self, device: u64) -> Self {
+        self.root_device = Some(device);
+        self
+    }
+
+    /// Whether the hidden rule or an exclude pattern keeps `path` out of the scan
+    pub fn excludes_path(&self, path: &Path) -> bool {
+        self.filters.excludes_path(path)
+    }
+
+    /// Check that the root is readable and count readable vs unreadable first-level entries
+    ///
+    /// An unreadable root is an error; unreadable children only reduce coverage.
+    pub fn probe_coverage(&self) -> Result<Coverage> {
+        let root = &self.args.path;
+        let entries = fs::read_dir(root).map_err(|error| {
+            if error.kind() == std::io::ErrorKind::PermissionDenied {
+                anyhow::anyhow!(
+                    "Permission denied reading {}: run with sudo or choose a directory you can read",
+                    root.display()
+                )
+            } else {
+                anyhow::Error::new(error).context(format!("Failed to read directory: {}", root.display()))
+            }
+        })?;
+
+        let mut coverage = Coverage::default();
+        for entry in entries {
+            let Ok(entry) = entry else {
+                coverage.unreadable_entries += 1;
+                continue;
+            };
+            let path = entry.path();
+            if self.filters.excludes_path(&path) {
+                continue;
+            }
+
+            let readable = match entry.file_type() {
+                Ok(file_type) if file_type.is_dir() => fs::read_dir(&path).is_ok(),
+                Ok(file_type) if file_type.is_file() => fs::File::open(&path).is_ok(),
+                Ok(_) => true,
+                Err(_) => false,
+            };
+            if readable {
+                coverage.readable_entries += 1;
+            } else {
+                coverage.unreadable_entries += 1;
             }
         }
 
+        Ok(coverage)
+    }
+
+    /// Scan the directory structure
+    pub async fn scan(&mut self) -> Result<ScanResults> {
+        self.walk_tree(None)
+    }
+
+    /// Scan the directory structure, handing each file to `sink` as it is found instead of keeping it
+    ///
+    /// The results hold no files (see [`ScanResults::is_streamed`]); every
+    /// total and directory is as [`DirectoryScanner::scan`] gives it. Files
+    /// arrive in walk order on the calling thread, so the memory the scan
+    /// holds grows with the directories, not with the files.
+    pub async fn scan_streaming(&mut self, mut sink: impl FnMut(FileEntry)) -> Result<ScanResults> {
+        self.walk_tree(Some(&mut sink))
+    }
+
+    /// Walk the contents of directories the depth limit stopped at, without a limit
+    ///
+    /// The directories themselves are not part of the results, only what lies
+    /// below them. Fold the results into the first scan with [`ScanResults::absorb`].
+    pub async fn scan_below(&mut self, directories: &[PathBuf]) -> Result<ScanResults> {
+        self.walk_below(directories, None)
+    }
+
+    fn walk_tree(&mut self, sink: Option<FileSink<'_>>) -> Result<ScanResults> {
+        let visited = self.visited.fresh();
+        let mut walk = Walk {
+            streamed: sink.is_some(),
+            tracked: true,
+            visited: Some(&visited),
+            budget: self.budget.fresh(),
+            case_collisions: self.detect_case_collisions.then(case::Found::default),
+            links: self.find_broken_links.then(|| links::Classifier::new(&self.args.path)),
+            errors: ErrorLog::new(self.errors_full),
+            ..Walk::default()
+        };
+        let root = self.args.path.clone();
+        self.walk_split(&root, 0, self.args.depth_limit(), &mut walk, sink);
+        let aliases = visited.take_aliases();
+        let partial = walk.budget.reached();
+        self.budget = std::mem::take(&mut walk.budget);
+
         if let Some(ref pb) = self.progress_bar {
             pb.finish_with_message(format!("Scan complete! {} files, {} directories", 
-                files.len(), directories.len()));
+                walk.collected(), walk.directories.len()));
         }
 
-        // Calculate directory sizes and convert to vector
-        let directories = self.calculate_directory_sizes(&files, directories);
+        let mut exclusions = ExclusionStats {
+            root_matches: self.patterns_matching_root().into_iter().map(str::to_string).collect(),
+            encountered: walk.encountered,
+            ..Default::default()
+        };
+        self.filters.take_exclusions(&mut exclusions);
+        let skipped_mounts = walk.take_skipped_mounts(&mut exclusions);
+        let empty_directories = walk.take_empty_directories(partial);
 
-        Ok(ScanResults {
-            total_files: files.len() as u64,
+        // Calculate directory sizes and convert to vector
+        let directories = walk.directory_totals(self.owner_limit);
+        let case_collisions = walk.case_collisions.take().map(|found| found.describe(&directories)).unwrap_or_default();
+        let results = ScanResults {
+            total_files: walk.collected(),
+            folded: walk.take_folded(),
             total_directories: directories.len() as u64,
-            total_size,
-            files,
+            total_size: walk.total_size,
+            ownership: self.filters.take_ownership(walk.total_size),
+            date_filter: self.filters.take_date_filter(),
+            files: walk.files,
             directories,
-            errors,
-        })
+            errors: walk.errors.take_listed(),
+            error_log: walk.errors,
+            exclusions,
+            physical_size: walk.physical.total(),
+            slow_directories: walk.slow_directories,
+            depth_boundary: walk.boundary,
+            aliases,
+            partial,
+            case_collisions,
+            symlink_report: walk.links.map(links::Classifier::finish).unwrap_or_default(),
+            dropped_directories: DroppedDirectories::default(),
+            skipped_mounts,
+            empty_directories,
+        };
+        self.visited = visited;
+        Ok(results)
     }
 
-    fn should_exclude(&self, entry: &DirEntry) -> bool {
-        let path_str = entry.path().to_string_lossy();
-        
-        // Check if hidden and hidden files are disabled
-        if !self.args.show_hidden && entry.file_name().to_string_lossy().starts_with('.') {
-            return true;
-        }
-
-        // Check exclude patterns
-        for pattern in &self.exclude_patterns {
-            if pattern.is_match(&path_str) {
-                return true;
+    fn walk_below(&mut self, directories: &[PathBuf], mut sink: Option<FileSink<'_>>) -> Result<ScanResults> {
+        let visited = std::mem::take(&mut self.visited);
+        let budget = std::mem::take(&mut self.budget);
+        let case_collisions = self.detect_case_collisions.then(case::Found::default);
+        let links = self.find_broken_links.then(|| links::Classifier::new(&self.args.path));
+        let errors = ErrorLog::new(self.errors_full);
+        let streamed = sink.is_some();
+        let mut walk = Walk { streamed, tracked: true, visited: Some(&visited), budget, case_collisions, links, errors, ..Walk::default() };
+        for directory in directories {
+            if walk.budget.reached().is_some() {
+                break;
             }
+            let depth = directory.strip_prefix(&self.args.path).map_or(0, |relative| relative.components().count());
+            self.walk_split(directory, depth, None, &mut walk, sink.as_deref_mut());
         }
+        let aliases = visited.take_aliases();
+        let partial = walk.budget.reached();
+        self.budget = std::mem::take(&mut walk.budget);
 
-        false
-    }
-
-    fn process_file_entry(&self, entry: &DirEntry) -> Result<FileEntry> {
-        let metadata = entry.metadata()?;
-        let modified = metadata.modified()
-            .ok()
-            .and_then(|time| {
-                time.duration_since(SystemTime::UNIX_EPOCH)
-                    .ok()
-                    .map(|duration| DateTime::from_timestamp(duration.as_secs() as i64, 0))
-                    .flatten()
-            });
+        if let Some(ref pb) = self.progress_bar {
+            pb.finish_with_message(format!("Descended below the depth limit! {} more files", walk.collected()));
+        }
 
-        Ok(FileEntry {
-            path: entry.path().to_path_buf(),
-            size: metadata.len(),
-            modified,
-            is_symlink: metadata.file_type().is_symlink(),
-            depth: entry.depth(),
-        })
+        let mut exclusions = ExclusionStats { encountered: walk.encountered, ..Default::default() };
+        self.filters.take_exclusions(&mut exclusions);
+        let skipped_mounts = walk.take_skipped_mounts(&mut exclusions);
+        let empty_directories = walk.take_empty_directories(partial);
+        let results = ScanResults {
+            total_files: walk.collected(),
+            folded: walk.take_folded(),
+            total_directories: walk.directories.len() as u64,
+            total_size: walk.total_size,
+            ownership: self.filters.take_ownership(walk.total_size),
+            date_filter: self.filters.take_date_filter(),
+            files: walk.files,
+            directories: walk.directories.into_values().collect(),
+            errors: walk.errors.take_listed(),
+            error_log: walk.errors,
+            exclusions,
+            physical_size: walk.physical.total(),
+            slow_directories: walk.slow_directories,
+            depth_boundary: DepthBoundary::default(),
+            aliases,
+            partial,
+            // Directories are sized once absorb has their totals
+            case_collisions: walk.case_collisions.map(|found| found.describe(&[])).unwrap_or_default(),
+            symlink_report: walk.links.map(links::Classifier::finish).unwrap_or_default(),
+            dropped_directories: DroppedDirectories::default(),
+            skipped_mounts,
+            empty_directories,
+        };
+        self.visited = visited;
+        Ok(results)
     }
 
-    fn process_directory_entry(
-        &self,
-        entry: &DirEntry,
-        directories: &mut HashMap<PathBuf, DirectoryEntry>,
-    ) {
-        directories.insert(
-            entry.path().to_path_buf(),
+    /// Totals of one directory the depth limit kept the scan out of, walked without a limit
+    ///
+    /// Nothing is added to the scan's results; only the directory's own entry is returned.
+    pub fn measure_directory(&self, directory: &Path) -> DirectoryEntry {
+        let depth = directory.strip_prefix(&self.args.path).map_or(0, |relative| relative.components().count());
+        let mut walk = Walk::default();
+        self.walk_split(directory, depth, None, &mut walk, None);
+        walk.directories.insert(
+            directory.to_path_buf(),
             DirectoryEntry {
-                path: entry.path().to_path_buf(),
-                total_size: 0, // Will be calculated later
+                path: directory.to_path_buf(),
+                total_size: 0,
                 file_count: 0,
+                recursive_file_count: 0,
                 subdirectory_count: 0,
-                depth: entry.depth(),
+                recursive_subdirectory_count: 0,
+                depth,
+                owners: Vec::new(),
+                root: 0,
+                mode: None,
             },
         );
+        // Keep this walk's filter counts out of the next scan's statistics
+        self.filters.take_exclusions(&mut ExclusionStats::default());
+        self.filters.take_ownership(0);
+        self.filters.take_date_filter();
+        aggregate::directory_totals(&walk.files, walk.directories, self.owner_limit)
+            .into_iter()
+            .find(|entry| entry.path == directory)
+            .expect("the measured directory is among the totals")
     }
 
-    fn calculate_directory_sizes(
-        &self,
-        files: &[FileEntry],
-        mut directories: HashMap<PathBuf, DirectoryEntry>,
-    ) -> Vec<DirectoryEntry> {
-        // Calculate sizes and counts for each directory
-        for file in files {
-            let mut current_path = file.path.parent();
-            
-            while let Some(dir_path) = current_path {
-                if let Some(dir_entry) = directories.get_mut(dir_path) {
-                    dir_entry.total_size += file.size;
-                    if dir_path == file.path.parent().unwrap_or_else(|| Path::new("")) {
-                        dir_entry.file_count += 1;
-                    }
-                }
-                current_path = dir_path.parent();
-            }
+    /// Walk `root` as [`walk`](Self::walk) does, walking its subdirectories at once on the `--threads` workers
+    ///
+    /// Each subdirectory is walked on its own, and the walks are taken in in
+    /// the order the directories were listed, so the totals are those of a
+    /// single walk. Streamed files and `--max-files` or `--max-bytes` cutoffs
+    /// are taken in walk order, so they keep the single walk.
+    fn walk_split(&self, root: &Path, base_depth: usize, max_depth: Option<usize>, walk: &mut Walk<'_>, sink: Option<&mut (dyn FnMut(FileEntry) + '_)>) {
+        let Some(pool) = self.threads.as_ref().filter(|_| sink.is_none() && !walk.budget.counts_files()) else {
+            return self.walk(root, base_depth, max_depth, walk, sink);
+        };
+        walk.subtrees = Some(Vec::new());
+        self.walk(root, base_depth, max_depth, walk, None);
+        let subtrees = walk.subtrees.take().unwrap_or_default();
+        let parts: Vec<_> = subtrees.iter().map(|directory| walk.part(directory)).collect();
+        let parts: Vec<_> = pool.install(|| {
+            subtrees
+                .par_iter()
+                .zip(parts)
+                .map(|(directory, mut part)| {
+                    self.walk(directory, base_depth + 1, max_depth, &mut part, None);
+                    part
+                })
+                .collect()
+        });
+        for part in parts {
+            walk.absorb(part);
         }
+        slow::slowest_first(&mut walk.slow_directories);
+    }
 
-        // Calculate subdirectory counts
-        let dir_paths: Vec<PathBuf> = directories.keys().cloned().collect();
-        for dir_path in &dir_paths {
-            let subdirs = dir_paths.iter()
-                .filter(|other_path| {
-                    other_path.parent() == Some(dir_path) && *other_path != dir_path
-                })
-                .count();
-            
-            if let Some(dir_entry) = directories.get_mut(dir_path) {
-                dir_entry.subdirectory_count = subdirs as u64;
-            }
+    /// Walk `root`, found `base_depth` levels below the scan root, into `walk`
+    ///
+    /// With a `max_depth`, directories at that depth are recorded as the
+    /// boundary instead of being entered. Below the scan root, `root` itself
+    /// is not yielded again, but what is read in it is still charged to it.
+    /// Files go to `sink` when there is one.
+    fn walk(&self, root: &Path, base_depth: usize, max_depth: Option<usize>, walk: &mut Walk<'_>, mut sink: Option<&mut (dyn FnMut(FileEntry) + '_)>) {
+        let mut reads = slow::ReadTracker::new(self.slow_thresholds);
+        if base_depth > 0 {
+            reads.record(root, 0, true);
         }
 
-        directories.into_values().collect()
+        let mut walker = WalkDir::new(root)
+            .min_depth(usize::from(base_depth > 0))
+            .max_depth(max_depth.map_or(usize::MAX, |limit| limit - base_depth))
+            .follow_links(self.args.follow_links)
+            .into_iter();
+
+        let interim = self.interim.as_ref().filter(|_| walk.tracked);
+        let mut siblings = walk.case_collisions.is_some().then(|| case::SiblingNames::new(root));
+        // A run of files waiting to be read together
+        let mut files = Vec::new();
+        loop {
+            // A cancelled run ends the walk between two steps, as a cap does
+            if walk.budget.stopped() {
+                break;
+            }
+            // A step of the walk reads the directory it is in, and opens the ones it enters
+            let started = self.latency.start();
+            let Some(entry) = walker.next() else {
+                break;
+            };
+            let read = match &entry {
+                Ok(entry) => entry.path().parent().filter(|_| entry.depth() > 0).unwrap_or(entry.path()),
+                Err(error) => error.path().unwrap_or(root),
+            };
+            self.latency.finish(started, IoOperation::ReadDir, read);
+
+            if let Some(interim) = interim {
+                interim.tick();
+            }
+            match entry {
+                Ok(entry) => {
+                    let slow_read = reads.record(entry.path(), entry.depth(), entry.file_type().is_dir());
+                    if let Some(ref pb) = self.progress_bar {
+                        pb.inc(1);
+                        if let Some(message) = slow_read {
+                            pb.println(format!("⏳ {}", message));
+                        }
+                    }
+
+                    walk.encountered += 1;
+                    let depth = base_depth + entry.depth();
+                    // Every name read counts, whatever the filters keep: they all collide when copied
+                    if let Some(siblings) = siblings.as_mut() {
+                        siblings.add(entry.path(), entry.depth(), entry.file_type().is_dir());
+                    }
+                    // Links are classified whether or not the walk follows them
+                    if let Some(links) = walk.links.as_mut().filter(|_| entry.depth() > 0 && entry.path_is_symlink()) {
+                        if !self.filters.excludes_path(entry.path()) {
+                            links.add(entry.path());
+                        }
+                    }
+
+                    // Links that are not followed are entries of their own
+                    if entry.file_type().is_file() || entry.file_type().is_symlink() {
+                        walk.empty.holds(entry.path());
+                        // Above --min-depth files are walked past, as walkdir's min_depth would
+                        if depth < self.args.min_depth {
+                            continue;
+                        }
+                        files.push((entry, depth));
+                        if files.len() >= FILE_BATCH && !self.take_files(&mut files, walk, interim, sink.as_deref_mut()) {
+                            break;
+                        }
+                        continue;
+                    }
+                    // Files met so far are taken before anything that follows them in the walk
+                    if !self.take_files(&mut files, walk, interim, sink.as_deref_mut()) {
+                        break;
+                    }
+                    if entry.file_type().is_dir() {
+                        if self.filters.prune(entry.path()) {
+                            // The root is left out of the listings but always walked
+                            if entry.depth() > 0 {
+                                walk.empty.holds(entry.path());
+                                walker.skip_current_dir();
+                            }
+                            continue;
+                        }
+                        let metadata = (walk.visited.is_some() || self.root_device.is_some())
+                            .then(|| self.latency.time(IoOperation::Stat, entry.path(), || entry.metadata()).ok())
+                            .flatten();
+                        // With --one-file-system, other filesystems are listed but not entered
+                        if let (Some(root_device), Some(metadata)) = (self.root_device, &metadata) {
+                            let device = file_device(metadata);
+                            if device != root_device && entry.depth() > 0 {
+                                walk.skipped_mounts.push(SkippedMount { path: entry.path().to_path_buf(), device, filesystem: None });
+                                walk.empty.holds(entry.path());
+                                walker.skip_current_dir();
+                                continue;
+                            }
+                        }
+                        // A bind mount shows a directory again under another path; count it once
+                        if let (Some(visited), Some(metadata)) = (walk.visited.as_mut(), &metadata) {
+                            if !visited.visit(entry.path(), metadata) {
+                                walk.empty.holds(entry.path());
+                                walker.skip_current_dir();
+                                continue;
+                            }
+                        }
+                        if depth >= self.args.min_depth {
+                            self.process_directory_entry(&entry, depth, &mut walk.directories);
+                            // The scan root is never a candidate for removal
+                            if depth > 0 {
+                                walk.empty.enter(entry.path());
+                            }
+                        }
+                        if Some(depth) == max_depth {
+                            if let Some(size) = self.boundary_contents(entry.path()) {
+                                walk.empty.unknown(entry.path());
+                                walk.boundary.directories.push(entry.path().to_path_buf());
+                                walk.boundary.immediate_size += size;
+                            }
+                        } else if let Some(subtrees) = walk.subtrees.as_mut().filter(|_| entry.depth() == 1) {
+                            subtrees.push(entry.path().to_path_buf());
+                            walker.skip_current_dir();
+                        }
+                    }
+                }
+                Err(error) => {
+                    if !self.take_files(&mut files, walk, interim, sink.as_deref_mut()) {
+                        break;
+                    }
+                    // A followed link that leads nowhere, or back up the tree, fails the step but is still a link
+                    if let (Some(links), Some(path)) = (walk.links.as_mut(), error.path()) {
+                        if fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_symlink()) && !self.filters.excludes_path(path) {
+                            links.add(path);
+                        }
+                    }
+                    let error_type = if error.to_string().contains("Permission denied") {
+                        ErrorType::PermissionDenied
+                    } else {
+                        ErrorType::Other
+                    };
+
+                    if let Some(interim) = interim {
+                        interim.add_error();
+                    }
+                    // Whatever failed, a directory or an entry in one, leaves its directory's contents unknown
+                    if let Some(path) = error.path() {
+                        walk.empty.unknown(path);
+                        walk.empty.holds(path);
+                    }
+                    walk.errors.push(ScanError {
+                        path: error.path().unwrap_or_else(|| Path::new("unknown")).to_path_buf(),
+                        error: error.to_string(),
+                        error_type,
+                        errno: errno(&error),
+                    });
+                }
+            }
+        }
+        self.take_files(&mut files, walk, interim, sink);
+        walk.slow_directories.extend(reads.finish());
+        if let (Some(found), Some(siblings)) = (walk.case_collisions.as_mut(), siblings) {
+            found.extend(siblings.finish());
+        }
+    }
+
+    /// Read, filter, and measure a run of files on the walk's threads, then take them in walk order
+    ///
+    /// Returns `false` once a cap is reached: the walk ends there, and the
+    /// files after the one that reached it are left out.
+    fn take_files(
+        &self,
+        files: &mut Vec<(DirEntry, usize)>,
+        walk: &mut Walk<'_>,
+        interim: Option<&interim::Interim>,
+        mut sink: Option<&mut (dyn FnMut(FileEntry) + '_)>,
+    ) -> bool {
+        let read = |(entry, depth): &(DirEntry, usize)| -> Result<FileRead> {
+            let (mut file_entry, device) = self.process_file_entry(entry, *depth)?;
+            let read = self.files_read.fetch_add(1, Ordering::Relaxed) + 1;
+            if let Some(pb) = self.progress_bar.as_ref().filter(|_| read.is_multiple_of(100)) {
+                pb.set_message(format!("Scanning... {} files found", read));
+            }
+            if self.root_device.is_some_and(|root_device| root_device != device) {
+                return Ok(FileRead::OtherDevice { device, size: file_entry.counted_size() });
+            }
+            if !self.filters.include(&file_entry) {
+                return Ok(FileRead::Filtered);
+            }
+            let extents = self.measure_file(&mut file_entry, device);
+            Ok(FileRead::Kept(file_entry, extents))
+        };
+        let files = std::mem::take(files);
+        let read: Vec<_> = match &self.threads {
+            Some(pool) if files.len() > 1 => pool.install(|| files.par_iter().map(read).collect()),
+            _ => files.iter().map(read).collect(),
+        };
+
+        for ((entry, _), result) in files.into_iter().zip(read) {
+            match result {
+                Ok(FileRead::Kept(file_entry, extents)) => {
+                    // Past a cap the walk ends here; nothing further is counted
+                    if !walk.budget.admit(file_entry.counted_size()) {
+                        return false;
+                    }
+                    walk.total_size += file_entry.counted_size();
+                    walk.physical.add(file_entry.counted_size(), extents.as_ref());
+                    if let Some(interim) = interim {
+                        interim.add_file(&file_entry);
+                    }
+                    walk.keep(file_entry, sink.as_deref_mut());
+                }
+                Ok(FileRead::Filtered) => {}
+                Ok(FileRead::OtherDevice { device, size }) => {
+                    walk.skipped_mounts.push(SkippedMount { path: entry.into_path(), device, filesystem: None });
+                    walk.skipped_mount_size += size;
+                }
+                Err(error) => {
+                    if let Some(interim) = interim {
+                        interim.add_error();
+                    }
+                    walk.errors.push(ScanError {
+                        path: entry.path().to_path_buf(),
+                        error: "Failed to process file".to_string(),
+                        error_type: ErrorType::IoError,
+                        errno: error.downcast_ref::<walkdir::Error>().and_then(errno),
+                    });
+                }
+            }
+        }
+        true
+    }
+
+    /// Total size of the files directly in a directory the walk will not enter,
+    /// or `None` if it holds nothing the filters would keep
+    ///
+    /// Only names and metadata are read, so this stays cheap on wide boundaries.
+    fn boundary_contents(&self, directory: &Path) -> Option<u64> {
+        let mut found = false;
+        let mut size = 0;
+        for entry in fs::read_dir(directory).ok()?.flatten() {
+            if self.filters.excludes_path(&entry.path()) {
+                continue;
+            }
+            found = true;
+            if let Ok(metadata) = entry.metadata() {
+                if metadata.is_file() {
+                    size += if self.args.disk_usage { allocated_size(&metadata) } else { metadata.len() };
+                }
+            }
+        }
+        found.then_some(size)
+    }
+
+    /// Exclude patterns that match the scan root, which usually means they match everything
+    pub fn patterns_matching_root(&self) -> Vec<&str> {
+        self.filters.exclude().matching(&self.args.path).map(ExcludePattern::as_str).collect()
+    }
+
+    /// Build a file's entry from its metadata, returning the device holding it
+    fn process_file_entry(&self, entry: &DirEntry, depth: usize) -> Result<(FileEntry, u64)> {
+        let metadata = self.latency.time(IoOperation::Stat, entry.path(), || entry.metadata())?;
+        let mut file_entry = file_entry(entry.path(), &metadata, depth);
+        if self.args.disk_usage {
+            file_entry.disk_usage = Some(allocated_size(&metadata));
+        }
+        // A link's own bits are always 0777; a followed one is audited as its target
+        if self.args.security_audit && !metadata.file_type().is_symlink() {
+            file_entry.mode = file_mode(&metadata);
+        }
+        // A followed link is measured as its target but stays a link
+        if entry.path_is_symlink() && !file_entry.is_symlink {
+            file_entry.is_symlink = true;
+            file_entry.symlink_target = fs::read_link(entry.path()).ok();
+        }
+        Ok((file_entry, file_device(&metadata)))
+    }
+
+    /// Measure the costlier properties of a file that passed the filters
+    fn measure_file(&self, file: &mut FileEntry, device: u64) -> Option<FileExtents> {
+        // Both would read through the link to a target the scan does not count
+        if file.is_symlink && !self.args.follow_links {
+            return None;
+        }
+        if self.args.include_xattrs {
+            file.xattr_size = xattr::total_size(&file.path);
+        }
+        let extents = self.extent_probe.probe(&file.path, file.size, device);
+        file.shared_extents = extents.as_ref().map(|extents| extents.shared_bytes);
+        extents
+    }
+
+    fn process_directory_entry(
+        &self,
+        entry: &DirEntry,
+        depth: usize,
+        directories: &mut HashMap<PathBuf, DirectoryEntry>,
+    ) {
+        directories.insert(
+            entry.path().to_path_buf(),
+            DirectoryEntry {
+                path: entry.path().to_path_buf(),
+                total_size: 0, // Will be calculated later
+                file_count: 0,
+                recursive_file_count: 0,
+                subdirectory_count: 0,
+                recursive_subdirectory_count: 0,
+                depth,
+                owners: Vec::new(),
+                root: 0,
+                mode: self.args.security_audit.then(|| entry.metadata().ok().and_then(|metadata| file_mode(&metadata))).flatten(),
+            },
+        );
+    }
+}
+
+/// The pool walking subtrees and reading files, or `None` to walk on the calling thread
+///
+/// Only more than one `--threads` starts a pool.
+fn walk_threads(threads: Option<usize>) -> Result<Option<rayon::ThreadPool>> {
+    let Some(threads) = threads.filter(|&threads| threads > 1) else {
+        return Ok(None);
+    };
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .thread_name(|index| format!("walk-{}", index))
+        .build()
+        .context("Cannot start the threads that walk the tree")?;
+    Ok(Some(pool))
+}
+
+/// A file's entry as the filters see it, before xattrs and extents are measured
+///
+/// Given a link's own metadata, the entry is the link, with its target recorded.
+fn file_entry(path: &Path, metadata: &fs::Metadata, depth: usize) -> FileEntry {
+    let timestamp = |time: std::io::Result<SystemTime>| {
+        time.ok().and_then(|time| {
+            time.duration_since(SystemTime::UNIX_EPOCH)
+                .ok()
+                .and_then(|duration| DateTime::from_timestamp(duration.as_secs() as i64, 0))
+        })
+    };
+    let modified = timestamp(metadata.modified());
+
+    FileEntry {
+        path: path.to_path_buf(),
+        size: metadata.len(),
+        modified,
+        accessed: timestamp(metadata.accessed()),
+        // Filesystems without birth times fail the call; the file is just undated
+        created: timestamp(metadata.created()),
+        is_symlink: metadata.file_type().is_symlink(),
+        depth,
+        uid: file_owner(metadata),
+        gid: file_group(metadata),
+        xattr_size: 0,
+        shared_extents: None,
+        etag: None,
+        symlink_target: metadata.file_type().is_symlink().then(|| fs::read_link(path).ok()).flatten(),
+        root: 0,
+        disk_usage: None,
+        mode: None,
+        dev: file_device(metadata),
+        ino: file_inode(metadata),
+        nlink: file_links(metadata),
+    }
+}
+
+/// The compiled `--exclude` globs followed by the `--exclude-regex` patterns
+pub(crate) fn exclude_patterns(args: &Args) -> Result<Vec<ExcludePattern>> {
+    let globs = args.exclude_patterns.iter().map(|pattern| ExcludePattern::glob(pattern));
+    let regexes = args.exclude_regexes.iter().map(|pattern| ExcludePattern::regex(pattern));
+    globs.chain(regexes).collect()
+}
+
+/// The compiled `--include` globs
+pub(crate) fn include_patterns(args: &Args) -> Result<Vec<ExcludePattern>> {
+    args.include_patterns.iter().map(|pattern| ExcludePattern::include_glob(pattern)).collect()
+}
+
+/// The exclude filter for a walk of `root`
+fn exclude_filter(args: &Args, root: &Path) -> Result<filter::ExcludeFilter> {
+    Ok(filter::ExcludeFilter::new(root.to_path_buf(), exclude_patterns(args)?))
+}
+
+/// Owning uid of a file, where the platform has one
+fn file_owner(metadata: &fs::Metadata) -> Option<u32> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        Some(metadata.uid())
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = metadata;
+        None
+    }
+}
+
+/// Owning gid of a file, where the platform has one
+fn file_group(metadata: &fs::Metadata) -> Option<u32> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        Some(metadata.gid())
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = metadata;
+        None
+    }
+}
+
+/// Permission bits of an entry, setuid, setgid, and sticky included
+fn file_mode(metadata: &fs::Metadata) -> Option<u32> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        Some(metadata.permissions().mode() & 0o7777)
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = metadata;
+        None
+    }
+}
+
+/// Bytes allocated to a file, as `du` counts them; the length where the platform has no blocks
+fn allocated_size(metadata: &fs::Metadata) -> u64 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        metadata.blocks() * 512
+    }
+    #[cfg(not(unix))]
+    {
+        metadata.len()
+    }
+}
+
+/// Device holding a file, used to cache per-filesystem capabilities
+fn file_device(metadata: &fs::Metadata) -> u64 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        metadata.dev()
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = metadata;
+        0
+    }
+}
+
+/// Inode number of a file, where the platform has one
+fn file_inode(metadata: &fs::Metadata) -> u64 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        metadata.ino()
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = metadata;
+        0
+    }
+}
+
+/// Number of hard links to a file, where the platform counts them
+fn file_links(metadata: &fs::Metadata) -> u64 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        metadata.nlink()
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = metadata;
+        0
+    }
+}
+
+/// OS error number behind a failed step of the walk
+fn errno(error: &walkdir::Error) -> Option<i32> {
+    error.io_error().and_then(std::io::Error::raw_os_error)
+}
+
+/// Effective uid of this process
+fn current_uid() -> Option<u32> {
+    #[cfg(unix)]
+    {
+        // SAFETY: geteuid has no preconditions
+        Some(unsafe { libc::geteuid() })
+    }
+    #[cfg(not(unix))]
+    {
+        None
+    }
+}
+
+/// Look up a user's uid by login name
+fn lookup_uid(name: &str) -> Option<u32> {
+    #[cfg(unix)]
+    {
+        let name = std::ffi::CString::new(name).ok()?;
+        // SAFETY: getpwnam returns null or a pointer to static storage we only read immediately
+        let entry = unsafe { libc::getpwnam(name.as_ptr()) };
+        if entry.is_null() {
+            None
+        } else {
+            Some(unsafe { (*entry).pw_uid })
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = name;
+        None
+    }
+}
+
+/// Look up a group's gid by name
+fn lookup_gid(name: &str) -> Option<u32> {
+    #[cfg(unix)]
+    {
+        let name = std::ffi::CString::new(name).ok()?;
+        // SAFETY: getgrnam returns null or a pointer to static storage we only read immediately
+        let entry = unsafe { libc::getgrnam(name.as_ptr()) };
+        if entry.is_null() {
+            None
+        } else {
+            Some(unsafe { (*entry).gr_gid })
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = name;
+        None
+    }
+}
+
+/// The directory a scan of `path` walks and roots its paths at
+///
+/// A root that is itself a symlink is resolved once, to its canonical target,
+/// so that the walk, depths, exclusions, and prefix-stripping all see the
+/// target's paths; `--follow-links` only governs the links below it. Any
+/// other root is kept as given, as is a link that cannot be resolved, whose
+/// walk reports why.
+pub fn resolve_root(path: &Path) -> PathBuf {
+    // `link/` would stat as the directory it points to
+    let trimmed: PathBuf = path.components().collect();
+    match std::fs::symlink_metadata(&trimmed) {
+        Ok(metadata) if metadata.file_type().is_symlink() => std::fs::canonicalize(&trimmed).unwrap_or_else(|_| path.to_path_buf()),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// The roots to walk, each once, and the ones left out because another root covers them
+///
+/// A root equal to an earlier one, or inside any other, is left out: the walk
+/// of the outer root already reaches everything below it.
+pub fn distinct_roots(roots: &[PathBuf]) -> (Vec<PathBuf>, Vec<PathBuf>) {
+    let absolute: Vec<PathBuf> = roots.iter().map(|root| std::path::absolute(root).unwrap_or_else(|_| root.clone())).collect();
+    let covered = |index: usize| {
+        absolute.iter().enumerate().any(|(other, path)| {
+            other != index && absolute[index].starts_with(path) && (absolute[index] != *path || other < index)
+        })
+    };
+    let (kept, skipped): (Vec<_>, Vec<_>) = roots.iter().cloned().enumerate().partition(|(index, _)| !covered(*index));
+    (kept.into_iter().map(|(_, root)| root).collect(), skipped.into_iter().map(|(_, root)| root).collect())
+}
+
+/// Look up the login name of a uid
+pub(crate) fn user_name(uid: u32) -> Option<String> {
+    #[cfg(unix)]
+    {
+        // SAFETY: getpwuid returns null or a pointer to static storage we only read immediately
+        let entry = unsafe { libc::getpwuid(uid) };
+        if entry.is_null() {
+            None
+        } else {
+            let name = unsafe { std::ffi::CStr::from_ptr((*entry).pw_name) };
+            Some(name.to_string_lossy().into_owned())
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = uid;
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use std::fs;
+
+    fn scanner_for(path: &Path) -> DirectoryScanner {
+        let args = Args::parse_from(["diranalyzer", "--quiet", path.to_str().unwrap()]);
+        DirectoryScanner::new(&args).unwrap()
+    }
+
+    fn scanner_for_user(path: &Path, user: &str) -> DirectoryScanner {
+        let args = Args::parse_from(["diranalyzer", "--quiet", "--user", user, path.to_str().unwrap()]);
+        DirectoryScanner::new(&args).unwrap()
+    }
+
+    fn running_as_root() -> bool {
+        #[cfg(unix)]
+        {
+            // SAFETY: geteuid has no preconditions
+            unsafe { libc::geteuid() == 0 }
+        }
+        #[cfg(not(unix))]
+        {
+            false
+        }
+    }
+
+    #[test]
+    fn test_coverage_of_readable_tree() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("a")).unwrap();
+        fs::write(dir.path().join("b.txt"), "data").unwrap();
+        fs::create_dir(dir.path().join(".hidden")).unwrap();
+
+        let coverage = scanner_for(dir.path()).probe_coverage().unwrap();
+        assert_eq!(coverage, Coverage { readable_entries: 2, unreadable_entries: 0 });
+        assert!(coverage.is_complete());
+        assert_eq!(coverage.readable_fraction(), 1.0);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_coverage_counts_restricted_children() {
+        use std::os::unix::fs::PermissionsExt;
+
+        if running_as_root() {
+            // Permission bits are not enforced for root
+            return;
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["open1", "open2", "locked"] {
+            fs::create_dir(dir.path().join(name)).unwrap();
+        }
+        fs::write(dir.path().join("secret.txt"), "data").unwrap();
+        fs::set_permissions(dir.path().join("locked"), fs::Permissions::from_mode(0o000)).unwrap();
+        fs::set_permissions(dir.path().join("secret.txt"), fs::Permissions::from_mode(0o000)).unwrap();
+
+        let coverage = scanner_for(dir.path()).probe_coverage().unwrap();
+
+        fs::set_permissions(dir.path().join("locked"), fs::Permissions::from_mode(0o755)).unwrap();
+        assert_eq!(coverage, Coverage { readable_entries: 2, unreadable_entries: 2 });
+        assert_eq!(coverage.readable_fraction(), 0.5);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unreadable_root_is_a_clear_error() {
+        use std::os::unix::fs::PermissionsExt;
+
+        if running_as_root() {
+            return;
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("root");
+        fs::create_dir(&root).unwrap();
+        fs::set_permissions(&root, fs::Permissions::from_mode(0o000)).unwrap();
+
+        let result = scanner_for(&root).probe_coverage();
+
+        fs::set_permissions(&root, fs::Permissions::from_mode(0o755)).unwrap();
+        let message = result.unwrap_err().to_string();
+        assert!(message.contains("Permission denied"));
+        assert!(message.contains("sudo"));
+    }
+
+    #[test]
+    fn test_empty_root_has_full_coverage() {
+        let dir = tempfile::tempdir().unwrap();
+        let coverage = scanner_for(dir.path()).probe_coverage().unwrap();
+        assert_eq!(coverage.readable_fraction(), 1.0);
+    }
+
+    #[tokio::test]
+    async fn test_mine_keeps_own_files() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.txt"), vec![0u8; 300]).unwrap();
+        fs::write(dir.path().join("b.txt"), vec![0u8; 700]).unwrap();
+
+        let args = Args::parse_from(["diranalyzer", "--quiet", "--mine", dir.path().to_str().unwrap()]);
+        let results = DirectoryScanner::new(&args).unwrap().scan().await.unwrap();
+
+        assert_eq!(results.total_files, 2);
+        let ownership = results.ownership.unwrap();
+        assert_eq!(ownership.uid, current_uid().unwrap());
+        assert_eq!(ownership.matched_size, 1000);
+        assert_eq!(ownership.covered_fraction(), 1.0);
+    }
+
+    #[tokio::test]
+    async fn test_foreign_user_filters_files_and_directory_totals() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("sub")).unwrap();
+        fs::write(dir.path().join("sub/a.txt"), vec![0u8; 400]).unwrap();
+        fs::write(dir.path().join("b.txt"), vec![0u8; 600]).unwrap();
+
+        // Numeric uids resolve without a passwd entry, so no chown is needed
+        let foreign = (current_uid().unwrap() + 4242).to_string();
+        let results = scanner_for_user(dir.path(), &foreign).scan().await.unwrap();
+
+        assert_eq!(results.total_files, 0);
+        assert_eq!(results.total_size, 0);
+        assert!(results.directories.iter().all(|d| d.total_size == 0));
+        let ownership = results.ownership.unwrap();
+        assert_eq!(ownership.encountered_size, 1000);
+        assert_eq!(ownership.covered_fraction(), 0.0);
+    }
+
+    #[tokio::test]
+    async fn test_user_filter_with_chowned_fixture() {
+        if !running_as_root() {
+            return;
+        }
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("mine.txt"), vec![0u8; 250]).unwrap();
+        fs::write(dir.path().join("theirs.txt"), vec![0u8; 750]).unwrap();
+        std::os::unix::fs::chown(dir.path().join("theirs.txt"), Some(65534), None).unwrap();
+
+        let results = scanner_for_user(dir.path(), "0").scan().await.unwrap();
+
+        assert_eq!(results.total_files, 1);
+        assert_eq!(results.files[0].path, dir.path().join("mine.txt"));
+        assert_eq!(results.ownership.unwrap().covered_fraction(), 0.25);
+    }
+
+    #[test]
+    fn test_unknown_user_is_an_error() {
+        let args = Args::parse_from(["diranalyzer", "--user", "no-such-user-diranalyzer", "."]);
+        assert!(OwnerFilter::from_args(&args).is_err());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_foreign_group_filters_files() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.txt"), vec![0u8; 300]).unwrap();
+        fs::write(dir.path().join("b.txt"), vec![0u8; 700]).unwrap();
+        let group = |group: &str| {
+            let args = Args::parse_from(["diranalyzer", "--quiet", "--group", group, dir.path().to_str().unwrap()]);
+            DirectoryScanner::new(&args).unwrap()
+        };
+
+        // SAFETY: getegid has no preconditions
+        let own = unsafe { libc::getegid() };
+        let results = group(&own.to_string()).scan().await.unwrap();
+        assert_eq!(results.total_files, 2);
+        assert!(results.files.iter().all(|file| file.gid == Some(own)));
+
+        let results = group(&(own + 4242).to_string()).scan().await.unwrap();
+        assert_eq!((results.total_files, results.total_size), (0, 0));
+        let cause = results.exclusions.causes.iter().find(|count| count.cause == ExclusionCause::Group).unwrap();
+        assert_eq!((cause.entries, cause.size), (2, 1000));
+    }
+
+    #[test]
+    fn test_unknown_group_is_an_error() {
+        let args = Args::parse_from(["diranalyzer", "--group", "no-such-group-diranalyzer", "."]);
+        assert!(GroupFilter::from_args(&args).is_err());
+    }
+
+    fn scanner_excluding(path: &Path, option: &str, patterns: &[&str]) -> DirectoryScanner {
+        let mut argv = vec!["diranalyzer", "--quiet"];
+        for pattern in patterns {
+            argv.extend([option, pattern]);
+        }
+        argv.push(path.to_str().unwrap());
+        DirectoryScanner::new(&Args::parse_from(argv)).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_catch_all_pattern_matches_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("tree");
+        fs::create_dir(&root).unwrap();
+        fs::write(root.join("a.txt"), "data").unwrap();
+        fs::write(root.join("b.log"), "data").unwrap();
+
+        let mut scanner = scanner_excluding(&root, "--exclude-regex", &[".*", r"\.log$"]);
+        assert_eq!(scanner.patterns_matching_root(), [".*"]);
+
+        let results = scanner.scan().await.unwrap();
+        assert_eq!(results.total_files, 0);
+        let exclusions = results.exclusions;
+        assert_eq!(exclusions.root_matches, [".*"]);
+        assert_eq!((exclusions.encountered, exclusions.excluded), (3, 3));
+        assert_eq!(exclusions.excluded_fraction(), 1.0);
+        assert_eq!(exclusions.responsible_patterns()[0].pattern, ".*");
+    }
+
+    #[tokio::test]
+    async fn test_pattern_hit_counts() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("tree");
+        fs::create_dir_all(root.join("logs")).unwrap();
+        fs::write(root.join("logs/a.log"), "data").unwrap();
+        fs::write(root.join("logs/b.log"), "data").unwrap();
+        fs::write(root.join("c.log"), "data").unwrap();
+        fs::write(root.join("keep.txt"), "data").unwrap();
+        fs::write(root.join(".env"), "data").unwrap();
+
+        let mut scanner = scanner_excluding(&root, "--exclude", &["*.log", "logs/*", "*.tmp"]);
+        assert!(scanner.patterns_matching_root().is_empty());
+        let results = scanner.scan().await.unwrap();
+
+        assert_eq!(results.total_files, 1);
+        let exclusions = results.exclusions;
+        // root, logs, 3 logs, keep.txt, .env
+        assert_eq!(exclusions.encountered, 7);
+        assert_eq!(exclusions.hidden, 1);
+        // Two of the log files match both patterns but are excluded once
+        assert_eq!(exclusions.excluded, 4);
+        let hits: Vec<_> = exclusions.patterns.iter().map(|p| (p.pattern.as_str(), p.hits)).collect();
+        assert_eq!(hits, [("*.log", 3), ("logs/*", 2), ("*.tmp", 0)]);
+        let responsible: Vec<_> = exclusions.responsible_patterns().iter().map(|p| p.pattern.as_str()).collect();
+        assert_eq!(responsible, ["*.log", "logs/*"]);
+    }
+
+    #[tokio::test]
+    async fn test_exclude_globs_match_below_the_root() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("tree");
+        for path in ["node_modules/pkg", "web/node_modules/lib", "build", "src/build"] {
+            fs::create_dir_all(root.join(path)).unwrap();
+        }
+        for path in ["node_modules/pkg/index.js", "web/node_modules/lib/a.js", "build/out.o", "src/build/keep.o", "disk.iso", "src/DISK.ISO", "src/main.rs"] {
+            fs::write(root.join(path), "data").unwrap();
+        }
+
+        // Globs see paths relative to the root, so even "*" leaves the root itself alone
+        assert!(scanner_excluding(&root, "--exclude", &["*", "**"]).patterns_matching_root().is_empty());
+
+        let results = scanner_excluding(&root, "--exclude", &["node_modules/**", "/build", "*.iso"]).scan().await.unwrap();
+        let mut kept: Vec<_> = results.files.iter().map(|file| file.path.strip_prefix(&root).unwrap().to_path_buf()).collect();
+        kept.sort();
+        assert_eq!(kept, [Path::new("src/DISK.ISO"), Path::new("src/build/keep.o"), Path::new("src/main.rs")]);
+        // Both node_modules directories and the top-level build directory are pruned whole
+        let hits: Vec<_> = results.exclusions.patterns.iter().map(|p| (p.pattern.as_str(), p.hits)).collect();
+        assert_eq!(hits, [("node_modules/**", 2), ("/build", 1), ("*.iso", 1)]);
+    }
+
+    #[test]
+    fn test_invalid_exclude_pattern_is_named() {
+        let args = Args::parse_from(["diranalyzer", "--exclude", "*.log", "--exclude-regex", "logs/(", "."]);
+        let error = format!("{:#}", DirectoryScanner::new(&args).err().unwrap());
+        assert!(error.contains("--exclude-regex") && error.contains("\"logs/(\""), "{}", error);
+    }
+
+    #[test]
+    fn test_roots_inside_others_are_walked_once() {
+        let roots: Vec<PathBuf> = ["/srv", "/home/alice", "/home", "/srv", "/var"].iter().map(PathBuf::from).collect();
+        let (kept, nested) = distinct_roots(&roots);
+        assert_eq!(kept, [PathBuf::from("/srv"), PathBuf::from("/home"), PathBuf::from("/var")]);
+        assert_eq!(nested, [PathBuf::from("/home/alice"), PathBuf::from("/srv")]);
+        // Only whole components nest
+        let roots: Vec<PathBuf> = ["/data", "/data2"].iter().map(PathBuf::from).collect();
+        assert_eq!(distinct_roots(&roots).0, roots);
+    }
+
+    /// Maps every file to a single shared extent at physical offset 0
+    struct ReflinkMapper;
+
+    impl crate::extents::ExtentMapper for ReflinkMapper {
+        fn supports_sharing(&self, _path: &Path) -> bool {
+            true
+        }
+
+        fn extents(&self, path: &Path) -> std::io::Result<Vec<crate::extents::Extent>> {
+            let length = fs::metadata(path)?.len();
+            Ok(vec![crate::extents::Extent { logical: 0, physical: 0, length, shared: true }])
+        }
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_hard_links_share_their_recorded_inode() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join("a.txt"), "data").unwrap();
+        fs::hard_link(dir.path().join("a.txt"), dir.path().join("b.txt")).unwrap();
+        fs::write(dir.path().join("c.txt"), "data").unwrap();
+
+        let results = scanner_for(dir.path()).scan().await.unwrap();
+        let file = |name: &str| results.files.iter().find(|file| file.path == dir.path().join(name)).unwrap();
+        assert_eq!((file("a.txt").nlink, file("c.txt").nlink), (2, 1));
+        assert_eq!(file("a.txt").inode(), file("b.txt").inode());
+        assert_ne!(file("a.txt").inode(), file("c.txt").inode());
+
+        // Exports carry the identity, and older ones without it read as unrecorded
+        let exported = serde_json::to_value(file("a.txt")).unwrap();
+        assert_eq!(exported["nlink"], 2);
+        let mut old = exported.clone();
+        for field in ["dev", "ino", "nlink"] {
+            old.as_object_mut().unwrap().remove(field);
+        }
+        assert_eq!(serde_json::from_value::<FileEntry>(old).unwrap().inode(), None);
+    }
+
+    #[tokio::test]
+    async fn test_shared_extents_are_counted_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("tree");
+        fs::create_dir(&root).unwrap();
+        fs::write(root.join("original.img"), vec![1u8; 100_000]).unwrap();
+        fs::write(root.join("clone.img"), vec![1u8; 100_000]).unwrap();
+        fs::write(root.join("small.txt"), "below the probe threshold").unwrap();
+
+        let probe = ExtentProbe::with_mapper(Box::new(ReflinkMapper));
+        let results = scanner_for(&root).with_extent_probe(probe).scan().await.unwrap();
+
+        let image = results.files.iter().find(|f| f.path.ends_with("clone.img")).unwrap();
+        assert_eq!(image.shared_extents, Some(100_000));
+        assert!(image.is_fully_shared());
+        let small = results.files.iter().find(|f| f.path.ends_with("small.txt")).unwrap();
+        assert_eq!(small.shared_extents, None);
+
+        assert_eq!(results.total_size, 200_000 + small.size);
+        assert_eq!(results.physical_size, Some(100_000 + small.size));
+    }
+
+    #[tokio::test]
+    async fn test_wide_directory_is_reported_as_slow() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("tree");
+        fs::create_dir_all(root.join("spool/sub")).unwrap();
+        fs::create_dir(root.join("narrow")).unwrap();
+        for i in 0..120 {
+            fs::write(root.join(format!("spool/msg{}", i)), "x").unwrap();
+        }
+        for i in 0..3 {
+            fs::write(root.join(format!("narrow/{}", i)), "x").unwrap();
+            fs::write(root.join(format!("spool/sub/{}", i)), "x").unwrap();
+        }
+
+        let thresholds = SlowThresholds { entries: 50, duration: std::time::Duration::MAX };
+        let results = scanner_for(&root).with_slow_thresholds(thresholds).scan().await.unwrap();
+
+        // Entries of the nested directory are not charged to the spool
+        let slow = &results.slow_directories;
+        assert_eq!(slow.len(), 1);
+        assert_eq!((slow[0].path.as_path(), slow[0].entries), (root.join("spool").as_path(), 121));
+    }
+
+    #[tokio::test]
+    async fn test_closure_filter_runs_after_builtin_filters() {
+        use std::sync::{Arc, Mutex};
+
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("tree");
+        fs::create_dir_all(root.join("build")).unwrap();
+        fs::write(root.join("keep.txt"), vec![0u8; 100]).unwrap();
+        fs::write(root.join("large.iso"), vec![0u8; 5_000]).unwrap();
+        fs::write(root.join("build/out.o"), vec![0u8; 300]).unwrap();
+        fs::write(root.join(".secret"), vec![0u8; 50]).unwrap();
+
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let recorder = Arc::clone(&seen);
+        let mut scanner = scanner_excluding(&root, "--exclude", &["*.o"]).with_filter(move |entry: &FileEntry| {
+            recorder.lock().unwrap().push(entry.path.file_name().unwrap().to_owned());
+            entry.size < 1_000
+        });
+        let results = scanner.scan().await.unwrap();
+
+        assert_eq!((results.total_files, results.total_size), (1, 100));
+        assert_eq!(results.files[0].path, root.join("keep.txt"));
+        // Hidden and excluded files never reach the added filter
+        let mut seen = seen.lock().unwrap().clone();
+        seen.sort();
+        assert_eq!(seen, ["keep.txt", "large.iso"]);
+        assert_eq!((results.exclusions.hidden, results.exclusions.excluded), (1, 2));
+    }
+
+    /// Prunes directories by name and keeps every file
+    struct SkipDirectory(&'static str);
+
+    impl FileFilter for SkipDirectory {
+        fn include(&self, _entry: &FileEntry) -> bool {
+            true
+        }
+
+        fn prune(&self, directory: &Path) -> bool {
+            directory.ends_with(self.0)
+        }
+    }
+
+    /// root/top.bin, root/a/mid.bin, root/a/b/loose.bin, root/a/b/c/d/deep.bin, and an empty root/a/empty
+    fn deep_tree() -> (tempfile::TempDir, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("deep");
+        fs::create_dir_all(root.join("a/b/c/d")).unwrap();
+        fs::create_dir_all(root.join("a/empty")).unwrap();
+        fs::write(root.join("top.bin"), vec![0u8; 1]).unwrap();
+        fs::write(root.join("a/mid.bin"), vec![0u8; 20]).unwrap();
+        fs::write(root.join("a/b/loose.bin"), vec![0u8; 300]).unwrap();
+        fs::write(root.join("a/b/c/d/deep.bin"), vec![0u8; 4_000]).unwrap();
+        (dir, root)
+    }
+
+    fn scanner_with_depth(path: &Path, depth: usize) -> DirectoryScanner {
+        let depth = depth.to_string();
+        let args = Args::parse_from(["diranalyzer", "--quiet", "--depth", &depth, path.to_str().unwrap()]);
+        DirectoryScanner::new(&args).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_depth_limit_records_boundary() {
+        let (_dir, root) = deep_tree();
+        let results = scanner_with_depth(&root, 2).scan().await.unwrap();
+
+        assert_eq!(results.total_size, 21);
+        // a/b has contents below the limit, a/empty has none
+        assert_eq!(results.depth_boundary.directories, vec![root.join("a/b")]);
+        assert_eq!(results.depth_boundary.immediate_size, 300);
+        assert!(!results.depth_boundary.expanded);
+
+        let complete = scanner_with_depth(&root, 10).scan().await.unwrap();
+        assert_eq!(complete.depth_boundary, DepthBoundary::default());
+        assert_eq!(complete.total_size, 4_321);
+    }
+
+    #[tokio::test]
+    async fn test_unlimited_depth_reaches_past_ten_levels() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("tall");
+        let deepest = (0..14).fold(root.clone(), |path, level| path.join(format!("l{}", level)));
+        fs::create_dir_all(&deepest).unwrap();
+        fs::write(deepest.join("bottom.bin"), vec![0u8; 7]).unwrap();
+
+        let limited = scanner_with_depth(&root, 10).scan().await.unwrap();
+        assert_eq!(limited.total_files, 0);
+        for depth in ["0", "unlimited"] {
+            let args = Args::parse_from(["diranalyzer", "--quiet", "--depth", depth, root.to_str().unwrap()]);
+            assert_eq!(args.depth_limit(), None);
+            let results = DirectoryScanner::new(&args).unwrap().scan().await.unwrap();
+            assert_eq!(results.total_files, 1, "--depth {}", depth);
+            assert_eq!(results.files[0].depth, 15);
+            assert_eq!(results.depth_boundary, DepthBoundary::default());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_min_depth_leaves_out_shallow_levels() {
+        let (_dir, root) = deep_tree();
+        let args = Args::parse_from(["diranalyzer", "--quiet", "--min-depth", "2", root.to_str().unwrap()]);
+        let results = DirectoryScanner::new(&args).unwrap().scan().await.unwrap();
+
+        let mut files: Vec<_> = results.files.iter().map(|f| f.path.clone()).collect();
+        files.sort();
+        // Depths count as walkdir's do: top.bin and a are one level down
+        assert_eq!(files, vec![root.join("a/b/c/d/deep.bin"), root.join("a/b/loose.bin"), root.join("a/mid.bin")]);
+        assert_eq!(results.total_size, 4_320);
+        assert!(results.directories.iter().all(|d| d.depth >= 2));
+        assert!(results.directories.iter().any(|d| d.path == root.join("a/b")));
+    }
+
+    #[tokio::test]
+    async fn test_scan_below_completes_the_totals() {
+        let (_dir, root) = deep_tree();
+        let mut scanner = scanner_with_depth(&root, 2);
+        let mut results = scanner.scan().await.unwrap();
+        let boundary = results.depth_boundary.directories.clone();
+        results.absorb(scanner.scan_below(&boundary).await.unwrap(), DEFAULT_OWNER_LIMIT);
+
+        let complete = scanner_with_depth(&root, 10).scan().await.unwrap();
+        let summary = |results: &ScanResults| {
+            let mut directories: Vec<_> = results
+                .directories
+                .iter()
+                .map(|d| (d.path.clone(), d.total_size, d.file_count, d.subdirectory_count, d.depth))
+                .collect();
+            directories.sort();
+            let mut files: Vec<_> = results.files.iter().map(|f| (f.path.clone(), f.depth)).collect();
+            files.sort();
+            (results.total_files, results.total_directories, results.total_size, directories, files)
+        };
+        assert_eq!(summary(&results), summary(&complete));
+        assert_eq!(results.exclusions.encountered, complete.exclusions.encountered);
+        assert!(results.depth_boundary.expanded);
+        assert_eq!(results.depth_boundary.directories, boundary);
+    }
+
+    #[tokio::test]
+    async fn test_streamed_scan_hands_out_every_file_and_keeps_the_totals() {
+        let (_dir, root) = deep_tree();
+        fs::write(root.join("a/empty.bin"), b"").unwrap();
+        let mut handed_out = Vec::new();
+        let mut scanner = scanner_with_depth(&root, 2);
+        let mut results = scanner.scan_streaming(|file| handed_out.push(file)).await.unwrap();
+        let boundary = results.depth_boundary.directories.clone();
+        let below = scanner.stream_below(&boundary, &mut |file| handed_out.push(file)).unwrap();
+        results.absorb(below, DEFAULT_OWNER_LIMIT);
+
+        let complete = scanner_with_depth(&root, 10).scan().await.unwrap();
+        assert!(results.is_streamed() && results.files.is_empty());
+        let directories = |results: &ScanResults| {
+            let mut directories: Vec<_> = results
+                .directories
+                .iter()
+                .map(|d| (d.path.clone(), d.total_size, d.file_count, d.subdirectory_count, d.owners.clone()))
+                .collect();
+            directories.sort_by(|a, b| a.0.cmp(&b.0));
+            directories
+        };
+        assert_eq!(directories(&results), directories(&complete));
+        assert_eq!((results.total_files, results.total_size), (complete.total_files, complete.total_size));
+        handed_out.sort_by(|a, b| a.path.cmp(&b.path));
+        let mut kept = complete.files.clone();
+        kept.sort_by(|a, b| a.path.cmp(&b.path));
+        assert_eq!(handed_out.iter().map(|f| (&f.path, f.size, f.depth)).collect::<Vec<_>>(), kept.iter().map(|f| (&f.path, f.size, f.depth)).collect::<Vec<_>>());
+    }
+
+    #[tokio::test]
+    async fn test_case_collisions_are_grouped_per_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("tree");
+        for directory in ["Docs", "docs/deep", "one", "two"] {
+            fs::create_dir_all(root.join(directory)).unwrap();
+        }
+        for (name, size) in [
+            ("Report.pdf", 10),
+            ("report.pdf", 20),
+            ("Docs/a.txt", 100),
+            ("docs/b.txt", 200),
+            ("docs/deep/Note.md", 1),
+            ("docs/deep/note.md", 2),
+            ("one/x.txt", 5),
+            ("two/X.txt", 5),
+        ] {
+            fs::write(root.join(name), vec![0u8; size]).unwrap();
+        }
+        let scanner = |depth: &str| {
+            let args = Args::parse_from(["diranalyzer", "--quiet", "--detect-case-collisions", "--depth", depth, root.to_str().unwrap()]);
+            DirectoryScanner::new(&args).unwrap()
+        };
+        // Each group as its directory and folded name, then every entry with a slash for directories and its size
+        let summary = |collisions: &[CaseCollision]| -> Vec<(PathBuf, String)> {
+            collisions
+                .iter()
+                .map(|collision| {
+                    assert!(collision.entries.iter().all(|entry| entry.modified.is_some()));
+                    let entries: Vec<String> = collision
+                        .entries
+                        .iter()
+                        .map(|entry| format!("{}{} {}", entry.name, if entry.is_dir { "/" } else { "" }, entry.size))
+                        .collect();
+                    (collision.directory.clone(), format!("{}: {}", collision.folded, entries.join(", ")))
+                })
+                .collect()
+        };
+        let expected = vec![
+            (root.clone(), "docs: Docs/ 100, docs/ 203".to_string()),
+            (root.clone(), "report.pdf: Report.pdf 10, report.pdf 20".to_string()),
+            (root.join("docs/deep"), "note.md: Note.md 1, note.md 2".to_string()),
+        ];
+
+        assert!(scanner_for(&root).scan().await.unwrap().case_collisions.is_empty());
+        assert_eq!(summary(&scanner("10").scan().await.unwrap().case_collisions), expected);
+
+        // Collisions past the depth limit are found when the walk descends there, sized with the complete totals
+        let mut limited = scanner("1");
+        let mut results = limited.scan().await.unwrap();
+        assert_eq!(results.case_collisions.len(), 2);
+        let boundary = results.depth_boundary.directories.clone();
+        results.absorb(limited.scan_below(&boundary).await.unwrap(), DEFAULT_OWNER_LIMIT);
+        assert_eq!(summary(&results.case_collisions), expected);
+    }
+
+    #[tokio::test]
+    async fn test_symlinks_are_classified_with_or_without_following() {
+        use std::os::unix::fs::symlink;
+
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("tree");
+        fs::create_dir_all(root.join("links/nested")).unwrap();
+        fs::create_dir_all(dir.path().join("outside")).unwrap();
+        fs::write(root.join("data.txt"), b"data").unwrap();
+        fs::write(dir.path().join("outside/shared.txt"), b"shared").unwrap();
+
+        // Valid: relative, resolved against the link's own directory, and through a chain
+        symlink("../data.txt", root.join("links/relative")).unwrap();
+        symlink("relative", root.join("links/chained")).unwrap();
+        // Out of the tree, but there
+        symlink(dir.path().join("outside/shared.txt"), root.join("links/outside")).unwrap();
+        // Broken: missing targets, a chain ending in one, and a loop
+        symlink("gone.txt", root.join("links/missing")).unwrap();
+        symlink("../missing", root.join("links/nested/to_missing")).unwrap();
+        symlink(root.join("removed/dir"), root.join("links/nested/absolute")).unwrap();
+        symlink("loop_b", root.join("links/loop_a")).unwrap();
+        symlink("loop_a", root.join("links/loop_b")).unwrap();
+
+        let scan = |extra: &[&str]| {
+            let mut argv = vec!["diranalyzer", "--quiet", "--find-broken-links"];
+            argv.extend_from_slice(extra);
+            argv.push(root.to_str().unwrap());
+            let mut scanner = DirectoryScanner::new(&Args::parse_from(argv)).unwrap();
+            async move { scanner.scan().await.unwrap().symlink_report }
+        };
+        let broken = |report: &SymlinkReport| -> Vec<(PathBuf, PathBuf, Option<PathBuf>)> {
+            report.broken.iter().map(|link| (link.path.clone(), link.target.clone(), link.dangling.clone())).collect()
+        };
+        let expected = vec![
+            (root.join("links/loop_a"), PathBuf::from("loop_b"), None),
+            (root.join("links/loop_b"), PathBuf::from("loop_a"), None),
+            (root.join("links/missing"), PathBuf::from("gone.txt"), Some(root.join("links/gone.txt"))),
+            (root.join("links/nested/absolute"), root.join("removed/dir"), Some(root.join("removed/dir"))),
+            (root.join("links/nested/to_missing"), PathBuf::from("../missing"), Some(root.join("links/nested/../gone.txt"))),
+        ];
+
+        let report = scan(&[]).await;
+        assert_eq!((report.valid, report.out_of_tree), (2, 1));
+        assert_eq!(broken(&report), expected);
+
+        // Following links changes what the walk reads, not how the links are classified
+        let report = scan(&["--follow-links"]).await;
+        assert_eq!((report.valid, report.out_of_tree), (2, 1));
+        assert_eq!(broken(&report), expected);
+
+        assert_eq!(scanner_for(&root).scan().await.unwrap().symlink_report, SymlinkReport::default());
+    }
+
+    #[tokio::test]
+    async fn test_pruned_directory_is_not_walked() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("tree");
+        fs::create_dir_all(root.join("node_modules/pkg")).unwrap();
+        fs::create_dir(root.join("src")).unwrap();
+        fs::write(root.join("node_modules/pkg/index.js"), vec![0u8; 900]).unwrap();
+        fs::write(root.join("src/main.js"), vec![0u8; 100]).unwrap();
+
+        let results = scanner_for(&root).with_filter(SkipDirectory("node_modules")).scan().await.unwrap();
+
+        assert_eq!((results.total_files, results.total_size), (1, 100));
+        assert!(results.directories.iter().all(|d| !d.path.starts_with(root.join("node_modules"))));
+        // root, node_modules, src, main.js
+        assert_eq!(results.exclusions.encountered, 4);
+    }
+
+    #[tokio::test]
+    async fn test_empty_directories_hold_nothing_but_empty_directories() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("tree");
+        fs::create_dir_all(root.join("a/b/c")).unwrap();
+        fs::create_dir_all(root.join("d/e")).unwrap();
+        fs::write(root.join("d/e/keep.txt"), "data").unwrap();
+        fs::create_dir(root.join("f")).unwrap();
+        fs::write(root.join("f/empty.txt"), "").unwrap();
+        fs::create_dir_all(root.join("g/node_modules/pkg")).unwrap();
+        #[cfg(unix)]
+        let locked = (!running_as_root()).then(|| {
+            use std::os::unix::fs::PermissionsExt;
+            fs::create_dir(root.join("locked")).unwrap();
+            fs::set_permissions(root.join("locked"), fs::Permissions::from_mode(0o000)).unwrap();
+            root.join("locked")
+        });
+
+        let results = scanner_for(&root).with_filter(SkipDirectory("node_modules")).scan().await.unwrap();
+
+        #[cfg(unix)]
+        if let Some(locked) = locked {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&locked, fs::Permissions::from_mode(0o755)).unwrap();
+            assert_eq!(results.error_log.total(), 1);
+        }
+        // A pruned directory and a file of no bytes are still something to keep
+        assert_eq!(results.empty_directories, [root.join("a"), root.join("a/b"), root.join("a/b/c")]);
+    }
+
+    #[tokio::test]
+    async fn test_interim_snapshots_are_consistent_with_the_results() {
+        use std::sync::{Arc, Mutex};
+
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("tree");
+        for (i, sub) in ["a", "a/b", "a/b/c", "d", "d/e"].iter().cycle().take(150).enumerate() {
+            fs::create_dir_all(root.join(sub)).unwrap();
+            fs::write(root.join(sub).join(format!("{}.bin", i)), vec![0u8; i * 10]).unwrap();
+        }
+
+        let snapshots = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&snapshots);
+        let mut scanner = scanner_for(&root)
+            .with_interim(Some(Duration::ZERO), move |snapshot| sink.lock().unwrap().push(snapshot.clone()));
+        let results = scanner.scan().await.unwrap();
+        let snapshots = snapshots.lock().unwrap();
+        assert!(!snapshots.is_empty());
+
+        let directory_size = |path: &Path| results.directories.iter().find(|d| d.path == path).unwrap().total_size;
+        for pair in snapshots.windows(2) {
+            assert!(pair[0].files <= pair[1].files && pair[0].bytes <= pair[1].bytes);
+            assert!(pair[0].errors <= pair[1].errors);
+            for (path, size) in &pair[0].directories {
+                let later = pair[1].directories.iter().find(|(p, _)| p == path).map(|(_, size)| *size);
+                assert!(later.is_none_or(|later| *size <= later));
+            }
+        }
+        for snapshot in snapshots.iter() {
+            assert!(snapshot.files <= results.total_files && snapshot.bytes <= results.total_size);
+            assert!(snapshot.errors <= results.errors.len() as u64);
+            for (path, size) in &snapshot.directories {
+                assert!(*size <= directory_size(path));
+            }
+        }
+
+        let last = scanner.interim_snapshot().unwrap();
+        assert_eq!((last.files, last.bytes, last.errors), (results.total_files, results.total_size, 0));
+        for (path, size) in &last.directories {
+            assert_eq!(*size, directory_size(path));
+        }
+        assert_eq!(last.directories.len(), 4);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_directory_reached_twice_is_counted_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("tree");
+        fs::create_dir_all(root.join("data/sub")).unwrap();
+        fs::create_dir(root.join("srv")).unwrap();
+        fs::write(root.join("data/sub/blob.bin"), vec![0u8; 1_000]).unwrap();
+        // A followed link stands in for a bind mount, which needs privileges
+        std::os::unix::fs::symlink(root.join("data"), root.join("srv/export")).unwrap();
+
+        let args = Args::parse_from(["diranalyzer", "--quiet", "--follow-links", root.to_str().unwrap()]);
+        let results = DirectoryScanner::new(&args).unwrap().scan().await.unwrap();
+
+        assert_eq!((results.total_files, results.total_size), (1, 1_000));
+        // Whichever path the walk lists first is the original
+        let [alias] = results.aliases.directories.as_slice() else { panic!("{:?}", results.aliases) };
+        let mut paths = [alias.original.clone(), alias.alias.clone()];
+        paths.sort();
+        assert_eq!(paths, [root.join("data"), root.join("srv/export")]);
+        assert!(results.directories.iter().all(|d| !d.path.starts_with(&alias.alias)));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_aliases_past_the_identity_limit_are_counted_again() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("tree");
+        fs::create_dir_all(root.join("data")).unwrap();
+        fs::write(root.join("data/blob.bin"), vec![0u8; 1_000]).unwrap();
+        std::os::unix::fs::symlink(root.join("data"), root.join("export")).unwrap();
+
+        let args = Args::parse_from(["diranalyzer", "--quiet", "--follow-links", root.to_str().unwrap()]);
+        let results = DirectoryScanner::new(&args).unwrap().with_identity_limit(1).scan().await.unwrap();
+
+        assert_eq!(results.total_size, 2_000);
+        assert_eq!((results.aliases.directories.len(), results.aliases.unrecorded), (0, 2));
+    }
+
+    #[tokio::test]
+    async fn test_walks_on_threads_give_the_same_results() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("tree");
+        for (index, directory) in ["a", "a/nested", "b", "c/deep/er"].iter().enumerate() {
+            fs::create_dir_all(root.join(directory)).unwrap();
+            for file in 0..30 {
+                fs::write(root.join(directory).join(format!("{}.bin", file)), vec![0u8; index * 100 + file]).unwrap();
+            }
+            fs::write(root.join(directory).join(".hidden"), b"skipped").unwrap();
+        }
+        fs::create_dir_all(root.join("vacant/inner")).unwrap();
+        fs::write(root.join("top.bin"), vec![0u8; 40]).unwrap();
+        let scanner = |extra: &[&str]| {
+            let mut argv = vec!["diranalyzer", "--quiet"];
+            argv.extend_from_slice(extra);
+            argv.push(root.to_str().unwrap());
+            DirectoryScanner::new(&Args::parse_from(argv)).unwrap()
+        };
+        let paths = |results: &ScanResults| {
+            let mut paths: Vec<_> = results.files.iter().map(|file| file.path.clone()).collect();
+            paths.sort();
+            paths
+        };
+
+        let totals = |results: &ScanResults| {
+            let mut totals: Vec<_> = results
+                .directories
+                .iter()
+                .map(|d| (d.path.clone(), d.total_size, d.recursive_file_count, d.recursive_subdirectory_count))
+                .collect();
+            totals.sort();
+            totals
+        };
+
+        let single = scanner(&["--threads", "1"]).scan().await.unwrap();
+        let parallel = scanner(&["--threads", "4"]).scan().await.unwrap();
+        assert_eq!((parallel.total_files, parallel.total_size, parallel.total_directories), (121, 19_780, 9));
+        assert_eq!((single.total_files, single.total_size, single.total_directories), (121, 19_780, 9));
+        assert_eq!(paths(&parallel), paths(&single));
+        assert_eq!(totals(&parallel), totals(&single));
+        assert_eq!(parallel.exclusions, single.exclusions);
+        assert_eq!(parallel.empty_directories, [root.join("vacant"), root.join("vacant/inner")]);
+        assert_eq!(parallel.empty_directories, single.empty_directories);
+
+        // Subtrees stop at the depth limit as a single walk does
+        let single = scanner(&["--threads", "1", "--depth", "2"]).scan().await.unwrap();
+        let parallel = scanner(&["--threads", "4", "--depth", "2"]).scan().await.unwrap();
+        let mut boundary = parallel.depth_boundary.directories.clone();
+        boundary.sort();
+        assert_eq!(boundary, [root.join("a/nested"), root.join("c/deep")]);
+        assert_eq!(parallel.depth_boundary.immediate_size, single.depth_boundary.immediate_size);
+        assert_eq!(totals(&parallel), totals(&single));
+
+        // A cap stops at the same file, whatever read it
+        let single = scanner(&["--threads", "1", "--max-files", "45"]).scan().await.unwrap();
+        let parallel = scanner(&["--threads", "4", "--max-files", "45"]).scan().await.unwrap();
+        assert_eq!((parallel.total_files, parallel.partial.is_some()), (45, true));
+        assert_eq!(paths(&parallel), paths(&single));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_symlinks_are_marked_and_sized_by_whether_they_are_followed() {
+        use crate::duplicates::DuplicateFinder;
+        use std::os::unix::fs::symlink;
+
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("tree");
+        fs::create_dir(&root).unwrap();
+        fs::write(root.join("data.bin"), vec![7u8; 5_000]).unwrap();
+        symlink("data.bin", root.join("relative")).unwrap();
+        symlink(root.join("data.bin"), root.join("absolute")).unwrap();
+        symlink("missing.bin", root.join("dangling")).unwrap();
+        let links = |files: &[FileEntry]| -> Vec<(String, u64, bool, Option<PathBuf>)> {
+            let mut links: Vec<_> = files
+                .iter()
+                .map(|file| (file.path.file_name().unwrap().to_string_lossy().into_owned(), file.size, file.is_symlink, file.symlink_target.clone()))
+                .collect();
+            links.sort();
+            links
+        };
+        let absolute_size = root.join("data.bin").as_os_str().len() as u64;
+
+        // Unfollowed, each link is an entry the size of the path it holds, dangling or not
+        let results = scanner_for(&root).scan().await.unwrap();
+        assert_eq!(
+            links(&results.files),
+            [
+                ("absolute".to_string(), absolute_size, true, Some(root.join("data.bin"))),
+                ("dangling".to_string(), 11, true, Some(PathBuf::from("missing.bin"))),
+                ("data.bin".to_string(), 5_000, false, None),
+                ("relative".to_string(), 8, true, Some(PathBuf::from("data.bin"))),
+            ]
+        );
+        assert_eq!(results.total_size, 5_000 + absolute_size + 19);
+
+        // Followed, links measure as their targets, the dangling one fails, and none is a duplicate copy
+        let args = Args::parse_from(["diranalyzer", "--quiet", "--follow-links", root.to_str().unwrap()]);
+        let results = DirectoryScanner::new(&args).unwrap().scan().await.unwrap();
+        assert_eq!(
+            links(&results.files),
+            [
+                ("absolute".to_string(), 5_000, true, Some(root.join("data.bin"))),
+                ("data.bin".to_string(), 5_000, false, None),
+                ("relative".to_string(), 5_000, true, Some(PathBuf::from("data.bin"))),
+            ]
+        );
+        assert_eq!(results.errors.len(), 1);
+        assert!(DuplicateFinder::new(1, Some(1)).find_duplicates(&results.files).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_other_filesystems_are_listed_but_not_entered() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("tree");
+        fs::create_dir_all(root.join("proc/1")).unwrap();
+        fs::write(root.join("proc/1/status"), vec![0u8; 5_000]).unwrap();
+        fs::write(root.join("swapfile"), vec![0u8; 700]).unwrap();
+        let args = Args::parse_from(["diranalyzer", "--quiet", "-x", root.to_str().unwrap()]);
+
+        // On one device, -x changes nothing
+        let results = DirectoryScanner::new(&args).unwrap().scan().await.unwrap();
+        assert_eq!((results.total_files, results.total_size), (2, 5_700));
+        assert!(results.skipped_mounts.is_empty());
+
+        // With the root on another device, every entry below it is on a foreign filesystem
+        let results = DirectoryScanner::new(&args).unwrap().with_root_device(u64::MAX).scan().await.unwrap();
+        assert_eq!((results.total_files, results.total_size, results.total_directories), (0, 0, 1));
+        let mut skipped: Vec<_> = results.skipped_mounts.iter().map(|mount| mount.path.clone()).collect();
+        skipped.sort();
+        assert_eq!(skipped, [root.join("proc"), root.join("swapfile")]);
+        let count = results.exclusions.causes.iter().find(|count| count.cause == ExclusionCause::OtherFilesystem).unwrap();
+        assert_eq!((count.entries, count.size), (2, 700));
+
+        let trace = DirectoryScanner::new(&args).unwrap().with_root_device(u64::MAX).explain_path(&root.join("proc/1/status")).unwrap();
+        assert_eq!(trace.outcome, TraceOutcome::Excluded { path: root.join("proc"), cause: ExclusionCause::OtherFilesystem });
+    }
+
+    /// Needs root to mount a tmpfs; run with `sudo -E cargo test tmpfs -- --ignored`.
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    #[ignore]
+    async fn test_tmpfs_below_the_root_is_skipped_with_one_file_system() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("tree");
+        fs::create_dir_all(root.join("scratch")).unwrap();
+        fs::write(root.join("kept.bin"), vec![0u8; 1_000]).unwrap();
+        let status = std::process::Command::new("mount").args(["-t", "tmpfs", "tmpfs", root.join("scratch").to_str().unwrap()]).status().unwrap();
+        assert!(status.success(), "mount -t tmpfs failed; the test needs root");
+        fs::write(root.join("scratch/big.bin"), vec![0u8; 9_000]).unwrap();
+
+        let args = Args::parse_from(["diranalyzer", "--quiet", "--one-file-system", root.to_str().unwrap()]);
+        let results = DirectoryScanner::new(&args).unwrap().scan().await;
+        std::process::Command::new("umount").arg(root.join("scratch")).status().unwrap();
+
+        let results = results.unwrap();
+        assert_eq!(results.total_size, 1_000);
+        let [mount] = results.skipped_mounts.as_slice() else { panic!("{:?}", results.skipped_mounts) };
+        assert_eq!((mount.path.clone(), mount.filesystem.as_deref()), (root.join("scratch"), Some("tmpfs")));
+    }
+
+    /// Needs root to bind mount; run with `sudo -E cargo test bind_mount -- --ignored`.
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    #[ignore]
+    async fn test_bind_mount_is_counted_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("tree");
+        fs::create_dir_all(root.join("data")).unwrap();
+        fs::create_dir_all(root.join("srv/export")).unwrap();
+        fs::write(root.join("data/blob.bin"), vec![0u8; 1_000]).unwrap();
+        let status = std::process::Command::new("mount")
+            .args(["--bind", root.join("data").to_str().unwrap(), root.join("srv/export").to_str().unwrap()])
+            .status()
+            .unwrap();
+        assert!(status.success(), "mount --bind failed; the test needs root");
+
+        let results = scanner_for(&root).scan().await;
+        std::process::Command::new("umount").arg(root.join("srv/export")).status().unwrap();
+
+        let results = results.unwrap();
+        assert_eq!(results.total_size, 1_000);
+        // Whichever path the walk lists first is the original
+        let [alias] = results.aliases.directories.as_slice() else { panic!("{:?}", results.aliases) };
+        let mut paths = [alias.original.clone(), alias.alias.clone()];
+        paths.sort();
+        assert_eq!(paths, [root.join("data"), root.join("srv/export")]);
     }
 }
@@ -0,0 +1,464 @@
+//! Directory totals computed from the scanned files
+//!
+//! Every directory path is hashed once to give it a numeric id. A file then
+//! costs a single lookup of its parent, and the sizes are pushed up the id
+//! tree in one pass over plain arrays, deepest directories first.
+//!
+//! A directory counts the files and subdirectories directly in it and, like
+//! its size, those anywhere below it. A directory that was not walked (a gap
+//! in the chain) is skipped over: its files count toward the nearest walked
+//! ancestor's total and recursive file count but toward no directory's direct
+//! file count, and it makes no subdirectory of its parent, though the walked
+//! directories below it are counted among the ancestor's recursive ones.
+//!
+//! Owner bytes travel up the same way. A directory keeps only its largest
+//! `owner_limit` owners once complete, so an owner that is small everywhere
+//! can be missing from an ancestor it would make the cut in; the dropped
+//! bytes still count toward the directory's total.
+//!
+//! A walk that hands its files out instead of keeping them sums them per
+//! directory as it goes ([`FoldedFiles`]), and the same pass runs on the sums.
+
+use super::{DirectoryEntry, FileEntry, OwnerShare};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Fill in the sizes and counts of `directories` from `files`
+pub(super) fn directory_totals(
+    files: &[FileEntry],
+    directories: HashMap<PathBuf, DirectoryEntry>,
+    owner_limit: usize,
+) -> Vec<DirectoryEntry> {
+    let contents = files.iter().map(|file| {
+        let size = file.counted_size();
+        (parent(key(&file.path)), Contents { size, files: 1, owners: file.uid.map(|uid| OwnerShare { uid, size }) })
+    });
+    totals(contents, directories, owner_limit)
+}
+
+/// The files of a walk that hands them out, summed per directory they are directly in
+///
+/// A directory's sums stand in for its files, so the totals come out as
+/// [`directory_totals`] gives them from the files themselves.
+#[derive(Debug, Clone, Default)]
+pub(super) struct FoldedFiles {
+    /// Keyed by the directory's key (see [`key`])
+    by_parent: HashMap<Vec<u8>, Contents<Vec<OwnerShare>>>,
+}
+
+impl FoldedFiles {
+    pub(super) fn add(&mut self, file: &FileEntry) {
+        // A file without a parent counts toward no directory
+        let Some(parent_key) = parent(key(&file.path)) else { return };
+        let size = file.counted_size();
+        let contents = match self.by_parent.get_mut(parent_key) {
+            Some(contents) => contents,
+            None => self.by_parent.entry(parent_key.to_vec()).or_default(),
+        };
+        contents.size += size;
+        contents.files += 1;
+        if let Some(uid) = file.uid {
+            add_owner(&mut contents.owners, OwnerShare { uid, size });
+        }
+    }
+
+    pub(super) fn absorb(&mut self, other: FoldedFiles) {
+        for (parent_key, more) in other.by_parent {
+            let contents = self.by_parent.entry(parent_key).or_default();
+            contents.size += more.size;
+            contents.files += more.files;
+            for share in more.owners {
+                add_owner(&mut contents.owners, share);
+            }
+        }
+    }
+
+    /// Fill in the sizes and counts of `directories` from the sums
+    pub(super) fn directory_totals(&self, directories: HashMap<PathBuf, DirectoryEntry>, owner_limit: usize) -> Vec<DirectoryEntry> {
+        let contents = self.by_parent.iter().map(|(parent_key, contents)| {
+            (Some(parent_key.as_slice()), Contents { size: contents.size, files: contents.files, owners: contents.owners.iter().copied() })
+        });
+        totals(contents, directories, owner_limit)
+    }
+}
+
+/// Bytes, files, and owner bytes found directly in one directory
+#[derive(Debug, Clone, Default)]
+struct Contents<O> {
+    size: u64,
+    files: u64,
+    owners: O,
+}
+
+/// Fill in `directories` from what lies directly in each of them, given by the key of the directory
+fn totals<'a, O: IntoIterator<Item = OwnerShare>>(
+    contents: impl Iterator<Item = (Option<&'a [u8]>, Contents<O>)>,
+    directories: HashMap<PathBuf, DirectoryEntry>,
+    owner_limit: usize,
+) -> Vec<DirectoryEntry> {
+    let mut entries: Vec<DirectoryEntry> = directories.into_values().collect();
+    // Ancestors are always shallower, so every directory is complete before it is added to its parent
+    entries.sort_by_key(|entry| std::cmp::Reverse(entry.depth));
+
+    let mut sizes = vec![0u64; entries.len()];
+    let mut file_counts = vec![0u64; entries.len()];
+    let mut recursive_file_counts = vec![0u64; entries.len()];
+    let mut subdirectory_counts = vec![0u64; entries.len()];
+    let mut recursive_subdirectory_counts = vec![0u64; entries.len()];
+    let mut owners: Vec<Vec<OwnerShare>> = vec![Vec::new(); entries.len()];
+    {
+        let index: HashMap<&[u8], usize> =
+            entries.iter().enumerate().map(|(id, entry)| (key(&entry.path), id)).collect();
+        let nearest = |mut path: Option<&[u8]>| {
+            while let Some(dir) = path {
+                if let Some(&id) = index.get(dir) {
+                    return Some(id);
+                }
+                path = parent(dir);
+            }
+            None
+        };
+
+        // The directory the contents of a directory count toward, and whether it is that directory
+        let resolve = |dir: &[u8]| match index.get(dir) {
+            Some(&id) => Some((id, true)),
+            None => nearest(parent(dir)).map(|id| (id, false)),
+        };
+
+        // The walk lists a directory's files together, so most files share the previous file's parent
+        // A file without a parent resolves to no directory, which is also the starting state
+        let (mut previous_parent, mut resolved) = (None, None);
+        for (parent_key, contents) in contents {
+            if parent_key != previous_parent {
+                resolved = parent_key.and_then(resolve);
+                previous_parent = parent_key;
+            }
+
+            if let Some((id, direct)) = resolved {
+                sizes[id] += contents.size;
+                file_counts[id] += direct as u64 * contents.files;
+                recursive_file_counts[id] += contents.files;
+                for share in contents.owners {
+                    add_owner(&mut owners[id], share);
+                }
+            }
+        }
+
+        for (id, entry) in entries.iter().enumerate() {
+            keep_largest(&mut owners[id], owner_limit);
+            if let Some((parent_id, direct)) = parent(key(&entry.path)).and_then(resolve) {
+                sizes[parent_id] += sizes[id];
+                recursive_file_counts[parent_id] += recursive_file_counts[id];
+                subdirectory_counts[parent_id] += direct as u64;
+                recursive_subdirectory_counts[parent_id] += recursive_subdirectory_counts[id] + 1;
+                let shares = std::mem::take(&mut owners[id]);
+                for &share in &shares {
+                    add_owner(&mut owners[parent_id], share);
+                }
+                owners[id] = shares;
+            }
+        }
+    }
+
+    for ((id, entry), owners) in entries.iter_mut().enumerate().zip(owners) {
+        entry.total_size = sizes[id];
+        entry.file_count = file_counts[id];
+        entry.recursive_file_count = recursive_file_counts[id];
+        entry.subdirectory_count = subdirectory_counts[id];
+        entry.recursive_subdirectory_count = recursive_subdirectory_counts[id];
+        entry.owners = owners;
+    }
+    entries
+}
+
+fn add_owner(owners: &mut Vec<OwnerShare>, share: OwnerShare) {
+    // Few distinct owners ever meet in one directory, so a scan beats a map here
+    match owners.iter_mut().find(|owner| owner.uid == share.uid) {
+        Some(owner) => owner.size += share.size,
+        None => owners.push(share),
+    }
+}
+
+/// Sort largest first, ties by uid, and drop all but the first `limit`
+fn keep_largest(owners: &mut Vec<OwnerShare>, limit: usize) {
+    owners.sort_by(|a, b| b.size.cmp(&a.size).then(a.uid.cmp(&b.uid)));
+    owners.truncate(limit);
+    owners.shrink_to_fit();
+}
+
+/// Raw bytes of a walked path without trailing separators
+///
+/// Hashing bytes is far cheaper than hashing a `Path`, which parses its components.
+/// Walked paths are the root joined with entry names, so the bytes only differ
+/// from the component form by separators trailing the root.
+fn key(path: &Path) -> &[u8] {
+    trim_separators(path.as_os_str().as_encoded_bytes())
+}
+
+/// The key of the parent of a key, or `None` at the top
+fn parent(key: &[u8]) -> Option<&[u8]> {
+    let cut = key.iter().rposition(|&byte| is_separator(byte))?;
+    if cut == 0 {
+        // The filesystem root is its own key and has no parent
+        return (key.len() > 1).then(|| &key[..1]);
+    }
+    Some(trim_separators(&key[..cut]))
+}
+
+fn trim_separators(bytes: &[u8]) -> &[u8] {
+    let end = bytes.iter().rposition(|&byte| !is_separator(byte)).map_or(bytes.len().min(1), |last| last + 1);
+    &bytes[..end]
+}
+
+fn is_separator(byte: u8) -> bool {
+    byte.is_ascii() && std::path::is_separator(byte as char)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    /// The previous implementation, walking every file's ancestor chain
+    fn ancestor_walk(files: &[FileEntry], mut directories: HashMap<PathBuf, DirectoryEntry>) -> Vec<DirectoryEntry> {
+        for file in files {
+            let mut current_path = file.path.parent();
+            while let Some(dir_path) = current_path {
+                if let Some(dir_entry) = directories.get_mut(dir_path) {
+                    dir_entry.total_size += file.size;
+                    if dir_path == file.path.parent().unwrap_or_else(|| Path::new("")) {
+                        dir_entry.file_count += 1;
+                    }
+                }
+                current_path = dir_path.parent();
+            }
+        }
+
+        let dir_paths: Vec<PathBuf> = directories.keys().cloned().collect();
+        for dir_path in &dir_paths {
+            let subdirs = dir_paths
+                .iter()
+                .filter(|other_path| other_path.parent() == Some(dir_path) && *other_path != dir_path)
+                .count();
+            if let Some(dir_entry) = directories.get_mut(dir_path) {
+                dir_entry.subdirectory_count = subdirs as u64;
+            }
+        }
+        directories.into_values().collect()
+    }
+
+    fn directory(path: &str, depth: usize) -> (PathBuf, DirectoryEntry) {
+        let entry = DirectoryEntry {
+            path: PathBuf::from(path),
+            total_size: 0,
+            file_count: 0,
+            recursive_file_count: 0,
+            subdirectory_count: 0,
+            recursive_subdirectory_count: 0,
+            depth,
+            owners: Vec::new(),
+            root: 0,
+            mode: None,
+        };
+        (entry.path.clone(), entry)
+    }
+
+    fn file(path: &str, size: u64) -> FileEntry {
+        FileEntry {
+            path: PathBuf::from(path),
+            size,
+            depth: Path::new(path).components().count(),
+            ..Default::default()
+        }
+    }
+
+    fn owned(path: &str, size: u64, uid: u32) -> FileEntry {
+        FileEntry { uid: Some(uid), ..file(path, size) }
+    }
+
+    /// Directories and files of a tree `levels` deep with `fanout` children per directory
+    fn generated_tree(root: &str, levels: usize, fanout: usize, files_per_dir: usize) -> (Vec<FileEntry>, HashMap<PathBuf, DirectoryEntry>) {
+        let mut directories = HashMap::from([directory(root, 0)]);
+        let mut files = Vec::new();
+        let mut frontier = vec![PathBuf::from(root)];
+        for depth in 0..=levels {
+            let mut next = Vec::new();
+            for dir in &frontier {
+                for f in 0..files_per_dir {
+                    files.push(file(&format!("{}/file{}.dat", dir.display(), f), (depth * 1000 + f) as u64));
+                }
+                if depth == levels {
+                    continue;
+                }
+                for c in 0..fanout {
+                    let child = dir.join(format!("d{}", c));
+                    directories.extend([directory(child.to_str().unwrap(), depth + 1)]);
+                    next.push(child);
+                }
+            }
+            frontier = next;
+        }
+        (files, directories)
+    }
+
+    fn sorted(mut entries: Vec<DirectoryEntry>) -> Vec<(PathBuf, u64, u64, u64, usize)> {
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+        entries
+            .into_iter()
+            .map(|e| (e.path, e.total_size, e.file_count, e.subdirectory_count, e.depth))
+            .collect()
+    }
+
+    #[test]
+    fn test_matches_ancestor_walk_on_generated_tree() {
+        let (files, directories) = generated_tree("/data", 4, 3, 2);
+        assert_eq!(
+            sorted(directory_totals(&files, directories.clone(), 5)),
+            sorted(ancestor_walk(&files, directories))
+        );
+    }
+
+    #[test]
+    fn test_matches_ancestor_walk_across_gaps() {
+        // "/data/skipped" was never walked; "/data/skipped/inner" was
+        let directories: HashMap<_, _> = [
+            directory("/data", 0),
+            directory("/data/a", 1),
+            directory("/data/skipped/inner", 2),
+            directory("/data/skipped/inner/deep", 3),
+        ]
+        .into_iter()
+        .collect();
+        let files = vec![
+            file("/data/top.txt", 1),
+            file("/data/a/one.txt", 10),
+            file("/data/skipped/loose.txt", 100),
+            file("/data/skipped/inner/two.txt", 1_000),
+            file("/data/skipped/inner/deep/three.txt", 10_000),
+            file("/elsewhere/outside.txt", 100_000),
+        ];
+
+        let totals = sorted(directory_totals(&files, directories.clone(), 5));
+        assert_eq!(totals, sorted(ancestor_walk(&files, directories)));
+        assert_eq!(totals[0], (PathBuf::from("/data"), 11_111, 1, 1, 0));
+        assert_eq!(totals[2], (PathBuf::from("/data/skipped/inner"), 11_000, 1, 1, 2));
+        // Files below the gap count toward the recursive counts, those outside toward none
+        let recursive = file_counts(directory_totals(&files, directories_of(&["/data", "/data/a", "/data/skipped/inner", "/data/skipped/inner/deep"]), 5));
+        assert_eq!(recursive[0], ("/data".into(), 1, 5));
+        assert_eq!(recursive[2], ("/data/skipped/inner".into(), 1, 2));
+    }
+
+    /// Walked directories at the depths their paths give below the first one
+    fn directories_of(paths: &[&str]) -> HashMap<PathBuf, DirectoryEntry> {
+        let top = Path::new(paths[0]).components().count();
+        paths.iter().map(|path| directory(path, Path::new(path).components().count() - top)).collect()
+    }
+
+    /// Path, direct, and recursive file count of each directory, sorted by path
+    fn file_counts(mut entries: Vec<DirectoryEntry>) -> Vec<(String, u64, u64)> {
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+        entries.into_iter().map(|e| (e.path.display().to_string(), e.file_count, e.recursive_file_count)).collect()
+    }
+
+    #[test]
+    fn test_direct_and_recursive_file_counts_at_every_level() {
+        let directories = directories_of(&["/scan", "/scan/x", "/scan/x/y", "/scan/z"]);
+        let files = ["/scan/a", "/scan/b", "/scan/x/c", "/scan/x/y/d", "/scan/x/y/e"].map(|path| file(path, 1));
+        assert_eq!(
+            file_counts(directory_totals(&files, directories, 5)),
+            [("/scan".into(), 2, 5), ("/scan/x".into(), 1, 3), ("/scan/x/y".into(), 2, 2), ("/scan/z".into(), 0, 0)]
+        );
+
+        let (files, directories) = generated_tree("/data", 3, 2, 2);
+        let totals = file_counts(directory_totals(&files, directories, 5));
+        // Two files in each directory, and 2 + 4 + 8 directories below the root
+        assert_eq!(totals[0], ("/data".into(), 2, files.len() as u64));
+        assert_eq!(files.len(), 30);
+        assert!(totals.iter().filter(|(path, ..)| path.matches('/').count() == 4).all(|&(_, direct, recursive)| (direct, recursive) == (2, 2)));
+    }
+
+    #[test]
+    fn test_recursive_subdirectory_counts_on_a_large_tree() {
+        // 1 + 14 + 196 + 2744 + 38416 directories, some 1.7 billion pairs for a pairwise count
+        let (files, directories) = generated_tree("/data", 4, 14, 1);
+        assert_eq!(directories.len(), 41_371);
+        let start = Instant::now();
+        let totals = directory_totals(&files, directories, 5);
+        assert!(start.elapsed() < std::time::Duration::from_secs(10));
+
+        let counts = |path: &str| {
+            let entry = totals.iter().find(|entry| entry.path == Path::new(path)).unwrap();
+            (entry.subdirectory_count, entry.recursive_subdirectory_count)
+        };
+        assert_eq!(counts("/data"), (14, 41_370));
+        assert_eq!(counts("/data/d3"), (14, 14 + 196 + 2744));
+        assert_eq!(counts("/data/d3/d0"), (14, 14 + 196));
+        assert_eq!(counts("/data/d3/d0/d1"), (14, 14));
+        assert_eq!(counts("/data/d3/d0/d1/d13"), (0, 0));
+    }
+
+    #[test]
+    fn test_recursive_subdirectories_count_past_gaps() {
+        // "/data/skipped" was never walked, so it is no subdirectory, but what lies below it is
+        let directories = directories_of(&["/data", "/data/a", "/data/skipped/inner", "/data/skipped/inner/deep"]);
+        let mut totals = directory_totals(&[], directories, 5);
+        totals.sort_by(|a, b| a.path.cmp(&b.path));
+        let counts: Vec<_> = totals.iter().map(|entry| (entry.subdirectory_count, entry.recursive_subdirectory_count)).collect();
+        assert_eq!(counts, [(1, 3), (0, 0), (1, 1), (0, 0)]);
+    }
+
+    #[test]
+    fn test_files_directly_in_the_scan_root() {
+        // A root given with a trailing separator, and the filesystem root itself
+        let mut directories = directories_of(&["/scan", "/scan/sub"]);
+        let root = directories.remove(Path::new("/scan")).unwrap();
+        directories.insert(PathBuf::from("/scan/"), DirectoryEntry { path: PathBuf::from("/scan/"), ..root });
+        let files = ["/scan/top", "/scan/sub/below"].map(|path| file(path, 1));
+        assert_eq!(file_counts(directory_totals(&files, directories, 5)), [("/scan/".into(), 1, 2), ("/scan/sub".into(), 1, 1)]);
+
+        let directories = directories_of(&["/", "/etc"]);
+        let files = ["/vmlinuz", "/etc/hosts", "/etc/passwd"].map(|path| file(path, 1));
+        assert_eq!(file_counts(directory_totals(&files, directories, 5)), [("/".into(), 1, 3), ("/etc".into(), 2, 2)]);
+    }
+
+    fn owners_of(totals: &[DirectoryEntry], path: &str) -> Vec<(u32, u64)> {
+        let entry = totals.iter().find(|entry| entry.path == Path::new(path)).unwrap();
+        entry.owners.iter().map(|owner| (owner.uid, owner.size)).collect()
+    }
+
+    #[test]
+    fn test_owner_bytes_add_up_the_tree() {
+        let directories: HashMap<_, _> =
+            [directory("/proj", 0), directory("/proj/a", 1), directory("/proj/b", 1)].into_iter().collect();
+        let files = vec![
+            owned("/proj/readme", 10, 1000),
+            owned("/proj/a/one", 500, 1000),
+            owned("/proj/a/two", 100, 1001),
+            owned("/proj/b/three", 300, 1001),
+            // No uid on this platform: counted in the size, attributed to no one
+            file("/proj/b/four", 7),
+        ];
+
+        let totals = directory_totals(&files, directories, 5);
+        assert_eq!(owners_of(&totals, "/proj/a"), vec![(1000, 500), (1001, 100)]);
+        assert_eq!(owners_of(&totals, "/proj/b"), vec![(1001, 300)]);
+        assert_eq!(owners_of(&totals, "/proj"), vec![(1000, 510), (1001, 400)]);
+    }
+
+    #[test]
+    fn test_owner_limit_keeps_the_largest() {
+        let directories: HashMap<_, _> = [directory("/proj", 0), directory("/proj/a", 1)].into_iter().collect();
+        let files = vec![
+            owned("/proj/a/big", 900, 1),
+            owned("/proj/a/mid", 50, 2),
+            owned("/proj/a/small", 5, 3),
+            owned("/proj/top", 60, 3),
+        ];
+
+        let totals = directory_totals(&files, directories, 2);
+        assert_eq!(owners_of(&totals, "/proj/a"), vec![(1, 900), (2, 50)]);
+        // uid 3's bytes below /proj/a were dropped there, so only its own file reaches /proj
+        assert_eq!(owners_of(&totals, "/proj"), vec![(1, 900), (3, 60)]);
+        assert_eq!(totals.iter().find(|entry| entry.path == Path::new("/proj")).unwrap().total_size, 1015);
+    }
+}
@@ -0,0 +1,142 @@
+//! Detection of directories reached twice, as through bind mounts
+//!
+//! A bind mount shows the same directory at a second path without a symlink
+//! in between, so `--follow-links` being off does not keep the walk from
+//! entering it again. Every directory's (device, inode) identity is remembered
+//! with the path it was first seen at; a directory whose identity was already
+//! seen is an alias, and the walk skips it instead of counting it twice.
+//!
+//! The identities are shared by the threads of a split walk, so whichever
+//! reaches a directory first walks it; which path is the original can then
+//! differ from run to run, but the directory is still counted once.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::Metadata;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Directory identities remembered by default, about 100 MB at typical path lengths
+pub const DEFAULT_IDENTITY_LIMIT: usize = 1 << 20;
+
+/// A directory the walk reached a second time and skipped
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DirectoryAlias {
+    /// Where the walk first entered the directory
+    pub original: PathBuf,
+    /// Where it appeared again
+    pub alias: PathBuf,
+}
+
+/// Directories reached more than once during a scan
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Aliases {
+    pub directories: Vec<DirectoryAlias>,
+    /// Directories seen after the identity limit was reached; later aliases of them go unnoticed
+    #[serde(default)]
+    pub unrecorded: u64,
+}
+
+/// (device, inode) of every directory walked so far, up to a limit
+#[derive(Debug)]
+pub(super) struct VisitedDirectories {
+    seen: Mutex<Seen>,
+    limit: usize,
+}
+
+#[derive(Debug, Default)]
+struct Seen {
+    identities: HashMap<(u64, u64), PathBuf>,
+    aliases: Aliases,
+}
+
+impl Default for VisitedDirectories {
+    fn default() -> Self {
+        Self::new(DEFAULT_IDENTITY_LIMIT)
+    }
+}
+
+impl VisitedDirectories {
+    pub fn new(limit: usize) -> Self {
+        Self { seen: Mutex::default(), limit }
+    }
+
+    /// An empty set with the same limit, for a new scan
+    pub fn fresh(&self) -> Self {
+        Self::new(self.limit)
+    }
+
+    /// Record the directory at `path`; false when it is an alias of one visited before
+    pub fn visit(&self, path: &Path, metadata: &Metadata) -> bool {
+        let Some(identity) = identity(metadata) else {
+            return true;
+        };
+        let mut seen = self.seen.lock().unwrap();
+        if let Some(original) = seen.identities.get(&identity) {
+            let alias = DirectoryAlias { original: original.clone(), alias: path.to_path_buf() };
+            seen.aliases.directories.push(alias);
+            return false;
+        }
+        if seen.identities.len() < self.limit {
+            seen.identities.insert(identity, path.to_path_buf());
+        } else {
+            seen.aliases.unrecorded += 1;
+        }
+        true
+    }
+
+    /// The aliases found so far, leaving the identities for a walk that continues the scan
+    pub fn take_aliases(&self) -> Aliases {
+        std::mem::take(&mut self.seen.lock().unwrap().aliases)
+    }
+}
+
+/// (device, inode) of a directory, where the platform has them
+fn identity(metadata: &Metadata) -> Option<(u64, u64)> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        Some((metadata.dev(), metadata.ino()))
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = metadata;
+        None
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_second_sighting_is_an_alias() {
+        let dir = tempfile::tempdir().unwrap();
+        let metadata = fs::metadata(dir.path()).unwrap();
+        let visited = VisitedDirectories::default();
+
+        assert!(visited.visit(Path::new("/data"), &metadata));
+        assert!(!visited.visit(Path::new("/srv/export"), &metadata));
+        assert_eq!(
+            visited.take_aliases().directories,
+            [DirectoryAlias { original: PathBuf::from("/data"), alias: PathBuf::from("/srv/export") }]
+        );
+    }
+
+    #[test]
+    fn test_identities_past_the_limit_are_not_remembered() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("a")).unwrap();
+        let (first, second) = (fs::metadata(dir.path()).unwrap(), fs::metadata(dir.path().join("a")).unwrap());
+        let visited = VisitedDirectories::new(1);
+
+        assert!(visited.visit(Path::new("/r"), &first));
+        assert!(visited.visit(Path::new("/r/a"), &second));
+        assert!(visited.visit(Path::new("/r/again"), &second));
+        // Identities recorded before the limit still catch their aliases
+        assert!(!visited.visit(Path::new("/r/root-again"), &first));
+        let aliases = visited.take_aliases();
+        assert_eq!((aliases.directories.len(), aliases.unrecorded), (1, 2));
+    }
+}
@@ -0,0 +1,201 @@
+//! Caps on how much a scan collects, for quick triage of unknown trees
+//!
+//! With `--max-files` or `--max-bytes` the walk stops once the next file would
+//! take it past a cap, and with `--max-duration` once the time is up. Directories already entered are not finished and no
+//! new ones are read; what was collected until then is analyzed and reported
+//! as usual, marked as partial. A cancelled run, such as one interrupted with
+//! Ctrl-C, stops the same way.
+
+use crate::interrupt::Cancellation;
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// The caps given on the command line; `None` leaves a quantity unlimited
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct ScanCaps {
+    pub files: Option<u64>,
+    pub bytes: Option<u64>,
+    /// How long the scan may take, counted from when its budget is made
+    pub duration: Option<Duration>,
+}
+
+/// Which cap stopped the scan
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CapKind {
+    Files,
+    Bytes,
+    Duration,
+    /// The run was cancelled, with Ctrl-C or through a [`Cancellation`]
+    Interrupted,
+}
+
+impl CapKind {
+    /// The flag that sets this cap, or the key that interrupts a run
+    pub fn flag(self) -> &'static str {
+        match self {
+            CapKind::Files => "--max-files",
+            CapKind::Bytes => "--max-bytes",
+            CapKind::Duration => "--max-duration",
+            CapKind::Interrupted => "Ctrl-C",
+        }
+    }
+}
+
+/// Marks results that cover only part of the tree because a cap was reached
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PartialScan {
+    pub cap: CapKind,
+    /// The cap's value, in files, bytes, or seconds; for an interrupted run, the files collected
+    pub limit: u64,
+}
+
+/// Files and bytes collected so far against the caps, across the walks of one scan
+#[derive(Debug, Clone, Default)]
+pub(super) struct Budget {
+    caps: ScanCaps,
+    files: u64,
+    bytes: u64,
+    reached: Option<PartialScan>,
+    cancellation: Option<Cancellation>,
+    /// When `caps.duration` runs out
+    deadline: Option<Instant>,
+}
+
+impl Budget {
+    pub fn new(caps: ScanCaps) -> Self {
+        Self { caps, deadline: caps.duration.map(|duration| Instant::now() + duration), ..Self::default() }
+    }
+
+    /// End the scan once `cancellation` is set, as if a cap were reached
+    pub fn cancelled_by(self, cancellation: Cancellation) -> Self {
+        Self { cancellation: Some(cancellation), ..self }
+    }
+
+    /// An unspent budget with the same caps, cancellation, and deadline, for a new scan
+    pub fn fresh(&self) -> Self {
+        Self { cancellation: self.cancellation.clone(), deadline: self.deadline, ..Self::new(self.caps) }
+    }
+
+    /// Whether files or bytes are capped, which takes the files in walk order to cut off
+    pub fn counts_files(&self) -> bool {
+        self.caps.files.is_some() || self.caps.bytes.is_some()
+    }
+
+    /// Take in what a walk of part of the tree spent, and where it stopped
+    ///
+    /// An interruption is reported after the files of every part taken in so far.
+    pub fn absorb(&mut self, part: Budget) {
+        self.files += part.files;
+        self.bytes += part.bytes;
+        self.reached = self.reached.or(part.reached).map(|reached| match reached.cap {
+            CapKind::Interrupted => PartialScan { limit: self.files, ..reached },
+            _ => reached,
+        });
+    }
+
+    /// Whether the scan has to end here, because a cap was reached, the time is up, or the run was cancelled
+    pub fn stopped(&mut self) -> bool {
+        if self.reached.is_none() && self.cancellation.as_ref().is_some_and(Cancellation::is_cancelled) {
+            self.reached = Some(PartialScan { cap: CapKind::Interrupted, limit: self.files });
+        }
+        if self.reached.is_none() && self.deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+            self.reached = self.caps.duration.map(|duration| PartialScan { cap: CapKind::Duration, limit: duration.as_secs() });
+        }
+        self.reached.is_some()
+    }
+
+    /// Count a file of `size` bytes; false when it would pass a cap, which ends the scan
+    pub fn admit(&mut self, size: u64) -> bool {
+        if self.stopped() {
+            return false;
+        }
+        let over = |cap: Option<u64>, total: u64| cap.is_some_and(|cap| total > cap);
+        if over(self.caps.files, self.files + 1) {
+            self.reached = self.caps.files.map(|limit| PartialScan { cap: CapKind::Files, limit });
+        } else if over(self.caps.bytes, self.bytes + size) {
+            self.reached = self.caps.bytes.map(|limit| PartialScan { cap: CapKind::Bytes, limit });
+        } else {
+            self.files += 1;
+            self.bytes += size;
+        }
+        self.reached.is_none()
+    }
+
+    /// The cap that stopped the scan, if one did
+    pub fn reached(&self) -> Option<PartialScan> {
+        self.reached
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_cap_admits_exactly_the_limit() {
+        let mut budget = Budget::new(ScanCaps { files: Some(3), ..ScanCaps::default() });
+        let admitted = (0..5).take_while(|_| budget.admit(100)).count();
+        assert_eq!(admitted, 3);
+        assert_eq!(budget.reached(), Some(PartialScan { cap: CapKind::Files, limit: 3 }));
+    }
+
+    #[test]
+    fn test_byte_cap_stops_before_the_file_that_passes_it() {
+        let mut budget = Budget::new(ScanCaps { bytes: Some(250), ..ScanCaps::default() });
+        assert!(budget.admit(100));
+        assert!(budget.admit(150));
+        assert!(!budget.admit(1));
+        // Once reached, the scan stays stopped even for files that would fit
+        assert!(!budget.admit(0));
+        assert_eq!(budget.reached(), Some(PartialScan { cap: CapKind::Bytes, limit: 250 }));
+        assert_eq!(budget.fresh().reached(), None);
+    }
+
+    #[test]
+    fn test_cancellation_stops_at_the_files_collected() {
+        let cancellation = Cancellation::new();
+        let mut budget = Budget::new(ScanCaps { files: Some(10), ..ScanCaps::default() }).cancelled_by(cancellation.clone());
+        assert!(budget.admit(100) && budget.admit(100));
+        cancellation.cancel();
+        assert!(budget.stopped());
+        assert!(!budget.admit(1));
+        assert_eq!(budget.reached(), Some(PartialScan { cap: CapKind::Interrupted, limit: 2 }));
+        // A new scan of the same run stops at once
+        assert!(budget.fresh().stopped());
+    }
+
+    #[test]
+    fn test_interrupted_part_counts_the_files_of_every_part() {
+        let cancellation = Cancellation::new();
+        let mut budget = Budget::default().cancelled_by(cancellation.clone());
+        assert!(budget.admit(100));
+        let (mut first, mut second) = (budget.fresh(), budget.fresh());
+        assert!(first.admit(100) && first.admit(100));
+        cancellation.cancel();
+        assert!(!second.admit(100));
+        assert_eq!(second.reached(), Some(PartialScan { cap: CapKind::Interrupted, limit: 0 }));
+
+        budget.absorb(first);
+        budget.absorb(second);
+        assert_eq!(budget.reached(), Some(PartialScan { cap: CapKind::Interrupted, limit: 3 }));
+    }
+
+    #[test]
+    fn test_duration_cap_stops_every_scan_of_the_run() {
+        let mut budget = Budget::new(ScanCaps { duration: Some(Duration::from_secs(30)), ..ScanCaps::default() });
+        assert!(budget.admit(100));
+        budget.deadline = Some(Instant::now());
+        assert!(!budget.admit(100));
+        assert_eq!(budget.reached(), Some(PartialScan { cap: CapKind::Duration, limit: 30 }));
+        // The time a new scan of the same run has left is what the first one left it
+        assert!(budget.fresh().stopped());
+    }
+
+    #[test]
+    fn test_no_caps_admit_everything() {
+        let mut budget = Budget::default();
+        assert!((0..1_000).all(|_| budget.admit(u64::MAX / 2_000)));
+        assert_eq!(budget.reached(), None);
+    }
+}
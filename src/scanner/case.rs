@@ -0,0 +1,217 @@
+//! Sibling names that collide on case-insensitive filesystems
+//!
+//! A tree copied from macOS or Windows onto Linux can end up holding
+//! `Report.pdf` and `report.pdf` side by side; copied back, one overwrites the
+//! other. With `--detect-case-collisions` the walk folds each name it reads
+//! and groups siblings whose folded names are equal. Only the directories on
+//! the walk's current path keep their names, so memory follows the depth and
+//! the width of the tree rather than its size.
+
+use super::DirectoryEntry;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+/// Names in one directory that are equal under case folding
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CaseCollision {
+    pub directory: PathBuf,
+    /// The name every entry folds to
+    pub folded: String,
+    /// The colliding entries, sorted by name
+    pub entries: Vec<CollidingEntry>,
+}
+
+/// One of the names in a [`CaseCollision`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CollidingEntry {
+    pub name: String,
+    pub is_dir: bool,
+    /// The file's size, or a directory's total size
+    pub size: u64,
+    pub modified: Option<DateTime<Utc>>,
+}
+
+/// Fold `name` with Unicode simple case folding
+///
+/// Each character maps to one character: its uppercase form lowercased, so
+/// that `ς`, `ſ`, and the Kelvin sign join `σ`, `s`, and `k`. Characters whose
+/// case mappings expand, like `ß` to `SS`, stay themselves, as simple folding
+/// leaves them.
+pub fn fold(name: &str) -> String {
+    fn single(mut chars: impl Iterator<Item = char>) -> Option<char> {
+        let first = chars.next()?;
+        chars.next().is_none().then_some(first)
+    }
+    name.chars()
+        .map(|c| {
+            let upper = single(c.to_uppercase()).unwrap_or(c);
+            single(upper.to_lowercase()).unwrap_or(c)
+        })
+        .collect()
+}
+
+/// Names that fold alike, each with whether it is a directory
+type Names = Vec<(OsString, bool)>;
+
+/// The names of a directory read so far, by folded name
+struct Frame {
+    directory: PathBuf,
+    names: HashMap<String, Names>,
+}
+
+impl Frame {
+    fn new(directory: &Path) -> Self {
+        Self { directory: directory.to_path_buf(), names: HashMap::new() }
+    }
+}
+
+/// Groups of colliding names found in one walk, before they are described
+#[derive(Debug, Default)]
+pub(super) struct Found(Vec<(PathBuf, String, Names)>);
+
+/// Follows a depth-first walk and keeps the names of each directory on its current path
+///
+/// A directory's names are complete once the walk yields an entry no deeper
+/// than the directory itself; its collisions are collected then.
+pub(super) struct SiblingNames {
+    /// The walk root at index 0 and below it the directories on the current path, one per depth
+    stack: Vec<Frame>,
+    found: Found,
+}
+
+impl SiblingNames {
+    pub fn new(root: &Path) -> Self {
+        Self { stack: vec![Frame::new(root)], found: Found::default() }
+    }
+
+    /// Record an entry `depth` levels below the walk root, as the walk yields it
+    pub fn add(&mut self, path: &Path, depth: usize, is_dir: bool) {
+        let Some(name) = path.file_name().filter(|_| depth > 0) else { return };
+        while self.stack.len() > depth {
+            self.close_top();
+        }
+        // The walk yields a directory before its contents, so the parent is on top
+        let Some(parent) = self.stack.last_mut() else { return };
+        parent.names.entry(fold(&name.to_string_lossy())).or_default().push((name.to_os_string(), is_dir));
+        if is_dir {
+            self.stack.push(Frame::new(path));
+        }
+    }
+
+    fn close_top(&mut self) {
+        let Some(frame) = self.stack.pop() else { return };
+        for (folded, names) in frame.names {
+            if names.len() > 1 {
+                self.found.0.push((frame.directory.clone(), folded, names));
+            }
+        }
+    }
+
+    /// The collisions of every directory the walk read
+    pub fn finish(mut self) -> Found {
+        while !self.stack.is_empty() {
+            self.close_top();
+        }
+        self.found
+    }
+}
+
+impl Found {
+    pub fn extend(&mut self, other: Found) {
+        self.0.extend(other.0);
+    }
+
+    /// Describe the groups with each entry's size and modification time
+    ///
+    /// Directories are sized from `directories`; files are statted again,
+    /// which costs one call per colliding name.
+    pub fn describe(self, directories: &[DirectoryEntry]) -> Vec<CaseCollision> {
+        let mut collisions: Vec<CaseCollision> = self
+            .0
+            .into_iter()
+            .map(|(directory, folded, names)| {
+                let mut entries: Vec<CollidingEntry> = names
+                    .into_iter()
+                    .map(|(name, is_dir)| {
+                        let metadata = std::fs::symlink_metadata(directory.join(&name)).ok();
+                        CollidingEntry {
+                            name: name.to_string_lossy().into_owned(),
+                            is_dir,
+                            size: metadata.as_ref().filter(|_| !is_dir).map_or(0, |metadata| metadata.len()),
+                            modified: metadata.and_then(|metadata| metadata.modified().ok()).and_then(timestamp),
+                        }
+                    })
+                    .collect();
+                entries.sort_by(|a, b| a.name.cmp(&b.name));
+                CaseCollision { directory, folded, entries }
+            })
+            .collect();
+        size_directories(&mut collisions, directories);
+        collisions.sort_by(|a, b| a.directory.cmp(&b.directory).then_with(|| a.folded.cmp(&b.folded)));
+        collisions
+    }
+}
+
+/// Set the size of the colliding directories from their totals
+pub(super) fn size_directories(collisions: &mut [CaseCollision], directories: &[DirectoryEntry]) {
+    if collisions.iter().all(|collision| collision.entries.iter().all(|entry| !entry.is_dir)) {
+        return;
+    }
+    let totals: HashMap<&Path, u64> =
+        directories.iter().map(|directory| (directory.path.as_path(), directory.total_size)).collect();
+    for collision in collisions {
+        for entry in collision.entries.iter_mut().filter(|entry| entry.is_dir) {
+            entry.size = totals.get(collision.directory.join(&entry.name).as_path()).copied().unwrap_or(0);
+        }
+    }
+}
+
+fn timestamp(time: SystemTime) -> Option<DateTime<Utc>> {
+    let since_epoch = time.duration_since(SystemTime::UNIX_EPOCH).ok()?;
+    DateTime::from_timestamp(since_epoch.as_secs() as i64, 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fold_uses_simple_case_folding() {
+        assert_eq!(fold("Report.PDF"), "report.pdf");
+        assert_eq!(fold("ΣΟΦΟΣ"), fold("σοφος"));
+        assert_eq!(fold("ſtraße"), "straße");
+        assert_eq!(fold("\u{212A}elvin"), "kelvin");
+        // Full folding would turn ß into ss; simple folding keeps them apart
+        assert_ne!(fold("Straße"), fold("STRASSE"));
+        assert_eq!(fold("ẞ"), "ß");
+    }
+
+    #[test]
+    fn test_siblings_collide_only_within_their_directory() {
+        let root = Path::new("/r");
+        let mut names = SiblingNames::new(root);
+        // Depth-first, as a walk yields them: a/ and its contents come before b.txt
+        for (path, depth, is_dir) in [
+            ("/r", 0, true),
+            ("/r/a", 1, true),
+            ("/r/a/X.txt", 2, false),
+            ("/r/a/sub", 2, true),
+            ("/r/a/sub/x.txt", 3, false),
+            ("/r/a/x.TXT", 2, false),
+            ("/r/A", 1, true),
+            ("/r/b.txt", 1, false),
+            ("/r/c", 1, true),
+            ("/r/c/B.txt", 2, false),
+        ] {
+            names.add(Path::new(path), depth, is_dir);
+        }
+        let mut found: Vec<(PathBuf, String, usize)> =
+            names.finish().0.into_iter().map(|(directory, folded, names)| (directory, folded, names.len())).collect();
+        found.sort();
+        assert_eq!(found, [(PathBuf::from("/r"), "a".to_string(), 2), (PathBuf::from("/r/a"), "x.txt".to_string(), 2)]);
+    }
+}
@@ -0,0 +1,105 @@
+//! Directories with nothing in them but other empty directories
+//!
+//! Every directory the walk enters starts out as empty. Anything met inside
+//! it takes that back: a file or link, whether the filters keep it or not, a
+//! subdirectory the walk does not enter (pruned, on another filesystem, an
+//! alias, or past the depth limit), and an error. Once the walk is done, a
+//! directory that is not empty makes all of its ancestors not empty either,
+//! so a tree of directories with no files anywhere in it is empty as a whole,
+//! while a directory that could not be read, and the ones above it, are not.
+
+use super::DirectoryEntry;
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// The directories entered with nothing found in them so far
+#[derive(Debug, Default)]
+pub(super) struct EmptyDirectories {
+    bare: HashSet<PathBuf>,
+}
+
+impl EmptyDirectories {
+    /// A directory the walk is entering
+    pub fn enter(&mut self, directory: &Path) {
+        self.bare.insert(directory.to_path_buf());
+    }
+
+    /// Something was met at `path`, so its parent is not empty
+    pub fn holds(&mut self, path: &Path) {
+        if let Some(parent) = path.parent() {
+            self.bare.remove(parent);
+        }
+    }
+
+    /// The contents of `directory` are not known, as when it could not be read
+    pub fn unknown(&mut self, directory: &Path) {
+        self.bare.remove(directory);
+    }
+
+    /// Hand `directory` over to a walk of its own contents, to be absorbed back afterwards
+    pub fn split(&mut self, directory: &Path) -> Self {
+        Self { bare: self.bare.take(directory).into_iter().collect() }
+    }
+
+    /// Take back what a walk split off with [`EmptyDirectories::split`] found
+    pub fn absorb(&mut self, part: Self) {
+        self.bare.extend(part.bare);
+    }
+
+    /// The empty directories among those walked, sorted
+    pub fn finish(mut self, walked: &HashMap<PathBuf, DirectoryEntry>) -> Vec<PathBuf> {
+        let held: Vec<_> = walked.keys().filter(|path| !self.bare.contains(*path)).collect();
+        for path in held {
+            // An ancestor already taken out was, or will be, climbed from itself
+            for ancestor in path.ancestors().skip(1) {
+                if !self.bare.remove(ancestor) {
+                    break;
+                }
+            }
+        }
+        let mut empty: Vec<_> = self.bare.into_iter().collect();
+        empty.sort();
+        empty
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn walked(paths: &[&str]) -> HashMap<PathBuf, DirectoryEntry> {
+        paths
+            .iter()
+            .map(|path| {
+                let entry = DirectoryEntry {
+                    path: PathBuf::from(path),
+                    total_size: 0,
+                    file_count: 0,
+                    recursive_file_count: 0,
+                    subdirectory_count: 0,
+                    recursive_subdirectory_count: 0,
+                    depth: Path::new(path).components().count() - 1,
+                    owners: Vec::new(),
+                    root: 0,
+                    mode: None,
+                };
+                (PathBuf::from(path), entry)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_only_directories_without_content_below_are_empty() {
+        let directories = ["/r/a", "/r/a/b", "/r/a/b/c", "/r/d", "/r/d/e", "/r/f", "/r/f/g", "/r/f/g/h"];
+        let mut empty = EmptyDirectories::default();
+        for directory in directories {
+            empty.enter(Path::new(directory));
+        }
+        // A file deep down keeps every directory above it
+        empty.holds(Path::new("/r/d/e/notes.txt"));
+        // An unreadable directory is not empty, nor is anything above it
+        empty.unknown(Path::new("/r/f/g/h"));
+
+        assert_eq!(empty.finish(&walked(&directories)), [PathBuf::from("/r/a"), PathBuf::from("/r/a/b"), PathBuf::from("/r/a/b/c")]);
+    }
+}
@@ -0,0 +1,302 @@
+//! Scan errors, counted by cause and rolled up by subtree
+//!
+//! A partly unreadable tree can fail the walk tens of thousands of times with
+//! the same error. The walk hands every error to an [`ErrorLog`], which counts
+//! it by cause, its [`ErrorType`] and errno, and by the directory holding it,
+//! and keeps only the first [`RETAINED_ERRORS`] one by one, or every one with
+//! `--errors-full`. Once more than [`TRACKED_DIRECTORIES`] directories are
+//! counted, the counts of the deepest ones move up to their parents, so the
+//! log stays bounded however many errors there are, at the price of coarser
+//! rollups.
+//!
+//! After the walk, [`rollup`] groups the counts of each cause under the
+//! longest common ancestor of at least [`ROLLUP_THRESHOLD`] of them, deepest
+//! directories first.
+
+use super::{ErrorType, ScanError};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Errors kept one by one unless `--errors-full` keeps them all
+pub const RETAINED_ERRORS: usize = 1_000;
+
+/// Directories counted before counts move up to their parents
+pub const TRACKED_DIRECTORIES: usize = 10_000;
+
+/// Errors of one cause a directory must hold to be rolled up
+pub const ROLLUP_THRESHOLD: u64 = 10;
+
+/// What a scan error comes down to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct Cause {
+    error_type: ErrorType,
+    errno: Option<i32>,
+}
+
+/// The scan errors of a run by cause and subtree, with the first ones listed
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ErrorSummary {
+    pub total: u64,
+    /// Most frequent first
+    pub causes: Vec<ErrorCause>,
+    /// Largest first
+    pub rollups: Vec<ErrorRollup>,
+    /// Errors listed one by one, in the order the walk met them
+    pub errors: Vec<ScanError>,
+    /// Errors not listed one by one
+    pub unlisted: u64,
+    /// Every error is listed, as `--errors-full` asks
+    #[serde(default)]
+    pub complete: bool,
+}
+
+/// Errors sharing a type and errno
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ErrorCause {
+    pub error_type: ErrorType,
+    pub errno: Option<i32>,
+    /// The system's message for the errno, or else the first error's message
+    pub description: String,
+    pub count: u64,
+}
+
+/// Errors of one cause below a directory
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ErrorRollup {
+    pub directory: PathBuf,
+    pub error_type: ErrorType,
+    pub errno: Option<i32>,
+    pub description: String,
+    pub count: u64,
+}
+
+/// Counts the errors of a walk in bounded memory
+#[derive(Debug, Clone, Default)]
+pub struct ErrorLog {
+    total: u64,
+    /// Count and first message of each cause
+    causes: HashMap<Cause, (u64, String)>,
+    /// Errors of each cause by the directory holding them
+    directories: HashMap<(PathBuf, Cause), u64>,
+    /// Errors to list one by one, until [`ErrorLog::take_listed`]
+    listed: Vec<ScanError>,
+    /// Keep every error, for `--errors-full`
+    full: bool,
+}
+
+impl ErrorLog {
+    pub fn new(full: bool) -> Self {
+        Self { full, ..Self::default() }
+    }
+
+    /// An empty log that lists errors the same way, for a walk to be absorbed into this one
+    pub fn fresh(&self) -> Self {
+        Self::new(self.full)
+    }
+
+    pub fn push(&mut self, error: ScanError) {
+        let cause = Cause { error_type: error.error_type, errno: error.errno };
+        self.total += 1;
+        self.causes.entry(cause).or_insert_with(|| (0, error.error.clone())).0 += 1;
+        let directory = error.path.parent().unwrap_or(&error.path).to_path_buf();
+        *self.directories.entry((directory, cause)).or_default() += 1;
+        if self.directories.len() > TRACKED_DIRECTORIES {
+            self.coarsen();
+        }
+        if self.listed.len() < self.listing_limit() {
+            self.listed.push(error);
+        }
+    }
+
+    /// Errors listed one by one at most
+    pub fn listing_limit(&self) -> usize {
+        if self.full { usize::MAX } else { RETAINED_ERRORS }
+    }
+
+    /// Hand over the errors to list one by one; the counts stay
+    pub fn take_listed(&mut self) -> Vec<ScanError> {
+        std::mem::take(&mut self.listed)
+    }
+
+    /// Fold in the counts of another walk of the same tree, and the errors it still lists
+    pub fn absorb(&mut self, other: ErrorLog) {
+        let room = self.listing_limit().saturating_sub(self.listed.len());
+        self.listed.extend(other.listed.into_iter().take(room));
+        self.total += other.total;
+        for (cause, (count, message)) in other.causes {
+            self.causes.entry(cause).or_insert((0, message)).0 += count;
+        }
+        for (key, count) in other.directories {
+            *self.directories.entry(key).or_default() += count;
+        }
+        if self.directories.len() > TRACKED_DIRECTORIES {
+            self.coarsen();
+        }
+    }
+
+    pub fn total(&self) -> u64 {
+        self.total
+    }
+
+    /// Move the counts of the deepest directories up to their parents until half the limit is left
+    fn coarsen(&mut self) {
+        while self.directories.len() > TRACKED_DIRECTORIES / 2 {
+            let depth = |directory: &Path| directory.components().count();
+            let deepest = self.directories.keys().map(|(directory, _)| depth(directory)).max().unwrap_or(0);
+            // Only roots are left
+            if deepest <= 1 {
+                break;
+            }
+            let mut coarser: HashMap<(PathBuf, Cause), u64> = HashMap::new();
+            for ((directory, cause), count) in self.directories.drain() {
+                let directory = match directory.parent() {
+                    Some(parent) if depth(&directory) == deepest => parent.to_path_buf(),
+                    _ => directory,
+                };
+                *coarser.entry((directory, cause)).or_default() += count;
+            }
+            self.directories = coarser;
+        }
+    }
+
+    /// The causes and rollups of the log, with `listed` as the errors listed one by one
+    pub fn summarize(&self, listed: &[ScanError]) -> ErrorSummary {
+        let mut causes: Vec<ErrorCause> = self
+            .causes
+            .iter()
+            .map(|(cause, (count, message))| ErrorCause {
+                error_type: cause.error_type,
+                errno: cause.errno,
+                description: describe(cause, message),
+                count: *count,
+            })
+            .collect();
+        causes.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.description.cmp(&b.description)));
+
+        let counts: Vec<(PathBuf, Cause, u64)> =
+            self.directories.iter().map(|((directory, cause), count)| (directory.clone(), *cause, *count)).collect();
+        let rollups = rollup(&counts, ROLLUP_THRESHOLD)
+            .into_iter()
+            .map(|(directory, cause, count)| ErrorRollup {
+                directory,
+                error_type: cause.error_type,
+                errno: cause.errno,
+                description: describe(&cause, &self.causes[&cause].1),
+                count,
+            })
+            .collect();
+
+        ErrorSummary {
+            total: self.total,
+            causes,
+            rollups,
+            errors: listed.to_vec(),
+            unlisted: self.total.saturating_sub(listed.len() as u64),
+            complete: self.full,
+        }
+    }
+}
+
+/// The system's message for the errno, or else `message`
+fn describe(cause: &Cause, message: &str) -> String {
+    match cause.errno {
+        Some(errno) => io::Error::from_raw_os_error(errno).to_string(),
+        None => message.to_string(),
+    }
+}
+
+/// Group error counts by directory under the directories holding at least `threshold` of a cause
+///
+/// Directories are tried deepest first, each with the errors below it that
+/// no deeper rollup took, so every rollup names the longest common ancestor
+/// of its errors. Errors no directory gathers enough of stay out of the
+/// rollups. Largest first.
+fn rollup(counts: &[(PathBuf, Cause, u64)], threshold: u64) -> Vec<(PathBuf, Cause, u64)> {
+    let mut below: HashMap<(&Path, Cause), u64> = HashMap::new();
+    for (directory, cause, count) in counts {
+        for ancestor in directory.ancestors().filter(|ancestor| !ancestor.as_os_str().is_empty()) {
+            *below.entry((ancestor, *cause)).or_default() += count;
+        }
+    }
+    let mut candidates: Vec<(&Path, Cause)> = below.keys().copied().collect();
+    candidates.sort_by(|a, b| {
+        b.0.components().count().cmp(&a.0.components().count()).then_with(|| a.0.cmp(b.0))
+    });
+
+    let mut taken: HashMap<(&Path, Cause), u64> = HashMap::new();
+    let mut rollups = Vec::new();
+    for (directory, cause) in candidates {
+        let left = below[&(directory, cause)] - taken.get(&(directory, cause)).copied().unwrap_or(0);
+        if left < threshold {
+            continue;
+        }
+        for ancestor in directory.ancestors().skip(1).filter(|ancestor| !ancestor.as_os_str().is_empty()) {
+            *taken.entry((ancestor, cause)).or_default() += left;
+        }
+        rollups.push((directory.to_path_buf(), cause, left));
+    }
+    rollups.sort_by(|a, b| b.2.cmp(&a.2).then_with(|| a.0.cmp(&b.0)));
+    rollups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DENIED: Cause = Cause { error_type: ErrorType::PermissionDenied, errno: Some(libc::EACCES) };
+    const GONE: Cause = Cause { error_type: ErrorType::Other, errno: Some(libc::ENOENT) };
+
+    fn error(path: &str, cause: Cause) -> ScanError {
+        ScanError { path: PathBuf::from(path), error: "failed".to_string(), error_type: cause.error_type, errno: cause.errno }
+    }
+
+    #[test]
+    fn test_rollup_takes_the_longest_common_ancestor() {
+        let counts = |entries: &[(&str, Cause, u64)]| -> Vec<(PathBuf, Cause, u64)> {
+            entries.iter().map(|(path, cause, count)| (PathBuf::from(path), *cause, *count)).collect()
+        };
+
+        // Spread over siblings, the errors meet at their parent
+        let spread = counts(&[("/srv/restricted/a", DENIED, 4), ("/srv/restricted/b", DENIED, 4), ("/srv/restricted/c/d", DENIED, 4)]);
+        assert_eq!(rollup(&spread, 10), [(PathBuf::from("/srv/restricted"), DENIED, 12)]);
+
+        // A deep cluster is its own rollup; the rest meets higher up, causes stay apart
+        let mixed = counts(&[
+            ("/srv/a/deep", DENIED, 30),
+            ("/srv/b", DENIED, 6),
+            ("/srv/c", DENIED, 6),
+            ("/srv/c", GONE, 5),
+            ("/home/x", DENIED, 3),
+        ]);
+        assert_eq!(rollup(&mixed, 10), [(PathBuf::from("/srv/a/deep"), DENIED, 30), (PathBuf::from("/srv"), DENIED, 12)]);
+        assert_eq!(rollup(&mixed, 5)[..2], [(PathBuf::from("/srv/a/deep"), DENIED, 30), (PathBuf::from("/srv/b"), DENIED, 6)]);
+    }
+
+    #[test]
+    fn test_log_stays_bounded_and_counts_every_error() {
+        let mut log = ErrorLog::new(false);
+        for i in 0..40_000 {
+            log.push(error(&format!("/srv/restricted/user{}/private", i), DENIED));
+        }
+        log.push(error("/srv/gone", GONE));
+        assert!(log.directories.len() <= TRACKED_DIRECTORIES);
+
+        let listed = log.take_listed();
+        let summary = log.summarize(&listed);
+        assert_eq!((summary.total, summary.errors.len(), summary.unlisted), (40_001, RETAINED_ERRORS, 40_001 - RETAINED_ERRORS as u64));
+        assert_eq!((summary.causes[0].count, summary.causes[0].errno), (40_000, Some(libc::EACCES)));
+        assert_eq!(summary.causes[1].count, 1);
+        assert_eq!(summary.rollups.len(), 1);
+        assert_eq!((summary.rollups[0].directory.as_path(), summary.rollups[0].count), (Path::new("/srv/restricted"), 40_000));
+
+        let mut full = ErrorLog::new(true);
+        for i in 0..RETAINED_ERRORS + 5 {
+            full.push(error(&format!("/srv/restricted/user{}/private", i), DENIED));
+        }
+        let listed = full.take_listed();
+        let summary = full.summarize(&listed);
+        assert_eq!((summary.errors.len(), summary.unlisted, summary.complete), (RETAINED_ERRORS + 5, 0, true));
+    }
+}
@@ -0,0 +1,286 @@
+//! Why entries are missing from a scan
+//!
+//! Every stage of the filter pipeline counts the entries it was the first to
+//! reject, so a run can say how much each cause kept out (see
+//! [`ExclusionStats::causes`](super::ExclusionStats::causes)). For a single
+//! path, [`DirectoryScanner::explain_path`] follows it down from the root
+//! through the same stages the walk would and names the one that stops it.
+
+use super::{file_device, file_entry, DirectoryScanner};
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// The stage of the filter pipeline that left an entry out
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum ExclusionCause {
+    /// An export, journal, or cache file of diranalyzer itself
+    OwnOutput,
+    /// A name starting with a dot, without `--all`
+    Hidden,
+    /// The first `--exclude` or `--exclude-regex` pattern that matched
+    Pattern { pattern: String },
+    /// A rule of a `.gitignore` or `.ignore` file, or the global excludes, with `--respect-gitignore`
+    IgnoreRule { file: PathBuf, pattern: String },
+    /// A file matching none of the `--include` globs
+    NotIncluded,
+    /// A file of another user, with `--mine` or `--user`
+    Owner,
+    /// A file of another group, with `--group`
+    Group,
+    /// A file dated outside `--modified-before`/`--modified-after`/`--created-after`, or of unknown age
+    Modified,
+    /// An entry on another filesystem than the root, with `--one-file-system`
+    OtherFilesystem,
+    /// A filter added through the library API, numbered from 1 in the order it was added
+    Filter { index: usize },
+}
+
+impl fmt::Display for ExclusionCause {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExclusionCause::OwnOutput => write!(f, "diranalyzer's own file"),
+            ExclusionCause::Hidden => write!(f, "hidden (see --all)"),
+            ExclusionCause::Pattern { pattern } => write!(f, "pattern `{}`", pattern),
+            ExclusionCause::IgnoreRule { file, pattern } => write!(f, "`{}` in {}", pattern, file.display()),
+            ExclusionCause::Owner => write!(f, "owned by another user (--mine/--user)"),
+            ExclusionCause::Group => write!(f, "of another group (--group)"),
+            ExclusionCause::NotIncluded => write!(f, "matched no --include pattern"),
+            ExclusionCause::Modified => write!(f, "dated outside the date range (--modified-before/--modified-after/--created-after)"),
+            ExclusionCause::OtherFilesystem => write!(f, "on another filesystem (--one-file-system)"),
+            ExclusionCause::Filter { index } => write!(f, "added filter #{}", index),
+        }
+    }
+}
+
+/// Entries one cause kept out of a scan
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExclusionCount {
+    pub cause: ExclusionCause,
+    /// Files and pruned directories; each entry counts for the first cause only
+    pub entries: u64,
+    /// Bytes of the files among them; the contents of pruned directories are never read
+    pub size: u64,
+}
+
+/// A path followed from the scan root through the filter pipeline
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExclusionTrace {
+    /// The path as the walk spells it
+    pub path: PathBuf,
+    /// The root and each directory on the way down, then the path itself,
+    /// ending at the first entry the walk does not take
+    pub steps: Vec<TraceStep>,
+    pub outcome: TraceOutcome,
+}
+
+/// One entry on the way from the root to a traced path
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TraceStep {
+    pub path: PathBuf,
+    pub directory: bool,
+    pub cause: Option<ExclusionCause>,
+}
+
+/// Whether a traced path ends up in the results
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TraceOutcome {
+    Included,
+    /// `path`, the traced path or a directory above it, is left out for `cause`
+    Excluded { path: PathBuf, cause: ExclusionCause },
+    /// `path` lies deeper than `--depth` allows, and `--auto-depth` is off
+    BeyondDepth { path: PathBuf, limit: usize },
+    /// `path` is a symbolic link, which the walk neither enters nor counts without `--follow-links`
+    Link { path: PathBuf },
+    /// The traced path lies less than `--min-depth` levels below the root; what lies deeper is counted
+    AboveMinDepth { path: PathBuf, min_depth: usize },
+}
+
+impl ExclusionTrace {
+    /// Whether the traced path is part of the results
+    pub fn is_included(&self) -> bool {
+        self.outcome == TraceOutcome::Included
+    }
+}
+
+impl DirectoryScanner {
+    /// Follow `path` down from the scan root and name the first rule that keeps it out
+    ///
+    /// Nothing is recorded in the scan's statistics. The path must exist below
+    /// the root; it may be given relative to the working directory.
+    pub fn explain_path(&self, path: &Path) -> Result<ExclusionTrace> {
+        let root = &self.args.path;
+        let relative = relative_to_root(root, path)?;
+        let target = root.join(&relative);
+        fs::symlink_metadata(&target).with_context(|| format!("Cannot trace {}", path.display()))?;
+
+        let mut steps = vec![TraceStep { path: root.clone(), directory: true, cause: None }];
+        let mut current = root.clone();
+        let components: Vec<_> = relative.components().collect();
+        for (depth, component) in components.iter().enumerate().map(|(index, component)| (index + 1, component)) {
+            current.push(component);
+            if depth > self.args.max_depth && !self.args.auto_depth {
+                let outcome = TraceOutcome::BeyondDepth { path: current, limit: self.args.max_depth };
+                return Ok(ExclusionTrace { path: target, steps, outcome });
+            }
+
+            let link = fs::symlink_metadata(&current)?;
+            // A link that is not followed is an entry itself, but nothing below it is reached
+            let metadata = if self.args.follow_links { fs::metadata(&current)? } else { link };
+            if metadata.file_type().is_symlink() && depth < components.len() {
+                return Ok(ExclusionTrace { path: target, steps, outcome: TraceOutcome::Link { path: current } });
+            }
+            let directory = metadata.is_dir();
+            let entry = (!directory).then(|| file_entry(&current, &metadata, depth));
+            let cause = self.filters.explain(&current, entry.as_ref()).or_else(|| {
+                self.root_device.filter(|&device| device != file_device(&metadata)).map(|_| ExclusionCause::OtherFilesystem)
+            });
+            steps.push(TraceStep { path: current.clone(), directory, cause: cause.clone() });
+            if let Some(cause) = cause {
+                return Ok(ExclusionTrace { path: target, steps, outcome: TraceOutcome::Excluded { path: current, cause } });
+            }
+        }
+        let outcome = if components.len() < self.args.min_depth {
+            TraceOutcome::AboveMinDepth { path: target.clone(), min_depth: self.args.min_depth }
+        } else {
+            TraceOutcome::Included
+        };
+        Ok(ExclusionTrace { path: target, steps, outcome })
+    }
+}
+
+/// `path` relative to `root`, both made absolute first; `..` is resolved lexically
+fn relative_to_root(root: &Path, path: &Path) -> Result<PathBuf> {
+    let absolute = |path: &Path| -> Result<PathBuf> {
+        let mut normal = PathBuf::new();
+        for component in std::path::absolute(path)?.components() {
+            match component {
+                Component::ParentDir => {
+                    normal.pop();
+                }
+                Component::CurDir => {}
+                component => normal.push(component),
+            }
+        }
+        Ok(normal)
+    };
+    let (root_absolute, path_absolute) = (absolute(root)?, absolute(path)?);
+    if let Ok(relative) = path_absolute.strip_prefix(&root_absolute) {
+        return Ok(relative.to_path_buf());
+    }
+    // The root may have been resolved from a link, or the path given through one
+    if let (Ok(root), Ok(path)) = (fs::canonicalize(root), fs::canonicalize(path)) {
+        if let Ok(relative) = path.strip_prefix(&root) {
+            return Ok(relative.to_path_buf());
+        }
+    }
+    bail!("{} is not below the scan root {}", path.display(), root.display())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::Args;
+    use clap::Parser;
+
+    fn scanner(root: &Path, extra: &[&str]) -> DirectoryScanner {
+        let mut argv = vec!["diranalyzer", "--quiet"];
+        argv.extend_from_slice(extra);
+        argv.push(root.to_str().unwrap());
+        DirectoryScanner::new(&Args::parse_from(argv)).unwrap()
+    }
+
+    /// node_modules below a hidden directory, logs both matched and below a matched directory
+    fn overlapping_tree() -> (tempfile::TempDir, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("tree");
+        for path in [".config/node_modules/pkg", "web/node_modules/lib", "logs/old", "src"] {
+            fs::create_dir_all(root.join(path)).unwrap();
+        }
+        for (path, size) in [
+            (".config/node_modules/pkg/a.js", 10),
+            ("web/node_modules/lib/b.js", 20),
+            ("web/node_modules/lib/c.log", 40),
+            ("logs/old/app.log", 100),
+            ("logs/today.log", 200),
+            ("src/main.rs", 400),
+            ("src/trace.log", 800),
+        ] {
+            fs::write(root.join(path), vec![0u8; size]).unwrap();
+        }
+        (dir, root)
+    }
+
+    #[tokio::test]
+    async fn test_causes_count_each_entry_once() {
+        let (_dir, root) = overlapping_tree();
+        let results = scanner(&root, &["--exclude", "node_modules/**", "--exclude", "*.log", "--exclude", "/logs"]).scan().await.unwrap();
+
+        assert_eq!(results.total_files, 1);
+        let causes: Vec<_> = results.exclusions.causes.iter().map(|count| (count.cause.to_string(), count.entries, count.size)).collect();
+        assert_eq!(
+            causes,
+            [
+                ("hidden (see --all)".to_string(), 1, 0),
+                ("pattern `node_modules/**`".to_string(), 1, 0),
+                // The log files outside the pruned directories
+                ("pattern `*.log`".to_string(), 1, 800),
+                ("pattern `/logs`".to_string(), 1, 0),
+            ]
+        );
+        // Pattern hits still count every match
+        let hits: Vec<_> = results.exclusions.patterns.iter().map(|p| p.hits).collect();
+        assert_eq!(hits, [1, 1, 1]);
+    }
+
+    #[test]
+    fn test_trace_names_the_first_rule_on_the_way_down() {
+        let (_dir, root) = overlapping_tree();
+        let scanner = scanner(&root, &["--exclude", "*.log", "--exclude", "node_modules/**"]);
+
+        // Hidden comes before the patterns, and a pruned directory stops the trace
+        let trace = scanner.explain_path(&root.join(".config/node_modules/pkg/a.js")).unwrap();
+        assert_eq!(trace.outcome, TraceOutcome::Excluded { path: root.join(".config"), cause: ExclusionCause::Hidden });
+        assert_eq!(trace.steps.len(), 2);
+
+        let trace = scanner.explain_path(&root.join("web/node_modules/lib/c.log")).unwrap();
+        let cause = ExclusionCause::Pattern { pattern: "node_modules/**".to_string() };
+        assert_eq!(trace.outcome, TraceOutcome::Excluded { path: root.join("web/node_modules"), cause });
+
+        let trace = scanner.explain_path(&root.join("logs/old/app.log")).unwrap();
+        let cause = ExclusionCause::Pattern { pattern: "*.log".to_string() };
+        assert_eq!(trace.outcome, TraceOutcome::Excluded { path: root.join("logs/old/app.log"), cause });
+        assert!(trace.steps[..3].iter().all(|step| step.directory && step.cause.is_none()));
+
+        let trace = scanner.explain_path(&root.join("src/main.rs")).unwrap();
+        assert!(trace.is_included());
+        assert_eq!(trace.steps.iter().map(|step| step.path.clone()).collect::<Vec<_>>(), [root.clone(), root.join("src"), root.join("src/main.rs")]);
+    }
+
+    #[test]
+    fn test_trace_of_depth_links_and_outside_paths() {
+        let (dir, root) = overlapping_tree();
+        std::os::unix::fs::symlink(root.join("src"), root.join("linked")).unwrap();
+
+        let shallow = scanner(&root, &["--depth", "1"]);
+        let trace = shallow.explain_path(&root.join("src/main.rs")).unwrap();
+        assert_eq!(trace.outcome, TraceOutcome::BeyondDepth { path: root.join("src/main.rs"), limit: 1 });
+        assert!(scanner(&root, &["--depth", "1", "--auto-depth"]).explain_path(&root.join("src/main.rs")).unwrap().is_included());
+        let trace = scanner(&root, &["--min-depth", "3"]).explain_path(&root.join("src/main.rs")).unwrap();
+        assert_eq!(trace.outcome, TraceOutcome::AboveMinDepth { path: root.join("src/main.rs"), min_depth: 3 });
+
+        let trace = scanner(&root, &[]).explain_path(&root.join("linked/main.rs")).unwrap();
+        assert_eq!(trace.outcome, TraceOutcome::Link { path: root.join("linked") });
+        assert!(scanner(&root, &["--follow-links"]).explain_path(&root.join("linked/main.rs")).unwrap().is_included());
+
+        // Relative spellings resolve against the root; paths elsewhere are refused
+        assert!(scanner(&root, &[]).explain_path(&root.join("web/../src/./main.rs")).unwrap().is_included());
+        let error = scanner(&root, &[]).explain_path(dir.path()).unwrap_err();
+        assert!(error.to_string().contains("is not below the scan root"), "{}", error);
+        assert!(scanner(&root, &[]).explain_path(&root.join("src/missing.rs")).is_err());
+    }
+}
@@ -0,0 +1,591 @@
+//! The filter pipeline deciding which walked entries end up in the results
+//!
+//! Every entry passes through the filters in a fixed order, and the first
+//! filter that rejects it wins; later filters never see it:
+//!
+//! 1. files the run itself writes, such as its export (see [`WriteTargets`])
+//! 2. hidden entries (skipped unless `--show-hidden`)
+//! 3. `--exclude` globs and `--exclude-regex` patterns
+//! 4. `.gitignore` and `.ignore` files, with `--respect-gitignore` (see [`IgnoreFiles`])
+//! 5. `--include` globs, which only files have to match; directories are still walked
+//! 6. the owner filter (`--mine` / `--user`)
+//! 7. the group filter (`--group`)
+//! 8. the date filter (`--modified-before` / `--modified-after` / `--created-after`)
+//! 9. filters added with [`DirectoryScanner::with_filter`](super::DirectoryScanner::with_filter),
+//!    in the order they were added
+//!
+//! Directories are asked [`FileFilter::prune`] before the walk enters them;
+//! files are asked [`FileFilter::include`] after their metadata is read but
+//! before xattrs and extents are measured.
+
+use super::{ExclusionCause, ExclusionCount, ExclusionStats, FileEntry, GroupFilter, OwnerFilter, Ownership, PatternHits};
+use super::ignores::IgnoreFiles;
+use super::modified::{DateFilter, ModifiedFilter, UNDATED_EXAMPLES};
+use crate::utils::glob_to_regex;
+use crate::writes::WriteTargets;
+use anyhow::{bail, Context, Result};
+use regex::Regex;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+
+/// Decides whether scanned files are part of the results
+///
+/// Any `Fn(&FileEntry) -> bool` closure is a filter that keeps the files it
+/// returns `true` for.
+pub trait FileFilter: Send + Sync {
+    /// Whether a file is kept
+    fn include(&self, entry: &FileEntry) -> bool;
+
+    /// Whether a directory is skipped together with everything below it
+    ///
+    /// The scan root is never pruned.
+    fn prune(&self, _directory: &Path) -> bool {
+        false
+    }
+}
+
+impl<F> FileFilter for F
+where
+    F: Fn(&FileEntry) -> bool + Send + Sync,
+{
+    fn include(&self, entry: &FileEntry) -> bool {
+        self(entry)
+    }
+}
+
+/// Entries a stage was the first to reject, and the bytes of the files among them
+///
+/// A pruned directory counts as one entry; what lies below it is never read.
+#[derive(Default)]
+struct Tally {
+    entries: AtomicU64,
+    size: AtomicU64,
+}
+
+impl Tally {
+    /// Record an entry of `size` bytes when `rejected`, passing the verdict on
+    fn count(&self, rejected: bool, size: u64) -> bool {
+        if rejected {
+            self.entries.fetch_add(1, Ordering::Relaxed);
+            self.size.fetch_add(size, Ordering::Relaxed);
+        }
+        rejected
+    }
+
+    /// The counts so far under `cause`, resetting them; `None` when nothing was rejected
+    fn take(&self, cause: ExclusionCause) -> Option<ExclusionCount> {
+        let entries = self.entries.swap(0, Ordering::Relaxed);
+        let size = self.size.swap(0, Ordering::Relaxed);
+        (entries > 0).then_some(ExclusionCount { cause, entries, size })
+    }
+}
+
+/// Skips the exports, journals, and caches the run writes inside the scanned tree
+pub(super) struct OwnOutputsFilter {
+    targets: WriteTargets,
+    tally: Tally,
+}
+
+impl FileFilter for OwnOutputsFilter {
+    fn include(&self, entry: &FileEntry) -> bool {
+        !self.tally.count(self.targets.covers_file(&entry.path), entry.counted_size())
+    }
+
+    fn prune(&self, directory: &Path) -> bool {
+        self.tally.count(self.targets.covers_directory(directory), 0)
+    }
+}
+
+/// Skips entries whose name starts with a dot
+#[derive(Default)]
+pub(super) struct HiddenFilter {
+    tally: Tally,
+}
+
+impl HiddenFilter {
+    pub fn is_hidden(path: &Path) -> bool {
+        path.file_name().is_some_and(|name| name.to_string_lossy().starts_with('.'))
+    }
+}
+
+impl FileFilter for HiddenFilter {
+    fn include(&self, entry: &FileEntry) -> bool {
+        !self.tally.count(Self::is_hidden(&entry.path), entry.counted_size())
+    }
+
+    fn prune(&self, directory: &Path) -> bool {
+        self.tally.count(Self::is_hidden(directory), 0)
+    }
+}
+
+/// One `--exclude` glob or `--exclude-regex` pattern
+///
+/// Globs are matched against the path relative to the scan root, as
+/// `node_modules/**` or `/build` (see [`glob_to_regex`]); a leading `/`
+/// anchors the glob at the root, a trailing `/**` covers the directory itself
+/// so that it is pruned with everything below it. Regexes are matched against
+/// the full path as the walk spells it. Both are case-sensitive.
+pub(crate) struct ExcludePattern {
+    pattern: String,
+    regex: Regex,
+    glob: bool,
+}
+
+impl ExcludePattern {
+    pub fn glob(pattern: &str) -> Result<Self> {
+        Self::option_glob("--exclude", pattern)
+    }
+
+    /// An `--include` glob, matched the same way as an `--exclude` glob
+    pub fn include_glob(pattern: &str) -> Result<Self> {
+        Self::option_glob("--include", pattern)
+    }
+
+    fn option_glob(option: &str, pattern: &str) -> Result<Self> {
+        let trimmed = pattern.trim_end_matches('/');
+        if trimmed.is_empty() {
+            bail!("Invalid {} glob {:?}: it names no path below the scan root", option, pattern);
+        }
+        let regex = match trimmed.strip_suffix("/**") {
+            Some(directory) if !directory.is_empty() => {
+                glob_to_regex(directory).map(|regex| format!("{}(?:/.*)?$", regex.trim_end_matches('$')))
+            }
+            _ => glob_to_regex(trimmed),
+        };
+        let regex = regex
+            .and_then(|regex| Ok(Regex::new(&regex)?))
+            .with_context(|| format!("Invalid {} glob {:?}", option, pattern))?;
+        Ok(Self { pattern: pattern.to_string(), regex, glob: true })
+    }
+
+    pub fn regex(pattern: &str) -> Result<Self> {
+        let regex = Regex::new(pattern).with_context(|| format!("Invalid --exclude-regex pattern {:?}", pattern))?;
+        Ok(Self { pattern: pattern.to_string(), regex, glob: false })
+    }
+
+    /// The pattern as it was given
+    pub fn as_str(&self) -> &str {
+        &self.pattern
+    }
+
+    /// Whether `path`, found below `root`, matches. A glob never matches the root itself.
+    pub fn is_match(&self, root: &Path, path: &Path) -> bool {
+        if !self.glob {
+            return self.regex.is_match(&path.to_string_lossy());
+        }
+        match path.strip_prefix(root) {
+            Ok(relative) if relative.as_os_str().is_empty() => false,
+            Ok(relative) => self.regex.is_match(&format!("/{}", relative.to_string_lossy())),
+            Err(_) => self.regex.is_match(&path.to_string_lossy()),
+        }
+    }
+}
+
+/// An exclude pattern with its hits, and the entries it was the first pattern to match
+struct ExcludeSlot {
+    pattern: ExcludePattern,
+    hits: AtomicU64,
+    first: Tally,
+}
+
+/// Skips entries whose path matches any `--exclude` or `--exclude-regex` pattern
+pub(super) struct ExcludeFilter {
+    root: PathBuf,
+    patterns: Vec<ExcludeSlot>,
+    excluded: AtomicU64,
+}
+
+impl ExcludeFilter {
+    pub fn new(root: PathBuf, patterns: Vec<ExcludePattern>) -> Self {
+        Self {
+            root,
+            patterns: patterns.into_iter().map(|pattern| ExcludeSlot { pattern, hits: AtomicU64::new(0), first: Tally::default() }).collect(),
+            excluded: AtomicU64::new(0),
+        }
+    }
+
+    /// Patterns matching `path`, without recording hits
+    pub fn matching<'a>(&'a self, path: &'a Path) -> impl Iterator<Item = &'a ExcludePattern> {
+        self.patterns
+            .iter()
+            .map(|slot| &slot.pattern)
+            .filter(move |pattern| pattern.is_match(&self.root, path))
+    }
+
+    /// Evaluates every pattern so that an entry matching several counts for each;
+    /// its size goes to the first one
+    fn reject(&self, path: &Path, size: u64) -> bool {
+        let mut excluded = false;
+        for slot in &self.patterns {
+            if slot.pattern.is_match(&self.root, path) {
+                slot.hits.fetch_add(1, Ordering::Relaxed);
+                slot.first.count(!excluded, size);
+                excluded = true;
+            }
+        }
+        if excluded {
+            self.excluded.fetch_add(1, Ordering::Relaxed);
+        }
+        excluded
+    }
+}
+
+impl FileFilter for ExcludeFilter {
+    fn include(&self, entry: &FileEntry) -> bool {
+        !self.reject(&entry.path, entry.counted_size())
+    }
+
+    fn prune(&self, directory: &Path) -> bool {
+        self.reject(directory, 0)
+    }
+}
+
+/// Keeps the files matching at least one `--include` glob, never pruning a directory
+pub(super) struct IncludeStage {
+    root: PathBuf,
+    patterns: Vec<ExcludePattern>,
+    tally: Tally,
+}
+
+impl IncludeStage {
+    fn matches(&self, path: &Path) -> bool {
+        self.patterns.iter().any(|pattern| pattern.is_match(&self.root, path))
+    }
+}
+
+impl FileFilter for IncludeStage {
+    fn include(&self, entry: &FileEntry) -> bool {
+        !self.tally.count(!self.matches(&entry.path), entry.counted_size())
+    }
+}
+
+/// Keeps files owned by one user, tracking how many bytes it saw
+pub(super) struct OwnerStage {
+    filter: OwnerFilter,
+    encountered_size: AtomicU64,
+    tally: Tally,
+}
+
+impl FileFilter for OwnerStage {
+    fn include(&self, entry: &FileEntry) -> bool {
+        self.encountered_size.fetch_add(entry.counted_size(), Ordering::Relaxed);
+        !self.tally.count(!self.filter.matches(entry.uid), entry.counted_size())
+    }
+}
+
+/// Keeps files of one group
+pub(super) struct GroupStage {
+    filter: GroupFilter,
+    tally: Tally,
+}
+
+impl FileFilter for GroupStage {
+    fn include(&self, entry: &FileEntry) -> bool {
+        !self.tally.count(!self.filter.matches(entry.gid), entry.counted_size())
+    }
+}
+
+/// Keeps files dated within the bounds, counting those without a time the bounds need
+pub(super) struct ModifiedStage {
+    filter: ModifiedFilter,
+    undated: AtomicU64,
+    undated_examples: Mutex<Vec<PathBuf>>,
+    tally: Tally,
+}
+
+impl FileFilter for ModifiedStage {
+    fn include(&self, entry: &FileEntry) -> bool {
+        let matches = self.filter.admits(entry);
+        if matches.is_none() {
+            self.undated.fetch_add(1, Ordering::Relaxed);
+            let mut examples = self.undated_examples.lock().unwrap();
+            if examples.len() < UNDATED_EXAMPLES {
+                examples.push(entry.path.clone());
+            }
+        }
+        !self.tally.count(matches != Some(true), entry.counted_size())
+    }
+}
+
+/// A filter added through the library API, counting what it rejects
+struct AddedFilter {
+    filter: Box<dyn FileFilter>,
+    tally: Tally,
+}
+
+impl FileFilter for AddedFilter {
+    fn include(&self, entry: &FileEntry) -> bool {
+        !self.tally.count(!self.filter.include(entry), entry.counted_size())
+    }
+
+    fn prune(&self, directory: &Path) -> bool {
+        self.tally.count(self.filter.prune(directory), 0)
+    }
+}
+
+/// The built-in filters followed by the added ones
+pub(super) struct FilterPipeline {
+    own_outputs: Option<OwnOutputsFilter>,
+    hidden: Option<HiddenFilter>,
+    exclude: ExcludeFilter,
+    ignore_files: Option<IgnoreFiles>,
+    include: Option<IncludeStage>,
+    owner: Option<OwnerStage>,
+    group: Option<GroupStage>,
+    modified: Option<ModifiedStage>,
+    added: Vec<AddedFilter>,
+}
+
+impl FilterPipeline {
+    pub fn new(show_hidden: bool, exclude: ExcludeFilter, owner: Option<OwnerFilter>) -> Self {
+        Self {
+            own_outputs: None,
+            hidden: (!show_hidden).then(HiddenFilter::default),
+            exclude,
+            ignore_files: None,
+            include: None,
+            owner: owner.map(|filter| OwnerStage { filter, encountered_size: AtomicU64::new(0), tally: Tally::default() }),
+            group: None,
+            modified: None,
+            added: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, filter: Box<dyn FileFilter>) {
+        self.added.push(AddedFilter { filter, tally: Tally::default() });
+    }
+
+    /// Leave out what the run writes at `targets`, given as the walk spells the paths
+    pub fn skip_own_outputs(&mut self, targets: WriteTargets) {
+        self.own_outputs = (!targets.is_empty()).then(|| OwnOutputsFilter { targets, tally: Tally::default() });
+    }
+
+    /// Leave out what the tree's ignore files ignore
+    pub fn respect_ignore_files(&mut self, files: IgnoreFiles) {
+        self.ignore_files = Some(files);
+    }
+
+    /// Keep only files below `root` matching one of `patterns`, when there are any
+    pub fn include_only(&mut self, root: PathBuf, patterns: Vec<ExcludePattern>) {
+        self.include = (!patterns.is_empty()).then(|| IncludeStage { root, patterns, tally: Tally::default() });
+    }
+
+    /// Keep only files of `filter`'s group
+    pub fn filter_group(&mut self, filter: GroupFilter) {
+        self.group = Some(GroupStage { filter, tally: Tally::default() });
+    }
+
+    /// Keep only files modified within `filter`'s bounds
+    pub fn filter_modified(&mut self, filter: ModifiedFilter) {
+        self.modified = Some(ModifiedStage { filter, undated: AtomicU64::new(0), undated_examples: Mutex::default(), tally: Tally::default() });
+    }
+
+    pub fn exclude(&self) -> &ExcludeFilter {
+        &self.exclude
+    }
+
+    /// Filters in evaluation order
+    fn stages(&self) -> impl Iterator<Item = &dyn FileFilter> {
+        let own_outputs = self.own_outputs.as_ref().map(|filter| filter as &dyn FileFilter);
+        let hidden = self.hidden.as_ref().map(|filter| filter as &dyn FileFilter);
+        let ignore_files = self.ignore_files.as_ref().map(|files| files as &dyn FileFilter);
+        let include = self.include.as_ref().map(|stage| stage as &dyn FileFilter);
+        let owner = self.owner.as_ref().map(|stage| stage as &dyn FileFilter);
+        let group = self.group.as_ref().map(|stage| stage as &dyn FileFilter);
+        let modified = self.modified.as_ref().map(|stage| stage as &dyn FileFilter);
+        own_outputs
+            .into_iter()
+            .chain(hidden)
+            .chain(std::iter::once(&self.exclude as &dyn FileFilter))
+            .chain(ignore_files)
+            .chain(include)
+            .chain(owner)
+            .chain(group)
+            .chain(modified)
+            .chain(self.added.iter().map(|filter| filter as &dyn FileFilter))
+    }
+
+    pub fn include(&self, entry: &FileEntry) -> bool {
+        self.stages().all(|filter| filter.include(entry))
+    }
+
+    pub fn prune(&self, directory: &Path) -> bool {
+        self.stages().any(|filter| filter.prune(directory))
+    }
+
+    /// Whether the hidden rule, an exclude pattern, or an ignore file rejects `path`, without recording hits
+    pub fn excludes_path(&self, path: &Path) -> bool {
+        (self.hidden.is_some() && HiddenFilter::is_hidden(path))
+            || self.exclude.matching(path).next().is_some()
+            || self.ignore_files.as_ref().is_some_and(|files| files.rule(path, path.is_dir()).is_some())
+    }
+
+    /// The first stage that rejects `path`, without recording anything
+    ///
+    /// Directories are asked as [`prune`](Self::prune) would ask them; a file
+    /// goes through every stage with its `entry`.
+    pub fn explain(&self, path: &Path, entry: Option<&FileEntry>) -> Option<ExclusionCause> {
+        if let Some(filter) = &self.own_outputs {
+            let covered = match entry {
+                Some(_) => filter.targets.covers_file(path),
+                None => filter.targets.covers_directory(path),
+            };
+            if covered {
+                return Some(ExclusionCause::OwnOutput);
+            }
+        }
+        if self.hidden.is_some() && HiddenFilter::is_hidden(path) {
+            return Some(ExclusionCause::Hidden);
+        }
+        if let Some(pattern) = self.exclude.matching(path).next() {
+            return Some(ExclusionCause::Pattern { pattern: pattern.as_str().to_string() });
+        }
+        if let Some(rule) = self.ignore_files.as_ref().and_then(|files| files.rule(path, entry.is_none())) {
+            return Some(rule.cause());
+        }
+        if let (Some(stage), Some(_)) = (&self.include, entry) {
+            if !stage.matches(path) {
+                return Some(ExclusionCause::NotIncluded);
+            }
+        }
+        if let (Some(stage), Some(entry)) = (&self.owner, entry) {
+            if !stage.filter.matches(entry.uid) {
+                return Some(ExclusionCause::Owner);
+            }
+        }
+        if let (Some(stage), Some(entry)) = (&self.group, entry) {
+            if !stage.filter.matches(entry.gid) {
+                return Some(ExclusionCause::Group);
+            }
+        }
+        if let (Some(stage), Some(entry)) = (&self.modified, entry) {
+            if stage.filter.admits(entry) != Some(true) {
+                return Some(ExclusionCause::Modified);
+            }
+        }
+        let rejected = |added: &AddedFilter| match entry {
+            Some(entry) => !added.filter.include(entry),
+            None => added.filter.prune(path),
+        };
+        self.added.iter().position(rejected).map(|index| ExclusionCause::Filter { index: index + 1 })
+    }
+
+    /// Move the hit counts of the hidden rule and exclude patterns, and what each stage rejected, into `stats`
+    pub fn take_exclusions(&self, stats: &mut ExclusionStats) {
+        let own_outputs = self.own_outputs.as_ref().and_then(|filter| filter.tally.take(ExclusionCause::OwnOutput));
+        let hidden = self.hidden.as_ref().and_then(|filter| filter.tally.take(ExclusionCause::Hidden));
+        stats.own_outputs = own_outputs.as_ref().map_or(0, |count| count.entries);
+        stats.hidden = hidden.as_ref().map_or(0, |count| count.entries);
+        let ignored = self.ignore_files.as_ref().map(IgnoreFiles::take);
+        stats.ignored = ignored.as_ref().map(|counts| counts.iter().map(|count| count.entries).sum());
+        stats.excluded = stats.hidden + self.exclude.excluded.swap(0, Ordering::Relaxed) + stats.ignored.unwrap_or(0);
+        stats.patterns = self
+            .exclude
+            .patterns
+            .iter()
+            .map(|slot| PatternHits {
+                pattern: slot.pattern.as_str().to_string(),
+                hits: slot.hits.swap(0, Ordering::Relaxed),
+            })
+            .collect();
+
+        let patterns = self.exclude.patterns.iter().map(|slot| slot.first.take(ExclusionCause::Pattern { pattern: slot.pattern.as_str().to_string() }));
+        let include = self.include.as_ref().and_then(|stage| stage.tally.take(ExclusionCause::NotIncluded));
+        let owner = self.owner.as_ref().and_then(|stage| stage.tally.take(ExclusionCause::Owner));
+        let group = self.group.as_ref().and_then(|stage| stage.tally.take(ExclusionCause::Group));
+        let modified = self.modified.as_ref().and_then(|stage| stage.tally.take(ExclusionCause::Modified));
+        let added = self.added.iter().enumerate().map(|(index, added)| added.tally.take(ExclusionCause::Filter { index: index + 1 }));
+        stats.causes = [own_outputs, hidden]
+            .into_iter()
+            .chain(patterns)
+            .flatten()
+            .chain(ignored.into_iter().flatten())
+            .chain(include)
+            .chain(owner)
+            .chain(group)
+            .chain(modified)
+            .chain(added.flatten())
+            .collect();
+    }
+
+    /// The bounds of the modification-time filter and the undated files of the last scan, if filtering by it
+    pub fn take_date_filter(&self) -> Option<DateFilter> {
+        self.modified.as_ref().map(|stage| DateFilter {
+            before: stage.filter.before,
+            after: stage.filter.after,
+            created_after: stage.filter.created_after,
+            undated_files: stage.undated.swap(0, Ordering::Relaxed),
+            undated_examples: std::mem::take(&mut *stage.undated_examples.lock().unwrap()),
+        })
+    }
+
+    /// Ownership summary of the last scan, if filtering by owner
+    pub fn take_ownership(&self, matched_size: u64) -> Option<Ownership> {
+        self.owner.as_ref().map(|stage| Ownership {
+            user: stage.filter.user.clone(),
+            uid: stage.filter.uid,
+            matched_size,
+            encountered_size: stage.encountered_size.swap(0, Ordering::Relaxed),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glob_matches(glob: &str, relative: &str) -> bool {
+        ExcludePattern::glob(glob).unwrap().is_match(Path::new("/scan/root"), &Path::new("/scan/root").join(relative))
+    }
+
+    #[test]
+    fn test_glob_matches_directory_components() {
+        assert!(glob_matches("node_modules", "node_modules"));
+        assert!(glob_matches("node_modules", "web/node_modules"));
+        assert!(!glob_matches("node_modules", "web/node_modules_old"));
+        assert!(glob_matches("node_modules/**", "node_modules"));
+        assert!(glob_matches("node_modules/**", "web/node_modules/lib/a.js"));
+        assert!(glob_matches("src/*.rs", "src/main.rs"));
+        assert!(!glob_matches("src/*.rs", "src/bin/main.rs"));
+        assert!(glob_matches("src/**/*.rs", "src/bin/main.rs"));
+        assert!(glob_matches("cache/", "cache"));
+    }
+
+    #[test]
+    fn test_leading_slash_anchors_glob_at_the_root() {
+        assert!(glob_matches("/build", "build"));
+        assert!(!glob_matches("/build", "src/build"));
+        assert!(glob_matches("build", "src/build"));
+        assert!(glob_matches("*.iso", "images/disk.iso"));
+        assert!(!glob_matches("/*.iso", "images/disk.iso"));
+        // The part of the path above the root never takes part in a match
+        assert!(!glob_matches("scan/**", "data.bin"));
+        assert!(!glob_matches("*", ""));
+    }
+
+    #[test]
+    fn test_patterns_are_case_sensitive() {
+        assert!(glob_matches("*.iso", "disk.iso"));
+        assert!(!glob_matches("*.iso", "DISK.ISO"));
+        let regex = ExcludePattern::regex(r"(?i)\.iso$").unwrap();
+        assert!(regex.is_match(Path::new("/scan/root"), Path::new("/scan/root/DISK.ISO")));
+    }
+
+    #[test]
+    fn test_regex_sees_the_full_path() {
+        let regex = ExcludePattern::regex("^/scan/").unwrap();
+        assert!(regex.is_match(Path::new("/scan/root"), Path::new("/scan/root")));
+        assert!(!ExcludePattern::regex("^root/").unwrap().is_match(Path::new("/scan/root"), Path::new("/scan/root/x")));
+    }
+
+    #[test]
+    fn test_invalid_patterns_name_themselves() {
+        let error = ExcludePattern::regex("a(").err().unwrap().to_string();
+        assert_eq!(error, r#"Invalid --exclude-regex pattern "a(""#);
+        let error = ExcludePattern::glob("/").err().unwrap().to_string();
+        assert!(error.starts_with(r#"Invalid --exclude glob "/""#), "{}", error);
+        let error = ExcludePattern::include_glob("/").err().unwrap().to_string();
+        assert!(error.starts_with(r#"Invalid --include glob "/""#), "{}", error);
+    }
+}
@@ -0,0 +1,196 @@
+//! `.gitignore` and `.ignore` files, for `--respect-gitignore`
+//!
+//! Each directory's ignore files are read the first time the walk asks about
+//! an entry in it, and apply to everything below that directory, the way git
+//! applies them: the deepest file with a matching rule decides, `!` rules
+//! re-include, and an ignored directory is skipped with everything it holds.
+//! A scan root inside a git work tree also honors the ignore files between
+//! the root and the top of the work tree, and the work tree's
+//! `.git/info/exclude`. Rules that no file matches fall through to the global
+//! excludes file (`core.excludesFile`), unless `--no-global-gitignore`.
+
+use super::filter::FileFilter;
+use super::{ExclusionCause, ExclusionCount, FileEntry};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Ignore files read in each directory, later ones overriding earlier ones
+const IGNORE_FILES: [&str; 2] = [".gitignore", ".ignore"];
+
+/// The rule that ignores an entry: a line of an ignore file
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub(super) struct IgnoreRule {
+    pub file: PathBuf,
+    pub pattern: String,
+}
+
+impl IgnoreRule {
+    pub fn cause(self) -> ExclusionCause {
+        ExclusionCause::IgnoreRule { file: self.file, pattern: self.pattern }
+    }
+}
+
+/// Skips entries that the ignore files of the tree, and the global excludes, ignore
+pub(super) struct IgnoreFiles {
+    /// The root as the walk spells it, and made absolute to match against the files
+    root: PathBuf,
+    absolute_root: PathBuf,
+    /// The top of the git work tree holding the root, or the root outside of one
+    top: PathBuf,
+    global: Gitignore,
+    /// Rules per directory, `None` where it has no ignore files
+    loaded: Mutex<HashMap<PathBuf, Option<Arc<Gitignore>>>>,
+    /// Entries and bytes each rule was the one to ignore
+    counts: Mutex<BTreeMap<IgnoreRule, (u64, u64)>>,
+}
+
+impl IgnoreFiles {
+    pub fn new(root: &Path, global: bool) -> Self {
+        let absolute_root = std::path::absolute(root).unwrap_or_else(|_| root.to_path_buf());
+        let top = absolute_root
+            .ancestors()
+            .find(|ancestor| ancestor.join(".git").exists())
+            .unwrap_or(&absolute_root)
+            .to_path_buf();
+        let global = if global { GitignoreBuilder::new(&top).build_global().0 } else { Gitignore::empty() };
+        Self {
+            root: root.to_path_buf(),
+            absolute_root,
+            top,
+            global,
+            loaded: Mutex::new(HashMap::new()),
+            counts: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    /// The rule that ignores `path`, if any; the scan root itself is never ignored
+    pub fn rule(&self, path: &Path, is_dir: bool) -> Option<IgnoreRule> {
+        let path = match path.strip_prefix(&self.root) {
+            Ok(relative) if relative.as_os_str().is_empty() => return None,
+            Ok(relative) => self.absolute_root.join(relative),
+            Err(_) => path.to_path_buf(),
+        };
+        let decided = path
+            .ancestors()
+            .skip(1)
+            .take_while(|directory| directory.starts_with(&self.top))
+            .filter_map(|directory| self.rules_in(directory))
+            .map(|rules| rules.matched(&path, is_dir).map(ignored_by))
+            .find(|matched| !matched.is_none());
+        let matched = decided.unwrap_or_else(|| self.global.matched(&path, is_dir).map(ignored_by));
+        match matched {
+            Match::Ignore(rule) => rule,
+            Match::None | Match::Whitelist(_) => None,
+        }
+    }
+
+    /// The rules of `directory`'s ignore files, read once
+    fn rules_in(&self, directory: &Path) -> Option<Arc<Gitignore>> {
+        let mut loaded = self.loaded.lock().unwrap();
+        if let Some(rules) = loaded.get(directory) {
+            return rules.clone();
+        }
+        let mut builder = GitignoreBuilder::new(directory);
+        let mut found = false;
+        let exclude = directory.join(".git/info/exclude");
+        let files = (directory == self.top).then_some(exclude).into_iter().chain(IGNORE_FILES.iter().map(|name| directory.join(name)));
+        for file in files.filter(|file| file.is_file()) {
+            // A malformed line is skipped; the rest of the file still applies
+            builder.add(file);
+            found = true;
+        }
+        let rules = found.then(|| builder.build().ok()).flatten().filter(|rules| !rules.is_empty()).map(Arc::new);
+        loaded.insert(directory.to_path_buf(), rules.clone());
+        rules
+    }
+
+    fn count(&self, path: &Path, is_dir: bool, size: u64) -> bool {
+        let Some(rule) = self.rule(path, is_dir) else {
+            return false;
+        };
+        let mut counts = self.counts.lock().unwrap();
+        let (entries, bytes) = counts.entry(rule).or_default();
+        *entries += 1;
+        *bytes += size;
+        true
+    }
+
+    /// What each rule ignored so far, resetting the counts
+    pub fn take(&self) -> Vec<ExclusionCount> {
+        std::mem::take(&mut *self.counts.lock().unwrap())
+            .into_iter()
+            .map(|(rule, (entries, size))| ExclusionCount { cause: rule.cause(), entries, size })
+            .collect()
+    }
+}
+
+/// The rule behind a match, or `None` for rules that did not come from a file
+fn ignored_by(glob: &ignore::gitignore::Glob) -> Option<IgnoreRule> {
+    Some(IgnoreRule { file: glob.from()?.to_path_buf(), pattern: glob.original().to_string() })
+}
+
+impl FileFilter for IgnoreFiles {
+    fn include(&self, entry: &FileEntry) -> bool {
+        !self.count(&entry.path, false, entry.counted_size())
+    }
+
+    fn prune(&self, directory: &Path) -> bool {
+        self.count(directory, true, 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn ignored(files: &IgnoreFiles, path: &Path, is_dir: bool) -> Option<String> {
+        files.rule(path, is_dir).map(|rule| format!("{} in {}", rule.pattern, rule.file.file_name().unwrap().to_string_lossy()))
+    }
+
+    #[test]
+    fn test_nested_ignore_files_and_negation() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::create_dir_all(root.join("app/keep")).unwrap();
+        fs::write(root.join(".gitignore"), "target/\n*.log\n").unwrap();
+        fs::write(root.join("app/.gitignore"), "!important.log\nkeep/*.tmp\n").unwrap();
+        fs::write(root.join("app/.ignore"), "secret.txt\n").unwrap();
+        let files = IgnoreFiles::new(root, false);
+
+        assert_eq!(ignored(&files, &root.join("target"), true).as_deref(), Some("target/ in .gitignore"));
+        // A directory-only rule leaves a file of that name alone
+        assert_eq!(ignored(&files, &root.join("app/target"), false), None);
+        assert_eq!(ignored(&files, &root.join("debug.log"), false).as_deref(), Some("*.log in .gitignore"));
+        assert_eq!(ignored(&files, &root.join("app/debug.log"), false).as_deref(), Some("*.log in .gitignore"));
+        // The deeper file re-includes what the upper one ignores
+        assert_eq!(ignored(&files, &root.join("app/important.log"), false), None);
+        assert_eq!(ignored(&files, &root.join("app/keep/a.tmp"), false).as_deref(), Some("keep/*.tmp in .gitignore"));
+        assert_eq!(ignored(&files, &root.join("app/secret.txt"), false).as_deref(), Some("secret.txt in .ignore"));
+        assert_eq!(ignored(&files, root, true), None);
+    }
+
+    #[test]
+    fn test_work_tree_above_the_root_applies() {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = dir.path();
+        fs::create_dir_all(repo.join(".git/info")).unwrap();
+        fs::create_dir_all(repo.join("crates/core/target")).unwrap();
+        fs::write(repo.join(".gitignore"), "target/\n").unwrap();
+        fs::write(repo.join(".git/info/exclude"), "*.swp\n").unwrap();
+
+        let root = repo.join("crates");
+        let files = IgnoreFiles::new(&root, false);
+        assert_eq!(ignored(&files, &root.join("core/target"), true).as_deref(), Some("target/ in .gitignore"));
+        assert_eq!(ignored(&files, &root.join("core/lib.rs.swp"), false).as_deref(), Some("*.swp in exclude"));
+        assert_eq!(ignored(&files, &root.join("core/lib.rs"), false), None);
+
+        // Outside a work tree, files above the root do not count
+        fs::remove_dir_all(repo.join(".git")).unwrap();
+        let files = IgnoreFiles::new(&root, false);
+        assert_eq!(ignored(&files, &root.join("core/target"), true), None);
+    }
+}
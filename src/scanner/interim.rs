@@ -0,0 +1,266 @@
+//! Running totals kept while the walk is in progress, for interim reports
+//!
+//! Long scans print a short report every `--interim-every` and on SIGUSR1:
+//! files and bytes so far, the largest directories on the first levels below
+//! the root, the largest files, and the error count. The totals grow as entries
+//! arrive, so a snapshot needs no pass over the collected files, and every
+//! snapshot is a lower bound of the final results.
+
+use super::FileEntry;
+use crate::utils::{format_count, format_duration};
+use humansize::{format_size, DECIMAL};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Levels below the root whose directories are tracked as largest-directory candidates
+pub const CANDIDATE_DEPTH: usize = 2;
+
+/// Entries listed per kind in an interim report
+const LISTED: usize = 5;
+
+/// Entries walked between looks at the clock
+const CLOCK_CHECK_INTERVAL: u64 = 64;
+
+/// Set by SIGUSR1, cleared by the report it asks for
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+
+/// Files, bytes, and errors seen so far, with the largest candidates
+#[derive(Default)]
+struct RunningTotals {
+    files: u64,
+    bytes: u64,
+    errors: u64,
+    /// Sizes so far of the directories up to [`CANDIDATE_DEPTH`] levels below the root
+    directories: HashMap<PathBuf, u64>,
+    /// The largest files so far, smallest on top so it is the one evicted
+    largest_files: BinaryHeap<Reverse<(u64, PathBuf)>>,
+}
+
+impl RunningTotals {
+    fn add_file(&mut self, file: &FileEntry) {
+        let size = file.counted_size();
+        self.files += 1;
+        self.bytes += size;
+
+        // A file at depth d has ancestors at depths 1..d below the root
+        for depth in 1..file.depth.min(CANDIDATE_DEPTH + 1) {
+            let Some(ancestor) = file.path.ancestors().nth(file.depth - depth) else { continue };
+            match self.directories.get_mut(ancestor) {
+                Some(total) => *total += size,
+                None => {
+                    self.directories.insert(ancestor.to_path_buf(), size);
+                }
+            }
+        }
+
+        if self.largest_files.len() < LISTED {
+            self.largest_files.push(Reverse((size, file.path.clone())));
+        } else if self.largest_files.peek().is_some_and(|Reverse((smallest, _))| size > *smallest) {
+            self.largest_files.pop();
+            self.largest_files.push(Reverse((size, file.path.clone())));
+        }
+    }
+
+    fn snapshot(&self, elapsed: Duration) -> InterimSnapshot {
+        let mut directories: Vec<(PathBuf, u64)> =
+            self.directories.iter().map(|(path, size)| (path.clone(), *size)).collect();
+        directories.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        directories.truncate(LISTED);
+
+        let mut largest_files: Vec<(PathBuf, u64)> =
+            self.largest_files.iter().map(|Reverse((size, path))| (path.clone(), *size)).collect();
+        largest_files.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+        InterimSnapshot {
+            elapsed,
+            files: self.files,
+            bytes: self.bytes,
+            errors: self.errors,
+            directories,
+            largest_files,
+        }
+    }
+}
+
+/// What the scan has found so far
+#[derive(Debug, Clone, PartialEq)]
+pub struct InterimSnapshot {
+    pub elapsed: Duration,
+    pub files: u64,
+    pub bytes: u64,
+    pub errors: u64,
+    /// Largest directories so far among the first [`CANDIDATE_DEPTH`] levels, largest first
+    pub directories: Vec<(PathBuf, u64)>,
+    /// Largest files so far, largest first
+    pub largest_files: Vec<(PathBuf, u64)>,
+}
+
+impl InterimSnapshot {
+    /// The interim report as printed
+    pub fn lines(&self) -> Vec<String> {
+        let mut lines = vec![format!(
+            "⏱  Interim after {}: {} files, {}, {} errors",
+            format_duration(self.elapsed),
+            format_count(self.files),
+            format_size(self.bytes, DECIMAL),
+            format_count(self.errors)
+        )];
+        let listing = |entries: &[(PathBuf, u64)]| {
+            entries
+                .iter()
+                .map(|(path, size)| format!("{} {}", path.display(), format_size(*size, DECIMAL)))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        if !self.directories.is_empty() {
+            lines.push(format!("   Largest directories so far: {}", listing(&self.directories)));
+        }
+        if !self.largest_files.is_empty() {
+            lines.push(format!("   Largest files so far: {}", listing(&self.largest_files)));
+        }
+        lines
+    }
+}
+
+/// Receives each interim snapshot as it is taken
+pub type InterimSink = Box<dyn Fn(&InterimSnapshot) + Send + Sync>;
+
+/// Running totals of a scan with the schedule of its interim reports
+pub(super) struct Interim {
+    totals: Mutex<RunningTotals>,
+    every: Option<Duration>,
+    started: Instant,
+    next: Mutex<(u64, Instant)>,
+    sink: InterimSink,
+}
+
+impl Interim {
+    /// Report every `every`, if set, and whenever SIGUSR1 arrives
+    pub fn new(every: Option<Duration>, sink: InterimSink) -> Self {
+        let started = Instant::now();
+        Self {
+            totals: Mutex::default(),
+            every,
+            started,
+            next: Mutex::new((0, started + every.unwrap_or_default())),
+            sink,
+        }
+    }
+
+    pub fn add_file(&self, file: &FileEntry) {
+        self.totals.lock().unwrap_or_else(|e| e.into_inner()).add_file(file);
+    }
+
+    pub fn add_error(&self) {
+        self.totals.lock().unwrap_or_else(|e| e.into_inner()).errors += 1;
+    }
+
+    /// Called once per walked entry; hands a snapshot to the sink when a report is due
+    pub fn tick(&self) {
+        if !self.due() {
+            return;
+        }
+        (self.sink)(&self.snapshot());
+    }
+
+    fn due(&self) -> bool {
+        if REQUESTED.swap(false, Ordering::Relaxed) {
+            return true;
+        }
+        let Some(every) = self.every else {
+            return false;
+        };
+        let mut next = self.next.lock().unwrap_or_else(|e| e.into_inner());
+        let (entries, deadline) = &mut *next;
+        *entries += 1;
+        if !entries.is_multiple_of(CLOCK_CHECK_INTERVAL) {
+            return false;
+        }
+        let now = Instant::now();
+        if now < *deadline {
+            return false;
+        }
+        *deadline = now + every;
+        true
+    }
+
+    /// Totals so far, taken under the lock the walk updates them with
+    pub fn snapshot(&self) -> InterimSnapshot {
+        self.totals.lock().unwrap_or_else(|e| e.into_inner()).snapshot(self.started.elapsed())
+    }
+}
+
+/// Print an interim report on SIGUSR1 from now on
+pub(super) fn install_signal_handler() {
+    #[cfg(unix)]
+    {
+        static INSTALLED: std::sync::Once = std::sync::Once::new();
+        INSTALLED.call_once(|| {
+            extern "C" fn request_interim(_signal: libc::c_int) {
+                REQUESTED.store(true, Ordering::Relaxed);
+            }
+            // SAFETY: the handler only stores to an atomic, which is async-signal-safe
+            unsafe {
+                libc::signal(libc::SIGUSR1, request_interim as extern "C" fn(libc::c_int) as libc::sighandler_t);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(path: &str, size: u64) -> FileEntry {
+        FileEntry {
+            path: PathBuf::from(path),
+            size,
+            depth: path.matches('/').count() - 1,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_candidates_are_the_first_levels() {
+        let mut totals = RunningTotals::default();
+        for (path, size) in [("/r/top.bin", 5), ("/r/a/x.bin", 10), ("/r/a/b/y.bin", 20), ("/r/a/b/c/z.bin", 40), ("/r/d/w.bin", 1)] {
+            totals.add_file(&file(path, size));
+        }
+
+        let snapshot = totals.snapshot(Duration::ZERO);
+        assert_eq!((snapshot.files, snapshot.bytes), (5, 76));
+        // "/r/a/b/c" is deeper than the candidates; its bytes count toward its ancestors only
+        assert_eq!(
+            snapshot.directories,
+            [(PathBuf::from("/r/a"), 70), (PathBuf::from("/r/a/b"), 60), (PathBuf::from("/r/d"), 1)]
+        );
+        assert_eq!(snapshot.largest_files[0], (PathBuf::from("/r/a/b/c/z.bin"), 40));
+    }
+
+    #[test]
+    fn test_largest_files_keep_the_top() {
+        let mut totals = RunningTotals::default();
+        for size in [3, 9, 1, 7, 5, 8, 2, 6] {
+            totals.add_file(&file(&format!("/r/{}.bin", size), size));
+        }
+        let sizes: Vec<u64> = totals.snapshot(Duration::ZERO).largest_files.iter().map(|(_, size)| *size).collect();
+        assert_eq!(sizes, [9, 8, 7, 6, 5]);
+    }
+
+    #[test]
+    fn test_report_lines() {
+        let mut totals = RunningTotals::default();
+        totals.add_file(&file("/r/a/x.bin", 2_000_000));
+        totals.errors = 3;
+        let lines = totals.snapshot(Duration::from_secs(3_725)).lines();
+        assert_eq!(lines, [
+            "⏱  Interim after 1h 2m 5s: 1 files, 2 MB, 3 errors",
+            "   Largest directories so far: /r/a 2 MB",
+            "   Largest files so far: /r/a/x.bin 2 MB",
+        ]);
+    }
+}
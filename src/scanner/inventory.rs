@@ -0,0 +1,598 @@
+//! S3 inventory listings as a file source
+//!
+//! `--source s3-inventory <listing.csv>` analyzes a bucket from the CSV an S3
+//! inventory report delivers, or an ORC or Parquet inventory converted to
+//! CSV, without any network access. Objects become files under
+//! `s3://<bucket>` and the slashes of their keys make the directories; a key
+//! ending in a slash, as the console creates for an empty folder, is a
+//! directory of its own.
+//!
+//! A header row names the columns, using the names of the manifest's
+//! `fileSchema` (`Bucket, Key, Size, LastModifiedDate, ETag, ...`). Inventory
+//! data files have none, and their columns are then taken to be `Bucket, Key,
+//! Size, LastModifiedDate, ETag`, the order an inventory with those fields
+//! writes them in. Keys are URL-decoded. Noncurrent versions and delete
+//! markers are skipped; rows that cannot be read are reported as scan errors.
+//!
+//! Objects carry their etag, which duplicate detection groups by instead of
+//! reading contents it cannot reach.
+
+use super::cap::Budget;
+use super::filter::FilterPipeline;
+use super::{aggregate, DepthBoundary, ErrorLog, ErrorType, ExclusionStats, FileEntry, OwnerFilter, ScanCaps, ScanError};
+use super::{Coverage, DirectoryEntry, ExcludePattern, FileFilter, FileSource, ScanResults, DEFAULT_OWNER_LIMIT};
+use crate::cli::Args;
+use crate::interrupt::Cancellation;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// Columns of a listing without a header row
+const DEFAULT_SCHEMA: [&str; 5] = ["Bucket", "Key", "Size", "LastModifiedDate", "ETag"];
+
+/// The objects of an S3 inventory listing, read once and listed like a walked tree
+pub struct InventorySource {
+    /// `s3://<bucket>`
+    root: PathBuf,
+    /// Current objects in listing order
+    objects: Vec<FileEntry>,
+    /// The root and every key prefix, parents before their children
+    directories: BTreeSet<PathBuf>,
+    /// Rows that could not be read, reported with the first listing
+    unreadable: Vec<ScanError>,
+    max_depth: Option<usize>,
+    min_depth: usize,
+    filters: FilterPipeline,
+    budget: Budget,
+    owner_limit: usize,
+    errors_full: bool,
+}
+
+/// Positions of the columns a listing is read from
+struct Schema {
+    bucket: usize,
+    key: usize,
+    size: Option<usize>,
+    modified: Option<usize>,
+    etag: Option<usize>,
+    is_latest: Option<usize>,
+    is_delete_marker: Option<usize>,
+}
+
+/// One current object of the listing
+struct Object {
+    bucket: String,
+    key: String,
+    size: u64,
+    modified: Option<DateTime<Utc>>,
+    etag: Option<String>,
+}
+
+impl InventorySource {
+    /// Read the listing at `args.path`, applying the run's depth, filters, and caps when listing
+    pub fn open(args: &Args) -> Result<Self> {
+        if OwnerFilter::from_args(args)?.is_some() {
+            anyhow::bail!("--mine and --user do not apply to an S3 inventory: objects have no owning uid");
+        }
+        if args.group.is_some() {
+            anyhow::bail!("--group does not apply to an S3 inventory: objects have no owning gid");
+        }
+        if args.security_audit {
+            anyhow::bail!("--security-audit does not apply to an S3 inventory: objects have no permissions");
+        }
+        let listing = args.path.clone();
+        let mut reader = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .from_path(&listing)
+            .with_context(|| format!("Failed to open S3 inventory listing: {}", listing.display()))?;
+
+        let mut schema = None;
+        let mut rows = Vec::new();
+        let mut unreadable = Vec::new();
+        for (index, record) in reader.records().enumerate() {
+            let line = index + 1;
+            let record = record.with_context(|| format!("Failed to read S3 inventory listing: {}", listing.display()))?;
+            let schema = match &schema {
+                Some(schema) => schema,
+                None => {
+                    let header = Schema::from_header(&record)?;
+                    let is_header = header.is_some();
+                    let schema = schema.insert(header.unwrap_or_else(Schema::default_order));
+                    if is_header {
+                        continue;
+                    }
+                    schema
+                }
+            };
+            match schema.read(&record) {
+                Ok(Some(object)) => rows.push(object),
+                Ok(None) => {}
+                Err(message) => unreadable.push(ScanError {
+                    path: listing.clone(),
+                    error: format!("line {}: {}", line, message),
+                    error_type: ErrorType::Other,
+                    errno: None,
+                }),
+            }
+        }
+
+        let Some(bucket) = rows.first().map(|object| object.bucket.clone()) else {
+            anyhow::bail!("S3 inventory listing {} lists no current objects", listing.display());
+        };
+        let root = PathBuf::from(format!("s3://{}", bucket));
+        let mut objects = Vec::with_capacity(rows.len());
+        let mut directories = BTreeSet::from([root.clone()]);
+        for object in rows {
+            let path = object_path(&root, &object.key);
+            if object.bucket != bucket {
+                unreadable.push(ScanError {
+                    path,
+                    error: format!("object of bucket {} in a listing of bucket {}", object.bucket, bucket),
+                    error_type: ErrorType::Other,
+                    errno: None,
+                });
+                continue;
+            }
+            directories.extend(path.ancestors().skip(1).take_while(|dir| dir.starts_with(&root)).map(Path::to_path_buf));
+            if object.key.ends_with('/') {
+                directories.insert(path);
+                continue;
+            }
+            objects.push(FileEntry {
+                depth: depth_below(&root, &path),
+                path,
+                size: object.size,
+                modified: object.modified,
+                accessed: None,
+                is_symlink: false,
+                uid: None,
+                gid: None,
+                xattr_size: 0,
+                shared_extents: None,
+                etag: object.etag,
+                symlink_target: None,
+                root: 0,
+                disk_usage: None,
+                mode: None,
+                dev: 0,
+                ino: 0,
+                nlink: 0,
+                created: None,
+            });
+        }
+
+        let mut filters = FilterPipeline::new(args.show_hidden, super::exclude_filter(args, &root)?, None);
+        filters.include_only(root.clone(), super::include_patterns(args)?);
+        if let Some(filter) = super::ModifiedFilter::from_args(args, chrono::Utc::now()) {
+            filters.filter_modified(filter);
+        }
+        Ok(Self {
+            root,
+            objects,
+            directories,
+            unreadable,
+            max_depth: args.depth_limit(),
+            min_depth: args.min_depth,
+            filters,
+            budget: Budget::new(ScanCaps { files: args.max_files, bytes: args.max_bytes, duration: args.max_duration }),
+            owner_limit: DEFAULT_OWNER_LIMIT,
+            errors_full: args.errors_full,
+        })
+    }
+
+    /// Keep at most this many owners per directory
+    pub fn with_owner_limit(mut self, limit: usize) -> Self {
+        self.owner_limit = limit;
+        self
+    }
+
+    /// The objects and directories strictly below one of `scope`, or everything with no scope,
+    /// down to `max_depth`
+    fn collect(&mut self, scope: Option<&[PathBuf]>, max_depth: Option<usize>) -> Collected {
+        let in_scope = |path: &Path| match scope {
+            Some(scope) => scope.iter().any(|dir| path != dir && path.starts_with(dir)),
+            None => true,
+        };
+        let within_depth = |depth: usize| max_depth.is_none_or(|limit| depth <= limit);
+        let mut collected = Collected::default();
+
+        // Parents come first, so a pruned directory is known before anything below it
+        let mut pruned = HashSet::new();
+        for directory in self.directories.iter().filter(|dir| in_scope(dir)) {
+            let depth = depth_below(&self.root, directory);
+            if !within_depth(depth) || directory.parent().is_some_and(|parent| pruned.contains(parent)) {
+                continue;
+            }
+            collected.encountered += 1;
+            if depth > 0 && self.filters.prune(directory) {
+                pruned.insert(directory.clone());
+                continue;
+            }
+            if depth < self.min_depth {
+                continue;
+            }
+            let entry = DirectoryEntry {
+                path: directory.clone(),
+                total_size: 0,
+                file_count: 0,
+                recursive_file_count: 0,
+                subdirectory_count: 0,
+                recursive_subdirectory_count: 0,
+                depth,
+                owners: Vec::new(),
+                root: 0,
+                mode: None,
+            };
+            collected.directories.insert(directory.clone(), entry);
+        }
+
+        let mut boundary = BTreeMap::new();
+        for object in self.objects.iter().filter(|object| in_scope(&object.path)) {
+            if let Some(limit) = max_depth.filter(|&limit| object.depth > limit) {
+                self.note_boundary(&object.path, object.depth - limit, object.size, &collected.directories, &mut boundary);
+                continue;
+            }
+            if object.path.parent().is_some_and(|parent| pruned.contains(parent)) || object.depth < self.min_depth {
+                continue;
+            }
+            collected.encountered += 1;
+            if !self.filters.include(object) {
+                continue;
+            }
+            // Past a cap the listing ends here, as a walk would
+            if !self.budget.admit(object.size) {
+                break;
+            }
+            collected.files.push(object.clone());
+        }
+        // Folders deeper than the limit hold something too, even without objects
+        if let Some(limit) = max_depth {
+            for directory in self.directories.iter().filter(|dir| in_scope(dir)) {
+                let depth = depth_below(&self.root, directory);
+                if depth > limit {
+                    self.note_boundary(directory, depth - limit, 0, &collected.directories, &mut boundary);
+                }
+            }
+        }
+        collected.boundary = DepthBoundary {
+            immediate_size: boundary.values().sum(),
+            directories: boundary.into_keys().collect(),
+            expanded: false,
+        };
+        collected
+    }
+
+    /// Count an entry `levels` below the depth limit toward the boundary directory above it
+    ///
+    /// As with a walk, only entries the hidden rule and exclude patterns let
+    /// through make a boundary directory, and only files directly inside add to its size.
+    fn note_boundary(
+        &self,
+        path: &Path,
+        levels: usize,
+        size: u64,
+        listed: &HashMap<PathBuf, DirectoryEntry>,
+        boundary: &mut BTreeMap<PathBuf, u64>,
+    ) {
+        let (Some(child), Some(directory)) = (path.ancestors().nth(levels - 1), path.ancestors().nth(levels)) else {
+            return;
+        };
+        if !listed.contains_key(directory) || self.filters.excludes_path(child) {
+            return;
+        }
+        *boundary.entry(directory.to_path_buf()).or_default() += if levels == 1 { size } else { 0 };
+    }
+
+    fn results(&mut self, collected: Collected, mut errors: ErrorLog, directories: Vec<DirectoryEntry>) -> ScanResults {
+        let mut exclusions = ExclusionStats { encountered: collected.encountered, ..Default::default() };
+        self.filters.take_exclusions(&mut exclusions);
+        exclusions.root_matches = self.patterns_matching_root().into_iter().map(str::to_string).collect();
+        let total_size = collected.files.iter().map(|file| file.size).sum();
+        ScanResults {
+            total_files: collected.files.len() as u64,
+            total_directories: directories.len() as u64,
+            total_size,
+            files: collected.files,
+            directories,
+            errors: errors.take_listed(),
+            error_log: errors,
+            exclusions,
+            date_filter: self.filters.take_date_filter(),
+            depth_boundary: collected.boundary,
+            partial: self.budget.reached(),
+            ..ScanResults::default()
+        }
+    }
+}
+
+/// What one listing collected before directory totals are computed
+#[derive(Default)]
+struct Collected {
+    files: Vec<FileEntry>,
+    directories: HashMap<PathBuf, DirectoryEntry>,
+    encountered: u64,
+    boundary: DepthBoundary,
+}
+
+impl FileSource for InventorySource {
+    fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Every object was read with the listing, so the whole first level is readable
+    fn probe_coverage(&self) -> Result<Coverage> {
+        let first_level = self.directories.iter().filter(|dir| depth_below(&self.root, dir) == 1).count()
+            + self.objects.iter().filter(|object| object.depth == 1).count();
+        Ok(Coverage { readable_entries: first_level as u64, unreadable_entries: 0 })
+    }
+
+    fn patterns_matching_root(&self) -> Vec<&str> {
+        self.filters.exclude().matching(&self.root).map(ExcludePattern::as_str).collect()
+    }
+
+    fn excludes_path(&self, path: &Path) -> bool {
+        self.filters.excludes_path(path)
+    }
+
+    fn list(&mut self) -> Result<ScanResults> {
+        self.budget = self.budget.fresh();
+        let mut collected = self.collect(None, self.max_depth);
+        let mut errors = ErrorLog::new(self.errors_full);
+        for error in &self.unreadable {
+            errors.push(error.clone());
+        }
+        let directories = aggregate::directory_totals(&collected.files, std::mem::take(&mut collected.directories), self.owner_limit);
+        Ok(self.results(collected, errors, directories))
+    }
+
+    fn list_below(&mut self, directories: &[PathBuf]) -> Result<ScanResults> {
+        let mut collected = self.collect(Some(directories), None);
+        // Directories are totalled once absorbed into the first listing
+        let below = std::mem::take(&mut collected.directories).into_values().collect();
+        Ok(self.results(collected, ErrorLog::new(self.errors_full), below))
+    }
+
+    fn measure_directory(&self, directory: &Path) -> DirectoryEntry {
+        let depth = depth_below(&self.root, directory);
+        let kept = |path: &Path| {
+            path.ancestors()
+                .take_while(|ancestor| *ancestor != directory)
+                .all(|ancestor| !self.filters.excludes_path(ancestor))
+        };
+        let files: Vec<FileEntry> = self
+            .objects
+            .iter()
+            .filter(|object| object.path.starts_with(directory) && kept(&object.path))
+            .cloned()
+            .collect();
+        let directories = self
+            .directories
+            .iter()
+            .filter(|dir| dir.starts_with(directory) && kept(dir))
+            .chain(std::iter::once(&directory.to_path_buf()).filter(|_| !self.directories.contains(directory)))
+            .map(|dir| {
+                let entry = DirectoryEntry {
+                    path: dir.clone(),
+                    total_size: 0,
+                    file_count: 0,
+                    recursive_file_count: 0,
+                    subdirectory_count: 0,
+                    recursive_subdirectory_count: 0,
+                    depth: depth_below(&self.root, dir),
+                    owners: Vec::new(),
+                    root: 0,
+                    mode: None,
+                };
+                (dir.clone(), entry)
+            })
+            .collect();
+        aggregate::directory_totals(&files, directories, self.owner_limit)
+            .into_iter()
+            .find(|entry| entry.path == directory)
+            .unwrap_or(DirectoryEntry {
+                path: directory.to_path_buf(),
+                total_size: 0,
+                file_count: 0,
+                recursive_file_count: 0,
+                subdirectory_count: 0,
+                recursive_subdirectory_count: 0,
+                depth,
+                owners: Vec::new(),
+                root: 0,
+                mode: None,
+            })
+    }
+
+    fn add_filter(&mut self, filter: Box<dyn FileFilter>) {
+        self.filters.push(filter);
+    }
+
+    fn stop_on(&mut self, cancellation: Cancellation) {
+        self.budget = std::mem::take(&mut self.budget).cancelled_by(cancellation);
+    }
+
+    /// Objects live in the bucket, not at their listed paths
+    fn reads_contents(&self) -> bool {
+        false
+    }
+}
+
+impl Schema {
+    /// The columns named by `record`, or `None` when it is not a header row
+    fn from_header(record: &csv::StringRecord) -> Result<Option<Self>> {
+        let names: Vec<String> = record.iter().map(|name| name.trim().to_ascii_lowercase()).collect();
+        let column = |name: &str| names.iter().position(|field| field == &name.to_ascii_lowercase());
+        if column("Key").is_none() && column("Bucket").is_none() {
+            return Ok(None);
+        }
+        let (Some(bucket), Some(key)) = (column("Bucket"), column("Key")) else {
+            anyhow::bail!("S3 inventory header names no Bucket and Key columns: {}", record.iter().collect::<Vec<_>>().join(", "));
+        };
+        Ok(Some(Self {
+            bucket,
+            key,
+            size: column("Size"),
+            modified: column("LastModifiedDate"),
+            etag: column("ETag"),
+            is_latest: column("IsLatest"),
+            is_delete_marker: column("IsDeleteMarker"),
+        }))
+    }
+
+    fn default_order() -> Self {
+        let column = |name| DEFAULT_SCHEMA.iter().position(|field| *field == name);
+        Self {
+            bucket: 0,
+            key: 1,
+            size: column("Size"),
+            modified: column("LastModifiedDate"),
+            etag: column("ETag"),
+            is_latest: None,
+            is_delete_marker: None,
+        }
+    }
+
+    /// The current object a row describes; `None` for noncurrent versions and delete markers
+    fn read(&self, record: &csv::StringRecord) -> Result<Option<Object>, String> {
+        let field = |column: Option<usize>| column.and_then(|column| record.get(column)).map(str::trim).filter(|value| !value.is_empty());
+        if field(self.is_latest).is_some_and(|value| value.eq_ignore_ascii_case("false"))
+            || field(self.is_delete_marker).is_some_and(|value| value.eq_ignore_ascii_case("true"))
+        {
+            return Ok(None);
+        }
+
+        let bucket = field(Some(self.bucket)).ok_or("no bucket")?.to_string();
+        let key = url_decode(field(Some(self.key)).ok_or("no key")?);
+        let size = match field(self.size) {
+            Some(size) => size.parse().map_err(|_| format!("invalid size {:?}", size))?,
+            None => 0,
+        };
+        let modified = match field(self.modified) {
+            Some(date) => Some(
+                DateTime::parse_from_rfc3339(date)
+                    .map_err(|_| format!("invalid last modified date {:?}", date))?
+                    .with_timezone(&Utc),
+            ),
+            None => None,
+        };
+        let etag = field(self.etag).map(|etag| etag.trim_matches('"').to_string()).filter(|etag| !etag.is_empty());
+        Ok(Some(Object { bucket, key, size, modified, etag }))
+    }
+}
+
+/// Where an object's key puts it below `root`; empty segments of the key are dropped
+fn object_path(root: &Path, key: &str) -> PathBuf {
+    let mut path = root.to_path_buf();
+    path.extend(key.split('/').filter(|segment| !segment.is_empty()));
+    path
+}
+
+/// Levels of `path` below `root`, which is at depth 0
+fn depth_below(root: &Path, path: &Path) -> usize {
+    path.strip_prefix(root).map_or(0, |relative| relative.components().count())
+}
+
+/// Decode a URL-encoded key, `+` standing for a space; malformed escapes are kept as written
+fn url_decode(encoded: &str) -> String {
+    let bytes = encoded.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = (bytes[i] == b'%')
+            .then(|| encoded.get(i + 1..i + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], escaped) {
+            (_, Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+                continue;
+            }
+            (b'+', None) => decoded.push(b' '),
+            (byte, None) => decoded.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use std::fs;
+
+    fn source(contents: &str, extra: &[&str]) -> (tempfile::TempDir, InventorySource) {
+        let dir = tempfile::tempdir().unwrap();
+        let listing = dir.path().join("inventory.csv");
+        fs::write(&listing, contents).unwrap();
+        let mut argv = vec!["diranalyzer", "--source", "s3-inventory"];
+        argv.extend_from_slice(extra);
+        argv.push(listing.to_str().unwrap());
+        let source = InventorySource::open(&Args::parse_from(argv)).unwrap();
+        (dir, source)
+    }
+
+    #[test]
+    fn test_listing_without_header_becomes_a_tree() {
+        let (_dir, mut source) = source(
+            "\"media\",\"raw/2024/a+b.mov\",\"700\",\"2024-03-01T10:00:00.000Z\",\"e1\"\n\
+             \"media\",\"raw/2024/c%C3%A9.mov\",\"700\",\"2024-03-02T10:00:00.000Z\",\"e1\"\n\
+             \"media\",\"empty/\",\"0\",\"2024-03-02T10:00:00.000Z\",\"e0\"\n\
+             \"media\",\"top.txt\",\"oops\",\"2024-03-02T10:00:00.000Z\",\"e2\"\n",
+            &[],
+        );
+        let results = source.list().unwrap();
+
+        assert_eq!(source.root(), Path::new("s3://media"));
+        let paths: Vec<&Path> = results.files.iter().map(|file| file.path.as_path()).collect();
+        assert_eq!(paths, [Path::new("s3://media/raw/2024/a b.mov"), Path::new("s3://media/raw/2024/cé.mov")]);
+        assert_eq!(results.files[0].etag.as_deref(), Some("e1"));
+        assert_eq!(results.files[0].depth, 3);
+        assert_eq!(results.total_size, 1_400);
+        // The root, raw, raw/2024, and the empty folder
+        assert_eq!(results.total_directories, 4);
+        let raw = results.directories.iter().find(|dir| dir.path == Path::new("s3://media/raw")).unwrap();
+        assert_eq!((raw.total_size, raw.subdirectory_count), (1_400, 1));
+        assert_eq!(results.error_log.total(), 1);
+        assert!(results.errors[0].error.starts_with("line 4: invalid size"));
+        assert!(!source.reads_contents());
+    }
+
+    #[test]
+    fn test_header_names_columns_and_versions_are_skipped() {
+        let (_dir, mut source) = source(
+            "Bucket, Key, VersionId, IsLatest, IsDeleteMarker, Size, ETag\n\
+             logs,app/today.log,v2,true,false,50,x\n\
+             logs,app/today.log,v1,false,false,40,y\n\
+             logs,app/gone.log,v3,true,true,,\n",
+            &[],
+        );
+        let results = source.list().unwrap();
+        assert_eq!(results.total_files, 1);
+        assert_eq!(results.files[0].size, 50);
+        assert_eq!(results.files[0].modified, None);
+    }
+
+    #[test]
+    fn test_depth_limit_and_filters_match_a_walk() {
+        let contents = "b,a/x.bin,10,,e\nb,a/deep/y.bin,20,,e\nb,.cache/z.bin,30,,e\nb,logs/old.log,5,,e\n";
+        let (_dir, mut source) = source(contents, &["--depth", "1", "--exclude", "y.bin"]);
+        let mut results = source.list().unwrap();
+
+        // Only directories at the limit are listed; the hidden one is pruned with what it holds
+        assert_eq!(results.total_files, 0);
+        assert_eq!(results.depth_boundary.directories, [PathBuf::from("s3://b/a"), PathBuf::from("s3://b/logs")]);
+        assert_eq!(results.depth_boundary.immediate_size, 15);
+        assert_eq!(results.exclusions.hidden, 1);
+
+        let boundary = results.depth_boundary.directories.clone();
+        results.absorb(source.list_below(&boundary).unwrap(), DEFAULT_OWNER_LIMIT);
+        assert_eq!(results.total_size, 15);
+        assert_eq!(results.exclusions.patterns[0].hits, 1);
+        assert_eq!(source.measure_directory(Path::new("s3://b/a")).total_size, 10);
+        assert!(InventorySource::open(&Args::parse_from(["diranalyzer", "--source", "s3-inventory", "--mine", "x.csv"])).is_err());
+    }
+}
@@ -0,0 +1,167 @@
+//! Symlinks whose targets are missing
+//!
+//! Cleanups and migrations leave symlinks pointing at nothing. With
+//! `--find-broken-links` every symlink the walk meets is resolved hop by
+//! hop, whether or not `--follow-links` follows it, and classified as valid,
+//! out-of-tree when its final target exists outside the scanned tree, or
+//! broken when some target along the chain is missing. Relative targets
+//! resolve against the directory of the link that holds them, and a chain
+//! longer than [`MAX_HOPS`] counts as broken, so a loop cannot stall the walk.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Links followed before a chain counts as broken, as the kernel's own limit
+pub const MAX_HOPS: usize = 40;
+
+/// The symlinks of a run by what their targets are
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SymlinkReport {
+    /// Links whose final target exists inside the scanned tree
+    pub valid: u64,
+    /// Links whose final target exists outside the scanned tree
+    pub out_of_tree: u64,
+    /// Links with a missing target or an endless chain, sorted by path
+    pub broken: Vec<BrokenLink>,
+}
+
+/// A symlink that leads nowhere
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BrokenLink {
+    pub path: PathBuf,
+    /// The target as stored in the link
+    pub target: PathBuf,
+    /// The missing path the chain ends at; `None` when it loops or is too long
+    pub dangling: Option<PathBuf>,
+}
+
+/// Where a symlink leads
+#[derive(Debug, Clone, PartialEq)]
+enum Resolution {
+    /// To something that exists, at this path
+    Target(PathBuf),
+    /// To this path, which does not exist
+    Dangling(PathBuf),
+    /// Round a loop, or through more than [`MAX_HOPS`] links
+    Endless,
+}
+
+impl SymlinkReport {
+    /// Fold in the links found by another walk of the same tree
+    pub fn absorb(&mut self, other: SymlinkReport) {
+        self.valid += other.valid;
+        self.out_of_tree += other.out_of_tree;
+        self.broken.extend(other.broken);
+        self.broken.sort_by(|a, b| a.path.cmp(&b.path));
+    }
+
+    /// Every symlink met
+    pub fn total(&self) -> u64 {
+        self.valid + self.out_of_tree + self.broken.len() as u64
+    }
+}
+
+/// Classifies the symlinks of a walk against the scanned tree
+#[derive(Debug, Default)]
+pub(super) struct Classifier {
+    /// The scan root with its own symlinks resolved
+    root: PathBuf,
+    report: SymlinkReport,
+}
+
+impl Classifier {
+    pub(super) fn new(root: &Path) -> Self {
+        let root = fs::canonicalize(root).unwrap_or_else(|_| root.to_path_buf());
+        Self { root, report: SymlinkReport::default() }
+    }
+
+    /// A classifier against the same root, for a walk to be absorbed into this one
+    pub(super) fn fresh(&self) -> Self {
+        Self { root: self.root.clone(), report: SymlinkReport::default() }
+    }
+
+    pub(super) fn absorb(&mut self, part: Classifier) {
+        self.report.absorb(part.report);
+    }
+
+    /// Classify the symlink at `path`
+    pub(super) fn add(&mut self, path: &Path) {
+        let Ok(target) = fs::read_link(path) else { return };
+        match resolve(path) {
+            Resolution::Target(resolved) => {
+                let inside = fs::canonicalize(&resolved).is_ok_and(|resolved| resolved.starts_with(&self.root));
+                if inside {
+                    self.report.valid += 1;
+                } else {
+                    self.report.out_of_tree += 1;
+                }
+            }
+            Resolution::Dangling(dangling) => {
+                self.report.broken.push(BrokenLink { path: path.to_path_buf(), target, dangling: Some(dangling) });
+            }
+            Resolution::Endless => self.report.broken.push(BrokenLink { path: path.to_path_buf(), target, dangling: None }),
+        }
+    }
+
+    pub(super) fn finish(mut self) -> SymlinkReport {
+        self.report.broken.sort_by(|a, b| a.path.cmp(&b.path));
+        self.report
+    }
+}
+
+/// Follow the chain of links starting at `link`, at most [`MAX_HOPS`] of them
+fn resolve(link: &Path) -> Resolution {
+    let mut current = link.to_path_buf();
+    for _ in 0..MAX_HOPS {
+        let Ok(target) = fs::read_link(&current) else {
+            return Resolution::Dangling(current);
+        };
+        // A relative target starts from the directory of the link holding it
+        let next = match current.parent() {
+            Some(directory) if target.is_relative() => directory.join(target),
+            _ => target,
+        };
+        match fs::symlink_metadata(&next) {
+            Ok(metadata) if metadata.file_type().is_symlink() => current = next,
+            Ok(_) => return Resolution::Target(next),
+            // A loop among the directories on the way
+            Err(error) if error.raw_os_error() == Some(libc::ELOOP) => return Resolution::Endless,
+            Err(error) if error.kind() == io::ErrorKind::NotFound || error.raw_os_error() == Some(libc::ENOTDIR) => {
+                return Resolution::Dangling(next)
+            }
+            // Unreadable on the way; whatever is there, the link cannot be called broken
+            Err(_) => return Resolution::Target(next),
+        }
+    }
+    Resolution::Endless
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::symlink;
+
+    #[test]
+    fn test_chains_are_followed_and_capped() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        fs::write(root.join("file"), b"x").unwrap();
+        symlink("file", root.join("one")).unwrap();
+        symlink("one", root.join("two")).unwrap();
+        assert_eq!(resolve(&root.join("two")), Resolution::Target(root.join("file")));
+
+        symlink("missing", root.join("dangling")).unwrap();
+        symlink("dangling", root.join("to_dangling")).unwrap();
+        assert_eq!(resolve(&root.join("to_dangling")), Resolution::Dangling(root.join("missing")));
+
+        symlink("loop_b", root.join("loop_a")).unwrap();
+        symlink("loop_a", root.join("loop_b")).unwrap();
+        assert_eq!(resolve(&root.join("loop_a")), Resolution::Endless);
+
+        // A file where a directory should be ends the chain too
+        symlink("file/inside", root.join("through_file")).unwrap();
+        assert_eq!(resolve(&root.join("through_file")), Resolution::Dangling(root.join("file/inside")));
+    }
+}
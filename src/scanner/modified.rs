@@ -0,0 +1,172 @@
+//! `--modified-before`, `--modified-after`, and `--created-after`
+//!
+//! Any bound is a date (`2023-01-01`, midnight UTC), an RFC 3339
+//! timestamp, or an age such as `2y` or `6months` counted back from the start
+//! of the scan. Ages are resolved once, so every file is held against the
+//! same instant. A file whose modification time cannot be read matches
+//! neither modification bound: it is left out, counted, and named with
+//! `--verbose`. So is a file without a birth time under `--created-after`,
+//! which many filesystems and older kernels do not record.
+
+use super::{Args, FileEntry};
+use anyhow::Result;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Undated files named in the verbose warning at most
+pub const UNDATED_EXAMPLES: usize = 5;
+
+/// A bound as given on the command line
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateSpec {
+    At(DateTime<Utc>),
+    /// This long before the scan
+    Ago(Duration),
+}
+
+/// Parse a date, an RFC 3339 timestamp, or an age (see [`parse_duration`](crate::utils::parse_duration))
+pub fn parse_date_spec(input: &str) -> Result<DateSpec, String> {
+    let input = input.trim();
+    if let Ok(at) = DateTime::parse_from_rfc3339(input) {
+        return Ok(DateSpec::At(at.with_timezone(&Utc)));
+    }
+    if let Ok(date) = NaiveDate::parse_from_str(input, "%Y-%m-%d") {
+        return Ok(DateSpec::At(date.and_time(chrono::NaiveTime::MIN).and_utc()));
+    }
+    crate::utils::parse_duration(input)
+        .map(DateSpec::Ago)
+        .map_err(|_| format!("invalid date '{}': expected a date (2023-01-01), an RFC 3339 timestamp, or an age (2y, 90d, 6months)", input))
+}
+
+impl DateSpec {
+    /// The instant this bound stands for, ages counted back from `now`
+    pub fn resolve(self, now: DateTime<Utc>) -> DateTime<Utc> {
+        match self {
+            Self::At(at) => at,
+            // An age beyond the calendar reaches back to its start
+            Self::Ago(age) => chrono::Duration::from_std(age)
+                .ok()
+                .and_then(|age| now.checked_sub_signed(age))
+                .unwrap_or(DateTime::<Utc>::MIN_UTC),
+        }
+    }
+}
+
+/// Keeps files modified before one instant, at or after another, or between the two,
+/// and with `created_after`, only those created at or after a third
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ModifiedFilter {
+    pub before: Option<DateTime<Utc>>,
+    pub after: Option<DateTime<Utc>>,
+    pub created_after: Option<DateTime<Utc>>,
+}
+
+impl ModifiedFilter {
+    /// Resolve `--modified-before` / `--modified-after` / `--created-after` against `now`, if any was given
+    pub fn from_args(args: &Args, now: DateTime<Utc>) -> Option<Self> {
+        let filter = Self {
+            before: args.modified_before.map(|spec| spec.resolve(now)),
+            after: args.modified_after.map(|spec| spec.resolve(now)),
+            created_after: args.created_after.map(|spec| spec.resolve(now)),
+        };
+        (filter.before.is_some() || filter.after.is_some() || filter.created_after.is_some()).then_some(filter)
+    }
+
+    /// Whether a file modified at `modified` passes the modification bounds, or `None` when it has no modification time
+    pub fn matches(&self, modified: Option<DateTime<Utc>>) -> Option<bool> {
+        let modified = modified?;
+        Some(self.before.is_none_or(|before| modified < before) && self.after.is_none_or(|after| modified >= after))
+    }
+
+    /// Whether `file` passes every bound, or `None` when it lacks a time one of them needs
+    pub fn admits(&self, file: &FileEntry) -> Option<bool> {
+        let modified = match (self.before, self.after) {
+            (None, None) => true,
+            _ => self.matches(file.modified)?,
+        };
+        let created = match self.created_after {
+            Some(after) => file.created? >= after,
+            None => true,
+        };
+        Some(modified && created)
+    }
+}
+
+/// The date bounds a scan applied, and the files they could not judge
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DateFilter {
+    /// Files had to be modified before this
+    pub before: Option<DateTime<Utc>>,
+    /// Files had to be modified at or after this
+    pub after: Option<DateTime<Utc>>,
+    /// Files had to be created at or after this
+    #[serde(default)]
+    pub created_after: Option<DateTime<Utc>>,
+    /// Files left out because a time the bounds need could not be read
+    pub undated_files: u64,
+    /// The first few of them, up to [`UNDATED_EXAMPLES`]
+    #[serde(default)]
+    pub undated_examples: Vec<PathBuf>,
+}
+
+impl DateFilter {
+    /// Fold in the counts of a further walk with the same bounds
+    pub fn absorb(&mut self, other: DateFilter) {
+        self.undated_files += other.undated_files;
+        let room = UNDATED_EXAMPLES.saturating_sub(self.undated_examples.len());
+        self.undated_examples.extend(other.undated_examples.into_iter().take(room));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_date_specs_and_bounds() {
+        let now = Utc.with_ymd_and_hms(2025, 6, 1, 12, 0, 0).unwrap();
+        let at = |spec: &str| parse_date_spec(spec).unwrap().resolve(now);
+        assert_eq!(at("2023-01-01"), Utc.with_ymd_and_hms(2023, 1, 1, 0, 0, 0).unwrap());
+        assert_eq!(at("2023-01-01T08:30:00+02:00"), Utc.with_ymd_and_hms(2023, 1, 1, 6, 30, 0).unwrap());
+        assert_eq!(at("90d"), now - chrono::Duration::days(90));
+        assert_eq!(at("6months"), now - chrono::Duration::days(180));
+        assert_eq!(at("2y"), now - chrono::Duration::days(730));
+        assert!(parse_date_spec("2023-13-01").is_err());
+        assert!(parse_date_spec("last week").is_err());
+
+        let filter = ModifiedFilter { before: Some(at("2024-01-01")), after: Some(at("2023-01-01")), created_after: None };
+        let day = |y, m, d| Some(Utc.with_ymd_and_hms(y, m, d, 0, 0, 0).unwrap());
+        // The lower bound is inclusive, the upper one exclusive
+        assert_eq!(filter.matches(day(2023, 1, 1)), Some(true));
+        assert_eq!(filter.matches(day(2023, 12, 31)), Some(true));
+        assert_eq!(filter.matches(day(2024, 1, 1)), Some(false));
+        assert_eq!(filter.matches(day(2022, 12, 31)), Some(false));
+        assert_eq!(filter.matches(None), None);
+    }
+
+    #[test]
+    fn test_created_after_needs_a_birth_time() {
+        let day = |y, m, d| Some(Utc.with_ymd_and_hms(y, m, d, 0, 0, 0).unwrap());
+        let file = |modified, created| FileEntry {
+            path: "/data/report.pdf".into(),
+            size: 1,
+            modified,
+            created,
+            depth: 1,
+            ..Default::default()
+        };
+        let created = ModifiedFilter { before: None, after: None, created_after: day(2023, 1, 1) };
+        assert_eq!(created.admits(&file(None, day(2023, 1, 1))), Some(true));
+        assert_eq!(created.admits(&file(day(2024, 1, 1), day(2022, 12, 31))), Some(false));
+        assert_eq!(created.admits(&file(day(2024, 1, 1), None)), None);
+
+        // Both kinds of bound must hold, and each needs its own time
+        let both = ModifiedFilter { before: day(2024, 1, 1), ..created };
+        assert_eq!(both.admits(&file(day(2023, 6, 1), day(2023, 2, 1))), Some(true));
+        assert_eq!(both.admits(&file(day(2024, 6, 1), day(2023, 2, 1))), Some(false));
+        assert_eq!(both.admits(&file(None, day(2023, 2, 1))), None);
+    }
+}
@@ -0,0 +1,132 @@
+//! Other filesystems below the scan root, left out with `--one-file-system`
+//!
+//! Like `du -x`, the walk remembers the device of the scan root and does not
+//! enter a directory on another device, such as /proc or an NFS mount below
+//! `/`. Files on another device, as bind-mounted files are, are skipped too.
+//! Each skipped entry is listed with its filesystem type, from the mount
+//! table where the platform has one.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A directory or file on another device than the scan root
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SkippedMount {
+    pub path: PathBuf,
+    pub device: u64,
+    /// Type of the filesystem mounted there, such as `proc` or `nfs4`, where known
+    #[serde(default)]
+    pub filesystem: Option<String>,
+}
+
+/// Fill in the filesystem types of `mounts` from the mount table
+pub(super) fn describe(mounts: &mut [SkippedMount]) {
+    if mounts.is_empty() {
+        return;
+    }
+    let Ok(table) = fs::read_to_string("/proc/self/mountinfo") else {
+        return;
+    };
+    let types = filesystem_types(&table);
+    for mount in mounts {
+        let point = fs::canonicalize(&mount.path).unwrap_or_else(|_| mount.path.clone());
+        mount.filesystem = types.get(&point).cloned();
+    }
+}
+
+/// Whether the filesystem holding `path` is mounted `noatime`, so that access times never change
+///
+/// Where the platform has no mount table, access times are taken to work.
+pub(crate) fn atime_disabled(path: &Path) -> bool {
+    let Ok(table) = fs::read_to_string("/proc/self/mountinfo") else {
+        return false;
+    };
+    let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    mount_of(&table, &path).is_some_and(|options| options.split(',').any(|option| option == "noatime"))
+}
+
+/// The per-mount options of the mount holding `path`: the deepest mount point above it, the last of stacked ones
+fn mount_of<'a>(table: &'a str, path: &Path) -> Option<&'a str> {
+    let mut found: Option<(PathBuf, &str)> = None;
+    for line in table.lines() {
+        let mut fields = line.split(' ');
+        let (Some(point), Some(options)) = (fields.nth(4), fields.next()) else { continue };
+        let point = PathBuf::from(unescape(point));
+        let deeper = found.as_ref().is_none_or(|(best, _)| point.components().count() >= best.components().count());
+        if path.starts_with(&point) && deeper {
+            found = Some((point, options));
+        }
+    }
+    found.map(|(_, options)| options)
+}
+
+/// Filesystem type per mount point of a `/proc/self/mountinfo` table; the last of stacked mounts wins
+fn filesystem_types(table: &str) -> HashMap<PathBuf, String> {
+    table
+        .lines()
+        .filter_map(|line| {
+            let (mount, source) = line.split_once(" - ")?;
+            let point = mount.split(' ').nth(4)?;
+            let filesystem = source.split(' ').next()?;
+            Some((PathBuf::from(unescape(point)), filesystem.to_string()))
+        })
+        .collect()
+}
+
+/// Undo the octal escapes of spaces, tabs, newlines, and backslashes in mount points
+fn unescape(field: &str) -> String {
+    let mut unescaped = String::with_capacity(field.len());
+    let mut rest = field;
+    while let Some(index) = rest.find('\\') {
+        unescaped.push_str(&rest[..index]);
+        let code = rest.get(index + 1..index + 4).and_then(|digits| u8::from_str_radix(digits, 8).ok());
+        match code {
+            Some(byte) => {
+                unescaped.push(byte as char);
+                rest = &rest[index + 4..];
+            }
+            None => {
+                unescaped.push('\\');
+                rest = &rest[index + 1..];
+            }
+        }
+    }
+    unescaped.push_str(rest);
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mount_table_gives_the_type_per_mount_point() {
+        let table = "\
+22 1 0:21 / /proc rw,nosuid,nodev,noexec,relatime shared:5 - proc proc rw
+23 1 0:22 / /sys rw,nosuid,nodev,noexec,relatime shared:6 - sysfs sysfs rw
+41 1 0:40 / /mnt/backup\\040disk rw,relatime shared:20 - ext4 /dev/sdb1 rw
+42 41 0:41 / /mnt/backup\\040disk rw,relatime shared:21 master:1 - nfs4 nas:/export rw
+";
+        let types = filesystem_types(table);
+        assert_eq!(types[&PathBuf::from("/proc")], "proc");
+        assert_eq!(types[&PathBuf::from("/sys")], "sysfs");
+        // Optional fields before the separator vary in number; stacked mounts keep the top one
+        assert_eq!(types[&PathBuf::from("/mnt/backup disk")], "nfs4");
+        assert_eq!(unescape(r"a\011b\134c\x"), "a\tb\\c\\x");
+    }
+
+    #[test]
+    fn test_mount_of_a_path_is_the_deepest_above_it() {
+        let table = "\
+1 0 8:1 / / rw,relatime shared:1 - ext4 /dev/sda1 rw
+30 1 8:2 / /archive rw,noatime shared:2 - xfs /dev/sda2 rw
+31 30 8:3 / /archive/hot rw,relatime shared:3 - xfs /dev/sda3 rw
+";
+        assert_eq!(mount_of(table, Path::new("/archive/2019/q1")), Some("rw,noatime"));
+        assert_eq!(mount_of(table, Path::new("/archive/hot/today")), Some("rw,relatime"));
+        assert_eq!(mount_of(table, Path::new("/archived")), Some("rw,relatime"));
+        assert_eq!(mount_of("", Path::new("/")), None);
+    }
+}
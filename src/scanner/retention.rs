@@ -0,0 +1,163 @@
+//! Which directories a run keeps once their totals are known
+//!
+//! Trees of tens of millions of directories, such as maildir farms, make the
+//! directory list the largest thing a run holds after the scan. The report
+//! only lists the largest directories and the pinned ones, so the rest are
+//! streamed past a bounded heap of the largest, counted, and dropped.
+//!
+//! A directory is dropped only if no listing could show it: the heap keeps
+//! the `top` largest at or over the size floor, in the order the listing
+//! ranks them, so the listing made from the kept directories is the one made
+//! from all of them. What the dropped directories add to the listing's
+//! residual and to its count of hidden directories is kept as counters.
+//! Features that need every directory, like `--export-scope full`, keep them
+//! all instead.
+
+use super::DirectoryEntry;
+use serde::{Deserialize, Serialize};
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashSet};
+use std::path::PathBuf;
+
+/// What a bounded directory list keeps
+#[derive(Debug, Clone, Default)]
+pub struct DirectoryRetention {
+    /// How many of the largest directories are kept
+    pub top: usize,
+    /// Directories smaller than this are never listed, only counted
+    pub min_size: u64,
+    /// Directories kept whatever their size, such as the pinned ones
+    pub keep: HashSet<PathBuf>,
+}
+
+/// Counters standing in for the directories a bounded list dropped
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DroppedDirectories {
+    /// Dropped directories at each depth below the root
+    pub by_depth: Vec<u64>,
+    /// Dropped directories at or over the size floor, which the listing ranked too low to show
+    pub ranked_out: u64,
+    /// Total size of the directories ranked out
+    pub ranked_out_size: u64,
+    /// Dropped directories under the size floor
+    pub under_floor: u64,
+}
+
+impl DroppedDirectories {
+    /// Whether nothing was dropped
+    pub fn is_empty(&self) -> bool {
+        self.by_depth.iter().all(|&count| count == 0)
+    }
+
+    fn count(&mut self, entry: &DirectoryEntry, min_size: u64) {
+        if self.by_depth.len() <= entry.depth {
+            self.by_depth.resize(entry.depth + 1, 0);
+        }
+        self.by_depth[entry.depth] += 1;
+        if entry.total_size >= min_size {
+            self.ranked_out += 1;
+            self.ranked_out_size += entry.total_size;
+        } else {
+            self.under_floor += 1;
+        }
+    }
+}
+
+impl DirectoryRetention {
+    /// Keep what `self` asks for of `directories`, counting the rest into `dropped`
+    ///
+    /// Directories are consumed one at a time, so each dropped one is freed
+    /// as soon as it has been counted.
+    pub(super) fn retain(&self, directories: impl IntoIterator<Item = DirectoryEntry>, dropped: &mut DroppedDirectories) -> Vec<DirectoryEntry> {
+        let mut kept = Vec::new();
+        // The least of the largest on top, ready to make room for a larger one
+        let mut largest: BinaryHeap<Reverse<Ranked>> = BinaryHeap::with_capacity(self.top + 1);
+        for entry in directories {
+            // The root anchors every other path of the listing
+            if entry.depth == 0 || self.keep.contains(&entry.path) {
+                kept.push(entry);
+            } else if entry.total_size < self.min_size {
+                dropped.count(&entry, self.min_size);
+            } else {
+                largest.push(Reverse(Ranked(entry)));
+                if largest.len() > self.top {
+                    if let Some(Reverse(Ranked(smallest))) = largest.pop() {
+                        dropped.count(&smallest, self.min_size);
+                    }
+                }
+            }
+        }
+        kept.extend(largest.into_iter().map(|Reverse(Ranked(entry))| entry));
+        kept
+    }
+}
+
+/// A directory ordered the way the largest directories are listed: larger first, then by path
+struct Ranked(DirectoryEntry);
+
+impl Ord for Ranked {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_size.cmp(&other.0.total_size).then_with(|| other.0.path.cmp(&self.0.path))
+    }
+}
+
+impl PartialOrd for Ranked {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Ranked {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Ranked {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn directory(path: &str, total_size: u64) -> DirectoryEntry {
+        DirectoryEntry {
+            path: PathBuf::from(path),
+            total_size,
+            file_count: 0,
+            recursive_file_count: 0,
+            subdirectory_count: 0,
+            recursive_subdirectory_count: 0,
+            depth: path.matches('/').count() - 1,
+            owners: Vec::new(),
+            root: 0,
+            mode: None,
+        }
+    }
+
+    fn paths(entries: &[DirectoryEntry]) -> Vec<&str> {
+        let mut paths: Vec<&str> = entries.iter().map(|entry| entry.path.to_str().unwrap()).collect();
+        paths.sort();
+        paths
+    }
+
+    #[test]
+    fn test_keeps_the_largest_the_root_and_the_pinned() {
+        let directories = vec![
+            directory("/r", 1_000),
+            directory("/r/a", 400),
+            directory("/r/b", 300),
+            // Ties go to the path listed first, as in the listing
+            directory("/r/d", 200),
+            directory("/r/c", 200),
+            directory("/r/a/x", 50),
+            directory("/r/tiny", 5),
+            directory("/r/pinned", 1),
+        ];
+        let retention = DirectoryRetention { top: 3, min_size: 10, keep: HashSet::from([PathBuf::from("/r/pinned")]) };
+        let mut dropped = DroppedDirectories::default();
+
+        let kept = retention.retain(directories, &mut dropped);
+        assert_eq!(paths(&kept), ["/r", "/r/a", "/r/b", "/r/c", "/r/pinned"]);
+        assert_eq!(dropped, DroppedDirectories { by_depth: vec![0, 2, 1], ranked_out: 2, ranked_out_size: 250, under_floor: 1 });
+    }
+}
@@ -0,0 +1,164 @@
+//! Detection of pathologically slow single directories
+//!
+//! The walk is depth-first, so the directories still being read form a stack
+//! indexed by depth. Each entry is attributed to its parent on that stack,
+//! together with the time since the previous entry; a directory's total is
+//! therefore the time spent reading its own entries, not its subtrees.
+
+use crate::utils::format_count;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+/// When a single directory counts as slow
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SlowThresholds {
+    pub entries: u64,
+    pub duration: Duration,
+}
+
+impl Default for SlowThresholds {
+    fn default() -> Self {
+        Self { entries: 100_000, duration: Duration::from_secs(10) }
+    }
+}
+
+/// A directory that exceeded a [`SlowThresholds`] limit
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SlowDirectory {
+    pub path: PathBuf,
+    /// Direct entries read from the directory
+    pub entries: u64,
+    /// Time spent reading those entries
+    pub elapsed_ms: u64,
+}
+
+/// A directory whose entries are still streaming in
+struct OpenDirectory {
+    path: PathBuf,
+    entries: u64,
+    elapsed: Duration,
+    next_entries_report: u64,
+    next_time_report: Duration,
+}
+
+/// Attributes walked entries to their parent directories
+pub(super) struct ReadTracker {
+    thresholds: SlowThresholds,
+    open: Vec<OpenDirectory>,
+    last_entry: Instant,
+    slow: Vec<SlowDirectory>,
+}
+
+impl ReadTracker {
+    pub fn new(thresholds: SlowThresholds) -> Self {
+        Self { thresholds, open: Vec::new(), last_entry: Instant::now(), slow: Vec::new() }
+    }
+
+    /// Record an entry at `depth`; returns a progress message when its parent crosses a threshold
+    pub fn record(&mut self, path: &Path, depth: usize, is_dir: bool) -> Option<String> {
+        self.record_after(path, depth, is_dir, self.last_entry.elapsed())
+    }
+
+    fn record_after(&mut self, path: &Path, depth: usize, is_dir: bool, gap: Duration) -> Option<String> {
+        self.last_entry = Instant::now();
+
+        // Directories deeper than this entry's parent have been fully read
+        while self.open.len() > depth {
+            self.close_innermost();
+        }
+
+        let thresholds = self.thresholds;
+        let message = depth.checked_sub(1).and_then(|parent| self.open.get_mut(parent)).and_then(|parent| {
+            parent.entries += 1;
+            parent.elapsed += gap;
+
+            let mut crossed = false;
+            if parent.entries >= parent.next_entries_report {
+                parent.next_entries_report = parent.next_entries_report.saturating_mul(2);
+                crossed = true;
+            }
+            if parent.elapsed >= parent.next_time_report {
+                parent.next_time_report += thresholds.duration;
+                crossed = true;
+            }
+            crossed.then(|| {
+                format!("still reading {} - {} entries so far", parent.path.display(), format_count(parent.entries))
+            })
+        });
+
+        if is_dir {
+            self.open.push(OpenDirectory {
+                path: path.to_path_buf(),
+                entries: 0,
+                elapsed: Duration::ZERO,
+                next_entries_report: thresholds.entries.max(1),
+                next_time_report: thresholds.duration,
+            });
+        }
+        message
+    }
+
+    fn close_innermost(&mut self) {
+        let Some(directory) = self.open.pop() else {
+            return;
+        };
+        if directory.entries >= self.thresholds.entries || directory.elapsed >= self.thresholds.duration {
+            self.slow.push(SlowDirectory {
+                path: directory.path,
+                entries: directory.entries,
+                elapsed_ms: directory.elapsed.as_millis() as u64,
+            });
+        }
+    }
+
+    /// Close the remaining directories and return the slow ones, slowest first
+    pub fn finish(mut self) -> Vec<SlowDirectory> {
+        while !self.open.is_empty() {
+            self.close_innermost();
+        }
+        slowest_first(&mut self.slow);
+        self.slow
+    }
+}
+
+/// Sort slow directories by the time spent reading them, then by their entries
+pub(super) fn slowest_first(directories: &mut [SlowDirectory]) {
+    directories.sort_by(|a, b| b.elapsed_ms.cmp(&a.elapsed_ms).then_with(|| b.entries.cmp(&a.entries)));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_time_is_charged_to_the_direct_parent() {
+        let thresholds = SlowThresholds { entries: 1_000, duration: Duration::from_secs(5) };
+        let mut tracker = ReadTracker::new(thresholds);
+        let ms = Duration::from_millis;
+
+        tracker.record_after(Path::new("/r"), 0, true, ms(0));
+        tracker.record_after(Path::new("/r/spool"), 1, true, ms(10));
+        // Reading the spool is slow; its parent only pays for its own entries
+        let message = tracker.record_after(Path::new("/r/spool/1"), 2, false, ms(4_000));
+        assert_eq!(message, None);
+        let message = tracker.record_after(Path::new("/r/spool/2"), 2, false, ms(1_500));
+        assert_eq!(message.as_deref(), Some("still reading /r/spool - 2 entries so far"));
+        tracker.record_after(Path::new("/r/quick"), 1, false, ms(20));
+
+        let slow = tracker.finish();
+        assert_eq!(slow, [SlowDirectory { path: PathBuf::from("/r/spool"), entries: 2, elapsed_ms: 5_500 }]);
+    }
+
+    #[test]
+    fn test_entry_reports_back_off() {
+        let thresholds = SlowThresholds { entries: 10, duration: Duration::from_secs(3_600) };
+        let mut tracker = ReadTracker::new(thresholds);
+        tracker.record_after(Path::new("/wide"), 0, true, Duration::ZERO);
+
+        let reported: Vec<u64> = (1..=100)
+            .filter(|i| tracker.record_after(&PathBuf::from(format!("/wide/{}", i)), 1, false, Duration::ZERO).is_some())
+            .collect();
+        assert_eq!(reported, [10, 20, 40, 80]);
+    }
+}
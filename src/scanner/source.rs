@@ -0,0 +1,138 @@
+//! Where the files of a run are listed
+//!
+//! A [`FileSource`] lists a tree's files and directories as [`ScanResults`],
+//! and everything after the listing (analysis, duplicate detection, reports,
+//! exports) works on those alone. The local walker, [`DirectoryScanner`], is
+//! the default source; [`InventorySource`](super::InventorySource) reads an
+//! S3 inventory listing instead, for `--source s3-inventory`.
+//!
+//! A source whose files cannot be opened at their paths says so with
+//! [`FileSource::reads_contents`]; the analyzer then leaves out what reads
+//! file contents, and duplicates are grouped by the entries' etags.
+//!
+//! [`FileSource::stream`] hands the files out as they are listed instead of
+//! keeping them, for runs too large to hold every file (`--low-memory`).
+//! A source that cannot list incrementally lists everything first and then
+//! hands it out, which gives the same results without saving memory.
+
+use super::{Coverage, DirectoryEntry, DirectoryScanner, ExclusionTrace, FileFilter, FileSink, InterimSink, ScanResults};
+use crate::interrupt::Cancellation;
+use anyhow::{bail, Result};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Lists the files and directories a run analyzes
+pub trait FileSource: Send {
+    /// The root every listed path starts with
+    fn root(&self) -> &Path;
+
+    /// Check that the root can be listed, counting the readable and unreadable entries of its first level
+    fn probe_coverage(&self) -> Result<Coverage>;
+
+    /// Exclude patterns that match the root itself
+    fn patterns_matching_root(&self) -> Vec<&str>;
+
+    /// Whether the hidden rule or an exclude pattern keeps `path` out of the listing
+    fn excludes_path(&self, path: &Path) -> bool;
+
+    /// List the tree down to the depth limit, recording the directories it stopped at
+    fn list(&mut self) -> Result<ScanResults>;
+
+    /// List what lies below directories the depth limit stopped at, without a limit
+    ///
+    /// The directories themselves are not part of the results; fold them into
+    /// the first listing with [`ScanResults::absorb`].
+    fn list_below(&mut self, directories: &[PathBuf]) -> Result<ScanResults>;
+
+    /// List the tree like [`FileSource::list`], handing each file to `sink` instead of keeping it
+    ///
+    /// The results hold no files; their totals and directories are complete.
+    fn stream(&mut self, sink: FileSink<'_>) -> Result<ScanResults> {
+        let mut results = self.list()?;
+        results.hand_out(sink);
+        Ok(results)
+    }
+
+    /// List what lies below directories like [`FileSource::list_below`], handing each file to `sink`
+    fn stream_below(&mut self, directories: &[PathBuf], sink: FileSink<'_>) -> Result<ScanResults> {
+        let mut results = self.list_below(directories)?;
+        results.hand_out(sink);
+        Ok(results)
+    }
+
+    /// Totals of one directory the depth limit kept out, adding nothing to the results
+    fn measure_directory(&self, directory: &Path) -> DirectoryEntry;
+
+    /// Add a filter, run after the built-in ones and those added before it
+    fn add_filter(&mut self, filter: Box<dyn FileFilter>);
+
+    /// Follow `path` from the root through the filters, naming the rule that keeps it out
+    fn explain_path(&self, path: &Path) -> Result<ExclusionTrace> {
+        bail!("Cannot trace {}: only local directory scans can be traced", path.display())
+    }
+
+    /// Report interim totals to `sink` every `every` while listing, where listing takes long enough to
+    fn report_interim(&mut self, _every: Option<Duration>, _sink: InterimSink) {}
+
+    /// Stop listing once `cancellation` is set, marking the results partial; sources that list at once may ignore it
+    fn stop_on(&mut self, _cancellation: Cancellation) {}
+
+    /// Whether the listed files can be opened at their paths to read their contents
+    fn reads_contents(&self) -> bool {
+        true
+    }
+}
+
+impl FileSource for DirectoryScanner {
+    fn root(&self) -> &Path {
+        &self.args.path
+    }
+
+    fn probe_coverage(&self) -> Result<Coverage> {
+        DirectoryScanner::probe_coverage(self)
+    }
+
+    fn patterns_matching_root(&self) -> Vec<&str> {
+        DirectoryScanner::patterns_matching_root(self)
+    }
+
+    fn excludes_path(&self, path: &Path) -> bool {
+        DirectoryScanner::excludes_path(self, path)
+    }
+
+    fn list(&mut self) -> Result<ScanResults> {
+        self.walk_tree(None)
+    }
+
+    fn list_below(&mut self, directories: &[PathBuf]) -> Result<ScanResults> {
+        self.walk_below(directories, None)
+    }
+
+    fn stream(&mut self, sink: FileSink<'_>) -> Result<ScanResults> {
+        self.walk_tree(Some(sink))
+    }
+
+    fn stream_below(&mut self, directories: &[PathBuf], sink: FileSink<'_>) -> Result<ScanResults> {
+        self.walk_below(directories, Some(sink))
+    }
+
+    fn measure_directory(&self, directory: &Path) -> DirectoryEntry {
+        DirectoryScanner::measure_directory(self, directory)
+    }
+
+    fn add_filter(&mut self, filter: Box<dyn FileFilter>) {
+        self.filters.push(filter);
+    }
+
+    fn explain_path(&self, path: &Path) -> Result<ExclusionTrace> {
+        DirectoryScanner::explain_path(self, path)
+    }
+
+    fn report_interim(&mut self, every: Option<Duration>, sink: InterimSink) {
+        self.interim = Some(super::interim::Interim::new(every, sink));
+    }
+
+    fn stop_on(&mut self, cancellation: Cancellation) {
+        self.budget = std::mem::take(&mut self.budget).cancelled_by(cancellation);
+    }
+}
@@ -0,0 +1,167 @@
+//! Risky permissions found among the scanned entries
+//!
+//! With `--security-audit`, the walk records the mode bits it already has from
+//! each entry's metadata, and the collected files and directories are checked
+//! for the permissions a hardening review asks about first: files anyone can
+//! write, setuid and setgid files, and directories anyone can write that lack
+//! the sticky bit, so that any user can delete or replace what others put there.
+//!
+//! Every finding is kept; the report lists the first few of each kind, and
+//! exports carry them all.
+
+use crate::scanner::{DirectoryEntry, FileEntry};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Writable by any user
+pub const WORLD_WRITABLE: u32 = 0o002;
+
+/// Runs with the owner's user id
+pub const SETUID: u32 = 0o4000;
+
+/// Runs with the owner's group id
+pub const SETGID: u32 = 0o2000;
+
+/// In a directory, only an entry's owner may remove or rename it
+pub const STICKY: u32 = 0o1000;
+
+/// One entry with risky permissions
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PermissionFinding {
+    pub path: PathBuf,
+    /// Permission bits, setuid, setgid, and sticky included
+    pub mode: u32,
+    /// The owner's login name, or the uid where there is none; unknown for directories
+    pub owner: Option<String>,
+    /// The file's size, or the bytes below a directory
+    pub size: u64,
+}
+
+/// The entries of each risky kind, ordered by path
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SecurityAudit {
+    /// Files and directories whose mode bits were read
+    pub audited_entries: u64,
+    pub world_writable_files: Vec<PermissionFinding>,
+    pub setuid_files: Vec<PermissionFinding>,
+    pub setgid_files: Vec<PermissionFinding>,
+    /// World-writable directories without the sticky bit
+    pub open_directories: Vec<PermissionFinding>,
+}
+
+impl SecurityAudit {
+    /// Check the entries whose mode bits were recorded; the others are left out
+    ///
+    /// `user_name` resolves the owners of the files found to login names.
+    pub fn of(files: &[FileEntry], directories: &[DirectoryEntry], mut user_name: impl FnMut(u32) -> Option<String>) -> Self {
+        let mut audit = SecurityAudit::default();
+        for file in files {
+            audit.add_file(file, &mut user_name);
+        }
+        audit.with_directories(directories)
+    }
+
+    /// Check one file, as a walk that keeps no list of them hands it out
+    pub fn add_file(&mut self, file: &FileEntry, mut user_name: impl FnMut(u32) -> Option<String>) {
+        let Some(mode) = file.mode else { return };
+        self.audited_entries += 1;
+        if mode & (WORLD_WRITABLE | SETUID | SETGID) == 0 {
+            return;
+        }
+        let owner = file.uid.map(|uid| user_name(uid).unwrap_or_else(|| uid.to_string()));
+        let finding = || PermissionFinding { path: file.path.clone(), mode, owner: owner.clone(), size: file.size };
+        if mode & WORLD_WRITABLE != 0 {
+            self.world_writable_files.push(finding());
+        }
+        if mode & SETUID != 0 {
+            self.setuid_files.push(finding());
+        }
+        if mode & SETGID != 0 {
+            self.setgid_files.push(finding());
+        }
+    }
+
+    /// Check the directories once their totals are known, and order every kind by path
+    pub fn with_directories(mut self, directories: &[DirectoryEntry]) -> Self {
+        for directory in directories {
+            let Some(mode) = directory.mode else { continue };
+            self.audited_entries += 1;
+            if mode & WORLD_WRITABLE != 0 && mode & STICKY == 0 {
+                self.open_directories.push(PermissionFinding {
+                    path: directory.path.clone(),
+                    mode,
+                    owner: None,
+                    size: directory.total_size,
+                });
+            }
+        }
+        for findings in self.kinds_mut() {
+            findings.sort_by(|a, b| a.path.cmp(&b.path));
+        }
+        self
+    }
+
+    /// Findings of every kind; an entry of several kinds counts once per kind
+    pub fn findings(&self) -> u64 {
+        (self.world_writable_files.len() + self.setuid_files.len() + self.setgid_files.len() + self.open_directories.len()) as u64
+    }
+
+    fn kinds_mut(&mut self) -> [&mut Vec<PermissionFinding>; 4] {
+        [&mut self.world_writable_files, &mut self.setuid_files, &mut self.setgid_files, &mut self.open_directories]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(path: &str, mode: Option<u32>) -> FileEntry {
+        FileEntry {
+            path: PathBuf::from(path),
+            size: 100,
+            depth: 1,
+            uid: Some(0),
+            mode,
+            ..Default::default()
+        }
+    }
+
+    fn directory(path: &str, mode: u32) -> DirectoryEntry {
+        DirectoryEntry {
+            path: PathBuf::from(path),
+            total_size: 5_000,
+            file_count: 0,
+            recursive_file_count: 0,
+            subdirectory_count: 0,
+            recursive_subdirectory_count: 0,
+            depth: 1,
+            owners: Vec::new(),
+            root: 0,
+            mode: Some(mode),
+        }
+    }
+
+    #[test]
+    fn test_each_kind_is_found_and_sorted_by_path() {
+        let files = [
+            file("/srv/z.sh", Some(0o777)),
+            file("/srv/a.log", Some(0o666)),
+            file("/usr/bin/passwd", Some(0o4755)),
+            file("/usr/bin/both", Some(0o6755)),
+            file("/srv/plain", Some(0o644)),
+            file("/srv/unread", None),
+        ];
+        let directories = [directory("/tmp", 0o1777), directory("/srv/drop", 0o777), directory("/srv", 0o755)];
+
+        let audit = SecurityAudit::of(&files, &directories, |uid| (uid == 0).then(|| "root".to_string()));
+        let paths = |findings: &[PermissionFinding]| findings.iter().map(|finding| finding.path.to_str().unwrap().to_string()).collect::<Vec<_>>();
+        assert_eq!(audit.audited_entries, 8);
+        assert_eq!(paths(&audit.world_writable_files), ["/srv/a.log", "/srv/z.sh"]);
+        assert_eq!(paths(&audit.setuid_files), ["/usr/bin/both", "/usr/bin/passwd"]);
+        assert_eq!(paths(&audit.setgid_files), ["/usr/bin/both"]);
+        assert_eq!(paths(&audit.open_directories), ["/srv/drop"]);
+        assert_eq!(audit.setuid_files[0].owner.as_deref(), Some("root"));
+        assert_eq!((audit.open_directories[0].size, audit.open_directories[0].owner.as_deref()), (5_000, None));
+        assert_eq!(audit.findings(), 6);
+    }
+}
@@ -0,0 +1,474 @@
+//! Concurrency soak run behind the hidden `self-test` command
+//!
+//! Races between the walk, the duplicate search, and the shared hash cache
+//! only show under load, on a tree that changes while it is read. The
+//! self-test builds a tree in a temporary directory and keeps background
+//! threads creating, rewriting, and deleting files in it, while it runs one
+//! analysis after another with options drawn from the seed: duplicates on or
+//! off, the hash cache, a file cap that stops the walk early, the hashing
+//! thread count, and two runs at once sharing the cache.
+//!
+//! After every run the churn is paused and the same options run once more
+//! without the cap. That run must match a plain walk of the tree, file for
+//! file and byte for byte, and every duplicate group it lists must hold
+//! identical contents. No run may panic or fail, and none may leave temporary
+//! files behind. The seed replays the option sequence and each churner's
+//! operations; their interleaving with the walk is up to the scheduler.
+
+use crate::analyzer::{AnalysisResults, DirectoryAnalyzer};
+use crate::cli::Args;
+use crate::utils::{random_seed, rng, Rng};
+use anyhow::{Context, Result};
+use clap::Parser;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+/// Background threads changing the tree
+const CHURNERS: usize = 2;
+
+/// Directories of the generated tree, relative to its root
+const DIRECTORIES: [&str; 8] = ["docs", "docs/old", "media", "media/raw", "media/raw/2024", "src", "src/build", "tmp"];
+
+/// File names used in each directory, so that churn keeps revisiting the same paths
+const NAMES_PER_DIRECTORY: usize = 24;
+
+/// Sizes of the contents written; the larger ones are candidates for duplicates
+const SIZES: [usize; 5] = [0, 300, 2_000, 5_000, 20_000];
+
+/// Distinct contents per size, so that rewritten files keep forming duplicates
+const CONTENTS_PER_SIZE: usize = 3;
+
+/// Options of one analysis, drawn from the seed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunOptions {
+    pub duplicates: bool,
+    pub hash_cache: bool,
+    /// Stops the walk partway, as `--max-files` does
+    pub max_files: Option<u64>,
+    pub threads: usize,
+    /// Run two analyses at once, sharing the hash cache
+    pub concurrent: bool,
+}
+
+impl RunOptions {
+    fn draw(rng: &mut Rng) -> Self {
+        let duplicates = rng.below(3) > 0;
+        Self {
+            duplicates,
+            hash_cache: duplicates && rng.below(2) == 0,
+            max_files: (rng.below(4) == 0).then(|| 1 + rng.below(100) as u64),
+            threads: 1 + rng.below(4),
+            concurrent: rng.below(3) == 0,
+        }
+    }
+
+    /// The same options without the cap, for the verification pass
+    fn uncapped(&self) -> Self {
+        Self { max_files: None, concurrent: false, ..self.clone() }
+    }
+
+    fn args(&self, root: &Path, cache: &Path) -> Args {
+        let mut argv = vec!["diranalyzer".to_string(), "--quiet".into(), "--no-history".into(), "--min-size".into(), "1".into()];
+        argv.extend(self.to_string().split_whitespace().map(str::to_string));
+        if self.hash_cache {
+            argv.extend(["--hash-cache-dir".to_string(), cache.display().to_string()]);
+        }
+        argv.push(root.display().to_string());
+        Args::parse_from(argv)
+    }
+}
+
+impl fmt::Display for RunOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "--threads {}", self.threads)?;
+        if self.duplicates {
+            write!(f, " --duplicates")?;
+        }
+        if self.hash_cache {
+            write!(f, " --hash-cache")?;
+        }
+        if let Some(max_files) = self.max_files {
+            write!(f, " --max-files {}", max_files)?;
+        }
+        Ok(())
+    }
+}
+
+/// A run whose results broke an invariant
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Failure {
+    /// Number of the run, from 1
+    pub run: u64,
+    pub options: String,
+    pub problem: String,
+}
+
+/// What a self-test did and what it found
+#[derive(Debug, Clone, Default)]
+pub struct SelfTestReport {
+    pub seed: u64,
+    pub runs: u64,
+    /// Runs with two analyses at once
+    pub concurrent_runs: u64,
+    /// Files created, rewritten, or deleted by the churners
+    pub churn_operations: u64,
+    pub elapsed: Duration,
+    pub failures: Vec<Failure>,
+}
+
+impl SelfTestReport {
+    pub fn passed(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+/// Repeated analyses of a changing tree, checked after each run
+pub struct SelfTest {
+    seed: u64,
+    duration: Duration,
+}
+
+impl SelfTest {
+    /// A self-test drawing everything from `seed`, or from a fresh one
+    pub fn new(seed: Option<u64>) -> Self {
+        Self { seed: seed.unwrap_or_else(random_seed), duration: Duration::from_secs(60) }
+    }
+
+    /// Keep starting runs for this long; at least one run is made
+    pub fn duration(mut self, duration: Duration) -> Self {
+        self.duration = duration;
+        self
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn run(&self) -> Result<SelfTestReport> {
+        let start = Instant::now();
+        let work = tempfile::Builder::new()
+            .prefix("diranalyzer-self-test-")
+            .tempdir()
+            .context("Failed to create the self-test directory")?;
+        // The tree itself must not be hidden, or the walk would skip all of it
+        let root = work.path().join("tree");
+        let cache = work.path().join("cache");
+        generate_tree(&root, &mut rng(self.seed, "self-test tree"))?;
+
+        let churn = Churn::start(&root, self.seed);
+        let mut plan = rng(self.seed, "self-test");
+        let mut report = SelfTestReport { seed: self.seed, ..SelfTestReport::default() };
+        while report.runs == 0 || start.elapsed() < self.duration {
+            report.runs += 1;
+            let options = RunOptions::draw(&mut plan);
+            let mut fail = |problem: String| {
+                report.failures.push(Failure { run: report.runs, options: options.to_string(), problem });
+            };
+
+            let analyses = if options.concurrent { 2 } else { 1 };
+            let handles: Vec<_> = (0..analyses).map(|_| analyze(options.args(&root, &cache))).collect();
+            for handle in handles {
+                match joined(handle.join()) {
+                    Ok(results) => check_consistent(&results, &options).into_iter().for_each(&mut fail),
+                    Err(problem) => fail(problem),
+                }
+            }
+
+            {
+                let _paused = churn.pause();
+                match joined(analyze(options.uncapped().args(&root, &cache)).join()) {
+                    Ok(results) => check_against_tree(&results, &root).into_iter().for_each(&mut fail),
+                    Err(problem) => fail(format!("verification pass: {}", problem)),
+                }
+            }
+            leftover_temp_files(work.path()).into_iter().for_each(&mut fail);
+            report.concurrent_runs += options.concurrent as u64;
+        }
+
+        let (operations, churn_panics) = churn.stop();
+        report.churn_operations = operations;
+        for problem in churn_panics {
+            report.failures.push(Failure { run: report.runs, options: String::new(), problem });
+        }
+        report.elapsed = start.elapsed();
+        Ok(report)
+    }
+}
+
+/// Start an analysis on a thread of its own, so that a panic ends only that thread
+fn analyze(args: Args) -> JoinHandle<Result<AnalysisResults>> {
+    thread::spawn(move || {
+        let runtime = tokio::runtime::Builder::new_current_thread().enable_all().build()?;
+        runtime.block_on(async { DirectoryAnalyzer::new(args)?.analyze().await })
+    })
+}
+
+/// The results of a finished analysis, or what went wrong with it
+fn joined(outcome: thread::Result<Result<AnalysisResults>>) -> std::result::Result<AnalysisResults, String> {
+    match outcome {
+        Ok(Ok(results)) => Ok(results),
+        Ok(Err(error)) => Err(format!("run failed: {:#}", error)),
+        Err(payload) => Err(format!("run panicked: {}", panic_message(payload))),
+    }
+}
+
+fn panic_message(payload: Box<dyn std::any::Any + Send>) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "no message".to_string())
+}
+
+/// Invariants that hold even while the tree changes under the walk
+fn check_consistent(results: &AnalysisResults, options: &RunOptions) -> Vec<String> {
+    let info = &results.scan_info;
+    let mut problems = Vec::new();
+    if let Some(root) = results.largest_directories.iter().find(|directory| directory.path == info.path) {
+        if root.size != info.total_size {
+            problems.push(format!("root directory holds {} bytes, the total is {}", root.size, info.total_size));
+        }
+    }
+    if let Some(largest) = results.largest_files.first() {
+        if largest.size > info.total_size {
+            problems.push(format!("largest file {} is larger than the total", largest.path.display()));
+        }
+    }
+    if let Some(max_files) = options.max_files {
+        if info.total_files > max_files {
+            problems.push(format!("{} files collected past --max-files {}", info.total_files, max_files));
+        }
+    }
+    if options.duplicates != results.duplicate_groups.is_some() {
+        problems.push("duplicate groups do not match --duplicates".to_string());
+    }
+    problems
+}
+
+/// Invariants of a run on a tree that held still throughout
+fn check_against_tree(results: &AnalysisResults, root: &Path) -> Vec<String> {
+    let info = &results.scan_info;
+    let mut problems = Vec::new();
+    let (files, bytes) = tree_totals(root);
+    if (info.total_files, info.total_size) != (files, bytes) {
+        problems.push(format!(
+            "verification pass found {} files of {} bytes, the tree holds {} files of {} bytes",
+            info.total_files, info.total_size, files, bytes
+        ));
+    }
+    for group in results.duplicate_groups.iter().flatten() {
+        let contents: Vec<_> = group.files.iter().map(fs::read).collect();
+        let identical = contents.iter().all(|content| {
+            content.as_ref().is_ok_and(|bytes| bytes.len() as u64 == group.file_size && Some(bytes) == contents[0].as_ref().ok())
+        });
+        if !identical {
+            problems.push(format!("duplicate group {} lists files that differ", group.hash));
+        }
+    }
+    problems
+}
+
+/// Files and bytes below `root`, counted without the scanner
+fn tree_totals(root: &Path) -> (u64, u64) {
+    let mut totals = (0, 0);
+    let Ok(entries) = fs::read_dir(root) else { return totals };
+    for entry in entries.flatten() {
+        let Ok(metadata) = entry.metadata() else { continue };
+        if metadata.is_dir() {
+            let (files, bytes) = tree_totals(&entry.path());
+            totals = (totals.0 + files, totals.1 + bytes);
+        } else if metadata.is_file() {
+            totals = (totals.0 + 1, totals.1 + metadata.len());
+        }
+    }
+    totals
+}
+
+/// Temporary files that a finished run left in the self-test directory
+fn leftover_temp_files(dir: &Path) -> Vec<String> {
+    let mut leftovers = Vec::new();
+    let Ok(entries) = fs::read_dir(dir) else { return leftovers };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if entry.file_type().is_ok_and(|file_type| file_type.is_dir()) {
+            leftovers.extend(leftover_temp_files(&path));
+        } else if path.extension().is_some_and(|extension| extension == "tmp") {
+            leftovers.push(format!("temporary file left behind: {}", path.display()));
+        }
+    }
+    leftovers
+}
+
+fn generate_tree(root: &Path, rng: &mut Rng) -> Result<()> {
+    for directory in DIRECTORIES {
+        let dir = root.join(directory);
+        fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+        for index in 0..NAMES_PER_DIRECTORY / 2 {
+            let path = dir.join(file_name(index));
+            fs::write(&path, contents(rng)).with_context(|| format!("Failed to write {}", path.display()))?;
+        }
+    }
+    Ok(())
+}
+
+fn file_name(index: usize) -> String {
+    format!("file{:02}.bin", index)
+}
+
+/// One of a few contents per size, so that equal sizes often mean equal files
+fn contents(rng: &mut Rng) -> Vec<u8> {
+    let size = SIZES[rng.below(SIZES.len())];
+    let variant = rng.below(CONTENTS_PER_SIZE) as u8;
+    (0..size).map(|offset| variant.wrapping_add((offset % 251) as u8)).collect()
+}
+
+/// Threads that keep changing the tree until stopped
+struct Churn {
+    stop: Arc<AtomicBool>,
+    paused: Arc<AtomicBool>,
+    /// Operations under way; a pause waits for them to finish
+    active: Arc<AtomicUsize>,
+    operations: Arc<AtomicU64>,
+    threads: Vec<JoinHandle<()>>,
+}
+
+/// Keeps the churn paused while held
+struct Paused<'a>(&'a Churn);
+
+impl Drop for Paused<'_> {
+    fn drop(&mut self) {
+        self.0.paused.store(false, Ordering::SeqCst);
+    }
+}
+
+impl Churn {
+    fn start(root: &Path, seed: u64) -> Self {
+        let mut churn = Self {
+            stop: Arc::default(),
+            paused: Arc::default(),
+            active: Arc::default(),
+            operations: Arc::default(),
+            threads: Vec::new(),
+        };
+        for index in 0..CHURNERS {
+            let root = root.to_path_buf();
+            let mut rng = rng(seed, &format!("self-test churn {}", index));
+            let (stop, paused, active, operations) =
+                (churn.stop.clone(), churn.paused.clone(), churn.active.clone(), churn.operations.clone());
+            churn.threads.push(thread::spawn(move || {
+                while !stop.load(Ordering::SeqCst) {
+                    // Announced before the pause is checked, so that a pause never misses it
+                    active.fetch_add(1, Ordering::SeqCst);
+                    if paused.load(Ordering::SeqCst) {
+                        active.fetch_sub(1, Ordering::SeqCst);
+                        thread::sleep(Duration::from_millis(1));
+                        continue;
+                    }
+                    change_tree(&root, &mut rng);
+                    active.fetch_sub(1, Ordering::SeqCst);
+                    operations.fetch_add(1, Ordering::Relaxed);
+                }
+            }));
+        }
+        churn
+    }
+
+    /// Stop changing the tree until the returned guard is dropped
+    fn pause(&self) -> Paused<'_> {
+        self.paused.store(true, Ordering::SeqCst);
+        while self.active.load(Ordering::SeqCst) > 0 {
+            thread::yield_now();
+        }
+        Paused(self)
+    }
+
+    /// Stop the threads, returning the operations they made and any panics
+    fn stop(self) -> (u64, Vec<String>) {
+        self.stop.store(true, Ordering::SeqCst);
+        let panics = self
+            .threads
+            .into_iter()
+            .filter_map(|thread| thread.join().err())
+            .map(|payload| format!("churn thread panicked: {}", panic_message(payload)))
+            .collect();
+        (self.operations.load(Ordering::Relaxed), panics)
+    }
+}
+
+/// Create, rewrite, grow, or delete one file, or empty and refill a whole directory
+///
+/// Failures are expected: the other churner may have removed the directory.
+fn change_tree(root: &Path, rng: &mut Rng) {
+    let dir = root.join(DIRECTORIES[rng.below(DIRECTORIES.len())]);
+    let path = dir.join(file_name(rng.below(NAMES_PER_DIRECTORY)));
+    match rng.below(16) {
+        0..=5 => {
+            let _ = fs::create_dir_all(&dir);
+            let _ = fs::write(&path, contents(rng));
+        }
+        6..=9 => {
+            use std::io::Write;
+            if let Ok(mut file) = fs::OpenOptions::new().append(true).open(&path) {
+                let _ = file.write_all(&contents(rng));
+            }
+        }
+        10..=14 => {
+            let _ = fs::remove_file(&path);
+        }
+        _ => {
+            let _ = fs::remove_dir_all(&dir);
+            let _ = fs::create_dir_all(&dir);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_options_replay_from_the_seed() {
+        let draw = |seed| {
+            let mut plan = rng(seed, "self-test");
+            (0..20).map(|_| RunOptions::draw(&mut plan)).collect::<Vec<_>>()
+        };
+        assert_eq!(draw(7), draw(7));
+        assert_ne!(draw(7), draw(8));
+        let options = draw(7);
+        assert!(options.iter().all(|options| options.duplicates || !options.hash_cache));
+        assert!(options.iter().any(|options| options.max_files.is_some()) && options.iter().any(|options| options.concurrent));
+    }
+
+    #[test]
+    fn test_verification_catches_a_miscounted_tree() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("tree");
+        generate_tree(&root, &mut rng(1, "test")).unwrap();
+        let (files, bytes) = tree_totals(&root);
+        assert_eq!(files, (DIRECTORIES.len() * NAMES_PER_DIRECTORY / 2) as u64);
+
+        let mut results: AnalysisResults = serde_json::from_str(include_str!("../tests/golden/results.json")).unwrap();
+        results.duplicate_groups = None;
+        results.scan_info.total_files = files;
+        results.scan_info.total_size = bytes;
+        assert!(check_against_tree(&results, &root).is_empty());
+        results.scan_info.total_size += 1;
+        assert_eq!(check_against_tree(&results, &root).len(), 1);
+
+        fs::write(root.join("tmp/segment.tmp"), b"").unwrap();
+        assert_eq!(leftover_temp_files(dir.path()).len(), 1);
+    }
+
+    #[test]
+    fn test_short_self_test_passes() {
+        let report = SelfTest::new(Some(42)).duration(Duration::from_millis(300)).run().unwrap();
+        assert!(report.passed(), "{:?}", report.failures);
+        assert!(report.runs >= 1);
+        assert!(report.churn_operations > 0);
+    }
+}
@@ -0,0 +1,428 @@
+//! Pre-flight check that a run's outputs will fit
+//!
+//! Before scanning, the size of every file the run will write is estimated:
+//! the export, the undo journal, and the history update. The estimates are
+//! summed per filesystem and compared with what `statvfs` reports as free,
+//! so a long scan does not end in a failed write.
+//!
+//! Export sizes scale with the listed entries, which follow from `--top`,
+//! plus duplicate entries, which follow from the file count. That count comes
+//! from the last recorded run, or else from a bounded pre-count walk.
+//!
+//! `--check` also asks whether the destinations are writable at all and
+//! whether the scanned tree sits on a spinning disk.
+
+use crate::cli::{Args, ExportFormat};
+use crate::history::TRACKED_GROUPS;
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Fixed part of an export: scan info, statistics, file types
+const EXPORT_OVERHEAD: u64 = 16 * 1024;
+
+/// Distinct file types a report typically lists
+const TYPICAL_FILE_TYPES: u64 = 50;
+
+/// One in this many files is assumed to be a duplicate copy
+const DUPLICATE_FILE_SHARE: u64 = 10;
+
+/// Serialized size of one history record without duplicate data
+const HISTORY_RECORD_BYTES: u64 = 256;
+
+/// Serialized size of one tracked duplicate hash in a history record
+const HISTORY_GROUP_BYTES: u64 = 70;
+
+/// Serialized size of one undo journal entry
+const JOURNAL_ENTRY_BYTES: u64 = 512;
+
+/// The pre-count walk stops after this many entries
+pub const PRECOUNT_LIMIT: u64 = 200_000;
+
+/// Free space on the filesystem holding a path
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Space {
+    /// Identifies the filesystem, so outputs sharing one are summed
+    pub device: u64,
+    pub available_bytes: u64,
+    /// `None` where the filesystem does not limit inodes
+    pub available_inodes: Option<u64>,
+}
+
+/// A file the run is going to write
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlannedOutput {
+    pub purpose: &'static str,
+    /// Where it goes; it need not exist yet
+    pub directory: PathBuf,
+    pub bytes: u64,
+    pub inodes: u64,
+}
+
+/// A filesystem without room for the outputs planned on it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Shortfall {
+    pub purposes: Vec<&'static str>,
+    pub directory: PathBuf,
+    pub needed_bytes: u64,
+    pub available_bytes: u64,
+    pub needed_inodes: u64,
+    pub available_inodes: Option<u64>,
+}
+
+impl Shortfall {
+    pub fn describe(&self) -> String {
+        let mut text = format!(
+            "{} in {} need about {} but only {} is free",
+            self.purposes.join(" and "),
+            self.directory.display(),
+            humansize::format_size(self.needed_bytes, humansize::DECIMAL),
+            humansize::format_size(self.available_bytes, humansize::DECIMAL)
+        );
+        if let Some(inodes) = self.available_inodes.filter(|&inodes| inodes < self.needed_inodes) {
+            text.push_str(&format!(", and {} of {} inodes", inodes, self.needed_inodes));
+        }
+        text
+    }
+}
+
+/// Estimated size of an export of a tree with `expected_files` files
+pub fn export_bytes(format: &ExportFormat, expected_files: u64, top_count: usize, duplicates: bool) -> u64 {
+    let listed = 2 * top_count as u64;
+    let duplicate_files = if duplicates { expected_files / DUPLICATE_FILE_SHARE } else { 0 };
+    match format {
+        // Listed entries carry size, type, dates, and flags; duplicates are a path in a group
+        ExportFormat::Json => EXPORT_OVERHEAD + listed * 400 + TYPICAL_FILE_TYPES * 300 + duplicate_files * 200,
+        ExportFormat::Csv => listed * 200 + duplicate_files * 200,
+        ExportFormat::RmlintJson => duplicate_files * 500,
+        // Progress records come on top but stay small: one per interim report and hashing percent
+        ExportFormat::Ndjson => EXPORT_OVERHEAD + listed * 300 + duplicate_files * 200,
+        // The manifest holds what a JSON export does beyond the sharded lines
+        ExportFormat::JsonBundle => EXPORT_OVERHEAD + listed * 300 + TYPICAL_FILE_TYPES * 300 + duplicate_files * 200,
+    }
+}
+
+/// Estimated size of the undo journal of a dedupe run
+pub fn journal_bytes(expected_files: u64) -> u64 {
+    (expected_files / DUPLICATE_FILE_SHARE + 1) * JOURNAL_ENTRY_BYTES
+}
+
+/// Estimated space for appending to a history file of `existing` bytes
+///
+/// The file is rewritten next to itself before it replaces the old one.
+pub fn history_bytes(existing: u64, duplicates: bool) -> u64 {
+    let record = HISTORY_RECORD_BYTES + if duplicates { TRACKED_GROUPS as u64 * HISTORY_GROUP_BYTES } else { 0 };
+    existing + record
+}
+
+/// The outputs `args` asks for, given the expected file count and the history file, if any
+///
+/// `history` is the history file's path and current size.
+pub fn plan(args: &Args, expected_files: u64, history: Option<(&Path, u64)>) -> Vec<PlannedOutput> {
+    let mut outputs = Vec::new();
+    // A stream to stdout takes no space here
+    let to_stdout = args.output.as_deref().is_some_and(crate::export::stream::is_stdout);
+    if let Some(format) = args.export.as_ref().filter(|_| !to_stdout) {
+        let directory = match &args.output {
+            Some(output) => parent_of(output),
+            None => parent_of(Path::new(args.output_template.as_deref().unwrap_or_default())),
+        };
+        outputs.push(PlannedOutput {
+            purpose: "the export",
+            directory,
+            bytes: export_bytes(format, expected_files, args.top_count, args.find_duplicates),
+            inodes: 1,
+        });
+    }
+    if args.dedupe.is_some() {
+        let journal = args.journal.as_deref().unwrap_or(Path::new("journal"));
+        outputs.push(PlannedOutput {
+            purpose: "the undo journal",
+            directory: parent_of(journal),
+            bytes: journal_bytes(expected_files),
+            inodes: 1,
+        });
+    }
+    if let Some((path, existing)) = history {
+        outputs.push(PlannedOutput {
+            purpose: "the scan history",
+            directory: parent_of(path),
+            bytes: history_bytes(existing, args.find_duplicates),
+            // The rewritten file and the lock
+            inodes: 2,
+        });
+    }
+    outputs
+}
+
+fn parent_of(path: &Path) -> PathBuf {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    }
+}
+
+/// Files below `root` up to `max_depth`, counting at most [`PRECOUNT_LIMIT`]
+///
+/// Without `show_hidden`, hidden files are not counted and hidden directories
+/// are not entered, as in the scan itself.
+pub fn precount(root: &Path, max_depth: usize, show_hidden: bool) -> u64 {
+    WalkDir::new(root)
+        .max_depth(max_depth)
+        .into_iter()
+        .filter_entry(|entry| show_hidden || entry.depth() == 0 || !entry.file_name().to_string_lossy().starts_with('.'))
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .take(PRECOUNT_LIMIT as usize)
+        .count() as u64
+}
+
+/// Filesystems without room for `outputs`, with space looked up through `probe`
+///
+/// Outputs on the same filesystem are summed. A directory whose space cannot
+/// be determined is assumed to have room.
+pub fn shortfalls(outputs: &[PlannedOutput], probe: impl Fn(&Path) -> Result<Space>) -> Vec<Shortfall> {
+    let mut filesystems: Vec<(Space, Shortfall)> = Vec::new();
+    for output in outputs {
+        let Ok(space) = probe(&output.directory) else {
+            continue;
+        };
+        match filesystems.iter_mut().find(|(known, _)| known.device == space.device) {
+            Some((_, need)) => {
+                need.purposes.push(output.purpose);
+                need.needed_bytes += output.bytes;
+                need.needed_inodes += output.inodes;
+            }
+            None => filesystems.push((
+                space,
+                Shortfall {
+                    purposes: vec![output.purpose],
+                    directory: output.directory.clone(),
+                    needed_bytes: output.bytes,
+                    available_bytes: space.available_bytes,
+                    needed_inodes: output.inodes,
+                    available_inodes: space.available_inodes,
+                },
+            )),
+        }
+    }
+    filesystems
+        .into_iter()
+        .map(|(_, need)| need)
+        .filter(|need| {
+            need.needed_bytes > need.available_bytes
+                || need.available_inodes.is_some_and(|inodes| need.needed_inodes > inodes)
+        })
+        .collect()
+}
+
+/// Free space on the filesystem that holds `path`, or would hold it once created
+pub fn available(path: &Path) -> Result<Space> {
+    let absolute = std::path::absolute(path)?;
+    let existing = absolute
+        .ancestors()
+        .find(|ancestor| ancestor.exists())
+        .ok_or_else(|| anyhow::anyhow!("No existing ancestor of {}", path.display()))?;
+    statvfs(existing)
+}
+
+#[cfg(unix)]
+fn statvfs(path: &Path) -> Result<Space> {
+    use anyhow::Context;
+    use std::os::unix::ffi::OsStrExt;
+    use std::os::unix::fs::MetadataExt;
+
+    let c_path = std::ffi::CString::new(path.as_os_str().as_bytes())?;
+    // SAFETY: statvfs is plain old data, and all zeroes is a valid value for it
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    // SAFETY: the path is NUL-terminated and `stat` is a valid out pointer
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return Err(std::io::Error::last_os_error()).with_context(|| format!("statvfs failed for {}", path.display()));
+    }
+    Ok(Space {
+        device: std::fs::metadata(path)?.dev(),
+        available_bytes: stat.f_bavail as u64 * stat.f_frsize as u64,
+        // Filesystems that allocate inodes dynamically report none in total
+        available_inodes: (stat.f_files > 0).then_some(stat.f_favail as u64),
+    })
+}
+
+#[cfg(not(unix))]
+fn statvfs(path: &Path) -> Result<Space> {
+    anyhow::bail!("Free space of {} is unknown on this platform", path.display())
+}
+
+/// Check that files can be created in `directory`, or below its nearest existing ancestor
+pub fn writable(directory: &Path) -> Result<()> {
+    let absolute = std::path::absolute(directory)?;
+    let existing = absolute
+        .ancestors()
+        .find(|ancestor| std::fs::symlink_metadata(ancestor).is_ok())
+        .ok_or_else(|| anyhow::anyhow!("No existing ancestor of {}", directory.display()))?;
+    if !existing.is_dir() {
+        anyhow::bail!("{} is not a directory", existing.display());
+    }
+    access(existing)
+}
+
+#[cfg(unix)]
+fn access(directory: &Path) -> Result<()> {
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_path = std::ffi::CString::new(directory.as_os_str().as_bytes())?;
+    // SAFETY: the path is NUL-terminated
+    if unsafe { libc::access(c_path.as_ptr(), libc::W_OK | libc::X_OK) } != 0 {
+        let error = std::io::Error::last_os_error();
+        anyhow::bail!("Cannot create files in {}: {}", directory.display(), error);
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn access(_directory: &Path) -> Result<()> {
+    Ok(())
+}
+
+/// Whether the disk holding `path` spins, where the kernel says so
+///
+/// Read from `/sys/dev/block`; a partition defers to the disk it is on.
+/// Network and virtual filesystems have no block device and yield `None`.
+#[cfg(target_os = "linux")]
+pub fn is_rotational(path: &Path) -> Option<bool> {
+    use std::os::unix::fs::MetadataExt;
+
+    let device = std::fs::metadata(path).ok()?.dev();
+    let block = PathBuf::from(format!("/sys/dev/block/{}:{}", libc::major(device), libc::minor(device)));
+    let flag = std::fs::read_to_string(block.join("queue/rotational"))
+        .or_else(|_| std::fs::read_to_string(block.join("../queue/rotational")))
+        .ok()?;
+    Some(flag.trim() == "1")
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn is_rotational(_path: &Path) -> Option<bool> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    fn space(device: u64, available_bytes: u64, available_inodes: Option<u64>) -> Space {
+        Space { device, available_bytes, available_inodes }
+    }
+
+    fn output(purpose: &'static str, directory: &str, bytes: u64) -> PlannedOutput {
+        PlannedOutput { purpose, directory: PathBuf::from(directory), bytes, inodes: 1 }
+    }
+
+    #[test]
+    fn test_export_estimates() {
+        // Without duplicates only the listed entries grow the export
+        assert_eq!(export_bytes(&ExportFormat::Json, 5_000_000, 20, false), export_bytes(&ExportFormat::Json, 10, 20, false));
+        assert_eq!(export_bytes(&ExportFormat::Csv, 1_000, 20, false), 8_000);
+        assert_eq!(export_bytes(&ExportFormat::RmlintJson, 1_000, 20, true), 50_000);
+
+        // A million files with duplicates: 100k duplicate entries at 200 bytes
+        let json = export_bytes(&ExportFormat::Json, 1_000_000, 20, true);
+        assert_eq!(json, EXPORT_OVERHEAD + 40 * 400 + 50 * 300 + 20_000_000);
+    }
+
+    #[test]
+    fn test_history_and_journal_estimates() {
+        assert_eq!(history_bytes(10_000, false), 10_256);
+        assert_eq!(history_bytes(0, true), 256 + 7_000);
+        assert_eq!(journal_bytes(0), 512);
+        assert_eq!(journal_bytes(1_000), 101 * 512);
+    }
+
+    #[test]
+    fn test_plan_places_outputs() {
+        let args = Args::parse_from([
+            "diranalyzer", "--duplicates", "--export", "csv", "--output", "/mnt/out/r.csv", "--dedupe", "hardlink", "/data",
+        ]);
+        let history = Path::new("/cache/history/data.ndjson");
+        let outputs = plan(&args, 1_000, Some((history, 4_000)));
+
+        let placed: Vec<_> = outputs.iter().map(|output| (output.purpose, output.directory.clone())).collect();
+        assert_eq!(
+            placed,
+            [
+                ("the export", PathBuf::from("/mnt/out")),
+                ("the undo journal", PathBuf::from(".")),
+                ("the scan history", PathBuf::from("/cache/history")),
+            ]
+        );
+        assert_eq!(outputs[2].bytes, history_bytes(4_000, true));
+        assert!(plan(&Args::parse_from(["diranalyzer", "/data"]), 1_000, None).is_empty());
+    }
+
+    #[test]
+    fn test_writable_looks_at_the_nearest_existing_ancestor() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("file"), b"x").unwrap();
+        assert!(writable(&dir.path().join("not/yet/created")).is_ok());
+        assert!(writable(&dir.path().join("file/below")).is_err());
+    }
+
+    #[test]
+    fn test_shortfalls_sum_outputs_per_filesystem() {
+        let probe = |path: &Path| -> Result<Space> {
+            match path.to_str().unwrap() {
+                "/small/a" | "/small/b" => Ok(space(1, 1_000, None)),
+                "/big" => Ok(space(2, 1_000_000, Some(1))),
+                _ => anyhow::bail!("unknown"),
+            }
+        };
+
+        // Each fits alone, together they do not
+        let outputs = [output("the export", "/small/a", 600), output("the scan history", "/small/b", 600)];
+        let short = shortfalls(&outputs, probe);
+        assert_eq!(short.len(), 1);
+        assert_eq!(short[0].purposes, ["the export", "the scan history"]);
+        assert_eq!(short[0].needed_bytes, 1_200);
+        assert_eq!(short[0].describe(), "the export and the scan history in /small/a need about 1.20 kB but only 1 kB is free");
+
+        // Bytes are plentiful but two files need two inodes
+        let short = shortfalls(&[output("the export", "/big", 10), output("the undo journal", "/big", 10)], probe);
+        assert!(short[0].describe().ends_with(", and 1 of 2 inodes"), "{}", short[0].describe());
+
+        // Unknown space never blocks
+        assert!(shortfalls(&[output("the export", "/elsewhere", u64::MAX)], probe).is_empty());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_statvfs_wrapper() {
+        let dir = tempfile::tempdir().unwrap();
+        let here = available(dir.path()).unwrap();
+        assert!(here.available_bytes > 0);
+
+        // Paths that do not exist yet resolve to the filesystem they would be created on
+        let later = available(&dir.path().join("not/yet/created.json")).unwrap();
+        assert_eq!(later.device, here.device);
+    }
+
+    #[test]
+    fn test_precount_stops_at_the_depth_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join("a/b")).unwrap();
+        for name in ["one", "a/two", "a/b/three"] {
+            std::fs::write(dir.path().join(name), "x").unwrap();
+        }
+        assert_eq!(precount(dir.path(), 10, false), 3);
+        assert_eq!(precount(dir.path(), 2, false), 2);
+    }
+
+    #[test]
+    fn test_precount_skips_hidden_directories() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(dir.path().join(".cache/foo")).unwrap();
+        for name in ["one", ".hidden", ".cache/foo/huge.bin", ".cache/foo/other.bin"] {
+            std::fs::write(dir.path().join(name), "x").unwrap();
+        }
+        assert_eq!(precount(dir.path(), 10, false), 1);
+        assert_eq!(precount(dir.path(), 10, true), 4);
+    }
+}
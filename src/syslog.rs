@@ -0,0 +1,360 @@
+//! Structured run summaries for journald and syslog
+//!
+//! `--log-summary-to-syslog` sends one entry per run so fleet-wide collectors
+//! can aggregate results. The entry goes to journald's native socket with one
+//! field per value; when journald is unavailable it falls back to an RFC 5424
+//! message with structured data on `/dev/log`.
+
+use crate::analyzer::AnalysisResults;
+use anyhow::{Context, Result};
+use chrono::{SecondsFormat, Utc};
+use humansize::{format_size, DECIMAL};
+use std::io;
+
+/// Identifier collectors can filter on (`SYSLOG_IDENTIFIER` / APP-NAME)
+pub const IDENTIFIER: &str = "diranalyzer";
+
+/// Prefix of the custom journald fields
+const FIELD_PREFIX: &str = "DIRANALYZER_";
+
+/// Structured-data ID of the syslog fallback (32473 is the documentation enterprise number)
+const SD_ID: &str = "diranalyzer@32473";
+
+const JOURNALD_SOCKET: &str = "/run/systemd/journal/socket";
+const SYSLOG_SOCKET: &str = "/dev/log";
+
+/// Facility `user` (1), severity `info` (6)
+const PRIORITY_INFO: u8 = 6;
+const FACILITY_USER: u8 = 1;
+
+/// A destination accepting one datagram per log entry
+pub trait LogTransport {
+    fn send(&self, payload: &[u8]) -> io::Result<()>;
+}
+
+/// Datagram socket of a local log daemon
+pub struct UnixSocketTransport {
+    path: &'static str,
+}
+
+impl LogTransport for UnixSocketTransport {
+    fn send(&self, payload: &[u8]) -> io::Result<()> {
+        #[cfg(unix)]
+        {
+            let socket = std::os::unix::net::UnixDatagram::unbound()?;
+            socket.send_to(payload, self.path).map(|_| ())
+        }
+        #[cfg(not(unix))]
+        {
+            let _ = payload;
+            Err(io::Error::new(io::ErrorKind::Unsupported, format!("{} is not available", self.path)))
+        }
+    }
+}
+
+/// Send the run summary to journald, falling back to syslog
+pub fn log_summary(results: &AnalysisResults) -> Result<()> {
+    log_summary_via(
+        results,
+        &UnixSocketTransport { path: JOURNALD_SOCKET },
+        &UnixSocketTransport { path: SYSLOG_SOCKET },
+    )
+}
+
+/// Send the run summary through explicit transports
+pub fn log_summary_via(
+    results: &AnalysisResults,
+    journald: &dyn LogTransport,
+    syslog: &dyn LogTransport,
+) -> Result<()> {
+    let fields = summary_fields(results);
+    if journald.send(&journald_payload(&fields)).is_ok() {
+        return Ok(());
+    }
+    syslog
+        .send(syslog_message(&fields).as_bytes())
+        .context("Neither journald nor syslog accepted the summary")
+}
+
+/// Journald fields describing a run
+pub fn summary_fields(results: &AnalysisResults) -> Vec<(String, String)> {
+    let info = &results.scan_info;
+    let duplicate_waste = results.statistics.reclaimable_bytes;
+    let top_directory = results
+        .largest_directories
+        .first()
+        .map(|dir| dir.path.display().to_string())
+        .unwrap_or_default();
+    let error_count = info.error_count + results.phase_errors.len() as u64;
+
+    let message = format!(
+        "{}: {} in {} files, {} duplicate waste, {} errors",
+        info.path.display(),
+        format_size(info.total_size, DECIMAL),
+        info.total_files,
+        format_size(duplicate_waste, DECIMAL),
+        error_count
+    );
+
+    let mut fields = vec![
+        ("MESSAGE".to_string(), message),
+        ("PRIORITY".to_string(), PRIORITY_INFO.to_string()),
+        ("SYSLOG_IDENTIFIER".to_string(), IDENTIFIER.to_string()),
+    ];
+    for (name, value) in [
+        ("PATH", info.path.display().to_string()),
+        ("TOTAL_BYTES", info.total_size.to_string()),
+        ("FILE_COUNT", info.total_files.to_string()),
+        ("DUPLICATE_WASTE", duplicate_waste.to_string()),
+        ("TOP_DIRECTORY", top_directory),
+        ("ERROR_COUNT", error_count.to_string()),
+        ("PARTIAL", info.partial.is_some().to_string()),
+    ] {
+        fields.push((format!("{}{}", FIELD_PREFIX, name), value));
+    }
+    fields
+}
+
+/// Encode fields in journald's native protocol
+pub fn journald_payload(fields: &[(String, String)]) -> Vec<u8> {
+    let mut payload = Vec::new();
+    for (name, value) in fields {
+        payload.extend_from_slice(name.as_bytes());
+        if value.contains('\n') {
+            // Values with newlines use the length-prefixed binary form
+            payload.push(b'\n');
+            payload.extend_from_slice(&(value.len() as u64).to_le_bytes());
+        } else {
+            payload.push(b'=');
+        }
+        payload.extend_from_slice(value.as_bytes());
+        payload.push(b'\n');
+    }
+    payload
+}
+
+/// Format fields as an RFC 5424 message with structured data
+pub fn syslog_message(fields: &[(String, String)]) -> String {
+    let message = fields
+        .iter()
+        .find(|(name, _)| name == "MESSAGE")
+        .map(|(_, value)| value.as_str())
+        .unwrap_or_default();
+
+    let params: String = fields
+        .iter()
+        .filter_map(|(name, value)| {
+            let name = name.strip_prefix(FIELD_PREFIX)?.to_ascii_lowercase();
+            Some(format!(" {}=\"{}\"", name, escape_param(value)))
+        })
+        .collect();
+
+    format!(
+        "<{}>1 {} - {} {} - [{}{}] {}",
+        FACILITY_USER * 8 + PRIORITY_INFO,
+        Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+        IDENTIFIER,
+        std::process::id(),
+        SD_ID,
+        params,
+        message
+    )
+}
+
+/// Escape an SD-PARAM value (RFC 5424 section 6.3.3)
+fn escape_param(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, '"' | '\\' | ']') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analyzer::{Diagnostics, DirectoryInfo, ScanInfo, Statistics};
+    use crate::scanner::Coverage;
+    use crate::utils::SizeBreakdown;
+    use std::cell::RefCell;
+    use std::collections::HashMap;
+    use std::path::PathBuf;
+
+    /// Records payloads, optionally refusing them like an absent daemon
+    struct MockTransport {
+        available: bool,
+        sent: RefCell<Vec<Vec<u8>>>,
+    }
+
+    impl MockTransport {
+        fn new(available: bool) -> Self {
+            Self { available, sent: RefCell::new(Vec::new()) }
+        }
+    }
+
+    impl LogTransport for MockTransport {
+        fn send(&self, payload: &[u8]) -> io::Result<()> {
+            if !self.available {
+                return Err(io::Error::new(io::ErrorKind::NotFound, "no daemon"));
+            }
+            self.sent.borrow_mut().push(payload.to_vec());
+            Ok(())
+        }
+    }
+
+    fn results() -> AnalysisResults {
+        AnalysisResults {
+            scan_info: ScanInfo {
+                path: PathBuf::from("/srv/data"),
+                path_given: PathBuf::from("/srv/data"),
+                path_resolved: PathBuf::from("/srv/data"),
+                roots: vec![PathBuf::from("/srv/data")],
+                timestamp: Utc::now(),
+                depth_limit: Some(10),
+                min_depth: 0,
+                total_files: 1200,
+                total_directories: 40,
+                total_size: 5_000_000_000,
+                scan_duration_ms: 900,
+                coverage: Coverage::default(),
+                ownership: None,
+                date_filter: None,
+                error_count: 2,
+                xattr_total: None,
+                physical_size: None,
+                disk_usage: None,
+                depth_boundary: Default::default(),
+                partial: None,
+                seed: None,
+                clock_skew: None,
+                assumed_clock_skew_secs: None,
+                age_source: Default::default(),
+            },
+            size_breakdown: SizeBreakdown::default(),
+            file_type_distribution: HashMap::new(),
+            largest_files: Vec::new(),
+            largest_directories: vec![DirectoryInfo {
+                path: PathBuf::from("/srv/data/backups"),
+                size: 3_000_000_000,
+                file_count: 10,
+                recursive_file_count: 10,
+                subdirectory_count: 0,
+                recursive_subdirectory_count: 0,
+                annotation: None,
+                owner_mix: None,
+                pinned: false,
+                composition: Default::default(),
+                accepted: false,
+                root: 0,
+            }],
+            duplicate_groups: None,
+            statistics: Statistics {
+                files_per_second: 0.0,
+                bytes_per_second: 0,
+                memory_usage_mb: 0.0,
+                duplicate_groups: 0,
+                redundant_files: 0,
+                reclaimable_bytes: 700_000_000,
+                compression_ratio: 1.0,
+                resource_usage: Vec::new(),
+                io_latency: None,
+            },
+            trend: None,
+            phase_errors: Vec::new(),
+            scan_errors: Default::default(),
+            directory_filter: None,
+            largest_xattr_files: Vec::new(),
+            diagnostics: Diagnostics::default(),
+            residuals: Default::default(),
+            regenerable: Default::default(),
+            entropy: None,
+            resolved_groups: None,
+            case_collisions: None,
+            heuristic_versions: None,
+            symlink_report: None,
+            skipped_mounts: None,
+            empty_directories: Vec::new(),
+            empty_files: Vec::new(),
+            annotations: Vec::new(),
+            accepted: Default::default(),
+            report_filters: None,
+            file_size_range: None,
+            entries: None,
+            custom_metrics: Vec::new(),
+            extensions: Vec::new(),
+            data_formats: None,
+            owners: HashMap::new(),
+            security_audit: None,
+            age_breakdown: Default::default(),
+            stale_files: None,
+        }
+    }
+
+    fn field<'a>(fields: &'a [(String, String)], name: &str) -> &'a str {
+        &fields.iter().find(|(n, _)| n == name).unwrap().1
+    }
+
+    #[test]
+    fn test_summary_fields() {
+        let fields = summary_fields(&results());
+
+        assert_eq!(field(&fields, "SYSLOG_IDENTIFIER"), "diranalyzer");
+        assert_eq!(field(&fields, "DIRANALYZER_TOTAL_BYTES"), "5000000000");
+        assert_eq!(field(&fields, "DIRANALYZER_FILE_COUNT"), "1200");
+        assert_eq!(field(&fields, "DIRANALYZER_DUPLICATE_WASTE"), "700000000");
+        assert_eq!(field(&fields, "DIRANALYZER_TOP_DIRECTORY"), "/srv/data/backups");
+        assert_eq!(field(&fields, "DIRANALYZER_ERROR_COUNT"), "2");
+        assert_eq!(field(&fields, "DIRANALYZER_PARTIAL"), "false");
+        assert_eq!(
+            field(&fields, "MESSAGE"),
+            "/srv/data: 5 GB in 1200 files, 700 MB duplicate waste, 2 errors"
+        );
+    }
+
+    #[test]
+    fn test_journald_encoding() {
+        let fields = vec![
+            ("A".to_string(), "one".to_string()),
+            ("B".to_string(), "two\nlines".to_string()),
+        ];
+        let payload = journald_payload(&fields);
+
+        let mut expected = b"A=one\nB\n".to_vec();
+        expected.extend_from_slice(&9u64.to_le_bytes());
+        expected.extend_from_slice(b"two\nlines\n");
+        assert_eq!(payload, expected);
+    }
+
+    #[test]
+    fn test_falls_back_to_syslog() {
+        let journald = MockTransport::new(false);
+        let syslog = MockTransport::new(true);
+        log_summary_via(&results(), &journald, &syslog).unwrap();
+
+        let sent = syslog.sent.borrow();
+        let message = String::from_utf8(sent[0].clone()).unwrap();
+        assert!(message.starts_with("<14>1 "));
+        assert!(message.contains("[diranalyzer@32473 path=\"/srv/data\" total_bytes=\"5000000000\""));
+        assert!(message.ends_with("2 errors"));
+    }
+
+    #[test]
+    fn test_prefers_journald_and_reports_unreachable_daemons() {
+        let journald = MockTransport::new(true);
+        let syslog = MockTransport::new(true);
+        log_summary_via(&results(), &journald, &syslog).unwrap();
+        assert_eq!(journald.sent.borrow().len(), 1);
+        assert!(syslog.sent.borrow().is_empty());
+
+        let down = MockTransport::new(false);
+        assert!(log_summary_via(&results(), &down, &down).is_err());
+    }
+
+    #[test]
+    fn test_param_escaping() {
+        assert_eq!(escape_param(r#"a"b\c]d"#), r#"a\"b\\c\]d"#);
+    }
+}
@@ -1,81 +1,368 @@
 //! Utility functions and helper types
 
+pub mod latency;
+pub mod names;
+pub mod random;
+pub mod resource;
+pub mod time;
+
+pub use random::{random_seed, rng, Rng};
+
 use crate::scanner::ScanResults;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use crate::config::{ClassificationRule, RegenerableRules};
+use anyhow::{Context, Result};
+use regex::Regex;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
 use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// A file type category, interned by the [`FileTypeClassifier`] that named it
+///
+/// Ids are only meaningful to the classifier that handed them out; its
+/// [`type_name`](FileTypeClassifier::type_name) turns them back into names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct TypeId(u16);
 
 /// File type classifier for organizing files by category
+///
+/// Categories are interned: classifying a file yields a [`TypeId`] without
+/// allocating, and names are only looked up when results are built.
 pub struct FileTypeClassifier {
-    type_map: HashMap<String, String>,
+    /// Category names, indexed by [`TypeId`]; "Other" comes first
+    names: Vec<String>,
+    type_map: HashMap<String, TypeId>,
+    regenerable_extensions: HashSet<String>,
+    regenerable_paths: Vec<Vec<String>>,
+    /// Path rules in the order they are tried: configured ones, then the built-in ones
+    rules: Vec<CompiledRule>,
+}
+
+/// A classification rule ready to match, with the number of files it claimed
+struct CompiledRule {
+    pattern: String,
+    regex: Regex,
+    extensions: HashSet<String>,
+    category: TypeId,
+    builtin: bool,
+    hits: AtomicU64,
 }
 
+impl CompiledRule {
+    fn compile(rule: &ClassificationRule, category: TypeId, builtin: bool) -> Result<Self> {
+        let regex = glob_to_regex(&rule.path)
+            .and_then(|regex| Ok(Regex::new(&regex)?))
+            .with_context(|| format!("Invalid classification rule path: {}", rule.path))?;
+        Ok(Self {
+            pattern: rule.path.clone(),
+            regex,
+            extensions: rule.extensions.iter().map(|ext| ext.trim_start_matches('.').to_lowercase()).collect(),
+            category,
+            builtin,
+            hits: AtomicU64::new(0),
+        })
+    }
+
+    fn matches(&self, path: &str, extension: Option<&str>) -> bool {
+        (self.extensions.is_empty() || extension.is_some_and(|ext| self.extensions.contains(ext)))
+            && self.regex.is_match(path)
+    }
+}
+
+/// Files claimed by one classification rule, listed under `--verbose`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RuleHits {
+    pub pattern: String,
+    #[serde(default)]
+    pub extensions: Vec<String>,
+    pub category: String,
+    pub builtin: bool,
+    pub hits: u64,
+}
+
+/// Category of files that no rule or extension claims
+const OTHER: &str = "Other";
+
+/// Category of tabular, columnar, array, and database files, broken down by format in the report
+pub const DATA: &str = "Data";
+
+/// Extensions of the Data category
+const DATA_FORMATS: &[&str] = &["parquet", "hdf5", "h5", "csv", "feather", "npz", "sqlite", "arrow"];
+
+/// Compression suffixes that leave a data file in the Data category, as in `.csv.gz`
+const DATA_COMPRESSIONS: &[&str] = &["gz", "bz2", "xz", "zst", "snappy", "lz4"];
+
+/// Categories by extension
+const BUILTIN_TYPES: &[(&str, &[&str])] = &[
+    ("Documents", &["pdf", "doc", "docx", "txt", "rtf", "odt", "pages"]),
+    ("Images", &["jpg", "jpeg", "png", "gif", "bmp", "svg", "tiff", "webp", "ico"]),
+    ("Videos", &["mp4", "avi", "mkv", "mov", "wmv", "flv", "webm", "m4v"]),
+    ("Audio", &["mp3", "wav", "flac", "aac", "ogg", "wma", "m4a"]),
+    ("Archives", &["zip", "tar", "gz", "bz2", "xz", "7z", "rar", "tar.gz", "tar.bz2"]),
+    ("Code", &["rs", "py", "js", "ts", "html", "css", "cpp", "c", "h", "java", "go", "php"]),
+    ("Executables", &["exe", "bin", "app", "deb", "rpm", "msi", "dmg"]),
+    (DATA, DATA_FORMATS),
+];
+
+/// Path contexts that decide a file's category whatever its extension
+const BUILTIN_RULES: &[(&str, &str)] = &[
+    ("node_modules/**", "Dependencies"),
+    ("site-packages/**", "Dependencies"),
+    ("vendor/bundle/**", "Dependencies"),
+    (".cache/**", "Cache"),
+    ("__pycache__/**", "Cache"),
+    (".git/**", "Version Control"),
+];
+
+/// Translate a path glob into an anchored regex
+///
+/// `**/` matches any number of leading components, other `**` anything at
+/// all, `*` and `?` stay within one component, and so do classes such as
+/// `[ch]`, `[0-9]`, and `[!.]`. A `[` without a closing `]` is an error. A
+/// glob not starting with `/` may match from any component boundary, so
+/// "node_modules/**" covers every `node_modules` directory in the tree.
+pub(crate) fn glob_to_regex(glob: &str) -> Result<String> {
+    let mut regex = String::from(if glob.starts_with('/') { "^" } else { "(?:^|/)" });
+    let mut chars = glob.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '[' => {
+                let (class, len) = glob_class(chars.clone()).context("a `[` has no closing `]`")?;
+                regex.push_str(&class);
+                chars.nth(len - 1);
+            }
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                    regex.push_str("(?:.*/)?");
+                } else {
+                    regex.push_str(".*");
+                }
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            _ => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push('$');
+    Ok(regex)
+}
+
+/// The regex class of the glob class whose `[` came just before `chars`, and how many chars it takes up
+///
+/// A `]` first in the class, after any `!` or `^`, is a member; a `-` between
+/// two members makes a range, elsewhere it is a member too.
+fn glob_class(mut chars: impl Iterator<Item = char>) -> Option<(String, usize)> {
+    let mut members = Vec::new();
+    let mut len = 0;
+    let mut negated = false;
+    loop {
+        let c = chars.next()?;
+        len += 1;
+        match c {
+            '!' | '^' if len == 1 => negated = true,
+            ']' if !members.is_empty() => break,
+            _ => members.push(c),
+        }
+    }
+
+    let mut class = String::from(if negated { "[^/" } else { "[" });
+    for (i, &c) in members.iter().enumerate() {
+        let range = c == '-' && i > 0 && i + 1 < members.len();
+        if range || c.is_alphanumeric() {
+            class.push(c);
+        } else {
+            class.push('\\');
+            class.push(c);
+        }
+    }
+    class.push(']');
+    Some((class, len))
+}
+
+/// Extensions of build outputs that are rebuilt rather than worth reclaiming
+const REGENERABLE_EXTENSIONS: &[&str] = &["o", "obj", "a", "lo", "pyc", "pyo", "class", "rlib", "rmeta", "gch", "pch"];
+
+/// Cache and build directories whose contents regenerate on demand
+const REGENERABLE_PATHS: &[&str] = &[
+    "__pycache__",
+    ".mypy_cache",
+    ".pytest_cache",
+    ".cache/pip",
+    "node_modules/.cache",
+    ".gradle/caches",
+    "target/debug",
+    "target/release",
+];
+
 impl FileTypeClassifier {
     /// Create a new file type classifier
     pub fn new() -> Self {
-        let mut type_map = HashMap::new();
-        
-        // Documents
-        let documents = vec!["pdf", "doc", "docx", "txt", "rtf", "odt", "pages"];
-        for ext in documents {
-            type_map.insert(ext.to_string(), "Documents".to_string());
+        let mut classifier = Self {
+            names: vec![OTHER.to_string()],
+            type_map: HashMap::new(),
+            regenerable_extensions: HashSet::new(),
+            regenerable_paths: Vec::new(),
+            rules: Vec::new(),
+        };
+        for (category, extensions) in BUILTIN_TYPES {
+            let id = classifier.intern(category);
+            classifier.type_map.extend(extensions.iter().map(|ext| (ext.to_string(), id)));
         }
-        
-        // Images
-        let images = vec!["jpg", "jpeg", "png", "gif", "bmp", "svg", "tiff", "webp", "ico"];
-        for ext in images {
-            type_map.insert(ext.to_string(), "Images".to_string());
+        let data = classifier.intern(DATA);
+        for format in DATA_FORMATS {
+            classifier.type_map.extend(DATA_COMPRESSIONS.iter().map(|compression| (format!("{}.{}", format, compression), data)));
         }
-        
-        // Videos
-        let videos = vec!["mp4", "avi", "mkv", "mov", "wmv", "flv", "webm", "m4v"];
-        for ext in videos {
-            type_map.insert(ext.to_string(), "Videos".to_string());
+        for (path, category) in BUILTIN_RULES {
+            let rule = ClassificationRule { path: path.to_string(), extensions: Vec::new(), category: category.to_string() };
+            let id = classifier.intern(category);
+            classifier.rules.push(CompiledRule::compile(&rule, id, true).expect("built-in classification rules are valid"));
         }
-        
-        // Audio
-        let audio = vec!["mp3", "wav", "flac", "aac", "ogg", "wma", "m4a"];
-        for ext in audio {
-            type_map.insert(ext.to_string(), "Audio".to_string());
-        }
-        
-        // Archives
-        let archives = vec!["zip", "tar", "gz", "bz2", "xz", "7z", "rar", "tar.gz", "tar.bz2"];
-        for ext in archives {
-            type_map.insert(ext.to_string(), "Archives".to_string());
+        classifier.with_regenerable(&RegenerableRules::default())
+    }
+
+    /// Add path rules, tried in order before the built-in ones
+    pub fn with_rules(mut self, rules: &[ClassificationRule]) -> Result<Self> {
+        let compiled = rules
+            .iter()
+            .map(|rule| {
+                let id = self.intern(&rule.category);
+                CompiledRule::compile(rule, id, false)
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let first_builtin = self.rules.iter().position(|rule| rule.builtin).unwrap_or(self.rules.len());
+        self.rules.splice(first_builtin..first_builtin, compiled);
+        Ok(self)
+    }
+
+    /// The id of `category`, handing out the next one if it is new
+    fn intern(&mut self, category: &str) -> TypeId {
+        if let Some(index) = self.names.iter().position(|name| name == category) {
+            return TypeId(index as u16);
         }
-        
-        // Code
-        let code = vec!["rs", "py", "js", "ts", "html", "css", "cpp", "c", "h", "java", "go", "php"];
-        for ext in code {
-            type_map.insert(ext.to_string(), "Code".to_string());
+        let id = TypeId(u16::try_from(self.names.len()).expect("fewer than 65536 categories"));
+        self.names.push(category.to_string());
+        id
+    }
+
+    /// The category name of an id this classifier handed out
+    pub fn type_name(&self, id: TypeId) -> &str {
+        &self.names[id.0 as usize]
+    }
+
+    /// Every category this classifier knows, with its id
+    pub fn types(&self) -> impl Iterator<Item = (TypeId, &str)> {
+        self.names.iter().enumerate().map(|(index, name)| (TypeId(index as u16), name.as_str()))
+    }
+
+    /// How many files each path rule has claimed so far, in the order the rules are tried
+    pub fn rule_hits(&self) -> Vec<RuleHits> {
+        self.rules
+            .iter()
+            .map(|rule| {
+                let mut extensions: Vec<String> = rule.extensions.iter().cloned().collect();
+                extensions.sort();
+                RuleHits {
+                    pattern: rule.pattern.clone(),
+                    extensions,
+                    category: self.type_name(rule.category).to_string(),
+                    builtin: rule.builtin,
+                    hits: rule.hits.load(Ordering::Relaxed),
+                }
+            })
+            .collect()
+    }
+
+    /// Add rules tagging files as regenerable to the built-in ones
+    pub fn with_regenerable(mut self, rules: &RegenerableRules) -> Self {
+        let extensions = REGENERABLE_EXTENSIONS.iter().copied().chain(rules.extensions.iter().map(String::as_str));
+        self.regenerable_extensions
+            .extend(extensions.map(|ext| ext.trim_start_matches('.').to_lowercase()));
+
+        let paths = REGENERABLE_PATHS.iter().copied().chain(rules.paths.iter().map(String::as_str));
+        for path in paths {
+            let components: Vec<String> = path.split('/').filter(|c| !c.is_empty()).map(str::to_string).collect();
+            if !components.is_empty() && !self.regenerable_paths.contains(&components) {
+                self.regenerable_paths.push(components);
+            }
         }
-        
-        // Executables
-        let executables = vec!["exe", "bin", "app", "deb", "rpm", "msi", "dmg"];
-        for ext in executables {
-            type_map.insert(ext.to_string(), "Executables".to_string());
+        self
+    }
+
+    /// Whether a file is a build output or cache entry that regenerates on its own
+    pub fn is_regenerable(&self, path: &Path) -> bool {
+        if lowercase_extension(path).is_some_and(|ext| self.regenerable_extensions.contains(ext.as_ref())) {
+            return true;
         }
-        
-        Self { type_map }
+
+        let components: Vec<_> = path.components().map(|c| c.as_os_str().to_string_lossy()).collect();
+        self.regenerable_paths.iter().any(|rule| {
+            components.windows(rule.len()).any(|window| window.iter().zip(rule).all(|(c, r)| c == r))
+        })
     }
     
-    /// Classify a file by its extension
+    /// Classify a file by the first path rule it matches, or else by its extension
     pub fn classify(&self, path: &Path) -> String {
-        if let Some(extension) = path.extension() {
-            if let Some(ext_str) = extension.to_str() {
-                let ext_lower = ext_str.to_lowercase();
-                return self.type_map.get(&ext_lower)
-                    .cloned()
-                    .unwrap_or_else(|| "Other".to_string());
+        self.type_name(self.classify_id(path)).to_string()
+    }
+
+    /// Classify a file as [`classify`](Self::classify) does, without allocating for UTF-8 paths
+    pub fn classify_id(&self, path: &Path) -> TypeId {
+        let extension = lowercase_extension(path);
+        if !self.rules.is_empty() {
+            let path = path.to_string_lossy();
+            let path = match std::path::MAIN_SEPARATOR {
+                '/' => path,
+                separator => Cow::Owned(path.replace(separator, "/")),
+            };
+            if let Some(rule) = self.rules.iter().find(|rule| rule.matches(&path, extension.as_deref())) {
+                rule.hits.fetch_add(1, Ordering::Relaxed);
+                return rule.category;
             }
         }
-        "Other".to_string()
+        self.mapped_extension(path)
+            .and_then(|ext| self.type_map.get(ext.as_ref()).copied())
+            .unwrap_or(TypeId(0))
+    }
+
+    /// The extension that decides a file's category by extension: a known compound one such
+    /// as `tar.gz` or `csv.gz`, or else the last one, in lower case
+    pub fn mapped_extension<'a>(&self, path: &'a Path) -> Option<Cow<'a, str>> {
+        compound_extension(path)
+            .filter(|compound| self.type_map.contains_key(compound.as_ref()))
+            .or_else(|| lowercase_extension(path))
+    }
+}
+
+/// The last two extensions of `path` in lower case, as `csv.gz`
+fn compound_extension(path: &Path) -> Option<Cow<'_, str>> {
+    let name = path.file_name()?.to_str()?;
+    let mut parts = name.rsplitn(3, '.');
+    let (last, inner) = (parts.next()?, parts.next()?);
+    // A leading dot starts a hidden name rather than an extension
+    if parts.next().is_none_or(str::is_empty) {
+        return None;
+    }
+    let compound = &name[name.len() - last.len() - inner.len() - 1..];
+    Some(if compound.chars().any(char::is_uppercase) { Cow::Owned(compound.to_lowercase()) } else { Cow::Borrowed(compound) })
+}
+
+/// The extension of `path` in lower case, borrowed when it already is
+fn lowercase_extension(path: &Path) -> Option<Cow<'_, str>> {
+    let extension = path.extension()?.to_str()?;
+    Some(if extension.chars().any(char::is_uppercase) { Cow::Owned(extension.to_lowercase()) } else { Cow::Borrowed(extension) })
+}
+
+impl Default for FileTypeClassifier {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
 /// Size breakdown categorization
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct SizeBreakdown {
     pub small_files_count: u64,    // < 1MB
     pub small_files_size: u64,
@@ -88,36 +375,32 @@ pub struct SizeBreakdown {
 impl SizeBreakdown {
     /// Create size breakdown from scan results
     pub fn from_scan_results(results: &ScanResults) -> Self {
-        let mut breakdown = SizeBreakdown {
-            small_files_count: 0,
-            small_files_size: 0,
-            medium_files_count: 0,
-            medium_files_size: 0,
-            large_files_count: 0,
-            large_files_size: 0,
-        };
-        
+        let mut breakdown = SizeBreakdown::default();
+        for file in &results.files {
+            breakdown.add(file.counted_size());
+        }
+        breakdown
+    }
+
+    /// Count one file of `size` bytes
+    pub fn add(&mut self, size: u64) {
         const ONE_MB: u64 = 1_024 * 1_024;
         const HUNDRED_MB: u64 = 100 * ONE_MB;
-        
-        for file in &results.files {
-            match file.size {
-                size if size < ONE_MB => {
-                    breakdown.small_files_count += 1;
-                    breakdown.small_files_size += size;
-                }
-                size if size < HUNDRED_MB => {
-                    breakdown.medium_files_count += 1;
-                    breakdown.medium_files_size += size;
-                }
-                size => {
-                    breakdown.large_files_count += 1;
-                    breakdown.large_files_size += size;
-                }
+
+        match size {
+            size if size < ONE_MB => {
+                self.small_files_count += 1;
+                self.small_files_size += size;
+            }
+            size if size < HUNDRED_MB => {
+                self.medium_files_count += 1;
+                self.medium_files_size += size;
+            }
+            size => {
+                self.large_files_count += 1;
+                self.large_files_size += size;
             }
         }
-        
-        breakdown
     }
 }
 
@@ -149,19 +432,292 @@ pub fn calculate_percentage(part: u64, total: u64) -> f64 {
     }
 }
 
-/// Validate directory path
+/// Parse a human-readable size such as `1MB`, `512k`, `1.5GiB`, or `4096`
+///
+/// Decimal units (kB, MB, GB, TB) are powers of 1000 and binary units (KiB,
+/// MiB, GiB, TiB) powers of 1024; a bare `K`/`M`/`G`/`T` is decimal.
+pub fn parse_size(input: &str) -> Result<u64, String> {
+    let input = input.trim();
+    let split = input
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(input.len());
+    let (number, unit) = input.split_at(split);
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("invalid size '{}': expected a number with an optional unit", input))?;
+
+    let multiplier: u64 = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kb" => 1_000,
+        "m" | "mb" => 1_000_000,
+        "g" | "gb" => 1_000_000_000,
+        "t" | "tb" => 1_000_000_000_000,
+        "kib" => 1 << 10,
+        "mib" => 1 << 20,
+        "gib" => 1 << 30,
+        "tib" => 1 << 40,
+        other => return Err(format!("invalid size unit '{}' in '{}'", other, input)),
+    };
+    Ok((number * multiplier as f64).round() as u64)
+}
+
+/// Parse a human-readable duration such as `90s`, `10m`, `1.5h`, `2w`, `6months`, `1y`, or `300` (seconds)
+///
+/// A month counts 30 days and a year 365.
+pub fn parse_duration(input: &str) -> Result<std::time::Duration, String> {
+    let input = input.trim();
+    let split = input
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(input.len());
+    let (number, unit) = input.split_at(split);
+    let number: f64 = number
+        .parse()
+        .map_err(|_| format!("invalid duration '{}': expected a number with an optional unit", input))?;
+
+    let seconds = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "s" | "sec" => 1.0,
+        "m" | "min" => 60.0,
+        "h" => 3_600.0,
+        "d" => 86_400.0,
+        "w" => 7.0 * 86_400.0,
+        "mo" | "month" | "months" => 30.0 * 86_400.0,
+        "y" => 365.0 * 86_400.0,
+        other => return Err(format!("invalid duration unit '{}' in '{}'", other, input)),
+    };
+    std::time::Duration::try_from_secs_f64(number * seconds).map_err(|_| format!("invalid duration '{}'", input))
+}
+
+/// Parse `--depth`: a number of levels, or `0` or `unlimited` for no limit ([`UNLIMITED_DEPTH`](crate::cli::UNLIMITED_DEPTH))
+pub fn parse_depth(input: &str) -> Result<usize, String> {
+    match input.trim() {
+        "0" | "unlimited" => Ok(crate::cli::UNLIMITED_DEPTH),
+        levels => levels.parse().map_err(|_| format!("invalid depth '{}': expected a number of levels, or 0 or 'unlimited' for no limit", input)),
+    }
+}
+
+/// Format a count with thousands separators (`4213` → `4,213`)
+pub fn format_count(count: u64) -> String {
+    let digits = count.to_string();
+    let mut formatted = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            formatted.push(',');
+        }
+        formatted.push(digit);
+    }
+    formatted
+}
+
+/// Extract a readable message from a panic payload
+pub fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
+
+/// Most suggestions offered for a path that does not exist
+const MAX_SUGGESTIONS: usize = 3;
+
+/// Check that `path` is a readable directory, explaining what is wrong otherwise
+///
+/// A missing path comes with close matches for the first component that
+/// does not exist ("did you mean /home/alice?"), a file is called out as
+/// one, and an unreadable directory shows its owner and mode.
 pub fn validate_directory(path: &Path) -> anyhow::Result<()> {
-    if !path.exists() {
-        return Err(anyhow::anyhow!("Path does not exist: {}", path.display()));
+    let metadata = match std::fs::metadata(path) {
+        Ok(metadata) => metadata,
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => {
+            let suggestions = suggest_paths(path);
+            return Err(match suggestions.as_slice() {
+                [] => anyhow::anyhow!("Path does not exist: {}", path.display()),
+                [only] => anyhow::anyhow!("Path does not exist: {} (did you mean {}?)", path.display(), only.display()),
+                several => {
+                    let listed: Vec<String> = several.iter().map(|path| format!("  {}", path.display())).collect();
+                    anyhow::anyhow!("Path does not exist: {}\nDid you mean one of these?\n{}", path.display(), listed.join("\n"))
+                }
+            });
+        }
+        Err(error) if error.kind() == std::io::ErrorKind::PermissionDenied => {
+            return Err(anyhow::anyhow!("Cannot access {}: {}", path.display(), error));
+        }
+        Err(error) => return Err(error).with_context(|| format!("Cannot access {}", path.display())),
+    };
+
+    if !metadata.is_dir() {
+        let parent = path.parent().filter(|parent| !parent.as_os_str().is_empty()).unwrap_or(Path::new("."));
+        return Err(anyhow::anyhow!(
+            "Path is a file, not a directory: {} (diranalyzer analyzes directories; try its parent {})",
+            path.display(),
+            parent.display()
+        ));
     }
-    
-    if !path.is_dir() {
-        return Err(anyhow::anyhow!("Path is not a directory: {}", path.display()));
+
+    if let Err(error) = std::fs::read_dir(path) {
+        if error.kind() == std::io::ErrorKind::PermissionDenied {
+            return Err(anyhow::anyhow!(
+                "Cannot read directory {}: {}, mode {:04o}; try again with sudo",
+                path.display(),
+                describe_owner(&metadata),
+                mode_of(&metadata)
+            ));
+        }
+        return Err(error).with_context(|| format!("Cannot read directory {}", path.display()));
     }
-    
+
     Ok(())
 }
 
+/// Paths listed one per line, skipping blank lines and `#` comments, or separated by NUL bytes
+///
+/// NUL-separated lists, as `find -print0` writes them, are taken byte for byte
+/// apart from empty entries, so that names may hold newlines or start with `#`.
+pub fn read_path_list(mut reader: impl std::io::Read, nul_separated: bool) -> std::io::Result<Vec<std::path::PathBuf>> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+    let entries: Vec<&[u8]> = if nul_separated {
+        bytes.split(|&byte| byte == 0).filter(|entry| !entry.is_empty()).collect()
+    } else {
+        bytes
+            .split(|&byte| byte == b'\n')
+            .map(|line| line.strip_suffix(b"\r").unwrap_or(line))
+            .filter(|line| {
+                let trimmed = line.trim_ascii();
+                !trimmed.is_empty() && !trimmed.starts_with(b"#")
+            })
+            .collect()
+    };
+    Ok(entries.into_iter().map(path_from_bytes).collect())
+}
+
+fn path_from_bytes(bytes: &[u8]) -> std::path::PathBuf {
+    #[cfg(unix)]
+    {
+        use std::os::unix::ffi::OsStrExt;
+        std::path::PathBuf::from(std::ffi::OsStr::from_bytes(bytes))
+    }
+    #[cfg(not(unix))]
+    {
+        std::path::PathBuf::from(String::from_utf8_lossy(bytes).into_owned())
+    }
+}
+
+/// Existing paths close to one that does not exist, best match first
+///
+/// Walks up to the nearest existing ancestor, matches the first missing
+/// component against that ancestor's entries by edit distance, and follows
+/// the remaining components the same way as far as they go.
+pub fn suggest_paths(path: &Path) -> Vec<std::path::PathBuf> {
+    let components: Vec<_> = path.components().collect();
+    let Some(existing) = (0..components.len()).rev().find(|&len| {
+        let prefix: std::path::PathBuf = components[..len].iter().collect();
+        len > 0 && prefix.exists()
+    }) else {
+        return Vec::new();
+    };
+    let ancestor: std::path::PathBuf = components[..existing].iter().collect();
+    let missing = components[existing].as_os_str().to_string_lossy();
+    let rest = &components[existing + 1..];
+
+    let mut suggestions = Vec::new();
+    for candidate in close_matches(&ancestor, &missing) {
+        let mut suggestion = ancestor.join(candidate);
+        for component in rest {
+            let name = component.as_os_str();
+            if suggestion.join(name).exists() {
+                suggestion.push(name);
+            } else if let Some(best) = close_matches(&suggestion, &name.to_string_lossy()).into_iter().next() {
+                suggestion.push(best);
+            } else {
+                break;
+            }
+        }
+        if !suggestions.contains(&suggestion) {
+            suggestions.push(suggestion);
+        }
+        if suggestions.len() == MAX_SUGGESTIONS {
+            break;
+        }
+    }
+    suggestions
+}
+
+/// Entries of `dir` within typo distance of `name`, closest first
+fn close_matches(dir: &Path, name: &str) -> Vec<String> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    // One edit per three characters, at least one: short names only tolerate a single slip
+    let limit = name.chars().count().div_ceil(3).max(1);
+    let mut matches: Vec<(usize, String)> = entries
+        .flatten()
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter_map(|candidate| {
+            let distance = edit_distance(&name.to_lowercase(), &candidate.to_lowercase());
+            (distance <= limit).then_some((distance, candidate))
+        })
+        .collect();
+    matches.sort();
+    matches.into_iter().map(|(_, candidate)| candidate).collect()
+}
+
+/// Edit distance counting insertions, deletions, substitutions and swaps of adjacent characters
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut rows = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    rows[0] = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut distance = (rows[i - 1][j] + 1).min(rows[i][j - 1] + 1).min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = distance;
+        }
+    }
+    rows[a.len()][b.len()]
+}
+
+/// "owned by alice (uid 1000)" for an error message
+fn describe_owner(metadata: &std::fs::Metadata) -> String {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        let uid = metadata.uid();
+        match crate::scanner::user_name(uid) {
+            Some(name) => format!("owned by {} (uid {})", name, uid),
+            None => format!("owned by uid {}", uid),
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = metadata;
+        "owner unknown".to_string()
+    }
+}
+
+/// Permission bits of a path, as `ls -l` would show them in octal
+fn mode_of(metadata: &std::fs::Metadata) -> u32 {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        metadata.permissions().mode() & 0o7777
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = metadata;
+        0
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -178,6 +734,148 @@ mod tests {
         assert_eq!(classifier.classify(&PathBuf::from("unknown.xyz")), "Other");
     }
     
+    #[test]
+    fn test_data_formats_and_their_compressed_variants() {
+        let classifier = FileTypeClassifier::new();
+        for name in ["runs.parquet", "grid.hdf5", "table.CSV", "batch.npz", "app.sqlite", "frame.arrow", "logs.csv.gz", "part-0.parquet.snappy"] {
+            assert_eq!(classifier.classify(Path::new(name)), "Data", "{}", name);
+        }
+        assert_eq!(classifier.mapped_extension(Path::new("Logs.CSV.GZ")).as_deref(), Some("csv.gz"));
+        assert_eq!(classifier.mapped_extension(Path::new("a.b.parquet")).as_deref(), Some("parquet"));
+        // Other compressed files stay archives, and a leading dot is no extension
+        assert_eq!(classifier.classify(Path::new("notes.json.gz")), "Archives");
+        assert_eq!(classifier.classify(Path::new("backup.tar.gz")), "Archives");
+        assert_eq!(classifier.mapped_extension(Path::new(".csv.gz")).as_deref(), Some("gz"));
+    }
+
+    fn rule(path: &str, extensions: &[&str], category: &str) -> ClassificationRule {
+        ClassificationRule {
+            path: path.to_string(),
+            extensions: extensions.iter().map(|ext| ext.to_string()).collect(),
+            category: category.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_glob_translation() {
+        let matches = |glob: &str, path: &str| Regex::new(&glob_to_regex(glob).unwrap()).unwrap().is_match(path);
+
+        assert!(matches("node_modules/**", "/app/node_modules/left-pad/index.js"));
+        assert!(matches("node_modules/**", "node_modules/x.js"));
+        assert!(!matches("node_modules/**", "/app/my_node_modules/x.js"));
+        assert!(matches("**/.cache/**", "/home/alex/.cache/pip/x.whl"));
+        assert!(matches("*.json", "/srv/data/a.json"));
+        assert!(!matches("data/*.json", "/srv/data/2024/a.json"));
+        assert!(matches("data/**/*.json", "/srv/data/a.json"));
+        assert!(matches("data/**/*.json", "/srv/data/2024/01/a.json"));
+        assert!(matches("log?.txt", "/var/log1.txt"));
+        assert!(!matches("log?.txt", "/var/log12.txt"));
+        assert!(matches("/srv/**", "/srv/a/b"));
+        assert!(!matches("/srv/**", "/home/srv/a"));
+        assert!(matches("a+b/(x).txt", "/a+b/(x).txt"));
+    }
+
+    #[test]
+    fn test_glob_character_classes() {
+        let matches = |glob: &str, path: &str| Regex::new(&glob_to_regex(glob).unwrap()).unwrap().is_match(path);
+
+        assert!(matches("*.[ch]", "/src/main.c"));
+        assert!(matches("*.[ch]", "/src/main.h"));
+        assert!(!matches("*.[ch]", "/src/main.o"));
+        assert!(matches("IMG_[0-9]*", "/photos/IMG_0042.jpg"));
+        assert!(!matches("IMG_[0-9]*", "/photos/IMG_edit.jpg"));
+        assert!(matches("[!.]*.txt", "/notes/todo.txt"));
+        assert!(!matches("[!.]*.txt", "/notes/.todo.txt"));
+        // A negated class stays within one component
+        assert!(!matches("a[!x]b", "/a/b"));
+        // `]` first and `-` at either end are members; regex syntax is not
+        assert!(matches("[]-]x", "/]x"));
+        assert!(matches("[]-]x", "/-x"));
+        assert!(matches("v[.^]", "/v^"));
+        assert!(!matches("v[.^]", "/vv"));
+        assert!(matches("[[:x]", "/:"));
+    }
+
+    #[test]
+    fn test_unclosed_class_names_the_glob_and_option() {
+        for glob in ["[ab", "report[1.pdf", "[]", "*.[!"] {
+            assert!(glob_to_regex(glob).is_err(), "{}", glob);
+        }
+        let error = crate::scanner::ExcludePattern::glob("[ab").err().unwrap();
+        assert_eq!(format!("{:#}", error), "Invalid --exclude glob \"[ab\": a `[` has no closing `]`");
+        let error = crate::scanner::ExcludePattern::include_glob("src/[ab/**").err().unwrap();
+        assert!(format!("{:#}", error).starts_with("Invalid --include glob \"src/[ab/**\""));
+    }
+
+    #[test]
+    fn test_path_rules_come_before_extensions() {
+        let classifier = FileTypeClassifier::new();
+
+        assert_eq!(classifier.classify(Path::new("/app/node_modules/react/index.js")), "Dependencies");
+        assert_eq!(classifier.classify(Path::new("/app/src/index.js")), "Code");
+        assert_eq!(classifier.classify(Path::new("/home/alex/.cache/thumbnails/a.png")), "Cache");
+        assert_eq!(classifier.classify(Path::new("/repo/.git/objects/ab/cdef")), "Version Control");
+        assert_eq!(classifier.classify(Path::new("/repo/.gitignore")), "Other");
+    }
+
+    #[test]
+    fn test_first_matching_rule_wins() {
+        let classifier = FileTypeClassifier::new()
+            .with_rules(&[
+                rule("data/**", &["json", ".CSV"], "Data"),
+                rule("node_modules/fixtures/**", &[], "Test Data"),
+                rule("data/**", &[], "Data Misc"),
+            ])
+            .unwrap();
+
+        // Configured rules go before the built-in ones, in their own order
+        assert_eq!(classifier.classify(Path::new("/app/data/users.json")), "Data");
+        assert_eq!(classifier.classify(Path::new("/app/data/Users.CSV")), "Data");
+        assert_eq!(classifier.classify(Path::new("/app/data/notes.txt")), "Data Misc");
+        assert_eq!(classifier.classify(Path::new("/app/node_modules/fixtures/a.json")), "Test Data");
+        assert_eq!(classifier.classify(Path::new("/app/node_modules/react/package.json")), "Dependencies");
+        // A configured rule also outranks a built-in one matching the same file
+        assert_eq!(classifier.classify(Path::new("/app/node_modules/pkg/data/x.json")), "Data");
+        // Extension-limited rules skip files without an extension
+        assert_eq!(classifier.classify(Path::new("/app/data/README")), "Data Misc");
+        assert_eq!(classifier.classify(Path::new("/app/config.json")), "Other");
+    }
+
+    #[test]
+    fn test_rule_hits_are_counted() {
+        let classifier = FileTypeClassifier::new().with_rules(&[rule("data/**", &["json"], "Data")]).unwrap();
+        for path in ["/app/data/a.json", "/app/data/b.json", "/app/node_modules/x.js", "/app/main.rs"] {
+            classifier.classify(Path::new(path));
+        }
+
+        let hits = classifier.rule_hits();
+        assert_eq!(hits.len(), BUILTIN_RULES.len() + 1);
+        assert_eq!((hits[0].pattern.as_str(), hits[0].builtin, hits[0].hits), ("data/**", false, 2));
+        assert_eq!(hits[0].extensions, vec!["json"]);
+        let node_modules = hits.iter().find(|hit| hit.pattern == "node_modules/**").unwrap();
+        assert_eq!((node_modules.builtin, node_modules.hits), (true, 1));
+        assert_eq!(hits.iter().map(|hit| hit.hits).sum::<u64>(), 3);
+    }
+
+    #[test]
+    fn test_regenerable_rules() {
+        let classifier = FileTypeClassifier::new().with_regenerable(&RegenerableRules {
+            extensions: vec![".PART".to_string()],
+            paths: vec!["build/out/".to_string()],
+        });
+
+        assert!(classifier.is_regenerable(Path::new("/src/lib/parser.o")));
+        assert!(classifier.is_regenerable(Path::new("/src/app/__pycache__/main.cpython-312.pyc")));
+        assert!(classifier.is_regenerable(Path::new("/home/alex/.cache/pip/wheels/ab/numpy.whl")));
+        assert!(classifier.is_regenerable(Path::new("download.part")));
+        assert!(classifier.is_regenerable(Path::new("/repo/build/out/app")));
+
+        assert!(!classifier.is_regenerable(Path::new("/src/lib/parser.c")));
+        assert!(!classifier.is_regenerable(Path::new("/home/alex/pip/numpy.whl")));
+        assert!(!classifier.is_regenerable(Path::new("/repo/build/output/app")));
+        assert!(!FileTypeClassifier::new().is_regenerable(Path::new("download.part")));
+    }
+
     #[test]
     fn test_percentage_calculation() {
         assert_eq!(calculate_percentage(25, 100), 25.0);
@@ -193,4 +891,118 @@ mod tests {
         let duration = std::time::Duration::from_secs(65);
         assert_eq!(format_duration(duration), "1m 5s");
     }
+
+    #[test]
+    fn test_size_parsing() {
+        assert_eq!(parse_size("4096"), Ok(4096));
+        assert_eq!(parse_size("1MB"), Ok(1_000_000));
+        assert_eq!(parse_size("512k"), Ok(512_000));
+        assert_eq!(parse_size("1.5GiB"), Ok(1_610_612_736));
+        assert_eq!(parse_size("2 mib"), Ok(2 * 1_048_576));
+        assert!(parse_size("MB").is_err());
+        assert!(parse_size("10 parsecs").is_err());
+    }
+
+    #[test]
+    fn test_duration_parsing() {
+        use std::time::Duration;
+        assert_eq!(parse_duration("300"), Ok(Duration::from_secs(300)));
+        assert_eq!(parse_duration("90s"), Ok(Duration::from_secs(90)));
+        assert_eq!(parse_duration("10m"), Ok(Duration::from_secs(600)));
+        assert_eq!(parse_duration("1.5h"), Ok(Duration::from_secs(5_400)));
+        assert_eq!(parse_duration("30d"), Ok(Duration::from_secs(30 * 86_400)));
+        assert_eq!(parse_duration("2w"), Ok(Duration::from_secs(14 * 86_400)));
+        assert_eq!(parse_duration("1y"), Ok(Duration::from_secs(365 * 86_400)));
+        assert_eq!(parse_duration("6months"), Ok(Duration::from_secs(180 * 86_400)));
+        assert_eq!(parse_duration("1mo"), parse_duration("30d"));
+        assert!(parse_duration("10 days").is_err());
+        assert!(parse_duration("soon").is_err());
+    }
+
+    #[test]
+    fn test_depth_parsing() {
+        assert_eq!(parse_depth("3"), Ok(3));
+        assert_eq!(parse_depth("0"), Ok(crate::cli::UNLIMITED_DEPTH));
+        assert_eq!(parse_depth("unlimited"), Ok(crate::cli::UNLIMITED_DEPTH));
+        assert!(parse_depth("-1").is_err());
+        assert!(parse_depth("deep").is_err());
+    }
+
+    #[test]
+    fn test_count_formatting() {
+        assert_eq!(format_count(0), "0");
+        assert_eq!(format_count(999), "999");
+        assert_eq!(format_count(4213), "4,213");
+        assert_eq!(format_count(1_234_567), "1,234,567");
+    }
+
+    /// Candidate names for the typo tests: /home/{alice,alicia,bob}, /home/alice/projects, /srv
+    fn candidates() -> (tempfile::TempDir, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("tree");
+        for path in ["home/alice/projects", "home/alicia", "home/bob", "srv"] {
+            std::fs::create_dir_all(root.join(path)).unwrap();
+        }
+        std::fs::write(root.join("home/notes.txt"), "x").unwrap();
+        (dir, root)
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("home", "home"), 0);
+        assert_eq!(edit_distance("hoem", "home"), 1);
+        assert_eq!(edit_distance("hme", "home"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "bob"), 3);
+    }
+
+    #[test]
+    fn test_suggestions_for_a_mistyped_path() {
+        let (_dir, root) = candidates();
+
+        assert_eq!(suggest_paths(&root.join("hoem/alice")), vec![root.join("home/alice")]);
+        // Later components are corrected as well
+        assert_eq!(suggest_paths(&root.join("hoem/alcie/projcets")), vec![root.join("home/alice/projects")]);
+        // Several close names, closest first
+        assert_eq!(suggest_paths(&root.join("home/alica")), vec![root.join("home/alice"), root.join("home/alicia")]);
+        assert!(suggest_paths(&root.join("home/zed")).is_empty());
+        assert!(suggest_paths(&root.join("completely-different")).is_empty());
+    }
+
+    #[test]
+    fn test_path_lists_skip_blanks_and_comments() {
+        let listed = read_path_list("/data/a\n\n# generated by find\n  \n/data/b c\r\n/data/#tmp\n".as_bytes(), false).unwrap();
+        assert_eq!(listed, [PathBuf::from("/data/a"), PathBuf::from("/data/b c"), PathBuf::from("/data/#tmp")]);
+        let listed = read_path_list("/data/line\nbreak\0#kept\0\0".as_bytes(), true).unwrap();
+        assert_eq!(listed, [PathBuf::from("/data/line\nbreak"), PathBuf::from("#kept")]);
+    }
+
+    #[test]
+    fn test_validate_directory_explains_the_problem() {
+        let (_dir, root) = candidates();
+        assert!(validate_directory(&root.join("home")).is_ok());
+
+        let missing = validate_directory(&root.join("hoem/alice")).unwrap_err().to_string();
+        assert!(missing.contains(&format!("did you mean {}?", root.join("home/alice").display())), "{}", missing);
+
+        let file = validate_directory(&root.join("home/notes.txt")).unwrap_err().to_string();
+        assert!(file.contains("is a file, not a directory"), "{}", file);
+        assert!(file.contains(&root.join("home").display().to_string()), "{}", file);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unreadable_directory_shows_owner_and_mode() {
+        use std::os::unix::fs::PermissionsExt;
+        let (_dir, root) = candidates();
+        let locked = root.join("srv");
+        std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o300)).unwrap();
+        // Root reads through any mode, so there is nothing to report
+        if std::fs::read_dir(&locked).is_ok() {
+            return;
+        }
+        let error = validate_directory(&locked).unwrap_err().to_string();
+        std::fs::set_permissions(&locked, std::fs::Permissions::from_mode(0o755)).unwrap();
+        assert!(error.contains("mode 0300") && error.contains("owned by") && error.contains("sudo"), "{}", error);
+    }
 }
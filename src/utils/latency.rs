@@ -0,0 +1,323 @@
+//! Latency of individual filesystem operations, with `--io-latency-stats`
+//!
+//! A slow scan is either many fast operations or a few terrible ones. Each
+//! readdir step, stat, open, and read while hashing is timed into a histogram
+//! per operation, and the slowest single operations are kept with their paths.
+//!
+//! Histograms are log-linear like HDR histograms: every power of two is split
+//! into 16 buckets, so a recorded value is known within 1/16 of itself from
+//! nanoseconds to hours, and only buckets that were hit are stored. When the
+//! flag is off, [`IoLatency::Off`] skips the clock entirely.
+
+use serde::{Deserialize, Serialize};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// Bits of a value kept below its leading one; 16 buckets per power of two
+const SUB_BUCKET_BITS: u32 = 4;
+
+/// Slowest single operations kept for the report
+pub const SLOWEST_OPERATIONS: usize = 10;
+
+/// The kinds of filesystem operation that are timed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IoOperation {
+    /// One step of the directory walk, which reads directory entries
+    ReadDir,
+    /// Reading a file's metadata
+    Stat,
+    /// Opening a file to hash it
+    Open,
+    /// One read while hashing a file
+    Read,
+}
+
+impl IoOperation {
+    pub const ALL: [IoOperation; 4] = [IoOperation::ReadDir, IoOperation::Stat, IoOperation::Open, IoOperation::Read];
+
+    /// Short name, as in the report
+    pub fn label(self) -> &'static str {
+        match self {
+            IoOperation::ReadDir => "readdir",
+            IoOperation::Stat => "stat",
+            IoOperation::Open => "open",
+            IoOperation::Read => "read",
+        }
+    }
+}
+
+/// Counts of latencies in log-linear buckets
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LatencyHistogram {
+    pub count: u64,
+    pub sum_ns: u64,
+    pub min_ns: u64,
+    pub max_ns: u64,
+    /// (lowest value of the bucket in ns, count), ordered by bucket, hit buckets only
+    pub buckets: Vec<(u64, u64)>,
+}
+
+impl LatencyHistogram {
+    pub fn record(&mut self, nanos: u64) {
+        if self.count == 0 || nanos < self.min_ns {
+            self.min_ns = nanos;
+        }
+        self.max_ns = self.max_ns.max(nanos);
+        self.count += 1;
+        self.sum_ns = self.sum_ns.saturating_add(nanos);
+
+        let (lowest, _) = bucket_of(nanos);
+        match self.buckets.binary_search_by_key(&lowest, |&(bucket, _)| bucket) {
+            Ok(index) => self.buckets[index].1 += 1,
+            Err(index) => self.buckets.insert(index, (lowest, 1)),
+        }
+    }
+
+    /// The latency `quantile` (0 to 1) of recorded operations stay at or below
+    ///
+    /// This is the highest value of the bucket holding that rank, kept within
+    /// the recorded minimum and maximum, so it is never below the true value.
+    pub fn percentile(&self, quantile: f64) -> u64 {
+        if self.count == 0 {
+            return 0;
+        }
+        let rank = ((quantile.clamp(0.0, 1.0) * self.count as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for &(lowest, count) in &self.buckets {
+            seen += count;
+            if seen >= rank {
+                let (_, highest) = bucket_of(lowest);
+                return highest.clamp(self.min_ns, self.max_ns);
+            }
+        }
+        self.max_ns
+    }
+}
+
+/// Lowest and highest value of the bucket holding `nanos`
+fn bucket_of(nanos: u64) -> (u64, u64) {
+    let magnitude = u64::BITS - 1 - nanos.max(1).leading_zeros();
+    let Some(shift) = magnitude.checked_sub(SUB_BUCKET_BITS) else {
+        return (nanos, nanos);
+    };
+    let lowest = (nanos >> shift) << shift;
+    (lowest, lowest + ((1 << shift) - 1))
+}
+
+/// Latencies of one kind of operation
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct OperationLatency {
+    pub operation: IoOperation,
+    pub histogram: LatencyHistogram,
+}
+
+/// One of the slowest single operations of the run
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SlowOperation {
+    pub operation: IoOperation,
+    pub path: PathBuf,
+    pub nanos: u64,
+}
+
+/// Latencies of a run's filesystem operations, as exported
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct IoLatencyReport {
+    /// Operations that happened at least once, in [`IoOperation::ALL`] order
+    pub operations: Vec<OperationLatency>,
+    /// The slowest single operations, slowest first
+    pub slowest: Vec<SlowOperation>,
+}
+
+/// Histograms per operation and the slowest operations so far
+#[derive(Default)]
+pub struct LatencyRecorder {
+    histograms: [LatencyHistogram; IoOperation::ALL.len()],
+    /// Fastest of the kept operations on top, so it is the one evicted
+    slowest: BinaryHeap<Reverse<(u64, IoOperation, PathBuf)>>,
+}
+
+impl LatencyRecorder {
+    fn record(&mut self, operation: IoOperation, nanos: u64, path: &Path) {
+        self.histograms[operation as usize].record(nanos);
+        if self.slowest.len() < SLOWEST_OPERATIONS {
+            self.slowest.push(Reverse((nanos, operation, path.to_path_buf())));
+        } else if self.slowest.peek().is_some_and(|Reverse((fastest, _, _))| nanos > *fastest) {
+            self.slowest.pop();
+            self.slowest.push(Reverse((nanos, operation, path.to_path_buf())));
+        }
+    }
+
+    fn report(&self) -> IoLatencyReport {
+        let operations = IoOperation::ALL
+            .into_iter()
+            .zip(&self.histograms)
+            .filter(|(_, histogram)| histogram.count > 0)
+            .map(|(operation, histogram)| OperationLatency { operation, histogram: histogram.clone() })
+            .collect();
+        let mut slowest: Vec<SlowOperation> = self
+            .slowest
+            .iter()
+            .map(|Reverse((nanos, operation, path))| SlowOperation { operation: *operation, path: path.clone(), nanos: *nanos })
+            .collect();
+        slowest.sort_by(|a, b| b.nanos.cmp(&a.nanos).then_with(|| a.path.cmp(&b.path)));
+        IoLatencyReport { operations, slowest }
+    }
+}
+
+/// Where timed operations go: nowhere, or a recorder shared by the scan and the duplicate search
+#[derive(Clone, Default)]
+pub enum IoLatency {
+    #[default]
+    Off,
+    On(Arc<Mutex<LatencyRecorder>>),
+}
+
+impl IoLatency {
+    pub fn new(enabled: bool) -> Self {
+        if enabled {
+            IoLatency::On(Arc::default())
+        } else {
+            IoLatency::Off
+        }
+    }
+
+    /// The start of an operation, read from the clock only when recording
+    pub fn start(&self) -> Option<Instant> {
+        match self {
+            IoLatency::Off => None,
+            IoLatency::On(_) => Some(Instant::now()),
+        }
+    }
+
+    /// Record an operation on `path` that began at `started`
+    pub fn finish(&self, started: Option<Instant>, operation: IoOperation, path: &Path) {
+        let (IoLatency::On(recorded), Some(started)) = (self, started) else {
+            return;
+        };
+        let nanos = u64::try_from(started.elapsed().as_nanos()).unwrap_or(u64::MAX);
+        recorded.lock().unwrap_or_else(|e| e.into_inner()).record(operation, nanos, path);
+    }
+
+    /// Time `run` as an operation on `path`
+    pub fn time<T>(&self, operation: IoOperation, path: &Path, run: impl FnOnce() -> T) -> T {
+        let started = self.start();
+        let result = run();
+        self.finish(started, operation, path);
+        result
+    }
+
+    /// What was recorded so far, or `None` when not recording
+    pub fn report(&self) -> Option<IoLatencyReport> {
+        match self {
+            IoLatency::Off => None,
+            IoLatency::On(recorded) => Some(recorded.lock().unwrap_or_else(|e| e.into_inner()).report()),
+        }
+    }
+}
+
+/// A latency in the largest unit that keeps it above one: "850 ns", "12.5 µs", "3.2 ms", "1.50 s"
+pub fn format_latency(nanos: u64) -> String {
+    match nanos {
+        0..=999 => format!("{} ns", nanos),
+        1_000..=999_999 => format!("{:.1} µs", nanos as f64 / 1e3),
+        1_000_000..=999_999_999 => format!("{:.1} ms", nanos as f64 / 1e6),
+        _ => format!("{:.2} s", nanos as f64 / 1e9),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_buckets_keep_sixteen_steps_per_power_of_two() {
+        assert_eq!(bucket_of(0), (0, 0));
+        assert_eq!(bucket_of(15), (15, 15));
+        assert_eq!(bucket_of(16), (16, 16));
+        assert_eq!(bucket_of(31), (31, 31));
+        assert_eq!(bucket_of(32), (32, 33));
+        assert_eq!(bucket_of(1_000), (992, 1_023));
+        // Every bucket is within 1/16 of its values
+        for nanos in [100, 12_345, 9_876_543, 3_000_000_000_000] {
+            let (lowest, highest) = bucket_of(nanos);
+            assert!(lowest <= nanos && nanos <= highest);
+            assert!((highest - lowest) * 16 <= lowest);
+        }
+    }
+
+    #[test]
+    fn test_percentiles() {
+        let mut histogram = LatencyHistogram::default();
+        for nanos in 1..=100 {
+            histogram.record(nanos * 1_000);
+        }
+        assert_eq!((histogram.count, histogram.min_ns, histogram.max_ns), (100, 1_000, 100_000));
+        assert_eq!(histogram.sum_ns, 5_050_000);
+
+        for (quantile, exact) in [(0.5, 50_000), (0.95, 95_000), (0.99, 99_000)] {
+            let estimate = histogram.percentile(quantile);
+            assert!(estimate >= exact && estimate - exact <= exact / 16, "p{} = {}", quantile * 100.0, estimate);
+        }
+        assert_eq!(histogram.percentile(1.0), 100_000);
+        assert_eq!(histogram.percentile(0.0), 1_023);
+        assert_eq!(LatencyHistogram::default().percentile(0.5), 0);
+    }
+
+    #[test]
+    fn test_one_outlier_shows_in_max_and_p99_only() {
+        let mut histogram = LatencyHistogram::default();
+        for _ in 0..999 {
+            histogram.record(2_000);
+        }
+        histogram.record(4_000_000_000);
+        assert_eq!(histogram.percentile(0.5), 2_047);
+        assert_eq!(histogram.percentile(0.99), 2_047);
+        assert_eq!(histogram.max_ns, 4_000_000_000);
+        assert_eq!(histogram.buckets.len(), 2);
+    }
+
+    #[test]
+    fn test_recorder_keeps_the_slowest_paths() {
+        let mut recorder = LatencyRecorder::default();
+        for index in 0..20u64 {
+            recorder.record(IoOperation::Stat, index, Path::new(&format!("/r/{}", index)));
+        }
+        recorder.record(IoOperation::Open, 0, Path::new("/r/opened"));
+
+        let report = recorder.report();
+        let operations: Vec<_> = report.operations.iter().map(|entry| (entry.operation, entry.histogram.count)).collect();
+        assert_eq!(operations, [(IoOperation::Stat, 20), (IoOperation::Open, 1)]);
+        assert_eq!(report.slowest.len(), SLOWEST_OPERATIONS);
+        assert!(report.slowest.windows(2).all(|pair| pair[0].nanos >= pair[1].nanos));
+        assert_eq!(report.slowest.last().unwrap().path, PathBuf::from("/r/10"));
+    }
+
+    #[test]
+    fn test_on_times_operations() {
+        let latency = IoLatency::new(true);
+        assert_eq!(latency.time(IoOperation::Read, Path::new("/r/x"), || 7), 7);
+        let report = latency.report().unwrap();
+        assert_eq!(report.operations[0].histogram.count, 1);
+        assert_eq!(report.slowest[0].path, PathBuf::from("/r/x"));
+    }
+
+    #[test]
+    fn test_off_records_nothing() {
+        let latency = IoLatency::default();
+        assert!(latency.start().is_none());
+        assert_eq!(latency.time(IoOperation::Read, Path::new("/r/x"), || 7), 7);
+        assert!(latency.report().is_none());
+    }
+
+    #[test]
+    fn test_format_latency() {
+        assert_eq!(format_latency(850), "850 ns");
+        assert_eq!(format_latency(12_500), "12.5 µs");
+        assert_eq!(format_latency(3_200_000), "3.2 ms");
+        assert_eq!(format_latency(1_500_000_000), "1.50 s");
+    }
+}
@@ -0,0 +1,233 @@
+//! Filenames with their versioning decorations taken off
+//!
+//! Copies and revisions of a file tend to keep its name and gain a
+//! decoration: `report (copy).docx`, `photo (1).jpg`, `plan_v2.pdf`,
+//! `config.yaml.bak`, `notes_2024-03-01.txt`. [`strip_version_decorations`]
+//! takes these off, so that siblings sharing a base name can be grouped as
+//! probable versions of one file. The rules are a heuristic over names only;
+//! they say nothing about the contents.
+
+use regex::Regex;
+use std::sync::LazyLock;
+
+/// A calendar date as in ISO 8601, with or without dashes
+const DATE: &str = r"(?:19|20)\d{2}(?:-(?:0[1-9]|1[0-2])-(?:0[1-9]|[12]\d|3[01])|(?:0[1-9]|1[0-2])(?:0[1-9]|[12]\d|3[01]))";
+
+/// A time of day following a date, with optional seconds
+const TIME: &str = r"(?:[T_ -]?(?:[01]\d|2[0-3])[:.-]?[0-5]\d(?:[:.-]?[0-5]\d)?)?";
+
+/// Compression extensions that keep the extension before them, as in `.tar.gz`
+const COMPRESSION: [&str; 6] = ["gz", "bz2", "xz", "zst", "lz4", "z"];
+
+/// Backup endings of the whole name, as `.bak`, `.old.2`, or an editor's `~`
+static BACKUP_SUFFIX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)(?:\.(?:bak|old|orig|backup)(?:\.?\d+)?|~)$").expect("the backup pattern is valid"));
+
+/// Decorations of the stem, tried in order until none matches
+static STEM_DECORATIONS: LazyLock<Vec<Regex>> = LazyLock::new(|| {
+    [
+        // "Copy of report"
+        r"(?i)^copy of\s+".to_string(),
+        // "report (copy)", "report (Copy 2)", "report [copy]"
+        r"(?i)\s*[(\[]\s*copy(?:\s+\d+)?\s*[)\]]$".to_string(),
+        // "report - Copy", "report - Copy (2)", "report copy", "report_copy2"
+        r"(?i)(?:\s+-\s+|[ _-])copy(?:\s*\(\d+\)|[ _-]?\d+)?$".to_string(),
+        // "photo (1)", "photo[2]"
+        r"\s*[(\[]\d{1,3}[)\]]$".to_string(),
+        // "plan_v2", "plan-v1.3", "plan v3"
+        r"(?i)[ _.-]v\d+(?:\.\d+)*$".to_string(),
+        // "notes_2024-03-01", "notes-20240301T0930"
+        format!(r"[ _.-]{}{}$", DATE, TIME),
+        // "2024-03-01 notes"
+        format!(r"^{}{}[ _.-]+", DATE, TIME),
+    ]
+    .iter()
+    .map(|pattern| Regex::new(pattern).expect("the decoration patterns are valid"))
+    .collect()
+});
+
+/// The name of a file with its versioning decorations taken off
+///
+/// Backup endings of the whole name go first, then decorations of the stem
+/// before the extension: copy markers, `(N)` counters, `_vN` revisions, and
+/// ISO dates, repeatedly, so that `report_v2 (copy).docx` becomes
+/// `report.docx`. `.tar.gz`, `.sql.xz` and the like count as one extension.
+/// Case is kept. A name that would be left with an empty stem, such as
+/// `2024-03-01.log`, is returned unchanged.
+pub fn strip_version_decorations(name: &str) -> String {
+    let mut name = name;
+    while let Some(found) = BACKUP_SUFFIX.find(name).filter(|found| found.start() > 0) {
+        name = &name[..found.start()];
+    }
+
+    let (stem, extension) = split_extension(name);
+    let mut base = stem.to_string();
+    loop {
+        let stripped = STEM_DECORATIONS.iter().find_map(|pattern| {
+            let stripped = pattern.replace(&base, "");
+            (stripped != base).then(|| stripped.into_owned())
+        });
+        match stripped {
+            Some(stripped) => base = stripped.trim_end_matches([' ', '_', '-', '.']).to_string(),
+            None => break,
+        }
+    }
+
+    if base.is_empty() {
+        return name.to_string();
+    }
+    format!("{}{}", base, extension)
+}
+
+/// Split `name` before its extension, keeping the dot with the extension
+///
+/// A leading dot starts no extension, and a short extension starting with a
+/// letter before a compression extension belongs to it.
+fn split_extension(name: &str) -> (&str, &str) {
+    let Some(dot) = last_extension(name) else {
+        return (name, "");
+    };
+    let compressed = COMPRESSION.iter().any(|compression| name[dot + 1..].eq_ignore_ascii_case(compression));
+    match last_extension(&name[..dot]).filter(|&inner| compressed && dot - inner <= 5 && name[inner + 1..].starts_with(|c: char| c.is_ascii_alphabetic())) {
+        Some(inner) => name.split_at(inner),
+        None => name.split_at(dot),
+    }
+}
+
+/// Position of the dot before the extension of `name`, if it has one
+fn last_extension(name: &str) -> Option<usize> {
+    let dot = name.rfind('.').filter(|&dot| dot > 0 && dot + 1 < name.len())?;
+    name[dot + 1..].chars().all(|c| c.is_ascii_alphanumeric()).then_some(dot)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_strips(cases: &[(&str, &str)]) {
+        for (name, base) in cases {
+            assert_eq!(strip_version_decorations(name), *base, "stripping {:?}", name);
+        }
+    }
+
+    #[test]
+    fn test_copy_markers() {
+        assert_strips(&[
+            ("report (copy).docx", "report.docx"),
+            ("report (Copy 2).docx", "report.docx"),
+            ("report [copy].docx", "report.docx"),
+            ("report - Copy.docx", "report.docx"),
+            ("report - Copy (3).docx", "report.docx"),
+            ("report copy.docx", "report.docx"),
+            ("report_copy.docx", "report.docx"),
+            ("report-copy2.docx", "report.docx"),
+            ("Copy of report.docx", "report.docx"),
+        ]);
+    }
+
+    #[test]
+    fn test_counters() {
+        assert_strips(&[
+            ("photo (1).jpg", "photo.jpg"),
+            ("photo(12).jpg", "photo.jpg"),
+            ("photo [2].jpg", "photo.jpg"),
+            ("photo (1) (2).jpg", "photo.jpg"),
+        ]);
+    }
+
+    #[test]
+    fn test_revisions() {
+        assert_strips(&[
+            ("plan_v2.pdf", "plan.pdf"),
+            ("plan-V10.pdf", "plan.pdf"),
+            ("plan v3.pdf", "plan.pdf"),
+            ("plan_v1.3.pdf", "plan.pdf"),
+            ("notes.v2.txt", "notes.txt"),
+        ]);
+    }
+
+    #[test]
+    fn test_backup_endings() {
+        assert_strips(&[
+            ("config.yaml.bak", "config.yaml"),
+            ("config.yaml.BAK", "config.yaml"),
+            ("config.yaml.old", "config.yaml"),
+            ("config.yaml.orig", "config.yaml"),
+            ("config.yaml.backup", "config.yaml"),
+            ("config.yaml.bak2", "config.yaml"),
+            ("config.yaml.old.1", "config.yaml"),
+            ("config.yaml~", "config.yaml"),
+            ("config.yaml.bak.old", "config.yaml"),
+            ("Makefile.bak", "Makefile"),
+        ]);
+    }
+
+    #[test]
+    fn test_iso_dates() {
+        assert_strips(&[
+            ("notes_2024-03-01.txt", "notes.txt"),
+            ("notes-20240301.txt", "notes.txt"),
+            ("notes 2024-03-01T09:30.txt", "notes.txt"),
+            ("notes_2024-03-01_093015.txt", "notes.txt"),
+            ("notes.20240301-0930.txt", "notes.txt"),
+            ("2024-03-01 notes.txt", "notes.txt"),
+            ("20240301_notes.txt", "notes.txt"),
+            ("dump_2023-12-31.sql.gz", "dump.sql.gz"),
+        ]);
+    }
+
+    #[test]
+    fn test_decorations_combine() {
+        assert_strips(&[
+            ("report_final_v2 (copy).docx", "report_final.docx"),
+            ("report_v2_2024-03-01.docx", "report.docx"),
+            ("photo (1).jpg.bak", "photo.jpg"),
+            ("Copy of plan_v3 (2).pdf", "plan.pdf"),
+            ("backup_2024-01-05_v2.tar.gz", "backup.tar.gz"),
+        ]);
+    }
+
+    #[test]
+    fn test_undecorated_names_stay() {
+        assert_strips(&[
+            ("report.docx", "report.docx"),
+            ("photocopy.jpg", "photocopy.jpg"),
+            ("IMG_0001.jpg", "IMG_0001.jpg"),
+            ("track_v8engine.mp3", "track_v8engine.mp3"),
+            ("dev2.log", "dev2.log"),
+            ("version 2.txt", "version 2.txt"),
+            ("notes_2024-13-01.txt", "notes_2024-13-01.txt"),
+            ("build_12345678.log", "build_12345678.log"),
+            ("archive.tar.gz", "archive.tar.gz"),
+            ("README", "README"),
+            ("file.", "file."),
+        ]);
+    }
+
+    #[test]
+    fn test_hidden_files_and_names_of_only_decorations() {
+        assert_strips(&[
+            (".bashrc", ".bashrc"),
+            (".bashrc.bak", ".bashrc"),
+            (".env (1)", ".env"),
+            (".bak", ".bak"),
+            ("2024-03-01.log", "2024-03-01.log"),
+            ("(1).jpg", "(1).jpg"),
+            ("v2.pdf", "v2.pdf"),
+            ("copy.txt", "copy.txt"),
+        ]);
+    }
+
+    #[test]
+    fn test_extension_split() {
+        assert_eq!(split_extension("a.txt"), ("a", ".txt"));
+        assert_eq!(split_extension("a.tar.gz"), ("a", ".tar.gz"));
+        assert_eq!(split_extension("dump.sql.xz"), ("dump", ".sql.xz"));
+        assert_eq!(split_extension("photo.jpg.gz"), ("photo", ".jpg.gz"));
+        assert_eq!(split_extension("release-1.2.0.gz"), ("release-1.2.0", ".gz"));
+        assert_eq!(split_extension(".tar.gz"), (".tar", ".gz"));
+        assert_eq!(split_extension(".profile"), (".profile", ""));
+        assert_eq!(split_extension("report (copy)"), ("report (copy)", ""));
+        assert_eq!(split_extension("v1.3 draft"), ("v1.3 draft", ""));
+    }
+}
@@ -0,0 +1,201 @@
+//! Process resource accounting
+//!
+//! Samples CPU time, context switches, and storage reads so the report can
+//! show whether a run was CPU-bound (hashing) or IO-bound (cold cache).
+//! Platforms without `getrusage` or `/proc/self/io` report zeros / `None`.
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Cumulative resource usage of this process, or the difference between two samples
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct ResourceUsage {
+    pub user_cpu: Duration,
+    pub system_cpu: Duration,
+    pub voluntary_switches: u64,
+    pub involuntary_switches: u64,
+    /// Bytes fetched from storage (Linux only; page-cache hits are not counted)
+    pub bytes_read: Option<u64>,
+}
+
+/// Usage attributed to one analysis phase
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PhaseUsage {
+    pub phase: String,
+    pub usage: ResourceUsage,
+}
+
+impl ResourceUsage {
+    /// Sample the current process
+    pub fn now() -> Self {
+        let mut usage = rusage().unwrap_or_default();
+        usage.bytes_read = storage_bytes_read();
+        usage
+    }
+
+    /// Usage accumulated since `earlier`
+    pub fn since(&self, earlier: &ResourceUsage) -> ResourceUsage {
+        ResourceUsage {
+            user_cpu: self.user_cpu.saturating_sub(earlier.user_cpu),
+            system_cpu: self.system_cpu.saturating_sub(earlier.system_cpu),
+            voluntary_switches: self.voluntary_switches.saturating_sub(earlier.voluntary_switches),
+            involuntary_switches: self.involuntary_switches.saturating_sub(earlier.involuntary_switches),
+            bytes_read: self.bytes_read.zip(earlier.bytes_read).map(|(now, then)| now.saturating_sub(then)),
+        }
+    }
+
+    /// Combined user and system CPU time
+    pub fn cpu_time(&self) -> Duration {
+        self.user_cpu + self.system_cpu
+    }
+
+    /// Sum of several usages
+    pub fn total<'a>(usages: impl IntoIterator<Item = &'a ResourceUsage>) -> ResourceUsage {
+        usages.into_iter().fold(ResourceUsage::default(), |mut total, usage| {
+            total.user_cpu += usage.user_cpu;
+            total.system_cpu += usage.system_cpu;
+            total.voluntary_switches += usage.voluntary_switches;
+            total.involuntary_switches += usage.involuntary_switches;
+            total.bytes_read = match (total.bytes_read, usage.bytes_read) {
+                (Some(a), Some(b)) => Some(a + b),
+                (a, b) => a.or(b),
+            };
+            total
+        })
+    }
+}
+
+/// Records usage between consecutive phase boundaries
+pub struct PhaseRecorder {
+    last: ResourceUsage,
+    phases: Vec<PhaseUsage>,
+}
+
+impl PhaseRecorder {
+    /// Start recording from the current usage
+    pub fn start() -> Self {
+        Self { last: ResourceUsage::now(), phases: Vec::new() }
+    }
+
+    /// Close the current phase under `phase`
+    pub fn mark(&mut self, phase: &str) {
+        let now = ResourceUsage::now();
+        self.phases.push(PhaseUsage { phase: phase.to_string(), usage: now.since(&self.last) });
+        self.last = now;
+    }
+
+    /// Phases recorded so far, in order
+    pub fn finish(self) -> Vec<PhaseUsage> {
+        self.phases
+    }
+}
+
+#[cfg(unix)]
+fn rusage() -> Option<ResourceUsage> {
+    // SAFETY: getrusage only writes into the zeroed struct we pass
+    let usage = unsafe {
+        let mut usage: libc::rusage = std::mem::zeroed();
+        if libc::getrusage(libc::RUSAGE_SELF, &mut usage) != 0 {
+            return None;
+        }
+        usage
+    };
+    let duration = |time: libc::timeval| {
+        Duration::from_secs(time.tv_sec.max(0) as u64) + Duration::from_micros(time.tv_usec.max(0) as u64)
+    };
+
+    Some(ResourceUsage {
+        user_cpu: duration(usage.ru_utime),
+        system_cpu: duration(usage.ru_stime),
+        voluntary_switches: usage.ru_nvcsw.max(0) as u64,
+        involuntary_switches: usage.ru_nivcsw.max(0) as u64,
+        bytes_read: None,
+    })
+}
+
+#[cfg(not(unix))]
+fn rusage() -> Option<ResourceUsage> {
+    None
+}
+
+/// `read_bytes` from `/proc/self/io`
+fn storage_bytes_read() -> Option<u64> {
+    let io = std::fs::read_to_string("/proc/self/io").ok()?;
+    io.lines()
+        .find_map(|line| line.strip_prefix("read_bytes:"))
+        .and_then(|value| value.trim().parse().ok())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    fn busy_work() -> u64 {
+        let mut acc = 0u64;
+        for i in 0..2_000_000u64 {
+            acc = acc.wrapping_mul(31).wrapping_add(i ^ (acc >> 7));
+        }
+        let mut buffer = Vec::new();
+        std::fs::File::open("Cargo.toml").unwrap().read_to_end(&mut buffer).unwrap();
+        acc.wrapping_add(buffer.len() as u64)
+    }
+
+    #[test]
+    fn test_samples_are_monotonic() {
+        let first = ResourceUsage::now();
+        std::hint::black_box(busy_work());
+        let second = ResourceUsage::now();
+
+        assert!(second.user_cpu >= first.user_cpu);
+        assert!(second.system_cpu >= first.system_cpu);
+        assert!(second.voluntary_switches >= first.voluntary_switches);
+        assert!(second.involuntary_switches >= first.involuntary_switches);
+        if let (Some(before), Some(after)) = (first.bytes_read, second.bytes_read) {
+            assert!(after >= before);
+        }
+    }
+
+    #[test]
+    fn test_phase_usage_is_non_negative() {
+        let mut recorder = PhaseRecorder::start();
+        std::hint::black_box(busy_work());
+        recorder.mark("scan");
+        recorder.mark("analysis");
+        let phases = recorder.finish();
+
+        assert_eq!(phases.iter().map(|p| p.phase.as_str()).collect::<Vec<_>>(), ["scan", "analysis"]);
+        // Phases end where the next begins, so the sum matches one overall sample
+        let total = ResourceUsage::total(phases.iter().map(|p| &p.usage));
+        assert!(total.cpu_time() >= phases[0].usage.cpu_time());
+        #[cfg(unix)]
+        assert!(phases[0].usage.cpu_time() > Duration::ZERO);
+    }
+
+    #[test]
+    fn test_since_and_total() {
+        let earlier = ResourceUsage {
+            user_cpu: Duration::from_millis(1000),
+            voluntary_switches: 5,
+            bytes_read: Some(100),
+            ..Default::default()
+        };
+        let later = ResourceUsage {
+            user_cpu: Duration::from_millis(1500),
+            voluntary_switches: 7,
+            bytes_read: Some(400),
+            ..Default::default()
+        };
+
+        let delta = later.since(&earlier);
+        assert_eq!(delta.user_cpu, Duration::from_millis(500));
+        assert_eq!(delta.voluntary_switches, 2);
+        assert_eq!(delta.bytes_read, Some(300));
+        // A reversed pair never goes negative
+        assert_eq!(earlier.since(&later), ResourceUsage { bytes_read: Some(0), ..Default::default() });
+
+        let total = ResourceUsage::total([&delta, &delta]);
+        assert_eq!(total.voluntary_switches, 4);
+        assert_eq!(total.bytes_read, Some(600));
+    }
+}
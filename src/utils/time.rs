@@ -0,0 +1,148 @@
+//! Clock skew between the scanned filesystem and this machine
+//!
+//! An NFS server whose clock runs ahead stamps every file it writes "in the
+//! future". Such files look brand new to every age-based computation: they
+//! count as recent for `--entropy-sample` and as freshly used in the
+//! duplicate priority score. A few future mtimes are normal (files copied
+//! with their timestamps, a clock corrected since); a large share of them is
+//! systematic, and [`detect_skew`] reports it with the median offset.
+//!
+//! With `--assume-clock-skew`, ages are measured from [`age_reference`], which
+//! is the same as moving every mtime back by the skew. Displayed and exported
+//! mtimes stay as the filesystem reports them.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// Share of dated files that must lie in the future before skew is reported
+pub const SKEW_SHARE: f64 = 0.1;
+
+/// Future files needed before skew is reported, so a handful in a small tree are not
+pub const MIN_FILES_AHEAD: u64 = 10;
+
+/// Offsets up to this are clock jitter or files written during the scan, not skew
+pub const SKEW_TOLERANCE: Duration = Duration::from_secs(2);
+
+/// Systematic skew found among the scanned files' modification times
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ClockSkew {
+    /// Files dated more than [`SKEW_TOLERANCE`] after the scan
+    pub files_ahead: u64,
+    /// Files with a modification time at all
+    pub files_dated: u64,
+    /// Median of how far ahead the future files are, in seconds
+    pub median_ahead_secs: u64,
+}
+
+impl ClockSkew {
+    pub fn share(&self) -> f64 {
+        self.files_ahead as f64 / self.files_dated.max(1) as f64
+    }
+
+    pub fn median_ahead(&self) -> Duration {
+        Duration::from_secs(self.median_ahead_secs)
+    }
+}
+
+/// Systematic skew of `modified` relative to `now`, if more than [`SKEW_SHARE`] of the times lie ahead
+pub fn detect_skew(modified: impl IntoIterator<Item = DateTime<Utc>>, now: DateTime<Utc>) -> Option<ClockSkew> {
+    let mut tally = SkewTally::new(now);
+    modified.into_iter().for_each(|modified| tally.add(modified));
+    tally.finish()
+}
+
+/// Modification times counted as they arrive, for [`detect_skew`] without a list of them
+///
+/// Only the times ahead of `now` are kept, which a filesystem with a sound clock has few of.
+#[derive(Debug, Clone)]
+pub struct SkewTally {
+    /// `now` plus [`SKEW_TOLERANCE`]
+    ahead_from: Option<DateTime<Utc>>,
+    now: DateTime<Utc>,
+    files_dated: u64,
+    ahead: Vec<u64>,
+}
+
+impl SkewTally {
+    pub fn new(now: DateTime<Utc>) -> Self {
+        let ahead_from = chrono::Duration::from_std(SKEW_TOLERANCE).ok().and_then(|tolerance| now.checked_add_signed(tolerance));
+        Self { ahead_from, now, files_dated: 0, ahead: Vec::new() }
+    }
+
+    pub fn add(&mut self, modified: DateTime<Utc>) {
+        self.files_dated += 1;
+        if self.ahead_from.is_some_and(|ahead_from| modified > ahead_from) {
+            self.ahead.push((modified - self.now).num_seconds().max(0) as u64);
+        }
+    }
+
+    /// The skew, if more than [`SKEW_SHARE`] of the times added lie ahead
+    pub fn finish(mut self) -> Option<ClockSkew> {
+        let files_ahead = self.ahead.len() as u64;
+        if self.ahead_from.is_none() || files_ahead < MIN_FILES_AHEAD || (files_ahead as f64) <= SKEW_SHARE * self.files_dated as f64 {
+            return None;
+        }
+        let middle = self.ahead.len() / 2;
+        let (_, &mut median_ahead_secs, _) = self.ahead.select_nth_unstable(middle);
+        Some(ClockSkew { files_ahead, files_dated: self.files_dated, median_ahead_secs })
+    }
+}
+
+/// The time ages are measured from when the filesystem's clock runs `skew` ahead
+///
+/// Moving `now` forward by the skew ages every file as if its mtime had been
+/// moved back by it, without touching the mtimes themselves.
+pub fn age_reference(now: DateTime<Utc>, skew: Duration) -> DateTime<Utc> {
+    chrono::Duration::from_std(skew).ok().and_then(|skew| now.checked_add_signed(skew)).unwrap_or(now)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 6, 1, 12, 0, 0).unwrap()
+    }
+
+    /// `count` mtimes spread evenly from `from` to `to` seconds relative to now
+    fn spread(count: i64, from: i64, to: i64) -> Vec<DateTime<Utc>> {
+        (0..count).map(|i| now() + chrono::Duration::seconds(from + (to - from) * i / (count - 1).max(1))).collect()
+    }
+
+    #[test]
+    fn test_systematic_skew_is_detected_with_its_median() {
+        // A server two hours ahead wrote 300 files in the last hour of its clock; 700 are older
+        let mut mtimes = spread(300, 3_600, 7_200);
+        mtimes.extend(spread(700, -90 * 86_400, -86_400));
+        let skew = detect_skew(mtimes, now()).unwrap();
+        assert_eq!((skew.files_ahead, skew.files_dated), (300, 1_000));
+        assert!((5_390..=5_410).contains(&skew.median_ahead_secs), "median {}", skew.median_ahead_secs);
+        assert!((skew.share() - 0.3).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_scattered_future_files_are_not_skew() {
+        // 5% ahead: copies that kept a future timestamp, not a skewed clock
+        let mut mtimes = spread(50, 600, 86_400);
+        mtimes.extend(spread(950, -86_400, -1));
+        assert_eq!(detect_skew(mtimes, now()), None);
+
+        // A small tree needs more than a share
+        assert_eq!(detect_skew(spread(5, 3_600, 3_600), now()), None);
+        // Files written while the scan ran are within the tolerance
+        assert_eq!(detect_skew(spread(100, 0, 2), now()), None);
+        assert_eq!(detect_skew(Vec::new(), now()), None);
+    }
+
+    #[test]
+    fn test_correction_moves_the_age_reference() {
+        let skew = Duration::from_secs(7_200);
+        // Written an hour ago by a clock two hours ahead
+        let modified = now() + chrono::Duration::seconds(3_600);
+        assert_eq!(age_reference(now(), skew) - modified, chrono::Duration::seconds(3_600));
+        assert_eq!(age_reference(now(), Duration::ZERO), now());
+        assert_eq!(age_reference(now(), Duration::MAX), now());
+    }
+}
@@ -0,0 +1,132 @@
+//! Probable versions of a file, found by name
+//!
+//! `report (copy).docx`, `report_v2.docx`, and `report.docx.bak` next to
+//! `report.docx` are rarely byte-identical, so the duplicate search misses
+//! them, yet all but the newest are usually dead weight. With
+//! `--find-versions`, files in the same directory whose names are equal once
+//! [`strip_version_decorations`] has taken their decorations off are grouped,
+//! newest first, and the bytes of every member but the newest are counted as
+//! probably superseded.
+//!
+//! This is a heuristic over names. A group can hold files that merely look
+//! related, and the newest member is not always the one worth keeping.
+
+use crate::utils::names::strip_version_decorations;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Groups of probable versions found in a run
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct VersionReport {
+    /// Largest superseded bytes first
+    pub groups: Vec<VersionGroup>,
+    /// Members of every group but its newest
+    pub superseded_files: u64,
+    pub superseded_bytes: u64,
+}
+
+/// Files in one directory that share a base name once decorations are taken off
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VersionGroup {
+    pub directory: PathBuf,
+    /// The name every member strips down to
+    pub base_name: String,
+    /// Newest first; members without a modification time come last
+    pub members: Vec<VersionMember>,
+    /// Bytes of every member but the newest
+    pub superseded_bytes: u64,
+}
+
+/// One file of a [`VersionGroup`]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VersionMember {
+    pub name: String,
+    pub size: u64,
+    pub modified: Option<DateTime<Utc>>,
+}
+
+/// Collects the files of a run by directory and base name
+///
+/// Only names, sizes, and times are kept, so the memory needed grows with
+/// the number of files but not with the length of their paths.
+#[derive(Debug, Default)]
+pub struct VersionGrouper {
+    directories: HashMap<PathBuf, HashMap<String, Vec<VersionMember>>>,
+}
+
+impl VersionGrouper {
+    /// Add a file as the analysis classifies it
+    pub fn add(&mut self, path: &Path, size: u64, modified: Option<DateTime<Utc>>) {
+        let (Some(directory), Some(name)) = (path.parent(), path.file_name()) else { return };
+        let name = name.to_string_lossy().into_owned();
+        let base_name = strip_version_decorations(&name);
+        let names = match self.directories.get_mut(directory) {
+            Some(names) => names,
+            None => self.directories.entry(directory.to_path_buf()).or_default(),
+        };
+        names.entry(base_name).or_default().push(VersionMember { name, size, modified });
+    }
+
+    /// The groups of more than one file, with their superseded totals
+    pub fn finish(self) -> VersionReport {
+        let mut report = VersionReport::default();
+        for (directory, names) in self.directories {
+            for (base_name, mut members) in names.into_iter().filter(|(_, members)| members.len() > 1) {
+                // Newest first; `None` sorts below every time, so undated members come last
+                members.sort_by(|a, b| b.modified.cmp(&a.modified).then_with(|| a.name.cmp(&b.name)));
+                let superseded_bytes = members[1..].iter().map(|member| member.size).sum();
+                report.superseded_files += members.len() as u64 - 1;
+                report.superseded_bytes += superseded_bytes;
+                report.groups.push(VersionGroup { directory: directory.clone(), base_name, members, superseded_bytes });
+            }
+        }
+        report.groups.sort_by(|a, b| {
+            b.superseded_bytes
+                .cmp(&a.superseded_bytes)
+                .then_with(|| a.directory.cmp(&b.directory))
+                .then_with(|| a.base_name.cmp(&b.base_name))
+        });
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_groups_siblings_newest_first() {
+        let day = |day| Some(Utc.with_ymd_and_hms(2024, 3, day, 12, 0, 0).unwrap());
+        let mut grouper = VersionGrouper::default();
+        for (path, size, modified) in [
+            ("/d/report.docx", 300, day(5)),
+            ("/d/report (copy).docx", 200, day(1)),
+            ("/d/report_v2.docx", 250, day(9)),
+            ("/d/report.docx.bak", 100, None),
+            ("/d/photo.jpg", 1_000, day(1)),
+            ("/d/other.txt", 10, day(1)),
+            // Same base name in another directory is another group
+            ("/e/photo (1).jpg", 1_000, day(2)),
+            ("/e/photo.jpg", 1_000, day(3)),
+        ] {
+            grouper.add(Path::new(path), size, modified);
+        }
+        let report = grouper.finish();
+
+        // Most superseded bytes first
+        assert_eq!(report.groups.len(), 2);
+        assert_eq!(report.groups[0].directory, Path::new("/e"));
+        assert_eq!(report.groups[0].members[0].name, "photo.jpg");
+        assert_eq!(report.groups[0].superseded_bytes, 1_000);
+
+        let report_group = &report.groups[1];
+        assert_eq!((report_group.directory.as_path(), report_group.base_name.as_str()), (Path::new("/d"), "report.docx"));
+        let names: Vec<&str> = report_group.members.iter().map(|member| member.name.as_str()).collect();
+        assert_eq!(names, ["report_v2.docx", "report.docx", "report (copy).docx", "report.docx.bak"]);
+        assert_eq!(report_group.superseded_bytes, 600);
+        assert_eq!((report.superseded_files, report.superseded_bytes), (4, 1_600));
+    }
+}
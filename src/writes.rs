@@ -0,0 +1,505 @@
+//! The single path through which diranalyzer writes files
+//!
+//! Every export, history update, undo journal, and dedupe operation goes
+//! through a [`WriteGuard`]. An unrestricted guard writes anywhere. Under
+//! `--read-only` the guard protects the scanned tree and refuses anything that
+//! would land inside it, and [`WriteGuard::for_args`] rejects up front every
+//! option that implies such a write, before anything is scanned.
+
+use crate::cli::{Args, ExportFormat};
+use crate::config;
+use crate::export::{naming, stream};
+use anyhow::{bail, Context, Result};
+use regex::Regex;
+use std::fs::{self, File, OpenOptions};
+use std::path::{Path, PathBuf};
+
+/// Writes files, refusing any inside a protected tree
+#[derive(Debug, Clone, Default)]
+pub struct WriteGuard {
+    /// Canonical root of the tree no write may touch
+    protected: Option<PathBuf>,
+}
+
+impl WriteGuard {
+    /// A guard that permits every write
+    pub fn unrestricted() -> Self {
+        Self::default()
+    }
+
+    /// A guard that refuses writes inside `root`
+    pub fn read_only(root: &Path) -> Self {
+        Self { protected: Some(resolve(root)) }
+    }
+
+    /// The guard for a run, or an error naming every option that conflicts with `--read-only`
+    pub fn for_args(args: &Args) -> Result<Self> {
+        if !args.read_only {
+            return Ok(Self::unrestricted());
+        }
+        let guard = Self::read_only(&args.path);
+        let conflicts = guard.conflicting_options(args);
+        if !conflicts.is_empty() {
+            bail!("--read-only forbids options that write: {}", conflicts.join(", "));
+        }
+        Ok(guard)
+    }
+
+    /// Options of `args` that would write inside the protected tree
+    fn conflicting_options(&self, args: &Args) -> Vec<String> {
+        let mut conflicts = Vec::new();
+        if args.dedupe.is_some() {
+            conflicts.push("--dedupe".to_string());
+        }
+        if args.journal.is_some() {
+            conflicts.push("--journal".to_string());
+        }
+        if args.preserve_dir_mtime {
+            conflicts.push("--preserve-dir-mtime".to_string());
+        }
+        // Auto-named exports land in the working directory, which may well be the scanned tree
+        if args.output_template.is_some() {
+            conflicts.push("--output-template".to_string());
+        }
+        if args.keep_last.is_some() {
+            conflicts.push("--keep-last".to_string());
+        }
+        // The duplicate journal it would take over is not written under --read-only
+        if args.resume {
+            conflicts.push("--resume".to_string());
+        }
+        match (&args.export, &args.output) {
+            (Some(_), None) => conflicts.push("--export without --output".to_string()),
+            (_, Some(output)) if !stream::is_stdout(output) && !self.permits(output) => {
+                conflicts.push(format!("--output {} (inside the scanned tree)", output.display()))
+            }
+            _ => {}
+        }
+        conflicts
+    }
+
+    /// Whether the guard protects a tree
+    pub fn is_read_only(&self) -> bool {
+        self.protected.is_some()
+    }
+
+    /// Whether writing `path` is allowed
+    pub fn permits(&self, path: &Path) -> bool {
+        match &self.protected {
+            Some(root) => !resolve(path).starts_with(root),
+            None => true,
+        }
+    }
+
+    /// Fail unless writing `path` is allowed
+    pub fn check(&self, path: &Path) -> Result<()> {
+        match &self.protected {
+            Some(root) if !self.permits(path) => {
+                bail!("Refusing to write {} inside the read-only tree {}", path.display(), root.display())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Create or truncate a file
+    pub fn create(&self, path: &Path) -> Result<File> {
+        self.check(path)?;
+        File::create(path).with_context(|| format!("Failed to create {}", path.display()))
+    }
+
+    /// Open a file for writing with `options`
+    pub fn open(&self, path: &Path, options: &OpenOptions) -> Result<File> {
+        self.check(path)?;
+        options.open(path).with_context(|| format!("Failed to open {}", path.display()))
+    }
+
+    pub fn create_dir_all(&self, path: &Path) -> Result<()> {
+        self.check(path)?;
+        fs::create_dir_all(path).with_context(|| format!("Failed to create directory {}", path.display()))
+    }
+
+    pub fn rename(&self, from: &Path, to: &Path) -> Result<()> {
+        self.check(from)?;
+        self.check(to)?;
+        fs::rename(from, to).with_context(|| format!("Failed to move {} to {}", from.display(), to.display()))
+    }
+
+    pub fn remove_file(&self, path: &Path) -> Result<()> {
+        self.check(path)?;
+        fs::remove_file(path).with_context(|| format!("Failed to remove {}", path.display()))
+    }
+
+    pub fn remove_dir_all(&self, path: &Path) -> Result<()> {
+        self.check(path)?;
+        fs::remove_dir_all(path).with_context(|| format!("Failed to remove {}", path.display()))
+    }
+
+    pub fn hard_link(&self, original: &Path, link: &Path) -> Result<()> {
+        self.check(link)?;
+        fs::hard_link(original, link)
+            .with_context(|| format!("Failed to link {} to {}", link.display(), original.display()))
+    }
+
+    pub fn copy(&self, from: &Path, to: &Path) -> Result<u64> {
+        self.check(to)?;
+        fs::copy(from, to).with_context(|| format!("Failed to copy {}", from.display()))
+    }
+}
+
+/// Files and directories a run writes to, so the scan can leave them out
+///
+/// Assembled from the arguments before anything is scanned. Exports and undo
+/// journals are written to a hidden `.partial` sibling first, which counts as
+/// the same target.
+#[derive(Debug, Clone, Default)]
+pub struct WriteTargets {
+    targets: Vec<WriteTarget>,
+}
+
+/// One thing a run writes
+#[derive(Debug, Clone)]
+pub struct WriteTarget {
+    /// What the run writes there, as in "the export"
+    pub purpose: &'static str,
+    /// Resolved path of the file or directory
+    pub path: PathBuf,
+    pub kind: TargetKind,
+}
+
+/// How a [`WriteTarget`] covers the paths below it
+#[derive(Debug, Clone)]
+pub enum TargetKind {
+    /// Exactly the file at the path
+    File,
+    /// The directory at the path and everything in it
+    Directory,
+    /// Files in the directory at the path whose names match, as auto-named exports
+    Named(Regex),
+    /// Directories in the directory at the path whose names match, as auto-named bundles
+    NamedDirectories(Regex),
+}
+
+impl WriteTargets {
+    /// Everything `args` will have the run write, resolved like [`WriteGuard`] resolves paths
+    pub fn for_args(args: &Args) -> Self {
+        let mut targets = Vec::new();
+        let mut push = |purpose, path: &Path, kind| targets.push(WriteTarget { purpose, path: resolve(path), kind });
+
+        if let Some(format) = &args.export {
+            match &args.output {
+                Some(output) if stream::is_stdout(output) => {}
+                // A bundle is written into its hidden `.partial` sibling directory first
+                Some(output) if matches!(format, ExportFormat::JsonBundle) => {
+                    let name = output.file_name().unwrap_or_default().to_string_lossy();
+                    push("the export", output, TargetKind::Directory);
+                    push("the export", &output.with_file_name(format!(".{}.partial", name)), TargetKind::Directory);
+                }
+                Some(output) => push("the export", output, TargetKind::File),
+                None => {
+                    let template = args.output_template.as_deref().unwrap_or(naming::DEFAULT_TEMPLATE);
+                    let (directory, _) = template.rsplit_once('/').unwrap_or((".", template));
+                    // A placeholder in the directory leaves nothing to resolve before the run
+                    if !directory.contains('{') {
+                        if let Ok(pattern) = naming::retention_pattern(template, format.extension()) {
+                            let kind = match format {
+                                ExportFormat::JsonBundle => TargetKind::NamedDirectories(pattern),
+                                _ => TargetKind::Named(pattern),
+                            };
+                            push("auto-named exports", Path::new(directory), kind);
+                        }
+                    }
+                }
+            }
+        }
+        if args.dedupe.is_some() {
+            match &args.journal {
+                Some(journal) => push("the undo journal", journal, TargetKind::File),
+                None => push("undo journals", Path::new("."), TargetKind::Named(
+                    Regex::new(r"^diranalyzer-undo-\d{8}_\d{6}\.ndjson$").expect("the journal pattern is valid"),
+                )),
+            }
+        }
+        // History and the default hash cache live here
+        if let Some(cache) = config::cache_dir() {
+            push("the cache", &cache, TargetKind::Directory);
+        }
+        if let Some(cache) = &args.hash_cache_dir {
+            push("the hash cache", cache, TargetKind::Directory);
+        }
+        Self { targets }
+    }
+
+    /// The targets inside the tree at `root`, with their paths as the walk of `root` spells them
+    pub fn within(&self, root: &Path) -> Self {
+        let resolved_root = resolve(root);
+        let targets = self
+            .targets
+            .iter()
+            .filter_map(|target| {
+                let relative = target.path.strip_prefix(&resolved_root).ok()?;
+                Some(WriteTarget { path: root.join(relative), ..target.clone() })
+            })
+            .collect();
+        Self { targets }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.targets.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &WriteTarget> {
+        self.targets.iter()
+    }
+
+    /// Whether the run writes the file at `path`
+    pub fn covers_file(&self, path: &Path) -> bool {
+        let (Some(parent), Some(name)) = (path.parent(), path.file_name()) else {
+            return false;
+        };
+        let name = name.to_string_lossy();
+        // The hidden `.partial` sibling a file is written to first
+        let written = name.strip_prefix('.').and_then(|name| name.strip_suffix(".partial")).unwrap_or(&name);
+        self.targets.iter().any(|target| match &target.kind {
+            TargetKind::File => target.path.parent() == Some(parent) && target.path.file_name().is_some_and(|target| *target == *written),
+            TargetKind::Directory => path.starts_with(&target.path),
+            TargetKind::Named(pattern) => target.path == parent && pattern.is_match(written),
+            TargetKind::NamedDirectories(pattern) => path.ancestors().skip(1).any(|ancestor| is_named_directory(ancestor, &target.path, pattern)),
+        })
+    }
+
+    /// Whether the run writes the directory at `path` and everything below it
+    pub fn covers_directory(&self, path: &Path) -> bool {
+        self.targets.iter().any(|target| match &target.kind {
+            TargetKind::Directory => path.starts_with(&target.path),
+            TargetKind::NamedDirectories(pattern) => path.ancestors().any(|ancestor| is_named_directory(ancestor, &target.path, pattern)),
+            TargetKind::File | TargetKind::Named(_) => false,
+        })
+    }
+}
+
+/// Whether `path` is a directory in `directory` whose name, or that of the hidden `.partial` sibling it is written to, matches
+fn is_named_directory(path: &Path, directory: &Path, pattern: &Regex) -> bool {
+    let (Some(parent), Some(name)) = (path.parent(), path.file_name()) else {
+        return false;
+    };
+    let name = name.to_string_lossy();
+    let written = name.strip_prefix('.').and_then(|name| name.strip_suffix(".partial")).unwrap_or(&name);
+    parent == directory && pattern.is_match(written)
+}
+
+impl std::fmt::Display for WriteTarget {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.kind {
+            TargetKind::File | TargetKind::Directory => write!(f, "{} {}", self.purpose, self.path.display()),
+            TargetKind::Named(_) | TargetKind::NamedDirectories(_) => write!(f, "{} in {}", self.purpose, self.path.display()),
+        }
+    }
+}
+
+/// Exclusive advisory lock held for the lifetime of the value
+pub(crate) struct FileLock {
+    _file: File,
+}
+
+impl FileLock {
+    /// Lock `path`, creating it if needed, and wait for any other holder
+    pub(crate) fn acquire(path: &Path, writes: &WriteGuard) -> Result<Self> {
+        let file = Self::open(path, writes)?;
+        lock_exclusive(&file, true).with_context(|| format!("Failed to lock: {}", path.display()))?;
+        // The lock is released when the descriptor is closed
+        Ok(Self { _file: file })
+    }
+
+    /// Lock `path` unless another holder has it
+    pub(crate) fn try_acquire(path: &Path, writes: &WriteGuard) -> Result<Option<Self>> {
+        let file = Self::open(path, writes)?;
+        let locked = lock_exclusive(&file, false).with_context(|| format!("Failed to lock: {}", path.display()))?;
+        Ok(locked.then_some(Self { _file: file }))
+    }
+
+    fn open(path: &Path, writes: &WriteGuard) -> Result<File> {
+        writes
+            .open(path, OpenOptions::new().create(true).truncate(false).write(true))
+            .with_context(|| format!("Failed to open lock file: {}", path.display()))
+    }
+}
+
+/// Take an exclusive advisory lock on `file`, released when its descriptor closes
+///
+/// Without `wait`, returns `false` instead of blocking when another
+/// descriptor holds the lock.
+pub(crate) fn lock_exclusive(file: &File, wait: bool) -> std::io::Result<bool> {
+    #[cfg(unix)]
+    {
+        use std::os::unix::io::AsRawFd;
+        let operation = if wait { libc::LOCK_EX } else { libc::LOCK_EX | libc::LOCK_NB };
+        // SAFETY: flock only operates on the descriptor owned by `file`
+        if unsafe { libc::flock(file.as_raw_fd(), operation) } != 0 {
+            let error = std::io::Error::last_os_error();
+            return match error.kind() {
+                std::io::ErrorKind::WouldBlock => Ok(false),
+                _ => Err(error),
+            };
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = (file, wait);
+    }
+    Ok(true)
+}
+
+/// Absolute form of `path` with symlinks in its existing part resolved
+///
+/// A path that does not exist yet resolves through its nearest existing
+/// ancestor, so a symlink cannot smuggle a new file into the protected tree.
+fn resolve(path: &Path) -> PathBuf {
+    let absolute = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+    let mut existing = absolute.as_path();
+    let mut missing = Vec::new();
+    loop {
+        if let Ok(canonical) = fs::canonicalize(existing) {
+            return missing.iter().rev().fold(canonical, |resolved, name| resolved.join(name));
+        }
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                missing.push(name.to_os_string());
+                existing = parent;
+            }
+            _ => return absolute,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    fn tree() -> (tempfile::TempDir, PathBuf) {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("scanned");
+        fs::create_dir_all(root.join("sub")).unwrap();
+        (dir, root)
+    }
+
+    #[test]
+    fn test_refuses_writes_inside_the_tree() {
+        let (dir, root) = tree();
+        let guard = WriteGuard::read_only(&root);
+
+        assert!(guard.create(&root.join("sub/report.json")).is_err());
+        assert!(guard.create_dir_all(&root.join("new/deeper")).is_err());
+        assert!(!root.join("new").exists());
+        assert!(guard.rename(&dir.path().join("outside"), &root.join("inside")).is_err());
+
+        let outside = dir.path().join("report.json");
+        guard.create(&outside).unwrap();
+        assert!(outside.exists());
+        assert!(WriteGuard::unrestricted().create(&root.join("sub/report.json")).is_ok());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_symlinks_do_not_escape_the_guard() {
+        let (dir, root) = tree();
+        std::os::unix::fs::symlink(root.join("sub"), dir.path().join("shortcut")).unwrap();
+        let guard = WriteGuard::read_only(&root);
+
+        assert!(!guard.permits(&dir.path().join("shortcut/new.json")));
+        assert!(WriteGuard::read_only(&dir.path().join("shortcut")).check(&root.join("sub/x")).is_err());
+    }
+
+    #[test]
+    fn test_conflicting_options_are_listed() {
+        let (dir, root) = tree();
+        let inside = root.join("out.json");
+        let args = Args::parse_from([
+            "diranalyzer",
+            "--read-only",
+            "--export",
+            "json",
+            "--output",
+            inside.to_str().unwrap(),
+            root.to_str().unwrap(),
+        ]);
+        let error = WriteGuard::for_args(&args).unwrap_err().to_string();
+        assert!(error.contains("--output"), "{}", error);
+
+        let outside = dir.path().join("out.json");
+        let args = Args::parse_from([
+            "diranalyzer",
+            "--read-only",
+            "--export",
+            "json",
+            "--output",
+            outside.to_str().unwrap(),
+            root.to_str().unwrap(),
+        ]);
+        assert!(WriteGuard::for_args(&args).unwrap().is_read_only());
+    }
+
+    #[test]
+    fn test_write_targets_inside_the_tree() {
+        let (dir, root) = tree();
+        let args = Args::parse_from([
+            "diranalyzer",
+            "--export",
+            "json",
+            "--output",
+            root.join("sub/report.json").to_str().unwrap(),
+            "--dedupe",
+            "hardlink",
+            "--journal",
+            dir.path().join("undo.ndjson").to_str().unwrap(),
+            "--hash-cache-dir",
+            root.join("hashes").to_str().unwrap(),
+            root.to_str().unwrap(),
+        ]);
+        let targets = WriteTargets::for_args(&args).within(&root);
+
+        // The journal and the user's cache are outside the tree
+        let purposes: Vec<_> = targets.iter().map(|target| target.purpose).collect();
+        assert_eq!(purposes, ["the export", "the hash cache"]);
+        assert!(targets.covers_file(&root.join("sub/report.json")));
+        assert!(targets.covers_file(&root.join("sub/.report.json.partial")));
+        assert!(!targets.covers_file(&root.join("report.json")));
+        assert!(targets.covers_directory(&root.join("hashes")));
+        assert!(targets.covers_file(&root.join("hashes/0a/1.seg")));
+        assert!(!targets.covers_directory(&root.join("sub")));
+    }
+
+    #[test]
+    fn test_auto_named_exports_are_targets() {
+        let (_dir, root) = tree();
+        let template = format!("{}/reports/{{root}}_{{seq}}.{{ext}}", root.display());
+        let args = Args::parse_from(["diranalyzer", "--export", "csv", "--output-template", &template, root.to_str().unwrap()]);
+        let targets = WriteTargets::for_args(&args).within(&root);
+
+        assert!(targets.covers_file(&root.join("reports/scanned_3.csv")));
+        assert!(targets.covers_file(&root.join("reports/.scanned_3.csv.partial")));
+        assert!(!targets.covers_file(&root.join("reports/scanned_3.json")));
+        assert!(!targets.covers_file(&root.join("scanned_3.csv")));
+        assert_eq!(targets.iter().next().unwrap().to_string(), format!("auto-named exports in {}", root.join("reports").display()));
+    }
+
+    #[test]
+    fn test_bundles_are_directory_targets() {
+        let (_dir, root) = tree();
+        let template = format!("{}/reports/{{root}}_{{seq}}.{{ext}}", root.display());
+        let args = Args::parse_from(["diranalyzer", "--export", "json-bundle", "--output-template", &template, root.to_str().unwrap()]);
+        let targets = WriteTargets::for_args(&args).within(&root);
+
+        assert!(targets.covers_directory(&root.join("reports/scanned_3.bundle")));
+        assert!(targets.covers_directory(&root.join("reports/.scanned_3.bundle.partial")));
+        assert!(targets.covers_file(&root.join("reports/scanned_3.bundle/files-00001.ndjson")));
+        assert!(!targets.covers_file(&root.join("reports/scanned_3.bundle")));
+        assert!(!targets.covers_directory(&root.join("reports")));
+
+        let output = root.join("out.bundle");
+        let args = Args::parse_from(["diranalyzer", "--export", "json-bundle", "--output", output.to_str().unwrap(), root.to_str().unwrap()]);
+        let targets = WriteTargets::for_args(&args).within(&root);
+        assert!(targets.covers_directory(&output));
+        assert!(targets.covers_file(&root.join(".out.bundle.partial/manifest.json")));
+    }
+}
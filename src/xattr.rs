@@ -0,0 +1,128 @@
+//! Extended attribute size accounting
+//!
+//! Extended attributes (security labels, Finder metadata, resource forks)
+//! occupy space that never shows up in file sizes. Reading them costs extra
+//! syscalls per file, so the scanner only does it with `--include-xattrs`.
+
+use std::path::Path;
+
+/// Total size of the values of all extended attributes on `path`.
+///
+/// Symlinks are not followed. Platforms and filesystems without extended
+/// attribute support report zero.
+pub fn total_size(path: &Path) -> u64 {
+    imp::total_size(path).unwrap_or(0)
+}
+
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+mod imp {
+    use std::ffi::{CStr, CString};
+    use std::os::unix::ffi::OsStrExt;
+    use std::path::Path;
+
+    pub fn total_size(path: &Path) -> Option<u64> {
+        let path = CString::new(path.as_os_str().as_bytes()).ok()?;
+
+        // Query the size of the name list, then fetch it
+        let len = list(&path, std::ptr::null_mut(), 0)?;
+        if len == 0 {
+            return Some(0);
+        }
+        let mut names = vec![0u8; len];
+        let len = list(&path, names.as_mut_ptr().cast(), names.len())?;
+        names.truncate(len);
+
+        let total = names
+            .split_inclusive(|&byte| byte == 0)
+            .filter_map(|name| CStr::from_bytes_with_nul(name).ok())
+            .filter(|name| !name.is_empty())
+            .filter_map(|name| value_size(&path, name))
+            .sum();
+        Some(total)
+    }
+
+    #[cfg(target_os = "linux")]
+    fn list(path: &CStr, buffer: *mut libc::c_char, size: usize) -> Option<usize> {
+        // SAFETY: `buffer` is null with size 0 or points to `size` writable bytes
+        let len = unsafe { libc::llistxattr(path.as_ptr(), buffer, size) };
+        usize::try_from(len).ok()
+    }
+
+    #[cfg(target_os = "linux")]
+    fn value_size(path: &CStr, name: &CStr) -> Option<u64> {
+        // SAFETY: a null buffer with size 0 only queries the value length
+        let len = unsafe { libc::lgetxattr(path.as_ptr(), name.as_ptr(), std::ptr::null_mut(), 0) };
+        u64::try_from(len).ok()
+    }
+
+    #[cfg(target_os = "macos")]
+    fn list(path: &CStr, buffer: *mut libc::c_char, size: usize) -> Option<usize> {
+        // SAFETY: `buffer` is null with size 0 or points to `size` writable bytes
+        let len = unsafe { libc::listxattr(path.as_ptr(), buffer, size, libc::XATTR_NOFOLLOW) };
+        usize::try_from(len).ok()
+    }
+
+    #[cfg(target_os = "macos")]
+    fn value_size(path: &CStr, name: &CStr) -> Option<u64> {
+        // SAFETY: a null buffer with size 0 only queries the value length
+        let len = unsafe {
+            libc::getxattr(path.as_ptr(), name.as_ptr(), std::ptr::null_mut(), 0, 0, libc::XATTR_NOFOLLOW)
+        };
+        u64::try_from(len).ok()
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+mod imp {
+    use std::path::Path;
+
+    pub fn total_size(_path: &Path) -> Option<u64> {
+        None
+    }
+}
+
+#[cfg(all(test, any(target_os = "linux", target_os = "macos")))]
+mod tests {
+    use super::*;
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    /// Set an attribute, returning false when the filesystem does not support them
+    fn set_xattr(path: &Path, name: &str, value: &[u8]) -> bool {
+        let path = CString::new(path.as_os_str().as_bytes()).unwrap();
+        let name = CString::new(name).unwrap();
+        // SAFETY: all pointers are valid for the duration of the call
+        let result = unsafe {
+            #[cfg(target_os = "linux")]
+            {
+                libc::setxattr(path.as_ptr(), name.as_ptr(), value.as_ptr().cast(), value.len(), 0)
+            }
+            #[cfg(target_os = "macos")]
+            {
+                libc::setxattr(path.as_ptr(), name.as_ptr(), value.as_ptr().cast(), value.len(), 0, 0)
+            }
+        };
+        result == 0
+    }
+
+    #[test]
+    fn test_sums_attribute_values() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("labelled.txt");
+        std::fs::write(&file, b"content").unwrap();
+
+        if !set_xattr(&file, "user.diranalyzer.a", &[1u8; 100]) {
+            eprintln!("skipping: extended attributes unsupported here");
+            return;
+        }
+        assert!(set_xattr(&file, "user.diranalyzer.b", &[2u8; 23]));
+
+        // Security labels (e.g. SELinux) may add to the total, never subtract
+        assert!(total_size(&file) >= 123);
+    }
+
+    #[test]
+    fn test_missing_file_reports_zero() {
+        assert_eq!(total_size(Path::new("/nonexistent/diranalyzer-xattr")), 0);
+    }
+}
@@ -0,0 +1,82 @@
+//! Allocations on the per-file classification path
+//!
+//! Every collected file is classified once, tens of millions of times on a
+//! large scan. This binary wraps the global allocator to count the
+//! allocations of the calling thread, and checks that classifying by type id
+//! makes none while classifying by name makes one per file. Run it with
+//! `--nocapture` to see the counts and the throughput of both.
+
+use diranalyzer::utils::FileTypeClassifier;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::path::PathBuf;
+use std::time::Instant;
+
+struct Counting;
+
+thread_local! {
+    static ALLOCATIONS: Cell<u64> = const { Cell::new(0) };
+}
+
+// SAFETY: every call is passed on to the system allocator unchanged
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+/// Allocations `run` makes on this thread, and how long it takes
+fn measure(run: impl FnOnce()) -> (u64, std::time::Duration) {
+    let before = ALLOCATIONS.with(Cell::get);
+    let started = Instant::now();
+    run();
+    (ALLOCATIONS.with(Cell::get) - before, started.elapsed())
+}
+
+#[test]
+fn test_classifying_by_id_does_not_allocate() {
+    const EXTENSIONS: [&str; 8] = ["rs", "jpg", "mp4", "tar", "txt", "xyz", "pyc", "json"];
+    let paths: Vec<PathBuf> = (0..20_000)
+        .map(|i| match i % 4 {
+            0 => PathBuf::from(format!("/srv/app/node_modules/pkg{}/index.js", i)),
+            1 => PathBuf::from(format!("/home/user/.cache/thumbs/{}.png", i)),
+            _ => PathBuf::from(format!("/data/project/dir{}/file{}.{}", i % 97, i, EXTENSIONS[i % EXTENSIONS.len()])),
+        })
+        .collect();
+    let classifier = FileTypeClassifier::new();
+    // The rules' regexes set up their matching caches on first use
+    for path in &paths {
+        classifier.classify_id(path);
+    }
+
+    let (by_id, id_time) = measure(|| {
+        for path in &paths {
+            std::hint::black_box(classifier.classify_id(path));
+        }
+    });
+    let (by_name, name_time) = measure(|| {
+        for path in &paths {
+            std::hint::black_box(classifier.classify(path));
+        }
+    });
+    println!(
+        "{} files: by id {} allocations in {:?}, by name {} allocations in {:?}",
+        paths.len(), by_id, id_time, by_name, name_time
+    );
+
+    assert_eq!(by_id, 0);
+    assert!(by_name >= paths.len() as u64);
+}
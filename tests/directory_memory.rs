@@ -0,0 +1,143 @@
+//! Memory held by the directory list after bounded retention
+//!
+//! A maildir farm has a few small directories per mailbox, millions in all,
+//! and only the largest ever make the report. This binary wraps the global
+//! allocator to track the bytes the calling thread holds, and checks that
+//! bounded retention gives back nearly all the memory of a generated tree's
+//! directory list while keeping the directories the listing ranks first.
+//! The million-directory run is ignored by default; run it with
+//! `cargo test --release --test directory_memory -- --ignored --nocapture`.
+
+use diranalyzer::prelude::*;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::time::Instant;
+
+struct Tracking;
+
+thread_local! {
+    static HELD: Cell<i64> = const { Cell::new(0) };
+}
+
+fn add_held(bytes: i64) {
+    // The thread-local itself may be torn down while the thread exits
+    let _ = HELD.try_with(|held| held.set(held.get() + bytes));
+}
+
+// SAFETY: every call is passed on to the system allocator unchanged
+unsafe impl GlobalAlloc for Tracking {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        add_held(layout.size() as i64);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        add_held(-(layout.size() as i64));
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        add_held(new_size as i64 - layout.size() as i64);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Tracking = Tracking;
+
+fn held() -> i64 {
+    HELD.with(Cell::get)
+}
+
+/// Scan results of a maildir farm: the root, then per mailbox its directory and `cur`, `new`, and `tmp`
+fn maildir_farm(mailboxes: usize) -> ScanResults {
+    let mut results = ScanResults::default();
+    let directory = |path: PathBuf, total_size: u64, depth: usize| DirectoryEntry {
+        path,
+        total_size,
+        file_count: 0,
+        recursive_file_count: 0,
+        subdirectory_count: 0,
+        recursive_subdirectory_count: 0,
+        depth,
+        owners: Vec::new(),
+        root: 0,
+        mode: None,
+    };
+    let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+    let mut next_size = || {
+        state = state.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1_442_695_040_888_963_407);
+        (state >> 33) % 1_000_000
+    };
+    let root = PathBuf::from("/var/mail");
+    let mut total = 0;
+    for mailbox in 0..mailboxes {
+        let path = root.join(format!("user{}", mailbox));
+        let sizes = [next_size(), next_size(), next_size() % 1_000];
+        for (name, size) in ["cur", "new", "tmp"].into_iter().zip(sizes) {
+            results.directories.push(directory(path.join(name), size, 2));
+        }
+        results.directories.push(directory(path, sizes.iter().sum(), 1));
+        total += sizes.iter().sum::<u64>();
+    }
+    results.directories.push(directory(root, total, 0));
+    results.total_directories = results.directories.len() as u64;
+    results.total_size = total;
+    results
+}
+
+/// The `top` directories below the root as the listing ranks them: larger first, then by path
+fn ranked(directories: &[DirectoryEntry], top: usize, min_size: u64) -> Vec<(PathBuf, u64)> {
+    let mut ranked: Vec<(PathBuf, u64)> = directories
+        .iter()
+        .filter(|d| d.depth > 0 && d.total_size >= min_size)
+        .map(|d| (d.path.clone(), d.total_size))
+        .collect();
+    ranked.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    ranked.truncate(top);
+    ranked
+}
+
+/// Bytes the directory list holds before and after retention, checking what it kept
+fn retain_farm(mailboxes: usize, top: usize, min_size: u64) -> (i64, i64) {
+    let start = held();
+    let mut results = maildir_farm(mailboxes);
+    let full = held() - start;
+    let expected = ranked(&results.directories, top, min_size);
+    let expected_bytes = held() - start - full;
+
+    results.retain_directories(&DirectoryRetention { top, min_size, keep: HashSet::new() });
+    let retained = held() - start - expected_bytes;
+
+    assert_eq!(ranked(&results.directories, top, min_size), expected);
+    assert_eq!(results.directories.len(), top + 1, "the largest and the root");
+    let dropped = &results.dropped_directories;
+    assert_eq!(dropped.by_depth.iter().sum::<u64>() + top as u64 + 1, results.total_directories);
+    assert_eq!(dropped.by_depth[0], 0);
+    assert_eq!(dropped.ranked_out + dropped.under_floor + top as u64, results.total_directories - 1);
+    (full, retained)
+}
+
+#[test]
+fn test_bounded_retention_gives_back_the_directory_list() {
+    let (full, retained) = retain_farm(25_000, 20, 1_000);
+    println!("100,000 directories: {} bytes held, {} after retention", full, retained);
+    assert!(retained * 100 < full, "{} of {} bytes still held", retained, full);
+}
+
+/// Holds and then trims the directory list of a million-directory maildir farm
+#[test]
+#[ignore]
+fn bounded_retention_on_a_million_directories() {
+    let start = Instant::now();
+    let (full, retained) = retain_farm(250_000, 100, 0);
+    println!(
+        "1,000,000 directories: {:.1} MB held, {:.1} kB after retention, in {:?}",
+        full as f64 / 1e6,
+        retained as f64 / 1e3,
+        start.elapsed()
+    );
+    assert!(retained * 1_000 < full);
+}
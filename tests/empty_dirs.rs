@@ -0,0 +1,116 @@
+//! Full-pipeline runs against trees with nothing (or almost nothing) in them
+
+use clap::Parser;
+use diranalyzer::cli::{Args, ExportFormat};
+use diranalyzer::export::naming::OutputNaming;
+use diranalyzer::writes::WriteGuard;
+use diranalyzer::reporter::{self, PlainRenderer, ReportRenderer};
+use diranalyzer::{export, AnalysisResults, DirectoryAnalyzer};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// A visible scan root inside a temp dir (temp dirs themselves are dot-named)
+fn scan_root() -> (tempfile::TempDir, PathBuf) {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().join("root");
+    std::fs::create_dir(&root).unwrap();
+    (dir, root)
+}
+
+async fn analyze(root: &Path) -> AnalysisResults {
+    let args = Args::parse_from([
+        "diranalyzer",
+        "--quiet",
+        "--no-history",
+        "--duplicates",
+        "--min-size",
+        "0",
+        root.to_str().unwrap(),
+    ]);
+    DirectoryAnalyzer::new(args).unwrap().analyze().await.unwrap()
+}
+
+fn render(results: &AnalysisResults) -> String {
+    let report = reporter::build_report(results, 20, Duration::ZERO, &reporter::Messages::default());
+    let mut out = Vec::new();
+    PlainRenderer.render(&report, &mut out).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+/// Statistics must stay finite and every export must round-trip
+fn assert_sane(results: &AnalysisResults, export_dir: &Path) {
+    let stats = &results.statistics;
+    for value in [stats.files_per_second, stats.memory_usage_mb, stats.compression_ratio] {
+        assert!(value.is_finite(), "non-finite statistic in {:?}", stats);
+    }
+
+    let text = render(results);
+    assert!(!text.contains("NaN") && !text.split_whitespace().any(|word| word == "inf"), "{}", text);
+
+    let json_path = export_dir.join("results.json");
+    export::export_results(results, &ExportFormat::Json, &Some(json_path.clone()), &OutputNaming::default(), export::bundle::DEFAULT_SHARD_SIZE, &WriteGuard::unrestricted()).unwrap();
+    let json = std::fs::read_to_string(&json_path).unwrap();
+    let mut reloaded: AnalysisResults = serde_json::from_str(&json).unwrap();
+    // Rates may lose their last bit in the decimal round trip
+    assert!((reloaded.statistics.files_per_second - stats.files_per_second).abs() < 1e-6);
+    reloaded.statistics.files_per_second = stats.files_per_second;
+    assert_eq!(serde_json::to_string(&reloaded).unwrap(), serde_json::to_string(results).unwrap());
+
+    let csv_path = export_dir.join("results.csv");
+    export::export_results(results, &ExportFormat::Csv, &Some(csv_path.clone()), &OutputNaming::default(), export::bundle::DEFAULT_SHARD_SIZE, &WriteGuard::unrestricted()).unwrap();
+    assert!(std::fs::read_to_string(&csv_path).unwrap().starts_with("Type,Path,Size"));
+}
+
+#[tokio::test]
+async fn empty_directory() {
+    let (dir, root) = scan_root();
+    let results = analyze(&root).await;
+
+    assert_eq!(results.scan_info.total_files, 0);
+    assert_eq!(results.scan_info.total_size, 0);
+    assert_eq!(results.duplicate_groups.as_deref().map(<[_]>::len), Some(0));
+
+    let text = render(&results);
+    assert!(text.contains("No files found."));
+    assert!(text.contains("No duplicate files found!"));
+    assert_sane(&results, dir.path());
+}
+
+#[tokio::test]
+async fn single_empty_file() {
+    let (dir, root) = scan_root();
+    std::fs::write(root.join("empty.txt"), b"").unwrap();
+    let results = analyze(&root).await;
+
+    assert_eq!(results.scan_info.total_files, 1);
+    assert_eq!(results.scan_info.total_size, 0);
+    assert_eq!(results.largest_files.len(), 1);
+    assert_eq!(results.size_breakdown.small_files_count, 1);
+    assert_eq!(results.empty_files, [root.join("empty.txt")]);
+    assert!(results.empty_directories.is_empty());
+
+    let text = render(&results);
+    assert!(text.contains("0 B - "));
+    assert!(text.contains("(0.0%)"));
+    assert_sane(&results, dir.path());
+}
+
+#[tokio::test]
+async fn only_subdirectories() {
+    let (dir, root) = scan_root();
+    std::fs::create_dir_all(root.join("a/b/c")).unwrap();
+    std::fs::create_dir(root.join("d")).unwrap();
+    let results = analyze(&root).await;
+
+    assert_eq!(results.scan_info.total_files, 0);
+    assert_eq!(results.scan_info.total_directories, 5);
+    assert!(results.largest_directories.iter().all(|d| d.size == 0));
+    // Everything below the root is empty, the root itself is never listed
+    assert_eq!(results.empty_directories, [root.join("a"), root.join("a/b"), root.join("a/b/c"), root.join("d")]);
+
+    let text = render(&results);
+    assert!(text.contains("No files found."));
+    assert!(text.contains("4 empty directories, 0 empty files"));
+    assert!(!text.contains("No directories found."));
+    assert_sane(&results, dir.path());
+}
@@ -0,0 +1,194 @@
+//! Golden-file tests of the whole pipeline: scan, analyze, report, export
+//!
+//! A fixture tree with fixed names, contents, and modification times is
+//! analyzed under a fixed clock. The plain-text report and the JSON export are
+//! compared with the files in `tests/golden/`, after the scan root, the
+//! timing-dependent figures, and the owning user are normalized. After an
+//! intended output change, regenerate them with
+//! `UPDATE_GOLDEN=1 cargo test --test golden` and review the diff.
+
+use chrono::{DateTime, TimeZone, Utc};
+use clap::Parser;
+use diranalyzer::analyzer::FixedClock;
+use diranalyzer::cli::{Args, ExportFormat};
+use diranalyzer::export::naming::OutputNaming;
+use diranalyzer::writes::WriteGuard;
+use diranalyzer::reporter::{self, PlainRenderer, ReportRenderer};
+use diranalyzer::prelude::AgeSource;
+use diranalyzer::{export, AnalysisResults, DirectoryAnalyzer};
+use std::fs::{self, File, FileTimes};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+/// Placeholder for the temporary scan root in golden files
+const ROOT: &str = "<root>";
+/// Placeholder for the user running the tests in golden files
+const OWNER: &str = "<owner>";
+
+fn clock() -> DateTime<Utc> {
+    Utc.with_ymd_and_hms(2024, 3, 1, 12, 0, 0).unwrap()
+}
+
+/// Write `len` bytes cycling through `pattern`, last modified `days` after 2024-01-01
+fn write_file(root: &Path, relative: &str, pattern: &[u8], len: usize, days: u64) {
+    let path = root.join(relative);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    let contents: Vec<u8> = pattern.iter().copied().cycle().take(len).collect();
+    fs::write(&path, contents).unwrap();
+
+    let epoch = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap().timestamp() as u64;
+    let modified = SystemTime::UNIX_EPOCH + Duration::from_secs(epoch + days * 86_400);
+    let times = FileTimes::new().set_accessed(modified).set_modified(modified);
+    File::options().write(true).open(&path).unwrap().set_times(times).unwrap();
+}
+
+/// Link `relative` to `target`, the link itself last modified `days` after 2024-01-01
+#[cfg(unix)]
+fn symlink(root: &Path, relative: &str, target: &str, days: u64) {
+    use std::os::unix::ffi::OsStrExt;
+    let path = root.join(relative);
+    std::os::unix::fs::symlink(target, &path).unwrap();
+
+    let epoch = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap().timestamp();
+    let times = [
+        libc::timespec { tv_sec: 0, tv_nsec: libc::UTIME_OMIT },
+        libc::timespec { tv_sec: (epoch + days as i64 * 86_400) as libc::time_t, tv_nsec: 0 },
+    ];
+    let c_path = std::ffi::CString::new(path.as_os_str().as_bytes()).unwrap();
+    // SAFETY: the path is NUL-terminated and `times` holds the two required entries
+    let result = unsafe { libc::utimensat(libc::AT_FDCWD, c_path.as_ptr(), times.as_ptr(), libc::AT_SYMLINK_NOFOLLOW) };
+    assert_eq!(result, 0, "{}", std::io::Error::last_os_error());
+}
+
+/// The fixture tree: documents, media, sources, two duplicate pairs, an empty
+/// file, a hidden directory, and a symlink
+fn fixture_tree() -> (tempfile::TempDir, PathBuf) {
+    let dir = tempfile::tempdir().unwrap();
+    // Temp dirs are dot-named, so scan a visible directory inside
+    let root = dir.path().join("fixture");
+
+    write_file(&root, "docs/report.pdf", b"%PDF report ", 120_000, 10);
+    write_file(&root, "docs/notes.txt", b"meeting notes\n", 3_000, 20);
+    write_file(&root, "docs/copy-a.bin", b"shared payload", 8_192, 30);
+    write_file(&root, "archive/copy-b.bin", b"shared payload", 8_192, 31);
+    write_file(&root, "media/photo.jpg", b"\xFF\xD8 jpeg data", 40_000, 40);
+    write_file(&root, "media/backup/photo.jpg", b"\xFF\xD8 jpeg data", 40_000, 41);
+    write_file(&root, "media/clip.mp4", b"ftyp mp4 frames", 250_000, 50);
+    write_file(&root, "src/main.rs", b"fn main() {}\n", 1_500, 55);
+    write_file(&root, "src/lib.rs", b"pub mod x;\n", 700, 56);
+    write_file(&root, "empty.log", b"", 0, 58);
+    write_file(&root, ".hidden/secret.txt", b"secret", 500, 59);
+    #[cfg(unix)]
+    symlink(&root, "latest-report", "docs/report.pdf", 60);
+
+    (dir, root)
+}
+
+async fn analyze(root: &Path) -> AnalysisResults {
+    let args = Args::parse_from([
+        "diranalyzer",
+        "--quiet",
+        "--no-history",
+        "--duplicates",
+        "--min-size",
+        "1",
+        "--top",
+        "5",
+        "--seed",
+        "0",
+        root.to_str().unwrap(),
+    ]);
+    let mut results = DirectoryAnalyzer::new(args).unwrap().with_clock(FixedClock(clock())).analyze().await.unwrap();
+
+    // Figures that depend on how fast and how heavily this machine ran
+    results.scan_info.scan_duration_ms = 0;
+    results.statistics.files_per_second = 0.0;
+    results.statistics.bytes_per_second = 0;
+    results.statistics.memory_usage_mb = 0.0;
+    results.statistics.resource_usage.clear();
+    // Access times count unless the temp directory is mounted noatime; the fixture's equal its modification times
+    results.scan_info.age_source = AgeSource::Accessed;
+    // Birth times are when the test ran, where the filesystem records them at all
+    let stats = results.file_type_distribution.values_mut().chain(results.data_formats.iter_mut().flat_map(|formats| formats.values_mut()));
+    for file in results.largest_files.iter_mut().chain(stats.filter_map(|stats| stats.largest_file.as_mut())) {
+        file.created = file.modified;
+    }
+    // The fixture belongs to whoever runs the tests
+    for owner in results.largest_directories.iter_mut().filter_map(|dir| dir.owner_mix.as_mut()).flat_map(|mix| &mut mix.owners) {
+        owner.user = OWNER.to_string();
+        owner.uid = 0;
+    }
+    results.owners = results.owners.drain().map(|(_, mut stats)| {
+        stats.uid = 0;
+        (OWNER.to_string(), stats)
+    }).collect();
+    results
+}
+
+fn render(results: &AnalysisResults) -> String {
+    let report = reporter::build_report(results, 5, Duration::ZERO, &reporter::Messages::default());
+    let mut out = Vec::new();
+    PlainRenderer.render(&report, &mut out).unwrap();
+    String::from_utf8(out).unwrap()
+}
+
+/// The JSON export with object keys sorted, as maps serialize in no fixed order
+///
+/// The integrity footer is dropped once it checks out: its digest covers the
+/// bytes as written, root and map order included.
+fn export_json(results: &AnalysisResults, dir: &Path) -> String {
+    let path = dir.join("results.json");
+    export::export_results(results, &ExportFormat::Json, &Some(path.clone()), &OutputNaming::default(), export::bundle::DEFAULT_SHARD_SIZE, &WriteGuard::unrestricted()).unwrap();
+    assert!(export::verify_export(&path).unwrap().is_intact());
+    let mut value: serde_json::Value = serde_json::from_str(&fs::read_to_string(path).unwrap()).unwrap();
+    value.as_object_mut().unwrap().remove("integrity").unwrap();
+    serde_json::to_string_pretty(&value).unwrap() + "\n"
+}
+
+/// Compare `actual` with a golden file, or rewrite it when `UPDATE_GOLDEN` is set
+fn assert_golden(name: &str, actual: &str) {
+    let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden").join(name);
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, actual).unwrap();
+        return;
+    }
+    let expected = fs::read_to_string(&path)
+        .unwrap_or_else(|error| panic!("cannot read {} ({}); run with UPDATE_GOLDEN=1", path.display(), error));
+    assert!(
+        actual == expected,
+        "{} is out of date; rerun with UPDATE_GOLDEN=1 and review the diff\n--- actual ---\n{}",
+        name,
+        actual
+    );
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn pipeline_matches_golden_files() {
+    let (dir, root) = fixture_tree();
+    let results = analyze(&root).await;
+    let root_text = root.to_str().unwrap();
+
+    assert_eq!(results.scan_info.timestamp, clock());
+    // The hidden file is not counted; the unfollowed symlink counts as itself
+    assert_eq!(results.scan_info.total_files, 11);
+    assert_eq!(results.duplicate_groups.as_ref().map(Vec::len), Some(2));
+
+    assert_golden("report.txt", &render(&results).replace(root_text, ROOT));
+    assert_golden("results.json", &export_json(&results, dir.path()).replace(root_text, ROOT));
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn pipeline_is_reproducible() {
+    let (first_dir, first_root) = fixture_tree();
+    let (second_dir, second_root) = fixture_tree();
+    let first = analyze(&first_root).await;
+    let second = analyze(&second_root).await;
+
+    assert_eq!(
+        export_json(&first, first_dir.path()).replace(first_root.to_str().unwrap(), ROOT),
+        export_json(&second, second_dir.path()).replace(second_root.to_str().unwrap(), ROOT)
+    );
+}
@@ -0,0 +1,394 @@
+//! The supported library surface, pinned by use
+//!
+//! Everything here goes through `diranalyzer::prelude` the way a downstream
+//! crate would. Renaming, moving, or retyping any item it names fails the
+//! build; adding items and fields does not. Change this file only together
+//! with a version bump that allows the break.
+
+use anyhow::Result;
+use chrono::{DateTime, TimeZone, Utc};
+use clap::Parser;
+use diranalyzer::prelude::*;
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+struct SkipLogs;
+
+impl FileFilter for SkipLogs {
+    fn include(&self, entry: &FileEntry) -> bool {
+        entry.path.extension().is_none_or(|extension| extension != "log")
+    }
+
+    fn prune(&self, directory: &Path) -> bool {
+        directory.ends_with("cache")
+    }
+}
+
+struct Summary;
+
+impl SectionProvider for Summary {
+    fn section(&self, results: &AnalysisResults) -> Option<ExtensionSection> {
+        Some(ExtensionSection::new("Summary").sized_row("Scanned", "everything", results.scan_info.total_size).row("Note", "none"))
+    }
+}
+
+struct LengthHasher;
+
+impl FileHasher for LengthHasher {
+    fn hash_file(&self, path: &Path) -> Result<String> {
+        Ok(std::fs::metadata(path)?.len().to_string())
+    }
+
+    fn algorithm(&self) -> &str {
+        "length"
+    }
+}
+
+/// Signatures of the entry points, checked by coercion
+#[test]
+fn test_entry_point_signatures() {
+    let _: fn(Args) -> Result<DirectoryAnalyzer> = DirectoryAnalyzer::new;
+    let _: fn(&Args) -> Vec<PathBuf> = Args::roots;
+    let _: fn(&Args) -> Option<(&Path, bool)> = Args::path_list;
+    let _: fn(DirectoryAnalyzer, FixedClock) -> DirectoryAnalyzer = DirectoryAnalyzer::with_clock;
+    let _: fn(DirectoryAnalyzer, SkipLogs) -> DirectoryAnalyzer = DirectoryAnalyzer::with_filter;
+    let _: fn(DirectoryAnalyzer, InventorySource) -> DirectoryAnalyzer = DirectoryAnalyzer::with_source;
+    let _: fn(DirectoryAnalyzer, Summary) -> DirectoryAnalyzer = DirectoryAnalyzer::with_section_provider;
+    let _: fn(DirectoryAnalyzer, DuplicateFinder) -> DirectoryAnalyzer = DirectoryAnalyzer::with_duplicate_finder;
+    let _: fn(&DirectoryAnalyzer) -> &Config = DirectoryAnalyzer::config;
+    let _: fn(&DirectoryAnalyzer, &Path) -> Result<ExclusionTrace> = DirectoryAnalyzer::explain_path;
+    let _: fn(&ExclusionTrace) -> bool = ExclusionTrace::is_included;
+    let _: fn(&ExclusionTrace) -> (&[TraceStep], &TraceOutcome) = |trace| (&trace.steps, &trace.outcome);
+    let _: fn(&AnalysisResults) -> Option<&[SkippedMount]> = |results| results.skipped_mounts.as_deref();
+    let _: fn(&SkippedMount) -> (&Path, u64, Option<&str>) = |mount| (&mount.path, mount.device, mount.filesystem.as_deref());
+    let _: fn(DirectoryAnalyzer, RecordSender) -> DirectoryAnalyzer = DirectoryAnalyzer::with_stream;
+    let _: fn(DirectoryAnalyzer, Cancellation) -> DirectoryAnalyzer = DirectoryAnalyzer::with_cancellation;
+    let _: fn() -> Cancellation = Cancellation::new;
+    let _: fn(&Cancellation) = Cancellation::cancel;
+    let _: fn(&Cancellation) -> bool = Cancellation::is_cancelled;
+    let _: &str = CapKind::Interrupted.flag();
+    let _: fn(&Args) -> (Option<Duration>, bool) = |args| (args.max_duration, args.strict_limits);
+    let _: fn(&Args) -> bool = |args| args.fail_on_errors;
+    let _: fn(&Args) -> bool = |args| args.print_empty_dirs;
+    let _: fn(&AnalysisResults) -> (&[PathBuf], &[PathBuf]) = |results| (&results.empty_directories, &results.empty_files);
+    let _: fn(&ScanResults) -> &[PathBuf] = |results| &results.empty_directories;
+    let _: fn(&FileEntry) -> (u64, u64, u64) = |file| (file.dev, file.ino, file.nlink);
+    let _: fn(&FileEntry) -> Option<(u64, u64)> = FileEntry::inode;
+    let _: fn(&FileEntry) -> Option<DateTime<Utc>> = |file| file.created;
+    let _: fn(&Args) -> Option<DateSpec> = |args| args.created_after;
+    let _: fn(&AnalysisResults, &Args, &Config, Duration) -> Result<()> = generate_report;
+    let _: fn(&AnalysisResults) -> bool = AnalysisResults::is_degraded;
+    let _: Vec<MetricDefinition> = Config { custom_metrics: vec![MetricDefinition { name: "media".into(), expression: "size(type:Images)".into() }], ..Config::default() }.custom_metrics;
+
+    let _: fn(&Args) -> Result<DirectoryScanner> = DirectoryScanner::new;
+    let _: fn(DirectoryScanner, SlowThresholds) -> DirectoryScanner = DirectoryScanner::with_slow_thresholds;
+    let _: fn(DirectoryScanner, usize) -> DirectoryScanner = DirectoryScanner::with_owner_limit;
+    let _: fn(DirectoryScanner, usize) -> DirectoryScanner = DirectoryScanner::with_identity_limit;
+    let _: fn(&DirectoryScanner) -> Result<Coverage> = DirectoryScanner::probe_coverage;
+    let _: fn(&DirectoryScanner) -> Option<InterimSnapshot> = DirectoryScanner::interim_snapshot;
+    let _: fn(DirectoryScanner, Cancellation) -> DirectoryScanner = DirectoryScanner::with_cancellation;
+    let _: fn(&mut InventorySource, Cancellation) = <InventorySource as FileSource>::stop_on;
+    let _: fn(&InterimSnapshot) -> Vec<String> = InterimSnapshot::lines;
+    let _: usize = CANDIDATE_DEPTH;
+    let _: fn(&Args) -> Result<InventorySource> = InventorySource::open;
+    let _: fn(InventorySource, usize) -> InventorySource = InventorySource::with_owner_limit;
+    let _: fn(&mut InventorySource) -> Result<ScanResults> = <InventorySource as FileSource>::list;
+    let _: fn(&DirectoryScanner) -> bool = <DirectoryScanner as FileSource>::reads_contents;
+    let _: fn(&mut DirectoryScanner, FileSink<'_>) -> Result<ScanResults> = <DirectoryScanner as FileSource>::stream;
+    let _: fn(&mut InventorySource, &[PathBuf], FileSink<'_>) -> Result<ScanResults> = <InventorySource as FileSource>::stream_below;
+    let _: fn(&ScanResults) -> bool = ScanResults::is_streamed;
+    let _: fn(&Args) -> bool = |args| args.low_memory;
+    let _: fn(&mut ScanResults, &DirectoryRetention) = ScanResults::retain_directories;
+    let _: fn(&DroppedDirectories) -> bool = DroppedDirectories::is_empty;
+
+    let _: fn(u64, Option<usize>) -> DuplicateFinder = DuplicateFinder::new;
+    let _: fn(DuplicateFinder, Box<dyn FileHasher>) -> DuplicateFinder = DuplicateFinder::with_hasher;
+    let _: fn(DuplicateFinder, bool) -> DuplicateFinder = DuplicateFinder::strict;
+    let _: fn(DuplicateFinder, Option<usize>) -> DuplicateFinder = DuplicateFinder::within_depth;
+    let _: fn(&mut DuplicateFinder) -> Vec<ScanError> = DuplicateFinder::take_failures;
+    let _: fn(DuplicateFinder, ResultsJournal) -> DuplicateFinder = DuplicateFinder::with_journal;
+    let _: fn(DuplicateFinder, Cancellation) -> DuplicateFinder = DuplicateFinder::with_cancellation;
+    let _: fn(DuplicateFinder, Option<std::time::Instant>) -> DuplicateFinder = DuplicateFinder::with_deadline;
+    let _: fn(&Path) -> Option<ResultsJournal> = ResultsJournal::for_root;
+    let _: fn(PathBuf) -> ResultsJournal = ResultsJournal::at;
+    let _: fn(ResultsJournal, bool) -> ResultsJournal = ResultsJournal::resuming;
+    let _: Box<dyn FileHasher> = Box::new(Sha256Hasher);
+    let _: Box<dyn FileHasher> = Box::new(LengthHasher);
+
+    let _: ExtensionSection = ExtensionSection::new("Title").row("Label", String::from("value")).sized_row("Label", "value", 1);
+    let _: fn(&CacheUsage) -> f64 = CacheUsage::hit_rate;
+    let _: f64 = JUMP_THRESHOLD;
+    let _: Box<dyn Clock> = Box::new(SystemClock);
+    let _: InterimSink = Box::new(|snapshot: &InterimSnapshot| drop(snapshot.lines()));
+
+    let _: fn(std::io::Sink, usize) -> RecordSink = RecordSink::to_writer;
+    let _: fn(&RecordSink) -> RecordSender = RecordSink::sender;
+    let _: fn(RecordSink) -> Result<u64> = RecordSink::finish;
+    let _: fn(&RecordSender, StreamRecord) -> Result<()> = RecordSender::send;
+    let _: fn(&AnalysisResults) -> Vec<StreamRecord> = StreamRecord::for_results;
+    let _: fn(&Path) -> Result<AnalysisResults> = read_bundle;
+    let _: StreamRecord = StreamRecord::HashProgress(HashProgress { hashed: 1, candidates: 2 });
+    let _: StreamRecord = StreamRecord::ScanProgress(ScanProgress {
+        elapsed_ms: 0,
+        files: 0,
+        bytes: 0,
+        errors: 0,
+        largest_directories: vec![ProgressEntry { path: PathBuf::from("/"), size: 0 }],
+    });
+}
+
+/// Field names and types of the results, read the way a report consumer reads them
+fn read_results(results: &AnalysisResults) {
+    let info: &ScanInfo = &results.scan_info;
+    let _: (&PathBuf, &DateTime<Utc>, Option<usize>, usize) = (&info.path, &info.timestamp, info.depth_limit, info.min_depth);
+    let _: (u64, u64, u64, u64, u64) =
+        (info.total_files, info.total_directories, info.total_size, info.scan_duration_ms, info.error_count);
+    let _: (&Coverage, &Option<Ownership>, Option<u64>, Option<u64>) =
+        (&info.coverage, &info.ownership, info.xattr_total, info.physical_size);
+    let _: (&DepthBoundary, Option<PartialScan>) = (&info.depth_boundary, info.partial);
+    let _: (Option<u64>, Option<u64>) = (info.seed, info.assumed_clock_skew_secs);
+    let _: (&PathBuf, &PathBuf) = (&info.path_given, &info.path_resolved);
+    let _: &[PathBuf] = &info.roots;
+    let _: fn(&GroupFilter, Option<u32>) -> bool = GroupFilter::matches;
+    if let Some(date_filter) = &info.date_filter {
+        let _: (&DateFilter, Option<DateTime<Utc>>, Option<DateTime<Utc>>) = (date_filter, date_filter.before, date_filter.after);
+        let _: (u64, &[PathBuf]) = (date_filter.undated_files, &date_filter.undated_examples);
+        let _: fn(&str) -> std::result::Result<DateSpec, String> = parse_date_spec;
+        let _: fn(DateSpec, DateTime<Utc>) -> DateTime<Utc> = DateSpec::resolve;
+        let _: fn(&ModifiedFilter, Option<DateTime<Utc>>) -> Option<bool> = ModifiedFilter::matches;
+        let _: fn(&ModifiedFilter, &FileEntry) -> Option<bool> = ModifiedFilter::admits;
+        let _: Option<DateTime<Utc>> = date_filter.created_after;
+    }
+    let _: AgeSource = info.age_source;
+    if let Some(usage) = &info.disk_usage {
+        let _: (&DiskUsage, u64, u64, u64, u64) = (usage, usage.apparent_size, usage.allocated_size, usage.sparse_files, usage.sparse_size);
+    }
+    if let Some(skew) = info.clock_skew {
+        let _: (u64, u64, u64, f64) = (skew.files_ahead, skew.files_dated, skew.median_ahead_secs, skew.share());
+    }
+    if let Some(partial) = info.partial {
+        let _: (CapKind, u64) = (partial.cap, partial.limit);
+    }
+
+    let _: &SizeBreakdown = &results.size_breakdown;
+    let _: &HashMap<String, TypeStats> = &results.file_type_distribution;
+    for file in &results.largest_files {
+        let _: (&FileInfo, u64, &str, bool) = (file, file.size, &file.file_type, file.regenerable);
+        let _: Option<DateTime<Utc>> = file.created;
+        let _: Option<&Path> = file.symlink_target.as_deref();
+        let _: (Option<u64>, bool) = (file.apparent_size, file.sparse);
+    }
+    for directory in &results.largest_directories {
+        let _: (&DirectoryInfo, &PathBuf, u64) = (directory, &directory.path, directory.size);
+        let _: (u64, u64, u64, u64) = (directory.file_count, directory.recursive_file_count, directory.subdirectory_count, directory.recursive_subdirectory_count);
+        let _: usize = directory.root;
+        let _: &BTreeMap<String, u64> = &directory.composition;
+        let _: Option<&OwnerMix> = directory.owner_mix.as_ref();
+        let _: Option<&[DirectoryOwner]> = directory.owner_mix.as_ref().map(|mix| mix.owners.as_slice());
+    }
+    for (user, owner) in &results.owners {
+        let _: (&String, &OwnerStats, u32, u64, u64) = (user, owner, owner.uid, owner.count, owner.total_size);
+    }
+    let ages: &AgeBreakdown = &results.age_breakdown;
+    let _: (u64, u64, u64) = (ages.undated_files, ages.undated_size, ages.total_size());
+    for bucket in &ages.buckets {
+        let _: (&AgeBucket, u64, Option<u64>, u64, u64) = (bucket, bucket.min_age_secs, bucket.max_age_secs, bucket.count, bucket.total_size);
+    }
+    if let Some(stale) = &results.stale_files {
+        let _: (&StaleFiles, u64, u64, u64) = (stale, stale.threshold_secs, stale.count, stale.total_size);
+        let _: Option<(&StaleFile, &PathBuf, u64, DateTime<Utc>)> = stale.largest.first().map(|file| (file, &file.path, file.size, file.last_used));
+    }
+    if let Some(audit) = &results.security_audit {
+        let _: (&SecurityAudit, u64, u64) = (audit, audit.audited_entries, audit.findings());
+        let _: [&Vec<PermissionFinding>; 4] = [&audit.world_writable_files, &audit.setuid_files, &audit.setgid_files, &audit.open_directories];
+        for finding in &audit.open_directories {
+            let _: (&PathBuf, u32, Option<&str>, u64) = (&finding.path, finding.mode, finding.owner.as_deref(), finding.size);
+        }
+    }
+    for group in results.duplicate_groups.iter().flatten() {
+        let _: (&DuplicateGroup, &Vec<PathBuf>, u64, u64, &str) =
+            (group, &group.files, group.file_size, group.wasted_space, &group.group_id);
+        let _: bool = group.unverified;
+    }
+
+    let statistics: &Statistics = &results.statistics;
+    let _: (f64, u64, u64, u64) =
+        (statistics.files_per_second, statistics.duplicate_groups, statistics.redundant_files, statistics.reclaimable_bytes);
+    for phase in &statistics.resource_usage {
+        let _: (&PhaseUsage, &ResourceUsage) = (phase, &phase.usage);
+    }
+    if let Some(latency) = &statistics.io_latency {
+        let _: (&IoLatencyReport, &[SlowOperation]) = (latency, &latency.slowest);
+        for entry in &latency.operations {
+            let _: (&OperationLatency, IoOperation, &LatencyHistogram) = (entry, entry.operation, &entry.histogram);
+            let _: (u64, u64) = (entry.histogram.percentile(0.99), entry.histogram.max_ns);
+        }
+    }
+    if let Some(trend) = &results.trend {
+        let _: (&Trend, Option<&TrendWindow>, Option<&GrowthAttribution>) = (trend, trend.window.as_ref(), trend.growth.as_ref());
+        let _: Option<&NewDuplicateGroup> = trend.growth.as_ref().and_then(|growth| growth.top_new_group.as_ref());
+    }
+    let _: &[PhaseError] = &results.phase_errors;
+    let _: Option<&DirectoryFilter> = results.directory_filter.as_ref();
+    let _: &[XattrFile] = &results.largest_xattr_files;
+
+    let diagnostics: &Diagnostics = &results.diagnostics;
+    let _: (&ExclusionStats, &[PatternHits]) = (&diagnostics.exclusions, &diagnostics.exclusions.patterns);
+    let _: Option<u64> = diagnostics.exclusions.ignored;
+    let _: Option<(&ExclusionCause, u64, u64)> = diagnostics.exclusions.causes.first().map(|count: &ExclusionCount| (&count.cause, count.entries, count.size));
+    let _: (&[SlowDirectory], &[RuleHits], &[String]) =
+        (&diagnostics.slow_directories, &diagnostics.classification_rules, &diagnostics.pin_warnings);
+    let _: (Option<CacheUsage>, &Aliases) = (diagnostics.hash_cache, &diagnostics.aliases);
+    let _: &[DirectoryAlias] = &diagnostics.aliases.directories;
+
+    let residuals: &Residuals = &results.residuals;
+    let _: (&Residual, u64, u64) = (&residuals.largest_files, residuals.largest_files.count, residuals.largest_files.size);
+    let _: &RegenerableTotals = &results.regenerable;
+    if let Some(entropy) = &results.entropy {
+        let _: (&EntropyReport, &[DirectoryEntropy]) = (entropy, &entropy.directories);
+    }
+    let _: Option<&[ResolvedGroup]> = results.resolved_groups.as_deref();
+    for collision in results.case_collisions.iter().flatten() {
+        let _: (&CaseCollision, &PathBuf, &str) = (collision, &collision.directory, &collision.folded);
+        for entry in &collision.entries {
+            let _: (&CollidingEntry, &str, bool, u64, Option<DateTime<Utc>>) =
+                (entry, &entry.name, entry.is_dir, entry.size, entry.modified);
+        }
+    }
+    if let Some(versions) = &results.heuristic_versions {
+        let _: (&VersionReport, u64, u64) = (versions, versions.superseded_files, versions.superseded_bytes);
+        for group in &versions.groups {
+            let _: (&VersionGroup, &PathBuf, &str, u64) = (group, &group.directory, &group.base_name, group.superseded_bytes);
+            for member in &group.members {
+                let _: (&VersionMember, &str, u64, Option<DateTime<Utc>>) = (member, &member.name, member.size, member.modified);
+            }
+        }
+    }
+    if let Some(links) = &results.symlink_report {
+        let _: (&SymlinkReport, u64, u64, u64) = (links, links.valid, links.out_of_tree, links.total());
+        for link in &links.broken {
+            let _: (&BrokenLink, &PathBuf, &PathBuf, Option<&PathBuf>) = (link, &link.path, &link.target, link.dangling.as_ref());
+        }
+    }
+    let errors: &ErrorSummary = &results.scan_errors;
+    let _: (u64, u64, bool) = (errors.total, errors.unlisted, errors.complete);
+    for cause in &errors.causes {
+        let _: (&ErrorCause, ErrorType, Option<i32>, &str, u64) = (cause, cause.error_type, cause.errno, &cause.description, cause.count);
+    }
+    for rollup in &errors.rollups {
+        let _: (&ErrorRollup, &PathBuf, ErrorType, Option<i32>, u64) = (rollup, &rollup.directory, rollup.error_type, rollup.errno, rollup.count);
+    }
+    for error in &errors.errors {
+        let _: (&ScanError, &PathBuf, Option<i32>) = (error, &error.path, error.errno);
+    }
+    let accepted: &AcceptedFindings = &results.accepted;
+    let _: (bool, usize) = (accepted.shown, results.accepted_count());
+    for finding in &accepted.findings {
+        let _: (&AcceptedFinding, &str, DateTime<Utc>) = (finding, &finding.reason, finding.accepted);
+        match &finding.target {
+            AcceptedTarget::Path(path) => {
+                let _: &PathBuf = path;
+            }
+            AcceptedTarget::Group(id) => {
+                let _: &String = id;
+            }
+        }
+    }
+    let _: (bool, bool) = (results.largest_files.iter().any(|file| file.accepted), results.largest_directories.iter().any(|dir| dir.accepted));
+    for annotation in &results.annotations {
+        let _: (&Annotation, &PathBuf, &str, DateTime<Utc>) = (annotation, &annotation.path, &annotation.text, annotation.added);
+    }
+    if let Some(applied) = &results.report_filters {
+        let _: (&AppliedFilters, &[String], u64, u64) = (applied, &applied.filters, applied.matched_files, applied.collected_files);
+    }
+    if let Some(applied) = &results.file_size_range {
+        let _: (&AppliedSizeRange, SizeRange, bool) = (applied, applied.range, applied.affects_directories);
+        let _: fn(&SizeRange, u64) -> bool = SizeRange::contains;
+        let _: fn(&Args) -> Result<Option<SizeRange>> = SizeRange::from_args;
+    }
+    for entry in results.entries.iter().flatten() {
+        match entry {
+            ScanEntry::Directory(directory) => {
+                let _: (&DirectoryEntry, usize, Option<u32>) = (directory, directory.root, directory.mode);
+            }
+            ScanEntry::File(file) => {
+                let _: (&FileEntry, usize) = (file, file.root);
+                let _: (Option<u64>, u64, bool) = (file.disk_usage, file.counted_size(), file.is_sparse());
+                let _: (Option<u32>, Option<u32>, Option<u32>) = (file.uid, file.gid, file.mode);
+                let _: Option<DateTime<Utc>> = file.accessed;
+            }
+        }
+    }
+    for metric in &results.custom_metrics {
+        let _: (&CustomMetric, &str, &str, MetricUnit, Option<f64>) = (metric, &metric.name, &metric.expression, metric.unit, metric.value);
+    }
+    for extension in &results.extensions {
+        let _: (&str, &[ExtensionRow]) = (&extension.title, &extension.rows);
+    }
+}
+
+#[tokio::test]
+async fn test_prelude_runs_an_analysis() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().join("tree");
+    std::fs::create_dir_all(root.join("cache")).unwrap();
+    std::fs::write(root.join("a.bin"), vec![1u8; 2_000]).unwrap();
+    std::fs::write(root.join("b.bin"), vec![1u8; 2_000]).unwrap();
+    std::fs::write(root.join("run.log"), b"log").unwrap();
+    std::fs::write(root.join("cache/blob"), vec![0u8; 500]).unwrap();
+
+    let args = Args::parse_from(["diranalyzer", "--quiet", "--no-history", "--duplicates", root.to_str().unwrap()]);
+    let fixed = Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+    let results = DirectoryAnalyzer::new(args)
+        .unwrap()
+        .with_clock(FixedClock(fixed))
+        .with_filter(SkipLogs)
+        .with_section_provider(Summary)
+        .with_duplicate_finder(DuplicateFinder::new(1_024, Some(1)).with_hasher(Box::new(LengthHasher)))
+        .analyze()
+        .await
+        .unwrap();
+    read_results(&results);
+
+    assert_eq!((results.scan_info.total_files, results.scan_info.total_size), (2, 4_000));
+    assert_eq!(results.scan_info.timestamp, fixed);
+    assert_eq!(results.duplicate_groups.as_ref().unwrap().len(), 1);
+    assert_eq!(results.extensions[0].rows[0].size, Some(4_000));
+}
+
+#[tokio::test]
+async fn test_prelude_scans_and_finds_duplicates_standalone() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().join("tree");
+    std::fs::create_dir_all(&root).unwrap();
+    for name in ["x.bin", "y.bin"] {
+        std::fs::write(root.join(name), vec![7u8; 4_096]).unwrap();
+    }
+
+    let snapshots = Arc::new(Mutex::new(Vec::new()));
+    let sink = Arc::clone(&snapshots);
+    let args = Args::parse_from(["diranalyzer", "--quiet", root.to_str().unwrap()]);
+    let mut scanner = DirectoryScanner::new(&args)
+        .unwrap()
+        .with_interim(Some(Duration::ZERO), move |snapshot: &InterimSnapshot| sink.lock().unwrap().push(snapshot.files));
+    let scanned: ScanResults = scanner.scan().await.unwrap();
+    let files: &[FileEntry] = &scanned.files;
+    assert!(files.iter().all(|file: &FileEntry| !file.is_symlink && file.symlink_target.is_none()));
+    assert_eq!(files.len(), 2);
+    assert!(scanned.directories.iter().all(|directory: &DirectoryEntry| directory.file_count <= 2));
+    assert!(snapshots.lock().unwrap().iter().all(|&seen| seen <= 2));
+    let errors: ErrorSummary = scanned.error_log.summarize(&scanned.errors);
+    assert_eq!((errors.total, scanned.error_log.total()), (0, 0));
+
+    // Streamed, the files go to the callback and the results keep only the totals
+    let mut streamed = Vec::new();
+    let totals = DirectoryScanner::new(&args).unwrap().scan_streaming(|file: FileEntry| streamed.push(file.size)).await.unwrap();
+    assert!(totals.is_streamed() && totals.files.is_empty());
+    assert_eq!((streamed, totals.total_size), (vec![4_096, 4_096], 8_192));
+
+    let groups = DuplicateFinder::new(1_024, Some(1)).find_duplicates(files).await.unwrap();
+    assert_eq!(groups.len(), 1);
+    assert_eq!(groups[0].files.len(), 2);
+}
@@ -0,0 +1,70 @@
+//! A full run over an S3 inventory listing instead of a local tree
+
+use clap::Parser;
+use diranalyzer::cli::{Args, ExportFormat};
+use diranalyzer::export::naming::OutputNaming;
+use diranalyzer::reporter::{self, PlainRenderer, ReportRenderer};
+use diranalyzer::writes::WriteGuard;
+use diranalyzer::{export, AnalysisResults, DirectoryAnalyzer};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const LISTING: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/s3-inventory.csv");
+
+async fn analyze() -> AnalysisResults {
+    let args = Args::parse_from([
+        "diranalyzer",
+        "--quiet",
+        "--no-history",
+        "--duplicates",
+        "--min-size",
+        "1",
+        "--source",
+        "s3-inventory",
+        LISTING,
+    ]);
+    DirectoryAnalyzer::new(args).unwrap().analyze().await.unwrap()
+}
+
+#[tokio::test]
+async fn inventory_listing_is_analyzed_like_a_tree() {
+    let results = analyze().await;
+
+    assert_eq!(results.scan_info.path, Path::new("s3://acme-media"));
+    // The hidden .trash prefix is left out as a hidden directory would be
+    assert_eq!(results.scan_info.total_files, 6);
+    assert_eq!(results.scan_info.total_size, 2 * 734_003_200 + 268_435_456 + 2 * 48_213 + 4_096);
+    assert_eq!(results.largest_files[0].path, Path::new("s3://acme-media/exports/interview-final.mov"));
+    let raw = results.largest_directories.iter().find(|dir| dir.path == Path::new("s3://acme-media/raw")).unwrap();
+    assert_eq!(raw.size, 734_003_200 + 268_435_456);
+    assert!(results.largest_directories.iter().any(|dir| dir.path == Path::new("s3://acme-media/archive")));
+
+    // Duplicates come from matching etags, never from reading the objects
+    let groups = results.duplicate_groups.as_deref().unwrap();
+    assert_eq!(groups.len(), 2);
+    assert!(groups.iter().all(|group| group.unverified && group.hash.starts_with("etag:")));
+    assert_eq!(groups[0].files, [
+        PathBuf::from("s3://acme-media/exports/interview-final.mov"),
+        PathBuf::from("s3://acme-media/raw/2024/interview.mov"),
+    ]);
+    assert_eq!(groups[1].files[0], Path::new("s3://acme-media/docs/Shot List (final).pdf"));
+    assert_eq!(results.statistics.reclaimable_bytes, 734_003_200 + 48_213);
+
+    let report = reporter::build_report(&results, 20, Duration::ZERO, &reporter::Messages::default());
+    let mut text = Vec::new();
+    PlainRenderer.render(&report, &mut text).unwrap();
+    let text = String::from_utf8(text).unwrap();
+    assert!(text.contains("s3://acme-media/raw/2024/interview.mov"), "{}", text);
+    assert!(text.contains("(not content-verified: same size and etag)"), "{}", text);
+
+    let dir = tempfile::tempdir().unwrap();
+    let csv_path = dir.path().join("results.csv");
+    export::export_results(&results, &ExportFormat::Csv, &Some(csv_path.clone()), &OutputNaming::default(), export::bundle::DEFAULT_SHARD_SIZE, &WriteGuard::unrestricted()).unwrap();
+    let csv = std::fs::read_to_string(&csv_path).unwrap();
+    assert!(csv.contains("Duplicate,s3://acme-media/raw/2024/interview.mov,734003200,Duplicate (not content-verified)"), "{}", csv);
+
+    let json_path = dir.path().join("results.json");
+    export::export_results(&results, &ExportFormat::Json, &Some(json_path.clone()), &OutputNaming::default(), export::bundle::DEFAULT_SHARD_SIZE, &WriteGuard::unrestricted()).unwrap();
+    let reloaded: AnalysisResults = serde_json::from_str(&std::fs::read_to_string(&json_path).unwrap()).unwrap();
+    assert_eq!(reloaded.duplicate_groups.unwrap()[0].group_id, groups[0].group_id);
+}
@@ -0,0 +1,38 @@
+//! Runs of the binary whose stdout carries data for another program
+
+use std::path::Path;
+use std::process::Command;
+
+/// `diranalyzer` with `args`, its home and caches inside `home`
+fn run(home: &Path, args: &[&str]) -> (String, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_diranalyzer"))
+        .args(args)
+        .env("HOME", home)
+        .env("XDG_CACHE_HOME", home.join(".cache"))
+        .env("XDG_CONFIG_HOME", home.join(".config"))
+        .env("XDG_DATA_HOME", home.join(".local/share"))
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    (String::from_utf8(output.stdout).unwrap(), String::from_utf8(output.stderr).unwrap())
+}
+
+#[test]
+fn verbose_ndjson_stream_holds_only_records() {
+    let dir = tempfile::tempdir().unwrap();
+    let root = dir.path().join("root");
+    std::fs::create_dir_all(root.join("sub")).unwrap();
+    std::fs::write(root.join("a.txt"), b"hi").unwrap();
+    std::fs::write(root.join("sub/b.txt"), b"x").unwrap();
+
+    let (stdout, stderr) = run(
+        dir.path(),
+        &["--verbose", "--no-history", "--export", "ndjson", "--output", "-", root.to_str().unwrap()],
+    );
+    let records: Vec<serde_json::Value> =
+        stdout.lines().map(|line| serde_json::from_str(line).unwrap_or_else(|_| panic!("not a record: {}", line))).collect();
+    assert_eq!(records.first().unwrap()["record"], "scan_info");
+    assert_eq!(records.last().unwrap()["record"], "end");
+    // The seed is still shown, on stderr
+    assert!(stderr.contains("replay with --seed"), "{}", stderr);
+}